@@ -0,0 +1,278 @@
+/*!
+A plugin exercising the registration of each kind of component into [Plugs].
+
+The examples are compiled by `cargo test`, `cargo build --all-targets`, and `cargo clippy --all-targets`, so a change to the signature of a trait or to a builder argument struct breaks this file before breaking the programs of the users of the crate.
+When that happens, update this file and add a note to the migration notes below, so the users know how to update their own plugs.
+It can be run with `cargo run --example plugs`.
+
+The builders destructure their argument structs without `..` when the struct may be built outside the crate, so that adding or removing a field is noticed.
+`RouterBuilderArgument` and `AllocatorBuilderArgument` are `non_exhaustive`, so there the fields are named with a final `..`, which notices removals but not additions.
+The plugs of the router and the allocator are registered with the names of built-in implementations, which they take precedence over.
+The output plug is employed by running the simulation as an experiment in a temporary directory, with the `local_and_output` action.
+
+# Migration notes
+
+* `Plugs::add_stage` was added for the stages of the `MultiStage` topology. Stages receive a `StageBuilderArgument`.
+* `RouterBuilderArgument` includes `statistics_temporal_step` and `general_frequency_divisor`. The `Basic` router is built from a `RouterBuilderArgument`.
+* `PatternBuilderArgument::with_cv` builds the argument for a sub-pattern with a different configuration.
+* `Router::stall_counts` and `Router::port_occupancy` have default implementations returning `None`, so routers need not implement them.
+* `Plugs::add_output` was added for the outputs of the `main.od` file. Outputs receive an `OutputBuilderArgument` and return a `Result<(),Error>`.
+*/
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+use ::rand::rngs::StdRng;
+use ::rand::SeedableRng;
+
+use caminos_lib::{Plugs,Simulation,directory_main,error,source_location};
+use caminos_lib::error::{Error,SourceLocation};
+use caminos_lib::experiments::{Action,ExperimentOptions};
+use caminos_lib::output::OutputBuilderArgument;
+use caminos_lib::quantify::Quantifiable;
+use caminos_lib::config_parser::{self,ConfigurationValue,Token};
+use caminos_lib::router::{Router,RouterBuilderArgument};
+use caminos_lib::router::input_output::InputOutput;
+use caminos_lib::topology::{Topology,TopologyBuilderArgument,new_topology};
+use caminos_lib::topology::cartesian::Hamming;
+use caminos_lib::topology::multistage::{Stage,StageBuilderArgument,new_stage};
+use caminos_lib::routing::{Routing,RoutingBuilderArgument,CandidateEgress,new_routing};
+use caminos_lib::traffic::{Traffic,TrafficBuilderArgument,new_traffic};
+use caminos_lib::pattern::{Pattern,PatternBuilderArgument};
+use caminos_lib::policies::{VirtualChannelPolicy,VCPolicyBuilderArgument,RequestInfo};
+use caminos_lib::allocator::{Allocator,AllocatorBuilderArgument};
+use caminos_lib::allocator::random::RandomAllocator;
+
+///Number of times each builder has been called, to check that the plugs are employed.
+static ROUTERS: AtomicUsize = AtomicUsize::new(0);
+static TOPOLOGIES: AtomicUsize = AtomicUsize::new(0);
+static STAGES: AtomicUsize = AtomicUsize::new(0);
+static ROUTINGS: AtomicUsize = AtomicUsize::new(0);
+static TRAFFICS: AtomicUsize = AtomicUsize::new(0);
+static PATTERNS: AtomicUsize = AtomicUsize::new(0);
+static POLICIES: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATORS: AtomicUsize = AtomicUsize::new(0);
+static OUTPUTS: AtomicUsize = AtomicUsize::new(0);
+
+///A copy of the object `cv` with other name.
+fn renamed(cv:&ConfigurationValue, name:&str) -> ConfigurationValue
+{
+	let mut cv = cv.clone();
+	cv.rename(name.to_string());
+	cv
+}
+
+///The `InputOutput` router, counting its builds.
+fn build_router(arg:RouterBuilderArgument) -> Rc<RefCell<dyn Router>>
+{
+	ROUTERS.fetch_add(1,Ordering::Relaxed);
+	let RouterBuilderArgument{router_index:_,cv:_,plugs:_,topology:_,maximum_packet_size:_,general_frequency_divisor:_,statistics_temporal_step:_,rng:_,..} = arg;
+	InputOutput::new(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///`ExampleTopology` is a `Hamming` topology.
+fn build_topology(arg:TopologyBuilderArgument) -> Box<dyn Topology>
+{
+	TOPOLOGIES.fetch_add(1,Ordering::Relaxed);
+	let TopologyBuilderArgument{cv,plugs:_,rng} = arg;
+	Box::new(Hamming::new(&renamed(cv,"Hamming"),rng).unwrap_or_else(|error|panic!("{}",error)))
+}
+
+///`ExampleStage` is a `Fat` stage.
+fn build_stage(arg:StageBuilderArgument) -> Box<dyn Stage>
+{
+	STAGES.fetch_add(1,Ordering::Relaxed);
+	let StageBuilderArgument{cv,plugs,rng} = arg;
+	new_stage(StageBuilderArgument{cv:&renamed(cv,"Fat"),plugs,rng})
+}
+
+///`ExampleRouting` is a `Shortest` routing.
+fn build_routing(arg:RoutingBuilderArgument) -> Box<dyn Routing>
+{
+	ROUTINGS.fetch_add(1,Ordering::Relaxed);
+	let RoutingBuilderArgument{cv,plugs} = arg;
+	new_routing(RoutingBuilderArgument{cv:&renamed(cv,"Shortest"),plugs})
+}
+
+///`ExampleTraffic` is a `HomogeneousTraffic`.
+fn build_traffic(arg:TrafficBuilderArgument) -> Box<dyn Traffic>
+{
+	TRAFFICS.fetch_add(1,Ordering::Relaxed);
+	let TrafficBuilderArgument{cv,plugs,topology,rng} = arg;
+	new_traffic(TrafficBuilderArgument{cv:&renamed(cv,"HomogeneousTraffic"),plugs,topology,rng})
+}
+
+///A pattern implemented here, sending from `origin` to `target_size-1-origin`, or to the next server when they coincide.
+#[derive(Debug)]
+struct ExampleReverse
+{
+	source_size: usize,
+	target_size: usize,
+}
+
+impl Quantifiable for ExampleReverse
+{
+	fn total_memory(&self) -> usize
+	{
+		std::mem::size_of::<ExampleReverse>()
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl Pattern for ExampleReverse
+{
+	fn initialize(&mut self, source_size:usize, target_size:usize, _topology:&dyn Topology, _rng:&mut StdRng)
+	{
+		self.source_size = source_size;
+		self.target_size = target_size;
+	}
+	fn get_destination(&self, origin:usize, _topology:&dyn Topology, _rng:&mut StdRng) -> usize
+	{
+		assert!(origin<self.source_size, "origin {} out of range",origin);
+		let destination = self.target_size-1-origin%self.target_size;
+		if destination==origin { (destination+1)%self.target_size } else { destination }
+	}
+}
+
+fn build_pattern(arg:PatternBuilderArgument) -> Box<dyn Pattern>
+{
+	PATTERNS.fetch_add(1,Ordering::Relaxed);
+	let PatternBuilderArgument{cv:_,plugs:_} = arg;
+	Box::new(ExampleReverse{source_size:0,target_size:0})
+}
+
+///A policy implemented here, keeping the candidates with the lowest virtual channel.
+#[derive(Debug)]
+struct ExampleLowestVirtualChannel;
+
+impl VirtualChannelPolicy for ExampleLowestVirtualChannel
+{
+	fn filter(&self, candidates:Vec<CandidateEgress>, _router:&dyn Router, _info:&RequestInfo, _topology:&dyn Topology, _rng:&mut StdRng) -> Vec<CandidateEgress>
+	{
+		let lowest = candidates.iter().map(|candidate|candidate.virtual_channel).min();
+		candidates.into_iter().filter(|candidate|Some(candidate.virtual_channel)==lowest).collect()
+	}
+	fn need_server_ports(&self) -> bool
+	{
+		false
+	}
+	fn need_port_average_queue_length(&self) -> bool
+	{
+		false
+	}
+	fn need_port_last_transmission(&self) -> bool
+	{
+		false
+	}
+}
+
+fn build_policy(arg:VCPolicyBuilderArgument) -> Box<dyn VirtualChannelPolicy>
+{
+	POLICIES.fetch_add(1,Ordering::Relaxed);
+	let VCPolicyBuilderArgument{cv:_,plugs:_} = arg;
+	Box::new(ExampleLowestVirtualChannel)
+}
+
+///The `Random` allocator, counting its builds.
+fn build_allocator(arg:AllocatorBuilderArgument) -> Box<dyn Allocator>
+{
+	ALLOCATORS.fetch_add(1,Ordering::Relaxed);
+	let AllocatorBuilderArgument{cv:_,num_resources:_,num_clients:_,plugs:_,rng:_,..} = arg;
+	Box::new(RandomAllocator::new(arg))
+}
+
+///An output implemented here, writing the accepted load of each experiment into `accepted.txt` in the outputs directory.
+fn build_output(arg:OutputBuilderArgument) -> Result<(),Error>
+{
+	OUTPUTS.fetch_add(1,Ordering::Relaxed);
+	let OutputBuilderArgument{cv:_,environment,plugs:_} = arg;
+	let mut lines = vec![];
+	let field = |cv:&ConfigurationValue, key:&str| match cv
+	{
+		ConfigurationValue::Object(_,fields) => fields.iter().find(|(name,_)|name==key).map(|(_,value)|value.clone()),
+		_ => None,
+	};
+	for context in environment.iter()
+	{
+		if let Some(accepted) = field(&context,"result").and_then(|result|field(&result,"accepted_load"))
+		{
+			lines.push(format!("{}",accepted));
+		}
+	}
+	let path = environment.files().get_outputs_path().join("accepted.txt");
+	std::fs::write(&path,lines.join("\n")).map_err(|e|error!(could_not_generate_file,path,e))?;
+	Ok(())
+}
+
+fn plugs() -> Plugs
+{
+	let mut plugs = Plugs::default();
+	plugs.add_router("InputOutput".to_string(),build_router);
+	plugs.add_topology("ExampleTopology".to_string(),build_topology);
+	plugs.add_stage("ExampleStage".to_string(),build_stage);
+	plugs.add_routing("ExampleRouting".to_string(),build_routing);
+	plugs.add_traffic("ExampleTraffic".to_string(),build_traffic);
+	plugs.add_pattern("ExampleReverse".to_string(),build_pattern);
+	plugs.add_policy("ExampleLowestVirtualChannel".to_string(),build_policy);
+	plugs.add_allocator("Random".to_string(),build_allocator);
+	plugs.add_output("ExampleOutput".to_string(),build_output);
+	plugs
+}
+
+fn parse(source:&str) -> ConfigurationValue
+{
+	match config_parser::parse(source).expect("could not parse the configuration")
+	{
+		Token::Value(value) => value,
+		_ => panic!("the configuration is not a value"),
+	}
+}
+
+fn main()
+{
+	let plugs = plugs();
+	let simulation_source = "Configuration
+	{
+		random_seed: 3,
+		warmup: 100,
+		measured: 400,
+		topology: ExampleTopology{ sides:[4], servers_per_router:2 },
+		traffic: ExampleTraffic{ pattern:ExampleReverse, servers:8, load:0.3, message_size:16 },
+		maximum_packet_size: 16,
+		router: InputOutput{ allocator:Random{seed:1}, crossbar_delay:0, virtual_channels:2, virtual_channel_policies:[EnforceFlowControl,ExampleLowestVirtualChannel,Random], delay:0, buffer_size:64, bubble:false, flit_size:16, allow_request_busy_port:true, intransit_priority:false, output_buffer_size:32 },
+		routing: ExampleRouting,
+		link_classes: [LinkClass{delay:1},LinkClass{delay:1}],
+	}";
+	let simulation_cv = parse(simulation_source);
+	let mut simulation = Simulation::new(&simulation_cv,&plugs);
+	simulation.run();
+	let results = simulation.results();
+	println!("Accepted load with the example plugs: {}",results.accepted_load);
+	assert!(results.accepted_load>0.0, "nothing was delivered");
+	//The stages are only employed by the multistage topologies.
+	let multistage_cv = parse("MultiStage{ stages:[ExampleStage{ bottom_factor:2, top_factor:2 }], servers_per_leaf:2 }");
+	let mut rng = StdRng::seed_from_u64(0);
+	let multistage = new_topology(TopologyBuilderArgument{cv:&multistage_cv,plugs:&plugs,rng:&mut rng});
+	println!("Routers in the multistage topology: {}",multistage.num_routers());
+	//The outputs are only employed by the experiments.
+	let path = std::env::temp_dir().join(format!("caminos_plugs_example_{}",std::process::id()));
+	std::fs::create_dir_all(&path).expect("could not create the experiment directory");
+	std::fs::write(path.join("main.cfg"),simulation_source).expect("could not write main.cfg");
+	std::fs::write(path.join("main.od"),"[ExampleOutput]").expect("could not write main.od");
+	directory_main(&path,"plugs",&plugs,Action::LocalAndOutput,ExperimentOptions::default()).expect("the experiment failed");
+	println!("Accepted load written by the example output: {}",std::fs::read_to_string(path.join("outputs").join("accepted.txt")).expect("could not read the example output"));
+	std::fs::remove_dir_all(&path).expect("could not remove the experiment directory");
+	for (kind,counter) in [("router",&ROUTERS),("topology",&TOPOLOGIES),("stage",&STAGES),("routing",&ROUTINGS),("traffic",&TRAFFICS),("pattern",&PATTERNS),("policy",&POLICIES),("allocator",&ALLOCATORS),("output",&OUTPUTS)]
+	{
+		assert!(counter.load(Ordering::Relaxed)>0, "the {} plug was not employed",kind);
+	}
+	println!("All the plugs were employed.");
+}
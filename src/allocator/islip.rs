@@ -243,6 +243,7 @@ impl Allocator for ISLIPAllocator {
                             client,
                             resource,
                             priority: Some(0),
+                            timestamp: None,
                         };
                         gr.add_granted_request(req);
                         
@@ -234,7 +234,7 @@ impl Allocator for LabelReduction {
             cleaned_requests.shuffle(&mut self.rng.as_mut().unwrap());
         }
 
-        for Request{ref resource, ref client, priority: _ } in cleaned_requests.iter() {
+        for Request{ref resource, ref client, .. } in cleaned_requests.iter() {
             // Check if the wanted resource is available and if the client has no resource
             if resources[*resource].client.is_none() && clients[*client].resource.is_none() {
                 // Add the request to the granted requests
@@ -242,6 +242,7 @@ impl Allocator for LabelReduction {
                     client: *client,
                     resource: *resource,
                     priority: None, // Don't care about the priority on this allocator
+                    timestamp: None,
                 });
                 // Allocate the resource to the client
                 resources[*resource].client = Some(*client);
@@ -9,16 +9,21 @@ see [`new_allocator`](fn.new_allocator.html) for documentation on the configurat
 pub mod random;
 pub mod random_priority;
 pub mod islip;
+pub mod wavefront;
+pub mod oldest_first;
 mod label_reduction;
 //pub mod separable_input_first;
 
 use crate::Plugs;
+use crate::event::Time;
 use crate::config_parser::ConfigurationValue;
 
 use ::rand::rngs::StdRng;
 use random::RandomAllocator;
 use random_priority::RandomPriorityAllocator;
 use islip::ISLIPAllocator;
+use wavefront::WavefrontAllocator;
+use oldest_first::OldestFirstAllocator;
 
 
 /// A request to a Virtual Channel Allocator.
@@ -32,6 +37,8 @@ pub struct VCARequest
 	pub requested_port: usize,
 	pub requested_vc: usize,
 	pub label: i32,
+	///The cycle in which the packet of the phit entered the network, for the allocators that grant the oldest packets first. `None` if unknown.
+	pub timestamp: Option<Time>,
 }
 
 impl VCARequest
@@ -43,7 +50,7 @@ impl VCARequest
 			self.entry_port*num_vcs+self.entry_vc,
 			self.requested_port*num_vcs+self.requested_vc,
 			if self.label<0 {None} else {	Some(self.label as usize) },
-		)
+		).with_timestamp(self.timestamp)
 	}
 }
 
@@ -61,10 +68,15 @@ pub struct Request {
 	/// The lower the priority, the earlier the request is granted
 	/// If the priority is 0, the request is an intransit request
 	pub priority: Option<usize>,
+	/// The cycle in which the packet of the request entered the network (None if not specified)
+	/// Used by the allocators that grant first the oldest packets, such as `OldestFirst`
+	pub timestamp: Option<Time>,
 }
 
 impl Request {
-	pub fn new(client: usize, resource: usize, priority: Option<usize>) -> Request { Self { client, resource, priority } }
+	pub fn new(client: usize, resource: usize, priority: Option<usize>) -> Request { Self { client, resource, priority, timestamp: None } }
+	/// The same request with the given timestamp.
+	pub fn with_timestamp(self, timestamp: Option<Time>) -> Request { Request { timestamp, ..self } }
 
 	// method to transform a Request into a router::basic_ioq::PortRequest
 	pub fn to_port_request(&self, num_vcs: usize)->VCARequest
@@ -75,6 +87,7 @@ impl Request {
 			requested_port: self.resource/num_vcs,
 			requested_vc: self.resource%num_vcs,
 			label: if self.priority.is_none() {0} else {self.priority.unwrap() as i32},
+			timestamp: self.timestamp,
 		}
 	}
 }
@@ -141,6 +154,10 @@ pub trait Allocator {
 	/// The intransit priority option is used to specify the give more priority to the requests
 	/// that come from the another router rather than a server.
 	fn support_intransit_priority(&self) -> bool;
+
+	/// Clears all collected statistics. Called by the router when the warmup ends.
+	/// The state of the allocator, such as the round-robin pointers, is kept.
+	fn reset_statistics(&mut self, _next_cycle: Time) {}
 }
 
 /// Arguments for the allocator builder
@@ -186,6 +203,25 @@ Islip{
 	num_iter:2,
 }
 ```
+
+The `Wavefront` allocator grants the requests by diagonals of the request matrix, rotating the priority diagonal after each allocation.
+It supports crossbars with different number of inputs and outputs. Requests with lesser labels are served first.
+```ignore
+Wavefront{
+	//Serve first the greatest labels instead.
+	//greatest_first:false
+}
+```
+
+The `OldestFirst` allocator grants first the requests whose packet entered the network first, using the `timestamp` of the requests.
+Ties are solved randomly. With `label_first` it sorts by the label before the age, which is required for the `intransit_priority` of the router.
+```ignore
+OldestFirst{
+	//Optional seed to build a new random generator independent of the simulation's global generator.
+	//seed:42,
+	//label_first:false,
+}
+```
 **/
 pub fn new_allocator(arg:AllocatorBuilderArgument) -> Box<dyn Allocator>
 {
@@ -207,6 +243,8 @@ pub fn new_allocator(arg:AllocatorBuilderArgument) -> Box<dyn Allocator>
 				Box::new(ISLIPAllocator::new(alias))
 			}
 			"ISLIP" => Box::new(ISLIPAllocator::new(arg)),
+			"Wavefront" => Box::new(WavefrontAllocator::new(arg)),
+			"OldestFirst" => Box::new(OldestFirstAllocator::new(arg)),
 			_ => panic!("Unknown allocator: {}", cv_name),
 		}
 	}
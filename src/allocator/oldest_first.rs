@@ -0,0 +1,154 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::prelude::SliceRandom;
+
+use crate::allocator::{Allocator, Request, GrantedRequests, AllocatorBuilderArgument};
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::match_object_panic;
+
+/**
+An allocator that grants each resource to the request whose packet entered the network first, as used against starvation.
+The age of a request is its `timestamp`, which the [InputOutput](crate::router::input_output::InputOutput) router sets to the cycle in which the packet entered the network.
+Requests without timestamp go after those with one. Ties are solved randomly.
+With `label_first` the requests are first sorted by their label, the lowest first, and the age only breaks the ties among the same label.
+This allows the `intransit_priority` option of the router, which gives the label 0 to the requests in transit.
+```ignore
+OldestFirst{
+	//seed:0
+	//label_first:false
+}
+```
+**/
+pub struct OldestFirstAllocator {
+    /// The max number of outputs of the router crossbar
+    num_resources: usize,
+    /// The max number of inputs of the router crossbar
+    num_clients: usize,
+    /// The requests of the clients
+    requests: Vec<Request>,
+    /// Whether to sort by the label before the age.
+    label_first: bool,
+    /// The RNG or None if the seed is not set
+    rng: Option<StdRng>,
+}
+
+impl OldestFirstAllocator {
+    /// Create a new oldest first allocator
+    /// # Parameters
+    /// * `args` - The arguments for the allocator
+    /// # Returns
+    /// * `OldestFirstAllocator` - The new oldest first allocator
+    pub fn new(args: AllocatorBuilderArgument) -> OldestFirstAllocator {
+        if args.num_clients == 0 || args.num_resources == 0 {
+            panic!("Invalid arguments")
+        }
+        let mut seed = None;
+        let mut label_first = false;
+        match_object_panic!(args.cv, "OldestFirst", value,
+			"seed" => match value
+			{
+				&ConfigurationValue::Number(s) => seed = Some(s as u64),
+				_ => panic!("Bad value for seed"),
+			},
+			"label_first" => label_first = value.as_bool().expect("Bad value for label_first"),
+        );
+        let rng = seed.map(StdRng::seed_from_u64);
+        OldestFirstAllocator {
+            num_resources: args.num_resources,
+            num_clients: args.num_clients,
+            requests: Vec::new(),
+            label_first,
+            rng,
+        }
+    }
+}
+
+impl Allocator for OldestFirstAllocator {
+    /// Add a request to the allocator
+    /// # Arguments
+    /// * `request` - The request to add
+    /// # Remarks
+    /// The request is valid if the client is in the range [0, num_clients) and the resource is in the range [0, num_resources)
+    fn add_request(&mut self, request: Request) {
+        if request.client >= self.num_clients || request.resource >= self.num_resources {
+            panic!("Invalid request");
+        }
+        self.requests.push(request);
+    }
+
+    /// Perform the allocation
+    /// # Arguments
+    /// * `rng` - The RNG to use if the seed is not set
+    /// # Returns
+    /// * `GrantedRequests` - The granted requests
+    fn perform_allocation(&mut self, rng : &mut StdRng) -> GrantedRequests {
+        let mut gr = GrantedRequests::default();
+        let mut resource_taken = vec![false; self.num_resources];
+        let mut client_served = vec![false; self.num_clients];
+
+        // Shuffle first, so that the stable sort leaves the ties in random order.
+        let rng = self.rng.as_mut().unwrap_or(rng);
+        self.requests.shuffle(rng);
+        // The requests without timestamp are considered the youngest.
+        let label_first = self.label_first;
+        self.requests.sort_by_key(|request| {
+            let age_key = request.timestamp.unwrap_or(Time::MAX);
+            if label_first { (request.priority.unwrap_or(usize::MAX), age_key) } else { (0, age_key) }
+        });
+
+        for request in self.requests.drain(..) {
+            if !resource_taken[request.resource] && !client_served[request.client] {
+                resource_taken[request.resource] = true;
+                client_served[request.client] = true;
+                gr.add_granted_request(request);
+            }
+        }
+        gr
+    }
+    /// The label 0 of the requests in transit is only considered with `label_first`.
+    fn support_intransit_priority(&self) -> bool {
+        self.label_first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Plugs;
+
+    fn oldest_first(label_first: bool) -> OldestFirstAllocator {
+        let cv = ConfigurationValue::Object("OldestFirst".to_string(), vec![
+            ("label_first".to_string(), if label_first { ConfigurationValue::True } else { ConfigurationValue::False }),
+        ]);
+        let plugs = Plugs::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        OldestFirstAllocator::new(AllocatorBuilderArgument { cv: &cv, num_resources: 2, num_clients: 3, plugs: &plugs, rng: &mut rng })
+    }
+
+    #[test]
+    fn oldest_packets_first() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut allocator = oldest_first(false);
+        for _ in 0..10 {
+            // The client 2 has the oldest packet, whatever its label, and the client 1 gets the other resource.
+            allocator.add_request(Request::new(0, 0, Some(0)).with_timestamp(Some(50)));
+            allocator.add_request(Request::new(1, 0, Some(3)).with_timestamp(Some(20)));
+            allocator.add_request(Request::new(1, 1, Some(3)).with_timestamp(Some(20)));
+            allocator.add_request(Request::new(2, 0, Some(5)).with_timestamp(Some(10)));
+            allocator.add_request(Request::new(0, 1, Some(0)));
+            let mut granted: Vec<(usize, usize)> = allocator.perform_allocation(&mut rng).into_iter().map(|r| (r.client, r.resource)).collect();
+            granted.sort();
+            assert_eq!(granted, vec![(1, 1), (2, 0)]);
+        }
+        // With label_first the lowest label wins, and the age breaks the ties.
+        let mut allocator = oldest_first(true);
+        assert!(allocator.support_intransit_priority());
+        allocator.add_request(Request::new(0, 0, Some(1)).with_timestamp(Some(50)));
+        allocator.add_request(Request::new(1, 0, Some(1)).with_timestamp(Some(40)));
+        allocator.add_request(Request::new(2, 0, Some(2)).with_timestamp(Some(10)));
+        let granted: Vec<Request> = allocator.perform_allocation(&mut rng).into_iter().collect();
+        assert_eq!(granted.len(), 1);
+        assert_eq!(granted[0].client, 1);
+    }
+}
@@ -106,7 +106,7 @@ impl Allocator for RandomAllocator {
         let rng = self.rng.as_mut().unwrap_or(rng);
         self.requests.shuffle(rng);
         // Allocate the requests with an iterator
-        for Request{ref resource, ref client, priority: _ } in self.requests.iter() {
+        for Request{ref resource, ref client, .. } in self.requests.iter() {
             // Check if the wanted resource is available and if the client has no resource
             if resources[*resource].client.is_none() && clients[*client].resource.is_none() {
                 // Add the request to the granted requests
@@ -114,6 +114,7 @@ impl Allocator for RandomAllocator {
                     client: *client,
                     resource: *resource,
                     priority: None, // Don't care about the priority on this allocator
+                    timestamp: None,
                 });
                 // Allocate the resource to the client
                 resources[*resource].client = Some(*client);
@@ -142,7 +142,7 @@ impl Allocator for RandomPriorityAllocator {
         }
 
         // Allocate the requests with an iterator
-        for Request{ref resource, ref client, ref priority } in self.requests.iter() {
+        for Request{ref resource, ref client, ref priority, ref timestamp } in self.requests.iter() {
             // Check if the wanted resource is available and the client has no resource
             if resources[*resource].client.is_none() && clients[*client].resource.is_none() {
                 // Add the request to the granted requests
@@ -150,6 +150,7 @@ impl Allocator for RandomPriorityAllocator {
                     client: *client,
                     resource: *resource,
                     priority: *priority,
+                    timestamp: *timestamp,
                 });
                 // Allocate the resource
                 resources[*resource].client = Some(*client);
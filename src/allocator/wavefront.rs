@@ -0,0 +1,202 @@
+/*!
+
+This file implements the wavefront allocator.
+
+\[Reference\] Yuval Tamir and Hsin-Chou Chi, "Symmetric Crossbar Arbiters for VLSI Communication Switches," IEEE Transactions on Parallel and Distributed Systems, vol. 4, no. 1, pp. 13-27, January 1993. doi: <https://doi.org/10.1109/71.205650>
+
+*/
+
+use rand::rngs::StdRng;
+
+use crate::allocator::{Allocator, AllocatorBuilderArgument, GrantedRequests, Request};
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+/**
+A wavefront allocator. The requests are placed in a square matrix with a row for each client and a column for each resource, padding with empty
+rows or columns when there are not as many clients as resources. The cells of a diagonal of this matrix never share a row nor a column, so all its
+requests can be granted at once. The diagonals are visited in order, starting from the priority diagonal, and each request is granted when both
+its client and its resource are still free. The priority diagonal advances after each allocation so that no cell keeps the highest priority.
+
+When the requests have different priorities, a wavefront is performed for each priority level, from the least to the greatest label, over the clients and
+resources left free by the previous levels. Requests without priority are considered after all the prioritized ones.
+```ignore
+Wavefront{
+	//Serve the greatest priority labels first. By default false, serving first the least labels, as the routing and policies consider them best.
+	//greatest_first:false
+}
+```
+**/
+pub struct WavefrontAllocator {
+    /// The number of inputs of the router crossbar
+    num_clients: usize,
+    /// The number of outputs of the router crossbar
+    num_resources: usize,
+    /// The side of the square matrix, the maximum of the clients and the resources.
+    size: usize,
+    /// The diagonal with the highest priority in the next allocation.
+    pointer: usize,
+    /// Whether to serve first the greatest priority labels.
+    greatest_first: bool,
+    /// The requests of the clients
+    requests: Vec<Request>,
+}
+
+impl WavefrontAllocator {
+    /// Creates a new wavefront allocator
+    /// # Arguments
+    /// * `args` - The arguments of the allocator
+    /// # Returns
+    /// * `WavefrontAllocator` - The new wavefront allocator
+    pub fn new(args: AllocatorBuilderArgument) -> WavefrontAllocator {
+        // Check if the arguments are valid
+        if args.num_clients == 0 || args.num_resources == 0 {
+            panic!("Invalid arguments for WavefrontAllocator");
+        }
+        let mut greatest_first = false;
+        match_object_panic!(args.cv, "Wavefront", value,
+            "greatest_first" => greatest_first = value.as_bool().expect("Bad value for greatest_first"),
+        );
+        WavefrontAllocator {
+            num_clients: args.num_clients,
+            num_resources: args.num_resources,
+            size: args.num_clients.max(args.num_resources),
+            pointer: 0,
+            greatest_first,
+            requests: Vec::new(),
+        }
+    }
+
+    /// Check if the request is valid
+    /// # Arguments
+    /// * `request` - The request to check
+    /// # Returns
+    /// * `bool` - True if the request is valid, false otherwise
+    /// # Remarks
+    /// The request is valid if the client is in the range [0, num_clients) and the resource is in the range [0, num_resources)
+    fn is_valid_request(&self, request: &Request) -> bool {
+        request.client < self.num_clients && request.resource < self.num_resources
+    }
+}
+
+impl Allocator for WavefrontAllocator {
+    /// Add a request to the allocator
+    /// # Arguments
+    /// * `request` - The request to add
+    /// # Panics
+    /// If the request is not valid
+    fn add_request(&mut self, request: Request) {
+        if !self.is_valid_request(&request) {
+            panic!("The request is not valid");
+        }
+        self.requests.push(request);
+    }
+
+    /// Perform the allocation
+    /// # Arguments
+    /// * `_rng` - NOT USED on this allocator
+    /// # Returns
+    /// * `GrantedRequests` - The granted requests
+    fn perform_allocation(&mut self, _rng: &mut StdRng) -> GrantedRequests {
+        let mut gr = GrantedRequests::default();
+        // The resource granted to each client and the client granted to each resource.
+        let mut client_match: Vec<Option<usize>> = vec![None; self.num_clients];
+        let mut resource_match: Vec<Option<usize>> = vec![None; self.num_resources];
+
+        // Group the requests by priority level, in the order in which they are served.
+        let greatest_first = self.greatest_first;
+        self.requests.sort_by_key(|request| match request.priority {
+            Some(priority) if greatest_first => (0, usize::MAX - priority),
+            Some(priority) => (0, priority),
+            None => (1, 0),
+        });
+
+        // The request matrix of the current level, indexed by `client*num_resources+resource`.
+        let mut matrix = vec![None; self.num_clients * self.num_resources];
+        let mut level_start = 0;
+        while level_start < self.requests.len() {
+            let priority = self.requests[level_start].priority;
+            let level_end = level_start + self.requests[level_start..].iter().take_while(|request| request.priority == priority).count();
+            for request in &self.requests[level_start..level_end] {
+                matrix[request.client * self.num_resources + request.resource] = Some(priority);
+            }
+            // Visit the diagonals starting from the priority one. The cells of the diagonal are independent.
+            for offset in 0..self.size {
+                let diagonal = (self.pointer + offset) % self.size;
+                for client in 0..self.num_clients {
+                    let resource = (client + diagonal) % self.size;
+                    if resource >= self.num_resources {
+                        // A padding column of a rectangular crossbar.
+                        continue;
+                    }
+                    if let Some(priority) = matrix[client * self.num_resources + resource] {
+                        if client_match[client].is_none() && resource_match[resource].is_none() {
+                            client_match[client] = Some(resource);
+                            resource_match[resource] = Some(client);
+                            gr.add_granted_request(Request { client, resource, priority, timestamp: None });
+                        }
+                    }
+                }
+            }
+            for request in &self.requests[level_start..level_end] {
+                matrix[request.client * self.num_resources + request.resource] = None;
+            }
+            level_start = level_end;
+        }
+
+        // Rotate the priority diagonal.
+        self.pointer = (self.pointer + 1) % self.size;
+        self.requests.clear();
+        gr
+    }
+
+    fn support_intransit_priority(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use crate::Plugs;
+
+    fn wavefront(num_clients: usize, num_resources: usize) -> WavefrontAllocator {
+        let cv = ConfigurationValue::Object("Wavefront".to_string(), vec![]);
+        let plugs = Plugs::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        WavefrontAllocator::new(AllocatorBuilderArgument { cv: &cv, num_resources, num_clients, plugs: &plugs, rng: &mut rng })
+    }
+
+    #[test]
+    fn wavefront_rectangular_and_priorities() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Three clients competing for two resources. Every cycle both resources must be granted and the diagonal rotation must serve every client.
+        let mut allocator = wavefront(3, 2);
+        let mut served = vec![0; 3];
+        for _ in 0..6 {
+            for client in 0..3 {
+                for resource in 0..2 {
+                    allocator.add_request(Request::new(client, resource, None));
+                }
+            }
+            let granted: Vec<Request> = allocator.perform_allocation(&mut rng).into_iter().collect();
+            assert_eq!(granted.len(), 2);
+            assert_ne!(granted[0].client, granted[1].client);
+            assert_ne!(granted[0].resource, granted[1].resource);
+            for request in granted {
+                served[request.client] += 1;
+            }
+        }
+        assert!(served.iter().all(|&count| count > 0), "some client starved: {:?}", served);
+        // A request with a lesser label takes the resource whatever the position of the priority diagonal.
+        let mut allocator = wavefront(2, 3);
+        for _ in 0..3 {
+            allocator.add_request(Request::new(0, 1, Some(5)));
+            allocator.add_request(Request::new(1, 1, Some(2)));
+            let granted: Vec<Request> = allocator.perform_allocation(&mut rng).into_iter().collect();
+            assert_eq!(granted.len(), 1);
+            assert_eq!(granted[0].client, 1);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+/*!
+
+Analytic estimation of the performance of a configuration, to be used instead of simulating it.
+
+When the configuration includes `analytic: AnalyticEstimate{...}` the simulation does not advance any cycle. Instead, the channel loads are computed
+by sampling messages from the traffic and following the candidates given by the routing from their origin to their destination.
+The delay of each link is then approximated as the waiting time of an M/D/1 queue, whose deterministic service time is the packet size.
+This is quick enough to sweep large configuration spaces and prune them before running the full simulations, but it ignores the router
+architecture, flow control, and any adaptiveness based on the network state.
+
+```ignore
+analytic: AnalyticEstimate{
+	//Number of messages sampled from the traffic for each task. By default 100.
+	samples: 100,
+	//Maximum number of hops of a route before considering that the routing does not reach the destination. By default the number of routers.
+	//maximum_hops: 50,
+}
+```
+
+The result is a `Result` object with `analytic: true`, so that it cannot be mistaken by a simulated result.
+It includes `injected_load`, `accepted_load`, `average_packet_network_delay`, `average_message_delay`, `average_packet_hops`, `average_link_utilization`, and `maximum_link_utilization`.
+When some link, including the links of the servers, would receive more load than it can transmit, the accepted load is the offered load scaled by that excess and the delays are `None`.
+With `statistics_link_utilization: true` it also includes the estimated `link_utilization`.
+
+*/
+
+use std::cell::RefCell;
+
+use ::rand::{Rng,rngs::StdRng};
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::{match_object,error,source_location};
+use crate::routing::RoutingInfo;
+use crate::topology::Location;
+use crate::SimulationShared;
+
+///The utilization from which a link is considered saturated. The loads are given in `f32` by the traffics, so there is some imprecision.
+const SATURATED: f64 = 1.0-1e-6;
+
+///The parameters of the analytic estimation. See the [module documentation](self).
+#[derive(Debug)]
+pub struct AnalyticEstimate
+{
+	///Messages sampled from each task.
+	samples: usize,
+	///Maximum number of hops of a route.
+	maximum_hops: Option<usize>,
+}
+
+///A route sampled from the traffic and the routing.
+struct SampledRoute
+{
+	///Phits per cycle represented by this sample.
+	rate: f64,
+	///Size of its packets.
+	packet_size: usize,
+	///Number of packets of its message.
+	packets: usize,
+	///Base delay of the route without waiting, including the links from and to the servers.
+	base_delay: f64,
+	///The links traversed, as `(router,port)` of the arrival.
+	links: Vec<(usize,usize)>,
+	///The server that consumes the message.
+	destination: usize,
+}
+
+impl AnalyticEstimate
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<AnalyticEstimate,Error>
+	{
+		let mut samples=100;
+		let mut maximum_hops=None;
+		match_object!(cv,"AnalyticEstimate",value,
+			"samples" => samples=value.as_usize()?,
+			"maximum_hops" => maximum_hops=Some(value.as_usize()?),
+		);
+		if samples==0
+		{
+			return Err(cv.ill("AnalyticEstimate requires some sample"));
+		}
+		Ok(AnalyticEstimate{
+			samples,
+			maximum_hops,
+		})
+	}
+	///Compute the estimation, returning the result object. When `link_utilization` is set it includes the estimated utilization of each link.
+	pub fn estimate(&self, shared:&mut SimulationShared, rng:&mut StdRng, link_utilization:bool) -> ConfigurationValue
+	{
+		let topology = shared.network.topology.as_ref();
+		let num_servers = shared.network.servers.len();
+		let num_virtual_channels = shared.network.routers.first().map(|router|router.borrow().num_virtual_channels()).unwrap_or(1);
+		let maximum_hops = self.maximum_hops.unwrap_or(topology.num_routers());
+		let server_delay = shared.link_classes.last().map(|class|class.delay).unwrap_or(0) as f64;
+		let router_of = |server:usize| match topology.server_neighbour(server).0
+		{
+			Location::RouterPort{router_index,..} => router_index,
+			_ => panic!("The server is not attached to a router"),
+		};
+		let mut load : Vec<Vec<f64>> = (0..topology.num_routers()).map(|router|vec![0.0;topology.ports(router)]).collect();
+		let mut injection = vec![0.0;num_servers];
+		let mut ejection = vec![0.0;num_servers];
+		let mut routes = vec![];
+		let mut offered = 0.0;
+		let tasks = shared.traffic.number_tasks().min(num_servers);
+		for origin in 0..tasks
+		{
+			let messages_per_cycle = shared.traffic.probability_per_cycle(origin) as f64;
+			if messages_per_cycle<=0.0
+			{
+				continue;
+			}
+			for _ in 0..self.samples
+			{
+				let message = match shared.traffic.generate_message(origin,0,topology,rng)
+				{
+					Ok(message) => message,
+					Err(_) => continue,
+				};
+				let rate = messages_per_cycle*message.size as f64/self.samples as f64;
+				offered += rate;
+				let destination = message.destination;
+				injection[origin] += rate;
+				ejection[destination] += rate;
+				let current_router = router_of(origin);
+				let target_router = router_of(destination);
+				let packet_size = message.size.min(shared.maximum_packet_size).max(1);
+				let packets = ((message.size+packet_size-1)/packet_size).max(1);
+				let routing_info = RefCell::new(RoutingInfo::new());
+				shared.routing.initialize_routing_info(&routing_info,topology,current_router,target_router,Some(destination),rng);
+				let mut current = current_router;
+				let mut links = vec![];
+				let mut base_delay = 2.0*server_delay + packet_size as f64;
+				while current!=target_router
+				{
+					assert!(links.len()<maximum_hops,"The route from server {} to server {} exceeds {} hops",origin,destination,maximum_hops);
+					let candidates = shared.routing.next(&routing_info.borrow(),topology,current,target_router,Some(destination),num_virtual_channels,rng)
+						.unwrap_or_else(|error|panic!("The routing failed from router {} to router {}: {}",current,target_router,error)).candidates;
+					assert!(!candidates.is_empty(),"The routing gave no candidates from router {} to router {}",current,target_router);
+					let candidate = &candidates[rng.gen_range(0..candidates.len())];
+					shared.routing.performed_request(candidate,&routing_info,topology,current,target_router,Some(destination),num_virtual_channels,rng);
+					let (next_router,next_port,link_class) = match topology.neighbour(current,candidate.port)
+					{
+						(Location::RouterPort{router_index,router_port},link_class) => (router_index,router_port,link_class),
+						_ => panic!("The routing selected a port of router {} not going to a router",current),
+					};
+					load[next_router][next_port] += rate;
+					base_delay += shared.link_classes[link_class].delay as f64;
+					links.push((next_router,next_port));
+					routing_info.borrow_mut().hops+=1;
+					shared.routing.update_routing_info(&routing_info,topology,next_router,next_port,target_router,Some(destination),rng);
+					current = next_router;
+				}
+				routes.push(SampledRoute{rate,packet_size,packets,base_delay,links,destination});
+			}
+		}
+		let router_links : Vec<f64> = (0..topology.num_routers()).flat_map(|router|(0..topology.degree(router)).map(|port|load[router][port]).collect::<Vec<f64>>()).collect();
+		let maximum_utilization = router_links.iter().cloned().fold(0.0,f64::max);
+		let average_utilization = if router_links.is_empty() { 0.0 } else { router_links.iter().sum::<f64>()/router_links.len() as f64 };
+		let total_rate : f64 = routes.iter().map(|route|route.rate).sum();
+		let weighted = |f:&dyn Fn(&SampledRoute)->f64| if total_rate>0.0 { routes.iter().map(|route|route.rate*f(route)).sum::<f64>()/total_rate } else { 0.0 };
+		let average_hops = weighted(&|route|route.links.len() as f64);
+		//The links from and to the servers are also limited to a phit per cycle.
+		let busiest = injection.iter().chain(ejection.iter()).cloned().fold(maximum_utilization,f64::max);
+		let saturation = busiest.max(1.0);
+		let (packet_delay,message_delay) = if busiest<SATURATED
+		{
+			let waiting = |utilization:f64, packet_size:usize| utilization*packet_size as f64/(2.0*(1.0-utilization));
+			let packet_delay = |route:&SampledRoute| route.base_delay
+				+ waiting(ejection[route.destination],route.packet_size)
+				+ route.links.iter().map(|&(router,port)|waiting(load[router][port],route.packet_size)).sum::<f64>();
+			(
+				ConfigurationValue::Number(weighted(&packet_delay)),
+				ConfigurationValue::Number(weighted(&|route|packet_delay(route)+((route.packets-1)*route.packet_size) as f64)),
+			)
+		}
+		else
+		{
+			(ConfigurationValue::None,ConfigurationValue::None)
+		};
+		let injected_load = offered/num_servers as f64;
+		println!("Analytic estimate: injected load {}, maximum link utilization {}. This is not a simulation.",injected_load,maximum_utilization);
+		let mut content = vec![
+			(String::from("analytic"),ConfigurationValue::True),
+			(String::from("cycle"),ConfigurationValue::Number(0.0)),
+			(String::from("injected_load"),ConfigurationValue::Number(injected_load)),
+			(String::from("accepted_load"),ConfigurationValue::Number(injected_load/saturation)),
+			(String::from("average_packet_network_delay"),packet_delay),
+			(String::from("average_message_delay"),message_delay),
+			(String::from("average_packet_hops"),ConfigurationValue::Number(average_hops)),
+			(String::from("average_link_utilization"),ConfigurationValue::Number(average_utilization)),
+			(String::from("maximum_link_utilization"),ConfigurationValue::Number(maximum_utilization)),
+		];
+		if link_utilization
+		{
+			let matrix = (0..topology.num_routers()).map(|router|ConfigurationValue::Array(
+				(0..topology.degree(router)).map(|port|ConfigurationValue::Number(load[router][port])).collect()
+			)).collect();
+			content.push((String::from("link_utilization"),ConfigurationValue::Array(matrix)));
+		}
+		ConfigurationValue::Object(String::from("Result"),content)
+	}
+}
@@ -0,0 +1,351 @@
+/*!
+
+Typed construction of simulations from Rust code.
+
+A [Simulation](crate::Simulation) is built from a `Configuration` object, usually parsed from a `main.cfg` file. Crates embedding the simulator may instead build it with a [SimulationBuilder],
+which fills the mandatory fields of the `Configuration` and checks that none is missing.
+The components, as the topology or the routing, are given as [ConfigurationObject]s, or as any other value convertible into a [ConfigurationValue].
+The common components have typed constructors, as [ConfigurationObject::torus], to which further fields can be added.
+```ignore
+use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+let plugs = caminos_lib::Plugs::default();
+let mut simulation = SimulationBuilder::new()
+	.random_seed(3)
+	.warmup(2000)
+	.measured(10000)
+	.topology(ConfigurationObject::torus(&[8,8],1))
+	.traffic(ConfigurationObject::homogeneous_traffic(ConfigurationObject::uniform(),64,0.5,16))
+	.router(ConfigurationObject::basic_router(2,64,32,16).field("bubble",true))
+	//Any component can also be written field by field.
+	.routing(ConfigurationObject::new("DOR").field("order",vec![0,1]))
+	.link_delays(&[1,1])
+	//Any other field of the `Configuration`, as the optional features.
+	.option("deadlock_watchdog",ConfigurationObject::new("DeadlockWatchdog").field("cycles",10000))
+	.build(&plugs)
+	.expect("the simulation should be well configured");
+simulation.run();
+let results = simulation.results();
+```
+The configuration can also be obtained with [SimulationBuilder::configuration] to be written to a file or to be modified further.
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::event::Time;
+use crate::{error,source_location,Simulation,Plugs};
+
+macro_rules! number_into_configuration_value{
+	($($number:ty),*) => {$(
+		impl From<$number> for ConfigurationValue
+		{
+			fn from(number:$number) -> ConfigurationValue
+			{
+				ConfigurationValue::Number(number as f64)
+			}
+		}
+	)*};
+}
+
+number_into_configuration_value!(i32,i64,u32,u64,usize,f32,f64);
+
+impl From<bool> for ConfigurationValue
+{
+	fn from(value:bool) -> ConfigurationValue
+	{
+		if value { ConfigurationValue::True } else { ConfigurationValue::False }
+	}
+}
+
+impl From<&str> for ConfigurationValue
+{
+	fn from(literal:&str) -> ConfigurationValue
+	{
+		ConfigurationValue::Literal(literal.to_string())
+	}
+}
+
+impl From<String> for ConfigurationValue
+{
+	fn from(literal:String) -> ConfigurationValue
+	{
+		ConfigurationValue::Literal(literal)
+	}
+}
+
+impl<T:Into<ConfigurationValue>> From<Vec<T>> for ConfigurationValue
+{
+	fn from(values:Vec<T>) -> ConfigurationValue
+	{
+		ConfigurationValue::Array(values.into_iter().map(|value|value.into()).collect())
+	}
+}
+
+///An object of the configuration, as `Torus{sides:[8,8],servers_per_router:1}`, built field by field.
+#[derive(Clone,Debug)]
+pub struct ConfigurationObject
+{
+	name: String,
+	fields: Vec<(String,ConfigurationValue)>,
+}
+
+impl ConfigurationObject
+{
+	///An object without fields, as `Shortest`.
+	pub fn new(name:&str) -> ConfigurationObject
+	{
+		ConfigurationObject{
+			name: name.to_string(),
+			fields: vec![],
+		}
+	}
+	///Adds the field `name` with the given value.
+	pub fn field(mut self, name:&str, value:impl Into<ConfigurationValue>) -> ConfigurationObject
+	{
+		self.fields.push((name.to_string(),value.into()));
+		self
+	}
+	///A `Hamming` topology, with a complete graph in each dimension.
+	pub fn hamming(sides:&[usize], servers_per_router:usize) -> ConfigurationObject
+	{
+		ConfigurationObject::new("Hamming").field("sides",sides.to_vec()).field("servers_per_router",servers_per_router)
+	}
+	///A `Mesh` topology.
+	pub fn mesh(sides:&[usize], servers_per_router:usize) -> ConfigurationObject
+	{
+		ConfigurationObject::new("Mesh").field("sides",sides.to_vec()).field("servers_per_router",servers_per_router)
+	}
+	///A `Torus` topology.
+	pub fn torus(sides:&[usize], servers_per_router:usize) -> ConfigurationObject
+	{
+		ConfigurationObject::new("Torus").field("sides",sides.to_vec()).field("servers_per_router",servers_per_router)
+	}
+	///A `CanonicDragonfly` topology, whose size is given by the global ports of each router.
+	pub fn dragonfly(global_ports_per_router:usize, servers_per_router:usize) -> ConfigurationObject
+	{
+		ConfigurationObject::new("CanonicDragonfly").field("global_ports_per_router",global_ports_per_router).field("servers_per_router",servers_per_router)
+	}
+	///A `HomogeneousTraffic` in which every server sends messages of `message_size` phits to the destinations given by `pattern` with the given `load`.
+	pub fn homogeneous_traffic(pattern:ConfigurationObject, servers:usize, load:f64, message_size:usize) -> ConfigurationObject
+	{
+		ConfigurationObject::new("HomogeneousTraffic").field("pattern",pattern).field("servers",servers).field("load",load).field("message_size",message_size)
+	}
+	///The `Uniform` pattern, to a random server other than the origin.
+	pub fn uniform() -> ConfigurationObject
+	{
+		ConfigurationObject::new("Uniform")
+	}
+	///A `Basic` router selecting randomly among the virtual channels with space, with neither bubble nor priorities.
+	///The other options can be added with [ConfigurationObject::field].
+	pub fn basic_router(virtual_channels:usize, buffer_size:usize, output_buffer_size:usize, flit_size:usize) -> ConfigurationObject
+	{
+		ConfigurationObject::new("Basic")
+			.field("virtual_channels",virtual_channels)
+			.field("virtual_channel_policies",vec![ConfigurationObject::new("EnforceFlowControl"),ConfigurationObject::new("Random")])
+			.field("buffer_size",buffer_size)
+			.field("output_buffer_size",output_buffer_size)
+			.field("bubble",false)
+			.field("flit_size",flit_size)
+			.field("allow_request_busy_port",true)
+			.field("intransit_priority",false)
+			.field("neglect_busy_output",false)
+			.field("output_prioritize_lowest_label",false)
+	}
+	///An `InputOutput` router with a `Random` allocator, selecting randomly among the virtual channels with space, with neither bubble nor priorities.
+	///The other options can be added with [ConfigurationObject::field].
+	pub fn input_output_router(virtual_channels:usize, buffer_size:usize, output_buffer_size:usize, flit_size:usize) -> ConfigurationObject
+	{
+		ConfigurationObject::new("InputOutput")
+			.field("virtual_channels",virtual_channels)
+			.field("virtual_channel_policies",vec![ConfigurationObject::new("EnforceFlowControl"),ConfigurationObject::new("Random")])
+			.field("allocator",ConfigurationObject::new("Random"))
+			.field("buffer_size",buffer_size)
+			.field("output_buffer_size",output_buffer_size)
+			.field("bubble",false)
+			.field("flit_size",flit_size)
+			.field("allow_request_busy_port",true)
+			.field("intransit_priority",false)
+			.field("neglect_busy_output",false)
+	}
+	///The `Shortest` routing, taking any minimal route.
+	pub fn shortest() -> ConfigurationObject
+	{
+		ConfigurationObject::new("Shortest")
+	}
+	///The `DOR` routing, correcting the dimensions in the given order.
+	pub fn dor(order:&[usize]) -> ConfigurationObject
+	{
+		ConfigurationObject::new("DOR").field("order",order.to_vec())
+	}
+	///The `Valiant` routing, going with `first` to a random intermediate router and then with `second` to the destination.
+	pub fn valiant(first:ConfigurationObject, second:ConfigurationObject) -> ConfigurationObject
+	{
+		ConfigurationObject::new("Valiant").field("first",first).field("second",second)
+	}
+}
+
+impl From<ConfigurationObject> for ConfigurationValue
+{
+	fn from(object:ConfigurationObject) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(object.name,object.fields)
+	}
+}
+
+///The fields of the `Configuration` set by the dedicated methods of [SimulationBuilder] instead of [SimulationBuilder::option].
+const DEDICATED_FIELDS: [&str;10] = ["random_seed","warmup","measured","topology","traffic","router","routing","link_classes","maximum_packet_size","general_frequency_divisor"];
+
+///Builds the `Configuration` of a simulation, and the simulation itself, from Rust code.
+///The `warmup`, `measured`, `topology`, `traffic`, `router`, `routing` and link classes are mandatory.
+///By default the `random_seed` is 1, the `maximum_packet_size` is 16, and the `general_frequency_divisor` is 1.
+#[derive(Clone,Debug)]
+pub struct SimulationBuilder
+{
+	random_seed: usize,
+	warmup: Option<Time>,
+	measured: Option<Time>,
+	topology: Option<ConfigurationValue>,
+	traffic: Option<ConfigurationValue>,
+	router: Option<ConfigurationValue>,
+	routing: Option<ConfigurationValue>,
+	link_classes: Option<ConfigurationValue>,
+	maximum_packet_size: usize,
+	general_frequency_divisor: Time,
+	///Any other field of the `Configuration`.
+	options: Vec<(String,ConfigurationValue)>,
+}
+
+impl Default for SimulationBuilder
+{
+	fn default() -> SimulationBuilder
+	{
+		SimulationBuilder::new()
+	}
+}
+
+impl SimulationBuilder
+{
+	pub fn new() -> SimulationBuilder
+	{
+		SimulationBuilder{
+			random_seed: 1,
+			warmup: None,
+			measured: None,
+			topology: None,
+			traffic: None,
+			router: None,
+			routing: None,
+			link_classes: None,
+			maximum_packet_size: 16,
+			general_frequency_divisor: 1,
+			options: vec![],
+		}
+	}
+	pub fn random_seed(mut self, random_seed:usize) -> SimulationBuilder
+	{
+		self.random_seed = random_seed;
+		self
+	}
+	///The cycles to simulate before beginning the measurement.
+	pub fn warmup(mut self, warmup:Time) -> SimulationBuilder
+	{
+		self.warmup = Some(warmup);
+		self
+	}
+	///The cycles in which the statistics are measured.
+	pub fn measured(mut self, measured:Time) -> SimulationBuilder
+	{
+		self.measured = Some(measured);
+		self
+	}
+	pub fn topology(mut self, topology:impl Into<ConfigurationValue>) -> SimulationBuilder
+	{
+		self.topology = Some(topology.into());
+		self
+	}
+	pub fn traffic(mut self, traffic:impl Into<ConfigurationValue>) -> SimulationBuilder
+	{
+		self.traffic = Some(traffic.into());
+		self
+	}
+	pub fn router(mut self, router:impl Into<ConfigurationValue>) -> SimulationBuilder
+	{
+		self.router = Some(router.into());
+		self
+	}
+	pub fn routing(mut self, routing:impl Into<ConfigurationValue>) -> SimulationBuilder
+	{
+		self.routing = Some(routing.into());
+		self
+	}
+	///The classes of the links, each a `LinkClass` object. The last class is the one of the links between servers and routers.
+	pub fn link_classes(mut self, link_classes:Vec<ConfigurationObject>) -> SimulationBuilder
+	{
+		self.link_classes = Some(link_classes.into());
+		self
+	}
+	///Link classes with the given delays and no other option.
+	pub fn link_delays(self, delays:&[Time]) -> SimulationBuilder
+	{
+		self.link_classes(delays.iter().map(|&delay|ConfigurationObject::new("LinkClass").field("delay",delay)).collect())
+	}
+	pub fn maximum_packet_size(mut self, maximum_packet_size:usize) -> SimulationBuilder
+	{
+		self.maximum_packet_size = maximum_packet_size;
+		self
+	}
+	pub fn general_frequency_divisor(mut self, general_frequency_divisor:Time) -> SimulationBuilder
+	{
+		self.general_frequency_divisor = general_frequency_divisor;
+		self
+	}
+	///Sets any other field of the `Configuration`, replacing a previous value of it.
+	///The fields with a dedicated method, as `warmup` or `topology`, must be given through it, and [SimulationBuilder::configuration] reports an error otherwise.
+	pub fn option(mut self, name:&str, value:impl Into<ConfigurationValue>) -> SimulationBuilder
+	{
+		let value = value.into();
+		match self.options.iter_mut().find(|(key,_)|key==name)
+		{
+			Some(option) => option.1 = value,
+			None => self.options.push((name.to_string(),value)),
+		}
+		self
+	}
+	///The `Configuration` object, or an error naming the first mandatory field missing or the first option that should have been set by its own method.
+	pub fn configuration(&self) -> Result<ConfigurationValue,Error>
+	{
+		if let Some((name,_)) = self.options.iter().find(|(name,_)|DEDICATED_FIELDS.contains(&name.as_str()))
+		{
+			return Err(error!(bad_argument).with_message(format!("The field {} must be set with its own method of the SimulationBuilder instead of option.",name)));
+		}
+		let warmup = self.warmup.ok_or_else(||error!(bad_argument).with_message("The simulation requires a warmup.".to_string()))?;
+		let measured = self.measured.ok_or_else(||error!(bad_argument).with_message("The simulation requires a measured.".to_string()))?;
+		let mandatory = [
+			("topology",&self.topology),
+			("traffic",&self.traffic),
+			("router",&self.router),
+			("routing",&self.routing),
+			("link_classes",&self.link_classes),
+		];
+		let mut fields = vec![
+			(String::from("random_seed"),ConfigurationValue::from(self.random_seed)),
+			(String::from("warmup"),ConfigurationValue::from(warmup)),
+			(String::from("measured"),ConfigurationValue::from(measured)),
+			(String::from("maximum_packet_size"),ConfigurationValue::from(self.maximum_packet_size)),
+			(String::from("general_frequency_divisor"),ConfigurationValue::from(self.general_frequency_divisor)),
+		];
+		for (name,value) in mandatory
+		{
+			let value = value.clone().ok_or_else(||error!(bad_argument).with_message(format!("The simulation requires a {}.",name)))?;
+			fields.push((name.to_string(),value));
+		}
+		fields.extend(self.options.iter().cloned());
+		Ok(ConfigurationValue::Object(String::from("Configuration"),fields))
+	}
+	///Builds the simulation, with the components registered in the `plugs`.
+	///A component that cannot be built from its configuration is reported as an error, see [Simulation::try_new].
+	pub fn build<'a>(&self, plugs:&'a Plugs) -> Result<Simulation<'a>,Error>
+	{
+		Simulation::try_new(&self.configuration()?,plugs)
+	}
+}
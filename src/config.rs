@@ -1,6 +1,6 @@
 
 use std::io::{self,Write,Read,Seek};
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap,BTreeSet};
 use std::convert::TryInto;
 use std::path::Path;
 use std::fs::File;
@@ -211,6 +211,110 @@ fn expand_named_experiments_range(experiments:ConfigurationValue, names:&BTreeMa
 	r
 }
 
+/**
+Expands each experiment with a `repetitions: n` field into `n` experiments, each one with a different `random_seed`.
+The repetition `r` gets `random_seed: base*n+r`, where `base` is the original `random_seed`, or 0 if there is none.
+Thus different base seeds do not share any repetition seed. The `repetitions` field is replaced by `repetition: r`, so that outputs may refer to it.
+Experiments without `repetitions` are kept as they are.
+**/
+pub fn expand_repetitions(experiments:Vec<ConfigurationValue>) -> Result<Vec<ConfigurationValue>,Error>
+{
+	let mut expanded = Vec::with_capacity(experiments.len());
+	for experiment in experiments
+	{
+		let (name,fields) = match experiment
+		{
+			ConfigurationValue::Object(ref name, ref fields) if fields.iter().any(|(key,_)|key=="repetitions") => (name,fields),
+			_ =>
+			{
+				expanded.push(experiment);
+				continue;
+			}
+		};
+		let mut repetitions = 1;
+		let mut base_seed = 0;
+		for (key,value) in fields
+		{
+			match key.as_ref()
+			{
+				"repetitions" => repetitions = value.as_usize()?,
+				"random_seed" => base_seed = value.as_usize()?,
+				_ => (),
+			}
+		}
+		for repetition in 0..repetitions
+		{
+			let mut new_fields : Vec<(String,ConfigurationValue)> = fields.iter().filter(|(key,_)|key!="repetitions" && key!="random_seed").cloned().collect();
+			new_fields.push( ("random_seed".to_string(),ConfigurationValue::Number((base_seed*repetitions+repetition) as f64)) );
+			new_fields.push( ("repetition".to_string(),ConfigurationValue::Number(repetition as f64)) );
+			expanded.push(ConfigurationValue::Object(name.clone(),new_fields));
+		}
+	}
+	Ok(expanded)
+}
+
+/**
+Expands each experiment with a `seeds: n` field into `n` experiments, each one with a `random_seed` from [spaced_seed].
+The seeds are derived from the original `random_seed`, or 0 if there is none, so that different base seeds give unrelated seeds.
+The `seeds` field is replaced by `seed_index: i`, so that outputs may refer to it.
+Experiments without `seeds` are kept as they are.
+**/
+pub fn expand_seeds(experiments:Vec<ConfigurationValue>) -> Result<Vec<ConfigurationValue>,Error>
+{
+	let mut expanded = Vec::with_capacity(experiments.len());
+	for experiment in experiments
+	{
+		let (name,fields) = match experiment
+		{
+			ConfigurationValue::Object(ref name, ref fields) if fields.iter().any(|(key,_)|key=="seeds") => (name,fields),
+			_ =>
+			{
+				expanded.push(experiment);
+				continue;
+			}
+		};
+		let mut seeds = 1;
+		let mut base_seed = 0;
+		for (key,value) in fields
+		{
+			match key.as_ref()
+			{
+				"seeds" => seeds = value.as_usize()?,
+				"random_seed" => base_seed = value.as_usize()?,
+				_ => (),
+			}
+		}
+		let mut used = BTreeSet::new();
+		for seed_index in 0..seeds
+		{
+			//Skip the extremely unlikely repeated seeds.
+			let mut seed = spaced_seed(base_seed,seed_index);
+			while !used.insert(seed)
+			{
+				seed = spaced_seed(seed,seed_index);
+			}
+			let mut new_fields : Vec<(String,ConfigurationValue)> = fields.iter().filter(|(key,_)|key!="seeds" && key!="random_seed").cloned().collect();
+			new_fields.push( ("random_seed".to_string(),ConfigurationValue::Number(seed as f64)) );
+			new_fields.push( ("seed_index".to_string(),ConfigurationValue::Number(seed_index as f64)) );
+			expanded.push(ConfigurationValue::Object(name.clone(),new_fields));
+		}
+	}
+	Ok(expanded)
+}
+
+/**
+The `index`-th seed generated from `base` by `seeds: n`. The pair is mixed by the SplitMix64 finalizer so that the seeds are spread over the range,
+and only its 32 most significant bits are kept, so that the seed is exactly represented in a [ConfigurationValue::Number].
+**/
+pub fn spaced_seed(base:usize, index:usize) -> usize
+{
+	let mut z = (base as u64).wrapping_mul(0x9E3779B97F4A7C15).wrapping_add((index as u64).wrapping_add(1).wrapping_mul(0xD1B54A32D192ED03));
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+	z ^= z >> 31;
+	(z >> 32) as usize
+}
+
 /**
 Expands in `value` all the `NamedExperiments` with a name in `names` to its value at index `names[name]`.
 **/
@@ -278,7 +382,7 @@ For example the expression `=Alpha.beta` will return 42 for the context `Alpha{b
 Arguments `first` and `second`. It evaluates to `ConfigurationValue::{True,False}`.
 
 * eq or equal
-* lt
+* lt, le, gt, ge for the numeric comparisons `<`, `<=`, `>` and `>=`
 
 ## Arithmetic
 
@@ -405,7 +509,7 @@ pub fn evaluate(expr:&Expr, context:&ConfigurationValue, path:&Path) -> Result<C
 					//allow any type
 					Ok(if first==second { ConfigurationValue::True } else { ConfigurationValue::False })
 				}
-				"lt" =>
+				"lt" | "le" | "gt" | "ge" =>
 				{
 					let mut first=None;
 					let mut second=None;
@@ -424,19 +528,26 @@ pub fn evaluate(expr:&Expr, context:&ConfigurationValue, path:&Path) -> Result<C
 							_ => panic!("unknown argument `{}' for function `{}'",key,function_name),
 						}
 					}
-					let first=first.expect("first argument of lt not given.");
-					let second=second.expect("second argument of lt not given.");
+					let first=first.unwrap_or_else(||panic!("first argument of {} not given.",function_name));
+					let second=second.unwrap_or_else(||panic!("second argument of {} not given.",function_name));
 					let first=match first
 					{
 						ConfigurationValue::Number(x) => x,
-						_ => panic!("first argument of lt evaluated to a non-number ({}:?)",first),
+						_ => panic!("first argument of {} evaluated to a non-number ({}:?)",function_name,first),
 					};
 					let second=match second
 					{
 						ConfigurationValue::Number(x) => x,
-						_ => panic!("second argument of lt evaluated to a non-number ({}:?)",second),
+						_ => panic!("second argument of {} evaluated to a non-number ({}:?)",function_name,second),
 					};
-					Ok(if first<second { ConfigurationValue::True } else { ConfigurationValue::False })
+					let holds = match function_name.as_ref()
+					{
+						"lt" => first<second,
+						"le" => first<=second,
+						"gt" => first>second,
+						_ => first>=second,
+					};
+					Ok(if holds { ConfigurationValue::True } else { ConfigurationValue::False })
 				}
 				"if" =>
 				{
@@ -1811,6 +1922,280 @@ pub fn config_from_binary(data:&[u8], offset:usize) -> Result<ConfigurationValue
 	}
 }
 
+/**
+Converts a [ConfigurationValue] into a line of JSON, to load the results with tools that do not understand the configuration syntax.
+* An `Object` becomes a JSON object with its name in the `_object` key followed by its fields.
+* An `Array` becomes a JSON array. `Experiments` become an object with the key `_experiments` and, if named, the key `_name`.
+* Numbers are written as is, except the non-finite ones, such as the `NaN` average delay of a simulation without consumed messages, which become `null` to keep the output standard JSON.
+* `None` becomes `null`. Expressions become a string with their text after a `=`.
+
+[config_from_json] reads it back, with the `null`s as `None`. It also accepts `NaN`, `Infinity`, and `-Infinity`, as written by previous versions.
+**/
+pub fn config_to_json(value:&ConfigurationValue) -> String
+{
+	let mut output = String::new();
+	write_json(value,&mut output);
+	output
+}
+
+fn write_json(value:&ConfigurationValue, output:&mut String)
+{
+	use std::fmt::Write;
+	let write_string = |s:&str, output:&mut String| {
+		output.push('"');
+		for c in s.chars()
+		{
+			match c
+			{
+				'"' => output.push_str("\\\""),
+				'\\' => output.push_str("\\\\"),
+				'\n' => output.push_str("\\n"),
+				'\r' => output.push_str("\\r"),
+				'\t' => output.push_str("\\t"),
+				c if (c as u32) < 0x20 => write!(output,"\\u{:04x}",c as u32).unwrap(),
+				c => output.push(c),
+			}
+		}
+		output.push('"');
+	};
+	let write_list = |list:&[ConfigurationValue], output:&mut String| {
+		output.push('[');
+		for (index,element) in list.iter().enumerate()
+		{
+			if index>0 { output.push(','); }
+			write_json(element,output);
+		}
+		output.push(']');
+	};
+	match value
+	{
+		ConfigurationValue::Literal(s) => write_string(s,output),
+		ConfigurationValue::Number(x) =>
+		{
+			if x.is_finite() { write!(output,"{}",x).unwrap() }
+			else { output.push_str("null") }
+		},
+		ConfigurationValue::Object(name,fields) =>
+		{
+			output.push_str("{\"_object\":");
+			write_string(name,output);
+			for (key,field) in fields.iter()
+			{
+				output.push(',');
+				write_string(key,output);
+				output.push(':');
+				write_json(field,output);
+			}
+			output.push('}');
+		},
+		ConfigurationValue::Array(list) => write_list(list,output),
+		ConfigurationValue::Experiments(list) =>
+		{
+			output.push_str("{\"_experiments\":");
+			write_list(list,output);
+			output.push('}');
+		},
+		ConfigurationValue::NamedExperiments(name,list) =>
+		{
+			output.push_str("{\"_name\":");
+			write_string(name,output);
+			output.push_str(",\"_experiments\":");
+			write_list(list,output);
+			output.push('}');
+		},
+		ConfigurationValue::True => output.push_str("true"),
+		ConfigurationValue::False => output.push_str("false"),
+		ConfigurationValue::Where(cv,_expr) => write_json(cv,output),
+		ConfigurationValue::Expression(expr) => write_string(&format!("={}",expr),output),
+		ConfigurationValue::None => output.push_str("null"),
+	}
+}
+
+/**
+Reads a [ConfigurationValue] from JSON, as written by [config_to_json].
+JSON objects without an `_object` key are read as objects named `JSON`. Expressions are kept as strings.
+**/
+pub fn config_from_json(text:&str) -> Result<ConfigurationValue,Error>
+{
+	let mut parser = JsonParser{ text:text.as_bytes(), position:0 };
+	let value = parser.value()?;
+	parser.skip_whitespace();
+	if parser.position<parser.text.len()
+	{
+		return Err(parser.error("unexpected content after the value"));
+	}
+	Ok(value)
+}
+
+///A recursive descent parser for [config_from_json].
+struct JsonParser<'a>
+{
+	text: &'a [u8],
+	position: usize,
+}
+
+impl<'a> JsonParser<'a>
+{
+	fn error(&self, message:&str) -> Error
+	{
+		error!(undetermined).with_message(format!("invalid JSON at byte {}: {}",self.position,message))
+	}
+	fn skip_whitespace(&mut self)
+	{
+		while self.position<self.text.len() && self.text[self.position].is_ascii_whitespace()
+		{
+			self.position+=1;
+		}
+	}
+	///Consumes `token` if it is next.
+	fn eat(&mut self, token:&str) -> bool
+	{
+		if self.text[self.position..].starts_with(token.as_bytes())
+		{
+			self.position+=token.len();
+			true
+		} else { false }
+	}
+	fn value(&mut self) -> Result<ConfigurationValue,Error>
+	{
+		self.skip_whitespace();
+		match self.text.get(self.position)
+		{
+			None => Err(self.error("expected a value")),
+			Some(b'{') => self.object(),
+			Some(b'[') =>
+			{
+				self.position+=1;
+				Ok(ConfigurationValue::Array(self.elements()?))
+			},
+			Some(b'"') => Ok(ConfigurationValue::Literal(self.string()?)),
+			_ =>
+			{
+				if self.eat("true") { return Ok(ConfigurationValue::True) }
+				if self.eat("false") { return Ok(ConfigurationValue::False) }
+				if self.eat("null") { return Ok(ConfigurationValue::None) }
+				if self.eat("NaN") { return Ok(ConfigurationValue::Number(f64::NAN)) }
+				if self.eat("Infinity") { return Ok(ConfigurationValue::Number(f64::INFINITY)) }
+				if self.eat("-Infinity") { return Ok(ConfigurationValue::Number(f64::NEG_INFINITY)) }
+				let begin = self.position;
+				while self.position<self.text.len() && matches!(self.text[self.position],b'0'..=b'9'|b'-'|b'+'|b'.'|b'e'|b'E')
+				{
+					self.position+=1;
+				}
+				std::str::from_utf8(&self.text[begin..self.position]).ok().and_then(|number|number.parse::<f64>().ok())
+					.map(ConfigurationValue::Number).ok_or_else(||self.error("expected a value"))
+			},
+		}
+	}
+	///The elements of an array, after its opening bracket.
+	fn elements(&mut self) -> Result<Vec<ConfigurationValue>,Error>
+	{
+		let mut list = vec![];
+		self.skip_whitespace();
+		if self.eat("]")
+		{
+			return Ok(list);
+		}
+		loop
+		{
+			list.push(self.value()?);
+			self.skip_whitespace();
+			if self.eat("]") { return Ok(list) }
+			if !self.eat(",") { return Err(self.error("expected a comma or a closing bracket")) }
+		}
+	}
+	fn object(&mut self) -> Result<ConfigurationValue,Error>
+	{
+		self.position+=1;
+		let mut name = None;
+		let mut experiments = None;
+		let mut fields = vec![];
+		self.skip_whitespace();
+		if !self.eat("}")
+		{
+			loop
+			{
+				self.skip_whitespace();
+				let key = self.string()?;
+				self.skip_whitespace();
+				if !self.eat(":") { return Err(self.error("expected a colon")) }
+				let value = self.value()?;
+				match (key.as_ref(),value)
+				{
+					("_object",ConfigurationValue::Literal(s)) | ("_name",ConfigurationValue::Literal(s)) => name=Some(s),
+					("_experiments",ConfigurationValue::Array(list)) => experiments=Some(list),
+					(_,value) => fields.push((key,value)),
+				}
+				self.skip_whitespace();
+				if self.eat("}") { break }
+				if !self.eat(",") { return Err(self.error("expected a comma or a closing brace")) }
+			}
+		}
+		Ok(match (experiments,name)
+		{
+			(Some(list),Some(name)) => ConfigurationValue::NamedExperiments(name,list),
+			(Some(list),None) => ConfigurationValue::Experiments(list),
+			(None,name) => ConfigurationValue::Object(name.unwrap_or_else(||"JSON".to_string()),fields),
+		})
+	}
+	fn string(&mut self) -> Result<String,Error>
+	{
+		if !self.eat("\"") { return Err(self.error("expected a string")) }
+		let mut bytes = vec![];
+		loop
+		{
+			match self.text.get(self.position)
+			{
+				None => return Err(self.error("unterminated string")),
+				Some(b'"') => { self.position+=1; break },
+				Some(b'\\') =>
+				{
+					let escaped = *self.text.get(self.position+1).ok_or_else(||self.error("unterminated string"))?;
+					self.position+=2;
+					match escaped
+					{
+						b'n' => bytes.push(b'\n'),
+						b'r' => bytes.push(b'\r'),
+						b't' => bytes.push(b'\t'),
+						b'b' => bytes.push(8),
+						b'f' => bytes.push(12),
+						b'u' =>
+						{
+							let code = self.text.get(self.position..self.position+4)
+								.and_then(|hex|std::str::from_utf8(hex).ok())
+								.and_then(|hex|u32::from_str_radix(hex,16).ok())
+								.ok_or_else(||self.error("bad unicode escape"))?;
+							self.position+=4;
+							let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+							bytes.extend_from_slice(c.to_string().as_bytes());
+						},
+						other => bytes.push(other),
+					}
+				},
+				Some(&byte) => { bytes.push(byte); self.position+=1 },
+			}
+		}
+		String::from_utf8(bytes).map_err(|_|self.error("the string is not UTF-8"))
+	}
+}
+
+/**
+Parses the contents of a result file, either in the configuration syntax or in JSON as written with `result_format: "json"`.
+**/
+pub fn parse_result(contents:&str) -> Result<ConfigurationValue,Error>
+{
+	if contents.trim_start().starts_with('{')
+	{
+		return config_from_json(contents.trim());
+	}
+	match config_parser::parse(contents)
+	{
+		Ok(config_parser::Token::Value(value)) => Ok(value),
+		Ok(_) => Err(error!(undetermined).with_message("the result is not a value".to_string())),
+		Err(e) => Err(error!(undetermined).with_message(format!("could not parse the result: {:?}",e))),
+	}
+}
+
 
 ///Rewrites the value in-place.
 ///If `edition` is `term=new_value` where `term` can be interpreted as a left-value then replace its content with `new_value`.
@@ -1991,6 +2376,9 @@ pub fn config_relaxed_cmp(a:&ConfigurationValue, b:&ConfigurationValue) -> bool
 /// the remaining arguments are the arms of the match.
 #[macro_export]
 macro_rules! match_object{
+	($cv:expr, $name:literal, $valueid:ident ) => {{
+		match_object!($cv,[$name],$valueid,)
+	}};
 	//($cv:expr, $name:literal, $valueid:ident, $($key:literal => $arm:tt)* ) => {{
 	($cv:expr, $name:literal, $valueid:ident, $($arm:tt)* ) => {{
 		match_object!($cv,[$name],$valueid,$($arm)*)
@@ -2002,9 +2390,9 @@ macro_rules! match_object{
 			if !$names.iter().any(|&x|x==cv_name)
 			{
 				if $names.len()==1 {
-					panic!("A {} must be created from a `{}` object not `{}`",$names[0],$names[0],cv_name);
+					return Err(error!(ill_formed_configuration,$cv.clone()).with_message(format!("A {} must be created from a `{}` object not `{}`",$names[0],$names[0],cv_name)));
 				} else {
-					panic!("Trying to create either of `{:?}` object from `{}`",$names,cv_name);
+					return Err(error!(ill_formed_configuration,$cv.clone()).with_message(format!("Trying to create either of `{:?}` object from `{}`",$names,cv_name)));
 				}
 			}
 			for &(ref name,ref $valueid) in cv_pairs
@@ -2188,9 +2576,13 @@ impl ConfigurationValue
 		}
 	}
 	/// Build a generic IllFormedConfiguration error from this ConfigurationValue.
+	/// The error is located at the caller, typically the builder rejecting the value.
+	#[track_caller]
 	pub fn ill(&self,message:&str) -> Error
 	{
-		error!(ill_formed_configuration,self.clone()).with_message(message.to_string())
+		let caller = std::panic::Location::caller();
+		let source_location = SourceLocation{ file:caller.file(), line:caller.line(), column:caller.column() };
+		Error::ill_formed_configuration(source_location,self.clone()).with_message(message.to_string())
 	}
 	/// Convert this value into some string without newlines or commas.
 	/// If not possible just return `"error".to_string()`.
@@ -2514,6 +2906,41 @@ impl ConfigurationValue
 mod tests {
 	use super::*;
 	#[test]
+	fn json_round_trip()
+	{
+		let result = ConfigurationValue::Object("Result".to_string(),vec![
+			("accepted_load".to_string(),ConfigurationValue::Number(0.75)),
+			("average_message_delay".to_string(),ConfigurationValue::Number(f64::NAN)),
+			("git_id".to_string(),ConfigurationValue::Literal("a \"quoted\"\tname".to_string())),
+			("per_hop".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0),ConfigurationValue::Number(-2.5e-3)])),
+			("flags".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::True,ConfigurationValue::False,ConfigurationValue::None])),
+			("routing_statistics".to_string(),ConfigurationValue::Object("Empty".to_string(),vec![])),
+		]);
+		let json = config_to_json(&result);
+		assert!(json.starts_with("{\"_object\":\"Result\",\"accepted_load\":0.75,\"average_message_delay\":null,"),"{}",json);
+		let back = parse_result(&json).expect("could not read back the JSON");
+		//The non-finite numbers are read back as None.
+		let mut expected = result.clone();
+		if let ConfigurationValue::Object(_,ref mut fields) = expected
+		{
+			fields[1].1 = ConfigurationValue::None;
+		}
+		assert_eq!(back,expected);
+		assert_eq!(config_to_json(&ConfigurationValue::Array(vec![ConfigurationValue::Number(f64::INFINITY),ConfigurationValue::Number(f64::NEG_INFINITY)])),"[null,null]");
+		//The non-standard values written by previous versions are still understood.
+		match config_from_json("[NaN,Infinity,-Infinity]").unwrap()
+		{
+			ConfigurationValue::Array(list) => assert!(list[0].as_f64().unwrap().is_nan() && list[1]==ConfigurationValue::Number(f64::INFINITY) && list[2]==ConfigurationValue::Number(f64::NEG_INFINITY)),
+			_ => panic!("expected an array"),
+		}
+		assert_eq!(parse_result("Result{accepted_load:0.75}").unwrap(),ConfigurationValue::Object("Result".to_string(),vec![("accepted_load".to_string(),ConfigurationValue::Number(0.75))]));
+		assert_eq!(config_from_json("{\"a\": [1, 2e1], \"b\": \"\\u00e9\"}").unwrap(),ConfigurationValue::Object("JSON".to_string(),vec![
+			("a".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0),ConfigurationValue::Number(20.0)])),
+			("b".to_string(),ConfigurationValue::Literal("\u{e9}".to_string())),
+		]));
+		assert!(config_from_json("{\"a\":1").is_err());
+	}
+	#[test]
 	fn config_functions()
 	{
 		use std::path::PathBuf;
@@ -2556,6 +2983,44 @@ mod tests {
 		assert_eq!(flatten_configuration_value(&original),target);
 	}
 	#[test]
+	fn expand_repetitions_test()
+	{
+		use ConfigurationValue::*;
+		let experiments = vec![
+			Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(1.0)),("repetitions".to_string(),Number(3.0))]),
+			Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(2.0))]),
+		];
+		let target = vec![
+			Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(3.0)),("repetition".to_string(),Number(0.0))]),
+			Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(4.0)),("repetition".to_string(),Number(1.0))]),
+			Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(5.0)),("repetition".to_string(),Number(2.0))]),
+			Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(2.0))]),
+		];
+		assert_eq!(expand_repetitions(experiments).unwrap(),target);
+	}
+	#[test]
+	fn expand_seeds_test()
+	{
+		use ConfigurationValue::*;
+		let experiments = vec![
+			Object("Alpha".to_string(),vec![("seeds".to_string(),Number(10.0)),("a".to_string(),Number(1.0))]),
+			Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(2.0))]),
+		];
+		let expanded = expand_seeds(experiments).unwrap();
+		assert_eq!(expanded.len(),11);
+		let mut seeds = BTreeSet::new();
+		for (index,experiment) in expanded[0..10].iter().enumerate()
+		{
+			let fields = match experiment { Object(_,fields) => fields, _ => panic!("expected an object") };
+			assert_eq!(fields[0],("a".to_string(),Number(1.0)));
+			assert_eq!(fields[1],("random_seed".to_string(),Number(spaced_seed(0,index) as f64)));
+			assert_eq!(fields[2],("seed_index".to_string(),Number(index as f64)));
+			seeds.insert(spaced_seed(0,index));
+		}
+		assert_eq!(seeds.len(),10);
+		assert_eq!(expanded[10],Object("Alpha".to_string(),vec![("random_seed".to_string(),Number(2.0))]));
+	}
+	#[test]
 	fn flatten_test_named()
 	{
 		use ConfigurationValue::*;
@@ -0,0 +1,334 @@
+/*!
+
+End-to-end congestion control, by which the servers throttle their injection according to the feedback received from the network.
+
+When the configuration includes `congestion_control` each packet waits in its server until the mechanism allows to inject it.
+The feedback of a consumed packet reaches its origin server after `feedback_delay` cycles, modelling the acknowledgement sent back by the destination.
+
+* `ECN` marks the packets that leave a router output port holding at least `threshold` phits, as given by [Router::port_occupancy](crate::router::Router::port_occupancy).
+  Each server keeps an injection rate, in phits per cycle. It is multiplied by `1-decrease` for each marked packet it hears of and increased by `increase` for each unmarked one, between `minimum_rate` and 1.
+  A server accumulates the rate as credit each cycle, up to the maximum packet size, and injects a packet when its credit covers the packet size.
+* `EndToEndCredits` allows each server at most `window` packets in flight, each one returning its credit when its feedback arrives.
+
+```ignore
+congestion_control: ECN{
+	//Phits in an output port from which the packets are marked.
+	threshold: 32,
+	//Fraction of the rate removed for each marked packet. By default 0.5.
+	decrease: 0.5,
+	//Rate added for each unmarked packet. By default 0.01.
+	increase: 0.01,
+	//The rate never goes below this. By default 0.01.
+	minimum_rate: 0.01,
+	//Cycles for the feedback to reach the origin. By default 0.
+	feedback_delay: 20,
+}
+//congestion_control: EndToEndCredits{ window: 4, feedback_delay: 20 },
+```
+
+The results include `congestion_control`, an object with the statistics of the measured period.
+For `ECN` these are `consumed_packets`, `marked_packets` and `average_rate`, the rate averaged over servers and cycles.
+For `EndToEndCredits` these are `consumed_packets` and `average_packets_in_flight`, averaged over servers and cycles.
+
+*/
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::packet::Packet;
+
+///A mechanism for the servers to regulate their injection from the feedback of the network. See the [module documentation](self).
+pub trait CongestionControl : Debug
+{
+	///The occupancy of a router output port, in phits, from which the packets traversing it are marked. `None` if the mechanism does not mark packets.
+	fn marking_threshold(&self) -> Option<f64>
+	{
+		None
+	}
+	///Called at the beginning of each cycle.
+	fn advance(&mut self, cycle:Time);
+	///Whether the `server` may inject the `packet` in this cycle.
+	fn can_inject(&self, server:usize, packet:&Packet) -> bool;
+	///The `server` has started the injection of the `packet`.
+	fn packet_injected(&mut self, server:usize, packet:&Packet, cycle:Time);
+	///The `packet` has been fully consumed at its destination.
+	fn packet_consumed(&mut self, packet:&Packet, cycle:Time);
+	///The retransmission timer of the `packet` has expired without its acknowledgement, and its origin is going to retransmit it. See [lossy](crate::lossy).
+	fn packet_lost(&mut self, _packet:&Packet, _cycle:Time)
+	{
+	}
+	///Clear the statistics, to begin the measured period.
+	fn reset_statistics(&mut self, cycle:Time);
+	///The statistics since the last reset.
+	fn result(&self, cycle:Time) -> ConfigurationValue;
+}
+
+///Build the congestion control given in the `congestion_control` field of the configuration.
+pub fn new_congestion_control(cv:&ConfigurationValue, num_servers:usize, maximum_packet_size:usize) -> Result<Box<dyn CongestionControl>,Error>
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=cv
+	{
+		match cv_name.as_ref()
+		{
+			"ECN" => Ok(Box::new(ExplicitCongestionNotification::new(cv,num_servers,maximum_packet_size)?)),
+			"EndToEndCredits" => Ok(Box::new(EndToEndCredits::new(cv,num_servers)?)),
+			_ => Err(cv.ill(&format!("Unknown congestion control {}",cv_name))),
+		}
+	}
+	else
+	{
+		Err(cv.ill("Trying to create a congestion control from a non-Object"))
+	}
+}
+
+///The feedback of the consumed packets on their way back to their origin servers.
+#[derive(Debug,Default)]
+struct FeedbackQueue
+{
+	///The cycle of arrival, the origin server, and whether the packet was marked. Sorted by cycle, as the delay is constant.
+	pending: VecDeque<(Time,usize,bool)>,
+}
+
+impl FeedbackQueue
+{
+	fn push(&mut self, cycle:Time, server:usize, marked:bool)
+	{
+		self.pending.push_back((cycle,server,marked));
+	}
+	///Removes and returns the next feedback arriving at `cycle` or before.
+	fn pop(&mut self, cycle:Time) -> Option<(usize,bool)>
+	{
+		match self.pending.front()
+		{
+			Some(&(arrival,server,marked)) if arrival<=cycle =>
+			{
+				self.pending.pop_front();
+				Some((server,marked))
+			},
+			_ => None,
+		}
+	}
+}
+
+///Explicit congestion notification with additive increase and multiplicative decrease of the injection rate. See the [module documentation](self).
+#[derive(Debug)]
+pub struct ExplicitCongestionNotification
+{
+	threshold: f64,
+	decrease: f64,
+	increase: f64,
+	minimum_rate: f64,
+	feedback_delay: Time,
+	///The maximum credit of a server.
+	maximum_credit: f64,
+	///The injection rate of each server, in phits per cycle.
+	rates: Vec<f64>,
+	///The phits that each server may inject.
+	credits: Vec<f64>,
+	feedback: FeedbackQueue,
+	begin_cycle: Time,
+	consumed_packets: usize,
+	marked_packets: usize,
+	///The sum of the rates of all servers in each measured cycle.
+	accumulated_rate: f64,
+}
+
+impl CongestionControl for ExplicitCongestionNotification
+{
+	fn marking_threshold(&self) -> Option<f64>
+	{
+		Some(self.threshold)
+	}
+	fn advance(&mut self, cycle:Time)
+	{
+		while let Some((server,marked)) = self.feedback.pop(cycle)
+		{
+			let rate = &mut self.rates[server];
+			*rate = if marked { *rate*(1.0-self.decrease) } else { *rate+self.increase };
+			*rate = rate.max(self.minimum_rate).min(1.0);
+		}
+		for (credit,rate) in self.credits.iter_mut().zip(self.rates.iter())
+		{
+			*credit = (*credit+rate).min(self.maximum_credit);
+		}
+		self.accumulated_rate += self.rates.iter().sum::<f64>();
+	}
+	fn can_inject(&self, server:usize, packet:&Packet) -> bool
+	{
+		self.credits[server] >= packet.size as f64
+	}
+	fn packet_injected(&mut self, server:usize, packet:&Packet, _cycle:Time)
+	{
+		self.credits[server] -= packet.size as f64;
+	}
+	fn packet_consumed(&mut self, packet:&Packet, cycle:Time)
+	{
+		let marked = *packet.congestion_marked.borrow();
+		self.consumed_packets += 1;
+		if marked
+		{
+			self.marked_packets += 1;
+		}
+		self.feedback.push(cycle+self.feedback_delay,packet.message.origin,marked);
+	}
+	///A loss reduces the rate as a marked packet, without waiting for the feedback.
+	fn packet_lost(&mut self, packet:&Packet, _cycle:Time)
+	{
+		let rate = &mut self.rates[packet.message.origin];
+		*rate = (*rate*(1.0-self.decrease)).max(self.minimum_rate);
+	}
+	fn reset_statistics(&mut self, cycle:Time)
+	{
+		self.begin_cycle = cycle;
+		self.consumed_packets = 0;
+		self.marked_packets = 0;
+		self.accumulated_rate = 0.0;
+	}
+	fn result(&self, cycle:Time) -> ConfigurationValue
+	{
+		let cycles = cycle-self.begin_cycle;
+		ConfigurationValue::Object(String::from("ECN"),vec![
+			(String::from("consumed_packets"),ConfigurationValue::Number(self.consumed_packets as f64)),
+			(String::from("marked_packets"),ConfigurationValue::Number(self.marked_packets as f64)),
+			(String::from("average_rate"),ConfigurationValue::Number(self.accumulated_rate/cycles as f64/self.rates.len() as f64)),
+		])
+	}
+}
+
+impl ExplicitCongestionNotification
+{
+	pub fn new(cv:&ConfigurationValue, num_servers:usize, maximum_packet_size:usize) -> Result<ExplicitCongestionNotification,Error>
+	{
+		let mut threshold=None;
+		let mut decrease=0.5;
+		let mut increase=0.01;
+		let mut minimum_rate=0.01;
+		let mut feedback_delay=0;
+		match_object!(cv,"ECN",value,
+			"threshold" => threshold=Some(value.as_f64()?),
+			"decrease" => decrease=value.as_f64()?,
+			"increase" => increase=value.as_f64()?,
+			"minimum_rate" => minimum_rate=value.as_f64()?,
+			"feedback_delay" => feedback_delay=value.as_time()?,
+		);
+		let threshold=threshold.ok_or_else(||cv.ill("There were no threshold"))?;
+		if !(0.0..=1.0).contains(&decrease)
+		{
+			return Err(cv.ill("The decrease of ECN must be in [0,1]."));
+		}
+		if minimum_rate<=0.0 || minimum_rate>1.0
+		{
+			return Err(cv.ill("The minimum_rate of ECN must be in (0,1]."));
+		}
+		let maximum_credit = maximum_packet_size as f64;
+		Ok(ExplicitCongestionNotification{
+			threshold,
+			decrease,
+			increase,
+			minimum_rate,
+			feedback_delay,
+			maximum_credit,
+			rates: vec![1.0;num_servers],
+			credits: vec![maximum_credit;num_servers],
+			feedback: FeedbackQueue::default(),
+			begin_cycle: 0,
+			consumed_packets: 0,
+			marked_packets: 0,
+			accumulated_rate: 0.0,
+		})
+	}
+	///The current injection rate of a server.
+	pub fn rate(&self, server:usize) -> f64
+	{
+		self.rates[server]
+	}
+}
+
+///A window of packets in flight for each server. See the [module documentation](self).
+#[derive(Debug)]
+pub struct EndToEndCredits
+{
+	window: usize,
+	feedback_delay: Time,
+	///The packets of each server whose feedback has not arrived.
+	in_flight: Vec<usize>,
+	feedback: FeedbackQueue,
+	begin_cycle: Time,
+	consumed_packets: usize,
+	///The sum of the packets in flight of all servers in each measured cycle.
+	accumulated_in_flight: usize,
+}
+
+impl CongestionControl for EndToEndCredits
+{
+	fn advance(&mut self, cycle:Time)
+	{
+		while let Some((server,_marked)) = self.feedback.pop(cycle)
+		{
+			self.in_flight[server] -= 1;
+		}
+		self.accumulated_in_flight += self.in_flight.iter().sum::<usize>();
+	}
+	///A retransmission keeps the credit taken by the first transmission of its packet.
+	fn can_inject(&self, server:usize, packet:&Packet) -> bool
+	{
+		packet.transmission > 0 || self.in_flight[server] < self.window
+	}
+	fn packet_injected(&mut self, server:usize, packet:&Packet, _cycle:Time)
+	{
+		if packet.transmission == 0
+		{
+			self.in_flight[server] += 1;
+		}
+	}
+	fn packet_consumed(&mut self, packet:&Packet, cycle:Time)
+	{
+		self.consumed_packets += 1;
+		self.feedback.push(cycle+self.feedback_delay,packet.message.origin,false);
+	}
+	fn reset_statistics(&mut self, cycle:Time)
+	{
+		self.begin_cycle = cycle;
+		self.consumed_packets = 0;
+		self.accumulated_in_flight = 0;
+	}
+	fn result(&self, cycle:Time) -> ConfigurationValue
+	{
+		let cycles = cycle-self.begin_cycle;
+		ConfigurationValue::Object(String::from("EndToEndCredits"),vec![
+			(String::from("consumed_packets"),ConfigurationValue::Number(self.consumed_packets as f64)),
+			(String::from("average_packets_in_flight"),ConfigurationValue::Number(self.accumulated_in_flight as f64/cycles as f64/self.in_flight.len() as f64)),
+		])
+	}
+}
+
+impl EndToEndCredits
+{
+	pub fn new(cv:&ConfigurationValue, num_servers:usize) -> Result<EndToEndCredits,Error>
+	{
+		let mut window=None;
+		let mut feedback_delay=0;
+		match_object!(cv,"EndToEndCredits",value,
+			"window" => window=Some(value.as_usize()?),
+			"feedback_delay" => feedback_delay=value.as_time()?,
+		);
+		let window=window.ok_or_else(||cv.ill("There were no window"))?;
+		if window==0
+		{
+			return Err(cv.ill("EndToEndCredits requires a positive window."));
+		}
+		Ok(EndToEndCredits{
+			window,
+			feedback_delay,
+			in_flight: vec![0;num_servers],
+			feedback: FeedbackQueue::default(),
+			begin_cycle: 0,
+			consumed_packets: 0,
+			accumulated_in_flight: 0,
+		})
+	}
+}
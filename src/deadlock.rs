@@ -0,0 +1,311 @@
+/*!
+
+Detection and diagnosis of deadlocks in the whole network.
+
+When the configuration includes `deadlock_watchdog` the simulation checks whether the phits keep moving. If no phit has crossed any link during `cycles` cycles while there are phits inside the routers, the network is assumed to be deadlocked.
+Then a diagnosis is printed, the simulation ends, and the diagnosis is included in the results as `deadlock`.
+
+The diagnosis considers the phits at the head of the input buffers of the routers, as given by [Router::input_heads](crate::router::Router::input_heads).
+Each head waits for the output already allocated to its packet or, when none has been allocated, for the candidates given by the routing.
+A head is blocked when none of these outputs has space in the next router, as known by the credits.
+The diagnosis then searches a cycle of blocked buffers, each one waiting for the next, which is a credit cycle causing the deadlock.
+The virtual channel policies of the routers are not applied to the candidates, so a head may be reported waiting for some outputs the router would never grant.
+Conditions of the routers beyond the credits, such as the bubble, are not considered either, so some deadlocks may be reported without blocked buffers.
+
+```ignore
+deadlock_watchdog: DeadlockWatchdog{
+	//Cycles without any phit moving to declare a deadlock.
+	cycles: 10000,
+	//Maximum number of blocked buffers to print and include in the results. By default 20.
+	maximum_reported: 20,
+}
+```
+
+The diagnosis is an object `DeadlockDiagnosis{cycle, last_movement_cycle, phits_in_routers, blocked_buffers, credit_cycle, blocked}`,
+where `credit_cycle` is a list of `Buffer{router,port,virtual_channel}`, empty if no cycle has been found,
+and `blocked` a list of `BlockedBuffer{router, port, virtual_channel, origin, destination, target_router, packet_index, waiting_on}`,
+each entry of `waiting_on` being `Output{port, virtual_channel, next_router, next_port, available_space}`.
+
+*/
+
+use std::collections::BTreeMap;
+
+use ::rand::{SeedableRng,rngs::StdRng};
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::topology::Location;
+use crate::SimulationShared;
+
+///A buffer of a router, given by the router, its input port and virtual channel.
+type BufferIndex = (usize,usize,usize);
+
+///An output requested by the head of a blocked buffer.
+#[derive(Debug,Clone)]
+struct RequestedOutput
+{
+	port: usize,
+	virtual_channel: usize,
+	///The buffer of the next router in which the phits would be stored.
+	next: BufferIndex,
+	///The space known by the credits in the next buffer, if known.
+	available_space: Option<usize>,
+}
+
+///The head of an input buffer that cannot advance.
+#[derive(Debug,Clone)]
+struct BlockedBuffer
+{
+	buffer: BufferIndex,
+	origin: usize,
+	destination: usize,
+	target_router: usize,
+	packet_index: usize,
+	waiting_on: Vec<RequestedOutput>,
+}
+
+///Watches the movement of the phits to detect deadlocks. See the [module documentation](self).
+#[derive(Debug)]
+pub struct DeadlockWatchdog
+{
+	///Cycles without movement to declare a deadlock.
+	cycles: Time,
+	///Maximum number of blocked buffers reported.
+	maximum_reported: usize,
+	///The last cycle in which a phit has moved or the network has been found empty.
+	last_movement: Time,
+	///A generator for the calls to the routing, to not alter the one of the simulation.
+	rng: StdRng,
+	///The diagnosis, once a deadlock has been detected.
+	diagnosis: Option<ConfigurationValue>,
+}
+
+impl DeadlockWatchdog
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<DeadlockWatchdog,Error>
+	{
+		let mut cycles=None;
+		let mut maximum_reported=20;
+		match_object!(cv,"DeadlockWatchdog",value,
+			"cycles" => cycles=Some(value.as_time()?),
+			"maximum_reported" => maximum_reported=value.as_usize()?,
+		);
+		let cycles=cycles.ok_or_else(||cv.ill("There were no cycles"))?;
+		if cycles==0
+		{
+			return Err(cv.ill("The cycles of the DeadlockWatchdog must be positive"));
+		}
+		Ok(DeadlockWatchdog{
+			cycles,
+			maximum_reported,
+			last_movement: 0,
+			rng: StdRng::seed_from_u64(0),
+			diagnosis: None,
+		})
+	}
+	///Some phit has crossed a link in the `cycle`.
+	pub fn track_movement(&mut self, cycle:Time)
+	{
+		self.last_movement = cycle;
+	}
+	///Checks for a deadlock at the current cycle. When detected, prints its diagnosis and returns true.
+	pub fn check(&mut self, shared:&SimulationShared) -> bool
+	{
+		if self.diagnosis.is_some()
+		{
+			return true;
+		}
+		if shared.cycle < self.last_movement + self.cycles
+		{
+			return false;
+		}
+		let phits_in_routers : usize = shared.network.routers.iter().map(|router|router.borrow().iter_phits().count()).sum();
+		if phits_in_routers==0
+		{
+			//The network is just idle.
+			self.last_movement = shared.cycle;
+			return false;
+		}
+		let blocked = self.blocked_buffers(shared);
+		let credit_cycle = find_cycle(&blocked);
+		println!("DEADLOCK detected at cycle {}: no phit has moved since cycle {} with {} phits in the routers.",shared.cycle,self.last_movement,phits_in_routers);
+		if credit_cycle.is_empty()
+		{
+			println!("No credit cycle has been found among the {} blocked buffers.",blocked.len());
+		}
+		else
+		{
+			println!("Credit cycle: {}",credit_cycle.iter().map(|(router,port,vc)|format!("router {} port {} vc {}",router,port,vc)).collect::<Vec<String>>().join(" -> "));
+		}
+		for entry in blocked.iter().take(self.maximum_reported)
+		{
+			let (router,port,vc) = entry.buffer;
+			let outputs = entry.waiting_on.iter().map(|output|format!("port {} vc {} (space {})",output.port,output.virtual_channel,output.available_space.map(|space|space.to_string()).unwrap_or_else(||"unknown".to_string()))).collect::<Vec<String>>();
+			println!("  router {} port {} vc {} holds packet {} of message {}->{} waiting on [{}]",router,port,vc,entry.packet_index,entry.origin,entry.destination,outputs.join(", "));
+		}
+		let buffer_cv = |&(router,port,virtual_channel):&BufferIndex| ConfigurationValue::Object(String::from("Buffer"),vec![
+			(String::from("router"),ConfigurationValue::Number(router as f64)),
+			(String::from("port"),ConfigurationValue::Number(port as f64)),
+			(String::from("virtual_channel"),ConfigurationValue::Number(virtual_channel as f64)),
+		]);
+		let blocked_cv = blocked.iter().take(self.maximum_reported).map(|entry|{
+			let (router,port,virtual_channel) = entry.buffer;
+			let waiting_on = entry.waiting_on.iter().map(|output|ConfigurationValue::Object(String::from("Output"),vec![
+				(String::from("port"),ConfigurationValue::Number(output.port as f64)),
+				(String::from("virtual_channel"),ConfigurationValue::Number(output.virtual_channel as f64)),
+				(String::from("next_router"),ConfigurationValue::Number(output.next.0 as f64)),
+				(String::from("next_port"),ConfigurationValue::Number(output.next.1 as f64)),
+				(String::from("available_space"),output.available_space.map(|space|ConfigurationValue::Number(space as f64)).unwrap_or(ConfigurationValue::None)),
+			])).collect();
+			ConfigurationValue::Object(String::from("BlockedBuffer"),vec![
+				(String::from("router"),ConfigurationValue::Number(router as f64)),
+				(String::from("port"),ConfigurationValue::Number(port as f64)),
+				(String::from("virtual_channel"),ConfigurationValue::Number(virtual_channel as f64)),
+				(String::from("origin"),ConfigurationValue::Number(entry.origin as f64)),
+				(String::from("destination"),ConfigurationValue::Number(entry.destination as f64)),
+				(String::from("target_router"),ConfigurationValue::Number(entry.target_router as f64)),
+				(String::from("packet_index"),ConfigurationValue::Number(entry.packet_index as f64)),
+				(String::from("waiting_on"),ConfigurationValue::Array(waiting_on)),
+			])
+		}).collect();
+		self.diagnosis = Some(ConfigurationValue::Object(String::from("DeadlockDiagnosis"),vec![
+			(String::from("cycle"),ConfigurationValue::Number(shared.cycle as f64)),
+			(String::from("last_movement_cycle"),ConfigurationValue::Number(self.last_movement as f64)),
+			(String::from("phits_in_routers"),ConfigurationValue::Number(phits_in_routers as f64)),
+			(String::from("blocked_buffers"),ConfigurationValue::Number(blocked.len() as f64)),
+			(String::from("credit_cycle"),ConfigurationValue::Array(credit_cycle.iter().map(buffer_cv).collect())),
+			(String::from("blocked"),ConfigurationValue::Array(blocked_cv)),
+		]));
+		true
+	}
+	///The diagnosis, if a deadlock has been detected.
+	pub fn result(&self) -> Option<ConfigurationValue>
+	{
+		self.diagnosis.clone()
+	}
+	///The heads of the input buffers that cannot advance, with the outputs they are waiting for.
+	fn blocked_buffers(&mut self, shared:&SimulationShared) -> Vec<BlockedBuffer>
+	{
+		let topology = shared.network.topology.as_ref();
+		let mut blocked = vec![];
+		for (router_index,router) in shared.network.routers.iter().enumerate()
+		{
+			let router = router.borrow();
+			let heads = match router.input_heads()
+			{
+				Some(heads) => heads,
+				None => continue,
+			};
+			for head in heads
+			{
+				let packet = &head.phit.packet;
+				let destination = packet.message.destination;
+				let target_router = match topology.server_neighbour(destination).0
+				{
+					Location::RouterPort{router_index,..} => router_index,
+					_ => panic!("The server is not attached to a router"),
+				};
+				let requested : Vec<(usize,usize)> = match head.selected_output
+				{
+					Some(selected) => vec![selected],
+					None => match shared.routing.next(&packet.routing_info.borrow(),topology,router_index,target_router,Some(destination),router.num_virtual_channels(),&mut self.rng)
+					{
+						Ok(candidates) => candidates.candidates.iter().map(|candidate|(candidate.port,candidate.virtual_channel)).collect(),
+						Err(_) => vec![],
+					},
+				};
+				let needed = if head.phit.is_begin() { packet.size } else { 1 };
+				let mut waiting_on = vec![];
+				let mut can_advance = false;
+				for (port,virtual_channel) in requested
+				{
+					match topology.neighbour(router_index,port).0
+					{
+						Location::RouterPort{router_index:next_router,router_port:next_port} =>
+						{
+							let available_space = router.get_status_at_emisor(port).and_then(|status|status.known_available_space_for_virtual_channel(virtual_channel));
+							if available_space.map_or(true,|space|space>=needed)
+							{
+								can_advance = true;
+							}
+							waiting_on.push(RequestedOutput{
+								port,
+								virtual_channel,
+								next: (next_router,next_port,virtual_channel),
+								available_space,
+							});
+						},
+						//The servers always consume.
+						_ => can_advance = true,
+					}
+				}
+				if !can_advance
+				{
+					blocked.push(BlockedBuffer{
+						buffer: (router_index,head.port,head.virtual_channel),
+						origin: packet.message.origin,
+						destination,
+						target_router,
+						packet_index: packet.index,
+						waiting_on,
+					});
+				}
+			}
+		}
+		blocked
+	}
+}
+
+///Finds a cycle of blocked buffers, each one waiting on the next. Returns an empty list if there is none.
+fn find_cycle(blocked:&[BlockedBuffer]) -> Vec<BufferIndex>
+{
+	let edges : BTreeMap<BufferIndex,Vec<BufferIndex>> = blocked.iter().map(|entry|(entry.buffer,entry.waiting_on.iter().map(|output|output.next).collect())).collect();
+	//1 for the buffers in the current path and 2 for the finished ones. The unvisited are absent.
+	let mut state : BTreeMap<BufferIndex,u8> = BTreeMap::new();
+	for &start in edges.keys()
+	{
+		if state.contains_key(&start)
+		{
+			continue;
+		}
+		//Depth first search keeping the path and the next edge to explore of each buffer in it.
+		let mut path : Vec<(BufferIndex,usize)> = vec![(start,0)];
+		state.insert(start,1);
+		while let Some(&mut (buffer,ref mut next_edge)) = path.last_mut()
+		{
+			let successors = &edges[&buffer];
+			if *next_edge < successors.len()
+			{
+				let successor = successors[*next_edge];
+				*next_edge += 1;
+				if !edges.contains_key(&successor)
+				{
+					//The next buffer is not blocked, so it will eventually drain.
+					continue;
+				}
+				match state.get(&successor)
+				{
+					Some(1) =>
+					{
+						let position = path.iter().position(|(b,_)|*b==successor).expect("the buffer should be in the path");
+						return path[position..].iter().map(|(b,_)|*b).collect();
+					},
+					Some(_) => (),
+					None =>
+					{
+						state.insert(successor,1);
+						path.push((successor,0));
+					},
+				}
+			}
+			else
+			{
+				state.insert(buffer,2);
+				path.pop();
+			}
+		}
+	}
+	vec![]
+}
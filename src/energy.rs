@@ -0,0 +1,195 @@
+/*!
+
+Estimation of the energy consumed by the network.
+
+When the configuration includes `energy_model` the simulation accounts the energy consumed by the links and the routers during the measured period.
+The links are grouped by their link class, so that, for example, the global links of a dragonfly may be given a higher energy per phit than the local ones.
+Each direction of a connection counts as a link, including the links between the servers and the routers.
+
+```ignore
+energy_model: EnergyModel{
+	//Energy of sending a phit through a link of each link class, in any unit such as picojoules.
+	phit_energy: [1.0, 3.0, 0.5],
+	//Power of each link of each class, in energy per cycle, consumed even when the link is idle.
+	link_static_power: [0.01, 0.05, 0.005],
+	//Power of each router in the cycles in which it receives no phit.
+	router_idle_power: 1.0,
+	//Power of each router in the cycles in which it receives some phit.
+	router_active_power: 2.5,
+	//Energy of buffering and switching each phit entering a router.
+	router_phit_energy: 0.2,
+}
+```
+A single number may be given instead of an array to use the same value for every link class. The omitted fields are taken as 0.
+
+The results include `energy: Energy{cycles, total, average_power, links_dynamic, links_static, routers_static, routers_dynamic, link_classes, routers}`,
+where `link_classes` has an entry `LinkClassEnergy{class, links, phits, dynamic, static}` for each link class and `routers` is the array with the energy consumed by each router.
+The `average_power` is the `total` divided by the measured `cycles`.
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::topology::{Location,Topology};
+
+///The energy parameters of the network and the activity measured to apply them.
+#[derive(Debug)]
+pub struct EnergyModel
+{
+	///The energy of a phit crossing a link of each class.
+	phit_energy: Vec<f64>,
+	///The power of a link of each class.
+	link_static_power: Vec<f64>,
+	router_idle_power: f64,
+	router_active_power: f64,
+	router_phit_energy: f64,
+	///The number of links of each class.
+	links: Vec<usize>,
+	///The phits that crossed the links of each class.
+	link_phits: Vec<usize>,
+	///The phits that entered each router.
+	router_phits: Vec<usize>,
+	///The cycles in which each router has received some phit.
+	router_active_cycles: Vec<Time>,
+	///The last cycle in which each router received some phit.
+	router_last_active: Vec<Option<Time>>,
+	///The cycle in which the measures begin.
+	begin_cycle: Time,
+}
+
+impl EnergyModel
+{
+	pub fn new(cv:&ConfigurationValue, topology:&dyn Topology) -> Result<EnergyModel,Error>
+	{
+		let mut links = vec![];
+		let mut count_link = |link_class:usize| {
+			if links.len()<=link_class
+			{
+				links.resize(link_class+1,0);
+			}
+			links[link_class]+=1;
+		};
+		for router in 0..topology.num_routers()
+		{
+			for port in 0..topology.ports(router)
+			{
+				match topology.neighbour(router,port)
+				{
+					(Location::None,_) => (),
+					(_,link_class) => count_link(link_class),
+				}
+			}
+		}
+		for server in 0..topology.num_servers()
+		{
+			count_link(topology.server_neighbour(server).1);
+		}
+		let num_classes = links.len();
+		let per_class = |value:&ConfigurationValue, name:&str| -> Result<Vec<f64>,Error> {
+			match value
+			{
+				ConfigurationValue::Number(x) => Ok(vec![*x;num_classes]),
+				ConfigurationValue::Array(values) =>
+				{
+					if values.len()<num_classes
+					{
+						return Err(value.ill(&format!("{} has {} values but there are {} link classes",name,values.len(),num_classes)));
+					}
+					values.iter().map(|v|v.as_f64()).collect()
+				},
+				_ => Err(value.ill(&format!("bad value for {}",name))),
+			}
+		};
+		let mut phit_energy = vec![0.0;num_classes];
+		let mut link_static_power = vec![0.0;num_classes];
+		let mut router_idle_power = 0.0;
+		let mut router_active_power = 0.0;
+		let mut router_phit_energy = 0.0;
+		match_object!(cv,"EnergyModel",value,
+			"phit_energy" => phit_energy=per_class(value,"phit_energy")?,
+			"link_static_power" => link_static_power=per_class(value,"link_static_power")?,
+			"router_idle_power" => router_idle_power=value.as_f64()?,
+			"router_active_power" => router_active_power=value.as_f64()?,
+			"router_phit_energy" => router_phit_energy=value.as_f64()?,
+		);
+		let num_routers = topology.num_routers();
+		Ok(EnergyModel{
+			phit_energy,
+			link_static_power,
+			router_idle_power,
+			router_active_power,
+			router_phit_energy,
+			links,
+			link_phits: vec![0;num_classes],
+			router_phits: vec![0;num_routers],
+			router_active_cycles: vec![0;num_routers],
+			router_last_active: vec![None;num_routers],
+			begin_cycle: 0,
+		})
+	}
+	///A phit has crossed a link of class `link_class` into the port of a `router`.
+	pub fn track_router_phit(&mut self, router:usize, link_class:usize, cycle:Time)
+	{
+		self.link_phits[link_class]+=1;
+		self.router_phits[router]+=1;
+		if self.router_last_active[router]!=Some(cycle)
+		{
+			self.router_last_active[router]=Some(cycle);
+			self.router_active_cycles[router]+=1;
+		}
+	}
+	///A phit has crossed a link of class `link_class` into a server.
+	pub fn track_server_phit(&mut self, link_class:usize)
+	{
+		self.link_phits[link_class]+=1;
+	}
+	///Clears the measures, to begin at `next_cycle`.
+	pub fn reset(&mut self, next_cycle:Time)
+	{
+		self.begin_cycle = next_cycle;
+		self.link_phits.iter_mut().for_each(|phits|*phits=0);
+		self.router_phits.iter_mut().for_each(|phits|*phits=0);
+		self.router_active_cycles.iter_mut().for_each(|cycles|*cycles=0);
+		self.router_last_active.iter_mut().for_each(|last|*last=None);
+	}
+	pub fn result(&self, cycle:Time) -> ConfigurationValue
+	{
+		let cycles = cycle.saturating_sub(self.begin_cycle);
+		let link_classes:Vec<(f64,f64)> = (0..self.links.len()).map(|class|(
+			self.link_phits[class] as f64*self.phit_energy[class],
+			(self.links[class] as Time*cycles) as f64*self.link_static_power[class],
+		)).collect();
+		let routers:Vec<(f64,f64)> = (0..self.router_phits.len()).map(|router|{
+			let active = self.router_active_cycles[router];
+			let idle = cycles.saturating_sub(active);
+			(
+				idle as f64*self.router_idle_power + active as f64*self.router_active_power,
+				self.router_phits[router] as f64*self.router_phit_energy,
+			)
+		}).collect();
+		let links_dynamic:f64 = link_classes.iter().map(|(dynamic,_)|dynamic).sum();
+		let links_static:f64 = link_classes.iter().map(|(_,link_static)|link_static).sum();
+		let routers_static:f64 = routers.iter().map(|(router_static,_)|router_static).sum();
+		let routers_dynamic:f64 = routers.iter().map(|(_,dynamic)|dynamic).sum();
+		let total = links_dynamic + links_static + routers_static + routers_dynamic;
+		ConfigurationValue::Object(String::from("Energy"),vec![
+			(String::from("cycles"),ConfigurationValue::Number(cycles as f64)),
+			(String::from("total"),ConfigurationValue::Number(total)),
+			(String::from("average_power"),ConfigurationValue::Number(if cycles==0 { 0.0 } else { total/cycles as f64 })),
+			(String::from("links_dynamic"),ConfigurationValue::Number(links_dynamic)),
+			(String::from("links_static"),ConfigurationValue::Number(links_static)),
+			(String::from("routers_static"),ConfigurationValue::Number(routers_static)),
+			(String::from("routers_dynamic"),ConfigurationValue::Number(routers_dynamic)),
+			(String::from("link_classes"),ConfigurationValue::Array(link_classes.iter().enumerate().map(|(class,(dynamic,link_static))|ConfigurationValue::Object(String::from("LinkClassEnergy"),vec![
+				(String::from("class"),ConfigurationValue::Number(class as f64)),
+				(String::from("links"),ConfigurationValue::Number(self.links[class] as f64)),
+				(String::from("phits"),ConfigurationValue::Number(self.link_phits[class] as f64)),
+				(String::from("dynamic"),ConfigurationValue::Number(*dynamic)),
+				(String::from("static"),ConfigurationValue::Number(*link_static)),
+			])).collect())),
+			(String::from("routers"),ConfigurationValue::Array(routers.iter().map(|(router_static,dynamic)|ConfigurationValue::Number(router_static+dynamic)).collect())),
+		])
+	}
+}
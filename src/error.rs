@@ -98,6 +98,9 @@ pub enum ErrorKind
 	IncompatibleConfigurations,
 	/// Some method received a bad argument. There should be an attached message with further explanation.
 	BadArgument,
+	/// The components of the simulation cannot work together as configured, such as a routing requiring more virtual channels than the routers have.
+	/// There should be an attached message with further explanation.
+	UnsupportedConfiguration,
 	/// Any other error. Better to add new types than to use this thing.
 	Undetermined,
 }
@@ -285,6 +288,14 @@ impl Error
 			message:None,
 		}
 	}
+	pub fn unsupported_configuration(source_location:SourceLocation)->Error
+	{
+		Error{
+			source_location,
+			kind: UnsupportedConfiguration,
+			message:None,
+		}
+	}
 	pub fn undetermined(source_location:SourceLocation)->Error
 	{
 		Error{
@@ -373,6 +384,10 @@ impl Display for ErrorKind
 			{
 				writeln!(formatter,"BadArgument: Bad arguments given to a function.")?;
 			},
+			UnsupportedConfiguration =>
+			{
+				writeln!(formatter,"UnsupportedConfiguration: The components of the simulation cannot work together as configured.")?;
+			},
 			Undetermined =>
 			{
 				writeln!(formatter,"Undetermined error: A generic error. The concrete error should be more specified.")?;
@@ -12,7 +12,7 @@ use std::io::{stdout,BufReader};
 use std::path::{Path,PathBuf};
 use std::process::Command;
 use std::net::TcpStream;
-use std::collections::{HashSet};
+use std::collections::{HashSet,BTreeMap};
 
 use ssh2::Session;
 use indicatif::{ProgressBar,ProgressStyle};
@@ -20,8 +20,9 @@ use indicatif::{ProgressBar,ProgressStyle};
 use crate::config_parser::{self,ConfigurationValue};
 use crate::{Simulation,Plugs,source_location,error,match_object_panic};
 use crate::output::{create_output,OutputEnvironment,OutputEnvironmentEntry};
-use crate::config::{self,evaluate,flatten_configuration_value};
+use crate::config::{self,evaluate,flatten_configuration_value,expand_repetitions,expand_seeds};
 use crate::error::{Error,ErrorKind,SourceLocation};
+use crate::progress::{PROGRESS_FILE_VARIABLE,read_progress_percent};
 
 #[derive(Debug,Clone,Copy,PartialEq)]
 pub enum Action
@@ -52,6 +53,8 @@ pub enum Action
 	Discard,
 	///Executes a few cycles of each simulation, to detect possible runtime failures.
 	QuickTest,
+	///Builds the simulation of each experiment without running it and reports all the configuration errors found, without creating any file.
+	Validate,
 }
 
 impl FromStr for Action
@@ -75,6 +78,7 @@ impl FromStr for Action
 			"pack" => Ok(Action::Pack),
 			"discard" => Ok(Action::Discard),
 			"quick_test" => Ok(Action::QuickTest),
+			"validate" => Ok(Action::Validate),
 			_ => Err(error!(bad_argument).with_message(format!("String {s} cannot be parsed as an Action."))),
 		}
 	}
@@ -343,7 +347,8 @@ impl Job
 
 	fn add_execution(&mut self, execution_id: usize, binary:&Path, execution_path_str: &str)
 	{
-		let job_line=format!("echo execution {}\n/bin/date\n{} {}/local.cfg --results={}/local.result",execution_id,binary.display(),execution_path_str,execution_path_str);
+		//The ProgressFile reporter without path writes into local.progress, for the check action.
+		let job_line=format!("echo execution {}\n/bin/date\n{}={}/local.progress {} {}/local.cfg --results={}/local.result",execution_id,PROGRESS_FILE_VARIABLE,execution_path_str,binary.display(),execution_path_str,execution_path_str);
 		self.execution_code_vec.push(job_line);
 		self.execution_id_vec.push(execution_id);
 	}
@@ -475,6 +480,7 @@ pub struct Experiment<'a>
 /// * launch in the future, instead of being inside main.cfg
 /// * runs/{run#,job#}
 /// * binary.results
+/// * results.cache, with the parsed local.result files, to be used by the Output action.
 pub struct ExperimentFiles
 {
 	///The host with the path of these files.
@@ -658,7 +664,7 @@ impl ExperimentFiles
 				let flat=flatten_configuration_value(value);
 				if let ConfigurationValue::Experiments(experiments)=flat
 				{
-					experiments
+					expand_seeds(expand_repetitions(experiments)?)?
 				}
 				else
 				{
@@ -891,6 +897,144 @@ impl Debug for ExperimentFiles
 	}
 }
 
+/**
+The parsed `local.result` files of an experiment, stored in `results.cache` at its root.
+Each entry records the modification time and size of the file when it was parsed, so that the Output action only parses again the new or modified results.
+**/
+#[derive(Default)]
+struct ResultsCache
+{
+	///For each experiment index, the stamp of the parsed file as in [ResultsCache::stamp] and the parsed result.
+	entries: BTreeMap<usize,((u64,u32,u64),ConfigurationValue)>,
+	///Whether some entry has been added or replaced since loaded.
+	modified: bool,
+}
+
+impl ResultsCache
+{
+	///Loads the cache from `path`. A missing or unreadable cache is just empty.
+	fn load(path:&Path) -> ResultsCache
+	{
+		let mut cache = ResultsCache::default();
+		let contents = match fs::read(path)
+		{
+			Ok(contents) => contents,
+			Err(_) => return cache,
+		};
+		let entries = match config::config_from_binary(&contents,0)
+		{
+			Ok(ConfigurationValue::Array(entries)) => entries,
+			_ =>
+			{
+				println!("WARNING: could not read the results cache {}. It will be rebuilt.",path.display());
+				return cache;
+			}
+		};
+		for entry in entries
+		{
+			let mut index = None;
+			let mut stamp = (0,0,0);
+			let mut result = None;
+			match_object_panic!(&entry,"CachedResult",value,
+				"index" => index = Some(value.as_usize().expect("bad value for index")),
+				"seconds" => stamp.0 = value.as_f64().expect("bad value for seconds") as u64,
+				"nanoseconds" => stamp.1 = value.as_f64().expect("bad value for nanoseconds") as u32,
+				"size" => stamp.2 = value.as_f64().expect("bad value for size") as u64,
+				"result" => result = Some(value.clone()),
+			);
+			if let (Some(index),Some(result)) = (index,result)
+			{
+				cache.entries.insert(index,(stamp,result));
+			}
+		}
+		cache
+	}
+	///Writes the cache into `path` if it has been modified.
+	fn save(&self, path:&Path) -> Result<(),Error>
+	{
+		if !self.modified
+		{
+			return Ok(());
+		}
+		let entries = self.entries.iter().map(|(&index,&((seconds,nanoseconds,size),ref result))|ConfigurationValue::Object("CachedResult".to_string(),vec![
+			("index".to_string(),ConfigurationValue::Number(index as f64)),
+			("seconds".to_string(),ConfigurationValue::Number(seconds as f64)),
+			("nanoseconds".to_string(),ConfigurationValue::Number(nanoseconds as f64)),
+			("size".to_string(),ConfigurationValue::Number(size as f64)),
+			("result".to_string(),result.clone()),
+		])).collect();
+		let binary = config::config_to_binary(&ConfigurationValue::Array(entries)).map_err(|e|error!(could_not_generate_file,path.to_path_buf(),e))?;
+		fs::write(path,binary).map_err(|e|error!(could_not_generate_file,path.to_path_buf(),e))?;
+		Ok(())
+	}
+	///The modification time, as seconds and nanoseconds since the epoch, and the size of a file.
+	fn stamp(path:&Path) -> Option<(u64,u32,u64)>
+	{
+		let metadata = fs::metadata(path).ok()?;
+		let modified = metadata.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+		Some((modified.as_secs(),modified.subsec_nanos(),metadata.len()))
+	}
+	///The result of the experiment `index` stored at `result_path`, from the cache if the file has not changed since parsed.
+	///It is returned together with whether the file has been parsed now, because it was new or modified.
+	///Returns `None` when the file is missing or cannot be parsed.
+	fn get(&mut self, index:usize, result_path:&Path) -> Option<(ConfigurationValue,bool)>
+	{
+		let stamp = ResultsCache::stamp(result_path)?;
+		if let Some((cached_stamp,result)) = self.entries.get(&index)
+		{
+			if *cached_stamp==stamp
+			{
+				return Some((result.clone(),false));
+			}
+		}
+		let contents = fs::read_to_string(result_path).ok()?;
+		match config::parse_result(&contents)
+		{
+			Ok(result) =>
+			{
+				self.entries.insert(index,(stamp,result.clone()));
+				self.modified = true;
+				Some((result,true))
+			},
+			_ => None,
+		}
+	}
+}
+
+/**
+Evaluates the `assertions` in the configuration of an experiment against its result.
+Each assertion is an expression evaluated in the context `Context{index,configuration,result}`, as in the output definitions, and it must evaluate to `true`.
+Returns the assertions not being satisfied, including those that cannot be evaluated or do not evaluate into a Boolean.
+**/
+pub fn failed_assertions(experiment_index:usize, experiment:&ConfigurationValue, result:&ConfigurationValue, path:&Path) -> Vec<String>
+{
+	let assertions = match experiment
+	{
+		ConfigurationValue::Object(_,ref fields) => match fields.iter().find(|(name,_)|name=="assertions")
+		{
+			Some((_,ConfigurationValue::Array(ref list))) => list,
+			Some((_,other)) => return vec![format!("assertions is not an array: {}",other)],
+			None => return vec![],
+		},
+		_ => return vec![],
+	};
+	let context = config::combine(experiment_index,experiment,result);
+	assertions.iter().filter_map(|assertion|{
+		let value = match assertion
+		{
+			ConfigurationValue::Expression(ref expr) => config::evaluate(expr,&context,path),
+			other => Ok(other.clone()),
+		};
+		match value
+		{
+			Ok(ConfigurationValue::True) => None,
+			Ok(ConfigurationValue::False) => Some(format!("{}",assertion)),
+			Ok(other) => Some(format!("{} (evaluated to {})",assertion,other)),
+			Err(e) => Some(format!("{} (could not be evaluated: {})",assertion,e)),
+		}
+	}).collect()
+}
+
 impl<'a> Experiment<'a>
 {
 	///Creates a new experiment object.
@@ -1001,6 +1145,57 @@ impl<'a> Experiment<'a>
 		let mut journal_file=OpenOptions::new().append(true).open(&self.journal).expect("Something went wrong reading or creating the journal file");
 		writeln!(journal_file,"{}: {}",self.journal_index,entry).expect("Could not write to journal");
 	}
+	/**
+	Builds the simulation of each selected experiment with [Simulation::try_new] and advances it a single cycle.
+	All the experiments are checked, and the failures with the same message are reported together.
+	Returns an error if any experiment failed.
+	**/
+	fn validate(&self) -> Result<(),Error>
+	{
+		let start_index = self.options.start_index.unwrap_or(0);
+		let end_index = self.options.end_index.unwrap_or(self.files.experiments.len()).min(self.files.experiments.len());
+		let root = self.files.root.as_ref().unwrap();
+		//The experiments failing with each message.
+		let mut failures : BTreeMap<String,Vec<usize>> = BTreeMap::new();
+		let mut checked = 0;
+		for (experiment_index,experiment) in self.files.experiments.iter().enumerate().skip(start_index).take(end_index.saturating_sub(start_index))
+		{
+			if let Some(ref expr) = self.options.where_clause
+			{
+				match evaluate(expr,experiment,root)
+				{
+					Ok(ConfigurationValue::True) => (),
+					Ok(ConfigurationValue::False) => continue,
+					_ => panic!("The where clause does not evaluate to a bool for experiment {}",experiment_index),
+				}
+			}
+			checked+=1;
+			match Simulation::try_new(experiment,self.plugs)
+			{
+				Ok(mut simulation) => simulation.advance(),
+				Err(error) =>
+				{
+					//Skip the first line, with the source location, to group the experiments by the message.
+					let text = format!("{}",error);
+					let message = text.lines().skip(1).collect::<Vec<_>>().join("\n");
+					failures.entry(message).or_default().push(experiment_index);
+				},
+			}
+		}
+		let failed : usize = failures.values().map(|indices|indices.len()).sum();
+		for (message,indices) in failures.iter()
+		{
+			println!("{} experiments fail with: {}\n\texperiments {:?}",indices.len(),message,indices);
+		}
+		let summary = format!("Validate checked {} experiments, {} failed.",checked,failed);
+		self.write_journal_entry(&summary);
+		println!("{}",summary);
+		if failed>0
+		{
+			return Err(error!(undetermined).with_message(format!("{} of {} experiments have a bad configuration",failed,checked)));
+		}
+		Ok(())
+	}
 	/// Executes an action over the experiment.
 	pub fn execute_action(&mut self,action:Action) -> Result<(),Error>
 	{
@@ -1069,6 +1264,10 @@ impl<'a> Experiment<'a>
 		let mut results;
 		//self.files.build_experiments()?;
 		self.files.build_experiments().or_else(|e|if self.options.foreign {Ok(())} else {Err(e)})?;
+		if let Action::Validate = action
+		{
+			return self.validate();
+		}
 
 		let external_files = if let (Some(path),true) = (self.options.external_source.as_ref(), action!=Action::Shell  ) {
 			let mut ef = ExperimentFiles{
@@ -1305,6 +1504,7 @@ impl<'a> Experiment<'a>
 			Action::Pack => (),
 			Action::Discard => (),
 			Action::QuickTest => (),
+			Action::Validate => (),
 		};
 
 		//Remove mutabiity to prevent mistakes.
@@ -1339,6 +1539,9 @@ impl<'a> Experiment<'a>
 		let mut delta_completed=0;
 		let sftp = self.remote_files.as_ref().map(|f|f.ssh2_session.as_ref().unwrap().sftp().unwrap());
 		let mut progress = ActionProgress::new(&action,end_index-start_index);
+		let mut experiments_failing_assertions = vec![];
+		//The percentage done of each experiment in each slurm job, for the check action.
+		let mut slurm_job_progress : BTreeMap<usize,Vec<f64>> = BTreeMap::new();
 		for (experiment_index,experiment) in self.files.experiments.iter().enumerate().skip(start_index).take(end_index-start_index)
 		{
 			progress.inc(1);
@@ -1431,15 +1634,10 @@ impl<'a> Experiment<'a>
 									{
 										if let Some(ref contents) = ext_result_contents
 										{
-											match config_parser::parse(contents)
+											match config::parse_result(contents)
 											{
-												Ok(cv) =>
+												Ok(result) =>
 												{
-													let result=match cv
-													{
-														config_parser::Token::Value(value) => value,
-														_ => panic!("wrong token"),
-													};
 													ext_result_value = Some(result);
 												}
 												Err(_error)=>
@@ -1483,16 +1681,9 @@ impl<'a> Experiment<'a>
 				};
 				let mut result_contents=String::new();
 				result_file.read_to_string(&mut result_contents).expect("something went wrong reading the result file.");
-				let result = match config_parser::parse(&result_contents)
+				let result = match config::parse_result(&result_contents)
 				{
-					Ok(cv) =>
-					{
-						match cv
-						{
-							config_parser::Token::Value(value) => value,
-							_ => panic!("wrong token"),
-						}
-					}
+					Ok(value) => value,
 					Err(_error)=>
 					{
 						eprintln!("There are missing results (experiment {}).",experiment_index);
@@ -1525,6 +1716,36 @@ impl<'a> Experiment<'a>
 			{
 				progress.before_amount_completed+=1;
 				//progress_bar.set_message(&format!("{} pulled, {} empty, {} missing, {} already, {} merged {} errors",pulled,empty,missing,before_amount_completed,merged,errors));
+				if let Action::Check = action
+				{
+					if let Some(&Some( (_,_,slurm_id) )) = self.experiment_to_slurm.get(experiment_index)
+					{
+						slurm_job_progress.entry(slurm_id).or_default().push(100.0);
+					}
+					//A local.result is preferred, as it may be newer than the packed one.
+					let file_result = if has_content {
+						fs::read_to_string(&result_path).ok().and_then(|contents|config::parse_result(&contents).ok())
+					} else { None };
+					let result = file_result.or_else(||match self.files.packed_results
+					{
+						ConfigurationValue::Experiments(ref a) => match a[experiment_index]
+						{
+							ConfigurationValue::None => None,
+							ref result => Some(result.clone()),
+						},
+						_ => None,
+					});
+					if let Some(result) = result
+					{
+						let failed = failed_assertions(experiment_index,experiment,&result,self.files.root.as_ref().unwrap());
+						if !failed.is_empty()
+						{
+							println!("Experiment {} failed its assertions: {}",experiment_index,failed.join(", "));
+							progress.failed_assertions+=1;
+							experiments_failing_assertions.push(experiment_index);
+						}
+					}
+				}
 				if let Action::Discard = action
 				{
 					let silent = ! self.options.interactive.unwrap_or(true);
@@ -1564,9 +1785,20 @@ impl<'a> Experiment<'a>
 					Action::Local | Action::LocalAndOutput =>
 					{
 						println!("experiment {} of {} is {}",experiment_index,self.files.experiments.len(),experiment.format_terminal());
-						let mut simulation=Simulation::new(experiment,self.plugs);
+						let mut simulation=Simulation::try_new_in_directory(experiment,self.plugs,&experiment_path).map_err(|e|e.with_message(format!("\nThe experiment {} could not be built.",experiment_index)))?;
 						simulation.run();
 						simulation.write_result(&mut File::create(&result_path).expect("Could not create the result file."));
+						let usage = simulation.resource_usage();
+						let cpu_time = usage.cpu_time.map(|t|format!("{:.2}",t)).unwrap_or_else(||"unknown".to_string());
+						let peak = usage.high_water_mark.map(|m|format!("{} KiB",m)).unwrap_or_else(||"unknown".to_string());
+						self.write_journal_entry(&format!("Experiment {} took {:.2} seconds of wall time, {} seconds of CPU time, peak memory of process {}.",experiment_index,usage.wall_time,cpu_time,peak));
+						let failed = failed_assertions(experiment_index,experiment,&simulation.get_simulation_results(),self.files.root.as_ref().unwrap());
+						if !failed.is_empty()
+						{
+							println!("Experiment {} failed its assertions: {}",experiment_index,failed.join(", "));
+							self.write_journal_entry(&format!("Experiment {} failed assertions: {}",experiment_index,failed.join(", ")));
+							progress.failed_assertions+=1;
+						}
 					},
 					Action::Slurm => if !self.experiments_on_slurm.contains(&experiment_index)
 					{
@@ -1647,15 +1879,10 @@ impl<'a> Experiment<'a>
 										progress.empty+=1;
 										(None,Some(remote_result_contents))
 									} else {
-										match config_parser::parse(&remote_result_contents)
+										match config::parse_result(&remote_result_contents)
 										{
-											Ok(cv) =>
+											Ok(result) =>
 											{
-												let result=match cv
-												{
-													config_parser::Token::Value(value) => value,
-													_ => panic!("wrong token"),
-												};
 												(Some(result),Some(remote_result_contents))
 											}
 											Err(_error)=>
@@ -1699,6 +1926,11 @@ impl<'a> Experiment<'a>
 						{
 							if let Some( (journal_entry,batch,slurm_id) ) = self.experiment_to_slurm[experiment_index]
 							{
+								if self.experiments_on_slurm.contains(&experiment_index)
+								{
+									let percent = fs::read_to_string(experiment_path.join("local.progress")).ok().and_then(|contents|read_progress_percent(&contents)).unwrap_or(0.0);
+									slurm_job_progress.entry(slurm_id).or_default().push(percent);
+								}
 								let slurm_stderr_path = runs_path.join(format!("jobs{}/launch{}-{}.err",journal_entry,batch,slurm_id));
 								let mut stderr_contents = String::new();
 								//let mut stderr_file=File::open(&slurm_stderr_path).unwrap_or_else(|_|panic!("{:?} could not be opened",slurm_stderr_path));
@@ -1719,14 +1951,14 @@ impl<'a> Experiment<'a>
 					Action::QuickTest =>
 					{
 						//println!("experiment {} of {} is {}",experiment_index,self.files.experiments.len(),experiment.format_terminal());
-						let mut simulation=Simulation::new(experiment,self.plugs);
+						let mut simulation=Simulation::try_new(experiment,self.plugs).map_err(|e|e.with_message(format!("\nThe experiment {} could not be built.",experiment_index)))?;
 						//simulation.run();
 						//simulation.write_result(&mut File::create(&result_path).expect("Could not create the result file."));
 						for _ in 0..20 {
 							simulation.advance();
 						}
 					},
-					Action::Output | Action::RemoteCheck | Action::Push | Action::SlurmCancel | Action::Shell | Action::Pack | Action::Discard =>
+					Action::Output | Action::RemoteCheck | Action::Push | Action::SlurmCancel | Action::Shell | Action::Pack | Action::Discard | Action::Validate =>
 					{
 					},
 				};
@@ -1754,6 +1986,21 @@ impl<'a> Experiment<'a>
 		{
 			self.write_journal_entry(&format!("Launched jobs {}",launch_entry));
 		}
+		for (slurm_id,percents) in slurm_job_progress.iter()
+		{
+			//Only the jobs with some experiment still on slurm.
+			if percents.iter().any(|&percent|percent<100.0)
+			{
+				let average = percents.iter().sum::<f64>()/percents.len() as f64;
+				println!("Slurm job {} is {:.1}% complete ({} of {} experiments done)",slurm_id,average,percents.iter().filter(|&&percent|percent>=100.0).count(),percents.len());
+			}
+		}
+		if !experiments_failing_assertions.is_empty()
+		{
+			let summary = format!("Check found {} experiments failing their assertions: {:?}",experiments_failing_assertions.len(),experiments_failing_assertions);
+			self.write_journal_entry(&summary);
+			println!("{}",summary);
+		}
 
 		let status_string = format!("Before: completed={} of {} slurm={} inactive={} active={} Changed: slurm=+{} completed=+{}",progress.before_amount_completed,self.files.experiments.len(),before_amount_slurm,before_amount_inactive,before_amount_active,delta_amount_slurm,delta_completed);
 		self.write_journal_entry(&status_string);
@@ -1763,9 +2010,43 @@ impl<'a> Experiment<'a>
 		if must_draw
 		{
 			results=Vec::with_capacity(self.files.experiments.len());
+			let results_cache_path = self.files.root.as_ref().unwrap().join("results.cache");
+			let mut results_cache = ResultsCache::load(&results_cache_path);
 			//for (experiment_index,experiment) in experiments.iter().enumerate()
 			for (experiment_index,experiment) in self.files.experiments.iter().enumerate().skip(start_index).take(end_index-start_index)
 			{
+				//A local.result is preferred over the packed one, as it may come from a later run of the experiment.
+				//Through the cache it is only parsed when modified, and only then it replaces the packed result.
+				let experiment_path=runs_path.join(format!("run{}",experiment_index));
+				let result_path=experiment_path.join("local.result");
+				if result_path.is_file()
+				{
+					match results_cache.get(experiment_index,&result_path)
+					{
+						Some((result,parsed_now)) =>
+						{
+							if let ConfigurationValue::Experiments(ref mut a) = self.files.packed_results
+							{
+								if parsed_now || a[experiment_index]==ConfigurationValue::None
+								{
+									a[experiment_index] = result.clone();
+									added_packed_results+=1;
+								}
+							}
+							//results.push((experiment_index,experiment.clone(),result));
+							results.push(
+								OutputEnvironmentEntry::new(experiment_index)
+								.with_experiment(experiment.clone())
+								.with_result(result)
+							);
+							continue;
+						}
+						None=>
+						{
+							println!("There are missing results (experiment {}).",experiment_index);
+						}
+					}
+				}
 				if let ConfigurationValue::Experiments(ref a) = self.files.packed_results
 				{
 					match &a[experiment_index]
@@ -1778,52 +2059,12 @@ impl<'a> Experiment<'a>
 								.with_experiment(experiment.clone())
 								.with_result(result.clone())
 							);
-							continue;
 						},
 					}
 				}
-				let experiment_path=runs_path.join(format!("run{}",experiment_index));
-				let result_path=experiment_path.join("local.result");
-				let mut result_file=match File::open(&result_path)
-				{
-					Ok(rf) => rf,
-					Err(_error) =>
-					{
-						//println!("There are problems opening results (experiment {}).",experiment_index);
-						continue;
-					}
-				};
-				let mut result_contents=String::new();
-				result_file.read_to_string(&mut result_contents).expect("something went wrong reading the result file.");
-				//println!("result file read into a String");
-				match config_parser::parse(&result_contents)
-				{
-					Ok(cv) =>
-					{
-						let result=match cv
-						{
-							config_parser::Token::Value(value) => value,
-							_ => panic!("wrong token"),
-						};
-						if let ConfigurationValue::Experiments(ref mut a) = self.files.packed_results
-						{
-							a[experiment_index] = result.clone();
-							added_packed_results+=1;
-						}
-						//results.push((experiment_index,experiment.clone(),result));
-						results.push(
-							OutputEnvironmentEntry::new(experiment_index)
-							.with_experiment(experiment.clone())
-							.with_result(result.clone())
-						);
-					}
-					Err(_error)=>
-					{
-						println!("There are missing results (experiment {}).",experiment_index);
-					}
-				}
 				//println!("result file processed.");
 			}
+			results_cache.save(&results_cache_path)?;
 			if let Some(csv) = &self.options.use_csv
 			{
 				let mut csv_contents = String::new();
@@ -1886,7 +2127,7 @@ impl<'a> Experiment<'a>
 						total,
 						&self.files,
 						&self.options.targets,
-					);
+					).with_plugs(self.plugs);
 					match config_parser::parse(&od_contents)
 					{
 						Err(x) => return Err(error!(could_not_parse_file,od).with_message(format!("error parsing output description file: {:?}",x))),
@@ -1943,6 +2184,12 @@ impl<'a> Experiment<'a>
 					}
 				}
 			}
+			//The cached results are now in binary.results.
+			let results_cache_path = self.files.root.as_ref().unwrap().join("results.cache");
+			if results_cache_path.exists()
+			{
+				fs::remove_file(&results_cache_path).unwrap_or_else(|e|panic!("Error {} when removing {:?}",e,results_cache_path));
+			}
 		}
 		let fin = format!("Finished action {} on {}.", action, now.format("%Y %m(%b) %0d(%a), %T (UTC%:z)"));
 		self.write_journal_entry(&fin);
@@ -2168,6 +2415,7 @@ pub struct ActionProgress
 	merged: usize,
 	discarded: usize,
 	errors: usize,
+	failed_assertions: usize,
 	before_amount_completed: usize,
 }
 
@@ -2192,6 +2440,7 @@ impl ActionProgress
 			merged: 0,
 			discarded: 0,
 			errors: 0,
+			failed_assertions: 0,
 			before_amount_completed: 0,
 		}
 	}
@@ -2207,7 +2456,7 @@ impl ActionProgress
 	}
 	pub fn update(&self)
 	{
-		let values = vec![ (self.pulled,"pulled"), (self.empty,"empty"), (self.missing,"missing"), (self.before_amount_completed,"already"), (self.merged,"merged"), (self.discarded,"discarded"), (self.errors,"errors")  ];
+		let values = [ (self.pulled,"pulled"), (self.empty,"empty"), (self.missing,"missing"), (self.before_amount_completed,"already"), (self.merged,"merged"), (self.discarded,"discarded"), (self.errors,"errors"), (self.failed_assertions,"failed assertions")  ];
 		let message : String = values.iter().filter_map(|(x,s)|{
 			if *x>0 { Some(format!("{} {}",x,s)) } else { None }
 		}).collect::<Vec<_>>().join(", ");
@@ -2215,8 +2464,54 @@ impl ActionProgress
 	}
 }
 
-
-
-
-
-
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn results_cache()
+	{
+		let root = std::env::temp_dir().join(format!("caminos_results_cache_{}",std::process::id()));
+		fs::create_dir_all(&root).unwrap();
+		let result_path = root.join("local.result");
+		let cache_path = root.join("results.cache");
+		fs::write(&result_path,"Result{accepted_load:0.5}").unwrap();
+		let mut cache = ResultsCache::load(&cache_path);
+		let (result,parsed_now) = cache.get(3,&result_path).expect("the result should be parsed");
+		assert!(parsed_now && cache.modified);
+		cache.save(&cache_path).unwrap();
+		// A loaded cache gives the result without modifications.
+		let mut cache = ResultsCache::load(&cache_path);
+		assert_eq!(cache.get(3,&result_path),Some((result,false)));
+		assert!(!cache.modified);
+		// A modified file is parsed again.
+		fs::write(&result_path,"Result{accepted_load:0.75}").unwrap();
+		let (result,parsed_now) = cache.get(3,&result_path).expect("the result should be parsed");
+		assert!(parsed_now && cache.modified);
+		assert_eq!(result,ConfigurationValue::Object("Result".to_string(),vec![("accepted_load".to_string(),ConfigurationValue::Number(0.75))]));
+		// Results written with `result_format: JSON` are understood as well.
+		fs::write(&result_path,"{\"_object\":\"Result\",\"accepted_load\":0.25}\n").unwrap();
+		let (result,_parsed_now) = cache.get(3,&result_path).expect("the JSON result should be parsed");
+		assert_eq!(result,ConfigurationValue::Object("Result".to_string(),vec![("accepted_load".to_string(),ConfigurationValue::Number(0.25))]));
+		fs::remove_dir_all(&root).unwrap();
+	}
+	#[test]
+	fn assertions()
+	{
+		let parse = |text:&str| match config_parser::parse(text)
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse {}",text),
+		};
+		let experiment = parse("Configuration{traffic:Homogeneous{load:0.8},assertions:[=ge{first:result.accepted_load,second:mul{first:0.95,second:configuration.traffic.load}}, =lt{first:result.average_packet_network_delay,second:100}]}");
+		let path = Path::new(".");
+		let good = parse("Result{accepted_load:0.79,average_packet_network_delay:40}");
+		assert!(failed_assertions(0,&experiment,&good,path).is_empty());
+		let bad = parse("Result{accepted_load:0.5,average_packet_network_delay:40}");
+		let failed = failed_assertions(0,&experiment,&bad,path);
+		assert_eq!(failed.len(),1);
+		assert!(failed[0].contains("accepted_load"));
+		// Experiments without assertions never fail.
+		let plain = parse("Configuration{traffic:Homogeneous{load:0.8}}");
+		assert!(failed_assertions(0,&plain,&bad,path).is_empty());
+	}
+}
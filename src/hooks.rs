@@ -0,0 +1,90 @@
+/*!
+
+Hooks allow programs embedding CAMINOS to observe the simulation without modifying the statistics module.
+A closure is registered into the [SimulationHooks] of a [Simulation](crate::Simulation) before running it, and it is called each time the corresponding event happens.
+When no closure has been registered for an event nothing is done for it beyond checking an empty list.
+
+```ignore
+let consumed = Rc::new(Cell::new(0));
+let mut simulation = Simulation::new(&cv,&plugs);
+let counter = consumed.clone();
+simulation.hooks.on_packet_consumed(move |_packet,_cycle| counter.set(counter.get()+1));
+simulation.run();
+println!("consumed {} packets",consumed.get());
+```
+
+*/
+
+use std::fmt::{Debug,Formatter};
+
+use crate::event::Time;
+use crate::packet::{Message,Packet};
+use crate::SimulationShared;
+
+///The closures registered to be called on simulation events.
+#[derive(Default)]
+pub struct SimulationHooks<'a>
+{
+	cycle_end: Vec<Box<dyn FnMut(&SimulationShared) + 'a>>,
+	packet_consumed: Vec<Box<dyn FnMut(&Packet,Time) + 'a>>,
+	message_generated: Vec<Box<dyn FnMut(&Message,Time) + 'a>>,
+}
+
+impl<'a> Debug for SimulationHooks<'a>
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		f.debug_struct("SimulationHooks")
+			.field("cycle_end",&self.cycle_end.len())
+			.field("packet_consumed",&self.packet_consumed.len())
+			.field("message_generated",&self.message_generated.len())
+			.finish()
+	}
+}
+
+impl<'a> SimulationHooks<'a>
+{
+	///Register a closure to be called at the end of each cycle, before advancing the cycle counter.
+	pub fn on_cycle_end<F:FnMut(&SimulationShared) + 'a>(&mut self, hook:F)
+	{
+		self.cycle_end.push(Box::new(hook));
+	}
+	///Register a closure to be called when the last phit of a packet is consumed by its destination server.
+	///It receives the packet and the current cycle.
+	pub fn on_packet_consumed<F:FnMut(&Packet,Time) + 'a>(&mut self, hook:F)
+	{
+		self.packet_consumed.push(Box::new(hook));
+	}
+	///Register a closure to be called when the traffic generates a message and it is stored in its origin server.
+	///It receives the message and the current cycle.
+	pub fn on_message_generated<F:FnMut(&Message,Time) + 'a>(&mut self, hook:F)
+	{
+		self.message_generated.push(Box::new(hook));
+	}
+	///Whether there is not any closure registered.
+	pub fn is_empty(&self) -> bool
+	{
+		self.cycle_end.is_empty() && self.packet_consumed.is_empty() && self.message_generated.is_empty()
+	}
+	pub(crate) fn cycle_end(&mut self, shared:&SimulationShared)
+	{
+		for hook in self.cycle_end.iter_mut()
+		{
+			hook(shared);
+		}
+	}
+	pub(crate) fn packet_consumed(&mut self, packet:&Packet, cycle:Time)
+	{
+		for hook in self.packet_consumed.iter_mut()
+		{
+			hook(packet,cycle);
+		}
+	}
+	pub(crate) fn message_generated(&mut self, message:&Message, cycle:Time)
+	{
+		for hook in self.message_generated.iter_mut()
+		{
+			hook(message,cycle);
+		}
+	}
+}
@@ -25,6 +25,7 @@ Alternatively, consider whether the binary crate `caminos` fits your intended us
 * Router methods insert and acknowledge now return `Vec<EventGeneration>` and are responsible for their scheduling.
 * Renamed in Traffic nomenclature servers into tasks. This includes ServerTrafficState renamed into TaskTrafficState, and `server_state` into `task_state`. Old configuration names are still supported.
 * Added method `number_tasks`required for trait Traffic.
+* Added a `timestamp` field to the allocator `Request` and `VCARequest`, with the cycle in which the packet entered the network. `Request::new` leaves it as `None`.
 
 ## [0.5.0] to [0.6.0]
 * Removed unnecessary generic parameter TM from routers Basic and InputOutput. They now may select [TransmissionMechanisms](router::TransmissionMechanism) to employ.
@@ -72,6 +73,7 @@ Alternatively, consider whether the binary crate `caminos` fits your intended us
 # Public Interface
 
 `caminos-lib` provides the functions `directory_main` and `file_main`, intended to use the file version when the final binary calls with a configuration file argument and the directory version when it is called with a directory argument.
+The simulations of a configuration file are run sequentially, unless `--jobs=N` is given to run `N` of them simultaneously in different threads, through `file_main_with_options`. Their results are written in the same order in any case.
 
 The `directory_main` function receives a `&Path` assumed to contain a `main.cfg`, `main.od`, optionally `remote`, plus any generated files and subdirectories.
 * `main.cfg` contains the definition of the experiment to perform, expected to unfold into multiple simulations.
@@ -86,9 +88,9 @@ The `directory_main` function receives a `&Path` assumed to contain a `main.cfg`
 The `directory_main` receives also an `Action`. In the crate `caminos` this is done via its `--action=<method>` falg.
 * `local_and_output` runs all the remaining simulations locally and generates the outputs.
 * `local` runs all the simulations locally, without processing the results afterwards.
-* `output` processes the currently available results and generates the outputs.
+* `output` processes the currently available results and generates the outputs. The parsed results are kept in a `results.cache` file, so that later outputs only parse the new or modified results. A `local.result` takes precedence over the result packed in `binary.results`, which is replaced when the `local.result` is modified.
 * `slurm` launches the remaining simulations onto the slurm system.
-* `check` just shows how many results we got and how many are currently in slurm.
+* `check` just shows how many results we got and how many are currently in slurm. It also lists the experiments whose results violate their `assertions`, and the percentage completed of the slurm jobs whose experiments write a [progress::ProgressFile].
 * `pull` brings result files from the defined remote host.
 * `remote_check` performs a `check` action in the remote host.
 * `push` compares the local main.cfg with the host remote.cfg. It reports discrepancies and create the remote path if missing.
@@ -96,6 +98,8 @@ The `directory_main` receives also an `Action`. In the crate `caminos` this is d
 * `shell` creates the experiment folder with default configuration files. Alternatively, when receiving `--source=another_experiment` it copies the configuration of the other experiment into this one.
 * `pack` forces the creation of a binary.results file and erases the verbose raw results files. In some extreme cases it can reduce a decent amount of space and sped up computations.
 
+A single simulation may also be built from Rust code, without configuration files, by a [builder::SimulationBuilder], and then run with [Simulation::run] to get its [Simulation::results].
+
 
 # Configuration Syntax
 
@@ -166,12 +170,13 @@ Configuration
 		delay: 0,//not actually implemented in the basic router. In the future it may be removed or actually implemented.
 		buffer_size: 64,//phits available in each input buffer
 		bubble: false,//to enable bubble mechanism in Cartesian topologies.
-		flit_size: 16,//set to maximum_packet_size to have Virtual Cut-Through.
+		switching: VirtualCutThrough,//or Wormhole or StoreAndForward. See router::Switching.
 		intransit_priority: false,//whether to give preference to transit over injection.
 		allow_request_busy_port: true,//whether to allow input buffer to make requests to ports that are transmitting
 		output_buffer_size:32,//Available phits in each output_buffer.
 		output_priorize_lowest_label: true,//whether arbiters give priority to requests with lowest label.
 	},
+	//The router may also be a list of RouterGroup{routers:[begin,end], role:"leaf", router:...} to use different routers in the network. See router::new_router.
 	routing: ![//Algorithm to provide candidate exit ports.
 		Shortest { legend_name: "shortest" },
 		Valiant {
@@ -191,7 +196,29 @@ Configuration
 		//The last class always correspond to the links between server and router
 		LinkClass { delay: 1},
 		//In a dragonfly topology we would have 0=routers from same group, 1=routers from different groups, and 2=from server
+		//A class may use `delay_distribution: Uniform{min:3,max:7}` instead of a fixed delay. See the link_delay module.
+		//A class may also set a smaller `maximum_packet_size`, so that the messages crossing it are split into smaller packets. See measures::Fragmentation.
 	],
+	//Optionally, the servers may throttle their injection with feedback from the network. See the congestion module.
+	//congestion_control: ECN{threshold:32, feedback_delay:20},
+	//Optionally, retransmit the packets dropped by routers with `drop_timeout` or `drop_when_full`. See the lossy module.
+	//lossy: Lossy{retransmission_timeout:2000},
+	//Optionally, end the simulation with a diagnosis when no phit moves for some cycles. See the deadlock module.
+	//deadlock_watchdog: DeadlockWatchdog{cycles:10000},
+	//Optionally, account the energy consumed by the links and routers. See the energy module.
+	//energy_model: EnergyModel{phit_energy:1.0, link_static_power:0.01, router_idle_power:1.0, router_active_power:2.0},
+	//Optionally, take links down and up again at given cycles. See the reconfiguration module.
+	//events: [LinkDown{cycle:2000, router:5, port:1}, LinkUp{cycle:6000, router:5, port:1}],
+	//Optionally, limit the injection of the servers with a model of their network interface. See the nic module.
+	//nic: NIC{injection_bandwidth:0.5, dma_latency:20},
+	//Optionally, how the servers with several rails or planes choose the rail of each packet: RoundRobin (default), Random or Adaptive.
+	//rail_selection: Adaptive,
+	//Optionally, write the result as a line of JSON instead of the configuration syntax. See results::ResultFormat.
+	//result_format: JSON,
+	//Optionally, check that the warmup reaches a steady state, extending it if needed. See measures::SteadyStateDetection.
+	//steady_state_detection: SteadyStateDetection{window:1000, tolerance:0.05, maximum_warmup:100000},
+	//Optionally, how to report the progress while simulating. By default the statistical columns are printed every 1000 cycles. See the progress module.
+	//progress_reporters: [Console{period:10000}, ProgressFile{period:100000}],
 	launch_configurations: [
 		//We may put here options to send to the SLURM system.
 		Slurm
@@ -203,6 +230,17 @@ Configuration
 }
 ```
 
+Instead of listing the seeds, a `Configuration` may include `repetitions: n` to simulate each of its experiments `n` times with different seeds.
+The repetition `r` of an experiment with `random_seed: s` is simulated with `random_seed: s*n+r` and it gets a field `repetition: r`.
+Similarly, `seeds: n` simulates each experiment with `n` well-spaced seeds derived from its `random_seed`, or 0 if absent.
+The seed `i` gets a field `seed_index: i` and the `random_seed` employed is also recorded in the result.
+The outputs can aggregate the repetitions by their mean and confidence interval, with `averaged` in a `CSV` or `confidence` in `Plots`.
+A `CSV` with `averaged` but without `fields` groups the runs by everything in the configuration except the seed.
+
+A `Configuration` may also include `assertions`, a list of expressions that must evaluate to `true` at the end of each simulation, in the same context as the outputs.
+For example, `assertions: [=ge{first:result.accepted_load,second:mul{first:0.95,second:configuration.traffic.load}}]` requires the accepted load to be close to the offered one.
+The experiments violating some assertion are marked as failed in the journal when run locally, and the `check` action lists them.
+
 ## Example output description
 
 An example of output description `main.od` is
@@ -280,7 +318,19 @@ Fot the `tikz` backend to work it is necessary to have a working `LaTeX` install
 
 # Plugging
 
-Both entries `directory_main` and `file_main` receive a `&Plugs` argument that may be used to provide the simulator with new implementations. This way, one can make a copy of the `main` in the `caminos` crate and declare plugs for their implemented `Router`, `Topology`, `Routing`, `Traffic`, `Pattern`, and `VirtualChannelPolicy`.
+Both entries `directory_main` and `file_main` receive a `&Plugs` argument that may be used to provide the simulator with new implementations. This way, one can make a copy of the `main` in the `caminos` crate and declare plugs for their implemented `Router`, `Topology`, `Stage`, `Routing`, `Traffic`, `Pattern`, `VirtualChannelPolicy`, and `Allocator`.
+New kinds of output for the `main.od` file may be added with `Plugs::add_output`. They are run by the `output` action with access to the results and the files of the experiment.
+
+The example `examples/plugs.rs` registers a plug of each kind. It is compiled with the tests, so changes to the builder arguments are noticed there first, and its documentation keeps migration notes for the users of the plugs.
+
+# Determinism
+
+A simulation must produce the same results for the same configuration, including its `random_seed`, in any machine. Implementations should follow these rules.
+* All the randomness must come from the generators received as arguments, or from generators with a seed derived from the configuration. Never use `StdRng::from_entropy` or `thread_rng`.
+* Never let the simulation depend on the iteration order of a `HashMap` or `HashSet`, since it changes between executions. Use `BTreeMap` and `BTreeSet` instead, or only make lookups.
+* Decisions should be taken over integer quantities. When comparing floating point values, avoid values computed by different sequences of operations, as different platforms may round them differently.
+
+The test `tests/determinism_test.rs` checks these properties for a sample configuration.
 
 */
 
@@ -337,6 +387,18 @@ pub mod error;
 pub mod measures;
 pub mod allocator;
 pub mod packet;
+pub mod results;
+pub mod hooks;
+pub mod analytic;
+pub mod link_delay;
+pub mod congestion;
+pub mod deadlock;
+pub mod energy;
+pub mod nic;
+pub mod reconfiguration;
+pub mod builder;
+pub mod progress;
+pub mod lossy;
 
 use std::rc::Rc;
 use std::boxed::Box;
@@ -347,30 +409,41 @@ use std::io::prelude::*;
 use std::io::{stdout};
 use std::collections::{VecDeque,BTreeMap};
 use std::ops::DerefMut;
-use std::path::{Path};
+use std::path::{Path,PathBuf};
 use std::mem::{size_of};
 use std::fmt::Debug;
 use std::cmp::Ordering;
+use std::time::Instant;
 //use std::default::default;
 //use std::borrow::Cow;
-use rand::{rngs::StdRng,SeedableRng};
+use rand::{rngs::StdRng,SeedableRng,seq::SliceRandom};
 
 use config_parser::{ConfigurationValue,Expr};
-use topology::{Topology,new_topology,TopologyBuilderArgument,Location,
+use topology::{Topology,new_topology,try_new_topology,TopologyBuilderArgument,Location,
 	multistage::{Stage,StageBuilderArgument}};
-use traffic::{Traffic,new_traffic,TrafficBuilderArgument,TrafficError};
-use router::{Router,new_router,RouterBuilderArgument};
-use routing::{RoutingInfo,Routing,new_routing,RoutingBuilderArgument};
+use traffic::{Traffic,try_new_traffic,TrafficBuilderArgument,TrafficError,InjectionRecord};
+use router::{Router,try_new_router,router_group_index,router_frequency_divisor,RouterBuilderArgument};
+use routing::{RoutingInfo,Routing,try_new_routing,RoutingBuilderArgument,RouterConfigurationSummary};
 use event::{EventQueue,Event,EventGeneration};
 use quantify::Quantifiable;
 use experiments::{Experiment,Action,ExperimentOptions};
 use policies::{VirtualChannelPolicy,VCPolicyBuilderArgument};
 use pattern::{Pattern,PatternBuilderArgument};
 use config::flatten_configuration_value;
-use measures::{Statistics,ServerStatistics};
+use measures::{Statistics,ServerStatistics,ResourceUsage,StallCounts,MeasurementExtension,SteadyStateDetection,TemporalStatisticsStream,LinkTraces,PacketTrace,GlobalLinkReport,BufferOccupancySeries,Fragmentation,DecisionFeatures,FlowStatistics,BatchMeans,PacketDecisions};
 use error::{Error,SourceLocation};
 use allocator::{Allocator,AllocatorBuilderArgument};
-pub use packet::{Phit,Packet,Message,PacketExtraInfo,PacketRef,AsMessage};
+use output::OutputBuilderArgument;
+use analytic::AnalyticEstimate;
+use congestion::{CongestionControl,new_congestion_control};
+use lossy::Lossy;
+use deadlock::DeadlockWatchdog;
+use energy::EnergyModel;
+use reconfiguration::{TopologyEvents,ReconfigurableLinks};
+use nic::Nic;
+use progress::{Progress,ProgressReporter,ConsoleProgress,new_progress_reporter};
+use link_delay::{DelayDistribution,DelaySampling,DelaySampler,SharedDelaySampler};
+pub use packet::{Phit,Packet,Message,PacketExtraInfo,PacketRef,AsMessage,RoutingHints,MessageTag};
 pub use event::Time;
 
 ///The objects that create and consume traffic to/from the network.
@@ -379,31 +452,100 @@ pub struct Server
 {
 	///The index of the server in the network.
 	index: usize,
-	///To which router the server is connected + link class index. Although we could just compute with the topology each time...
-	port: (Location,usize),
-	///Known available capacity in the connected router.
-	router_status: Box<dyn router::StatusAtEmissor+'static>,
+	///The ports of the server, one per rail. Usually there is a single rail.
+	rails: Vec<ServerRail>,
+	///The rail to be considered first for the next packet, to alternate among the rails. See [RailSelection].
+	next_rail: usize,
 	///Created messages but not sent.
 	stored_messages: VecDeque<Rc<Message>>,
 	///The packets of the message that have not yet been sent.
 	stored_packets: VecDeque<PacketRef>,
-	///The phits of a packet being sent.
-	stored_phits: VecDeque<Rc<Phit>>,
-	/// If there is a packet currently being transmitted, then the virtual channel requested if any.
-	outcoming_virtual_channel: Option<usize>,
 	///For each message we store the number of consumed phits, until the whole message is consumed.
 	consumed_phits: BTreeMap<*const Message,usize>,
 	///Statistics local to the server.
 	statistics: ServerStatistics,
+	///The network interface limiting the injection, if configured. See [nic].
+	nic: Option<Nic>,
+}
+
+///A port of a server towards a router. See [Topology::server_neighbours].
+#[derive(Quantifiable)]
+pub struct ServerRail
+{
+	///To which router the rail is connected + link class index. Although we could just compute with the topology each time...
+	port: (Location,usize),
+	///Known available capacity in the connected router.
+	router_status: Box<dyn router::StatusAtEmissor+'static>,
+	///The frequency divisor of the link to the router. See [SimulationShared::port_frequency_divisor].
+	frequency_divisor: Time,
+	///The phits of a packet being sent through this rail.
+	stored_phits: VecDeque<Rc<Phit>>,
+	/// If there is a packet currently being transmitted, then the virtual channel requested if any.
+	outcoming_virtual_channel: Option<usize>,
+}
+
+///How a server with several rails chooses the rail for each packet. It is set by the `rail_selection` of the configuration.
+///```ignore
+///rail_selection: RoundRobin,//the default, alternate among the free rails
+///rail_selection: Random,//a random free rail
+///rail_selection: Adaptive,//the free rail whose router has more known available space
+///```
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum RailSelection
+{
+	RoundRobin,
+	Random,
+	Adaptive,
+}
+
+impl RailSelection
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<RailSelection,Error>
+	{
+		match cv
+		{
+			ConfigurationValue::Object(name,_) if name=="RoundRobin" => Ok(RailSelection::RoundRobin),
+			ConfigurationValue::Object(name,_) if name=="Random" => Ok(RailSelection::Random),
+			ConfigurationValue::Object(name,_) if name=="Adaptive" => Ok(RailSelection::Adaptive),
+			_ => Err(cv.ill("bad value for rail_selection")),
+		}
+	}
+	///Chooses a rail among those without a packet being sent. `None` if all of them are busy.
+	fn select(&self, server:&Server, rng:&mut StdRng) -> Option<usize>
+	{
+		let num_rails=server.rails.len();
+		let mut free=(0..num_rails).map(|offset|(server.next_rail+offset)%num_rails).filter(|&rail_index|server.rails[rail_index].stored_phits.is_empty());
+		match self
+		{
+			RailSelection::RoundRobin => free.next(),
+			RailSelection::Random => free.collect::<Vec<usize>>().choose(rng).copied(),
+			RailSelection::Adaptive => free.max_by_key(|&rail_index|{
+				let status=&server.rails[rail_index].router_status;
+				let space:usize=(0..status.num_virtual_channels()).map(|vc|status.known_available_space_for_virtual_channel(vc).unwrap_or(0)).sum();
+				//Break ties towards the rails considered first.
+				(space,std::cmp::Reverse((rail_index+num_rails-server.next_rail)%num_rails))
+			}),
+		}
+	}
 }
 
 impl Server
 {
+	///The rail connected to the given location of a router. Servers with a single rail always return it.
+	fn rail_at(&mut self, receptor:&Location) -> &mut ServerRail
+	{
+		if self.rails.len()==1
+		{
+			return &mut self.rails[0];
+		}
+		let index = self.rails.iter().position(|rail|rail.port.0==*receptor).unwrap_or_else(||panic!("Server {} has no rail at {:?}",self.index,receptor));
+		&mut self.rails[index]
+	}
 	///Consumes a phit
 	fn consume(&mut self, phit:Rc<Phit>, traffic:&mut dyn Traffic, statistics:&mut Statistics, cycle:Time, topology:&dyn Topology, rng: &mut StdRng)
 	{
 		self.statistics.track_consumed_phit(cycle);
-		statistics.track_consumed_phit(cycle);
+		statistics.track_consumed_phit(cycle,&phit.packet.message);
 		let message=phit.packet.message.clone();
 		let message_ptr=message.as_ref() as *const Message;
 		//println!("phit consumed at server {}: stats {:?}",self.index,statistics);
@@ -416,9 +558,9 @@ impl Server
 		{
 			//The whole message has been consumed
 			self.statistics.track_consumed_message(cycle);
-			statistics.track_consumed_message(cycle);
+			statistics.track_consumed_message(cycle,&message);
 			self.statistics.track_message_delay(cycle-message.creation_cycle,cycle);
-			statistics.track_message_delay(cycle-message.creation_cycle,cycle);
+			statistics.track_message_delay(cycle-message.creation_cycle,cycle,&message);
 			self.consumed_phits.remove(&message_ptr);
 			if !traffic.consume(self.index, &*message, cycle, topology, rng)
 			{
@@ -442,6 +584,10 @@ impl Server
 		if phit.is_end()
 		{
 			statistics.track_consumed_packet(cycle,&phit.packet);
+			if let Some(nic) = self.nic.as_mut()
+			{
+				nic.packet_received(&phit.packet);
+			}
 			if cp < phit.packet.size
 			{
 				println!("phit tail has been consuming without having consumed a whole packet.");
@@ -479,6 +625,10 @@ pub struct Network
 	//routers: Vec<Box<RefCell<dyn Router>>>,
 	///TThe collection of all the servers in the network.
 	pub servers: Vec<Server>,
+	///The index of the `RouterGroup` from which each router has been built, or empty when all routers share a configuration.
+	pub router_groups: Vec<usize>,
+	///The frequency divisor of each router. See [router_frequency_divisor](router::router_frequency_divisor).
+	pub router_frequency_divisors: Vec<Time>,
 }
 
 
@@ -505,7 +655,7 @@ impl Quantifiable for Network
 		}
 		for server in self.servers.iter()
 		{
-			for phit in server.stored_phits.iter()
+			for phit in server.rails.iter().flat_map(|rail|rail.stored_phits.iter())
 			{
 				total+=phit.as_ref().total_memory();
 			}
@@ -583,7 +733,7 @@ impl Network
 						(String::from("accepted_phits"), ConfigurationValue::Number(server.statistics.temporal_statistics.get(index_cycle).map(|m|m.consumed_phits as f64).unwrap_or(0f64))),
 						(String::from("missed_generations"), ConfigurationValue::Number(server.statistics.temporal_statistics.get(index_cycle).map(|m|m.missed_generations as f64).unwrap_or(0f64))),
 						(String::from("server_index"), ConfigurationValue::Number(server.index as f64)),
-						(String::from("switches"), ConfigurationValue::Number( match server.port.0{
+						(String::from("switches"), ConfigurationValue::Number( match server.rails[0].port.0{
 							Location::RouterPort {router_index, router_port: _} => router_index as f64,
 							_ => panic!("Here there should be a router")
 						} )),
@@ -632,28 +782,90 @@ impl Network
 pub struct LinkClass
 {
 	///Cycles the phit needs to move from one endpoint to the other endpoint.
+	///With a `delay_distribution` this is the nominal delay, used for the acknowledgements.
 	pub delay: Time,
 	//(x,y) means x phits each y cycles ??
 	//transference_speed: (usize,usize)
 	///A phit can enter the link only in those cycles multiple of `frequency_divisor`.
 	///By default it is set a value of 0, value which will be replaced with the global frequency divisor of the simulation (whose default is 1).
+	///Each link of a class without a `frequency_divisor` then follows the slower of its endpoints, see [LinkClass::frequency_divisor_between].
 	frequency_divisor: Time,
+	///Whether the `frequency_divisor` was not given, so that each link takes it from its endpoints. See [LinkClass::frequency_divisor_between].
+	frequency_divisor_from_endpoints: bool,
+	///The sampler of variable delays, if any. It is shared by the clones of the link class. See [link_delay].
+	delay_sampler: Option<SharedDelaySampler>,
+	///The maximum size in phits of the packets crossing links of this class, if smaller than the global `maximum_packet_size`. See [Fragmentation].
+	pub maximum_packet_size: Option<usize>,
 }
 
 impl LinkClass
 {
-	fn new(cv:&ConfigurationValue) -> LinkClass
+	fn new(cv:&ConfigurationValue) -> Result<LinkClass,Error>
 	{
 		let mut delay=None;
 		let mut frequency_divisor = 0;
-		match_object_panic!(cv,"LinkClass",value,
-			"delay" => delay=Some(value.as_time().expect("bad value for delay")),
-			"frequency_divisor" => frequency_divisor = value.as_time().expect("bad value for frequency_divisor"),
+		let mut delay_distribution=None;
+		let mut delay_sampling=None;
+		let mut maximum_packet_size=None;
+		match_object!(cv,"LinkClass",value,
+			"delay" => delay=Some(value.as_time()?),
+			"frequency_divisor" => frequency_divisor = value.as_time()?,
+			"delay_distribution" => delay_distribution=Some(DelayDistribution::new(value)?),
+			"delay_sampling" => delay_sampling=Some(DelaySampling::new(value)?),
+			"maximum_packet_size" => maximum_packet_size=Some(value.as_usize()?),
 		);
-		let delay=delay.expect("There were no delay");
-		LinkClass{
+		let delay=match (delay,&delay_distribution)
+		{
+			(Some(delay),_) => delay,
+			(None,Some(distribution)) => distribution.mean(),
+			(None,None) => return Err(cv.ill("There were no delay")),
+		};
+		if delay_sampling.is_some() && delay_distribution.is_none()
+		{
+			println!("WARNING: delay_sampling has no effect without a delay_distribution.");
+		}
+		let delay_sampler=delay_distribution.map(|distribution|Rc::new(RefCell::new(DelaySampler::new(distribution,delay_sampling.unwrap_or(DelaySampling::Packet)))));
+		if maximum_packet_size==Some(0)
+		{
+			return Err(cv.ill("The maximum_packet_size of a link class must be positive"));
+		}
+		Ok(LinkClass{
 			delay,
 			frequency_divisor,
+			frequency_divisor_from_endpoints: frequency_divisor==0,
+			delay_sampler,
+			maximum_packet_size,
+		})
+	}
+	///The delay of a `phit` sent at `cycle` to the location `destination`. It is the fixed `delay` unless the link class has a `delay_distribution`.
+	pub fn phit_delay(&self, cycle:Time, phit:&Phit, destination:&Location, topology:&dyn Topology) -> Time
+	{
+		match self.delay_sampler
+		{
+			Some(ref sampler) => sampler.borrow_mut().phit_delay(cycle,phit,destination,topology),
+			None => self.delay,
+		}
+	}
+	///The frequency divisor of a link of this class whose endpoints have the frequency divisors `first` and `second`.
+	///It is the `frequency_divisor` of the class when given, and otherwise the largest of the endpoints, so that both can follow the link.
+	pub fn frequency_divisor_between(&self, first:Time, second:Time) -> Time
+	{
+		if self.frequency_divisor_from_endpoints
+		{
+			first.max(second)
+		}
+		else
+		{
+			self.frequency_divisor
+		}
+	}
+	///The delay of an acknowledgement sent to the location `destination`.
+	pub fn acknowledge_delay(&self, destination:&Location, topology:&dyn Topology) -> Time
+	{
+		match self.delay_sampler
+		{
+			Some(ref sampler) => sampler.borrow_mut().acknowledge_delay(self.delay,destination,topology),
+			None => self.delay,
 		}
 	}
 }
@@ -679,6 +891,9 @@ pub struct SimulationShared
 	/// Components using this value will only execute at cycles multiple of it.
 	/// This parameter allows to reduce the global frequency, allowing in turn to override some component to have greater frequency than the rest.
 	pub general_frequency_divisor: Time,
+	///The occupancy of each port of each router, as given by [Router::port_occupancy](router::Router::port_occupancy), taken at the beginning of the cycle.
+	///Only collected when the routing requires it, see [Routing::need_global_occupancy].
+	pub network_occupancy: Option<Vec<Vec<f64>>>,
 }
 
 impl SimulationShared
@@ -694,15 +909,57 @@ impl SimulationShared
 	}
 	/**
 		Schedule an event to be executed at the arrival across a link.
-		Counts both the wait for the time slot and the delay.
+		Counts both the wait for the time slot and the delay, which is sampled for phits and acknowledgements when the link class has a `delay_distribution`.
 	**/
 	pub fn schedule_link_arrival(&self, link_class:usize, event:Event) -> EventGeneration
+	{
+		self.schedule_arrival(link_class,self.link_classes[link_class].frequency_divisor,event)
+	}
+	/**
+	The frequency divisor of the link connected to the `port` of the router `router_index`.
+	It is derived from the frequency divisors of the router and the other endpoint, the `general_frequency_divisor` for servers, unless the link class sets one.
+	**/
+	pub fn port_frequency_divisor(&self, router_index:usize, port:usize) -> Time
+	{
+		let (location,link_class) = self.network.topology.neighbour(router_index,port);
+		let other = match location
+		{
+			Location::RouterPort{router_index:other,..} => self.network.router_frequency_divisors[other],
+			_ => self.general_frequency_divisor,
+		};
+		self.link_classes[link_class].frequency_divisor_between(self.network.router_frequency_divisors[router_index],other)
+	}
+	/**
+	Whether the current cycle is a multiple of the frequency divisor of the link connected to the `port` of the router `router_index`.
+	Like [SimulationShared::is_link_cycle] but taking into account the clock domains of the endpoints.
+	**/
+	pub fn is_port_cycle(&self, router_index:usize, port:usize) -> bool
+	{
+		self.cycle % self.port_frequency_divisor(router_index,port) == 0
+	}
+	/**
+		Schedule an event to be executed at the arrival across the link connected to the `port` of the router `router_index`.
+		Like [SimulationShared::schedule_link_arrival] but waiting for a time slot of the [port_frequency_divisor](SimulationShared::port_frequency_divisor).
+	**/
+	pub fn schedule_port_arrival(&self, router_index:usize, port:usize, event:Event) -> EventGeneration
+	{
+		let (_location,link_class) = self.network.topology.neighbour(router_index,port);
+		self.schedule_arrival(link_class,self.port_frequency_divisor(router_index,port),event)
+	}
+	fn schedule_arrival(&self, link_class:usize, frequency_divisor:Time, event:Event) -> EventGeneration
 	{
 		let link = &self.link_classes[link_class];
-		let slot = event::round_to_multiple(self.cycle,link.frequency_divisor);
+		let slot = event::round_to_multiple(self.cycle,frequency_divisor);
 		let wait = slot - self.cycle;
+		let topology = self.network.topology.as_ref();
+		let delay = match event
+		{
+			Event::PhitToLocation{ref phit,ref new,..} => link.phit_delay(slot,phit,new,topology),
+			Event::Acknowledge{ref location,..} => link.acknowledge_delay(location,topology),
+			_ => link.delay,
+		};
 		EventGeneration{
-			delay: wait + link.delay,
+			delay: wait + delay,
 			position: event::CyclePosition::Begin,
 			event,
 		}
@@ -716,6 +973,39 @@ pub struct SimulationMut
 {
 	///The random number generator itself, with its current state.
 	pub rng: StdRng,
+	///When `task_random_streams` is enabled, a random number generator for each task of the root traffic.
+	///Each one is keyed by `(random_seed, task)`, so the sequence of a task does not depend on the number of servers
+	///nor the order in which tasks are processed.
+	pub task_rngs: Option<Vec<StdRng>>,
+	///The packets dropped by the routers in the current cycle, to be handled by [Simulation::lossy].
+	pub dropped_packets: Vec<PacketRef>,
+}
+
+impl SimulationMut
+{
+	/**
+	Build the generator of a task. `StdRng` is a ChaCha generator, which is counter-based, so by keying it with
+	`(seed,task)` each task gets a stream independent of every other one.
+	**/
+	pub fn new_task_rng(seed:usize, task:usize) -> StdRng
+	{
+		let mut key = [0u8;32];
+		key[0..8].copy_from_slice(&(seed as u64).to_le_bytes());
+		key[8..16].copy_from_slice(&(task as u64).to_le_bytes());
+		//A tag to keep these streams apart from the ones produced by `seed_from_u64`.
+		key[16..24].copy_from_slice(b"tasks:rn");
+		StdRng::from_seed(key)
+	}
+	///The generator to be used for the traffic of the given task.
+	///It is the global generator unless `task_random_streams` was enabled.
+	pub fn task_rng(&mut self, task:usize) -> &mut StdRng
+	{
+		match self.task_rngs
+		{
+			Some(ref mut rngs) => &mut rngs[task],
+			None => &mut self.rng,
+		}
+	}
 }
 
 ///The object representing the whole simulation.
@@ -735,14 +1025,51 @@ pub struct Simulation<'a>
 	pub warmup: Time,
 	///Cycles of measurement
 	pub measured: Time,
+	///When set, the measured period is extended until the accepted load is stable.
+	pub measurement_extension: Option<MeasurementExtension>,
+	///When set, checks whether the warmup reaches a steady state, possibly extending it.
+	pub steady_state_detection: Option<SteadyStateDetection>,
 	///Maximum number of messages for generation to store in each server. Its default value is 20 messages.
 	///Attempts to generate traffic that fails because of the limit are tracked into the `missed_generations` statistic.
 	///Note that packets are not generated until it is the turn for the message to be sent to a router.
 	pub server_queue_size: usize,
+	///How the servers with several rails choose the rail of each packet.
+	pub rail_selection: RailSelection,
+	///The format in which [Simulation::write_result] writes the results.
+	pub result_format: results::ResultFormat,
 	///The queue of events guiding the simulation.
 	pub event_queue: EventQueue,
 	///The statistics being collected.
 	pub statistics: Statistics,
+	///When set, the periodic measurements are written into a file as they are completed instead of being kept in memory.
+	pub temporal_statistics_stream: Option<TemporalStatisticsStream>,
+	///When set, the busy/idle state of some links is recorded during the measured period.
+	pub link_traces: Option<LinkTraces>,
+	///When set, the generated messages are written into a file, to be replayed by the `TraceReplay` traffic.
+	pub injection_record: Option<InjectionRecord>,
+	///When set, the utilization of the global links is reported, warning about hotspots.
+	pub global_link_report: Option<GlobalLinkReport>,
+	///When set, the occupancy of the buffers of the routers is sampled periodically.
+	pub buffer_occupancy: Option<BufferOccupancySeries>,
+	///The segmentation of the messages when some link class limits the packet size.
+	pub fragmentation: Option<Fragmentation>,
+	///When set, the performance is estimated analytically instead of simulating, see [analytic].
+	pub analytic: Option<AnalyticEstimate>,
+	///When set, the servers throttle their injection from the feedback of the network, see [congestion].
+	pub congestion_control: Option<Box<dyn CongestionControl>>,
+	///Retransmits the packets dropped by the routers, when configured. See [lossy].
+	///It is also created, without retransmission, when some router drops a packet.
+	pub lossy: Option<Lossy>,
+	///Detects the deadlocks of the network, when configured. See [deadlock].
+	pub deadlock_watchdog: Option<DeadlockWatchdog>,
+	///Accounts the energy consumed by the network, when configured. See [energy].
+	pub energy_model: Option<EnergyModel>,
+	///The changes of the topology scheduled during the simulation, when configured. See [reconfiguration].
+	pub topology_events: Option<TopologyEvents>,
+	///The result of the analytic estimation, once computed by [Simulation::run].
+	pub analytic_result: Option<ConfigurationValue>,
+	///Whether to include the utilization of each link in the result, as requested by `statistics_link_utilization`.
+	pub statistics_link_utilization: bool,
 	///Information abut how to launch simulations to different systems.
 	#[allow(dead_code)]
 	pub launch_configurations: Vec<ConfigurationValue>,
@@ -750,12 +1077,60 @@ pub struct Simulation<'a>
 	pub plugs: &'a Plugs,
 	///Number of cycles to wait between reports of memory usage.
 	pub memory_report_period: Option<Time>,
+	///The reporters of the progress of the simulation, see [progress]. By default a console reporter every 1000 cycles.
+	pub progress_reporters: Vec<Box<dyn ProgressReporter>>,
+	///When the simulation was created. To report its wall time.
+	pub creation_instant: Instant,
+	///The CPU seconds employed by the process before creating the simulation. To report the CPU time of this simulation alone.
+	pub creation_cpu_time: Option<f64>,
+	///Closures provided by library users to be called on simulation events.
+	pub hooks: hooks::SimulationHooks<'a>,
+}
+
+///Parses the definitions of `statistics_packet_definitions`, `statistics_message_definitions`, or `statistics_temporal_definitions`,
+///each of them being a pair `[keys,values]` of arrays of expressions. The `name` of the field is used in the errors.
+fn parse_statistics_definitions(cv:&ConfigurationValue, name:&str) -> Result<Vec<(Vec<Expr>,Vec<Expr>)>,Error>
+{
+	let expressions = |list:&ConfigurationValue| -> Result<Vec<Expr>,Error> {
+		match list
+		{
+			ConfigurationValue::Array(ref lx) => lx.iter().map(|x|match x{
+				ConfigurationValue::Expression(expr) => Ok(expr.clone()),
+				_ => Err(x.ill(&format!("bad value for {}",name))),
+				}).collect(),
+			_ => Err(list.ill(&format!("bad value for {}",name))),
+		}
+	};
+	cv.as_array()?.iter().map(|definition|match definition {
+		ConfigurationValue::Array(ref dl) if dl.len()==2 => Ok((expressions(&dl[0])?,expressions(&dl[1])?)),
+		_ => Err(definition.ill(&format!("Each definition of {} must be composed of [keys,values]",name))),
+	}).collect()
 }
 
 impl<'a> Simulation<'a>
 {
+	///Like [Simulation::new] but returning an error instead of panicking when some component cannot be built from the configuration.
+	pub fn try_new(cv: &ConfigurationValue, plugs:&'a Plugs) -> Result<Simulation<'a>,Error>
+	{
+		Simulation::try_new_in_directory(cv,plugs,Path::new(""))
+	}
+	///Builds the simulation, creating the files it writes, other than the results, relative to the current directory.
 	pub fn new(cv: &ConfigurationValue, plugs:&'a Plugs) -> Simulation<'a>
 	{
+		Simulation::new_in_directory(cv,plugs,Path::new(""))
+	}
+	///Builds the simulation, creating the files it writes, other than the results, inside the `directory`, typically the run directory of an experiment.
+	///These are the `statistics_temporal_file`, the `statistics_link_traces`, the `packet_trace_file`, the `injection_record_file`, and the `statistics_decision_features`.
+	///Their absolute paths are kept as given.
+	pub fn new_in_directory(cv: &ConfigurationValue, plugs:&'a Plugs, directory:&Path) -> Simulation<'a>
+	{
+		Simulation::try_new_in_directory(cv,plugs,directory).unwrap_or_else(|error|panic!("{}",error))
+	}
+	///Like [Simulation::new_in_directory] but returning an error instead of panicking when some component cannot be built from the configuration.
+	pub fn try_new_in_directory(cv: &ConfigurationValue, plugs:&'a Plugs, directory:&Path) -> Result<Simulation<'a>,Error>
+	{
+		let creation_instant = Instant::now();
+		let creation_cpu_time = ResourceUsage::process_cpu_time();
 		let mut seed: Option<usize> = None;
 		let mut topology =None;
 		let mut traffic =None;
@@ -773,133 +1148,101 @@ impl<'a> Simulation<'a>
 		let mut statistics_message_definitions:Vec< (Vec<Expr>,Vec<Expr>) > = vec![];
 		let mut temporal_defined_statistics:Vec< (Vec<Expr>, Vec<Expr>) > = vec![];
 		let mut server_queue_size = None;
+		let mut rail_selection = RailSelection::RoundRobin;
+		let mut result_format = results::ResultFormat::Configuration;
 		let mut memory_report_period = None;
+		let mut progress_reporters = None;
 		let mut general_frequency_divisor = 1;
-		match_object_panic!(cv,"Configuration",value,
-			"random_seed" => seed=Some(value.as_usize().expect("bad value for random_seed")),
-			"warmup" => warmup=Some(value.as_time().expect("bad value for warmup")),
-			"measured" => measured=Some(value.as_time().expect("bad value for measured")),
+		let mut task_random_streams = false;
+		let mut measurement_extension = None;
+		let mut steady_state_detection = None;
+		let mut statistics_temporal_file = None;
+		let mut statistics_link_traces = None;
+		let mut statistics_global_links = None;
+		let mut statistics_buffer_occupancy_step = None;
+		let mut statistics_buffer_occupancy_maximum_samples = None;
+		let mut analytic = None;
+		let mut congestion_control = None;
+		let mut lossy = None;
+		let mut deadlock_watchdog = None;
+		let mut energy_model = None;
+		let mut topology_events = None;
+		let mut nic = None;
+		let mut statistics_link_utilization = false;
+		let mut packet_trace_file = None;
+		let mut statistics_decision_features = None;
+		let mut statistics_flows = None;
+		let mut statistics_batch_means = None;
+		let mut injection_record_file = None;
+		match_object!(cv,"Configuration",value,
+			"random_seed" => seed=Some(value.as_usize()?),
+			"repetition" => (),
+			"seed_index" => (),
+			"assertions" => (),
+			"warmup" => warmup=Some(value.as_time()?),
+			"measured" => measured=Some(value.as_time()?),
 			"topology" => topology=Some(value),
 			"traffic" => traffic=Some(value),
-			"maximum_packet_size" => maximum_packet_size=Some(value.as_usize().expect("bad value for maximum_packet_size")),
-			"server_queue_size" => server_queue_size=Some(value.as_usize().expect("bad value for server_queue_size")),
+			"maximum_packet_size" => maximum_packet_size=Some(value.as_usize()?),
+			"server_queue_size" => server_queue_size=Some(value.as_usize()?),
+			"rail_selection" => rail_selection=RailSelection::new(value)?,
+			"result_format" => result_format=results::ResultFormat::new(value)?,
 			"router" => router_cfg=Some(value),
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,plugs})),
-			"link_classes" => link_classes = Some(value.as_array().expect("bad value for link_classes").iter()
-				.map(LinkClass::new).collect()),
-			"statistics_temporal_step" => statistics_temporal_step=value.as_time().expect("bad value for statistics_temporal_step"),
-			"launch_configurations" => launch_configurations = value.as_array().expect("bad value for launch_configurations").clone(),
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,plugs})?),
+			"link_classes" => link_classes = Some(value.as_array()?.iter()
+				.map(LinkClass::new).collect::<Result<_,_>>()?),
+			"statistics_temporal_step" => statistics_temporal_step=value.as_time()?,
+			"statistics_temporal_file" => statistics_temporal_file=Some(value.as_str()?.to_string()),
+			"statistics_link_traces" => statistics_link_traces=Some(value),
+			"statistics_global_links" => statistics_global_links=Some(value),
+			"statistics_buffer_occupancy_step" => statistics_buffer_occupancy_step=Some(value.as_time()?),
+			"statistics_buffer_occupancy_maximum_samples" => statistics_buffer_occupancy_maximum_samples=Some(value.as_usize()?),
+			"analytic" => analytic=Some(AnalyticEstimate::new(value)?),
+			"congestion_control" => congestion_control=Some(value),
+			"lossy" => lossy=Some(Lossy::new(value)?),
+			"deadlock_watchdog" => deadlock_watchdog=Some(DeadlockWatchdog::new(value)?),
+			"energy_model" => energy_model=Some(value),
+			"events" => topology_events=Some(TopologyEvents::new(value)?),
+			"nic" => nic=Some(Nic::new(value)?),
+			"statistics_link_utilization" => statistics_link_utilization=value.as_bool()?,
+			"packet_trace_file" => packet_trace_file=Some(value.as_str()?.to_string()),
+			"statistics_decision_features" => statistics_decision_features=Some(DecisionFeatures::new(value,directory)?),
+			"statistics_flows" => statistics_flows=Some(FlowStatistics::new(value)?),
+			"statistics_batch_means" => statistics_batch_means=Some(value),
+			"injection_record_file" => injection_record_file=Some(value.as_str()?.to_string()),
+			"launch_configurations" => launch_configurations = value.as_array()?.clone(),
 			"statistics_server_percentiles" => statistics_server_percentiles = value
-				.as_array().expect("bad value for statistics_server_percentiles").iter()
-				.map(|v|v.as_f64().expect("bad value in statistics_server_percentiles").round() as u8).collect(),
+				.as_array()?.iter()
+				.map(|v|Ok(v.as_f64()?.round() as u8)).collect::<Result<_,Error>>()?,
 			"statistics_packet_percentiles" => statistics_packet_percentiles = value
-				.as_array().expect("bad value for statistics_packet_percentiles").iter()
-				.map(|v|v.as_f64().expect("bad value in statistics_packet_percentiles").round() as u8).collect(),
-			"statistics_packet_definitions" => match value
-			{
-				&ConfigurationValue::Array(ref l) => statistics_packet_definitions=l.iter().map(|definition|match definition {
-					&ConfigurationValue::Array(ref dl) => {
-						if dl.len()!=2
-						{
-							panic!("Each definition of statistics_packet_definitions must be composed of [keys,values]");
-						}
-						let keys = match dl[0]
-						{
-							ConfigurationValue::Array(ref lx) => lx.iter().map(|x|match x{
-								ConfigurationValue::Expression(expr) => expr.clone(),
-								_ => panic!("bad value for statistics_packet_definitions"),
-								}).collect(),
-							_ => panic!("bad value for statistics_packet_definitions"),
-						};
-						let values = match dl[1]
-						{
-							ConfigurationValue::Array(ref lx) => lx.iter().map(|x|match x{
-								ConfigurationValue::Expression(expr) => expr.clone(),
-								_ => panic!("bad value for statistics_packet_definitions"),
-								}).collect(),
-							_ => panic!("bad value for statistics_packet_definitions"),
-						};
-						(keys,values)
-					},
-					_ => panic!("bad value for statistics_packet_definitions"),
-				}).collect(),
-				_ => panic!("bad value for statistics_packet_definitions"),
-			}
-			"statistics_message_definitions" => match value
-			{
-				&ConfigurationValue::Array(ref l) => statistics_message_definitions=l.iter().map(|definition|match definition {
-					&ConfigurationValue::Array(ref dl) => {
-						if dl.len()!=2
-						{
-							panic!("Each definition of statistics_message_definitions must be composed of [keys,values]");
-						}
-						let keys = match dl[0]
-						{
-							ConfigurationValue::Array(ref lx) => lx.iter().map(|x|match x{
-								ConfigurationValue::Expression(expr) => expr.clone(),
-								_ => panic!("bad value for statistics_message_definitions"),
-								}).collect(),
-							_ => panic!("bad value for statistics_message_definitions"),
-						};
-						let values = match dl[1]
-						{
-							ConfigurationValue::Array(ref lx) => lx.iter().map(|x|match x{
-								ConfigurationValue::Expression(expr) => expr.clone(),
-								_ => panic!("bad value for statistics_message_definitions"),
-								}).collect(),
-							_ => panic!("bad value for statistics_message_definitions"),
-						};
-						(keys,values)
-					},
-					_ => panic!("bad value for statistics_message_definitions"),
-				}).collect(),
-				_ => panic!("bad value for statistics_message_definitions"),
-			}
-			"statistics_temporal_definitions" | "temporal_statistics_packet_definitions" => match value
-			{
-				&ConfigurationValue::Array(ref l) => temporal_defined_statistics=l.iter().map(|definition|match definition {
-					&ConfigurationValue::Array(ref dl) => {
-						if dl.len()!=2
-						{
-							panic!("Each definition of temporal_defined_statistics must be composed of [keys,values]");
-						}
-						let keys = match dl[0]
-						{
-							ConfigurationValue::Array(ref lx) => lx.iter().map(|x|match x{
-								ConfigurationValue::Expression(expr) => expr.clone(),
-								_ => panic!("bad value for temporal_defined_statistics"),
-								}).collect(),
-							_ => panic!("bad value for temporal_defined_statistics"),
-						};
-						let values = match dl[1]
-						{
-							ConfigurationValue::Array(ref lx) => lx.iter().map(|x|match x{
-								ConfigurationValue::Expression(expr) => expr.clone(),
-								_ => panic!("bad value for temporal_defined_statistics"),
-								}).collect(),
-							_ => panic!("bad value for temporal_defined_statistics"),
-						};
-						(keys,values)
-					},
-					_ => panic!("bad value for temporal_defined_statistics"),
-				}).collect(),
-				_ => panic!("bad value for temporal_defined_statistics"),
-			}
-
-			"memory_report_period" => memory_report_period=Some(value.as_time().expect("bad value for memory_report_period")),
-			"general_frequency_divisor" => general_frequency_divisor = value.as_time().expect("bad value for general_frequency_divisor"),
+				.as_array()?.iter()
+				.map(|v|Ok(v.as_f64()?.round() as u8)).collect::<Result<_,Error>>()?,
+			"statistics_packet_definitions" => statistics_packet_definitions=parse_statistics_definitions(value,"statistics_packet_definitions")?,
+			"statistics_message_definitions" => statistics_message_definitions=parse_statistics_definitions(value,"statistics_message_definitions")?,
+			"statistics_temporal_definitions" | "temporal_statistics_packet_definitions" => temporal_defined_statistics=parse_statistics_definitions(value,"temporal_defined_statistics")?,
+
+			"memory_report_period" => memory_report_period=Some(value.as_time()?),
+			"progress_reporters" => progress_reporters=Some(value.as_array()?.iter()
+				.map(|reporter|new_progress_reporter(reporter,directory)).collect::<Result<_,_>>()?),
+			"general_frequency_divisor" => general_frequency_divisor = value.as_time()?,
+			"task_random_streams" => task_random_streams = value.as_bool()?,
+			"measurement_extension" => measurement_extension = Some(MeasurementExtension::new(value)?),
+			"steady_state_detection" => steady_state_detection = Some(SteadyStateDetection::new(value)?),
 		);
-		let seed=seed.expect("There were no random_seed");
-		let warmup=warmup.expect("There were no warmup");
-		let measured=measured.expect("There were no measured");
-		let topology=topology.expect("There were no topology");
-		let traffic=traffic.expect("There were no traffic");
-		let maximum_packet_size=maximum_packet_size.expect("There were no maximum_packet_size");
+		let seed=seed.ok_or_else(||cv.ill("There were no random_seed"))?;
+		let warmup=warmup.ok_or_else(||cv.ill("There were no warmup"))?;
+		let measured=measured.ok_or_else(||cv.ill("There were no measured"))?;
+		let topology=topology.ok_or_else(||cv.ill("There were no topology"))?;
+		let traffic=traffic.ok_or_else(||cv.ill("There were no traffic"))?;
+		let maximum_packet_size=maximum_packet_size.ok_or_else(||cv.ill("There were no maximum_packet_size"))?;
 		let server_queue_size = server_queue_size.unwrap_or(20);
-		assert!(server_queue_size>0, "we need space in the servers to store generated messages.");
-		let router_cfg=router_cfg.expect("There were no router");
-		let mut routing=routing.expect("There were no routing");
-		let mut link_classes:Vec<LinkClass>=link_classes.expect("There were no link_classes");
+		if server_queue_size==0
+		{
+			return Err(cv.ill("we need space in the servers to store generated messages."));
+		}
+		let router_cfg=router_cfg.ok_or_else(||cv.ill("There were no router"))?;
+		let mut routing=routing.ok_or_else(||cv.ill("There were no routing"))?;
+		let mut link_classes:Vec<LinkClass>=link_classes.ok_or_else(||cv.ill("There were no link_classes"))?;
 		for link_class in link_classes.iter_mut()
 		{
 			if link_class.frequency_divisor == 0
@@ -907,74 +1250,135 @@ impl<'a> Simulation<'a>
 				link_class.frequency_divisor = general_frequency_divisor;
 			}
 		}
+		for (index,link_class) in link_classes.iter().enumerate()
+		{
+			if let Some(ref sampler) = link_class.delay_sampler
+			{
+				sampler.borrow_mut().seed(seed,index);
+			}
+		}
 		//This has been changed from rand-0.4 to rand-0.8
 		let mut rng=StdRng::seed_from_u64(seed as u64);
-		let topology=new_topology(TopologyBuilderArgument{
+		let topology=try_new_topology(TopologyBuilderArgument{
 			cv:topology,
 			plugs,
 			rng:&mut rng,
-		});
+		})?;
 		topology.check_adjacency_consistency(Some(link_classes.len()));
+		let topology : Box<dyn Topology> = match topology_events
+		{
+			Some(ref events) => {
+				events.check(topology.as_ref())?;
+				Box::new(ReconfigurableLinks::new(topology))
+			},
+			None => topology,
+		};
 		routing.initialize(topology.as_ref(),&mut rng);
 		let num_routers=topology.num_routers();
 		let num_servers=topology.num_servers();
 		//let routers: Vec<Rc<RefCell<dyn Router>>>=(0..num_routers).map(|index|new_router(index,router_cfg,plugs,topology.as_ref(),maximum_packet_size)).collect();
-		let routers: Vec<Rc<RefCell<dyn Router>>>=(0..num_routers).map(|index|new_router(RouterBuilderArgument{
+		let router_frequency_divisors: Vec<Time> = (0..num_routers).map(|index|router_frequency_divisor(router_cfg,index,topology.as_ref(),general_frequency_divisor)).collect::<Result<_,_>>()?;
+		let routers: Vec<Rc<RefCell<dyn Router>>>=(0..num_routers).map(|index|try_new_router(RouterBuilderArgument{
 			router_index:index,
 			cv:router_cfg,
 			plugs,
 			topology:topology.as_ref(),
 			maximum_packet_size,
 			general_frequency_divisor,
+			frequency_divisor: router_frequency_divisors[index],
 			statistics_temporal_step,
 			rng:&mut rng,
-		})).collect();
+		})).collect::<Result<_,_>>()?;
+		let router_groups = match router_cfg
+		{
+			ConfigurationValue::Array(groups) => (0..num_routers).map(|index|router_group_index(groups,index,topology.as_ref())).collect::<Result<_,_>>()?,
+			_ => vec![],
+		};
+		if lossy.is_some() && !routers.iter().any(|router|router.borrow().may_drop_packets())
+		{
+			return Err(cv.ill("The configuration has `lossy` but no router may drop packets. Use a Basic router with `drop_when_full` or `drop_timeout`."));
+		}
+		let router_summary = RouterConfigurationSummary{
+			virtual_channels: routers.iter().enumerate().map(|(index,router)|vec![router.borrow().num_virtual_channels();topology.ports(index)]).collect(),
+			buffer_sizes: routers.iter().enumerate().map(|(index,router)|{
+				let router=router.borrow();
+				(0..topology.ports(index)).map(|port|(0..router.num_virtual_channels()).map(|vc|router.virtual_port_size(port,vc)).collect()).collect()
+			}).collect(),
+		};
+		routing.check_router_configuration(topology.as_ref(),&router_summary)?;
 		let servers=(0..num_servers).map(|index|{
-			let port=topology.server_neighbour(index);
-			let router_status=match port.0
-			{
-				Location::RouterPort{
-					router_index,
-					router_port
-				} => {
-					let router=routers[router_index].borrow();
-					// let nvc=router.num_virtual_channels();
-					// let buffer_amount=nvc;
-					// //TODO: this seems that should a function of the TransmissionFromServer...
-					// let buffer_size=(0..nvc).map(|vc|router.virtual_port_size(router_port,vc)).max().expect("0 buffers in the router");
-					// let size_to_send=maximum_packet_size;
-					// let from_server_mechanism = TransmissionFromServer::new(buffer_amount,buffer_size,size_to_send);
-					// let status = from_server_mechanism.new_status_at_emissor();
-					// Box::new(status)
-					router.build_emissor_status(router_port,&*topology)
+			let rails=topology.server_neighbours(index).into_iter().map(|port|{
+				let (router_status,frequency_divisor)=match port.0
+				{
+					Location::RouterPort{
+						router_index,
+						router_port
+					} => {
+						let router=routers[router_index].borrow();
+						// let nvc=router.num_virtual_channels();
+						// let buffer_amount=nvc;
+						// //TODO: this seems that should a function of the TransmissionFromServer...
+						// let buffer_size=(0..nvc).map(|vc|router.virtual_port_size(router_port,vc)).max().expect("0 buffers in the router");
+						// let size_to_send=maximum_packet_size;
+						// let from_server_mechanism = TransmissionFromServer::new(buffer_amount,buffer_size,size_to_send);
+						// let status = from_server_mechanism.new_status_at_emissor();
+						// Box::new(status)
+						(router.build_emissor_status(router_port,&*topology),link_classes[port.1].frequency_divisor_between(general_frequency_divisor,router_frequency_divisors[router_index]))
+					}
+					_ => panic!("Server is not connected to router"),
+				};
+				ServerRail{
+					port,
+					router_status,
+					frequency_divisor,
+					stored_phits:VecDeque::new(),
+					outcoming_virtual_channel: None,
 				}
-				_ => panic!("Server is not connected to router"),
-			};
+			}).collect();
 			Server{
 				index,
-				port,
-				router_status,
+				rails,
+				next_rail: 0,
 				stored_messages:VecDeque::new(),
 				stored_packets:VecDeque::new(),
-				stored_phits:VecDeque::new(),
-				outcoming_virtual_channel: None,
 				consumed_phits: BTreeMap::new(),
 				statistics: ServerStatistics::new(statistics_temporal_step),
+				nic: nic.clone(),
 			}
 		}).collect();
-		let traffic=new_traffic(TrafficBuilderArgument{
+		let traffic=try_new_traffic(TrafficBuilderArgument{
 			cv:traffic,
 			plugs,
 			topology:topology.as_ref(),
 			rng:&mut rng,
-		});
+		})?;
 		let num_tasks = traffic.number_tasks();
 		if num_tasks != num_servers
 		{
 			println!("WARNING: Generating traffic over {} tasks when the topology has {} servers.",num_tasks,num_servers);
 		}
-		let statistics=Statistics::new(statistics_temporal_step, statistics_server_percentiles, statistics_packet_percentiles, statistics_packet_definitions, statistics_message_definitions, temporal_defined_statistics, topology.as_ref());
-		Simulation{
+		let task_rngs = if task_random_streams
+		{
+			Some( (0..num_tasks.max(num_servers)).map(|task|SimulationMut::new_task_rng(seed,task)).collect() )
+		} else { None };
+		if statistics_temporal_file.is_some() && statistics_temporal_step==0
+		{
+			return Err(cv.ill("statistics_temporal_file requires a positive statistics_temporal_step."));
+		}
+		let temporal_statistics_stream = statistics_temporal_file.map(|filename|TemporalStatisticsStream::new(&filename,directory)).transpose()?;
+		let link_traces = statistics_link_traces.map(|cv|LinkTraces::new(cv,topology.as_ref(),directory)).transpose()?;
+		let injection_record = injection_record_file.map(|filename|InjectionRecord::new(&filename,directory)).transpose()?;
+		let global_link_report = statistics_global_links.map(|cv|GlobalLinkReport::new(cv,topology.as_ref())).transpose()?;
+		let energy_model = energy_model.map(|cv|EnergyModel::new(cv,topology.as_ref())).transpose()?;
+		let buffer_occupancy = statistics_buffer_occupancy_step.map(|step|BufferOccupancySeries::new(step,statistics_buffer_occupancy_maximum_samples));
+		let fragmentation = Fragmentation::new(link_classes.iter().map(|link_class|link_class.maximum_packet_size).collect());
+		let congestion_control = congestion_control.map(|cv|new_congestion_control(cv,num_servers,maximum_packet_size)).transpose()?;
+		let mut statistics=Statistics::new(statistics_temporal_step, statistics_server_percentiles, statistics_packet_percentiles, statistics_packet_definitions, statistics_message_definitions, temporal_defined_statistics, topology.as_ref());
+		statistics.packet_trace = packet_trace_file.map(|filename|PacketTrace::new(&filename,directory)).transpose()?;
+		statistics.decision_features = statistics_decision_features;
+		statistics.flow_statistics = statistics_flows;
+		statistics.batch_means = statistics_batch_means.map(|cv|BatchMeans::new(cv,measured)).transpose()?;
+		Ok(Simulation{
 			configuration: cv.clone(),
 			seed,
 			shared: SimulationShared{
@@ -983,49 +1387,279 @@ impl<'a> Simulation<'a>
 					topology,
 					routers,
 					servers,
+					router_groups,
+					router_frequency_divisors,
 				},
 				traffic,
 				routing,
 				link_classes,
 				maximum_packet_size,
 				general_frequency_divisor,
+				network_occupancy: None,
 			},
 			mutable: SimulationMut{
 				rng,
+				task_rngs,
+				dropped_packets: vec![],
 			},
 			warmup,
 			measured,
+			measurement_extension,
+			steady_state_detection,
 			server_queue_size,
+			rail_selection,
+			result_format,
 			event_queue: EventQueue::new(1000),
 			statistics,
+			temporal_statistics_stream,
+			link_traces,
+			injection_record,
+			global_link_report,
+			buffer_occupancy,
+			fragmentation,
+			analytic,
+			congestion_control,
+			lossy,
+			deadlock_watchdog,
+			energy_model,
+			topology_events,
+			analytic_result: None,
+			statistics_link_utilization,
 			launch_configurations,
 			plugs,
 			memory_report_period,
-		}
+			progress_reporters: progress_reporters.unwrap_or_else(||vec![Box::new(ConsoleProgress::default()) as Box<dyn ProgressReporter>]),
+			creation_instant,
+			creation_cpu_time,
+			hooks: hooks::SimulationHooks::default(),
+		})
 	}
 	///Run the simulations until it finishes.
 	pub fn run(&mut self)
 	{
+		if let Some(ref analytic) = self.analytic
+		{
+			self.analytic_result = Some(analytic.estimate(&mut self.shared,&mut self.mutable.rng,self.statistics_link_utilization));
+			return;
+		}
+		let run_instant = Instant::now();
 		self.print_memory_breakdown();
-		self.statistics.print_header();
-		while self.shared.cycle < self.warmup+self.measured
+		for reporter in self.progress_reporters.iter_mut()
+		{
+			reporter.begin(&self.statistics);
+		}
+		while self.shared.cycle < self.warmup || self.continue_measurement()
 		{
-			self.advance();
-			if self.shared.cycle==self.warmup
+			if !self.run_cycle(run_instant)
 			{
-				self.statistics.reset(self.shared.cycle,&mut self.shared.network);
-				self.shared.routing.reset_statistics(self.shared.cycle);
+				break;
 			}
-			if self.shared.traffic.is_finished()
+		}
+		if self.temporal_statistics_stream.is_some()
+		{
+			//Write the last period, even if incomplete.
+			if !self.statistics.temporal_statistics.is_empty()
 			{
-				println!("Traffic consumed before cycle {}",self.shared.cycle);
-				break;
+				self.stream_temporal_sample();
 			}
+			self.temporal_statistics_stream.as_mut().unwrap().flush();
+		}
+		if let Some(traces) = self.link_traces.as_mut()
+		{
+			traces.write(self.shared.cycle);
+		}
+		if let Some(trace) = self.statistics.packet_trace.as_mut()
+		{
+			trace.flush();
+		}
+		if let Some(features) = self.statistics.decision_features.as_mut()
+		{
+			features.flush();
+		}
+		if let Some(record) = self.injection_record.as_mut()
+		{
+			record.flush();
+		}
+		let progress = self.progress(run_instant);
+		for reporter in self.progress_reporters.iter_mut()
+		{
+			reporter.end(&progress);
+		}
+	}
+	///Executes a cycle of [Simulation::run], including the measurements made between cycles. Returns whether the simulation should continue.
+	///`run_instant` is the instant in which the run began, to report the progress.
+	fn run_cycle(&mut self, run_instant:Instant) -> bool
+	{
+		self.advance();
+		if self.progress_reporters.iter().any(|reporter|self.shared.cycle%reporter.period()==0)
+		{
+			let progress = self.progress(run_instant);
+			let cycle = self.shared.cycle;
+			for reporter in self.progress_reporters.iter_mut().filter(|reporter|cycle%reporter.period()==0)
+			{
+				reporter.report(&progress,&self.statistics,&self.shared.network);
+			}
+		}
+		if let Some(detection) = self.steady_state_detection.as_mut()
+		{
+			let num_servers = self.shared.network.servers.len();
+			let consumed_phits = self.statistics.current_measurement.consumed_phits;
+			if self.shared.cycle<=self.warmup
+			{
+				if detection.is_window_cycle(self.shared.cycle)
+				{
+					detection.track_warmup(self.shared.cycle,consumed_phits,num_servers);
+				}
+				if self.shared.cycle==self.warmup
+				{
+					if detection.should_extend_warmup(self.shared.cycle)
+					{
+						self.warmup+=detection.window;
+					}
+					else if !detection.is_reached()
+					{
+						println!("WARNING: the steady state was not reached during the warmup of {} cycles.",self.warmup);
+					}
+				}
+			}
+			else if detection.is_window_cycle(self.shared.cycle-self.warmup)
+			{
+				detection.track_measured(consumed_phits,num_servers);
+			}
+		}
+		if self.shared.cycle==self.warmup
+		{
+			self.statistics.reset(self.shared.cycle,&mut self.shared.network);
+			self.shared.routing.reset_statistics(self.shared.cycle);
+			self.shared.traffic.reset_statistics(self.shared.cycle);
+			if let Some(traces) = self.link_traces.as_mut()
+			{
+				traces.reset(self.shared.cycle);
+			}
+			if let Some(report) = self.global_link_report.as_mut()
+			{
+				report.reset(self.shared.cycle);
+			}
+			if let Some(series) = self.buffer_occupancy.as_mut()
+			{
+				series.reset(self.shared.cycle);
+			}
+			if let Some(energy) = self.energy_model.as_mut()
+			{
+				energy.reset(self.shared.cycle);
+			}
+			for nic in self.shared.network.servers.iter_mut().filter_map(|server|server.nic.as_mut())
+			{
+				nic.reset_statistics();
+			}
+			if let Some(fragmentation) = self.fragmentation.as_mut()
+			{
+				fragmentation.reset();
+			}
+			if let Some(control) = self.congestion_control.as_mut()
+			{
+				control.reset_statistics(self.shared.cycle);
+			}
+			if let Some(lossy) = self.lossy.as_mut()
+			{
+				lossy.reset_statistics();
+			}
+			if let Some(detection) = self.steady_state_detection.as_mut()
+			{
+				detection.reset();
+			}
+		}
+		if let Some(series) = self.buffer_occupancy.as_mut()
+		{
+			if self.shared.cycle>self.warmup && series.is_sample_cycle(self.shared.cycle)
+			{
+				series.sample(&self.shared.network);
+			}
+		}
+		if let Some(extension) = self.measurement_extension.as_mut()
+		{
+			let measured_cycles = self.shared.cycle.saturating_sub(self.warmup);
+			if extension.is_check_cycle(measured_cycles)
+			{
+				let accepted_load = self.statistics.current_measurement.consumed_phits as f64/measured_cycles as f64/self.shared.network.servers.len() as f64;
+				extension.track(accepted_load);
+			}
+		}
+		if self.shared.traffic.is_finished()
+		{
+			println!("Traffic consumed before cycle {}",self.shared.cycle);
+			return false;
+		}
+		if let Some(watchdog) = self.deadlock_watchdog.as_mut()
+		{
+			if watchdog.check(&self.shared)
+			{
+				println!("Simulation ended by the deadlock watchdog at cycle {}",self.shared.cycle);
+				return false;
+			}
+		}
+		true
+	}
+	///The progress of the simulation, running since `run_instant`.
+	fn progress(&self, run_instant:Instant) -> Progress
+	{
+		Progress{
+			cycle: self.shared.cycle,
+			expected_cycles: self.shared.cycle.max(self.warmup+self.measured),
+			elapsed_seconds: run_instant.elapsed().as_secs_f64(),
+		}
+	}
+	///Writes the oldest periodic measurement into `temporal_statistics_stream` and removes it from memory.
+	fn stream_temporal_sample(&mut self)
+	{
+		let step = self.statistics.temporal_step;
+		let num_servers = self.shared.network.servers.len();
+		let measurement = self.statistics.pop_temporal_measurement();
+		let server_measurements : Vec<_> = self.shared.network.servers.iter_mut().map(|server|server.statistics.pop_temporal_measurement()).collect();
+		let values = [
+			measurement.begin_cycle as f64,
+			measurement.created_phits as f64/step as f64/num_servers as f64,
+			measurement.consumed_phits as f64/step as f64/num_servers as f64,
+			measurement.total_message_delay as f64/measurement.consumed_messages as f64,
+			measurement.total_packet_network_delay as f64/measurement.consumed_packets as f64,
+			measures::jain(server_measurements.iter().map(|m|m.created_phits as f64)),
+			measures::jain(server_measurements.iter().map(|m|m.consumed_phits as f64)),
+			measurement.total_packet_hops as f64/measurement.consumed_packets as f64,
+		];
+		self.temporal_statistics_stream.as_mut().expect("there is no stream for temporal statistics").write_sample(&values);
+	}
+	///Whether to keep simulating once the warmup is completed.
+	fn continue_measurement(&self) -> bool
+	{
+		let measured_cycles = self.shared.cycle - self.warmup;
+		match self.measurement_extension
+		{
+			Some(ref extension) => extension.should_continue(measured_cycles,self.measured),
+			None => measured_cycles < self.measured,
 		}
 	}
 	///Execute a single cycle of the simulation.
 	fn advance(&mut self)
 	{
+		if let Some(events) = self.topology_events.as_mut()
+		{
+			match events.apply(self.shared.cycle,self.shared.network.topology.as_mut())
+			{
+				Ok(true) => self.shared.routing.on_topology_change(self.shared.network.topology.as_ref(),&mut self.mutable.rng),
+				Ok(false) => (),
+				Err(error) => panic!("Could not apply the topology events at cycle {}.\n{}",self.shared.cycle,error),
+			}
+		}
+		self.shared.routing.advance_cycle(self.shared.cycle);
+		self.shared.traffic.advance_cycle(self.shared.cycle);
+		if let Some(control) = self.congestion_control.as_mut()
+		{
+			control.advance(self.shared.cycle);
+		}
+		if self.shared.routing.need_global_occupancy()
+		{
+			self.shared.network_occupancy = Some(self.shared.network.routers.iter().map(|router|router.borrow().port_occupancy().unwrap_or_default()).collect());
+		}
 		let mut ievent=0;
 		//println!("Begin advance");
 		//while let Some(event) = self.event_queue.access_begin(ievent)
@@ -1053,19 +1687,50 @@ impl<'a> Simulation<'a>
 					ref new,
 				} =>
 				{
+					if let Some(watchdog) = self.deadlock_watchdog.as_mut()
+					{
+						watchdog.track_movement(self.shared.cycle);
+					}
 					let target_server = phit.packet.message.destination;
-					let (target_location,_link_class)=self.shared.network.topology.server_neighbour(target_server);
-					let target_router=match target_location
+					if let (true,Some(control),&Location::RouterPort{router_index:previous_router,router_port:previous_port}) = (phit.is_begin(),self.congestion_control.as_ref(),previous)
 					{
-						Location::RouterPort{router_index,router_port:_} =>router_index,
-						_ => panic!("The server is not attached to a router"),
-					};
+						//Mark the packet if it comes from a congested output port, either towards another router or towards the server.
+						if let Some(threshold) = control.marking_threshold()
+						{
+							if let Some(occupancy) = self.shared.network.routers[previous_router].borrow().port_occupancy()
+							{
+								if occupancy[previous_port] >= threshold
+								{
+									*phit.packet.congestion_marked.borrow_mut() = true;
+								}
+							}
+						}
+					}
 					match new
 					{
 						&Location::RouterPort{router_index:router,router_port:port} =>
 						{
+							let target_router=self.shared.network.topology.server_router_from(target_server,router);
 							self.statistics.link_statistics[router][port].phit_arrivals+=1;
-							if phit.is_begin() && !self.statistics.packet_defined_statistics_definitions.is_empty()
+							if let Some(traces) = self.link_traces.as_mut()
+							{
+								traces.track(router,port,self.shared.cycle);
+							}
+							if let Some(report) = self.global_link_report.as_mut()
+							{
+								report.track(router,port,phit,self.shared.network.topology.as_ref());
+							}
+							if let Some(energy) = self.energy_model.as_mut()
+							{
+								let (_,link_class) = self.shared.network.topology.neighbour(router,port);
+								energy.track_router_phit(router,link_class,self.shared.cycle);
+							}
+							if let (true,Some(fragmentation)) = (phit.is_begin(),self.fragmentation.as_mut())
+							{
+								let (_,link_class) = self.shared.network.topology.neighbour(router,port);
+								fragmentation.track_hop(phit.packet.size,link_class);
+							}
+							if phit.is_begin() && self.statistics.track_packet_extra()
 							{
 								let mut be = phit.packet.extra.borrow_mut();
 								if be.is_none()
@@ -1091,11 +1756,16 @@ impl<'a> Simulation<'a>
 									*phit.packet.cycle_into_network.borrow_mut() = self.shared.cycle;
 									self.shared.routing.initialize_routing_info(&phit.packet.routing_info, self.shared.network.topology.as_ref(), router, target_router, Some(target_server), &mut self.mutable.rng);
 								},
-								&Location::RouterPort{../*router_index,router_port*/} =>
+								&Location::RouterPort{router_index:previous_router,..} =>
 								{
 									self.statistics.track_phit_hop(phit,self.shared.cycle);
 									if phit.is_begin()
 									{
+										//The `previous_port` is the input port in the previous router; the exit port is the other end of the link.
+										if let (Location::RouterPort{router_port:exit_port,..},_) = self.shared.network.topology.neighbour(router,port)
+										{
+											PacketDecisions::record_taken(&phit.packet,previous_router,exit_port,*phit.virtual_channel.borrow());
+										}
 										phit.packet.routing_info.borrow_mut().hops+=1;
 										self.shared.routing.update_routing_info(&phit.packet.routing_info, self.shared.network.topology.as_ref(), router, port, target_router, Some(target_server), &mut self.mutable.rng);
 									}
@@ -1109,13 +1779,36 @@ impl<'a> Simulation<'a>
 							{
 								panic!("Packet reached wrong server, {} instead of {}!\n",server,phit.packet.message.destination);
 							}
-							self.shared.network.servers[server].consume(phit.clone(),self.shared.traffic.deref_mut(),&mut self.statistics,self.shared.cycle,self.shared.network.topology.as_ref(),&mut self.mutable.rng);
+							if let Some(energy) = self.energy_model.as_mut()
+							{
+								let (_,link_class) = self.shared.network.topology.server_neighbour(server);
+								energy.track_server_phit(link_class);
+							}
+							//The copies of a packet other than the one accepted by the destination are discarded.
+							let cycle = self.shared.cycle;
+							if self.lossy.as_mut().map_or(true,|lossy|lossy.phit_arrived(phit,cycle))
+							{
+								if phit.is_end()
+								{
+									self.hooks.packet_consumed(&phit.packet,self.shared.cycle);
+									if let Some(control) = self.congestion_control.as_mut()
+									{
+										control.packet_consumed(&phit.packet,self.shared.cycle);
+									}
+								}
+								self.shared.network.servers[server].consume(phit.clone(),self.shared.traffic.deref_mut(),&mut self.statistics,self.shared.cycle,self.shared.network.topology.as_ref(),self.mutable.task_rng(server));
+							}
+							else if phit.is_end()
+							{
+								phit.packet.destroy();//See the notes on the raw_packet feature.
+							}
 						}
 						&Location::None => panic!("Phit went nowhere previous={:?}",previous),
 					};
 				},
 				Event::Acknowledge{
 					location,
+					receptor,
 					//virtual_channel,
 					message: ack_message,
 				} => match location
@@ -1131,7 +1824,7 @@ impl<'a> Simulation<'a>
 							self.event_queue.enqueue(event);
 						}
 					},
-					Location::ServerPort(server) => self.shared.network.servers[server].router_status.acknowledge(ack_message),
+					Location::ServerPort(server) => self.shared.network.servers[server].rail_at(&receptor).router_status.acknowledge(ack_message),
 					//&Location::ServerPort(server) => TransmissionFromServer::acknowledge(self.shared.network.servers[server].router_status,ack_message),
 					_ => (),
 				},
@@ -1194,149 +1887,227 @@ impl<'a> Simulation<'a>
 		}
 		//println!("Done cycle-end events");
 		let num_servers=self.shared.network.servers.len();
+		let cycle=self.shared.cycle;
+		if !self.mutable.dropped_packets.is_empty() && self.lossy.is_none()
+		{
+			println!("WARNING: the routers are dropping packets at cycle {} but there is no `lossy` to retransmit them.",cycle);
+			self.lossy = Some(Lossy::default());
+		}
+		if let Some(lossy) = self.lossy.as_mut()
+		{
+			for packet in self.mutable.dropped_packets.drain(..)
+			{
+				lossy.packet_dropped(packet);
+			}
+			//The retransmissions go first in their servers, in the order in which they become due.
+			for packet in lossy.due_retransmissions(cycle).into_iter().rev()
+			{
+				if let Some(control) = self.congestion_control.as_mut()
+				{
+					control.packet_lost(&packet,cycle);
+				}
+				let server = &mut self.shared.network.servers[packet.message.origin];
+				server.stored_packets.push_front(packet.into_ref());
+			}
+		}
 		for (iserver,server) in self.shared.network.servers.iter_mut().enumerate()
 		{
 			//println!("credits of {} = {}",iserver,server.credits);
-			if let (Location::RouterPort{router_index: index,router_port: port},link_class)=server.port
+			if let Some(nic) = server.nic.as_mut()
 			{
-				if self.shared.traffic.should_generate(iserver,self.shared.cycle,&mut self.mutable.rng)
-				{
-					if server.stored_messages.len()<self.server_queue_size {
-						match self.shared.traffic.generate_message(iserver,self.shared.cycle,self.shared.network.topology.as_ref(),&mut self.mutable.rng)
+				nic.begin_cycle();
+			}
+			if self.shared.traffic.should_generate(iserver,self.shared.cycle,self.mutable.task_rng(iserver))
+			{
+				if server.stored_messages.len()<self.server_queue_size {
+					match self.shared.traffic.generate_message(iserver,self.shared.cycle,self.shared.network.topology.as_ref(),self.mutable.task_rng(iserver))
+					{
+						Ok(message) =>
 						{
-							Ok(message) =>
+							if message.destination>=num_servers
 							{
-								if message.destination>=num_servers
-								{
-									panic!("Message sent to outside the network unexpectedly. destination={destination}",destination=message.destination);
-								}
-								if message.destination==iserver
-								{
-									panic!("Generated message to self unexpectedly.");
-								}
-								server.stored_messages.push_back(message);
-							},
-							Err(TrafficError::OriginOutsideTraffic) => (),
-							Err(TrafficError::SelfMessage) => (),
-							//Err(error) => panic!("An error happened when generating traffic: {:?}",error),
-						};
-					} else {
-						//There is no space in the server queue of messages.
-						server.statistics.track_missed_generation(self.shared.cycle);
-					}
+								panic!("Message sent to outside the network unexpectedly. destination={destination}",destination=message.destination);
+							}
+							if message.destination==iserver
+							{
+								panic!("Generated message to self unexpectedly.");
+							}
+							self.hooks.message_generated(&message,self.shared.cycle);
+							if let Some(record) = self.injection_record.as_mut()
+							{
+								record.write_message(&message,self.shared.cycle);
+							}
+							server.stored_messages.push_back(message);
+						},
+						Err(TrafficError::OriginOutsideTraffic) => (),
+						Err(TrafficError::SelfMessage) => (),
+						//Err(error) => panic!("An error happened when generating traffic: {:?}",error),
+					};
+				} else {
+					//There is no space in the server queue of messages.
+					server.statistics.track_missed_generation(self.shared.cycle);
+				}
+			}
+			if server.stored_packets.is_empty() && !server.stored_messages.is_empty()
+			{
+				let message=server.stored_messages.pop_front().expect("There are not messages in queue");
+				if let Some(nic) = server.nic.as_mut()
+				{
+					nic.setup_message(self.shared.cycle);
 				}
-				if server.stored_packets.is_empty() && !server.stored_messages.is_empty()
+				let mut size=message.size;
+				let mut index_packet=0;
+				let maximum_packet_size=match self.fragmentation.as_mut()
 				{
-					let message=server.stored_messages.pop_front().expect("There are not messages in queue");
-					let mut size=message.size;
-					let mut index_packet=0;
-					while size>0
+					Some(fragmentation) =>
 					{
-						let ps=if size>self.shared.maximum_packet_size
-						{
-							self.shared.maximum_packet_size
-						}
-						else
-						{
-							size
-						};
-						let mut routing_info = RoutingInfo::new();
-						routing_info.source_server = Some(iserver);
-						server.stored_packets.push_back(Packet{
-							size:ps,
-							routing_info: RefCell::new(routing_info),
-							message:message.clone(),
-							index:index_packet,
-							cycle_into_network:RefCell::new(0),
-							extra: RefCell::new(None),
-						}.into_ref());
-						index_packet+=1;
-						size-=ps;
+						let global_size=self.shared.maximum_packet_size;
+						let packet_size=fragmentation.packet_size(iserver,message.destination,self.shared.network.topology.as_ref()).map_or(global_size,|limit|limit.min(global_size));
+						fragmentation.track_message(message.size,packet_size,global_size);
+						packet_size
+					},
+					None => self.shared.maximum_packet_size,
+				};
+				while size>0
+				{
+					let ps=if size>maximum_packet_size
+					{
+						maximum_packet_size
 					}
+					else
+					{
+						size
+					};
+					let mut routing_info = RoutingInfo::new();
+					routing_info.source_server = Some(iserver);
+					routing_info.hints = message.routing_hints.clone();
+					let sampled = self.statistics.decision_features.as_mut().map_or(false,|features|features.sample());
+					server.stored_packets.push_back(Packet{
+						size:ps,
+						routing_info: RefCell::new(routing_info),
+						message:message.clone(),
+						index:index_packet,
+						transmission:0,
+						cycle_into_network:RefCell::new(0),
+						extra: RefCell::new(None),
+						congestion_marked: RefCell::new(false),
+						decisions: RefCell::new(if sampled { Some(PacketDecisions::default()) } else { None }),
+					}.into_ref());
+					index_packet+=1;
+					size-=ps;
 				}
-				if server.stored_phits.is_empty() && !server.stored_packets.is_empty()
+			}
+			//Each free rail takes the next packet, in the order given by the `rail_selection`.
+			let num_rails=server.rails.len();
+			while !server.stored_packets.is_empty() && server.nic.as_ref().map_or(true,|nic|nic.is_ready(cycle))
+			{
+				let rail_index=match self.rail_selection.select(server,self.mutable.task_rng(iserver))
+				{
+					Some(rail_index) => rail_index,
+					None => break,
+				};
+				if let Some(control) = self.congestion_control.as_mut()
 				{
-					let packet=server.stored_packets.pop_front().expect("There are not packets in queue");
-					for index in 0..packet.size
+					let packet=server.stored_packets.front().expect("There are not packets in queue");
+					if !control.can_inject(iserver,packet)
 					{
-						server.stored_phits.push_back(Rc::new(Phit{
-							packet:packet.clone(),
-							index,
-							virtual_channel: RefCell::new(None),
-						}));
+						break;
 					}
+					control.packet_injected(iserver,packet,self.shared.cycle);
+				}
+				let packet=server.stored_packets.pop_front().expect("There are not packets in queue");
+				if let Some(lossy) = self.lossy.as_mut()
+				{
+					lossy.packet_injected(&packet,self.shared.cycle);
 				}
-				//if server.stored_phits.len()>0 && server.credits>0
-				//{
-				//	let phit=server.stored_phits.pop_front().expect("There are not phits");
-				//	let event=Event::PhitToLocation{
-				//		phit,
-				//		previous: Location::ServerPort(iserver),
-				//		new: Location::RouterPort{router_index:index,router_port:port},
-				//	};
-				//	self.statistics.created_phits+=1;
-				//	server.statistics.created_phits+=1;
-				//	self.event_queue.enqueue_begin(event,self.link_classes[link_class].delay);
-				//	server.credits-=1;
-				//}
-				if !server.stored_phits.is_empty()
+				let rail=&mut server.rails[rail_index];
+				for index in 0..packet.size
+				{
+					rail.stored_phits.push_back(Rc::new(Phit{
+						packet:packet.clone(),
+						index,
+						virtual_channel: RefCell::new(None),
+					}));
+				}
+				server.next_rail=(rail_index+1)%num_rails;
+			}
+			//if server.stored_phits.len()>0 && server.credits>0
+			//{
+			//	let phit=server.stored_phits.pop_front().expect("There are not phits");
+			//	let event=Event::PhitToLocation{
+			//		phit,
+			//		previous: Location::ServerPort(iserver),
+			//		new: Location::RouterPort{router_index:index,router_port:port},
+			//	};
+			//	self.statistics.created_phits+=1;
+			//	server.statistics.created_phits+=1;
+			//	self.event_queue.enqueue_begin(event,self.link_classes[link_class].delay);
+			//	server.credits-=1;
+			//}
+			for rail in server.rails.iter_mut()
+			{
+				let (index,port,link_class)=match rail.port
+				{
+					(Location::RouterPort{router_index,router_port},link_class) => (router_index,router_port,link_class),
+					_ => panic!("Where goes this port?"),
+				};
+				if !rail.stored_phits.is_empty()
 				{
 					//Do not extract the phit until we know whether we can transmit it.
-					let phit=server.stored_phits.front().expect("There are not phits");
-					if let None = server.outcoming_virtual_channel
+					let phit=rail.stored_phits.front().expect("There are not phits");
+					if let None = rail.outcoming_virtual_channel
 					{
 						// Try to assign one
 						assert!(phit.is_begin(),"Not VC assigned for server--router while transmitting a middle phit.");
-						let status = &server.router_status;
+						let status = &rail.router_status;
 						for vc in  0..status.num_virtual_channels()
 						{
 							if status.can_transmit(phit,vc)
 							{
-								server.outcoming_virtual_channel = Some(vc);
+								rail.outcoming_virtual_channel = Some(vc);
 								break;
 							}
 						}
 					}
-					// if self.shared.is_link_cycle(link_class) // XXX we cannot call this since we are mutating the servers.
-					if self.shared.cycle % self.shared.link_classes[link_class].frequency_divisor == 0
+					// if self.shared.is_port_cycle(index,port) // XXX we cannot call this since we are mutating the servers.
+					if self.shared.cycle % rail.frequency_divisor == 0
 					{
-						if let Some(vc) = server.outcoming_virtual_channel
+						if let Some(vc) = rail.outcoming_virtual_channel
 						{
-							if server.router_status.can_transmit(phit,vc)
+							if rail.router_status.can_transmit(phit,vc) && server.nic.as_mut().map_or(true,|nic|nic.take_token())
 							{
-								let phit=server.stored_phits.pop_front().expect("There are not phits");
+								let phit=rail.stored_phits.pop_front().expect("There are not phits");
 								*phit.virtual_channel.borrow_mut() = Some(vc);
 								if phit.is_end()
 								{
-									server.outcoming_virtual_channel = None;
+									rail.outcoming_virtual_channel = None;
 								}
+								let new = Location::RouterPort{router_index:index,router_port:port};
+								let message = phit.packet.message.clone();
+								let delay = self.shared.link_classes[link_class].phit_delay(self.shared.cycle,&phit,&new,self.shared.network.topology.as_ref());
 								let event=Event::PhitToLocation{
 									phit,
 									previous: Location::ServerPort(iserver),
-									new: Location::RouterPort{router_index:index,router_port:port},
+									new,
 								};
 								//self.statistics.created_phits+=1;
-								self.statistics.track_created_phit(self.shared.cycle);
+								self.statistics.track_created_phit(self.shared.cycle,&message);
 								server.statistics.track_created_phit(self.shared.cycle);
-								self.event_queue.enqueue_begin(event,self.shared.link_classes[link_class].delay);
-								server.router_status.notify_outcoming_phit(vc,self.shared.cycle);
+								self.event_queue.enqueue_begin(event,delay);
+								rail.router_status.notify_outcoming_phit(vc,self.shared.cycle);
 							}
 						}
 					}
 				}
 			}
-			else
-			{
-				panic!("Where goes this port?");
-			}
 		}
 		//println!("Done generation");
 		self.event_queue.advance();
+		self.hooks.cycle_end(&self.shared);
 		self.shared.cycle+=1;
-		if self.shared.cycle%1000==0
+		if self.temporal_statistics_stream.is_some() && self.shared.cycle%self.statistics.temporal_step==0
 		{
-			//println!("Statistics up to cycle {}: {:?}",self.shared.cycle,self.statistics);
-			self.statistics.print(self.shared.cycle,&self.shared.network);
+			self.stream_temporal_sample();
 		}
 		if let Some(period) = self.memory_report_period
 		{
@@ -1346,9 +2117,97 @@ impl<'a> Simulation<'a>
 			}
 		}
 	}
+	///Get the results of the simulation as a typed [SimulationResults](results::SimulationResults).
+	///This contains the main quantities of [Simulation::get_simulation_results] in a form more convenient for programs embedding the simulator.
+	pub fn results(&self) -> results::SimulationResults
+	{
+		let measurement = &self.statistics.current_measurement;
+		let cycles=self.shared.cycle-measurement.begin_cycle;
+		let num_servers=self.shared.network.servers.len();
+		let topology = self.shared.network.topology.as_ref();
+		let total_arrivals:usize = (0..topology.num_routers()).map(|i|(0..topology.degree(i)).map(|j|self.statistics.link_statistics[i][j].phit_arrivals).sum::<usize>()).sum();
+		let total_links: usize = (0..topology.num_routers()).map(|i|topology.degree(i)).sum();
+		let maximum_arrivals:usize = self.statistics.link_statistics.iter().map(|rls|rls.iter().map(|ls|ls.phit_arrivals).max().unwrap()).max().unwrap();
+		let link_utilization = (0..topology.num_routers()).map(|i|(0..topology.degree(i)).map(|j|self.statistics.link_statistics[i][j].phit_arrivals as f64 / cycles as f64).collect()).collect();
+		let class_results = |m:&measures::ClassMeasurement|results::ClassResults{
+			injected_load: m.created_phits as f64/cycles as f64/num_servers as f64,
+			accepted_load: m.consumed_phits as f64/cycles as f64/num_servers as f64,
+			average_message_delay: m.total_message_delay as f64/m.consumed_messages as f64,
+			average_packet_network_delay: m.total_packet_network_delay as f64/m.consumed_packets as f64,
+		};
+		let servers = self.shared.network.servers.iter().map(|server|{
+			let server_measurement = &server.statistics.current_measurement;
+			results::ServerResults{
+				injected_load: server_measurement.created_phits as f64/cycles as f64,
+				accepted_load: server_measurement.consumed_phits as f64/cycles as f64,
+				average_message_delay: server_measurement.total_message_delay as f64/server_measurement.consumed_messages as f64,
+				cycle_last_created_phit: server.statistics.cycle_last_created_phit,
+				cycle_last_consumed_message: server.statistics.cycle_last_consumed_message,
+				missed_generations: server_measurement.missed_generations,
+			}
+		}).collect();
+		let temporal = if self.temporal_statistics_stream.is_none() && self.statistics.temporal_step > 0
+		{
+			let step = self.statistics.temporal_step;
+			let series = |f:&dyn Fn(&measures::StatisticMeasurement)->f64| self.statistics.temporal_statistics.iter().map(f).collect::<Vec<f64>>();
+			Some(results::TemporalResults{
+				step,
+				injected_load: series(&|m|m.created_phits as f64/step as f64/num_servers as f64),
+				accepted_load: series(&|m|m.consumed_phits as f64/step as f64/num_servers as f64),
+				average_message_delay: series(&|m|m.total_message_delay as f64/m.consumed_messages as f64),
+				average_packet_network_delay: series(&|m|m.total_packet_network_delay as f64/m.consumed_packets as f64),
+				average_packet_hops: series(&|m|m.total_packet_hops as f64/m.consumed_packets as f64),
+				server_generation_jain_index: self.shared.network.temporal_jain_server_created_phits(),
+				server_consumption_jain_index: self.shared.network.temporal_jain_server_consumed_phits(),
+				virtual_channel_usage: self.statistics.temporal_statistics.iter().map(|m|
+					m.virtual_channel_usage.iter().map(|&count|count as f64/step as f64/total_links as f64).collect()
+				).collect(),
+			})
+		}
+		else
+		{
+			None
+		};
+		results::SimulationResults{
+			cycle: self.shared.cycle,
+			measured_cycles: cycles,
+			injected_load: measurement.created_phits as f64/cycles as f64/num_servers as f64,
+			accepted_load: measurement.consumed_phits as f64/cycles as f64/num_servers as f64,
+			average_message_delay: measurement.total_message_delay as f64/measurement.consumed_messages as f64,
+			average_packet_network_delay: measurement.total_packet_network_delay as f64/measurement.consumed_packets as f64,
+			server_generation_jain_index: self.shared.network.jain_server_created_phits(),
+			server_consumption_jain_index: self.shared.network.jain_server_consumed_phits(),
+			average_packet_hops: measurement.total_packet_hops as f64 / measurement.consumed_packets as f64,
+			total_packet_per_hop_count: measurement.total_packet_per_hop_count.clone(),
+			average_link_utilization: total_arrivals as f64 / cycles as f64 / total_links as f64,
+			maximum_link_utilization: maximum_arrivals as f64 / cycles as f64,
+			link_utilization,
+			virtual_channel_usage: measurement.virtual_channel_usage.iter().map(|&count|count as f64 / cycles as f64 / total_links as f64).collect(),
+			classes: measurement.class_measurements.iter().map(class_results).collect(),
+			sub_traffics: measurement.sub_traffic_measurements.iter().map(class_results).collect(),
+			sub_traffic_names: self.shared.traffic.sub_traffic_names(),
+			servers,
+			temporal,
+			routing_statistics: self.shared.routing.statistics(self.shared.cycle),
+			traffic_statistics: self.shared.traffic.statistics(self.shared.cycle),
+			router_statistics: self.router_statistics(),
+			stall_cycles: self.shared.network.routers.iter().fold(None,|maybe_counts:Option<StallCounts>,router|{
+				match (maybe_counts,router.borrow().stall_counts())
+				{
+					(Some(mut total),Some(counts)) => { total.accumulate(&counts); Some(total) },
+					(total,counts) => total.or(counts),
+				}
+			}),
+			resource_usage: self.resource_usage(),
+		}
+	}
 	///Get config value for the simulation results.
 	pub fn get_simulation_results(&self) -> ConfigurationValue
 	{
+		if let Some(ref result) = self.analytic_result
+		{
+			return result.clone();
+		}
 		// https://stackoverflow.com/questions/22355273/writing-to-a-file-or-stdout-in-rust
 		//output.write(b"Hello from the simulator\n").unwrap();
 		//Result
@@ -1356,31 +2215,25 @@ impl<'a> Simulation<'a>
 		//	accepted_load: 0.9,
 		//	average_message_delay: 100,
 		//}
-		let measurement = &self.statistics.current_measurement;
-		let cycles=self.shared.cycle-measurement.begin_cycle;
+		let results = self.results();
+		let cycles = results.measured_cycles;
 		let num_servers=self.shared.network.servers.len();
-		let injected_load=measurement.created_phits as f64/cycles as f64/num_servers as f64;
-		let accepted_load=measurement.consumed_phits as f64/cycles as f64/num_servers as f64;
-		let average_message_delay=measurement.total_message_delay as f64/measurement.consumed_messages as f64;
-		let average_packet_network_delay=measurement.total_packet_network_delay as f64/measurement.consumed_packets as f64;
-		let jscp=self.shared.network.jain_server_consumed_phits();
-		let jsgp=self.shared.network.jain_server_created_phits();
-		let average_packet_hops=measurement.total_packet_hops as f64 / measurement.consumed_packets as f64;
-		let total_packet_per_hop_count=measurement.total_packet_per_hop_count.iter().map(|&count|ConfigurationValue::Number(count as f64)).collect();
-		//let total_arrivals:usize = self.statistics.link_statistics.iter().map(|rls|rls.iter().map(|ls|ls.phit_arrivals).sum::<usize>()).sum();
-		//let total_links:usize = self.statistics.link_statistics.iter().map(|rls|rls.len()).sum();
-		let total_arrivals:usize = (0..self.shared.network.topology.num_routers()).map(|i|(0..self.shared.network.topology.degree(i)).map(|j|self.statistics.link_statistics[i][j].phit_arrivals).sum::<usize>()).sum();
+		let injected_load = results.injected_load;
+		let accepted_load = results.accepted_load;
+		let average_message_delay = results.average_message_delay;
+		let average_packet_network_delay = results.average_packet_network_delay;
+		let jscp = results.server_consumption_jain_index;
+		let jsgp = results.server_generation_jain_index;
+		let average_packet_hops = results.average_packet_hops;
+		let total_packet_per_hop_count=results.total_packet_per_hop_count.iter().map(|&count|ConfigurationValue::Number(count as f64)).collect();
 		let total_links: usize = (0..self.shared.network.topology.num_routers()).map(|i|self.shared.network.topology.degree(i)).sum();
-		let average_link_utilization = total_arrivals as f64 / cycles as f64 / total_links as f64;
-		let maximum_arrivals:usize = self.statistics.link_statistics.iter().map(|rls|rls.iter().map(|ls|ls.phit_arrivals).max().unwrap()).max().unwrap();
-		let maximum_link_utilization = maximum_arrivals as f64 / cycles as f64;
+		let average_link_utilization = results.average_link_utilization;
+		let maximum_link_utilization = results.maximum_link_utilization;
 		let server_average_cycle_last_created_phit : f64 = (self.shared.network.servers.iter().map(|s|s.statistics.cycle_last_created_phit).sum::<Time>() as f64)/(self.shared.network.servers.len() as f64);
 		let server_average_cycle_last_consumed_message : f64 = (self.shared.network.servers.iter().map(|s|s.statistics.cycle_last_consumed_message).sum::<Time>() as f64)/(self.shared.network.servers.len() as f64);
 		let server_average_missed_generations : f64 = (self.shared.network.servers.iter().map(|s|s.statistics.current_measurement.missed_generations).sum::<usize>() as f64)/(self.shared.network.servers.len() as f64);
 		let servers_with_missed_generations : usize = self.shared.network.servers.iter().map(|s|if s.statistics.current_measurement.missed_generations > 0 {1} else {0}).sum::<usize>();
-		let virtual_channel_usage: Vec<_> =measurement.virtual_channel_usage.iter().map(|&count|
-			ConfigurationValue::Number(count as f64 / cycles as f64 / total_links as f64)
-		).collect();
+		let virtual_channel_usage: Vec<_> = results.virtual_channel_usage.iter().map(|&usage|ConfigurationValue::Number(usage)).collect();
 		let git_id=get_git_id();
 		let version_number = get_version_number();
 		let mut result_content = vec![
@@ -1403,15 +2256,61 @@ impl<'a> Simulation<'a>
 			//(String::from("git_id"),ConfigurationValue::Literal(format!("\"{}\"",git_id))),
 			(String::from("git_id"),ConfigurationValue::Literal(git_id.to_string())),
 			(String::from("version_number"),ConfigurationValue::Literal(version_number.to_string())),
+			(String::from("random_seed"),ConfigurationValue::Number(self.seed as f64)),
 		];
-		if let Some(content)=self.shared.routing.statistics(self.shared.cycle)
+		if results.classes.len() > 1
+		{
+			let class_statistics = results.classes.iter().enumerate().map(|(class,class_results)|ConfigurationValue::Object(String::from("ClassStatistics"),vec![
+				(String::from("class"),ConfigurationValue::Number(class as f64)),
+				(String::from("injected_load"),ConfigurationValue::Number(class_results.injected_load)),
+				(String::from("accepted_load"),ConfigurationValue::Number(class_results.accepted_load)),
+				(String::from("average_message_delay"),ConfigurationValue::Number(class_results.average_message_delay)),
+				(String::from("average_packet_network_delay"),ConfigurationValue::Number(class_results.average_packet_network_delay)),
+			])).collect();
+			result_content.push((String::from("class_statistics"),ConfigurationValue::Array(class_statistics)));
+		}
+		if !results.sub_traffics.is_empty()
+		{
+			let sub_traffic_statistics = results.sub_traffics.iter().enumerate().map(|(index,sub_results)|{
+				let mut content = vec![(String::from("index"),ConfigurationValue::Number(index as f64))];
+				if let Some(name) = results.sub_traffic_names.get(index)
+				{
+					content.push((String::from("name"),ConfigurationValue::Literal(name.clone())));
+				}
+				content.extend([
+					(String::from("injected_load"),ConfigurationValue::Number(sub_results.injected_load)),
+					(String::from("accepted_load"),ConfigurationValue::Number(sub_results.accepted_load)),
+					(String::from("average_message_delay"),ConfigurationValue::Number(sub_results.average_message_delay)),
+					(String::from("average_packet_network_delay"),ConfigurationValue::Number(sub_results.average_packet_network_delay)),
+				]);
+				ConfigurationValue::Object(String::from("SubTrafficStatistics"),content)
+			}).collect();
+			result_content.push((String::from("sub_traffic_statistics"),ConfigurationValue::Array(sub_traffic_statistics)));
+		}
+		if let Some(content)=results.routing_statistics
 		{
 			result_content.push((String::from("routing_statistics"),content));
 		}
-		if let Some(content) = self.shared.network.routers.iter().enumerate().fold(None,|maybe_stat,(index,router)|router.borrow().aggregate_statistics(maybe_stat,index,self.shared.network.routers.len(),self.shared.cycle))
+		if let Some(ref extension) = self.measurement_extension
+		{
+			result_content.push((String::from("measurement_extension"),extension.result(self.shared.cycle.saturating_sub(self.warmup))));
+		}
+		if let Some(ref detection) = self.steady_state_detection
+		{
+			result_content.push((String::from("steady_state"),detection.result(self.warmup)));
+		}
+		if let Some(content)=results.traffic_statistics
+		{
+			result_content.push((String::from("traffic_extra_statistics"),content));
+		}
+		if let Some(content)=results.router_statistics
 		{
 			result_content.push((String::from("router_aggregated_statistics"),content));
 		}
+		if let Some(counts)=results.stall_cycles
+		{
+			result_content.push((String::from("stall_cycles"),counts.to_config_value()));
+		}
 		if let Ok(linux_process) = procfs::process::Process::myself()
 		{
 			let status = linux_process.status().expect("failed to get status of the self process");
@@ -1425,7 +2324,84 @@ impl<'a> Simulation<'a>
 			result_content.push((String::from("user_time"),ConfigurationValue::Number(stat.utime as f64/tps)));
 			result_content.push((String::from("system_time"),ConfigurationValue::Number(stat.stime as f64/tps)));
 		}
-		if self.statistics.temporal_step > 0
+		let usage = results.resource_usage;
+		result_content.push((String::from("wall_time"),ConfigurationValue::Number(usage.wall_time)));
+		if let Some(cpu_time) = usage.cpu_time
+		{
+			result_content.push((String::from("cpu_time"),ConfigurationValue::Number(cpu_time)));
+		}
+		if let Some(ref traces) = self.link_traces
+		{
+			result_content.push((String::from("link_traces"),traces.result()));
+		}
+		if let Some(ref report) = self.global_link_report
+		{
+			result_content.push((String::from("global_links"),report.result(self.shared.cycle)));
+		}
+		if let Some(ref series) = self.buffer_occupancy
+		{
+			result_content.push((String::from("buffer_occupancy"),series.result()));
+		}
+		if let Some(ref fragmentation) = self.fragmentation
+		{
+			result_content.push((String::from("fragmentation"),fragmentation.result()));
+		}
+		if let Some(ref control) = self.congestion_control
+		{
+			result_content.push((String::from("congestion_control"),control.result(self.shared.cycle)));
+		}
+		if let Some(ref lossy) = self.lossy
+		{
+			result_content.push((String::from("lossy"),lossy.result()));
+		}
+		if self.shared.network.servers.iter().any(|server|server.nic.is_some())
+		{
+			result_content.push((String::from("nic"),Nic::result(self.shared.network.servers.iter().filter_map(|server|server.nic.as_ref()))));
+		}
+		if let Some(ref energy) = self.energy_model
+		{
+			result_content.push((String::from("energy"),energy.result(self.shared.cycle)));
+		}
+		if let Some(ref events) = self.topology_events
+		{
+			result_content.push((String::from("topology_events"),ConfigurationValue::Number(events.applied() as f64)));
+		}
+		if let Some(diagnosis) = self.deadlock_watchdog.as_ref().and_then(|watchdog|watchdog.result())
+		{
+			result_content.push((String::from("deadlock"),diagnosis));
+		}
+		if self.statistics_link_utilization
+		{
+			let link_utilization = results.link_utilization.iter().map(|router_links|
+				ConfigurationValue::Array(router_links.iter().map(|&utilization|ConfigurationValue::Number(utilization)).collect())
+			).collect();
+			result_content.push((String::from("link_utilization"),ConfigurationValue::Array(link_utilization)));
+		}
+		if let Some(ref trace) = self.statistics.packet_trace
+		{
+			result_content.push((String::from("packet_trace"),trace.result()));
+		}
+		if let Some(ref features) = self.statistics.decision_features
+		{
+			result_content.push((String::from("decision_features"),features.result()));
+		}
+		if let Some(ref flows) = self.statistics.flow_statistics
+		{
+			result_content.push((String::from("flows"),flows.result()));
+		}
+		if let Some(ref batches) = self.statistics.batch_means
+		{
+			result_content.push((String::from("batch_means"),batches.result(self.shared.cycle,self.shared.network.servers.len())));
+		}
+		if let Some(ref record) = self.injection_record
+		{
+			result_content.push((String::from("injection_record"),record.result()));
+		}
+		if let Some(ref stream) = self.temporal_statistics_stream
+		{
+			result_content.push((String::from("temporal_statistics"),stream.result()));
+		}
+		else if self.statistics.temporal_step > 0
 		{
 			let step = self.statistics.temporal_step;
 			let samples = self.statistics.temporal_statistics.len();
@@ -1651,6 +2627,36 @@ impl<'a> Simulation<'a>
 		ConfigurationValue::Object(String::from("Result"),result_content)
 	}
 
+	///The statistics of the routers, aggregated over all of them or over each of their `RouterGroup`s.
+	fn router_statistics(&self) -> Option<ConfigurationValue>
+	{
+		let network = &self.shared.network;
+		if network.router_groups.is_empty()
+		{
+			return network.routers.iter().enumerate().fold(None,|maybe_stat,(index,router)|router.borrow().aggregate_statistics(maybe_stat,index,network.routers.len(),self.shared.cycle));
+		}
+		let num_groups = network.router_groups.iter().max().map(|&max|max+1).unwrap_or(0);
+		let groups = (0..num_groups).map(|group|{
+			let routers:Vec<_> = network.routers.iter().zip(network.router_groups.iter()).filter(|&(_,&router_group)|router_group==group).map(|(router,_)|router).collect();
+			routers.iter().enumerate().fold(None,|maybe_stat,(index,router)|router.borrow().aggregate_statistics(maybe_stat,index,routers.len(),self.shared.cycle)).unwrap_or(ConfigurationValue::None)
+		}).collect();
+		Some(ConfigurationValue::Array(groups))
+	}
+
+	///The resources employed by the simulation since its creation.
+	pub fn resource_usage(&self) -> ResourceUsage
+	{
+		let cpu_time = match (ResourceUsage::process_cpu_time(),self.creation_cpu_time)
+		{
+			(Some(now),Some(before)) => Some(now-before),
+			_ => None,
+		};
+		ResourceUsage{
+			wall_time: self.creation_instant.elapsed().as_secs_f64(),
+			cpu_time,
+			high_water_mark: ResourceUsage::process_high_water_mark(),
+		}
+	}
 	///Write the result of the simulation somewhere, typically to a 'result' file in a 'run*' directory.
 	fn write_result(&self,output:&mut dyn Write)
 	{
@@ -1870,7 +2876,11 @@ impl<'a> Simulation<'a>
 		// }
 		// let result=ConfigurationValue::Object(String::from("Result"),result_content);
 		let result = self.get_simulation_results();
-		writeln!(output,"{}",result).unwrap();
+		match self.result_format
+		{
+			results::ResultFormat::Configuration => writeln!(output,"{}",result).unwrap(),
+			results::ResultFormat::JSON => writeln!(output,"{}",config::config_to_json(&result)).unwrap(),
+		}
 	}
 }
 
@@ -1923,7 +2933,7 @@ impl<'a> Quantifiable for Simulation<'a>
 }
 
 
-#[derive(Default)]
+#[derive(Default,Clone)]
 pub struct Plugs
 {
 	routers: BTreeMap<String, fn(RouterBuilderArgument) -> Rc<RefCell<dyn Router>>  >,
@@ -1934,6 +2944,7 @@ pub struct Plugs
 	patterns: BTreeMap<String, fn(PatternBuilderArgument) -> Box<dyn Pattern> >,
 	policies: BTreeMap<String, fn(VCPolicyBuilderArgument) -> Box<dyn VirtualChannelPolicy> >,
 	allocators: BTreeMap<String, fn(AllocatorBuilderArgument) -> Box<dyn Allocator> >,
+	outputs: BTreeMap<String, fn(OutputBuilderArgument) -> Result<(),Error> >,
 }
 
 impl Plugs
@@ -1966,6 +2977,14 @@ impl Plugs
 	{
 		self.patterns.insert(key,builder);
 	}
+	pub fn add_allocator(&mut self, key:String, builder: fn(AllocatorBuilderArgument) -> Box<dyn Allocator>)
+	{
+		self.allocators.insert(key,builder);
+	}
+	pub fn add_output(&mut self, key:String, builder: fn(OutputBuilderArgument) -> Result<(),Error>)
+	{
+		self.outputs.insert(key,builder);
+	}
 }
 
 impl Debug for Plugs
@@ -1979,16 +2998,57 @@ impl Debug for Plugs
 		write!(f,"{};",self.traffics.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
 		write!(f,"{};",self.patterns.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
 		write!(f,"{};",self.policies.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
+		write!(f,"{};",self.allocators.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
+		write!(f,"{};",self.outputs.keys().map(|s|s.to_string()).collect::<Vec<String>>().join(","))?;
 		Ok(())
 	}
 }
 
-/// Main when passed a configuration file as path
+/// Main when passed a configuration file as path.
 /// `file` must be a configuration file with the experiment to simulate.
 /// `plugs` contains the plugged builder functions.
-/// `result_file` indicates where to write the results.
-/// `free_args` are free arguments. Those of the form `path=value` are used to override configurations.
-pub fn file_main(file:&mut File, plugs:&Plugs, mut results_file:Option<File>,free_args:&[String]) -> Result<(),Error>
+/// `results_file` receives the results of all the experiments, or the standard output if `None`.
+/// `free_args` of the form `path=value` override the configuration.
+/// The experiments are simulated sequentially; see [file_main_with_options] to change it.
+pub fn file_main(file:&mut File, plugs:&Plugs, results_file:Option<File>,free_args:&[String]) -> Result<(),Error>
+{
+	file_main_with_options(file,plugs,results_file,free_args,&FileOptions::default())
+}
+
+///Options for [file_main_with_options].
+#[non_exhaustive]
+#[derive(Default)]
+pub struct FileOptions
+{
+	///The number of experiments to simulate simultaneously in different threads. Results are still written in the order of the experiments.
+	///Both 0 and 1 simulate them sequentially.
+	pub jobs: usize,
+	///The directory in which the simulations create the files they write other than the results, see [Simulation::new_in_directory].
+	///By default empty, which means the current directory.
+	pub directory: PathBuf,
+}
+
+///The fields of the configuration of a simulation that make it write into a file other than the results.
+///The `progress_reporters` only do so when they include a [progress::ProgressFile].
+const OUTPUT_FILE_FIELDS: [&str;6] = ["statistics_temporal_file","statistics_link_traces","packet_trace_file","injection_record_file","statistics_decision_features","progress_reporters"];
+
+///Whether the `field` of the configuration of a simulation makes it write into a file other than the results.
+fn writes_output_file(name:&str, value:&ConfigurationValue) -> bool
+{
+	match name
+	{
+		"progress_reporters" => match value
+		{
+			ConfigurationValue::Array(reporters) => reporters.iter().any(|reporter|matches!(reporter,ConfigurationValue::Object(name,_) if name=="ProgressFile")),
+			_ => false,
+		},
+		_ => OUTPUT_FILE_FIELDS.contains(&name),
+	}
+}
+
+/// Like [file_main], with some `options` on how to simulate the experiments.
+/// Simulating several `jobs` in parallel is rejected when the experiments write into other files, as they would write into the same ones.
+pub fn file_main_with_options(file:&mut File, plugs:&Plugs, mut results_file:Option<File>,free_args:&[String], options:&FileOptions) -> Result<(),Error>
 {
 	let mut contents = String::new();
 	file.read_to_string(&mut contents).expect("something went wrong reading the file");
@@ -2040,10 +3100,24 @@ pub fn file_main(file:&mut File, plugs:&Plugs, mut results_file:Option<File>,fre
 					let flat=flatten_configuration_value(value);
 					if let ConfigurationValue::Experiments(ref experiments)=flat
 					{
+						if options.jobs>1
+						{
+							for (i,experiment) in experiments.iter().enumerate()
+							{
+								if let ConfigurationValue::Object(_,ref fields) = experiment
+								{
+									if let Some((name,_)) = fields.iter().find(|(name,value)|writes_output_file(name,value))
+									{
+										return Err(error!(bad_argument).with_message(format!("The experiment {} sets {}, so the experiments cannot be simulated in parallel jobs, as they would write into the same file.",i,name)));
+									}
+								}
+							}
+							return run_experiments_in_parallel(experiments,plugs,&mut results_file,options);
+						}
 						for (i,experiment) in experiments.iter().enumerate()
 						{
 							println!("experiment {} of {} is {}",i,experiments.len(),experiment.format_terminal());
-							let mut simulation=Simulation::new(experiment,plugs);
+							let mut simulation=Simulation::try_new_in_directory(experiment,plugs,&options.directory).map_err(|e|e.with_message(format!("\nThe experiment {} could not be built.",i)))?;
 							simulation.run();
 							match results_file
 							{
@@ -2054,10 +3128,10 @@ pub fn file_main(file:&mut File, plugs:&Plugs, mut results_file:Option<File>,fre
 					}
 					else
 					{
-						panic!("there are not experiments");
+						return Err(error!(ill_formed_configuration,flat).with_message("there are not experiments".to_string()));
 					}
 				},
-				_ => panic!("Not a value"),
+				_ => return Err(error!(undetermined).with_message("The configuration file does not contain a value".to_string())),
 			};
 		},
 	};
@@ -2065,6 +3139,77 @@ pub fn file_main(file:&mut File, plugs:&Plugs, mut results_file:Option<File>,fre
 }
 
 
+/**
+Simulates the `experiments` using `options.jobs` threads, writing their results in the order of the experiments.
+Each thread takes the next pending experiment and builds its own [Simulation] in `options.directory`, with its own random generators.
+As the configurations are not `Send`, they are given to the threads as text and parsed again.
+**/
+fn run_experiments_in_parallel(experiments:&[ConfigurationValue], plugs:&Plugs, results_file:&mut Option<File>, options:&FileOptions) -> Result<(),Error>
+{
+	use std::sync::{Arc,mpsc};
+	use std::sync::atomic::{self,AtomicUsize};
+	let total = experiments.len();
+	let texts : Arc<Vec<String>> = Arc::new(experiments.iter().map(|experiment|format!("{}",experiment)).collect());
+	let next_experiment = Arc::new(AtomicUsize::new(0));
+	//The experiments that could not be built send the text of their error.
+	let (sender,receiver) = mpsc::channel::<(usize,Result<Vec<u8>,String>)>();
+	let handles : Vec<_> = (0..options.jobs.min(total)).map(|_|{
+		let texts = texts.clone();
+		let directory = options.directory.clone();
+		let next_experiment = next_experiment.clone();
+		let sender = sender.clone();
+		let plugs = plugs.clone();
+		std::thread::spawn(move||loop
+		{
+			let index = next_experiment.fetch_add(1,atomic::Ordering::Relaxed);
+			if index>=texts.len()
+			{
+				break;
+			}
+			let experiment = match config_parser::parse(&texts[index])
+			{
+				Ok(config_parser::Token::Value(value)) => value,
+				_ => panic!("could not parse again the experiment {}",index),
+			};
+			println!("experiment {} of {} is {}",index,texts.len(),experiment.format_terminal());
+			let outcome = Simulation::try_new_in_directory(&experiment,&plugs,&directory).map(|mut simulation|{
+				simulation.run();
+				let mut buffer = vec![];
+				simulation.write_result(&mut buffer);
+				buffer
+			}).map_err(|e|format!("{}",e));
+			if sender.send((index,outcome)).is_err()
+			{
+				break;
+			}
+		})
+	}).collect();
+	drop(sender);
+	//The results finished before some previous one wait here until it is written.
+	let mut pending = BTreeMap::new();
+	let mut next_to_write = 0;
+	for (index,outcome) in receiver
+	{
+		let buffer = outcome.map_err(|text|error!(undetermined).with_message(format!("The experiment {} could not be built.\n{}",index,text)))?;
+		pending.insert(index,buffer);
+		while let Some(buffer) = pending.remove(&next_to_write)
+		{
+			match results_file
+			{
+				Some(ref mut f) => f.write_all(&buffer),
+				None => stdout().write_all(&buffer),
+			}.map_err(|e|error!(file_system_error,e).with_message("could not write the results".to_string()))?;
+			next_to_write+=1;
+		}
+	}
+	let panicked = handles.into_iter().map(|handle|handle.join()).filter(|joined|joined.is_err()).count();
+	if panicked>0 || next_to_write<total
+	{
+		return Err(error!(undetermined).with_message(format!("only {} of {} experiments were completed, as {} threads panicked",next_to_write,total,panicked)));
+	}
+	Ok(())
+}
+
 /// Main when passed a directory as path
 /// `path` must be a directory containing a `main.cfg`.
 /// `plugs` contains the plugged builder functions.
@@ -2119,6 +3264,7 @@ pub fn terminal_default_options() -> getopts::Options
 	let mut opts = getopts::Options::new();
 	opts.optopt("a","action","selected action to execute (for directory experiment)","METHOD");
 	opts.optopt("r","results","file in which to write the simulation results (for file experiment)","FILE");
+	opts.optopt("j","jobs","number of simulations to run in parallel threads (for file experiment)","AMOUNT");
 	opts.optopt("s","start_index","experiment index in which to start processing","INDEX");
 	opts.optopt("e","end_index","experiment index in which to end processing","INDEX");
 	opts.optopt("x","special","some special execution","SPECIAL_VALUE");
@@ -2231,20 +3377,35 @@ pub fn terminal_main_normal_opts(args:&[String], plugs:&Plugs, option_matches:ge
 	else
 	{
 		let mut f = File::open(&path).map_err(|err|error!(could_not_open_file,path.to_path_buf(),err).with_message("could not open configuration file.".to_string()))?;
-		let results_file= if option_matches.opt_present("results")
-		{
-			Some(File::create(option_matches.opt_str("results").unwrap()).expect("Could not create results file"))
-		}
-		else
-		{
-			None
-		};
+		let results_path = option_matches.opt_str("results").map(PathBuf::from);
+		let results_file = results_path.as_ref().map(|results_path|File::create(results_path).expect("Could not create results file"));
 		//let free_args = option_matches.free.iter().skip(1).collect();
 		let free_args=&option_matches.free[1..];
-		return file_main(&mut f,&plugs,results_file,free_args);
+		let jobs = match option_matches.opt_str("jobs")
+		{
+			Some(s) => s.parse::<usize>().map_err(|_|error!(bad_argument).with_message(format!("--jobs={} is not a valid number of threads.",s)))?,
+			None => 1,
+		};
+		//Other files are created next to the results, as in the run directories of the experiments.
+		let directory = results_path.as_ref().and_then(|results_path|results_path.parent()).map(Path::to_path_buf).unwrap_or_default();
+		let options = FileOptions{ jobs, directory };
+		return file_main_with_options(&mut f,&plugs,results_file,free_args,&options);
 	}
 }
 
+/**
+Writes the adjacencies of a topology into a file. The arguments are given as an `Export` object.
+The `format` is 0 for the plain format of CAMINOS, 1 for GraphML, and 2 for Graphviz DOT, see [Topology::write_adjacencies_to_file](topology::Topology::write_adjacencies_to_file).
+The plain and GraphML files can be loaded back with the `File` topology.
+```ignore
+Export{
+	topology: RandomRegularGraph{routers:100,degree:8,servers_per_router:4},
+	seed: 42,//optional, defaults to 42
+	format: 1,//optional, defaults to 0
+	filename: "rrg.graphml",
+}
+```
+**/
 pub fn special_export(args: &str, plugs:&Plugs)
 {
 	let topology_cfg = match config_parser::parse(args)
@@ -2316,10 +3477,246 @@ pub fn special_export(args: &str, plugs:&Plugs)
 	topology.write_adjacencies_to_file(&mut topology_file,format).expect("Failed writing topology to file");
 }
 
+/**
+Prints a report of the properties of a topology. The arguments are given as a `TopologyReport` object.
+The path diversity is estimated by sampling `samples` origin routers, which allows to analyze topologies with hundreds of thousands of routers.
+Each estimation is given with its 95% confidence interval. With `exact:true` the exact average amount of shortest paths is also printed, which may be very expensive.
+```ignore
+TopologyReport{
+	topology: Hamming{sides:[16,16,16],servers_per_router:8},
+	seed: 42,//optional, defaults to 42
+	samples: 1000,//optional, defaults to 1000
+	exact: false,//optional, defaults to false
+}
+```
+**/
+pub fn special_topology_report(args: &str, plugs:&Plugs)
+{
+	let report_cfg = match config_parser::parse(args)
+	{
+		Ok(config_parser::Token::Value(value)) => value,
+		Ok(_) => panic!("Not a value"),
+		Err(x) => panic!("Error parsing topology to report ({:?})",x),
+	};
+	let mut topology_cfg = None;
+	let mut seed = None;
+	let mut samples = None;
+	let mut exact = false;
+	match_object_panic!(&report_cfg,"TopologyReport",value,
+		"topology" => topology_cfg=Some(value.clone()),
+		"seed" => seed=Some(value.as_usize().expect("bad value for seed")),
+		"samples" => samples=Some(value.as_usize().expect("bad value for samples")),
+		"exact" => exact=value.as_bool().expect("bad value for exact"),
+	);
+	let topology_cfg=topology_cfg.expect("There were no topology.");
+	let seed=seed.unwrap_or(42);
+	let samples=samples.unwrap_or(1000);
+	let mut rng=StdRng::from_seed({
+		let mut std_rng_seed = [0u8;32];
+		for (index,value) in seed.to_ne_bytes().iter().enumerate()
+		{
+			std_rng_seed[index] = *value;
+		}
+		std_rng_seed
+	});
+	let topology = new_topology(TopologyBuilderArgument{cv:&topology_cfg,plugs,rng:&mut rng});
+	println!("routers: {}",topology.num_routers());
+	println!("servers: {}",topology.num_servers());
+	println!("degree: minimum {} maximum {}",topology.minimum_degree(),topology.maximum_degree());
+	let estimate = topology.estimate_path_diversity(samples,&mut rng);
+	println!("sampled origins: {}",estimate.samples);
+	println!("average_distance: {} ± {}",estimate.average_distance.mean,estimate.average_distance.half_width);
+	println!("average_amount_shortest_paths: {} ± {}",estimate.average_amount_shortest_paths.mean,estimate.average_amount_shortest_paths.half_width);
+	println!("multiple_paths_fraction: {} ± {}",estimate.multiple_paths_fraction.mean,estimate.multiple_paths_fraction.half_width);
+	if exact
+	{
+		println!("exact average_amount_shortest_paths: {}",topology.average_amount_shortest_paths());
+	}
+}
+
+/**
+Checks the symmetries of a topology. The arguments are given as a `SymmetryReport` object.
+The routers are partitioned with [Topology::symmetry_classes], whose classes contain the orbits of the automorphisms of the topology, and the partition is printed.
+When a `simulation` is given instead of a `topology`, the simulation is run and the statistics of the servers attached to routers of the same class are compared.
+With a traffic that respects the symmetries of the topology, such as a uniform one, the servers of the same class should have statistically equal statistics, and a class that does not is flagged as SUSPICIOUS. This usually indicates a bug in the wiring of the topology or in the routing.
+ * The number of consumed messages is taken as a count of independent events, and a class is flagged when its index of dispersion is too high, with a z-score over `maximum_z`.
+ * The average message delay of the servers is flagged when its standard deviation exceeds `tolerance` times its mean.
+```ignore
+SymmetryReport{
+	topology: Hamming{sides:[8,8],servers_per_router:8},//either a topology
+	simulation: Configuration{...},//or a whole simulation, whose topology is checked
+	seed: 42,//optional, defaults to 42. Only used to build the topology.
+	maximum_z: 3,//optional, defaults to 3
+	tolerance: 0.1,//optional, defaults to 0.1
+}
+```
+**/
+pub fn special_symmetry_report(args: &str, plugs:&Plugs)
+{
+	let report_cfg = match config_parser::parse(args)
+	{
+		Ok(config_parser::Token::Value(value)) => value,
+		Ok(_) => panic!("Not a value"),
+		Err(x) => panic!("Error parsing symmetry report ({:?})",x),
+	};
+	let mut topology_cfg = None;
+	let mut simulation_cfg = None;
+	let mut seed = None;
+	let mut maximum_z = None;
+	let mut tolerance = None;
+	match_object_panic!(&report_cfg,"SymmetryReport",value,
+		"topology" => topology_cfg=Some(value.clone()),
+		"simulation" => simulation_cfg=Some(value.clone()),
+		"seed" => seed=Some(value.as_usize().expect("bad value for seed")),
+		"maximum_z" => maximum_z=Some(value.as_f64().expect("bad value for maximum_z")),
+		"tolerance" => tolerance=Some(value.as_f64().expect("bad value for tolerance")),
+	);
+	let maximum_z=maximum_z.unwrap_or(3.0);
+	let tolerance=tolerance.unwrap_or(0.1);
+	if let Some(simulation_cfg) = simulation_cfg
+	{
+		let mut simulation=Simulation::new(&simulation_cfg,plugs);
+		simulation.run();
+		let topology = simulation.shared.network.topology.as_ref();
+		let router_class = print_symmetry_classes(topology);
+		let server_class : Vec<usize> = (0..topology.num_servers()).map(|server|match topology.server_neighbour(server).0
+		{
+			Location::RouterPort{router_index,..} => router_class[router_index],
+			_ => panic!("The server {} is not attached to a router",server),
+		}).collect();
+		let servers = &simulation.shared.network.servers;
+		let consumed_messages : Vec<f64> = servers.iter().map(|server|server.statistics.current_measurement.consumed_messages as f64).collect();
+		let message_delay : Vec<f64> = servers.iter().map(|server|{
+			let measurement = &server.statistics.current_measurement;
+			measurement.total_message_delay as f64/measurement.consumed_messages as f64
+		}).collect();
+		let mut suspicious=0;
+		for deviation in symmetry_deviations(&server_class,&consumed_messages)
+		{
+			let flagged = deviation.dispersion_z > maximum_z;
+			println!("class {} consumed_messages: mean {} dispersion z-score {} over {} servers{}",deviation.class,deviation.mean,deviation.dispersion_z,deviation.members,if flagged {" SUSPICIOUS"} else {""});
+			if flagged
+			{
+				suspicious+=1;
+			}
+		}
+		for deviation in symmetry_deviations(&server_class,&message_delay)
+		{
+			let flagged = deviation.relative_deviation > tolerance;
+			println!("class {} average_message_delay: mean {} relative deviation {} over {} servers{}",deviation.class,deviation.mean,deviation.relative_deviation,deviation.members,if flagged {" SUSPICIOUS"} else {""});
+			if flagged
+			{
+				suspicious+=1;
+			}
+		}
+		println!("suspicious classes: {}",suspicious);
+	}
+	else
+	{
+		let topology_cfg=topology_cfg.expect("There were no topology nor simulation.");
+		let seed=seed.unwrap_or(42);
+		let mut rng=StdRng::from_seed({
+			let mut std_rng_seed = [0u8;32];
+			for (index,value) in seed.to_ne_bytes().iter().enumerate()
+			{
+				std_rng_seed[index] = *value;
+			}
+			std_rng_seed
+		});
+		let topology = new_topology(TopologyBuilderArgument{cv:&topology_cfg,plugs,rng:&mut rng});
+		print_symmetry_classes(topology.as_ref());
+	}
+}
+
+///Prints the classes of [Topology::symmetry_classes] and returns them.
+fn print_symmetry_classes(topology:&dyn Topology) -> Vec<usize>
+{
+	let class = topology.symmetry_classes();
+	let amount = class.iter().max().map(|c|c+1).unwrap_or(0);
+	println!("routers: {}",topology.num_routers());
+	println!("symmetry classes: {}",amount);
+	if amount==1
+	{
+		println!("the topology may be vertex-transitive");
+	}
+	else
+	{
+		for c in 0..amount
+		{
+			let members : Vec<usize> = (0..class.len()).filter(|&router|class[router]==c).collect();
+			println!("class {}: {} routers {:?}",c,members.len(),members);
+		}
+	}
+	class
+}
+
+///The dispersion of a statistic among the members of a symmetry class.
+#[derive(Clone,Debug)]
+pub struct SymmetryDeviation
+{
+	pub class: usize,
+	///Number of members of the class.
+	pub members: usize,
+	///The mean of the statistic over the class.
+	pub mean: f64,
+	///The standard deviation of the statistic over the class divided by its mean. It is 0 when the mean is 0.
+	pub relative_deviation: f64,
+	///Only meaningful when the statistic is a count of independent events, as the number of consumed messages.
+	///The z-score of the index of dispersion `sum (x-mean)^2/mean`, which follows a chi-squared distribution with `members-1` degrees of freedom when the members have equal rates.
+	///High values mean that the members differ more than explained by chance.
+	pub dispersion_z: f64,
+}
+
+///Computes the dispersion of `values[i]` within each class `class[i]`. Classes with a single member are not reported, as there is nothing to compare.
+pub fn symmetry_deviations(class:&[usize], values:&[f64]) -> Vec<SymmetryDeviation>
+{
+	let amount = class.iter().max().map(|c|c+1).unwrap_or(0);
+	(0..amount).filter_map(|c|{
+		let members : Vec<f64> = class.iter().zip(values.iter()).filter(|(&x,_)|x==c).map(|(_,&v)|v).collect();
+		if members.len()<2
+		{
+			return None;
+		}
+		let k = members.len() as f64;
+		let mean = members.iter().sum::<f64>()/k;
+		let square_sum = members.iter().map(|x|(x-mean)*(x-mean)).sum::<f64>();
+		let (relative_deviation,dispersion_z) = if mean==0.0 { (0.0,0.0) } else {
+			((square_sum/k).sqrt()/mean.abs(), (square_sum/mean-(k-1.0))/(2.0*(k-1.0)).sqrt())
+		};
+		Some(SymmetryDeviation{class:c,members:members.len(),mean,relative_deviation,dispersion_z})
+	}).collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use rand::Rng;
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+    #[test]
+    fn task_rng_streams() {
+        let sequence = |seed:usize,task:usize| {
+            let mut rng = SimulationMut::new_task_rng(seed,task);
+            (0..8).map(|_|rng.gen::<u64>()).collect::<Vec<u64>>()
+        };
+        assert_eq!(sequence(42,7),sequence(42,7));
+        assert_ne!(sequence(42,7),sequence(42,8));
+        assert_ne!(sequence(42,7),sequence(43,7));
+    }
+    #[test]
+    fn symmetry_deviations_flag_outliers() {
+        let deviations = symmetry_deviations(&[0,0,1,1,1,2],&[1.0,1.0,1.0,1.0,1.6,3.0]);
+        assert_eq!(deviations.len(),2);
+        assert_eq!((deviations[0].class,deviations[0].members,deviations[0].relative_deviation),(0,2,0.0));
+        assert_eq!((deviations[1].class,deviations[1].members),(1,3));
+        assert!((deviations[1].mean-1.2).abs()<1e-9);
+        assert!(deviations[1].relative_deviation>0.2);
+        // Counts close to Poisson are not dispersed, while a server receiving twice as much is.
+        let even = symmetry_deviations(&[0;4],&[100.0,90.0,110.0,105.0]);
+        assert!(even[0].dispersion_z<3.0);
+        let uneven = symmetry_deviations(&[0;4],&[100.0,90.0,210.0,105.0]);
+        assert!(uneven[0].dispersion_z>3.0);
+    }
 }
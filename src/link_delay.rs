@@ -0,0 +1,246 @@
+/*!
+
+Variable delays of the links, to study the sensitivity of a network to the variations in the length of the cables or to jitter.
+
+A [LinkClass](crate::LinkClass) can replace its fixed `delay` by a `delay_distribution`, from which the delays are sampled.
+```ignore
+LinkClass{
+	delay_distribution: Uniform{min:3,max:7},//or Gaussian{mean:5,deviation:1}
+	delay_sampling: Packet,//optional, one of Phit, Packet, or Link. By default Packet.
+	//delay: 5,//optional, the nominal delay, used by the components that do not sample it. By default the mean of the distribution.
+}
+```
+With `Uniform` the delays are integers in `[min,max]`, both included. With `Gaussian` they are rounded to the nearest integer, and the optional `min` and `max` clamp them. By default the minimum is 0.
+The `delay_sampling` decides how often the delay is sampled.
+* With `Phit` each phit samples its delay.
+* With `Packet` the leading phit of each packet samples its delay and the rest of the packet reuses it.
+* With `Link` each link samples its delay once, at its first use, and keeps it for the whole simulation, as a cable of random length would.
+  Both directions of the link share the delay.
+
+The links never reorder the phits. When a phit would arrive before a phit previously sent through the same link, its arrival is delayed to the cycle after the previous arrival.
+Only the phits are affected by the `Phit` and `Packet` samplings, as the acknowledgements of the flow control are sent with the nominal `delay`.
+The samples are taken from a generator derived from the `random_seed` of the simulation, so the other components see the same random sequence as with fixed delays.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use ::rand::{Rng,SeedableRng,rngs::StdRng};
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::packet::Phit;
+use crate::topology::{Location,Topology};
+
+///A distribution of link delays.
+#[derive(Debug,Clone)]
+pub enum DelayDistribution
+{
+	///Integers in `[min,max]`, all with the same probability.
+	Uniform{min:Time,max:Time},
+	///A normal distribution rounded to integers and clamped to `[min,max]`.
+	Gaussian{mean:f64,deviation:f64,min:Time,max:Time},
+}
+
+impl DelayDistribution
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<DelayDistribution,Error>
+	{
+		let name = match cv
+		{
+			ConfigurationValue::Object(name,_) => name.as_str(),
+			_ => return Err(cv.ill("The delay_distribution must be an object")),
+		};
+		match name
+		{
+			"Uniform" =>
+			{
+				let mut min=None;
+				let mut max=None;
+				match_object!(cv,"Uniform",value,
+					"min" => min=Some(value.as_time()?),
+					"max" => max=Some(value.as_time()?),
+				);
+				let min=min.ok_or_else(||cv.ill("There were no min"))?;
+				let max=max.ok_or_else(||cv.ill("There were no max"))?;
+				if min>max
+				{
+					return Err(cv.ill("The delay_distribution Uniform requires min<=max"));
+				}
+				Ok(DelayDistribution::Uniform{min,max})
+			},
+			"Gaussian" =>
+			{
+				let mut mean=None;
+				let mut deviation=None;
+				let mut min=0;
+				let mut max=Time::MAX;
+				match_object!(cv,"Gaussian",value,
+					"mean" => mean=Some(value.as_f64()?),
+					"deviation" => deviation=Some(value.as_f64()?),
+					"min" => min=value.as_time()?,
+					"max" => max=value.as_time()?,
+				);
+				let mean=mean.ok_or_else(||cv.ill("There were no mean"))?;
+				let deviation=deviation.ok_or_else(||cv.ill("There were no deviation"))?;
+				if deviation<0.0
+				{
+					return Err(cv.ill("The delay_distribution Gaussian requires a non-negative deviation"));
+				}
+				if min>max
+				{
+					return Err(cv.ill("The delay_distribution Gaussian requires min<=max"));
+				}
+				Ok(DelayDistribution::Gaussian{mean,deviation,min,max})
+			},
+			_ => Err(cv.ill(&format!("Unknown delay_distribution {}",name))),
+		}
+	}
+	///The mean of the distribution, rounded, to be used as nominal delay.
+	pub fn mean(&self) -> Time
+	{
+		match self
+		{
+			&DelayDistribution::Uniform{min,max} => (min+max+1)/2,
+			&DelayDistribution::Gaussian{mean,min,max,..} => (mean.round().max(0.0) as Time).clamp(min,max),
+		}
+	}
+	pub fn sample(&self, rng:&mut StdRng) -> Time
+	{
+		match self
+		{
+			&DelayDistribution::Uniform{min,max} => rng.gen_range(min..=max),
+			&DelayDistribution::Gaussian{mean,deviation,min,max} =>
+			{
+				//Box-Muller transform.
+				let u1 : f64 = 1.0 - rng.gen::<f64>();
+				let u2 : f64 = rng.gen();
+				let z = (-2.0*u1.ln()).sqrt() * (2.0*std::f64::consts::PI*u2).cos();
+				let x = (mean + deviation*z).round();
+				if x <= min as f64 { min } else if x >= max as f64 { max } else { x as Time }
+			},
+		}
+	}
+}
+
+///How often the delay is sampled. See the [module documentation](self).
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum DelaySampling
+{
+	Phit,
+	Packet,
+	Link,
+}
+
+impl DelaySampling
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<DelaySampling,Error>
+	{
+		match cv
+		{
+			ConfigurationValue::Object(name,_) | ConfigurationValue::Literal(name) => match name.as_str()
+			{
+				"Phit" => Ok(DelaySampling::Phit),
+				"Packet" => Ok(DelaySampling::Packet),
+				"Link" => Ok(DelaySampling::Link),
+				_ => Err(cv.ill(&format!("Unknown delay_sampling {}",name))),
+			},
+			_ => Err(cv.ill("bad value for delay_sampling")),
+		}
+	}
+}
+
+///The state of the sampling of the delays of a link class.
+#[derive(Debug)]
+pub struct DelaySampler
+{
+	distribution: DelayDistribution,
+	sampling: DelaySampling,
+	rng: StdRng,
+	///The cycle of the last arrival through each link, keyed by the location receiving the phits.
+	last_arrival: BTreeMap<Location,Time>,
+	///The delay of the packet currently crossing each link, keyed by the location receiving the phits.
+	packet_delay: BTreeMap<Location,Time>,
+	///The delay of each link, keyed by its two endpoints in order.
+	link_delay: BTreeMap<(Location,Location),Time>,
+}
+
+///A [DelaySampler] shared among the clones of a [LinkClass](crate::LinkClass).
+pub type SharedDelaySampler = Rc<RefCell<DelaySampler>>;
+
+impl DelaySampler
+{
+	pub fn new(distribution:DelayDistribution, sampling:DelaySampling) -> DelaySampler
+	{
+		DelaySampler{
+			distribution,
+			sampling,
+			rng: StdRng::seed_from_u64(0),
+			last_arrival: BTreeMap::new(),
+			packet_delay: BTreeMap::new(),
+			link_delay: BTreeMap::new(),
+		}
+	}
+	///Seed the generator of the samples for the link class of index `link_class` of a simulation with the given `random_seed`.
+	pub fn seed(&mut self, random_seed:usize, link_class:usize)
+	{
+		let mut key = [0u8;32];
+		key[0..8].copy_from_slice(&(random_seed as u64).to_le_bytes());
+		key[8..16].copy_from_slice(&(link_class as u64).to_le_bytes());
+		//A tag to keep these streams apart from the others.
+		key[16..24].copy_from_slice(b"linkdlay");
+		self.rng = StdRng::from_seed(key);
+	}
+	///The delay of a phit sent at `cycle` towards the location `destination`.
+	pub fn phit_delay(&mut self, cycle:Time, phit:&Phit, destination:&Location, topology:&dyn Topology) -> Time
+	{
+		let delay = match self.sampling
+		{
+			DelaySampling::Phit => self.distribution.sample(&mut self.rng),
+			DelaySampling::Packet =>
+			{
+				if phit.is_begin() || !self.packet_delay.contains_key(destination)
+				{
+					let delay = self.distribution.sample(&mut self.rng);
+					self.packet_delay.insert(destination.clone(),delay);
+				}
+				self.packet_delay[destination]
+			},
+			DelaySampling::Link => self.link_delay(destination,topology),
+		};
+		//Keep the order of the phits in the link.
+		let arrival = match self.last_arrival.get(destination)
+		{
+			Some(&last) if last >= cycle+delay => last+1,
+			_ => cycle+delay,
+		};
+		self.last_arrival.insert(destination.clone(),arrival);
+		arrival-cycle
+	}
+	///The delay of an acknowledgement sent towards `destination`, which uses the `nominal` delay unless each link has its own delay.
+	pub fn acknowledge_delay(&mut self, nominal:Time, destination:&Location, topology:&dyn Topology) -> Time
+	{
+		match self.sampling
+		{
+			DelaySampling::Link => self.link_delay(destination,topology),
+			_ => nominal,
+		}
+	}
+	///The delay of the link with an endpoint at `location`, sampling it at its first use.
+	fn link_delay(&mut self, location:&Location, topology:&dyn Topology) -> Time
+	{
+		let other = match location
+		{
+			&Location::RouterPort{router_index,router_port} => topology.neighbour(router_index,router_port).0,
+			&Location::ServerPort(server) => topology.server_neighbour(server).0,
+			Location::None => Location::None,
+		};
+		let key = if *location <= other { (location.clone(),other) } else { (other,location.clone()) };
+		let DelaySampler{ref mut link_delay,ref distribution,ref mut rng,..} = *self;
+		*link_delay.entry(key).or_insert_with(||distribution.sample(rng))
+	}
+}
@@ -0,0 +1,267 @@
+/*!
+
+Lossy networks, in which the routers may drop packets and the servers retransmit them.
+
+By default the network is lossless: a packet waits in its buffer until it can advance, as flow control guarantees that there is space for it.
+The [Basic](crate::router::basic::Basic) router may instead drop the packets at the head of its input buffers, as Ethernet switches do.
+* With `drop_when_full: true` a packet is dropped when every output to which it could advance lacks space in the next buffer.
+* With `drop_timeout: t` a packet is dropped when it has waited more than `t` cycles at the head of its input buffer without advancing.
+
+Only packets wholly in the buffer are dropped, which is always the case with virtual cut-through.
+Configuring `lossy` when no router can drop packets is an error.
+The dropped packets are recovered with end-to-end retransmission when the configuration includes `lossy`.
+```ignore
+lossy: Lossy{
+	//Cycles since the injection of a packet after which it is retransmitted if it has not been acknowledged. Without it the dropped packets are lost.
+	retransmission_timeout: 2000,
+	//Cycles since the consumption of a packet until its acknowledgement reaches the origin. Defaults to 0.
+	acknowledgement_delay: 100,
+}
+```
+The origin server does not know which packets have been dropped. It starts a timer when it injects a packet, and sends the packet again when the timer expires before the acknowledgement arrives.
+Thus a dropped packet is only recovered after the whole timeout, and a packet that is just delayed is retransmitted spuriously.
+The destination accepts the first transmission of each packet that begins to arrive and discards the other copies as duplicates.
+The retransmitted packet goes before the packets waiting in the server and follows a new route.
+The delay of a message accounts for the retransmission of its packets, and the injected load counts the phits of each transmission.
+A `congestion_control` hears of each expired timer at its retransmission, see [CongestionControl::packet_lost](crate::congestion::CongestionControl::packet_lost).
+
+The results include `lossy`, an object with the `dropped_packets`, `dropped_phits`, `retransmitted_packets`, `spurious_retransmissions`, `duplicate_packets`, and `lost_packets` of the measured period.
+A retransmission is spurious when some copy of the packet was still in the network or had been accepted.
+When some router drops packets without `lossy` in the configuration the dropped packets are counted as lost.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::error::{Error,SourceLocation};
+use crate::packet::{Message,Packet,PacketRef,Phit};
+use crate::routing::RoutingInfo;
+
+///Identifies a packet through all its transmissions: its message and its index in it.
+type PacketKey = (*const Message,usize);
+
+fn packet_key(packet:&Packet) -> PacketKey
+{
+	(packet.message.as_ref() as *const Message,packet.index)
+}
+
+///The transmissions of a packet whose delivery has not been settled.
+#[derive(Debug)]
+struct Outstanding
+{
+	///A clean copy of the latest transmission, from which to build the next one.
+	latest: Packet,
+	///The copies injected and neither dropped nor consumed.
+	in_flight: usize,
+	///The transmission accepted by the destination, if any has begun to arrive.
+	accepted: Option<usize>,
+	///The cycle in which the acknowledgement of the accepted transmission reaches the origin.
+	acknowledgement_cycle: Option<Time>,
+	///Whether the origin has seen the acknowledgement, so that the entry is kept only until the remaining copies leave the network.
+	done: bool,
+}
+
+///The end-to-end recovery of the packets dropped by the routers. See the [module documentation](self).
+#[derive(Debug,Default)]
+pub struct Lossy
+{
+	///The cycles since the injection of a packet before its retransmission if not acknowledged. `None` to not retransmit.
+	retransmission_timeout: Option<Time>,
+	///The cycles from the consumption of a packet to the arrival of its acknowledgement at the origin.
+	acknowledgement_delay: Time,
+	///The packets injected whose delivery has not been settled.
+	outstanding: BTreeMap<PacketKey,Outstanding>,
+	///The running timers, indexed by their expiration cycle, with the transmission that started each one.
+	timers: BTreeMap<Time,Vec<(PacketKey,usize)>>,
+	//statistics:
+	dropped_packets: usize,
+	dropped_phits: usize,
+	retransmitted_packets: usize,
+	spurious_retransmissions: usize,
+	duplicate_packets: usize,
+	lost_packets: usize,
+}
+
+impl Lossy
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<Lossy,Error>
+	{
+		let mut retransmission_timeout = None;
+		let mut acknowledgement_delay = 0;
+		match_object!(cv,"Lossy",value,
+			"retransmission_timeout" => retransmission_timeout=Some(value.as_time()?),
+			"acknowledgement_delay" => acknowledgement_delay=value.as_time()?,
+		);
+		if retransmission_timeout == Some(0)
+		{
+			return Err(cv.ill("The retransmission_timeout must be positive."));
+		}
+		Ok(Lossy{
+			retransmission_timeout,
+			acknowledgement_delay,
+			..Default::default()
+		})
+	}
+	///The origin has injected the `packet` at `cycle`, starting its retransmission timer.
+	pub fn packet_injected(&mut self, packet:&Packet, cycle:Time)
+	{
+		let timeout = match self.retransmission_timeout
+		{
+			Some(timeout) => timeout,
+			None => return,
+		};
+		let key = packet_key(packet);
+		if packet.transmission == 0
+		{
+			self.outstanding.insert(key,Outstanding{
+				latest: retransmitted_packet(packet,0),
+				in_flight: 1,
+				accepted: None,
+				acknowledgement_cycle: None,
+				done: false,
+			});
+		}
+		self.timers.entry(cycle+timeout).or_default().push((key,packet.transmission));
+	}
+	///Tracks a packet dropped by a router.
+	pub fn packet_dropped(&mut self, packet:PacketRef)
+	{
+		self.dropped_packets += 1;
+		self.dropped_phits += packet.size;
+		let key = packet_key(&packet);
+		match self.outstanding.get_mut(&key)
+		{
+			Some(entry) =>
+			{
+				entry.in_flight -= 1;
+				if entry.done && entry.in_flight == 0
+				{
+					self.outstanding.remove(&key);
+				}
+			},
+			None => self.lost_packets += 1,
+		}
+		packet.destroy();
+	}
+	///A `phit` arrives at its destination at `cycle`. Returns whether it belongs to the accepted transmission of its packet, otherwise it must be discarded.
+	pub fn phit_arrived(&mut self, phit:&Phit, cycle:Time) -> bool
+	{
+		let packet = &phit.packet;
+		let key = packet_key(packet);
+		let entry = match self.outstanding.get_mut(&key)
+		{
+			Some(entry) => entry,
+			None => return true,
+		};
+		if phit.is_begin() && entry.accepted.is_none()
+		{
+			entry.accepted = Some(packet.transmission);
+		}
+		let accepted = entry.accepted == Some(packet.transmission);
+		if phit.is_end()
+		{
+			entry.in_flight -= 1;
+			if accepted
+			{
+				entry.acknowledgement_cycle = Some(cycle+self.acknowledgement_delay);
+			}
+			else
+			{
+				self.duplicate_packets += 1;
+				if entry.done && entry.in_flight == 0
+				{
+					self.outstanding.remove(&key);
+				}
+			}
+		}
+		accepted
+	}
+	///Expires the timers due at `cycle`, returning the packets to retransmit.
+	pub fn due_retransmissions(&mut self, cycle:Time) -> Vec<Packet>
+	{
+		let mut due = vec![];
+		while let Some(&first) = self.timers.keys().next()
+		{
+			if first > cycle
+			{
+				break;
+			}
+			for (key,transmission) in self.timers.remove(&first).expect("the key was just found")
+			{
+				let entry = match self.outstanding.get_mut(&key)
+				{
+					Some(entry) => entry,
+					None => continue,
+				};
+				if entry.latest.transmission != transmission
+				{
+					//A previous timer, superseded by a retransmission.
+					continue;
+				}
+				if entry.acknowledgement_cycle.map_or(false,|acknowledgement|acknowledgement<=cycle)
+				{
+					entry.done = true;
+					if entry.in_flight == 0
+					{
+						self.outstanding.remove(&key);
+					}
+					continue;
+				}
+				if entry.accepted.is_some() || entry.in_flight > 0
+				{
+					self.spurious_retransmissions += 1;
+				}
+				entry.in_flight += 1;
+				entry.latest = retransmitted_packet(&entry.latest,transmission+1);
+				due.push(retransmitted_packet(&entry.latest,transmission+1));
+			}
+		}
+		self.retransmitted_packets += due.len();
+		due
+	}
+	///Clear the statistics, to begin the measured period.
+	pub fn reset_statistics(&mut self)
+	{
+		self.dropped_packets = 0;
+		self.dropped_phits = 0;
+		self.retransmitted_packets = 0;
+		self.spurious_retransmissions = 0;
+		self.duplicate_packets = 0;
+		self.lost_packets = 0;
+	}
+	///The statistics since the last reset.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("Lossy"),vec![
+			(String::from("dropped_packets"),ConfigurationValue::Number(self.dropped_packets as f64)),
+			(String::from("dropped_phits"),ConfigurationValue::Number(self.dropped_phits as f64)),
+			(String::from("retransmitted_packets"),ConfigurationValue::Number(self.retransmitted_packets as f64)),
+			(String::from("spurious_retransmissions"),ConfigurationValue::Number(self.spurious_retransmissions as f64)),
+			(String::from("duplicate_packets"),ConfigurationValue::Number(self.duplicate_packets as f64)),
+			(String::from("lost_packets"),ConfigurationValue::Number(self.lost_packets as f64)),
+		])
+	}
+}
+
+///A new transmission of a packet, with the same message and position in it, but with a clean routing state.
+fn retransmitted_packet(packet:&Packet, transmission:usize) -> Packet
+{
+	let mut routing_info = RoutingInfo::new();
+	routing_info.source_server = packet.routing_info.borrow().source_server;
+	routing_info.hints = packet.message.routing_hints.clone();
+	Packet{
+		size: packet.size,
+		routing_info: RefCell::new(routing_info),
+		message: packet.message.clone(),
+		index: packet.index,
+		transmission,
+		cycle_into_network: RefCell::new(0),
+		extra: RefCell::new(None),
+		congestion_marked: RefCell::new(false),
+		decisions: RefCell::new(None),
+	}
+}
@@ -22,20 +22,53 @@ the message was created until the cycle in its consumption was completed. Note t
 * `server_average_missed_generations` counts the average of times a server has skipped generating a message because its internal queue is full. Under some assumptions a greater than 0 value means some flows have infinite latency. It may also mean that `server_queue_size` is not large enough.
 * `servers_with_missed_generations` counts the number of severs that have missed some generations. Under some assumptions this is couting the number of flows with infinite latency.
 * `virtual_channel_usage` is an array with the link utilization indexed by the virtual channel. This is, when a phit is transmitted by a link requesting a virtual channel `vc`, a `+1` is tracked into the index `vc`.
+* `class_statistics` is only included when the messages have more than one traffic `class`. It is an array with a `ClassStatistics` object for each class, with the `class`, `injected_load`, `accepted_load`, `average_message_delay`, and `average_packet_network_delay` restricted to the messages of that class. The loads are averaged over all the servers, so they sum to the total loads.
+* `sub_traffic_statistics` is only included when the traffic is a `TrafficSum`. It is an array with a `SubTrafficStatistics` object for each summand, with the `index` of the summand, its `name` if the `TrafficSum` gives `names`, and the same fields as `class_statistics`, restricted to the messages generated by that summand.
 * `git_id` has an id of the CAMINOS binary, which is meaningful when building from a git repository.
 * `version_number` has the CAMINOS version as read from the Cargo.toml.
 
+When the configuration includes a `measurement_extension` the result also includes a `measurement_extension` object. See [MeasurementExtension] for its fields.
+
+When the configuration includes a `steady_state_detection` the result also includes a `steady_state` object telling whether the warmup was enough, which may also be extended automatically. See [SteadyStateDetection].
+
+With a non-zero `statistics_temporal_step` the result includes `temporal_statistics` with the measures of each period. For long simulations these can be written into a file as they are completed by setting `statistics_temporal_file`, see [TemporalStatisticsStream].
+
+Setting `statistics_link_utilization: true` includes `link_utilization`, an array with an array for each router with the utilization of the link arriving at each of its ports towards other routers. The `LinkHeatmap` output draws it, see [create_output](crate::output::create_output).
+
+Setting `packet_trace_file` writes a record for each consumed packet, see [PacketTrace].
+
+Setting `statistics_decision_features` writes the features of the routing decisions of a sample of the packets with their latencies, see [DecisionFeatures].
+
+Setting `statistics_flows` includes `flows` with the completion times of the flows between pairs of servers and the incast at each destination, see [FlowStatistics].
+
+Setting `statistics_batch_means` includes `batch_means` with confidence intervals of the accepted load and the message delay computed from batches of the measured period, see [BatchMeans].
+
+Setting `injection_record_file` writes a record for each generated message, which the `TraceReplay` traffic can replay, see [InjectionRecord](crate::traffic::InjectionRecord).
+
+Setting `statistics_global_links` includes `global_links` with the utilization of each global link and warns about the links carrying much more than the average, see [GlobalLinkReport].
+
+Setting `statistics_buffer_occupancy_step` includes `buffer_occupancy` with periodic samples of the phits in each buffer of the routers, see [BufferOccupancySeries].
+
+When some link class sets a `maximum_packet_size` the result includes `fragmentation` with the additional packets due to these limits, see [Fragmentation].
+
+The routers that track why their phits stall add a `stall_cycles` object with the cycles of each cause, see [StallCounts].
+
 */
 
 
 use std::cmp;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap,HashMap};
+use std::path::{Path,PathBuf};
 use std::convert::TryInto;
 
-use crate::{Quantifiable,Packet,Phit,Network,Topology,ConfigurationValue,Expr,Time};
+use crate::{Quantifiable,Packet,Phit,Message,Network,Topology,ConfigurationValue,Expr,Time,MessageTag};
 use crate::config;
+use crate::{match_object,error,source_location};
 use crate::traffic::TaskTrafficState;
+use crate::topology::Location;
+use crate::router::Router;
+use crate::routing::CandidateEgress;
+use crate::error::{Error,SourceLocation};
 
 #[derive(Clone,Quantifiable)]
 pub struct ServerStatistics
@@ -49,6 +82,8 @@ pub struct ServerStatistics
 	pub temporal_step: Time,
 	///The periodic measurements requested by non-zero statistics_temporal_step.
 	pub temporal_statistics: Vec<ServerMeasurement>,
+	///The number of periodic measurements removed from the beginning of `temporal_statistics`.
+	pub temporal_offset: usize,
 }
 
 #[derive(Clone,Default,Quantifiable)]
@@ -74,6 +109,7 @@ impl ServerStatistics
 			cycle_last_consumed_message: 0,
 			temporal_step,
 			temporal_statistics: vec![],
+			temporal_offset: 0,
 		}
 	}
 	fn reset(&mut self, next_cycle: Time)
@@ -133,15 +169,26 @@ impl ServerStatistics
 	{
 		if self.temporal_step>0
 		{
-			let index : usize = (cycle / self.temporal_step).try_into().unwrap();
+			let absolute_index : usize = (cycle / self.temporal_step).try_into().unwrap();
+			let index = absolute_index - self.temporal_offset;
 			if self.temporal_statistics.len()<=index
 			{
+				let first = self.temporal_statistics.len();
 				self.temporal_statistics.resize_with(index+1,Default::default);
-				self.temporal_statistics[index].begin_cycle = index as Time * self.temporal_step;
+				for (offset,measurement) in self.temporal_statistics[first..].iter_mut().enumerate()
+				{
+					measurement.begin_cycle = (self.temporal_offset+first+offset) as Time * self.temporal_step;
+				}
 			}
 			Some(&mut self.temporal_statistics[index])
 		} else { None }
 	}
+	///Removes the oldest periodic measurement, to be called once it has been completed and stored elsewhere.
+	pub fn pop_temporal_measurement(&mut self) -> ServerMeasurement
+	{
+		self.temporal_offset+=1;
+		if self.temporal_statistics.is_empty() { ServerMeasurement::default() } else { self.temporal_statistics.remove(0) }
+	}
 }
 
 #[derive(Clone,Quantifiable, Debug)]
@@ -230,11 +277,27 @@ impl TrafficStatistics
 			waiting_data_histogram: HashMap::new(),
 		}
 	}
-	// fn reset(&mut self, next_cycle: Time)
-	// {
-	// 	self.current_measurement= TrafficMeasurement::default();
-	// 	self.current_measurement.begin_cycle=next_cycle;
-	// }
+	///Clears the accumulated totals and the delay histogram, including those of the subtraffics.
+	///The series indexed by cycle, as `temporal_statistics` and the task histograms, are kept, since they already tell the warmup apart.
+	pub fn reset(&mut self, next_cycle: Time)
+	{
+		self.current_measurement= TrafficMeasurement::default();
+		self.current_measurement.begin_cycle=next_cycle;
+		self.total_created_messages=0;
+		self.total_created_phits=0;
+		self.total_consumed_messages=0;
+		self.total_consumed_phits=0;
+		self.total_message_delay=0;
+		self.total_message_network_delay=0;
+		self.histogram_messages_delay.clear();
+		if let Some(sub) = self.sub_traffic_statistics.as_mut()
+		{
+			for statistics in sub.iter_mut()
+			{
+				statistics.reset(next_cycle);
+			}
+		}
+	}
 
 	/// Called when a task recieves a message.
 	//	pub fn track_consumed_message(&mut self, cycle: Time, total_delay:Time, injection_delay:Time, size: usize, subtraffic: Option<usize>)
@@ -354,7 +417,7 @@ impl TrafficStatistics
 
 	pub fn parse_statistics(&self) -> ConfigurationValue
 	{
-		let max = self.histogram_messages_delay.clone().into_keys().max().unwrap();
+		let max = self.histogram_messages_delay.keys().max().cloned().unwrap_or(0);
 		let messages_latency_histogram = (0..max+1).map(|i|
 			ConfigurationValue::Number(self.histogram_messages_delay.get(&i).unwrap_or(&0).clone() as f64)
 		).collect();
@@ -471,6 +534,59 @@ pub struct StatisticMeasurement
 	///For each virtual channel `vc`, `virtual_channel_usage[vc]` counts the total number of times
 	///a phit has advanced by any link using that virtual channel.
 	pub virtual_channel_usage: Vec<usize>,
+	///The measures of each traffic class, indexed by the `class` of the messages. Only kept for the whole measurement, not for the temporal statistics.
+	pub class_measurements: Vec<ClassMeasurement>,
+	///The measures of each sub-traffic of a TrafficSum, indexed by the `id_traffic` of the messages. Only kept for the whole measurement.
+	pub sub_traffic_measurements: Vec<ClassMeasurement>,
+}
+
+impl StatisticMeasurement
+{
+	///The measures of the given traffic class, growing the vector as required.
+	pub fn class_measurement(&mut self, class:usize) -> &mut ClassMeasurement
+	{
+		if self.class_measurements.len() <= class
+		{
+			self.class_measurements.resize_with(class+1, Default::default);
+		}
+		&mut self.class_measurements[class]
+	}
+	///The measures of the given sub-traffic, growing the vector as required.
+	pub fn sub_traffic_measurement(&mut self, index:usize) -> &mut ClassMeasurement
+	{
+		if self.sub_traffic_measurements.len() <= index
+		{
+			self.sub_traffic_measurements.resize_with(index+1, Default::default);
+		}
+		&mut self.sub_traffic_measurements[index]
+	}
+	///Apply `update` to the measures of the class of the message and of its sub-traffic, if any.
+	pub fn track_message_measurements<F:Fn(&mut ClassMeasurement)>(&mut self, message:&Message, update:F)
+	{
+		update(self.class_measurement(message.class));
+		if let Some(index) = message.id_traffic
+		{
+			update(self.sub_traffic_measurement(index));
+		}
+	}
+}
+
+///The measures restricted to the messages of a traffic class or of a sub-traffic.
+#[derive(Debug,Default,Quantifiable)]
+pub struct ClassMeasurement
+{
+	///The number of phits of the class that servers have sent to routers.
+	pub created_phits: usize,
+	///Number of phits of the class that have reached their destination server.
+	pub consumed_phits: usize,
+	///Number of phit tails of the class consumed.
+	pub consumed_packets: usize,
+	///Number of messages of the class completely consumed.
+	pub consumed_messages: usize,
+	///Accumulated delay of the messages of the class.
+	pub total_message_delay: Time,
+	///Accumulated network delay of the packets of the class.
+	pub total_packet_network_delay: Time,
 }
 
 //impl StatisticMeasurement
@@ -556,6 +672,8 @@ pub struct Statistics
 	pub temporal_step: Time,
 	///The periodic measurements requested by non-zero statistics_temporal_step.
 	pub temporal_statistics: Vec<StatisticMeasurement>,
+	///The number of periodic measurements removed from the beginning of `temporal_statistics`, when they are being streamed into a file.
+	pub temporal_offset: usize,
 	///For each percentile `perc` write server statistics for that percentile. This is, the lowest value such that `perc`% of the servers have lower value.
 	///These values will appear in the `server_percentile{perc}` field of the result file.
 	///For example, `server_percentile25.injected_load` will be a value with 25% of the servers generating less load and `server_percentile25.accepted_load` will be a value with 25% of the servers consuming less load. Note those values will probably correspond to different servers, despite being written into the same record.
@@ -578,6 +696,7 @@ pub struct Statistics
 	///A list of statistic definitions for message statistics.
 	/// Each definition is a tuple `(keys,values)`, that are evaluated on each message.
 	/// Messages are classified via `keys` into their bin. The number of messages in each bin is counted and the associated `values` are averaged.
+	/// The expressions can use the `delay` of the message and its `tag`, see [MessageTag].
 	pub message_defined_statistics_definitions: Vec< (Vec<Expr>,Vec<Expr>) >,
 	///For each definition of message statistics, we have a vector with an element for each actual value of `keys`.
 	/// Each of these elements have that value of `key`, together with the averages and the count.
@@ -586,6 +705,14 @@ pub struct Statistics
 	pub temporal_defined_statistics_definitions: Vec< (Vec<Expr>, Vec<Expr>) >,
 	///For each definition of server statistics, we have a vector with an element for each actual value of `keys`.
 	pub temporal_defined_statistics_measurement: Vec< Vec< Vec< (Vec<ConfigurationValue>, Vec<f32>, usize) >>>,
+	///Where to write a record for each consumed packet, if requested by `packet_trace_file`.
+	pub packet_trace: Option<PacketTrace>,
+	///Where to write the features of the routing decisions of the sampled packets, if requested by `statistics_decision_features`.
+	pub decision_features: Option<DecisionFeatures>,
+	///The statistics of the flows, if requested by `statistics_flows`.
+	pub flow_statistics: Option<FlowStatistics>,
+	///The batches of the measured period, if requested by `statistics_batch_means`.
+	pub batch_means: Option<BatchMeans>,
 }
 
 impl Statistics
@@ -608,6 +735,7 @@ impl Statistics
 			link_statistics: (0..topology.num_routers()).map(|i| (0..topology.ports(i)).map(|_|LinkStatistics::new()).collect() ).collect(),
 			temporal_step: statistics_temporal_step,
 			temporal_statistics: vec![],
+			temporal_offset: 0,
 			server_percentiles,
 			packet_percentiles,
 			packet_statistics: vec![],
@@ -628,17 +756,25 @@ impl Statistics
 			message_defined_statistics_measurement,
 			temporal_defined_statistics_definitions,
 			temporal_defined_statistics_measurement,
+			packet_trace: None,
+			decision_features: None,
+			flow_statistics: None,
+			batch_means: None,
 		}
 	}
-	///Print in stdout a header showing the statistical columns to be periodically printed.
-	pub fn print_header(&self)
+	///Whether the routers and hops traversed by packets have to be tracked in their `PacketExtraInfo`.
+	pub fn track_packet_extra(&self) -> bool
+	{
+		!self.packet_defined_statistics_definitions.is_empty() || self.packet_trace.is_some()
+	}
+	///A header showing the statistical columns to be periodically reported. See [crate::progress].
+	pub fn header_line(&self) -> String
 	{
 		//println!("cycle_begin-cycle_end injected_load accepted_load server_generation_jain_index server_consumption_jain_index");
-		let report:String = self.columns.iter().map(|c|c.header()).collect();
-		println!("{}",report);
+		self.columns.iter().map(|c|c.header()).collect()
 	}
-	///Print in stdout the current values of the statistical columns indicated to be periodically printed.
-	pub fn print(&self, next_cycle:Time, network:&Network)
+	///The current values of the statistical columns indicated to be periodically reported.
+	pub fn report_line(&self, next_cycle:Time, network:&Network) -> String
 	{
 		//let cycles=next_cycle-self.begin_cycle+1;
 		//let injected_load=self.created_phits as f32/cycles as f32/network.servers.len() as f32;
@@ -646,8 +782,7 @@ impl Statistics
 		//let jsgp=self.jain_server_created_phits(network);
 		//let jscp=self.jain_server_consumed_phits(network);
 		//println!("{:>11}-{:<9} {:<13} {:<13} {:<17} {:<12}",self.begin_cycle,next_cycle-1,injected_load,accepted_load,jsgp,jscp);
-		let report:String = self.columns.iter().map(|c|c.format(self,next_cycle,network)).collect();
-		println!("{}",report);
+		self.columns.iter().map(|c|c.format(self,next_cycle,network)).collect()
 	}
 	///Forgets all captured statistics and began capturing again.
 	pub fn reset(&mut self,next_cycle:Time, network:&mut Network)
@@ -662,6 +797,14 @@ impl Statistics
 		//self.total_packet_per_hop_count=Vec::new();
 		self.current_measurement=Default::default();
 		self.current_measurement.begin_cycle=next_cycle;
+		if let Some(flows) = self.flow_statistics.as_mut()
+		{
+			flows.reset();
+		}
+		if let Some(batches) = self.batch_means.as_mut()
+		{
+			batches.reset(next_cycle);
+		}
 		for server in network.servers.iter_mut()
 		{
 			server.statistics.reset(next_cycle);
@@ -678,14 +821,19 @@ impl Statistics
 			}
 		}
 	}
-	/// Called each time a server consumes a phit.
-	pub fn track_consumed_phit(&mut self, cycle: Time)
+	/// Called each time a server consumes a phit of a message.
+	pub fn track_consumed_phit(&mut self, cycle: Time, message: &Message)
 	{
 		self.current_measurement.consumed_phits+=1;
+		self.current_measurement.track_message_measurements(message,|m|m.consumed_phits+=1);
 		if let Some(m) = self.current_temporal_measurement(cycle)
 		{
 			m.consumed_phits+=1;
 		}
+		if let Some(batches) = self.batch_means.as_mut()
+		{
+			batches.track_consumed_phit(cycle);
+		}
 	}
 	/// Called when a server consumes a tail phit.
 	pub fn track_consumed_packet(&mut self, cycle: Time, packet:&Packet)
@@ -693,6 +841,10 @@ impl Statistics
 		self.current_measurement.consumed_packets+=1;
 		let network_delay = cycle-*packet.cycle_into_network.borrow();
 		self.current_measurement.total_packet_network_delay += network_delay;
+		self.current_measurement.track_message_measurements(&packet.message,|m|{
+			m.consumed_packets+=1;
+			m.total_packet_network_delay+=network_delay;
+		});
 		let hops=packet.routing_info.borrow().hops;
 		self.current_measurement.total_packet_hops+=hops;
 		if self.current_measurement.total_packet_per_hop_count.len() <= hops
@@ -710,6 +862,14 @@ impl Statistics
 		{
 			self.packet_statistics.push(StatisticPacketMeasurement{consumed_cycle:cycle,hops,delay:network_delay});
 		}
+		if let Some(trace) = self.packet_trace.as_mut()
+		{
+			trace.write_packet(cycle,packet);
+		}
+		if let Some(features) = self.decision_features.as_mut()
+		{
+			features.write_packet(cycle,packet);
+		}
 		if !self.packet_defined_statistics_definitions.is_empty()
 		{
 			let be = packet.extra.borrow();
@@ -730,6 +890,7 @@ impl Statistics
 				(String::from("switches"), ConfigurationValue::Array(switches)),
 				(String::from("entry_virtual_channels"), ConfigurationValue::Array(entry_virtual_channels)),
 				(String::from("cycle_per_hop"), ConfigurationValue::Array(cycle_per_hop)),
+				(String::from("tag"), MessageTag::to_config_value(packet.message.tag.as_deref())),
 			];
 			let context = ConfigurationValue::Object( String::from("packet"), context_content );
 			let path = Path::new(".");
@@ -761,18 +922,24 @@ impl Statistics
 		}
 	}
 	/// Called when a server consumes the last phit from a message.
-	pub fn track_consumed_message(&mut self, cycle: Time)
+	pub fn track_consumed_message(&mut self, cycle: Time, message: &Message)
 	{
 		self.current_measurement.consumed_messages+=1;
+		self.current_measurement.track_message_measurements(message,|m|m.consumed_messages+=1);
 		if let Some(m) = self.current_temporal_measurement(cycle)
 		{
 			m.consumed_messages+=1;
 		}
+		if let Some(flows) = self.flow_statistics.as_mut()
+		{
+			flows.track_consumed_message(cycle,message);
+		}
 	}
-	/// Called each time a phit is created.
-	pub fn track_created_phit(&mut self, cycle: Time)
+	/// Called each time a phit of a message is created.
+	pub fn track_created_phit(&mut self, cycle: Time, message: &Message)
 	{
 		self.current_measurement.created_phits+=1;
+		self.current_measurement.track_message_measurements(message,|m|m.created_phits+=1);
 		if let Some(m) = self.current_temporal_measurement(cycle)
 		{
 			m.created_phits+=1;
@@ -780,18 +947,26 @@ impl Statistics
 	}
 	/// Called when a server consumes the last phit from a message.
 	/// XXX: Perhaps this should be part of `track_consumed_message`.
-	pub fn track_message_delay(&mut self, delay:Time, cycle: Time)
+	/// The `tag` of the message is available to the `message_defined_statistics_definitions`.
+	pub fn track_message_delay(&mut self, delay:Time, cycle: Time, message: &Message)
 	{
+		let tag = message.tag.as_deref();
 		self.current_measurement.total_message_delay+= delay;
+		self.current_measurement.track_message_measurements(message,|m|m.total_message_delay+=delay);
 		if let Some(m) = self.current_temporal_measurement(cycle)
 		{
 			m.total_message_delay+=delay;
 		}
+		if let Some(batches) = self.batch_means.as_mut()
+		{
+			batches.track_message_delay(cycle,delay);
+		}
 
 		if !self.message_defined_statistics_definitions.is_empty()
 		{
 			let context_content = vec![
 				(String::from("delay"), ConfigurationValue::Number(delay as f64)),
+				(String::from("tag"), MessageTag::to_config_value(tag)),
 			];
 			let context = ConfigurationValue::Object( String::from("message"), context_content );
 			let path = Path::new(".");
@@ -864,15 +1039,34 @@ impl Statistics
 	{
 		if self.temporal_step>0
 		{
-			let index : usize = (cycle / self.temporal_step).try_into().unwrap();
+			let absolute_index : usize = (cycle / self.temporal_step).try_into().unwrap();
+			let index = absolute_index - self.temporal_offset;
 			if self.temporal_statistics.len()<=index
 			{
+				let first = self.temporal_statistics.len();
 				self.temporal_statistics.resize_with(index+1,Default::default);
-				self.temporal_statistics[index].begin_cycle = index as Time * self.temporal_step;
+				for (offset,measurement) in self.temporal_statistics[first..].iter_mut().enumerate()
+				{
+					measurement.begin_cycle = (self.temporal_offset+first+offset) as Time * self.temporal_step;
+				}
 			}
 			Some(&mut self.temporal_statistics[index])
 		} else { None }
 	}
+	///Removes the oldest periodic measurement, to be called once it has been completed and stored elsewhere.
+	pub fn pop_temporal_measurement(&mut self) -> StatisticMeasurement
+	{
+		let begin_cycle = self.temporal_offset as Time * self.temporal_step;
+		self.temporal_offset+=1;
+		if self.temporal_statistics.is_empty()
+		{
+			StatisticMeasurement{ begin_cycle, ..Default::default() }
+		}
+		else
+		{
+			self.temporal_statistics.remove(0)
+		}
+	}
 }
 
 ///The available statistical columns. Each column has a string for the header and a way to compute what to print each period.
@@ -977,4 +1171,1806 @@ impl From<ReportColumnKind> for ReportColumn
 	}
 }
 
+///Computational resources employed by a simulation. Reported in its results and in the journal of the experiment.
+#[derive(Debug,Clone,Copy,Default)]
+#[cfg_attr(feature="serde",derive(serde::Serialize))]
+pub struct ResourceUsage
+{
+	///Seconds elapsed since the creation of the simulation.
+	pub wall_time: f64,
+	///Seconds of CPU, user plus system, employed by the process since the creation of the simulation.
+	pub cpu_time: Option<f64>,
+	///Peak resident set size of the process, in kibibytes. Note this is of the whole process, which may have run other simulations before.
+	pub high_water_mark: Option<u64>,
+}
+
+impl ResourceUsage
+{
+	///The seconds of CPU, user plus system, employed by this process until now.
+	pub fn process_cpu_time() -> Option<f64>
+	{
+		let stat = procfs::process::Process::myself().ok()?.stat().ok()?;
+		let tps = procfs::ticks_per_second().ok()? as f64;
+		Some( (stat.utime + stat.stime) as f64 / tps )
+	}
+	///The peak resident set size of this process until now, in kibibytes.
+	pub fn process_high_water_mark() -> Option<u64>
+	{
+		procfs::process::Process::myself().ok()?.status().ok()?.vmhwm
+	}
+}
+
+///The reason for which the phit at the head of an input buffer of a router has not advanced in a cycle.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum StallCause
+{
+	///The candidate exits lack credits for the phit in the next router or server.
+	NoCredits,
+	///There were no candidate exits, either from the routing or after applying the virtual channel policies.
+	NoCandidates,
+	///The phit requested some exit but it was given to another phit, or the exit was already in use.
+	LostAllocation,
+	///The candidate exits lack space in their output buffers in the router.
+	OutputBufferFull,
+	///The packet has not been completely received, as required by store-and-forward switching. See [Switching](crate::router::Switching).
+	IncompletePacket,
+}
+
+/**
+Cycles that phits at the head of the input buffers of the routers have been stalled, by [StallCause].
+Each cycle that a head phit does not advance counts once in its cause, so the sum of all causes is the total number of stalled phit-cycles.
+It is reported in the results as a `stall_cycles` object by the routers that track it, which are [Basic](crate::router::basic::Basic) and [InputOutput](crate::router::input_output::InputOutput).
+**/
+#[derive(Debug,Clone,Default,Quantifiable)]
+#[cfg_attr(feature="serde",derive(serde::Serialize))]
+pub struct StallCounts
+{
+	pub no_credits: Time,
+	pub no_candidates: Time,
+	pub lost_allocation: Time,
+	pub output_buffer_full: Time,
+	pub incomplete_packet: Time,
+}
+
+impl StallCounts
+{
+	pub fn add(&mut self, cause:StallCause, cycles:Time)
+	{
+		match cause
+		{
+			StallCause::NoCredits => self.no_credits+=cycles,
+			StallCause::NoCandidates => self.no_candidates+=cycles,
+			StallCause::LostAllocation => self.lost_allocation+=cycles,
+			StallCause::OutputBufferFull => self.output_buffer_full+=cycles,
+			StallCause::IncompletePacket => self.incomplete_packet+=cycles,
+		}
+	}
+	pub fn accumulate(&mut self, other:&StallCounts)
+	{
+		self.no_credits+=other.no_credits;
+		self.no_candidates+=other.no_candidates;
+		self.lost_allocation+=other.lost_allocation;
+		self.output_buffer_full+=other.output_buffer_full;
+		self.incomplete_packet+=other.incomplete_packet;
+	}
+	///The total of stalled cycles, of any cause.
+	pub fn total(&self) -> Time
+	{
+		self.no_credits + self.no_candidates + self.lost_allocation + self.output_buffer_full + self.incomplete_packet
+	}
+	pub fn to_config_value(&self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("StallCycles"),vec![
+			(String::from("no_credits"),ConfigurationValue::Number(self.no_credits as f64)),
+			(String::from("no_candidates"),ConfigurationValue::Number(self.no_candidates as f64)),
+			(String::from("lost_allocation"),ConfigurationValue::Number(self.lost_allocation as f64)),
+			(String::from("output_buffer_full"),ConfigurationValue::Number(self.output_buffer_full as f64)),
+			(String::from("incomplete_packet"),ConfigurationValue::Number(self.incomplete_packet as f64)),
+		])
+	}
+}
+
+/**
+Tracks the [StallCounts] of a router.
+Each time the router is processed it sets the cause of each head phit that cannot advance, and clears it for the phits that advance.
+The causes are counted in the next processing of the router, once for each elapsed cycle, as the router may sleep while its phits are stalled.
+**/
+#[derive(Debug,Clone)]
+pub struct StallTracker
+{
+	///`causes[port][virtual_channel]` for the head phit of each input buffer.
+	causes: Vec<Vec<Option<StallCause>>>,
+	pub counts: StallCounts,
+}
+
+impl StallTracker
+{
+	pub fn new(ports:usize, virtual_channels:usize) -> StallTracker
+	{
+		StallTracker{
+			causes: vec![vec![None;virtual_channels];ports],
+			counts: StallCounts::default(),
+		}
+	}
+	///Counts the causes set in the previous processing as lasting `cycles` cycles and clears them.
+	pub fn account(&mut self, cycles:Time)
+	{
+		for port_causes in self.causes.iter_mut()
+		{
+			for cause in port_causes.iter_mut()
+			{
+				if let Some(cause) = cause.take()
+				{
+					self.counts.add(cause,cycles);
+				}
+			}
+		}
+	}
+	pub fn set(&mut self, port:usize, virtual_channel:usize, cause:StallCause)
+	{
+		self.causes[port][virtual_channel]=Some(cause);
+	}
+	///The phit at the head of the buffer has advanced.
+	pub fn clear(&mut self, port:usize, virtual_channel:usize)
+	{
+		self.causes[port][virtual_channel]=None;
+	}
+}
+
+/**
+Extends the measured period of a simulation until the estimate of the accepted load stabilizes.
+Every `check_period` cycles after the warmup the accepted load since the warmup is computed and compared with the previous estimate.
+When the relative change has been within `tolerance` for `stable_checks` consecutive checks the estimate is considered stable.
+The simulation measures at least the configured `measured` cycles and stops when the estimate is stable. In any case it stops after `maximum_measured` cycles of measurement.
+
+```ignore
+measurement_extension: MeasurementExtension{
+	check_period: 1000,
+	tolerance: 0.01,
+	stable_checks: 3,//optional, defaults to 2.
+	maximum_measured: 200000,
+}
+```
+
+The result includes a `measurement_extension` object with the fields
+* `measured_cycles`: the cycles actually measured.
+* `checks`: the number of checks performed.
+* `converged`: whether the estimate became stable before reaching `maximum_measured`.
+* `relative_change`: the relative change of the estimate in the last check, a measure of the achieved confidence.
+**/
+#[derive(Debug,Clone)]
+pub struct MeasurementExtension
+{
+	///Cycles between consecutive checks of the estimate.
+	pub check_period: Time,
+	///Maximum relative change between consecutive estimates to consider them equal.
+	pub tolerance: f64,
+	///Number of consecutive checks within the tolerance required to consider the estimate stable.
+	pub stable_checks: usize,
+	///The maximum number of measured cycles.
+	pub maximum_measured: Time,
+	///The estimate in the previous check.
+	last_estimate: Option<f64>,
+	///The relative change in the last check.
+	last_relative_change: Option<f64>,
+	///How many consecutive checks have been within the tolerance.
+	consecutive_stable: usize,
+	///Number of checks performed.
+	checks: usize,
+}
+
+impl MeasurementExtension
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<MeasurementExtension,Error>
+	{
+		let mut check_period=None;
+		let mut tolerance=None;
+		let mut stable_checks=2;
+		let mut maximum_measured=None;
+		match_object!(cv,"MeasurementExtension",value,
+			"check_period" => check_period=Some(value.as_time()?),
+			"tolerance" => tolerance=Some(value.as_f64()?),
+			"stable_checks" => stable_checks=value.as_usize()?,
+			"maximum_measured" => maximum_measured=Some(value.as_time()?),
+		);
+		let check_period=check_period.ok_or_else(||cv.ill("There were no check_period"))?;
+		if check_period==0
+		{
+			return Err(cv.ill("check_period must be positive."));
+		}
+		Ok(MeasurementExtension{
+			check_period,
+			tolerance:tolerance.ok_or_else(||cv.ill("There were no tolerance"))?,
+			stable_checks,
+			maximum_measured:maximum_measured.ok_or_else(||cv.ill("There were no maximum_measured"))?,
+			last_estimate:None,
+			last_relative_change:None,
+			consecutive_stable:0,
+			checks:0,
+		})
+	}
+	///Whether the estimate should be checked after `measured_cycles` of measurement.
+	pub fn is_check_cycle(&self, measured_cycles:Time) -> bool
+	{
+		measured_cycles>0 && measured_cycles%self.check_period==0
+	}
+	///Track a new value of the estimate.
+	pub fn track(&mut self, estimate:f64)
+	{
+		self.checks+=1;
+		if let Some(last)=self.last_estimate
+		{
+			let relative_change = if last==0.0 { if estimate==0.0 {0.0} else {f64::INFINITY} } else { ((estimate-last)/last).abs() };
+			self.last_relative_change=Some(relative_change);
+			if relative_change<=self.tolerance { self.consecutive_stable+=1 } else { self.consecutive_stable=0 }
+		}
+		self.last_estimate=Some(estimate);
+	}
+	///Whether the estimate has been stable for enough checks.
+	pub fn is_stable(&self) -> bool
+	{
+		self.consecutive_stable>=self.stable_checks
+	}
+	///Whether the measurement should continue after `measured_cycles`, given the minimum `measured` cycles.
+	pub fn should_continue(&self, measured_cycles:Time, measured:Time) -> bool
+	{
+		measured_cycles<self.maximum_measured && (measured_cycles<measured || !self.is_stable())
+	}
+	///Summary to include into the results.
+	pub fn result(&self, measured_cycles:Time) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("MeasurementExtension"),vec![
+			(String::from("measured_cycles"),ConfigurationValue::Number(measured_cycles as f64)),
+			(String::from("checks"),ConfigurationValue::Number(self.checks as f64)),
+			(String::from("converged"),if self.is_stable() {ConfigurationValue::True} else {ConfigurationValue::False}),
+			(String::from("relative_change"),ConfigurationValue::Number(self.last_relative_change.unwrap_or(f64::NAN))),
+		])
+	}
+}
+
+/**
+Checks whether the network has reached a steady state, to detect a warmup too short for the offered load.
+During the warmup the accepted load is computed over windows of `window` cycles, and the steady state is reached when it has changed less than `tolerance`, relatively, for `stable_windows` consecutive windows.
+When `maximum_warmup` is given the warmup is extended, a window at a time, until the steady state is reached or the warmup reaches `maximum_warmup` cycles.
+Otherwise the configured warmup is kept and the result only flags whether it was enough.
+
+Independently, the accepted load of the first and second halves of the measured period are compared, as a steady measurement should give similar values.
+
+```ignore
+steady_state_detection: SteadyStateDetection{
+	window: 1000,
+	tolerance: 0.05,
+	stable_windows: 3,//optional, defaults to 2.
+	maximum_warmup: 100000,//optional, to extend the warmup.
+}
+```
+
+The result includes a `steady_state` object with the fields
+* `reached`: whether the steady state was reached during the warmup.
+* `reached_cycle`: the cycle in which it was reached, or 0 if it was not.
+* `warmup_cycles`: the warmup actually simulated, which is greater than the configured one when it has been extended.
+* `first_half_accepted_load` and `second_half_accepted_load`: the accepted load in each half of the measured windows.
+* `relative_difference`: the relative difference between both halves.
+* `stable_measurement`: whether that difference is within the `tolerance`.
+**/
+#[derive(Debug,Clone)]
+pub struct SteadyStateDetection
+{
+	///Cycles of each window in which the accepted load is computed.
+	pub window: Time,
+	///Maximum relative change between consecutive windows to consider them equal.
+	pub tolerance: f64,
+	///Number of consecutive windows within the tolerance required to consider the steady state reached.
+	pub stable_windows: usize,
+	///When given, the warmup is extended up to this number of cycles until the steady state is reached.
+	pub maximum_warmup: Option<Time>,
+	///The total consumed phits at the last window.
+	last_consumed_phits: usize,
+	///The accepted load of the last window of the warmup.
+	last_load: Option<f64>,
+	///How many consecutive windows have been within the tolerance.
+	consecutive_stable: usize,
+	///The cycle in which the steady state was reached.
+	reached_cycle: Option<Time>,
+	///The accepted load of each window of the measured period.
+	measured_loads: Vec<f64>,
+}
+
+impl SteadyStateDetection
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<SteadyStateDetection,Error>
+	{
+		let mut window=None;
+		let mut tolerance=None;
+		let mut stable_windows=2;
+		let mut maximum_warmup=None;
+		match_object!(cv,"SteadyStateDetection",value,
+			"window" => window=Some(value.as_time()?),
+			"tolerance" => tolerance=Some(value.as_f64()?),
+			"stable_windows" => stable_windows=value.as_usize()?,
+			"maximum_warmup" => maximum_warmup=Some(value.as_time()?),
+		);
+		let window=window.ok_or_else(||cv.ill("There were no window"))?;
+		if window==0
+		{
+			return Err(cv.ill("window must be positive."));
+		}
+		Ok(SteadyStateDetection{
+			window,
+			tolerance:tolerance.ok_or_else(||cv.ill("There were no tolerance"))?,
+			stable_windows,
+			maximum_warmup,
+			last_consumed_phits:0,
+			last_load:None,
+			consecutive_stable:0,
+			reached_cycle:None,
+			measured_loads:vec![],
+		})
+	}
+	///Whether a window ends after `cycles` cycles from its reference, the beginning of the simulation or of the measurement.
+	pub fn is_window_cycle(&self, cycles:Time) -> bool
+	{
+		cycles>0 && cycles%self.window==0
+	}
+	///The accepted load of the window ending now, given the phits consumed in total since the last reset of the statistics.
+	fn window_load(&mut self, consumed_phits:usize, num_servers:usize) -> f64
+	{
+		let load = (consumed_phits-self.last_consumed_phits) as f64/self.window as f64/num_servers as f64;
+		self.last_consumed_phits=consumed_phits;
+		load
+	}
+	///Track a window of the warmup ending at `cycle`.
+	pub fn track_warmup(&mut self, cycle:Time, consumed_phits:usize, num_servers:usize)
+	{
+		let load = self.window_load(consumed_phits,num_servers);
+		if let Some(last)=self.last_load
+		{
+			let relative_change = if last==0.0 { if load==0.0 {0.0} else {f64::INFINITY} } else { ((load-last)/last).abs() };
+			if relative_change<=self.tolerance { self.consecutive_stable+=1 } else { self.consecutive_stable=0 }
+			if self.reached_cycle.is_none() && self.consecutive_stable>=self.stable_windows
+			{
+				self.reached_cycle=Some(cycle);
+			}
+		}
+		self.last_load=Some(load);
+	}
+	///Track a window of the measured period.
+	pub fn track_measured(&mut self, consumed_phits:usize, num_servers:usize)
+	{
+		let load = self.window_load(consumed_phits,num_servers);
+		self.measured_loads.push(load);
+	}
+	///Whether the steady state has been reached.
+	pub fn is_reached(&self) -> bool
+	{
+		self.reached_cycle.is_some()
+	}
+	///Whether the warmup, ending at `cycle`, should be extended by another window.
+	pub fn should_extend_warmup(&self, cycle:Time) -> bool
+	{
+		match self.maximum_warmup
+		{
+			Some(maximum) => !self.is_reached() && cycle<maximum,
+			None => false,
+		}
+	}
+	///To be called when the statistics are reset at the end of the warmup.
+	pub fn reset(&mut self)
+	{
+		self.last_consumed_phits=0;
+	}
+	///Summary to include into the results.
+	pub fn result(&self, warmup_cycles:Time) -> ConfigurationValue
+	{
+		let half = self.measured_loads.len()/2;
+		let average = |loads:&[f64]| if loads.is_empty() { f64::NAN } else { loads.iter().sum::<f64>()/loads.len() as f64 };
+		let first = average(&self.measured_loads[..half]);
+		let second = average(&self.measured_loads[half..]);
+		let relative_difference = if first==0.0 && second==0.0 { 0.0 } else { ((second-first)/first).abs() };
+		ConfigurationValue::Object(String::from("SteadyState"),vec![
+			(String::from("reached"),if self.is_reached() {ConfigurationValue::True} else {ConfigurationValue::False}),
+			(String::from("reached_cycle"),ConfigurationValue::Number(self.reached_cycle.unwrap_or(0) as f64)),
+			(String::from("warmup_cycles"),ConfigurationValue::Number(warmup_cycles as f64)),
+			(String::from("first_half_accepted_load"),ConfigurationValue::Number(first)),
+			(String::from("second_half_accepted_load"),ConfigurationValue::Number(second)),
+			(String::from("relative_difference"),ConfigurationValue::Number(relative_difference)),
+			(String::from("stable_measurement"),if relative_difference<=self.tolerance {ConfigurationValue::True} else {ConfigurationValue::False}),
+		])
+	}
+}
+
+/**
+Writes the periodic measurements into a file as they are completed, instead of keeping them in memory.
+It is enabled by setting `statistics_temporal_file` in the configuration, together with a non-zero `statistics_temporal_step`.
+The file has a header line followed by a line per period with comma separated values.
+The result then includes `temporal_statistics: TemporalStatisticsFile{filename, samples}` instead of the arrays.
+A relative `filename` is created inside the directory of the run, see [Simulation::new_in_directory](crate::Simulation::new_in_directory).
+**/
+#[derive(Debug)]
+pub struct TemporalStatisticsStream
+{
+	///The path of the file.
+	pub filename: String,
+	///Where the samples are written.
+	writer: std::io::BufWriter<std::fs::File>,
+	///Number of samples written.
+	pub samples: usize,
+}
+
+impl TemporalStatisticsStream
+{
+	///The names of the columns, in the order they are written.
+	pub const COLUMNS: [&'static str;8] = ["begin_cycle","injected_load","accepted_load","average_message_delay","average_packet_network_delay","server_generation_jain_index","server_consumption_jain_index","average_packet_hops"];
+	///Creates the file `filename` inside the `directory`.
+	pub fn new(filename:&str, directory:&Path) -> Result<TemporalStatisticsStream,Error>
+	{
+		use std::io::Write;
+		let path = directory.join(filename);
+		let file = std::fs::File::create(&path).map_err(|error|error!(could_not_generate_file,path.clone(),error))?;
+		let mut writer = std::io::BufWriter::new(file);
+		writeln!(writer,"{}",Self::COLUMNS.join(",")).map_err(|error|error!(could_not_generate_file,path,error))?;
+		Ok(TemporalStatisticsStream{
+			filename: filename.to_string(),
+			writer,
+			samples: 0,
+		})
+	}
+	///Append a sample, with a value for each of the `COLUMNS`.
+	pub fn write_sample(&mut self, values:&[f64])
+	{
+		use std::io::Write;
+		let line:Vec<String> = values.iter().map(|v|v.to_string()).collect();
+		writeln!(self.writer,"{}",line.join(",")).expect("Could not write the temporal statistics file");
+		self.samples+=1;
+	}
+	///Ensure all the samples are written into the file.
+	pub fn flush(&mut self)
+	{
+		use std::io::Write;
+		self.writer.flush().expect("Could not write the temporal statistics file");
+	}
+	///The entry to include into the results.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("TemporalStatisticsFile"),vec![
+			(String::from("filename"),ConfigurationValue::Literal(self.filename.clone())),
+			(String::from("samples"),ConfigurationValue::Number(self.samples as f64)),
+		])
+	}
+}
+
+/**
+Writes a record for each packet consumed along the whole simulation, including the warmup, to allow a post-processing of the trajectories of the packets.
+It is enabled by setting `packet_trace_file: "trace.csv"` in the configuration.
+The file has a header line followed by a line per packet with the comma separated fields
+`origin,destination,message_size,packet_index,packet_size,generation_cycle,injection_cycle,consumption_cycle,hops,routers,link_classes,virtual_channels,hop_cycles,collective,phase,event`.
+* `origin` and `destination` are the servers of the message, of `message_size` phits. The packet is the number `packet_index` of the message and has `packet_size` phits.
+* `generation_cycle` is the cycle in which the message was created, `injection_cycle` the one in which the packet entered its first router, and `consumption_cycle` the one in which its last phit was consumed.
+* `hops` is the number of router-to-router links traversed.
+* `routers` are the routers visited, `link_classes` the class of the link used to enter each of them, `virtual_channels` the virtual channel used in that link, and `hop_cycles` the cycle in which the packet head entered each of them.
+  These four fields are lists separated by spaces with an element per router, including the link from the server. A virtual channel may be `-` when it has not been set.
+* `collective`, `phase`, and `event` are the fields of the [MessageTag](crate::MessageTag) of the message, empty when not set.
+
+The results include `packet_trace: PacketTraceFile{filename, packets}`.
+A relative filename is created inside the directory of the run, see [Simulation::new_in_directory](crate::Simulation::new_in_directory).
+**/
+#[derive(Debug)]
+pub struct PacketTrace
+{
+	///The path of the file.
+	pub filename: String,
+	///Where the records are written.
+	writer: std::io::BufWriter<std::fs::File>,
+	///Number of packets written.
+	pub packets: usize,
+}
+
+impl Quantifiable for PacketTrace
+{
+	fn total_memory(&self) -> usize
+	{
+		std::mem::size_of::<PacketTrace>() + self.filename.total_memory()
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl PacketTrace
+{
+	///The names of the fields, in the order they are written.
+	pub const COLUMNS: [&'static str;16] = ["origin","destination","message_size","packet_index","packet_size","generation_cycle","injection_cycle","consumption_cycle","hops","routers","link_classes","virtual_channels","hop_cycles","collective","phase","event"];
+	///Creates the file `filename` inside the `directory`.
+	pub fn new(filename:&str, directory:&Path) -> Result<PacketTrace,Error>
+	{
+		use std::io::Write;
+		let path = directory.join(filename);
+		let file = std::fs::File::create(&path).map_err(|error|error!(could_not_generate_file,path.clone(),error))?;
+		let mut writer = std::io::BufWriter::new(file);
+		writeln!(writer,"{}",Self::COLUMNS.join(",")).map_err(|error|error!(could_not_generate_file,path,error))?;
+		Ok(PacketTrace{
+			filename: filename.to_string(),
+			writer,
+			packets: 0,
+		})
+	}
+	///Append the record of a `packet` consumed in `cycle`. Its `extra` must have been tracked.
+	pub fn write_packet(&mut self, cycle:Time, packet:&Packet)
+	{
+		use std::io::Write;
+		let join = |values:Vec<String>|values.join(" ");
+		let extra = packet.extra.borrow();
+		let extra = extra.as_ref().expect("the packet has not tracked its extra information");
+		let message = &packet.message;
+		let tag = message.tag.as_deref().cloned().unwrap_or_default();
+		let field = |value:Option<usize>|value.map(|x|x.to_string()).unwrap_or_default();
+		writeln!(self.writer,"{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+			message.origin,
+			message.destination,
+			message.size,
+			packet.index,
+			packet.size,
+			message.creation_cycle,
+			*packet.cycle_into_network.borrow(),
+			cycle,
+			packet.routing_info.borrow().hops,
+			join(extra.id_switches.iter().map(|x|x.to_string()).collect()),
+			join(extra.link_classes.iter().map(|x|x.to_string()).collect()),
+			join(extra.entry_virtual_channels.iter().map(|x|match x{ Some(vc)=>vc.to_string(), None=>String::from("-") }).collect()),
+			join(extra.cycle_per_hop.iter().map(|x|x.to_string()).collect()),
+			field(tag.collective),
+			field(tag.phase),
+			field(tag.event),
+		).expect("Could not write the packet trace file");
+		self.packets+=1;
+	}
+	///Ensure all the records are written into the file.
+	pub fn flush(&mut self)
+	{
+		use std::io::Write;
+		self.writer.flush().expect("Could not write the packet trace file");
+	}
+	///The entry to include into the results.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("PacketTraceFile"),vec![
+			(String::from("filename"),ConfigurationValue::Literal(self.filename.clone())),
+			(String::from("packets"),ConfigurationValue::Number(self.packets as f64)),
+		])
+	}
+}
+
+/**
+Records the busy/idle state of some selected links during the measured period, as run-length encoded traces written into a file at the end of the simulation.
+A link is busy in a cycle when a phit arrives through it. It is enabled with `statistics_link_traces` in the configuration.
+```ignore
+statistics_link_traces: LinkTraces{
+	filename: "link_traces.csv",
+	//Links given by their endpoint routers, [source,destination]. All parallel links are included.
+	links: [[0,1],[5,3]],
+	//Optionally, include every link between routers of these classes.
+	classes: [1],
+}
+```
+The file has a header line and a line per traced link, with the fields `source,destination,port,link_class,runs`, where `port` is the port of the destination router,
+and `runs` are the space separated lengths of the alternating idle and busy periods, starting with an idle period, which may be of length 0.
+The results include `link_traces: LinkTracesFile{filename, links, begin_cycle, end_cycle}`.
+A relative `filename` is created inside the directory of the run, see [Simulation::new_in_directory](crate::Simulation::new_in_directory).
+**/
+#[derive(Debug)]
+pub struct LinkTraces
+{
+	///The path of the file, as configured.
+	pub filename: String,
+	///The path in which the file is created.
+	path: PathBuf,
+	///`trace_index[router][port]` is the index in `traces` of the link arriving at that port, if traced.
+	trace_index: Vec<Vec<Option<usize>>>,
+	///The traces of the selected links.
+	traces: Vec<LinkTrace>,
+	///The cycle in which the traces begin.
+	begin_cycle: Time,
+	///The cycle in which the traces were written, if already.
+	end_cycle: Option<Time>,
+}
+
+///The trace of a single link.
+#[derive(Debug)]
+struct LinkTrace
+{
+	source: usize,
+	destination: usize,
+	port: usize,
+	link_class: usize,
+	///The busy periods, as `(first_cycle,length)`.
+	busy: Vec<(Time,Time)>,
+}
 
+impl LinkTraces
+{
+	///The file will be created inside the `directory`.
+	pub fn new(cv:&ConfigurationValue, topology:&dyn Topology, directory:&Path) -> Result<LinkTraces,Error>
+	{
+		let mut filename=None;
+		let mut links:Vec<(usize,usize)>=vec![];
+		let mut classes:Vec<usize>=vec![];
+		match_object!(cv,"LinkTraces",value,
+			"filename" => filename=Some(value.as_str()?.to_string()),
+			"links" => links=value.as_array()?.iter().map(|link|{
+				let endpoints = link.as_array()?;
+				if endpoints.len()!=2
+				{
+					return Err(link.ill("Each link must be given as [source,destination]"));
+				}
+				Ok((endpoints[0].as_usize()?,endpoints[1].as_usize()?))
+			}).collect::<Result<_,Error>>()?,
+			"classes" => classes=value.as_array()?.iter().map(|c|c.as_usize()).collect::<Result<_,_>>()?,
+		);
+		let filename=filename.ok_or_else(||cv.ill("There were no filename"))?;
+		let mut trace_index = vec![];
+		let mut traces = vec![];
+		for router in 0..topology.num_routers()
+		{
+			let mut router_index = vec![None;topology.ports(router)];
+			for (port,index) in router_index.iter_mut().enumerate()
+			{
+				if let (Location::RouterPort{router_index:source,..},link_class) = topology.neighbour(router,port)
+				{
+					if links.contains(&(source,router)) || classes.contains(&link_class)
+					{
+						*index = Some(traces.len());
+						traces.push(LinkTrace{ source, destination:router, port, link_class, busy:vec![] });
+					}
+				}
+			}
+			trace_index.push(router_index);
+		}
+		for &(source,destination) in links.iter()
+		{
+			if !traces.iter().any(|trace|trace.source==source && trace.destination==destination)
+			{
+				return Err(cv.ill(&format!("There is no link from router {} to router {} to trace",source,destination)));
+			}
+		}
+		Ok(LinkTraces{
+			path: directory.join(&filename),
+			filename,
+			trace_index,
+			traces,
+			begin_cycle: 0,
+			end_cycle: None,
+		})
+	}
+	///Register that a phit has arrived to the `port` of the `router` in the `cycle`.
+	pub fn track(&mut self, router:usize, port:usize, cycle:Time)
+	{
+		if let Some(index) = self.trace_index[router][port]
+		{
+			let busy = &mut self.traces[index].busy;
+			match busy.last_mut()
+			{
+				Some((first,length)) if *first+*length==cycle => *length+=1,
+				Some((first,length)) if *first+*length>cycle => (),//Already marked as busy.
+				_ => busy.push((cycle,1)),
+			}
+		}
+	}
+	///Discard what has been recorded, to begin the traces at `next_cycle`.
+	pub fn reset(&mut self, next_cycle:Time)
+	{
+		self.begin_cycle = next_cycle;
+		for trace in self.traces.iter_mut()
+		{
+			trace.busy.clear();
+		}
+	}
+	///Write the traces into the file, considering the period to end at `end_cycle`.
+	pub fn write(&mut self, end_cycle:Time)
+	{
+		use std::io::Write;
+		let file = std::fs::File::create(&self.path).unwrap_or_else(|error|panic!("Could not create the link traces file {}: {}",self.filename,error));
+		let mut writer = std::io::BufWriter::new(file);
+		writeln!(writer,"source,destination,port,link_class,runs").expect("Could not write the link traces file");
+		for trace in self.traces.iter()
+		{
+			let mut runs = vec![];
+			let mut current = self.begin_cycle;
+			for &(first,length) in trace.busy.iter()
+			{
+				runs.push((first-current).to_string());
+				runs.push(length.to_string());
+				current = first+length;
+			}
+			if current<end_cycle
+			{
+				runs.push((end_cycle-current).to_string());
+			}
+			writeln!(writer,"{},{},{},{},{}",trace.source,trace.destination,trace.port,trace.link_class,runs.join(" ")).expect("Could not write the link traces file");
+		}
+		writer.flush().expect("Could not write the link traces file");
+		self.end_cycle = Some(end_cycle);
+	}
+	///The entry to include into the results.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("LinkTracesFile"),vec![
+			(String::from("filename"),ConfigurationValue::Literal(self.filename.clone())),
+			(String::from("links"),ConfigurationValue::Number(self.traces.len() as f64)),
+			(String::from("begin_cycle"),ConfigurationValue::Number(self.begin_cycle as f64)),
+			(String::from("end_cycle"),ConfigurationValue::Number(self.end_cycle.unwrap_or(self.begin_cycle) as f64)),
+		])
+	}
+}
+
+/**
+Reports the utilization of each global link, this is, of the links of some given classes, such as the links between groups of a Dragonfly.
+A global link whose utilization exceeds `warning_factor` times the average of the global links is considered a hotspot, which may reveal an adversarial alignment between the traffic and the topology.
+For each hotspot a warning is printed and the pairs of groups and the sub-traffics that sent the most packets through it are reported.
+The groups are those of the Dragonfly topologies. In other topologies each router is its own group.
+The sub-traffics are the indices of the summands of a TrafficSum.
+It is enabled with `statistics_global_links` in the configuration.
+```ignore
+statistics_global_links: GlobalLinkReport{
+	//The classes of the global links. By default [1], the global links of a Dragonfly.
+	classes: [1],
+	//The factor over the average utilization to consider a link a hotspot. By default 2.
+	warning_factor: 2.0,
+	//The number of pairs of groups and of sub-traffics to report for each hotspot. By default 3.
+	top: 3,
+}
+```
+The results include `global_links: GlobalLinkReport{average_utilization, maximum_utilization, links, hotspots}`.
+Each entry of `links` is `GlobalLink{source, destination, port, utilization}`, where `port` is the port of the destination router.
+Each entry of `hotspots` is `GlobalLinkHotspot{source, destination, port, utilization, ratio, group_pairs, traffics}`, where `ratio` is the utilization divided by the average,
+`group_pairs` are `GroupPair{source_group, destination_group, packets}`, and `traffics` are `SubTraffic{index, packets}`, with `index` being `None` for messages not generated by a TrafficSum.
+**/
+#[derive(Debug)]
+pub struct GlobalLinkReport
+{
+	///Utilization above `warning_factor` times the average is reported as a hotspot.
+	warning_factor: f64,
+	///Number of group pairs and sub-traffics reported for each hotspot.
+	top: usize,
+	///The group of each router.
+	router_group: Vec<usize>,
+	///`link_index[router][port]` is the index in `links` of the global link arriving at that port, if any.
+	link_index: Vec<Vec<Option<usize>>>,
+	///The global links.
+	links: Vec<GlobalLink>,
+	///The cycle in which the measurement begins.
+	begin_cycle: Time,
+}
+
+///The measurements of a single global link.
+#[derive(Debug)]
+struct GlobalLink
+{
+	source: usize,
+	destination: usize,
+	port: usize,
+	phit_arrivals: usize,
+	///Packets that have crossed the link by `(source_group,destination_group)`.
+	group_pairs: HashMap<(usize,usize),usize>,
+	///Packets that have crossed the link by the index of the sub-traffic.
+	traffics: HashMap<Option<usize>,usize>,
+}
+
+impl GlobalLinkReport
+{
+	pub fn new(cv:&ConfigurationValue, topology:&dyn Topology) -> Result<GlobalLinkReport,Error>
+	{
+		let mut classes:Vec<usize>=vec![1];
+		let mut warning_factor=2.0;
+		let mut top=3;
+		match_object!(cv,"GlobalLinkReport",value,
+			"classes" => classes=value.as_array()?.iter().map(|c|c.as_usize()).collect::<Result<_,_>>()?,
+			"warning_factor" => warning_factor=value.as_f64()?,
+			"top" => top=value.as_usize()?,
+		);
+		let router_group = match topology.dragonfly_size()
+		{
+			Some(size) => (0..topology.num_routers()).map(|router|size.unpack(router).1).collect(),
+			None => (0..topology.num_routers()).collect(),
+		};
+		let mut link_index = vec![];
+		let mut links = vec![];
+		for router in 0..topology.num_routers()
+		{
+			let mut router_index = vec![None;topology.ports(router)];
+			for (port,index) in router_index.iter_mut().enumerate()
+			{
+				if let (Location::RouterPort{router_index:source,..},link_class) = topology.neighbour(router,port)
+				{
+					if classes.contains(&link_class)
+					{
+						*index = Some(links.len());
+						links.push(GlobalLink{ source, destination:router, port, phit_arrivals:0, group_pairs:HashMap::new(), traffics:HashMap::new() });
+					}
+				}
+			}
+			link_index.push(router_index);
+		}
+		Ok(GlobalLinkReport{
+			warning_factor,
+			top,
+			router_group,
+			link_index,
+			links,
+			begin_cycle: 0,
+		})
+	}
+	///Register that a phit has arrived to the `port` of the `router`. The packet is counted at its first phit.
+	pub fn track(&mut self, router:usize, port:usize, phit:&Phit, topology:&dyn Topology)
+	{
+		if let Some(index) = self.link_index[router][port]
+		{
+			let link = &mut self.links[index];
+			link.phit_arrivals += 1;
+			if phit.is_begin()
+			{
+				let message = &phit.packet.message;
+				let router_group = &self.router_group;
+				let group = |server:usize| match topology.server_neighbour(server).0
+				{
+					Location::RouterPort{router_index,..} => router_group[router_index],
+					_ => panic!("The server is not attached to a router"),
+				};
+				*link.group_pairs.entry((group(message.origin),group(message.destination))).or_insert(0) += 1;
+				*link.traffics.entry(message.id_traffic).or_insert(0) += 1;
+			}
+		}
+	}
+	///Discard what has been recorded, to begin the measurement at `next_cycle`.
+	pub fn reset(&mut self, next_cycle:Time)
+	{
+		self.begin_cycle = next_cycle;
+		for link in self.links.iter_mut()
+		{
+			link.phit_arrivals = 0;
+			link.group_pairs.clear();
+			link.traffics.clear();
+		}
+	}
+	///The entry to include into the results, considering the measurement to end at `end_cycle`. A warning is printed for each hotspot.
+	pub fn result(&self, end_cycle:Time) -> ConfigurationValue
+	{
+		let cycles = end_cycle.saturating_sub(self.begin_cycle).max(1) as f64;
+		let utilization = |link:&GlobalLink| link.phit_arrivals as f64 / cycles;
+		let average = if self.links.is_empty() { 0.0 } else { self.links.iter().map(utilization).sum::<f64>() / self.links.len() as f64 };
+		let maximum = self.links.iter().map(utilization).fold(0.0,f64::max);
+		let links = self.links.iter().map(|link|ConfigurationValue::Object(String::from("GlobalLink"),vec![
+			(String::from("source"),ConfigurationValue::Number(link.source as f64)),
+			(String::from("destination"),ConfigurationValue::Number(link.destination as f64)),
+			(String::from("port"),ConfigurationValue::Number(link.port as f64)),
+			(String::from("utilization"),ConfigurationValue::Number(utilization(link))),
+		])).collect();
+		let mut hotspots = vec![];
+		for link in self.links.iter().filter(|link|average>0.0 && utilization(link)>self.warning_factor*average)
+		{
+			let ratio = utilization(link)/average;
+			let mut group_pairs : Vec<(&(usize,usize),&usize)> = link.group_pairs.iter().collect();
+			group_pairs.sort_by(|a,b|b.1.cmp(a.1).then(a.0.cmp(b.0)));
+			group_pairs.truncate(self.top);
+			let mut traffics : Vec<(&Option<usize>,&usize)> = link.traffics.iter().collect();
+			traffics.sort_by(|a,b|b.1.cmp(a.1).then(a.0.cmp(b.0)));
+			traffics.truncate(self.top);
+			let culprits = group_pairs.iter().map(|((source_group,destination_group),packets)|format!("{}->{} ({} packets)",source_group,destination_group,packets)).collect::<Vec<String>>().join(", ");
+			println!("WARNING: the global link from router {} to router {} has a utilization of {:.3}, {:.1} times the average. The main pairs of groups using it are {}.",link.source,link.destination,utilization(link),ratio,culprits);
+			hotspots.push(ConfigurationValue::Object(String::from("GlobalLinkHotspot"),vec![
+				(String::from("source"),ConfigurationValue::Number(link.source as f64)),
+				(String::from("destination"),ConfigurationValue::Number(link.destination as f64)),
+				(String::from("port"),ConfigurationValue::Number(link.port as f64)),
+				(String::from("utilization"),ConfigurationValue::Number(utilization(link))),
+				(String::from("ratio"),ConfigurationValue::Number(ratio)),
+				(String::from("group_pairs"),ConfigurationValue::Array(group_pairs.iter().map(|((source_group,destination_group),packets)|ConfigurationValue::Object(String::from("GroupPair"),vec![
+					(String::from("source_group"),ConfigurationValue::Number(*source_group as f64)),
+					(String::from("destination_group"),ConfigurationValue::Number(*destination_group as f64)),
+					(String::from("packets"),ConfigurationValue::Number(**packets as f64)),
+				])).collect())),
+				(String::from("traffics"),ConfigurationValue::Array(traffics.iter().map(|(index,packets)|ConfigurationValue::Object(String::from("SubTraffic"),vec![
+					(String::from("index"),match index { Some(index) => ConfigurationValue::Number(*index as f64), None => ConfigurationValue::None }),
+					(String::from("packets"),ConfigurationValue::Number(**packets as f64)),
+				])).collect())),
+			]));
+		}
+		ConfigurationValue::Object(String::from("GlobalLinkReport"),vec![
+			(String::from("average_utilization"),ConfigurationValue::Number(average)),
+			(String::from("maximum_utilization"),ConfigurationValue::Number(maximum)),
+			(String::from("links"),ConfigurationValue::Array(links)),
+			(String::from("hotspots"),ConfigurationValue::Array(hotspots)),
+		])
+	}
+}
+
+/**
+Periodic samples of the number of phits in each input and output buffer of the routers during the measured period.
+It is enabled by setting `statistics_buffer_occupancy_step` in the configuration to the number of cycles between samples.
+To keep the size of the result bounded, once there are `statistics_buffer_occupancy_maximum_samples` samples (by default 256) each pair of consecutive samples is merged into their average and the step is doubled.
+Thus each value is the average of the occupancy sampled at `2^k` instants.
+
+The results include `buffer_occupancy: BufferOccupancy{begin_cycle, step, input, output}`, where `step` is the final number of cycles covered by each value,
+`input[router][port][virtual_channel]` is the array of values for that input buffer, and similarly `output` for the output buffers.
+The routers without output buffers have an empty array in `output`, and routers not exposing their buffers have empty arrays in both.
+**/
+#[derive(Debug)]
+pub struct BufferOccupancySeries
+{
+	///The cycles between samples.
+	step: Time,
+	///The number of values from which merging begins.
+	maximum_samples: usize,
+	///The cycle in which the series begins.
+	begin_cycle: Time,
+	///How many samples are averaged into each value.
+	samples_per_value: usize,
+	///How many samples have been accumulated into `pending` since the last value.
+	pending_samples: usize,
+	///The series of each input buffer, indexed by `[router][port][virtual_channel]`.
+	input: Vec<Vec<Vec<OccupancySeries>>>,
+	///The series of each output buffer, indexed by `[router][port][virtual_channel]`.
+	output: Vec<Vec<Vec<OccupancySeries>>>,
+}
+
+///The series of a single buffer.
+#[derive(Clone,Debug,Default)]
+struct OccupancySeries
+{
+	///The average occupancy in each period.
+	values: Vec<f64>,
+	///The sum of the samples not yet averaged into a value.
+	pending: usize,
+}
+
+impl BufferOccupancySeries
+{
+	pub fn new(step:Time, maximum_samples:Option<usize>) -> BufferOccupancySeries
+	{
+		assert!(step>0,"statistics_buffer_occupancy_step must be positive");
+		let maximum_samples = maximum_samples.unwrap_or(256);
+		assert!(maximum_samples>=2 && maximum_samples%2==0,"statistics_buffer_occupancy_maximum_samples must be even and at least 2");
+		BufferOccupancySeries{
+			step,
+			maximum_samples,
+			begin_cycle: 0,
+			samples_per_value: 1,
+			pending_samples: 0,
+			input: vec![],
+			output: vec![],
+		}
+	}
+	///Whether a sample should be taken at `cycle`.
+	pub fn is_sample_cycle(&self, cycle:Time) -> bool
+	{
+		cycle>self.begin_cycle && (cycle-self.begin_cycle)%self.step==0
+	}
+	///Samples the occupancy of the buffers of every router.
+	pub fn sample(&mut self, network:&Network)
+	{
+		if self.input.is_empty()
+		{
+			for router in network.routers.iter()
+			{
+				let occupancy = router.borrow().buffer_occupancy().unwrap_or_default();
+				self.input.push(occupancy.input.iter().map(|port|vec![OccupancySeries::default();port.len()]).collect());
+				self.output.push(occupancy.output.iter().map(|port|vec![OccupancySeries::default();port.len()]).collect());
+			}
+		}
+		for (router_index,router) in network.routers.iter().enumerate()
+		{
+			if let Some(occupancy) = router.borrow().buffer_occupancy()
+			{
+				for (series,current) in [(&mut self.input[router_index],&occupancy.input),(&mut self.output[router_index],&occupancy.output)].iter_mut()
+				{
+					for (port_series,port_current) in series.iter_mut().zip(current.iter())
+					{
+						for (buffer_series,phits) in port_series.iter_mut().zip(port_current.iter())
+						{
+							buffer_series.pending += phits;
+						}
+					}
+				}
+			}
+		}
+		self.pending_samples += 1;
+		if self.pending_samples < self.samples_per_value
+		{
+			return;
+		}
+		let samples = self.samples_per_value as f64;
+		let merge = self.input.iter().chain(self.output.iter()).flatten().flatten().next().map(|series|series.values.len()+1==self.maximum_samples).unwrap_or(false);
+		for series in self.input.iter_mut().chain(self.output.iter_mut()).flatten().flatten()
+		{
+			series.values.push(series.pending as f64/samples);
+			series.pending = 0;
+			if merge
+			{
+				series.values = series.values.chunks(2).map(|pair|(pair[0]+pair[1])/2.0).collect();
+			}
+		}
+		self.pending_samples = 0;
+		if merge
+		{
+			self.samples_per_value *= 2;
+		}
+	}
+	///Discards the samples, to begin the series at `next_cycle`.
+	pub fn reset(&mut self, next_cycle:Time)
+	{
+		self.begin_cycle = next_cycle;
+		self.samples_per_value = 1;
+		self.pending_samples = 0;
+		self.input.clear();
+		self.output.clear();
+	}
+	pub fn result(&self) -> ConfigurationValue
+	{
+		let series_to_cv = |series:&Vec<Vec<Vec<OccupancySeries>>>| ConfigurationValue::Array(series.iter().map(|router|
+			ConfigurationValue::Array(router.iter().map(|port|
+				ConfigurationValue::Array(port.iter().map(|buffer|
+					ConfigurationValue::Array(buffer.values.iter().map(|value|ConfigurationValue::Number(*value)).collect())
+				).collect())
+			).collect())
+		).collect());
+		ConfigurationValue::Object(String::from("BufferOccupancy"),vec![
+			(String::from("begin_cycle"),ConfigurationValue::Number(self.begin_cycle as f64)),
+			(String::from("step"),ConfigurationValue::Number((self.step*self.samples_per_value as Time) as f64)),
+			(String::from("input"),series_to_cv(&self.input)),
+			(String::from("output"),series_to_cv(&self.output)),
+		])
+	}
+}
+
+/**
+Segmentation of the messages according to the `maximum_packet_size` of the link classes, created when any link class sets it.
+For example, the global links of a dragonfly may carry larger packets than the local ones.
+
+Packets cannot be split once inside the network, so the segmentation is made at injection, as with path MTU discovery:
+the size of the packets of a message is the least `maximum_packet_size` among the links of the servers and the links in any minimal route between their routers, capped by the global `maximum_packet_size`.
+Hops of packets through links with a smaller `maximum_packet_size` than their size, as may happen with non-minimal routes, are counted as `oversized_hops`.
+
+The results include `fragmentation: Fragmentation{packets, packets_without_limits, overhead, oversized_hops}`,
+where `packets_without_limits` are the packets that would have been created with only the global `maximum_packet_size` and `overhead` is the fraction of additional packets.
+**/
+#[derive(Debug)]
+pub struct Fragmentation
+{
+	///The maximum packet size of each link class, if limited.
+	link_class_sizes: Vec<Option<usize>>,
+	///The least maximum packet size in the minimal routes between each pair of routers, computed on demand.
+	route_sizes: HashMap<(usize,usize),Option<usize>>,
+	///Packets created.
+	packets: usize,
+	///Packets that would have been created without the limits of the link classes.
+	packets_without_limits: usize,
+	///Hops of packets through links with smaller maximum packet size than theirs.
+	oversized_hops: usize,
+}
+
+impl Fragmentation
+{
+	///Builds the segmentation for the given limits of the link classes, or None if there is no limit.
+	pub fn new(link_class_sizes:Vec<Option<usize>>) -> Option<Fragmentation>
+	{
+		if link_class_sizes.iter().all(Option::is_none)
+		{
+			return None;
+		}
+		Some(Fragmentation{
+			link_class_sizes,
+			route_sizes: HashMap::new(),
+			packets: 0,
+			packets_without_limits: 0,
+			oversized_hops: 0,
+		})
+	}
+	///The limit of a link class, if any.
+	fn class_size(&self, link_class:usize) -> Option<usize>
+	{
+		self.link_class_sizes.get(link_class).copied().flatten()
+	}
+	///The size of the packets of a message from the server `origin` to the server `destination`, before capping it by the global `maximum_packet_size`.
+	pub fn packet_size(&mut self, origin:usize, destination:usize, topology:&dyn Topology) -> Option<usize>
+	{
+		let (origin_location,origin_class) = topology.server_neighbour(origin);
+		let (destination_location,destination_class) = topology.server_neighbour(destination);
+		let (source,target) = match (origin_location,destination_location)
+		{
+			(Location::RouterPort{router_index:source,..},Location::RouterPort{router_index:target,..}) => (source,target),
+			_ => panic!("The servers are not attached to routers"),
+		};
+		let server_sizes = [self.class_size(origin_class),self.class_size(destination_class)];
+		let route_size = match self.route_sizes.get(&(source,target))
+		{
+			Some(&size) => size,
+			None =>
+			{
+				let size = self.minimal_route_size(source,target,topology);
+				self.route_sizes.insert((source,target),size);
+				size
+			},
+		};
+		server_sizes.iter().chain(std::iter::once(&route_size)).flatten().min().copied()
+	}
+	///The least limit among the links in the minimal routes from `source` to `target`.
+	fn minimal_route_size(&self, source:usize, target:usize, topology:&dyn Topology) -> Option<usize>
+	{
+		let mut size:Option<usize> = None;
+		let mut visited = vec![false;topology.num_routers()];
+		visited[source] = true;
+		let mut pending = vec![source];
+		while let Some(router) = pending.pop()
+		{
+			let distance = topology.distance(router,target);
+			for port in 0..topology.ports(router)
+			{
+				if let (Location::RouterPort{router_index:next,..},link_class) = topology.neighbour(router,port)
+				{
+					if topology.is_link_alive(router,port) && topology.distance(next,target)+1==distance
+					{
+						if let Some(limit) = self.class_size(link_class)
+						{
+							size = Some(size.map_or(limit,|s|s.min(limit)));
+						}
+						if !visited[next]
+						{
+							visited[next] = true;
+							pending.push(next);
+						}
+					}
+				}
+			}
+		}
+		size
+	}
+	///Account the packets of a message of `size` phits split into packets of `packet_size`.
+	pub fn track_message(&mut self, size:usize, packet_size:usize, maximum_packet_size:usize)
+	{
+		self.packets += (size+packet_size-1)/packet_size;
+		self.packets_without_limits += (size+maximum_packet_size-1)/maximum_packet_size;
+	}
+	///Account a packet of `packet_size` phits crossing a link of class `link_class`.
+	pub fn track_hop(&mut self, packet_size:usize, link_class:usize)
+	{
+		if self.class_size(link_class).map_or(false,|limit|packet_size>limit)
+		{
+			self.oversized_hops += 1;
+		}
+	}
+	pub fn reset(&mut self)
+	{
+		self.packets = 0;
+		self.packets_without_limits = 0;
+		self.oversized_hops = 0;
+	}
+	///The entry to include into the results.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		let overhead = if self.packets_without_limits==0 { 0f64 } else { self.packets as f64/self.packets_without_limits as f64 - 1.0 };
+		ConfigurationValue::Object(String::from("Fragmentation"),vec![
+			(String::from("packets"),ConfigurationValue::Number(self.packets as f64)),
+			(String::from("packets_without_limits"),ConfigurationValue::Number(self.packets_without_limits as f64)),
+			(String::from("overhead"),ConfigurationValue::Number(overhead)),
+			(String::from("oversized_hops"),ConfigurationValue::Number(self.oversized_hops as f64)),
+		])
+	}
+}
+
+/**
+Statistics of the flows, each flow being the messages from a source server to a destination server, and of the incast at each destination.
+It is enabled with `statistics_flows` in the configuration. The flows only include the messages consumed in the measured period, so it is intended for traffics with a clear ending, such as `Burst`, usually without warmup.
+```ignore
+statistics_flows: FlowStatistics{
+	//The percentiles to report. By default [50,99].
+	percentiles: [50,90,99,100],
+	//Number of destinations with the greatest completion time to report. By default 5.
+	top_destinations: 5,
+}
+```
+The completion time of a flow is the span from the creation of its first message to the consumption of its last one. The completion time of a destination is the same span over all the messages it receives, and its incast degree the number of sources sending to it.
+The results include `flows: FlowStatistics{flows, messages, average_flow_size, average_completion_time, completion_time_percentileX, message_delay_percentileX, destinations, average_incast_degree, maximum_incast_degree, destination_completion_time_percentileX, top_destinations}`,
+where `X` is each of the percentiles and `top_destinations` a list of `Destination{server, sources, messages, phits, completion_time, maximum_message_delay}`.
+**/
+#[derive(Debug)]
+pub struct FlowStatistics
+{
+	///The percentiles to report.
+	percentiles: Vec<u8>,
+	///Number of destinations reported in detail.
+	top_destinations: usize,
+	///The records of the flows by `(source,destination)`.
+	flows: BTreeMap<(usize,usize),FlowRecord>,
+	///The delay of each consumed message.
+	message_delays: Vec<Time>,
+}
+
+///The measurements of a flow, or of all the messages to a destination.
+#[derive(Debug,Clone,Copy)]
+struct FlowRecord
+{
+	first_creation: Time,
+	last_consumption: Time,
+	messages: usize,
+	phits: usize,
+	maximum_message_delay: Time,
+}
+
+impl FlowRecord
+{
+	fn completion_time(&self) -> Time
+	{
+		self.last_consumption - self.first_creation
+	}
+	fn merge(&mut self, other:&FlowRecord)
+	{
+		self.first_creation = self.first_creation.min(other.first_creation);
+		self.last_consumption = self.last_consumption.max(other.last_consumption);
+		self.messages += other.messages;
+		self.phits += other.phits;
+		self.maximum_message_delay = self.maximum_message_delay.max(other.maximum_message_delay);
+	}
+}
+
+impl Quantifiable for FlowStatistics
+{
+	fn total_memory(&self) -> usize
+	{
+		std::mem::size_of::<FlowStatistics>() + self.percentiles.total_memory() + self.flows.len()*std::mem::size_of::<((usize,usize),FlowRecord)>() + self.message_delays.total_memory()
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl FlowStatistics
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<FlowStatistics,Error>
+	{
+		let mut percentiles=vec![50,99];
+		let mut top_destinations=5;
+		match_object!(cv,"FlowStatistics",value,
+			"percentiles" => percentiles=value.as_array()?.iter()
+				.map(|v|Ok(v.as_f64()?.round() as u8)).collect::<Result<_,Error>>()?,
+			"top_destinations" => top_destinations=value.as_usize()?,
+		);
+		Ok(FlowStatistics{
+			percentiles,
+			top_destinations,
+			flows: BTreeMap::new(),
+			message_delays: vec![],
+		})
+	}
+	///Called when a server consumes the last phit of a message.
+	pub fn track_consumed_message(&mut self, cycle:Time, message:&Message)
+	{
+		let delay = cycle - message.creation_cycle;
+		self.message_delays.push(delay);
+		let record = FlowRecord{
+			first_creation: message.creation_cycle,
+			last_consumption: cycle,
+			messages: 1,
+			phits: message.size,
+			maximum_message_delay: delay,
+		};
+		self.flows.entry((message.origin,message.destination)).and_modify(|flow|flow.merge(&record)).or_insert(record);
+	}
+	pub fn reset(&mut self)
+	{
+		self.flows.clear();
+		self.message_delays.clear();
+	}
+	///The entry to include into the results.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		let percentile = |sorted:&[Time],percentile:u8| -> f64 {
+			if sorted.is_empty() { return 0.0; }
+			let index = (sorted.len()*usize::from(percentile)/100).min(sorted.len()-1);
+			sorted[index] as f64
+		};
+		let mut destinations : BTreeMap<usize,(FlowRecord,usize)> = BTreeMap::new();
+		for (&(_source,destination),flow) in self.flows.iter()
+		{
+			destinations.entry(destination).and_modify(|(record,sources)|{record.merge(flow);*sources+=1;}).or_insert((*flow,1));
+		}
+		let mut completion_times : Vec<Time> = self.flows.values().map(FlowRecord::completion_time).collect();
+		completion_times.sort_unstable();
+		let mut message_delays = self.message_delays.clone();
+		message_delays.sort_unstable();
+		let mut destination_completion_times : Vec<Time> = destinations.values().map(|(record,_)|record.completion_time()).collect();
+		destination_completion_times.sort_unstable();
+		let num_flows = self.flows.len();
+		let average = |total:usize,count:usize| if count==0 { 0f64 } else { total as f64/count as f64 };
+		let mut content = vec![
+			(String::from("flows"),ConfigurationValue::Number(num_flows as f64)),
+			(String::from("messages"),ConfigurationValue::Number(self.message_delays.len() as f64)),
+			(String::from("average_flow_size"),ConfigurationValue::Number(average(self.flows.values().map(|flow|flow.phits).sum(),num_flows))),
+			(String::from("average_completion_time"),ConfigurationValue::Number(average(completion_times.iter().sum::<Time>() as usize,num_flows))),
+		];
+		for &p in self.percentiles.iter()
+		{
+			content.push((format!("completion_time_percentile{}",p),ConfigurationValue::Number(percentile(&completion_times,p))));
+		}
+		for &p in self.percentiles.iter()
+		{
+			content.push((format!("message_delay_percentile{}",p),ConfigurationValue::Number(percentile(&message_delays,p))));
+		}
+		content.push((String::from("destinations"),ConfigurationValue::Number(destinations.len() as f64)));
+		content.push((String::from("average_incast_degree"),ConfigurationValue::Number(average(num_flows,destinations.len()))));
+		content.push((String::from("maximum_incast_degree"),ConfigurationValue::Number(destinations.values().map(|(_,sources)|*sources).max().unwrap_or(0) as f64)));
+		for &p in self.percentiles.iter()
+		{
+			content.push((format!("destination_completion_time_percentile{}",p),ConfigurationValue::Number(percentile(&destination_completion_times,p))));
+		}
+		let mut top : Vec<(&usize,&(FlowRecord,usize))> = destinations.iter().collect();
+		top.sort_by_key(|(_,(record,_))|std::cmp::Reverse(record.completion_time()));
+		let top = top.into_iter().take(self.top_destinations).map(|(&server,&(record,sources))|ConfigurationValue::Object(String::from("Destination"),vec![
+			(String::from("server"),ConfigurationValue::Number(server as f64)),
+			(String::from("sources"),ConfigurationValue::Number(sources as f64)),
+			(String::from("messages"),ConfigurationValue::Number(record.messages as f64)),
+			(String::from("phits"),ConfigurationValue::Number(record.phits as f64)),
+			(String::from("completion_time"),ConfigurationValue::Number(record.completion_time() as f64)),
+			(String::from("maximum_message_delay"),ConfigurationValue::Number(record.maximum_message_delay as f64)),
+		])).collect();
+		content.push((String::from("top_destinations"),ConfigurationValue::Array(top)));
+		ConfigurationValue::Object(String::from("FlowStatistics"),content)
+	}
+}
+
+/**
+Confidence intervals of the accepted load and of the message delay computed by batch means within a single run.
+The measured period is divided into `batches` batches of the same number of cycles and the mean of each batch is taken as a sample. A message is accounted in the batch in which it is consumed.
+It is enabled with `statistics_batch_means` in the configuration.
+```ignore
+statistics_batch_means: BatchMeans{
+	//Number of batches in which the measured cycles are divided. At least 10 are recommended.
+	batches: 20,
+	//The confidence level of the intervals. By default 0.95.
+	confidence: 0.99,
+}
+```
+Consecutive batches are correlated when they are short compared with the dynamics of the network, which makes the plain interval too narrow. Hence the lag-1 autocorrelation of the batch means is measured and, when positive, the variance is scaled by `(1+r)/(1-r)`.
+The results include `batch_means: BatchMeans{batches, batch_cycles, confidence, accepted_load, average_message_delay}`, where each measure is a `ConfidenceInterval{mean, half_width, lower, upper, lag1_autocorrelation, independent}`.
+`independent` tells whether the autocorrelation is small enough for the batches to be considered independent, and when false a greater number of cycles should be measured.
+Batches left incomplete, such as when the measurement is extended, are ignored.
+**/
+#[derive(Debug,Quantifiable)]
+pub struct BatchMeans
+{
+	///The number of batches requested.
+	batches: usize,
+	///The confidence level of the intervals.
+	confidence: f64,
+	///The number of cycles of each batch.
+	batch_cycles: Time,
+	///The first cycle of the first batch.
+	begin_cycle: Time,
+	///The phits consumed in each batch.
+	consumed_phits: Vec<usize>,
+	///The messages consumed in each batch.
+	consumed_messages: Vec<usize>,
+	///The accumulated delay of the messages consumed in each batch.
+	total_message_delay: Vec<Time>,
+}
+
+impl BatchMeans
+{
+	pub fn new(cv:&ConfigurationValue, measured:Time) -> Result<BatchMeans,Error>
+	{
+		let mut batches=None;
+		let mut confidence=0.95;
+		match_object!(cv,"BatchMeans",value,
+			"batches" => batches=Some(value.as_usize()?),
+			"confidence" => confidence=value.as_f64()?,
+		);
+		let batches=batches.ok_or_else(||cv.ill("There were no batches"))?;
+		if batches<2
+		{
+			return Err(cv.ill("BatchMeans requires at least 2 batches."));
+		}
+		if !(confidence>0.0 && confidence<1.0)
+		{
+			return Err(cv.ill("The confidence must be between 0 and 1."));
+		}
+		let batch_cycles = measured/batches as Time;
+		if batch_cycles==0
+		{
+			return Err(cv.ill("There are more batches than measured cycles."));
+		}
+		Ok(BatchMeans{
+			batches,
+			confidence,
+			batch_cycles,
+			begin_cycle:0,
+			consumed_phits:vec![],
+			consumed_messages:vec![],
+			total_message_delay:vec![],
+		})
+	}
+	///The index of the batch including `cycle`, growing the vectors as required.
+	fn batch(&mut self, cycle:Time) -> usize
+	{
+		let index = (cycle.saturating_sub(self.begin_cycle)/self.batch_cycles) as usize;
+		if self.consumed_phits.len() <= index
+		{
+			self.consumed_phits.resize(index+1,0);
+			self.consumed_messages.resize(index+1,0);
+			self.total_message_delay.resize(index+1,0);
+		}
+		index
+	}
+	///Called each time a server consumes a phit.
+	pub fn track_consumed_phit(&mut self, cycle:Time)
+	{
+		let index = self.batch(cycle);
+		self.consumed_phits[index]+=1;
+	}
+	///Called when a server consumes the last phit of a message.
+	pub fn track_message_delay(&mut self, cycle:Time, delay:Time)
+	{
+		let index = self.batch(cycle);
+		self.consumed_messages[index]+=1;
+		self.total_message_delay[index]+=delay;
+	}
+	///Forgets the batches and begins again at `next_cycle`.
+	pub fn reset(&mut self, next_cycle:Time)
+	{
+		self.begin_cycle=next_cycle;
+		self.consumed_phits.clear();
+		self.consumed_messages.clear();
+		self.total_message_delay.clear();
+	}
+	///The entry to include into the results, at the given final `cycle`.
+	pub fn result(&self, cycle:Time, num_servers:usize) -> ConfigurationValue
+	{
+		let complete = ((cycle.saturating_sub(self.begin_cycle)/self.batch_cycles) as usize).min(self.consumed_phits.len());
+		let accepted_load : Vec<f64> = self.consumed_phits[..complete].iter().map(|&phits|phits as f64/self.batch_cycles as f64/num_servers as f64).collect();
+		//The batches without messages have no delay to sample.
+		let message_delay : Vec<f64> = (0..complete).filter(|&index|self.consumed_messages[index]>0)
+			.map(|index|self.total_message_delay[index] as f64/self.consumed_messages[index] as f64).collect();
+		ConfigurationValue::Object(String::from("BatchMeans"),vec![
+			(String::from("batches"),ConfigurationValue::Number(complete as f64)),
+			(String::from("batch_cycles"),ConfigurationValue::Number(self.batch_cycles as f64)),
+			(String::from("confidence"),ConfigurationValue::Number(self.confidence)),
+			(String::from("accepted_load"),self.confidence_interval(&accepted_load)),
+			(String::from("average_message_delay"),self.confidence_interval(&message_delay)),
+		])
+	}
+	///The confidence interval of the mean of the given batch means.
+	fn confidence_interval(&self, samples:&[f64]) -> ConfigurationValue
+	{
+		let n = samples.len();
+		let mean = samples.iter().sum::<f64>()/n as f64;
+		let deviations : Vec<f64> = samples.iter().map(|x|x-mean).collect();
+		let sum_squares : f64 = deviations.iter().map(|d|d*d).sum();
+		let autocorrelation = if sum_squares==0.0 { 0.0 } else { deviations.windows(2).map(|w|w[0]*w[1]).sum::<f64>()/sum_squares };
+		let (half_width,independent) = if n<2 { (f64::NAN,false) } else {
+			let variance = sum_squares/(n-1) as f64;
+			//Positive correlation makes the batch means vary less than independent samples would.
+			let inflation = if autocorrelation>0.0 { (1.0+autocorrelation)/(1.0-autocorrelation.min(0.99)) } else { 1.0 };
+			let p = (1.0+self.confidence)/2.0;
+			let half_width = student_t_quantile(p,(n-1) as f64)*(variance*inflation/n as f64).sqrt();
+			(half_width, autocorrelation.abs()*(n as f64).sqrt() <= normal_quantile(p))
+		};
+		ConfigurationValue::Object(String::from("ConfidenceInterval"),vec![
+			(String::from("mean"),ConfigurationValue::Number(mean)),
+			(String::from("half_width"),ConfigurationValue::Number(half_width)),
+			(String::from("lower"),ConfigurationValue::Number(mean-half_width)),
+			(String::from("upper"),ConfigurationValue::Number(mean+half_width)),
+			(String::from("lag1_autocorrelation"),ConfigurationValue::Number(autocorrelation)),
+			(String::from("independent"),if independent {ConfigurationValue::True} else {ConfigurationValue::False}),
+		])
+	}
+}
+
+///The quantile of probability `p` of the standard normal distribution, by the rational approximation of Acklam, with relative error below 1.2e-9.
+fn normal_quantile(p:f64) -> f64
+{
+	let a = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02, 1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+	let b = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02, 6.680131188771972e+01, -1.328068155288572e+01];
+	let c = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00, -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+	let d = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00, 3.754408661907416e+00];
+	let tail = |q:f64| (((((c[0]*q+c[1])*q+c[2])*q+c[3])*q+c[4])*q+c[5]) / ((((d[0]*q+d[1])*q+d[2])*q+d[3])*q+1.0);
+	let p_low = 0.02425;
+	if p < p_low
+	{
+		tail((-2.0*p.ln()).sqrt())
+	}
+	else if p <= 1.0-p_low
+	{
+		let q = p-0.5;
+		let r = q*q;
+		(((((a[0]*r+a[1])*r+a[2])*r+a[3])*r+a[4])*r+a[5])*q / (((((b[0]*r+b[1])*r+b[2])*r+b[3])*r+b[4])*r+1.0)
+	}
+	else
+	{
+		-tail((-2.0*(1.0-p).ln()).sqrt())
+	}
+}
+
+///The quantile of probability `p` of the Student's t distribution with `dof` degrees of freedom, by the Cornish-Fisher expansion around the normal quantile.
+///It is accurate to a few thousandths from 5 degrees of freedom, but it underestimates the quantile with very few degrees of freedom.
+fn student_t_quantile(p:f64, dof:f64) -> f64
+{
+	let z = normal_quantile(p);
+	let z2 = z*z;
+	let g1 = (z2+1.0)*z/4.0;
+	let g2 = ((5.0*z2+16.0)*z2+3.0)*z/96.0;
+	let g3 = (((3.0*z2+19.0)*z2+17.0)*z2-15.0)*z/384.0;
+	let g4 = ((((79.0*z2+776.0)*z2+1482.0)*z2-1920.0)*z2-945.0)*z/92160.0;
+	z + g1/dof + g2/dof.powi(2) + g3/dof.powi(3) + g4/dof.powi(4)
+}
+
+/**
+Records, for a sample of the packets, the features of the candidates of each of their routing decisions together with the latency that followed, to train decision models offline.
+It is enabled with `statistics_decision_features` in the configuration.
+```ignore
+statistics_decision_features: DecisionFeatures{
+	filename: "decisions.csv",
+	//Probability of recording the decisions of each packet. By default 1.
+	sampling: 0.05,
+	//Seed of the sampling, independent of the random generator of the simulation. By default 0.
+	seed: 1,
+}
+```
+A decision is recorded each time the routing of a sampled packet is computed in a router other than the one of its destination. When it is computed several times in a router, because the packet waits, the last one is kept.
+The file has a header line followed by a line per candidate with the comma separated fields
+`decision,origin,destination,packet_index,router,target_router,cycle,hops,port,virtual_channel,link_class,label,estimated_remaining_hops,occupancy,port_occupancy,distance,chosen,latency,network_delay`.
+* `decision` numbers the decisions in the file, being the same for all the candidates of a decision.
+* `origin`, `destination`, and `packet_index` identify the packet.
+* `router` is the router taking the decision towards `target_router`, in the `cycle`, after the packet has performed `hops` hops.
+* `port`, `virtual_channel`, `link_class`, `label`, and `estimated_remaining_hops` describe the candidate, the latter being empty when not given by the routing.
+* `occupancy` is the number of phits in the virtual channel of the candidate in the next router, as known by the credits, and `port_occupancy` the occupancy of the port as given by the router, empty if it does not provide it.
+* `distance` is the distance from the router reached by the candidate to the target router.
+* `chosen` is 1 for the candidate taken by the packet and 0 for the others.
+* `latency` is the number of cycles from the decision to the consumption of the packet and `network_delay` those from its injection.
+
+The fields `hops`, `link_class`, `label`, `occupancy`, and `virtual_channel` are those of the keys of the [TablePolicy](crate::policies::TablePolicy), so that a model trained with them can be evaluated by writing it as a table.
+The results include `decision_features: DecisionFeaturesFile{filename, packets, decisions}`.
+A relative `filename` is created inside the directory of the run, see [Simulation::new_in_directory](crate::Simulation::new_in_directory).
+**/
+#[derive(Debug)]
+pub struct DecisionFeatures
+{
+	///The path of the file.
+	pub filename: String,
+	///Where the records are written.
+	writer: std::io::BufWriter<std::fs::File>,
+	///The probability of sampling each packet.
+	sampling: f64,
+	///The generator for the sampling.
+	rng: rand::rngs::StdRng,
+	///Number of packets written.
+	pub packets: usize,
+	///Number of decisions written.
+	pub decisions: usize,
+}
+
+impl Quantifiable for DecisionFeatures
+{
+	fn total_memory(&self) -> usize
+	{
+		std::mem::size_of::<DecisionFeatures>() + self.filename.total_memory()
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+///The features of a candidate given by the routing, as written by [DecisionFeatures].
+#[derive(Quantifiable,Debug,Clone)]
+pub struct CandidateFeatures
+{
+	pub port: usize,
+	pub virtual_channel: usize,
+	pub link_class: usize,
+	pub label: i32,
+	pub estimated_remaining_hops: Option<usize>,
+	pub occupancy: usize,
+	pub port_occupancy: Option<f64>,
+	pub distance: usize,
+}
+
+///A routing decision of a packet sampled by [DecisionFeatures].
+#[derive(Quantifiable,Debug)]
+pub struct RoutingDecision
+{
+	pub router: usize,
+	pub target_router: usize,
+	pub cycle: Time,
+	pub hops: usize,
+	pub candidates: Vec<CandidateFeatures>,
+	///The index in `candidates` of the one taken, if known.
+	pub chosen: Option<usize>,
+}
+
+///The decisions of a packet sampled by [DecisionFeatures].
+#[derive(Quantifiable,Debug,Default)]
+pub struct PacketDecisions
+{
+	///The last decision computed in the current router, which is taken when the packet leaves it.
+	pub pending: Option<RoutingDecision>,
+	///The decisions taken.
+	pub decisions: Vec<RoutingDecision>,
+}
+
+impl PacketDecisions
+{
+	///Records the `candidates` given by the routing to the `packet` in the `router`, if the packet has been sampled.
+	///Called by the routers each time they compute the candidates.
+	pub fn record_candidates(packet:&Packet, router:&dyn Router, topology:&dyn Topology, candidates:&[CandidateEgress], target_router:usize, cycle:Time)
+	{
+		let mut decisions = packet.decisions.borrow_mut();
+		let decisions = match decisions.as_mut()
+		{
+			Some(decisions) => decisions,
+			None => return,
+		};
+		let router_index = router.get_index().expect("we need routers with index");
+		if router_index==target_router
+		{
+			return;
+		}
+		let port_occupancy = router.port_occupancy();
+		let candidates = candidates.iter().map(|candidate|{
+			let (location,link_class) = topology.neighbour(router_index,candidate.port);
+			let occupancy = match router.get_status_at_emisor(candidate.port)
+			{
+				Some(status) => router.get_maximum_credits_towards(candidate.port,candidate.virtual_channel).unwrap_or(0)
+					.saturating_sub(status.known_available_space_for_virtual_channel(candidate.virtual_channel).unwrap_or(0)),
+				None => 0,
+			};
+			let distance = match location
+			{
+				Location::RouterPort{router_index:next_router,..} => topology.distance(next_router,target_router),
+				_ => 0,
+			};
+			CandidateFeatures{
+				port: candidate.port,
+				virtual_channel: candidate.virtual_channel,
+				link_class,
+				label: candidate.label,
+				estimated_remaining_hops: candidate.estimated_remaining_hops,
+				occupancy,
+				port_occupancy: port_occupancy.as_ref().map(|occupancy|occupancy[candidate.port]),
+				distance,
+			}
+		}).collect();
+		decisions.pending = Some(RoutingDecision{
+			router: router_index,
+			target_router,
+			cycle,
+			hops: packet.routing_info.borrow().hops,
+			candidates,
+			chosen: None,
+		});
+	}
+	///Takes the pending decision of the `packet`, if it was sampled, after it has left the router `previous_router` through its port `exit_port` with the `virtual_channel`.
+	pub fn record_taken(packet:&Packet, previous_router:usize, exit_port:usize, virtual_channel:Option<usize>)
+	{
+		let mut decisions = packet.decisions.borrow_mut();
+		let decisions = match decisions.as_mut()
+		{
+			Some(decisions) => decisions,
+			None => return,
+		};
+		if let Some(mut decision) = decisions.pending.take()
+		{
+			if decision.router==previous_router
+			{
+				decision.chosen = decision.candidates.iter().position(|candidate|candidate.port==exit_port && Some(candidate.virtual_channel)==virtual_channel)
+					.or_else(||decision.candidates.iter().position(|candidate|candidate.port==exit_port));
+				decisions.decisions.push(decision);
+			}
+		}
+	}
+}
+
+impl DecisionFeatures
+{
+	///The names of the fields, in the order they are written.
+	pub const COLUMNS: [&'static str;19] = ["decision","origin","destination","packet_index","router","target_router","cycle","hops","port","virtual_channel","link_class","label","estimated_remaining_hops","occupancy","port_occupancy","distance","chosen","latency","network_delay"];
+	///Creates the file inside the `directory`.
+	pub fn new(cv:&ConfigurationValue, directory:&Path) -> Result<DecisionFeatures,Error>
+	{
+		use std::io::Write;
+		use rand::SeedableRng;
+		let mut filename=None;
+		let mut sampling=1.0;
+		let mut seed=0;
+		match_object!(cv,"DecisionFeatures",value,
+			"filename" => filename=Some(value.as_str()?.to_string()),
+			"sampling" => sampling=value.as_f64()?,
+			"seed" => seed=value.as_usize()? as u64,
+		);
+		let filename=filename.ok_or_else(||cv.ill("There were no filename"))?;
+		let path = directory.join(&filename);
+		let file = std::fs::File::create(&path).map_err(|error|error!(could_not_generate_file,path.clone(),error))?;
+		let mut writer = std::io::BufWriter::new(file);
+		writeln!(writer,"{}",Self::COLUMNS.join(",")).map_err(|error|error!(could_not_generate_file,path,error))?;
+		Ok(DecisionFeatures{
+			filename,
+			writer,
+			sampling,
+			rng: rand::rngs::StdRng::seed_from_u64(seed),
+			packets: 0,
+			decisions: 0,
+		})
+	}
+	///Whether to record the decisions of a packet being injected.
+	pub fn sample(&mut self) -> bool
+	{
+		use rand::Rng;
+		self.rng.gen::<f64>() < self.sampling
+	}
+	///Append the decisions of a `packet` consumed in `cycle`, if it was sampled.
+	pub fn write_packet(&mut self, cycle:Time, packet:&Packet)
+	{
+		use std::io::Write;
+		let decisions = packet.decisions.borrow();
+		let decisions = match decisions.as_ref()
+		{
+			Some(decisions) => decisions,
+			None => return,
+		};
+		let message = &packet.message;
+		let network_delay = cycle-*packet.cycle_into_network.borrow();
+		for decision in decisions.decisions.iter()
+		{
+			for (index,candidate) in decision.candidates.iter().enumerate()
+			{
+				writeln!(self.writer,"{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+					self.decisions,
+					message.origin,
+					message.destination,
+					packet.index,
+					decision.router,
+					decision.target_router,
+					decision.cycle,
+					decision.hops,
+					candidate.port,
+					candidate.virtual_channel,
+					candidate.link_class,
+					candidate.label,
+					candidate.estimated_remaining_hops.map(|x|x.to_string()).unwrap_or_default(),
+					candidate.occupancy,
+					candidate.port_occupancy.map(|x|x.to_string()).unwrap_or_default(),
+					candidate.distance,
+					if decision.chosen==Some(index) { 1 } else { 0 },
+					cycle-decision.cycle,
+					network_delay,
+				).expect("Could not write the decision features file");
+			}
+			self.decisions+=1;
+		}
+		self.packets+=1;
+	}
+	///Ensure all the records are written into the file.
+	pub fn flush(&mut self)
+	{
+		use std::io::Write;
+		self.writer.flush().expect("Could not write the decision features file");
+	}
+	///The entry to include into the results.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("DecisionFeaturesFile"),vec![
+			(String::from("filename"),ConfigurationValue::Literal(self.filename.clone())),
+			(String::from("packets"),ConfigurationValue::Number(self.packets as f64)),
+			(String::from("decisions"),ConfigurationValue::Number(self.decisions as f64)),
+		])
+	}
+}
@@ -0,0 +1,195 @@
+/*!
+
+Model of the network interface (NIC) of the servers.
+
+Without a NIC model each server injects the packets of its messages as soon as they are generated, at up to one phit per cycle in each of its rails.
+When the configuration includes `nic` each server has a NIC with limited injection bandwidth and a setup latency for each message.
+```ignore
+nic: NIC{
+	//Phits per cycle that the server may inject, shared among its rails. By default 1.
+	injection_bandwidth: 0.5,
+	//Cycles to set up the transfer of each message before injecting its first packet. By default 0.
+	dma_latency: 20,
+	//Phits of the packets that arrive before a preceding packet of their message. By default unbounded.
+	reorder_buffer_size: 64,
+}
+```
+The NIC sets up one message at a time, so the `dma_latency` of a message begins when the packets of the previous message have been taken for injection.
+The bandwidth is enforced by a token bucket that gains `injection_bandwidth` tokens each cycle, each phit injected consuming one, with room for `max(1,injection_bandwidth)` tokens.
+
+At reception the NIC delivers the packets of each message in order. The packets arriving before a preceding packet of their message, as may happen with adaptive routings or several rails, wait in the reorder buffer.
+The routers do not receive backpressure from the servers, so the packets that find the reorder buffer full are accepted anyway and counted as overflows.
+
+The results include `nic: NIC{bandwidth_stalls, out_of_order_packets, maximum_reorder_occupancy, reorder_overflows}`, aggregated over all the servers,
+where `bandwidth_stalls` counts the times that a rail had a phit ready to inject and allowed by the router, but there were no tokens to inject it.
+
+*/
+
+use std::collections::BTreeMap;
+
+use quantifiable_derive::Quantifiable;//the derive macro
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::packet::{Packet,Message};
+
+///The NIC of a server, with its parameters and state.
+#[derive(Clone,Debug,Quantifiable)]
+pub struct Nic
+{
+	///Phits per cycle that the server may inject.
+	injection_bandwidth: f64,
+	///Cycles to set up each message.
+	dma_latency: Time,
+	///The capacity in phits of the reorder buffer, if bounded.
+	reorder_buffer_size: Option<usize>,
+	///The tokens available to inject phits.
+	tokens: f64,
+	///The cycle from which the packets of the message being set up can be injected.
+	ready_cycle: Time,
+	///For each message being received with packets out of order, the index of the next packet to deliver, the phits already delivered, and the `(index,size)` of the packets in the reorder buffer.
+	reorder: BTreeMap<*const Message,(usize,usize,Vec<(usize,usize)>)>,
+	///The phits currently in the reorder buffer.
+	reorder_occupancy: usize,
+	///Times a phit was ready to inject but there were no tokens.
+	bandwidth_stalls: usize,
+	///Packets that arrived before a preceding packet of their message.
+	out_of_order_packets: usize,
+	///The maximum number of phits in the reorder buffer.
+	maximum_reorder_occupancy: usize,
+	///Packets that found the reorder buffer full.
+	reorder_overflows: usize,
+}
+
+impl Nic
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<Nic,Error>
+	{
+		let mut injection_bandwidth=1.0;
+		let mut dma_latency=0;
+		let mut reorder_buffer_size=None;
+		match_object!(cv,"NIC",value,
+			"injection_bandwidth" => injection_bandwidth=value.as_f64()?,
+			"dma_latency" => dma_latency=value.as_time()?,
+			"reorder_buffer_size" => reorder_buffer_size=Some(value.as_usize()?),
+		);
+		if injection_bandwidth<=0.0
+		{
+			return Err(cv.ill("The injection_bandwidth of the NIC must be positive"));
+		}
+		Ok(Nic{
+			injection_bandwidth,
+			dma_latency,
+			reorder_buffer_size,
+			tokens: injection_bandwidth.max(1.0),
+			ready_cycle: 0,
+			reorder: BTreeMap::new(),
+			reorder_occupancy: 0,
+			bandwidth_stalls: 0,
+			out_of_order_packets: 0,
+			maximum_reorder_occupancy: 0,
+			reorder_overflows: 0,
+		})
+	}
+	///Gains the tokens of a new cycle.
+	pub fn begin_cycle(&mut self)
+	{
+		self.tokens = (self.tokens+self.injection_bandwidth).min(self.injection_bandwidth.max(1.0));
+	}
+	///Begins to set up a message taken from the queue of the server at `cycle`.
+	pub fn setup_message(&mut self, cycle:Time)
+	{
+		self.ready_cycle = cycle+self.dma_latency;
+	}
+	///Whether the message being set up can begin its injection.
+	pub fn is_ready(&self, cycle:Time) -> bool
+	{
+		cycle>=self.ready_cycle
+	}
+	///Tries to take a token to inject a phit, counting a stall if there are none.
+	pub fn take_token(&mut self) -> bool
+	{
+		if self.tokens>=1.0
+		{
+			self.tokens-=1.0;
+			true
+		}
+		else
+		{
+			self.bandwidth_stalls+=1;
+			false
+		}
+	}
+	///A whole packet has arrived to the server.
+	pub fn packet_received(&mut self, packet:&Packet)
+	{
+		let message=&packet.message;
+		if packet.index==0 && packet.size==message.size
+		{
+			//Single packet messages are always in order.
+			return;
+		}
+		let key=message.as_ref() as *const Message;
+		let entry=self.reorder.entry(key).or_insert((0,0,vec![]));
+		if packet.index!=entry.0
+		{
+			self.out_of_order_packets+=1;
+			if let Some(size)=self.reorder_buffer_size
+			{
+				if self.reorder_occupancy+packet.size>size
+				{
+					self.reorder_overflows+=1;
+				}
+			}
+			entry.2.push((packet.index,packet.size));
+			self.reorder_occupancy+=packet.size;
+			self.maximum_reorder_occupancy=self.maximum_reorder_occupancy.max(self.reorder_occupancy);
+			return;
+		}
+		//Deliver the packet and those waiting for it.
+		entry.0+=1;
+		entry.1+=packet.size;
+		while let Some(position)=entry.2.iter().position(|&(index,_)|index==entry.0)
+		{
+			let (_,size)=entry.2.swap_remove(position);
+			entry.0+=1;
+			entry.1+=size;
+			self.reorder_occupancy-=size;
+		}
+		if entry.1==message.size
+		{
+			self.reorder.remove(&key);
+		}
+	}
+	///Clears the statistics.
+	pub fn reset_statistics(&mut self)
+	{
+		self.bandwidth_stalls=0;
+		self.out_of_order_packets=0;
+		self.maximum_reorder_occupancy=self.reorder_occupancy;
+		self.reorder_overflows=0;
+	}
+	///The statistics aggregated over the NICs of all the servers.
+	pub fn result<'a>(nics:impl Iterator<Item=&'a Nic>) -> ConfigurationValue
+	{
+		let mut bandwidth_stalls=0;
+		let mut out_of_order_packets=0;
+		let mut maximum_reorder_occupancy=0;
+		let mut reorder_overflows=0;
+		for nic in nics
+		{
+			bandwidth_stalls+=nic.bandwidth_stalls;
+			out_of_order_packets+=nic.out_of_order_packets;
+			maximum_reorder_occupancy=maximum_reorder_occupancy.max(nic.maximum_reorder_occupancy);
+			reorder_overflows+=nic.reorder_overflows;
+		}
+		ConfigurationValue::Object(String::from("NIC"),vec![
+			(String::from("bandwidth_stalls"),ConfigurationValue::Number(bandwidth_stalls as f64)),
+			(String::from("out_of_order_packets"),ConfigurationValue::Number(out_of_order_packets as f64)),
+			(String::from("maximum_reorder_occupancy"),ConfigurationValue::Number(maximum_reorder_occupancy as f64)),
+			(String::from("reorder_overflows"),ConfigurationValue::Number(reorder_overflows as f64)),
+		])
+	}
+}
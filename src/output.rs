@@ -20,7 +20,7 @@ use crate::config_parser::{ConfigurationValue,Expr};
 use crate::config::{self,evaluate,reevaluate,values_to_f32_with_count};
 use crate::experiments::ExperimentFiles;
 use crate::error::{Error,SourceLocation};
-use crate::{get_git_id,get_version_number,source_location,match_object_panic,match_object,error};
+use crate::{get_git_id,get_version_number,source_location,match_object_panic,match_object,error,Plugs};
 
 
 /** Creates some output using an output description object as guide.
@@ -39,6 +39,40 @@ CSV
 }
 ```
 
+With `averaged` the rows with the same values of `fields` are merged into one. Its columns are the `fields`, the number of `runs` merged,
+and for each expression in `averaged` its mean and the half width of its confidence interval. The `confidence` level is 0.95 by default.
+This is intended to aggregate the repetitions of an experiment, as generated by `repetitions` or `seeds` in the configuration.
+If `fields` is omitted then the rows are grouped by everything in the configuration except the seed, that is,
+the top-level `random_seed`, `seed_index` and `repetition`. Then the columns are the paths of the configuration values that vary among the experiments.
+
+```ignore
+CSV
+{
+	fields: [=configuration.traffic.load,=configuration.routing.legend_name],
+	averaged: [=result.accepted_load,=result.average_message_delay],
+	confidence: 0.95,//optional
+	filename: "averaged.csv",
+}
+```
+
+With `long` the CSV is written in long format, also called tidy data, with a row for each experiment and metric. Its columns are the `fields`,
+`metric`, `step`, and `value`. Each expression in `metrics` is flattened: objects extend the name of the metric with their members, as
+`temporal_statistics.injected_load`, and the position in the first array found goes into the `step` column, so temporal series get a row per step.
+The indices of further nested arrays are appended to the metric, as `metric[2]`. Only numbers are written, with booleans as 0 and 1, so that `value` is always numeric.
+If `metrics` is omitted then the whole `result` is flattened, with the metrics named by their path inside it.
+If `fields` is omitted then the rows are identified by the `index` of the experiment and the configuration values that vary among the experiments.
+This is intended to be loaded directly into pandas or ggplot.
+
+```ignore
+CSV
+{
+	long: true,
+	fields: [=configuration.traffic.load,=configuration.routing.legend_name],//optional
+	metrics: [=result.accepted_load,["delay",=result.average_message_delay],=result.temporal_statistics],//optional
+	filename: "tidy.csv",
+}
+```
+
 ### Plots of data
 
 See the reference of [Plotkind] for detailed information.
@@ -65,7 +99,10 @@ Plots
 	legend: [=configuration.routing.legend_name,=configuration.legend_name],
 	//Prefix to use in texmporal files and similar. Must contain only simple characters and should be unique.
 	prefix: "throughput",
-	//The backend to actually draw the data. Only `Tikz` is supported. To execute the output action with this backend it is required a latex installation including the `pgfplots` latex package, which may be located at the `texlive-pictures` package of some linux distributions. Its temporal files are stored into a `tikz_tmp` directory, which may be inspected in case of errors.
+	//Optional. By default the error bars are the standard deviation of the averaged values.
+	//With a confidence level they are the half width of the confidence interval of the mean, by the Student's t distribution.
+	//confidence: 0.95,
+	//The backend to actually draw the data. Either `Tikz`, `Gnuplot` or `Matplotlib`. To execute the output action with this backend it is required a latex installation including the `pgfplots` latex package, which may be located at the `texlive-pictures` package of some linux distributions. Its temporal files are stored into a `tikz_tmp` directory, which may be inspected in case of errors.
 	backend: Tikz
 	{
 		//A generated file with latex code to generate the plots. Prepared to be inserted into another document; it is not an standalone file.
@@ -73,6 +110,18 @@ Plots
 		//A pdf generated with the plots. Its source is actually in the `tikz_tmp` directory, which has some additional preambles than `tex_filename`.
 		pdf_filename: "throughput.pdf",
 	},
+	//The `Gnuplot` backend writes a gnuplot script and a data file with a block per line, and executes `gnuplot` on it if it is available.
+	//backend: Gnuplot
+	//{
+	//	script_filename: "throughput.gp",
+	//	pdf_filename: "throughput.pdf",
+	//	//Optional. By default it is the prefix with extension `.dat`.
+	//	data_filename: "throughput.dat",
+	//	//Optional. Whether to run the script. By default true. A failure to run it only prints a warning.
+	//	execute: true,
+	//},
+	//The `Matplotlib` backend is similar, with a python script executed by `python3`. The box plots are drawn as averages in these backends.
+	//backend: Matplotlib{ script_filename: "throughput.py", pdf_filename: "throughput.pdf" },
 },
 ```
 
@@ -138,12 +187,81 @@ Plots
 },
 ```
 
+### Resource usage
+
+A `ResourceUsage` summarizes the compute consumed by the experiment, as registered by each simulation in its result.
+It creates a CSV with a row for each value of the optional `selector`, whose values are joined by spaces when it is an array, with the amount of runs, the total and average
+wall and CPU times in seconds, and the maximum peak memory in kibibytes. A last row with the `total` of all runs is appended.
+Results from older versions do not have `wall_time` nor `cpu_time`. In that case `user_time+system_time` is used as CPU time, which includes any
+previous simulation in the same process.
+
+```ignore
+ResourceUsage
+{
+	filename: "resources.csv",
+	selector: [=configuration.routing.legend_name],//optional
+}
+```
+
+### Link heatmap
+
+A `LinkHeatmap` writes the utilization of each link as a matrix, with a row for each router and a column for each of its ports towards other routers.
+It requires the simulations to be configured with `statistics_link_utilization: true`, so that their results include `link_utilization`.
+The runs with the same value of the optional `selector` are averaged. The CSV `filename` has, for each selector value, a header line with the selector
+followed by the rows of its matrix. When `tex_filename` is given it also writes a standalone LaTeX document with a pgfplots heatmap for each selector value.
+Empty cells correspond to routers with fewer ports than the column.
+
+```ignore
+LinkHeatmap
+{
+	filename: "link_utilization.csv",
+	selector: =configuration.traffic.load,//optional
+	tex_filename: "link_utilization.tex",//optional
+}
+```
+
+### Exporting the results
+
+A `JSON` output writes a line of JSON for each experiment with results, with its `index`, `configuration`, and `result`, to load them without parsing the configuration syntax.
+Objects are written with their name in the `_object` key, see [config_to_json](crate::config::config_to_json). In pandas it can be read with `pandas.read_json(filename,lines=True)`.
+
+```ignore
+JSON
+{
+	filename: "results.jsonl",
+}
+```
+
+A `Parquet` output writes a table with a row for each experiment, intended for large sweeps. It requires compiling with the `parquet` feature.
+Its columns are the paths of the values in the `index`, `configuration`, and `result`, as `result.accepted_load`. The column of an object has its name, and its fields have their own columns.
+Columns are of doubles when all their values are numbers, of Booleans when all are Booleans, and otherwise of strings, with the arrays written in JSON. Missing values are null.
+
+```ignore
+Parquet
+{
+	filename: "results.parquet",
+}
+```
+
+### Plugged outputs
+
+The outputs registered with [Plugs::add_output] are built by their builder, which receives an [OutputBuilderArgument] with the description and the environment.
+They take precedence over the predefined outputs with the same name.
+
 */
 pub fn create_output(description: &ConfigurationValue, environment: &mut OutputEnvironment)
 	-> Result<(),Error>
 {
 	if let &ConfigurationValue::Object(ref name, ref _attributes) = description
 	{
+		if let Some(plugs) = environment.plugs
+		{
+			if let Some(builder) = plugs.outputs.get(name)
+			{
+				println!("Creating a plugged output {}...",name);
+				return builder(OutputBuilderArgument{cv:description,environment,plugs});
+			}
+		}
 		match name.as_ref()
 		{
 			"CSV" =>
@@ -156,6 +274,26 @@ pub fn create_output(description: &ConfigurationValue, environment: &mut OutputE
 				println!("Creating a plot...");
 				return create_plots(description,environment);
 			},
+			"ResourceUsage" =>
+			{
+				println!("Creating a summary of resource usage...");
+				return create_resource_usage(description,environment);
+			},
+			"LinkHeatmap" =>
+			{
+				println!("Creating a link utilization heatmap...");
+				return create_link_heatmap(description,environment);
+			},
+			"JSON" =>
+			{
+				println!("Creating a JSON file...");
+				return create_json(description,environment);
+			},
+			"Parquet" =>
+			{
+				println!("Creating a Parquet file...");
+				return create_parquet(description,environment);
+			},
 			"PreprocessArgMax" =>
 			{
 				println!("Creating a file with ArgMax preprocessing...");
@@ -189,6 +327,20 @@ pub struct OutputEnvironment<'a>
 	legend_map: EnumeratedMap<ConfigurationValue>,
 	/// When not None, only generate targets in the list.
 	pub targets: &'a Option<Vec<String>>,
+	///The user defined plugs, to search the plugged outputs.
+	plugs: Option<&'a Plugs>,
+}
+
+///The argument of a builder function for plugged outputs. See [create_output].
+#[derive(Debug)]
+pub struct OutputBuilderArgument<'a,'e>
+{
+	///A ConfigurationValue::Object describing the output.
+	pub cv: &'a ConfigurationValue,
+	///The results available and the files of the experiment.
+	pub environment: &'a mut OutputEnvironment<'e>,
+	///The user defined plugs.
+	pub plugs: &'a Plugs,
 }
 
 #[derive(Debug,Clone)]
@@ -270,8 +422,20 @@ impl<'a> OutputEnvironment<'a>
 			selector_map: EnumeratedMap::default(),
 			legend_map: EnumeratedMap::default(),
 			targets,
+			plugs: None,
 		}
 	}
+	///Set the plugs in which to search the plugged outputs.
+	pub fn with_plugs(mut self, plugs:&'a Plugs) -> Self
+	{
+		self.plugs = Some(plugs);
+		self
+	}
+	///The files of the experiment, from which to get the paths.
+	pub fn files(&self) -> &ExperimentFiles
+	{
+		self.files
+	}
 	///Iterate over `ConfigurationValue`s with the context of each result.
 	///Just like `config::combine`.
 	pub fn iter(&self) -> OutputEnvironmentIterator
@@ -338,35 +502,47 @@ fn create_csv(description: &ConfigurationValue, environment:&mut OutputEnvironme
 {
 	let mut fields=None;
 	let mut filename=None;
+	let mut averaged=None;
+	let mut confidence=0.95;
+	let mut long=false;
+	let mut metrics=None;
+	let csv_fields = |value:&ConfigurationValue| match value
+	{
+		&ConfigurationValue::Array(ref a) => a.iter().map(|v|{
+			match v{
+				&ConfigurationValue::Expression(ref expr) => {
+					(format!("{expr}"), expr.clone())
+				},
+				&ConfigurationValue::Array(ref arr) => {
+					if arr.len() != 2
+					{
+						panic!("Each CSV header must be an Expression or an Array [Name,Expression].");
+					}
+					let h = arr[0].as_str().expect("bad value for fields");
+					let e = arr[1].as_expr().expect("bad value for fields");
+					(h.to_string(),e.clone())
+				},
+				_ => panic!("bad value for fields"),
+			}
+		}).collect::<Vec<(String,Expr)>>(),
+		_ => panic!("bad value for fields"),
+	};
 	match_object_panic!(description,"CSV",value,
-		"fields" => match value
-		{
-			&ConfigurationValue::Array(ref a) => fields=Some(a.iter().map(|v|{
-				match v{
-					&ConfigurationValue::Expression(ref expr) => {
-						(format!("{expr}"), expr.clone())
-					},
-					&ConfigurationValue::Array(ref arr) => {
-						if arr.len() != 2
-						{
-							panic!("Each CSV header must be an Expression or an Array [Name,Expression].");
-						}
-						let h = arr[0].as_str().expect("bad value for fields");
-						let e = arr[1].as_expr().expect("bad value for fields");
-						(h.to_string(),e.clone())
-					},
-					_ => panic!("bad value for fields"),
-				}
-			}).collect::<Vec<(String,Expr)>>()),
-			_ => panic!("bad value for fields"),
-		}
+		"fields" => fields=Some(csv_fields(value)),
 		"filename" => match value
 		{
 			&ConfigurationValue::Literal(ref s) => filename=Some(s.to_string()),
 			_ => panic!("bad value for filename ({:?})",value),
 		}
+		"averaged" => averaged=Some(csv_fields(value)),
+		"confidence" => confidence=value.as_f64().expect("bad value for confidence"),
+		"long" => long=value.as_bool().expect("bad value for long"),
+		"metrics" => metrics=Some(csv_fields(value)),
 	);
-	let fields=fields.expect("There were no fields");
+	if fields.is_none() && averaged.is_none() && !long
+	{
+		panic!("There were no fields");
+	}
 	let filename=filename.expect("There were no filename");
 	if let Some(targets) = environment.targets {
 		if !targets.contains(&filename) {
@@ -379,7 +555,100 @@ fn create_csv(description: &ConfigurationValue, environment:&mut OutputEnvironme
 	let output_path=path.join(filename);
 	let mut output_file=File::create(&output_path).expect("Could not create output file.");
 	//let header=fields.iter().map(|e|format!("{}",e)).collect::<Vec<String>>().join(", ");
-	let (headers,fields) : (Vec<_>,Vec<_>) = fields.into_iter().unzip();
+	let (headers,fields) : (Vec<_>,Vec<_>) = fields.unwrap_or_default().into_iter().unzip();
+	if long
+	{
+		//Without `fields` the rows are identified by the experiment index and every varying value of the configuration.
+		let varying = if fields.is_empty() { varying_configuration_leaves(environment) } else { vec![] };
+		let headers = if fields.is_empty() { std::iter::once("index".to_string()).chain(varying.iter().cloned()).collect() } else { headers };
+		let metrics = metrics.unwrap_or_else(||vec![(String::new(),Expr::Ident("result".to_string()))]);
+		let header = headers.into_iter().chain(["metric","step","value"].iter().map(|h|h.to_string())).collect::<Vec<String>>().join(", ");
+		writeln!(output_file,"{}",header).unwrap();
+		for context in environment.iter()
+		{
+			let key=if fields.is_empty()
+			{
+				let leaves = configuration_leaves(&context);
+				let index = evaluate(&Expr::Ident("index".to_string()),&context,&path)?.to_csv_field();
+				std::iter::once(index).chain(varying.iter().map(|leaf|leaves.get(leaf).cloned().unwrap_or_default())).collect::<Vec<String>>().join(", ")
+			}
+			else
+			{
+				fields.iter()
+					.map(|e| Ok(evaluate(e,&context,&path)?.to_csv_field()) )
+					.collect::<Result<Vec<String>,Error>>()?
+					.join(", ")
+			};
+			let mut rows = vec![];
+			for (name,expression) in metrics.iter()
+			{
+				flatten_metric(&evaluate(expression,&context,&path)?,name.clone(),None,&mut rows);
+			}
+			for (metric,step,value) in rows
+			{
+				let step = step.map(|step|step.to_string()).unwrap_or_default();
+				writeln!(output_file,"{}, {}, {}, {}",key,metric,step,value).unwrap();
+			}
+		}
+		return Ok(());
+	}
+	if let Some(averaged) = averaged
+	{
+		//Rows with the same `fields` are merged, in order of first appearance.
+		let (averaged_headers,averaged) : (Vec<_>,Vec<_>) = averaged.into_iter().unzip();
+		//Without `fields` the rows are grouped by every varying value of the configuration except the seed.
+		let varying = if fields.is_empty() { varying_configuration_leaves(environment) } else { vec![] };
+		let headers = if fields.is_empty() { varying.clone() } else { headers };
+		let header = headers.into_iter().chain(std::iter::once("runs".to_string()))
+			.chain(averaged_headers.iter().flat_map(|h|[format!("{h} mean"),format!("{h} ci")]))
+			.collect::<Vec<String>>().join(", ");
+		writeln!(output_file,"{}",header).unwrap();
+		let mut groups : Vec<(String,Vec<Vec<ConfigurationValue>>)> = vec![];
+		let mut group_index : HashMap<String,usize> = HashMap::new();
+		for context in environment.iter()
+		{
+			let key=if fields.is_empty()
+			{
+				let leaves = configuration_leaves(&context);
+				varying.iter().map(|leaf|leaves.get(leaf).cloned().unwrap_or_default()).collect::<Vec<String>>().join(", ")
+			}
+			else
+			{
+				fields.iter()
+					.map(|e| Ok(evaluate(e,&context,&path)?.to_csv_field()) )
+					.collect::<Result<Vec<String>,Error>>()?
+					.join(", ")
+			};
+			let values=averaged.iter()
+				.map(|e| evaluate(e,&context,&path) )
+				.collect::<Result<Vec<ConfigurationValue>,Error>>()?;
+			let index = *group_index.entry(key.clone()).or_insert_with(||{
+				groups.push( (key,vec![vec![];averaged.len()]) );
+				groups.len()-1
+			});
+			for (list,value) in groups[index].1.iter_mut().zip(values)
+			{
+				list.push(value);
+			}
+		}
+		for (key,lists) in groups
+		{
+			let mut row = vec![key,format!("{}",lists.first().map(|list|list.len()).unwrap_or(0))];
+			for list in lists.iter()
+			{
+				let (average,deviation) = standard_deviation(list);
+				row.push(average.map(|x|x.to_string()).unwrap_or_default());
+				row.push(match (average,deviation)
+				{
+					(Some(_),Some(deviation)) => confidence_half_width(deviation,list.len(),confidence).to_string(),
+					(Some(_),None) => "0".to_string(),
+					_ => String::new(),
+				});
+			}
+			writeln!(output_file,"{}",row.join(", ")).unwrap();
+		}
+		return Ok(());
+	}
 	let header = headers.join(", ");
 	writeln!(output_file,"{}",header).unwrap();
 	for context in environment.iter()
@@ -395,6 +664,98 @@ fn create_csv(description: &ConfigurationValue, environment:&mut OutputEnvironme
 	Ok(())
 }
 
+///Appends to `rows` the numeric leaves of `value` as `(metric,step,value)`, for the long format of [create_csv].
+///Objects extend the `metric` with their members. The index in the first array goes to the step and the indices of inner arrays to the metric.
+fn flatten_metric(value:&ConfigurationValue, metric:String, step:Option<usize>, rows:&mut Vec<(String,Option<usize>,f64)>)
+{
+	match value
+	{
+		ConfigurationValue::Object(_,attributes) =>
+		{
+			for (attribute,attribute_value) in attributes
+			{
+				let name = if metric.is_empty() { attribute.clone() } else { format!("{metric}.{attribute}") };
+				flatten_metric(attribute_value,name,step,rows);
+			}
+		},
+		ConfigurationValue::Array(list) =>
+		{
+			for (index,element) in list.iter().enumerate()
+			{
+				match step
+				{
+					None => flatten_metric(element,metric.clone(),Some(index),rows),
+					Some(_) => flatten_metric(element,format!("{metric}[{index}]"),step,rows),
+				}
+			}
+		},
+		ConfigurationValue::Number(x) => rows.push((metric,step,*x)),
+		ConfigurationValue::True => rows.push((metric,step,1.0)),
+		ConfigurationValue::False => rows.push((metric,step,0.0)),
+		_ => (),
+	}
+}
+
+///The top-level configuration fields that only distinguish the runs of the same experiment.
+const SEED_FIELDS : [&str;3] = ["random_seed","seed_index","repetition"];
+
+///The leaves of the configuration in a context, as their path, such as `configuration.traffic.load`, and their value in the CSV.
+///Objects appear with their name besides their members. The fields in [SEED_FIELDS] are skipped.
+fn configuration_leaves(context:&ConfigurationValue) -> BTreeMap<String,String>
+{
+	fn add_leaves(value:&ConfigurationValue, path:String, leaves:&mut BTreeMap<String,String>)
+	{
+		match value
+		{
+			ConfigurationValue::Object(name,attributes) =>
+			{
+				for (attribute,attribute_value) in attributes
+				{
+					add_leaves(attribute_value,format!("{path}.{attribute}"),leaves);
+				}
+				leaves.insert(path,name.clone());
+			},
+			ConfigurationValue::Array(_) => { leaves.insert(path,format!("{value}").replace(',',";")); },
+			_ => { leaves.insert(path,value.to_csv_field()); },
+		}
+	}
+	let mut leaves = BTreeMap::new();
+	if let ConfigurationValue::Object(_,attributes) = context
+	{
+		if let Some((_,ConfigurationValue::Object(_,configuration))) = attributes.iter().find(|(name,_)|name=="configuration")
+		{
+			for (attribute,value) in configuration
+			{
+				if !SEED_FIELDS.contains(&attribute.as_str())
+				{
+					add_leaves(value,format!("configuration.{attribute}"),&mut leaves);
+				}
+			}
+		}
+	}
+	leaves
+}
+
+///The paths of the [configuration_leaves] that do not have the same value in all the results.
+fn varying_configuration_leaves(environment:&OutputEnvironment) -> Vec<String>
+{
+	//For each leaf, its distinct values and the number of results in which it appears.
+	let mut values : BTreeMap<String,(HashSet<String>,usize)> = BTreeMap::new();
+	let mut count = 0;
+	for context in environment.iter()
+	{
+		for (leaf,value) in configuration_leaves(&context)
+		{
+			let entry = values.entry(leaf).or_default();
+			entry.0.insert(value);
+			entry.1 += 1;
+		}
+		count += 1;
+	}
+	//A leaf missing in some result also varies.
+	values.into_iter().filter(|(_,(set,presence))|set.len()>1 || *presence<count).map(|(leaf,_)|leaf).collect()
+}
+
 ///The raw `ConfigurationValue`s to be used in a plot. Before being averaged.
 #[derive(PartialEq,PartialOrd,Debug)]
 struct RawRecord
@@ -661,6 +1022,7 @@ fn create_plots(description: &ConfigurationValue, environment:&mut OutputEnviron
 	let mut prefix=None;
 	let mut kind:Option<Vec<Plotkind>>=None;
 	let mut extra: Option<Vec<ConfigurationValue>> =None;
+	let mut confidence: Option<f64> = None;
 	match_object!(description,"Plots",value,
 		"selector" => selector=Some(value),
 		"legend" => legend=Some(value),
@@ -668,6 +1030,7 @@ fn create_plots(description: &ConfigurationValue, environment:&mut OutputEnviron
 		"kind" => kind = Some(value.as_array()?.iter().map(Plotkind::new).collect()),
 		"prefix" => prefix=Some(value.as_str()?.to_string()),
 		"extra" => extra=Some(value.as_array()?.clone()),
+		"confidence" => confidence=Some(value.as_f64()?),
 	);
 	let selector = selector.ok_or_else(||description.ill("There were no selector"))?;
 	let legend=legend.ok_or_else(||description.ill("There were no legend"))?;
@@ -877,10 +1240,16 @@ fn create_plots(description: &ConfigurationValue, environment:&mut OutputEnviron
 				let version = version_string(used_git,used_version);
 				version_set.insert(version);
 			}
+			let (mut abscissa,mut ordinate) = (standard_deviation(&current_abscissas),standard_deviation(&current_ordinates));
+			if let Some(confidence) = confidence
+			{
+				abscissa.1 = abscissa.1.map(|deviation|confidence_half_width(deviation,current_abscissas.len(),confidence));
+				ordinate.1 = ordinate.1.map(|deviation|confidence_half_width(deviation,current_ordinates.len(),confidence));
+			}
 			let averaged_record = AveragedRecord{selector:selector_value.clone(),
 							legend:legend_value.clone(),parameter:parameter_value.clone(),
-							abscissa:standard_deviation(&current_abscissas),
-							ordinate:standard_deviation(&current_ordinates),
+							abscissa,
+							ordinate,
 							len:current_abscissas.len(),
 							shared_abscissa:shared_element(&mut current_abscissas.iter()).cloned(),
 							upper_whisker:if boxplot {standard_deviation(&current_upper_whiskers).0} else {None},
@@ -983,6 +1352,8 @@ fn create_plots(description: &ConfigurationValue, environment:&mut OutputEnviron
 		{
 			//"Tikz" => return tikz_backend(backend,avgs,kind,(environment.results.len(),environment.total_experiments),prefix,environment.files),
 			"Tikz" => return tikz_backend(backend,avgs,kind,environment,prefix),
+			"Gnuplot" => return gnuplot_backend(backend,avgs,kind,environment,prefix),
+			"Matplotlib" => return matplotlib_backend(backend,avgs,kind,environment,prefix),
 			_ => panic!("unrecognized backend object {}",name),
 		};
 	}
@@ -1934,6 +2305,337 @@ fn tikz_backend(backend: &ConfigurationValue, averages: Vec<PlotData>, kind:Vec<
 	Ok(())
 }
 
+///A line of a figure for the script backends, with its points as `(x,y,x deviation,y deviation)`.
+struct ScriptPlotLine
+{
+	legend: String,
+	points: Vec<(f32,f32,f32,f32)>,
+}
+
+///The drawing of a [Plotkind] inside a figure for the script backends.
+struct ScriptPlotPanel
+{
+	kind_index: usize,
+	///The labels of the symbolic abscissas, which are drawn at their index.
+	ticks: Vec<String>,
+	lines: Vec<ScriptPlotLine>,
+}
+
+///A figure for the script backends, with a panel for each [Plotkind] with data for its selector value.
+struct ScriptPlotFigure
+{
+	selector: String,
+	panels: Vec<ScriptPlotPanel>,
+}
+
+///Arrange the averaged data by selector, kind, and legend, for the backends that write scripts.
+///The symbolic abscissas are replaced by their index and the box plots are reduced to their averages.
+fn script_plot_figures(averages:&[PlotData], kind:&[Plotkind]) -> Vec<ScriptPlotFigure>
+{
+	let mut figures : Vec<ScriptPlotFigure> = vec![];
+	for (kind_index,kaverages) in averages.iter().enumerate()
+	{
+		if kind[kind_index].upper_box_limit.is_some()
+		{
+			println!("WARNING: the box plots are drawn by their averages in the script backends.");
+		}
+		for record in kaverages.data.iter()
+		{
+			let selector = script_plot_label(&record.selector);
+			let figure_index = match figures.iter().position(|figure|figure.selector==selector)
+			{
+				Some(index) => index,
+				None =>
+				{
+					figures.push(ScriptPlotFigure{selector,panels:vec![]});
+					figures.len()-1
+				},
+			};
+			let figure = &mut figures[figure_index];
+			if figure.panels.last().map(|panel|panel.kind_index)!=Some(kind_index)
+			{
+				figure.panels.push(ScriptPlotPanel{kind_index,ticks:vec![],lines:vec![]});
+			}
+			let panel = figure.panels.last_mut().unwrap();
+			let legend = script_plot_label(&record.legend);
+			if panel.lines.last().map(|line|&line.legend)!=Some(&legend)
+			{
+				panel.lines.push(ScriptPlotLine{legend,points:vec![]});
+			}
+			let y = match record.ordinate.0
+			{
+				Some(y) => y,
+				None => continue,
+			};
+			let x = match (record.abscissa.0,record.shared_abscissa.as_ref())
+			{
+				(Some(x),_) => x,
+				(None,Some(symbol)) =>
+				{
+					let symbol = script_plot_label(symbol);
+					match panel.ticks.iter().position(|tick|*tick==symbol)
+					{
+						Some(index) => index as f32,
+						None =>
+						{
+							panel.ticks.push(symbol);
+							(panel.ticks.len()-1) as f32
+						},
+					}
+				},
+				(None,None) => continue,
+			};
+			let line = panel.lines.last_mut().unwrap();
+			line.points.push( (x,y,record.abscissa.1.unwrap_or(0f32),record.ordinate.1.unwrap_or(0f32)) );
+		}
+	}
+	//Remove what would be drawn empty.
+	for figure in figures.iter_mut()
+	{
+		for panel in figure.panels.iter_mut()
+		{
+			panel.lines.retain(|line|!line.points.is_empty());
+		}
+		figure.panels.retain(|panel|!panel.lines.is_empty());
+	}
+	figures.retain(|figure|!figure.panels.is_empty());
+	figures
+}
+
+///Writes the data of the figures, with a block for each line separated by two empty lines, as the `index` of gnuplot expects.
+fn write_script_plot_data(figures:&[ScriptPlotFigure], kind:&[Plotkind], data_path:&PathBuf) -> Result<(),Error>
+{
+	let mut data_file = File::create(data_path).map_err(|e|error!(could_not_generate_file,data_path.clone(),e))?;
+	let io_error = |e|error!(could_not_generate_file,data_path.clone(),e);
+	for figure in figures
+	{
+		for panel in figure.panels.iter()
+		{
+			for line in panel.lines.iter()
+			{
+				//The labels are written as comments, the lines may be broken by the values.
+				let comment = |text:&str|text.replace(['\n','\r']," ");
+				writeln!(data_file,"# {} / {} / {}",comment(&figure.selector),comment(&kind[panel.kind_index].label_ordinates),comment(&line.legend)).map_err(io_error)?;
+				writeln!(data_file,"# x y x_deviation y_deviation").map_err(io_error)?;
+				for (x,y,dx,dy) in line.points.iter()
+				{
+					writeln!(data_file,"{} {} {} {}",x,y,dx,dy).map_err(io_error)?;
+				}
+				writeln!(data_file,"\n").map_err(io_error)?;
+			}
+		}
+	}
+	Ok(())
+}
+
+///Read the options shared by the `Gnuplot` and `Matplotlib` backends: `(script_filename, data_filename, pdf_filename, execute)`.
+fn script_backend_options(backend: &ConfigurationValue, name:&str, prefix:&str) -> Result<(String,String,String,bool),Error>
+{
+	let mut script_filename=None;
+	let mut data_filename=None;
+	let mut pdf_filename=None;
+	let mut execute=true;
+	match_object!(backend,[name],value,
+		"script_filename" => script_filename = Some(value.as_str()?.to_string()),
+		"data_filename" => data_filename = Some(value.as_str()?.to_string()),
+		"pdf_filename" => pdf_filename = Some(value.as_str()?.to_string()),
+		"execute" => execute = value.as_bool()?,
+	);
+	let script_filename=script_filename.ok_or_else(||backend.ill("There were no script_filename"))?;
+	let pdf_filename=pdf_filename.ok_or_else(||backend.ill("There were no pdf_filename"))?;
+	let data_filename=data_filename.unwrap_or_else(||format!("{}.dat",prefix));
+	Ok((script_filename,data_filename,pdf_filename,execute))
+}
+
+///Run a script generated by a backend in the outputs directory. Failing to run it is only a warning, as the script can be run elsewhere.
+fn run_plot_script(program:&str, script_filename:&str, environment:&OutputEnvironment)
+{
+	let outputs_path = environment.files.get_outputs_path();
+	println!("Running {} {}",program,script_filename);
+	match Command::new(program).current_dir(&outputs_path).arg(script_filename).output()
+	{
+		Ok(output) if output.status.success() => (),
+		Ok(output) => println!("WARNING: {} failed on {}:\n{}",program,script_filename,String::from_utf8_lossy(&output.stderr)),
+		Err(error) => println!("WARNING: could not run {} ({}). The script {} has been written anyway.",program,error,script_filename),
+	}
+}
+
+///The text to show for a value in the script backends. The literals are shown without quotes.
+fn script_plot_label(value:&ConfigurationValue) -> String
+{
+	match value
+	{
+		ConfigurationValue::Literal(text) => text.clone(),
+		_ => value.to_string(),
+	}
+}
+
+///Escape a text to be inside double quotes in gnuplot and python.
+fn script_string(text:&str) -> String
+{
+	let escaped : String = text.chars().map(|c|match c{
+		'\\' => "\\\\".to_string(),
+		'"' => "\\\"".to_string(),
+		'\n' | '\r' => " ".to_string(),
+		x => format!("{}",x),
+	}).collect();
+	format!("\"{}\"",escaped)
+}
+
+///Draw a plot by writing a gnuplot script and its data file.
+///It has the same arguments as [tikz_backend].
+fn gnuplot_backend(backend: &ConfigurationValue, averages: Vec<PlotData>, kind:Vec<Plotkind>, environment:&mut OutputEnvironment, prefix:String)
+	-> Result<(),Error>
+{
+	let (script_filename,data_filename,pdf_filename,execute) = script_backend_options(backend,"Gnuplot",&prefix)?;
+	if let Some(targets) = environment.targets {
+		if !targets.contains(&script_filename) && !targets.contains(&pdf_filename) {
+			return Ok(());
+		}
+	};
+	let outputs_path = environment.files.get_outputs_path();
+	let figures = script_plot_figures(&averages,&kind);
+	write_script_plot_data(&figures,&kind,&outputs_path.join(&data_filename))?;
+	let script_path = outputs_path.join(&script_filename);
+	println!("Creating {:?}",script_path);
+	let mut script = String::new();
+	script.push_str(&format!("# Generated by CAMINOS {} with {} of {} results.\n",version_string(Some(get_git_id()),Some(get_version_number())),environment.available_results(),environment.total_experiments()));
+	script.push_str(&format!("set terminal pdfcairo noenhanced size {}cm,7cm\n",figures.iter().map(|figure|figure.panels.len()).max().unwrap_or(1)*10));
+	script.push_str(&format!("set output {}\n",script_string(&pdf_filename)));
+	script.push_str("set grid\nset key outside below\n");
+	let mut block = 0;
+	for figure in figures.iter()
+	{
+		script.push_str(&format!("set multiplot layout 1,{} title {}\n",figure.panels.len(),script_string(&figure.selector)));
+		for panel in figure.panels.iter()
+		{
+			let kd = &kind[panel.kind_index];
+			let bound = |value:Option<f32>|value.map(|x|x.to_string()).unwrap_or_else(||"*".to_string());
+			script.push_str(&format!("set xlabel {}\nset ylabel {}\n",script_string(&kd.label_abscissas),script_string(&kd.label_ordinates)));
+			script.push_str(&format!("set xrange [{}:{}]\nset yrange [{}:{}]\n",bound(kd.min_abscissa),bound(kd.max_abscissa),bound(kd.min_ordinate),bound(kd.max_ordinate)));
+			if panel.ticks.is_empty()
+			{
+				script.push_str("set xtics autofreq\n");
+			}
+			else
+			{
+				let ticks : Vec<String> = panel.ticks.iter().enumerate().map(|(index,tick)|format!("{} {}",script_string(tick),index)).collect();
+				script.push_str(&format!("set xtics rotate ({})\n",ticks.join(", ")));
+			}
+			let style = if kd.bar { "using 1:2:4 with boxerrorbars" } else { "using 1:2:3:4 with xyerrorlines" };
+			let plots : Vec<String> = panel.lines.iter().enumerate().map(|(line_index,line)|
+				format!("{} index {} {} title {}",script_string(&data_filename),block+line_index,style,script_string(&line.legend))
+			).collect();
+			block += panel.lines.len();
+			script.push_str(&format!("plot {}\n",plots.join(", \\\n\t")));
+		}
+		script.push_str("unset multiplot\n");
+	}
+	let mut script_file = File::create(&script_path).map_err(|e|error!(could_not_generate_file,script_path.clone(),e))?;
+	script_file.write_all(script.as_bytes()).map_err(|e|error!(could_not_generate_file,script_path.clone(),e))?;
+	if execute
+	{
+		run_plot_script("gnuplot",&script_filename,environment);
+	}
+	Ok(())
+}
+
+///Draw a plot by writing a python script using matplotlib and its data file.
+///It has the same arguments as [tikz_backend].
+fn matplotlib_backend(backend: &ConfigurationValue, averages: Vec<PlotData>, kind:Vec<Plotkind>, environment:&mut OutputEnvironment, prefix:String)
+	-> Result<(),Error>
+{
+	let (script_filename,data_filename,pdf_filename,execute) = script_backend_options(backend,"Matplotlib",&prefix)?;
+	if let Some(targets) = environment.targets {
+		if !targets.contains(&script_filename) && !targets.contains(&pdf_filename) {
+			return Ok(());
+		}
+	};
+	let outputs_path = environment.files.get_outputs_path();
+	let figures = script_plot_figures(&averages,&kind);
+	write_script_plot_data(&figures,&kind,&outputs_path.join(&data_filename))?;
+	let script_path = outputs_path.join(&script_filename);
+	println!("Creating {:?}",script_path);
+	let option = |value:Option<f32>|value.map(|x|x.to_string()).unwrap_or_else(||"None".to_string());
+	let mut block = 0;
+	let figures_code : Vec<String> = figures.iter().map(|figure|{
+		let panels : Vec<String> = figure.panels.iter().map(|panel|{
+			let kd = &kind[panel.kind_index];
+			let ticks : Vec<String> = panel.ticks.iter().map(|tick|script_string(tick)).collect();
+			let lines : Vec<String> = panel.lines.iter().map(|line|{
+				block += 1;
+				format!("({},{})",script_string(&line.legend),block-1)
+			}).collect();
+			format!("\t\tdict(xlabel={}, ylabel={}, xlim=({},{}), ylim=({},{}), bar={}, ticks=[{}], lines=[{}]),\n",
+				script_string(&kd.label_abscissas),script_string(&kd.label_ordinates),
+				option(kd.min_abscissa),option(kd.max_abscissa),option(kd.min_ordinate),option(kd.max_ordinate),
+				if kd.bar {"True"} else {"False"},ticks.join(", "),lines.join(", "))
+		}).collect();
+		format!("\tdict(title={}, panels=[\n{}\t]),\n",script_string(&figure.selector),panels.concat())
+	}).collect();
+	let script = format!(r##"# Generated by CAMINOS {version} with {done} of {total} results.
+import matplotlib
+matplotlib.use("Agg")
+import matplotlib.pyplot as plt
+from matplotlib.backends.backend_pdf import PdfPages
+
+def read_blocks(filename):
+	"""The blocks of points (x, y, x deviation, y deviation), separated by empty lines."""
+	blocks = [[]]
+	with open(filename) as data:
+		for line in data:
+			line = line.strip()
+			if line.startswith("#"):
+				continue
+			if line == "":
+				if blocks[-1]:
+					blocks.append([])
+				continue
+			blocks[-1].append([float(x) for x in line.split()])
+	if not blocks[-1]:
+		blocks.pop()
+	return blocks
+
+figures = [
+{figures}]
+
+blocks = read_blocks({data_filename})
+with PdfPages({pdf_filename}) as pdf:
+	for figure in figures:
+		fig, axes = plt.subplots(1, len(figure["panels"]), figsize=(5*len(figure["panels"]), 4), squeeze=False)
+		for ax, panel in zip(axes[0], figure["panels"]):
+			for legend, block in panel["lines"]:
+				points = blocks[block]
+				x = [p[0] for p in points]
+				y = [p[1] for p in points]
+				if panel["bar"]:
+					ax.bar(x, y, yerr=[p[3] for p in points], label=legend)
+				else:
+					ax.errorbar(x, y, xerr=[p[2] for p in points], yerr=[p[3] for p in points], marker="o", label=legend)
+			ax.set_xlabel(panel["xlabel"])
+			ax.set_ylabel(panel["ylabel"])
+			ax.set_xlim(*panel["xlim"])
+			ax.set_ylim(*panel["ylim"])
+			if panel["ticks"]:
+				ax.set_xticks(range(len(panel["ticks"])))
+				ax.set_xticklabels(panel["ticks"], rotation=90)
+			ax.grid(True)
+		axes[0][0].legend(fontsize="small")
+		fig.suptitle(figure["title"])
+		fig.tight_layout()
+		pdf.savefig(fig)
+		plt.close(fig)
+"##,version=version_string(Some(get_git_id()),Some(get_version_number())),done=environment.available_results(),total=environment.total_experiments(),
+		figures=figures_code.concat(),data_filename=script_string(&data_filename),pdf_filename=script_string(&pdf_filename));
+	let mut script_file = File::create(&script_path).map_err(|e|error!(could_not_generate_file,script_path.clone(),e))?;
+	script_file.write_all(script.as_bytes()).map_err(|e|error!(could_not_generate_file,script_path.clone(),e))?;
+	if execute
+	{
+		run_plot_script("python3",&script_filename,environment);
+	}
+	Ok(())
+}
+
 
 
 /**
@@ -2051,6 +2753,177 @@ fn create_preprocess_arg_max(description: &ConfigurationValue, environment:&mut
 
 
 
+///Creates a CSV summarizing the computational resources employed by the simulations.
+fn create_resource_usage(description: &ConfigurationValue, environment:&mut OutputEnvironment) -> Result<(),Error>
+{
+	let mut filename = None;
+	let mut selector = None;
+	match_object!(description,"ResourceUsage",value,
+		"filename" => filename = Some(value.as_str()?.to_string()),
+		"selector" => selector=Some(value),
+	);
+	let filename = filename.ok_or_else(||description.ill("There were no filename"))?;
+	if let Some(targets) = environment.targets {
+		if !targets.contains(&filename) {
+			return Ok(());
+		}
+	};
+	let outputs_path = environment.files.get_outputs_path();
+	let result_field = |context:&ConfigurationValue, name:&str| -> Option<f64> {
+		let expr = Expr::Member(Rc::new(Expr::Ident("result".to_string())),name.to_string());
+		evaluate(&expr,context,&outputs_path).ok()?.as_f64().ok()
+	};
+	//The accumulated [runs, wall_time, cpu_time, maximum high water mark] of each selector value.
+	let mut groups : BTreeMap<String,(usize,f64,f64,f64)> = BTreeMap::new();
+	let mut total = (0usize,0f64,0f64,0f64);
+	for context in environment.iter()
+	{
+		let key = match selector
+		{
+			Some(selector) => match reevaluate(selector,&context,&outputs_path)?
+			{
+				ConfigurationValue::Array(values) => values.iter().map(|value|value.to_csv_field()).collect::<Vec<_>>().join(" "),
+				value => value.to_csv_field(),
+			},
+			None => String::new(),
+		};
+		let wall_time = result_field(&context,"wall_time").unwrap_or(0f64);
+		let cpu_time = result_field(&context,"cpu_time").unwrap_or_else(||
+			result_field(&context,"user_time").unwrap_or(0f64) + result_field(&context,"system_time").unwrap_or(0f64)
+		);
+		let memory = result_field(&context,"linux_high_water_mark").unwrap_or(0f64);
+		for entry in [groups.entry(key).or_insert((0,0f64,0f64,0f64)), &mut total]
+		{
+			entry.0 += 1;
+			entry.1 += wall_time;
+			entry.2 += cpu_time;
+			entry.3 = entry.3.max(memory);
+		}
+	}
+	println!("Creating resource usage summary with name \"{}\"",filename);
+	let output_path = outputs_path.join(&filename);
+	let mut output_file = File::create(&output_path).map_err(|e|error!(could_not_generate_file,output_path.clone(),e))?;
+	let write_row = |output_file:&mut File, name:&str, (runs,wall_time,cpu_time,memory):(usize,f64,f64,f64)|{
+		writeln!(output_file,"{}, {}, {}, {}, {}, {}, {}",name,runs,wall_time,wall_time/runs as f64,cpu_time,cpu_time/runs as f64,memory)
+	};
+	let io_error = |e|error!(could_not_generate_file,output_path.clone(),e);
+	writeln!(output_file,"selector, runs, total_wall_time, average_wall_time, total_cpu_time, average_cpu_time, maximum_high_water_mark").map_err(io_error)?;
+	if selector.is_some()
+	{
+		for (name,accumulated) in groups.into_iter()
+		{
+			write_row(&mut output_file,&name,accumulated).map_err(io_error)?;
+		}
+	}
+	write_row(&mut output_file,"total",total).map_err(io_error)?;
+	println!("The experiment employed {} seconds of CPU in {} runs.",total.2,total.0);
+	Ok(())
+}
+
+///Writes the matrices of the utilization of the links, averaged for each selector value. See [create_output].
+fn create_link_heatmap(description: &ConfigurationValue, environment:&mut OutputEnvironment) -> Result<(),Error>
+{
+	let mut filename = None;
+	let mut selector = None;
+	let mut tex_filename = None;
+	match_object!(description,"LinkHeatmap",value,
+		"filename" => filename = Some(value.as_str()?.to_string()),
+		"selector" => selector=Some(value),
+		"tex_filename" => tex_filename = Some(value.as_str()?.to_string()),
+	);
+	let filename = filename.ok_or_else(||description.ill("There were no filename"))?;
+	if let Some(targets) = environment.targets {
+		if !targets.contains(&filename) && !tex_filename.as_ref().map(|tex|targets.contains(tex)).unwrap_or(false) {
+			return Ok(());
+		}
+	};
+	let outputs_path = environment.files.get_outputs_path();
+	let utilization_expr = Expr::Member(Rc::new(Expr::Ident("result".to_string())),"link_utilization".to_string());
+	//The number of runs and the summed matrix of each selector value.
+	let mut groups : BTreeMap<String,(usize,Vec<Vec<f64>>)> = BTreeMap::new();
+	for context in environment.iter()
+	{
+		let matrix = match evaluate(&utilization_expr,&context,&outputs_path)
+		{
+			Ok(ConfigurationValue::Array(rows)) => rows.iter().map(|row|
+				row.as_array()?.iter().map(|x|x.as_f64()).collect::<Result<Vec<f64>,Error>>()
+			).collect::<Result<Vec<Vec<f64>>,Error>>()?,
+			//Results without the utilization of the links, such as the ones of simulations without `statistics_link_utilization`.
+			_ => continue,
+		};
+		let key = match selector
+		{
+			Some(selector) => reevaluate(selector,&context,&outputs_path)?.to_csv_field(),
+			None => String::new(),
+		};
+		let (runs,sum) = groups.entry(key).or_insert_with(||(0,vec![]));
+		if *runs==0
+		{
+			*sum = matrix;
+		}
+		else
+		{
+			if sum.len()!=matrix.len() || sum.iter().zip(matrix.iter()).any(|(a,b)|a.len()!=b.len())
+			{
+				return Err(description.ill("runs with the same selector have different topologies"));
+			}
+			for (sum_row,row) in sum.iter_mut().zip(matrix.iter())
+			{
+				for (total,x) in sum_row.iter_mut().zip(row.iter())
+				{
+					*total += x;
+				}
+			}
+		}
+		*runs += 1;
+	}
+	if groups.is_empty()
+	{
+		println!("WARNING: there are no results with link_utilization. Set statistics_link_utilization:true in the simulations.");
+	}
+	let averages : Vec<(String,Vec<Vec<f64>>)> = groups.into_iter().map(|(key,(runs,sum))|
+		(key, sum.into_iter().map(|row|row.into_iter().map(|x|x/runs as f64).collect()).collect())
+	).collect();
+	println!("Creating link heatmap with name \"{}\"",filename);
+	let output_path = outputs_path.join(&filename);
+	let mut output_file = File::create(&output_path).map_err(|e|error!(could_not_generate_file,output_path.clone(),e))?;
+	let io_error = |e|error!(could_not_generate_file,output_path.clone(),e);
+	for (key,matrix) in averages.iter()
+	{
+		let columns = matrix.iter().map(|row|row.len()).max().unwrap_or(0);
+		writeln!(output_file,"selector, {}",key).map_err(io_error)?;
+		writeln!(output_file,"router, {}",(0..columns).map(|port|format!("port{}",port)).collect::<Vec<String>>().join(", ")).map_err(io_error)?;
+		for (router,row) in matrix.iter().enumerate()
+		{
+			let cells = (0..columns).map(|port|row.get(port).map(|x|x.to_string()).unwrap_or_default()).collect::<Vec<String>>();
+			writeln!(output_file,"{}, {}",router,cells.join(", ")).map_err(io_error)?;
+		}
+	}
+	if let Some(tex_filename) = tex_filename
+	{
+		let tex_path = outputs_path.join(&tex_filename);
+		let mut tex_file = File::create(&tex_path).map_err(|e|error!(could_not_generate_file,tex_path.clone(),e))?;
+		let io_error = |e|error!(could_not_generate_file,tex_path.clone(),e);
+		writeln!(tex_file,"\\documentclass{{standalone}}\n\\usepackage{{pgfplots}}\n\\pgfplotsset{{compat=1.15}}\n\\begin{{document}}").map_err(io_error)?;
+		for (key,matrix) in averages.iter()
+		{
+			let columns = matrix.iter().map(|row|row.len()).max().unwrap_or(0);
+			writeln!(tex_file,"\\begin{{tikzpicture}}\n\\begin{{axis}}[title={{{}}}, xlabel={{port}}, ylabel={{router}}, y dir=reverse, colorbar, colormap/hot2, point meta min=0, enlargelimits=false, axis on top]",latex_protect_text(key)).map_err(io_error)?;
+			writeln!(tex_file,"\\addplot[matrix plot*, mesh/cols={}, point meta=explicit] coordinates {{",columns).map_err(io_error)?;
+			for (router,row) in matrix.iter().enumerate()
+			{
+				//Missing ports are drawn as unused links, as pgfplots requires complete rows.
+				let points = (0..columns).map(|port|format!("({},{}) [{}]",port,router,row.get(port).copied().unwrap_or(0f64))).collect::<Vec<String>>();
+				writeln!(tex_file,"{}",points.join(" ")).map_err(io_error)?;
+				writeln!(tex_file).map_err(io_error)?;
+			}
+			writeln!(tex_file,"}};\n\\end{{axis}}\n\\end{{tikzpicture}}").map_err(io_error)?;
+		}
+		writeln!(tex_file,"\\end{{document}}").map_err(io_error)?;
+	}
+	Ok(())
+}
+
 /// Calculates the average and deviation of the values in a Vec.
 fn standard_deviation(list:&Vec<ConfigurationValue>) -> (Option<f32>,Option<f32>)
 {
@@ -2080,6 +2953,65 @@ fn standard_deviation(list:&Vec<ConfigurationValue>) -> (Option<f32>,Option<f32>
 	(Some(average),Some(deviation))
 }
 
+///The half width of the confidence interval of the mean of `count` values with the given sample `deviation`, using the Student's t distribution.
+fn confidence_half_width(deviation:f32, count:usize, confidence:f64) -> f32
+{
+	if count<2
+	{
+		return 0.0;
+	}
+	let quantile = student_t_quantile(0.5+confidence/2.0, count-1);
+	(quantile*deviation as f64/(count as f64).sqrt()) as f32
+}
+
+///The quantile `p` of the Student's t distribution with `freedom` degrees of freedom.
+///Exact for 1 and 2 degrees of freedom and by the Cornish-Fisher expansion of the normal quantile otherwise.
+fn student_t_quantile(p:f64, freedom:usize) -> f64
+{
+	match freedom
+	{
+		1 => (std::f64::consts::PI*(p-0.5)).tan(),
+		2 => (2.0*p-1.0)/(2.0*p*(1.0-p)).sqrt(),
+		_ =>
+		{
+			let z = normal_quantile(p);
+			let n = freedom as f64;
+			let z3 = z.powi(3);
+			let z5 = z.powi(5);
+			let z7 = z.powi(7);
+			let z9 = z.powi(9);
+			z + (z3+z)/(4.0*n)
+				+ (5.0*z5+16.0*z3+3.0*z)/(96.0*n*n)
+				+ (3.0*z7+19.0*z5+17.0*z3-15.0*z)/(384.0*n.powi(3))
+				+ (79.0*z9+776.0*z7+1482.0*z5-1920.0*z3-945.0*z)/(92160.0*n.powi(4))
+		},
+	}
+}
+
+///The quantile `p` of the standard normal distribution, by the rational approximation of Acklam.
+fn normal_quantile(p:f64) -> f64
+{
+	let a = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02, 1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+	let b = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02, 6.680131188771972e+01, -1.328068155288572e+01];
+	let c = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00, -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+	let d = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00, 3.754408661907416e+00];
+	let tail = |q:f64| (((((c[0]*q+c[1])*q+c[2])*q+c[3])*q+c[4])*q+c[5]) / ((((d[0]*q+d[1])*q+d[2])*q+d[3])*q+1.0);
+	if p<0.02425
+	{
+		tail((-2.0*p.ln()).sqrt())
+	}
+	else if p>1.0-0.02425
+	{
+		-tail((-2.0*(1.0-p).ln()).sqrt())
+	}
+	else
+	{
+		let q = p-0.5;
+		let r = q*q;
+		(((((a[0]*r+a[1])*r+a[2])*r+a[3])*r+a[4])*r+a[5])*q / (((((b[0]*r+b[1])*r+b[2])*r+b[3])*r+b[4])*r+1.0)
+	}
+}
+
 ///Get a Some(x) if all elements are equal.
 fn shared_element<I:Iterator>(iter:&mut I) -> Option<I::Item> where I::Item : PartialEq
 {
@@ -2139,5 +3071,205 @@ pub fn version_string(git_id:Option<&str>,version_number:Option<&str>) -> String
 	}
 }
 
+///Writes a JSON line with the context of each experiment. See [create_output].
+fn create_json(description: &ConfigurationValue, environment:&mut OutputEnvironment) -> Result<(),Error>
+{
+	let mut filename = None;
+	match_object!(description,"JSON",value,
+		"filename" => filename = Some(value.as_str()?.to_string()),
+	);
+	let filename = filename.ok_or_else(||description.ill("There were no filename"))?;
+	if let Some(targets) = environment.targets {
+		if !targets.contains(&filename) {
+			return Ok(());
+		}
+	};
+	println!("Creating JSON with name \"{}\"",filename);
+	let output_path = environment.files.get_outputs_path().join(&filename);
+	let mut output_file = File::create(&output_path).map_err(|e|error!(could_not_generate_file,output_path.clone(),e))?;
+	for context in environment.iter()
+	{
+		writeln!(output_file,"{}",config::config_to_json(&context)).map_err(|e|error!(could_not_generate_file,output_path.clone(),e))?;
+	}
+	Ok(())
+}
+
+///Appends to `columns` the leaves of `value` with their paths below `prefix`.
+///An object gives its name at its own path, and its fields are flattened below it. Arrays are leaves.
+#[cfg_attr(not(feature="parquet"),allow(dead_code))]
+fn flatten_columns(prefix:&str, value:&ConfigurationValue, columns:&mut Vec<(String,ConfigurationValue)>)
+{
+	match value
+	{
+		ConfigurationValue::Object(name,fields) =>
+		{
+			if !prefix.is_empty()
+			{
+				columns.push( (prefix.to_string(),ConfigurationValue::Literal(name.clone())) );
+			}
+			for (key,field) in fields.iter()
+			{
+				let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}",prefix,key) };
+				flatten_columns(&path,field,columns);
+			}
+		},
+		_ => columns.push( (prefix.to_string(),value.clone()) ),
+	}
+}
 
+///Writes a Parquet table with the flattened context of each experiment. See [create_output].
+#[cfg(feature="parquet")]
+fn create_parquet(description: &ConfigurationValue, environment:&mut OutputEnvironment) -> Result<(),Error>
+{
+	use std::sync::Arc;
+	use parquet::basic::{LogicalType,Repetition,Type as PhysicalType};
+	use parquet::data_type::{BoolType,ByteArray,ByteArrayType,DoubleType};
+	use parquet::file::{properties::WriterProperties,writer::SerializedFileWriter};
+	use parquet::schema::types::Type;
+	let mut filename = None;
+	match_object!(description,"Parquet",value,
+		"filename" => filename = Some(value.as_str()?.to_string()),
+	);
+	let filename = filename.ok_or_else(||description.ill("There were no filename"))?;
+	if let Some(targets) = environment.targets {
+		if !targets.contains(&filename) {
+			return Ok(());
+		}
+	};
+	println!("Creating Parquet with name \"{}\"",filename);
+	//The columns in order of appearance, with the value of each row.
+	let mut names : Vec<String> = vec![];
+	let mut positions : HashMap<String,usize> = HashMap::new();
+	let mut values : Vec<Vec<Option<ConfigurationValue>>> = vec![];
+	let rows = environment.available_results();
+	for (row,context) in environment.iter().enumerate()
+	{
+		let mut columns = vec![];
+		flatten_columns("",&context,&mut columns);
+		for (name,value) in columns
+		{
+			let position = *positions.entry(name.clone()).or_insert_with(||{
+				names.push(name);
+				values.push(vec![None;rows]);
+				values.len()-1
+			});
+			if !matches!(value,ConfigurationValue::None)
+			{
+				values[position][row] = Some(value);
+			}
+		}
+	}
+	enum Kind { Double, Boolean, Text }
+	let kinds : Vec<Kind> = values.iter().map(|column|{
+		let mut present = column.iter().flatten().peekable();
+		if present.peek().is_none() { Kind::Text }
+		else if column.iter().flatten().all(|value|matches!(value,ConfigurationValue::Number(_))) { Kind::Double }
+		else if column.iter().flatten().all(|value|matches!(value,ConfigurationValue::True|ConfigurationValue::False)) { Kind::Boolean }
+		else { Kind::Text }
+	}).collect();
+	let parquet_error = |e:parquet::errors::ParquetError|error!(undetermined).with_message(format!("could not write the Parquet file {}: {}",filename,e));
+	let fields = names.iter().zip(kinds.iter()).map(|(name,kind)|{
+		let builder = match kind
+		{
+			Kind::Double => Type::primitive_type_builder(name,PhysicalType::DOUBLE),
+			Kind::Boolean => Type::primitive_type_builder(name,PhysicalType::BOOLEAN),
+			Kind::Text => Type::primitive_type_builder(name,PhysicalType::BYTE_ARRAY).with_logical_type(Some(LogicalType::String)),
+		};
+		builder.with_repetition(Repetition::OPTIONAL).build().map(Arc::new)
+	}).collect::<Result<Vec<_>,_>>().map_err(parquet_error)?;
+	let schema = Arc::new(Type::group_type_builder("results").with_fields(fields).build().map_err(parquet_error)?);
+	let output_path = environment.files.get_outputs_path().join(&filename);
+	let output_file = File::create(&output_path).map_err(|e|error!(could_not_generate_file,output_path.clone(),e))?;
+	let mut writer = SerializedFileWriter::new(output_file,schema,Arc::new(WriterProperties::builder().build())).map_err(parquet_error)?;
+	let mut row_group = writer.next_row_group().map_err(parquet_error)?;
+	let mut index = 0;
+	while let Some(mut column_writer) = row_group.next_column().map_err(parquet_error)?
+	{
+		let column = &values[index];
+		let definition_levels : Vec<i16> = column.iter().map(|value|if value.is_some() {1} else {0}).collect();
+		match kinds[index]
+		{
+			Kind::Double =>
+			{
+				let data : Vec<f64> = column.iter().flatten().map(|value|value.as_f64().unwrap()).collect();
+				column_writer.typed::<DoubleType>().write_batch(&data,Some(&definition_levels),None).map_err(parquet_error)?;
+			},
+			Kind::Boolean =>
+			{
+				let data : Vec<bool> = column.iter().flatten().map(|value|value.as_bool().unwrap()).collect();
+				column_writer.typed::<BoolType>().write_batch(&data,Some(&definition_levels),None).map_err(parquet_error)?;
+			},
+			Kind::Text =>
+			{
+				let data : Vec<ByteArray> = column.iter().flatten().map(|value|match value
+				{
+					ConfigurationValue::Literal(s) => ByteArray::from(s.as_str()),
+					ConfigurationValue::Number(x) => ByteArray::from(format!("{}",x).as_str()),
+					other => ByteArray::from(config::config_to_json(other).as_str()),
+				}).collect();
+				column_writer.typed::<ByteArrayType>().write_batch(&data,Some(&definition_levels),None).map_err(parquet_error)?;
+			},
+		}
+		column_writer.close().map_err(parquet_error)?;
+		index+=1;
+	}
+	row_group.close().map_err(parquet_error)?;
+	writer.close().map_err(parquet_error)?;
+	Ok(())
+}
 
+#[cfg(not(feature="parquet"))]
+fn create_parquet(description: &ConfigurationValue, _environment:&mut OutputEnvironment) -> Result<(),Error>
+{
+	Err(description.ill("The Parquet output requires compiling caminos-lib with the `parquet` feature"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn long_format_flattening()
+	{
+		let number = |x:f64| ConfigurationValue::Number(x);
+		let result = ConfigurationValue::Object("Result".to_string(),vec![
+			("accepted_load".to_string(),number(0.5)),
+			("git_id".to_string(),ConfigurationValue::Literal("abc".to_string())),
+			("temporal_statistics".to_string(),ConfigurationValue::Object("TemporalStatistics".to_string(),vec![
+				("injected_load".to_string(),ConfigurationValue::Array(vec![number(0.25),number(0.75)])),
+			])),
+			("histograms".to_string(),ConfigurationValue::Array(vec![
+				ConfigurationValue::Array(vec![number(1.0),number(2.0)]),
+				ConfigurationValue::Object("Bin".to_string(),vec![("saturated".to_string(),ConfigurationValue::True)]),
+			])),
+		]);
+		let mut rows = vec![];
+		flatten_metric(&result,String::new(),None,&mut rows);
+		assert_eq!(rows,vec![
+			("accepted_load".to_string(),None,0.5),
+			("temporal_statistics.injected_load".to_string(),Some(0),0.25),
+			("temporal_statistics.injected_load".to_string(),Some(1),0.75),
+			("histograms[0]".to_string(),Some(0),1.0),
+			("histograms[1]".to_string(),Some(0),2.0),
+			("histograms.saturated".to_string(),Some(1),1.0),
+		]);
+	}
+	#[test]
+	fn column_flattening()
+	{
+		let context = ConfigurationValue::Object("Context".to_string(),vec![
+			("index".to_string(),ConfigurationValue::Number(3.0)),
+			("configuration".to_string(),ConfigurationValue::Object("Configuration".to_string(),vec![
+				("traffic".to_string(),ConfigurationValue::Object("Homogeneous".to_string(),vec![("load".to_string(),ConfigurationValue::Number(0.5))])),
+			])),
+			("result".to_string(),ConfigurationValue::Object("Result".to_string(),vec![
+				("per_hop".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)])),
+			])),
+		]);
+		let mut columns = vec![];
+		flatten_columns("",&context,&mut columns);
+		let names : Vec<&str> = columns.iter().map(|(name,_)|name.as_str()).collect();
+		assert_eq!(names,vec!["index","configuration","configuration.traffic","configuration.traffic.load","result","result.per_hop"]);
+		assert_eq!(columns[2].1,ConfigurationValue::Literal("Homogeneous".to_string()));
+		assert_eq!(columns[5].1,ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)]));
+	}
+}
@@ -18,6 +18,8 @@ use std::ops::Deref;
 
 use crate::routing::RoutingInfo;
 use crate::event::Time;
+use crate::config_parser::ConfigurationValue;
+use crate::measures::PacketDecisions;
 
 ///Minimal unit to be processed by the network.
 ///Not to be confused with flits.
@@ -62,11 +64,17 @@ pub struct Packet
 	pub message: Rc<Message>,
 	///position inside the message
 	pub index: usize,
+	///The number of earlier transmissions of this packet, 0 for the first one. See [lossy](crate::lossy).
+	pub transmission: usize,
 	///The cycle when the packet has touched the first router. This is, the packet leading phit has been inserted into a router.
 	///We set it to 0 if the packet has not entered the network yet.
 	pub cycle_into_network: RefCell<Time>,
 	///Extra info tracked for some special statistics.
 	pub extra: RefCell<Option<PacketExtraInfo>>,
+	///Whether some router has marked the packet as having traversed a congested port. See [congestion](crate::congestion).
+	pub congestion_marked: RefCell<bool>,
+	///The routing decisions of the packet, when sampled by [DecisionFeatures](crate::measures::DecisionFeatures).
+	pub decisions: RefCell<Option<PacketDecisions>>,
 }
 
 #[cfg(feature="slab_packet")]
@@ -115,10 +123,77 @@ pub struct Message
 	pub payload: Vec<u8>,
 	///Id traffic owner
 	pub id_traffic: Option<usize>,
+	///Hints given by the traffic to the routing. They are copied into the [RoutingInfo] of each packet of the message.
+	pub routing_hints: Option<Rc<RoutingHints>>,
+	///Semantic identifiers given by the traffic, to group the statistics of the messages by them.
+	pub tag: Option<Rc<MessageTag>>,
+	///The traffic class of the message, for quality of service. The `MapClass` policy can assign different virtual channels to each class, and the results include statistics per class. By default 0.
+	pub class: usize,
 	// ///Cycle when the first packet of the message was injected into the network.
 	// pub cycle_into_network: RefCell<Option<Time>>,
 }
 
+///Information about a message that the traffic gives to the routing, so that applications can inform the routing decisions.
+///Routings are free to ignore any of them. See the `WithRoutingHints` traffic in [new_traffic](crate::traffic::new_traffic) to attach them to the messages of a traffic.
+#[derive(Quantifiable)]
+#[derive(Debug,Clone,Default)]
+pub struct RoutingHints
+{
+	///The message is sensitive to latency and should avoid long routes. For example, [UGAL](crate::routing::basic::UGAL) always routes it minimally.
+	pub latency_critical: bool,
+	///The preferred plane, for routings over networks with several planes.
+	pub plane: Option<usize>,
+	///An explicit intermediate router for the routings that select one, such as [Valiant](crate::routing::basic::Valiant) and [UGAL](crate::routing::basic::UGAL).
+	pub intermediate: Option<usize>,
+}
+
+///Semantic identifiers of a message, such as the collective and the phase of the collective to which it belongs.
+///They are available to the `statistics_packet_definitions` and `statistics_message_definitions` as `tag.collective`, `tag.phase`, and `tag.event`, and are written into the `packet_trace_file`.
+///The collectives built as a `CollectiveSchedule` set the phase and the `TraceReplay` traffic sets the event. See the `Tagged` traffic in [new_traffic](crate::traffic::new_traffic) to set them on any traffic.
+#[derive(Quantifiable)]
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct MessageTag
+{
+	///An identifier of the collective operation.
+	pub collective: Option<usize>,
+	///The phase of the collective or application.
+	pub phase: Option<usize>,
+	///The event of a trace or task graph that generated the message, which identifies the node of the dependency graph.
+	pub event: Option<usize>,
+}
+
+impl MessageTag
+{
+	///Fill the fields that are not set with the ones of `other`.
+	pub fn or(&self, other:Option<&MessageTag>) -> MessageTag
+	{
+		match other
+		{
+			None => self.clone(),
+			Some(other) => MessageTag{
+				collective: self.collective.or(other.collective),
+				phase: self.phase.or(other.phase),
+				event: self.event.or(other.event),
+			},
+		}
+	}
+	///The tag as an object `MessageTag{collective,phase,event}`, with `None` for the fields not set. A message without tag gets all of them `None`.
+	pub fn to_config_value(tag:Option<&MessageTag>) -> ConfigurationValue
+	{
+		let field = |value:Option<usize>| match value
+		{
+			Some(x) => ConfigurationValue::Number(x as f64),
+			None => ConfigurationValue::None,
+		};
+		let tag = tag.cloned().unwrap_or_default();
+		ConfigurationValue::Object(String::from("MessageTag"),vec![
+			(String::from("collective"),field(tag.collective)),
+			(String::from("phase"),field(tag.phase)),
+			(String::from("event"),field(tag.event)),
+		])
+	}
+}
+
 impl Phit
 {
 	///Whether the phit is leading a packet. Routers check this to make requests, stablish flows, etc.
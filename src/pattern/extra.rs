@@ -1,15 +1,17 @@
-use crate::pattern::new_pattern;
+use crate::pattern::try_new_pattern;
 use std::cell::{RefCell};
 use std::collections::VecDeque;
 use std::convert::TryInto;
 use ::rand::{Rng,rngs::StdRng};
 use std::fs::File;
 use std::io::{BufRead,BufReader};
+use std::path::PathBuf;
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::topology::cartesian::CartesianData;//for CartesianTransform
 use crate::topology::{Topology, Location};
-use crate::{match_object_panic};
+use crate::{match_object,error,source_location};
 use crate::pattern::{Pattern, PatternBuilderArgument};
 
 
@@ -48,13 +50,13 @@ impl Pattern for FileMap
 
 impl FileMap
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> FileMap
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<FileMap,Error>
     {
         let mut filename=None;
-        match_object_panic!(arg.cv,"FileMap",value,
-			"filename" => filename = Some(value.as_str().expect("bad value for filename").to_string()),
+        match_object!(arg.cv,"FileMap",value,
+			"filename" => filename = Some(value.as_str()?.to_string()),
 		);
-        let filename=filename.expect("There were no filename");
+        let filename=filename.ok_or_else(||arg.cv.ill("There were no filename"))?;
         let file=File::open(&filename).expect("could not open pattern file.");
         let reader = BufReader::new(&file);
         let mut permutation=Vec::new();
@@ -70,23 +72,192 @@ impl FileMap
             }
             permutation[origin]=destination;
         }
-        FileMap{
+        Ok(FileMap{
             permutation,
-        }
+        })
     }
-    pub(crate) fn embedded(arg:PatternBuilderArgument) -> FileMap
+    pub(crate) fn embedded(arg:PatternBuilderArgument) -> Result<FileMap,Error>
     {
         let mut map = None;
-        match_object_panic!(arg.cv,"EmbeddedMap",value,
+        match_object!(arg.cv,"EmbeddedMap",value,
 			"map" => map = Some(value.as_array()
-				.expect("bad value for map").iter()
-				.map(|v|v.as_f64().expect("bad value for map") as usize).collect()),
+				?.iter()
+				.map(|v|Ok(v.as_f64()? as usize)).collect::<Result<_,_>>()?),
 		);
-        let permutation = map.expect("There were no map");
-        FileMap{
+        let permutation = map.ok_or_else(||arg.cv.ill("There were no map"))?;
+        Ok(FileMap{
             permutation
+        })
+    }
+}
+
+
+/**
+A traffic matrix read from file, in which each source selects its destinations with probability proportional to the weights of its row.
+This allows to reproduce dense matrices measured in real systems, while [FileMap] only gives a destination to each source.
+The entry `(i,j)` is the weight of sending from `i` to `j`. The weights may be any non-negative numbers, such as bytes or messages.
+The matrix must have a row for each source and a column for each destination. A source with null row cannot generate messages.
+
+If the filename ends in `.npy` it is read as a two-dimensional NumPy array in C order, of any of the types `f8`, `f4`, `i8`, `i4`, `u8` or `u4`.
+Otherwise it is read as a text file with a row of the matrix in each line, with the values separated by commas or spaces. Empty lines and lines starting by `#` are skipped.
+
+Example configuration:
+```ignore
+TrafficMatrix{
+	/// Note this is a string literal.
+	filename: "/path/to/matrix.csv",
+	legend_name: "measured matrix",
+}
+```
+ **/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct TrafficMatrix
+{
+    ///The file from which the matrix was read, for the messages.
+    filename: String,
+    ///`cumulative_weights[source][destination]` is the sum of the weights of the row `source` up to `destination`, included.
+    cumulative_weights: Vec<Vec<f64>>,
+}
+
+impl Pattern for TrafficMatrix
+{
+    fn initialize(&mut self, source_size:usize, target_size:usize, _topology:&dyn Topology, _rng: &mut StdRng)
+    {
+        if self.cumulative_weights.len()!=source_size
+        {
+            panic!("The traffic matrix {} has {} rows but the pattern has {} sources.",self.filename,self.cumulative_weights.len(),source_size);
+        }
+        if let Some(row) = self.cumulative_weights.iter().find(|row|row.len()!=target_size)
+        {
+            panic!("The traffic matrix {} has a row with {} columns but the pattern has {} destinations.",self.filename,row.len(),target_size);
+        }
+    }
+    fn get_destination(&self, origin:usize, _topology:&dyn Topology, rng: &mut StdRng)->usize
+    {
+        let row = &self.cumulative_weights[origin];
+        let total = row.last().copied().unwrap_or(0.0);
+        if total<=0.0
+        {
+            panic!("The source {} has no weights in the traffic matrix {}.",origin,self.filename);
+        }
+        let r = rng.gen::<f64>()*total;
+        //The first destination whose cumulative weight exceeds r. Destinations with null weight are never selected.
+        row.partition_point(|&w|w<=r).min(row.len()-1)
+    }
+}
+
+impl TrafficMatrix
+{
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<TrafficMatrix,Error>
+    {
+        let mut filename=None;
+        match_object!(arg.cv,"TrafficMatrix",value,
+			"filename" => filename = Some(value.as_str()?.to_string()),
+		);
+        let filename=filename.ok_or_else(||arg.cv.ill("There were no filename"))?;
+        let rows = if filename.ends_with(".npy") { read_npy_matrix(arg.cv,&filename)? } else { read_text_matrix(arg.cv,&filename)? };
+        if let Some(row) = rows.iter().find(|row|row.len()!=rows[0].len())
+        {
+            return Err(arg.cv.ill(&format!("The traffic matrix {} has rows of {} and {} columns.",filename,rows[0].len(),row.len())));
+        }
+        let mut cumulative_weights = Vec::with_capacity(rows.len());
+        for (index,row) in rows.into_iter().enumerate()
+        {
+            let mut total = 0f64;
+            let mut cumulative = Vec::with_capacity(row.len());
+            for w in row
+            {
+                if w.is_nan() || w<0.0
+                {
+                    return Err(arg.cv.ill(&format!("The traffic matrix {} has the invalid weight {} in its row {}.",filename,w,index)));
+                }
+                total+=w;
+                cumulative.push(total);
+            }
+            cumulative_weights.push(cumulative);
         }
+        Ok(TrafficMatrix{
+            filename,
+            cumulative_weights,
+        })
+    }
+}
+
+///Reads a matrix from a text file with a row in each line and the values separated by commas or whitespace.
+fn read_text_matrix(cv:&ConfigurationValue, filename:&str) -> Result<Vec<Vec<f64>>,Error>
+{
+    let file=File::open(filename).map_err(|e|error!(could_not_open_file,PathBuf::from(filename),e).with_message("could not open traffic matrix file.".to_string()))?;
+    let reader = BufReader::new(&file);
+    let mut rows = Vec::new();
+    for rline in reader.lines()
+    {
+        let line=rline.map_err(|e|error!(could_not_open_file,PathBuf::from(filename),e).with_message("could not read the traffic matrix file.".to_string()))?;
+        let line=line.trim();
+        if line.is_empty() || line.starts_with('#')
+        {
+            continue;
+        }
+        let row = line.split(|c:char|c==',' || c.is_whitespace()).filter(|word|!word.is_empty())
+            .map(|word|word.parse::<f64>().map_err(|_|cv.ill(&format!("bad value {} in the traffic matrix {}",word,filename)))).collect::<Result<_,_>>()?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+///Reads a two-dimensional array from a file in the NumPy `.npy` format.
+///Only little-endian numbers in C order are supported.
+fn read_npy_matrix(cv:&ConfigurationValue, filename:&str) -> Result<Vec<Vec<f64>>,Error>
+{
+    let bytes = std::fs::read(filename).map_err(|e|error!(could_not_open_file,PathBuf::from(filename),e).with_message("could not open traffic matrix file.".to_string()))?;
+    let bad_header = |what:&str| cv.ill(&format!("The npy file {} has {}.",filename,what));
+    if bytes.len()<12 || &bytes[0..6]!=b"\x93NUMPY"
+    {
+        return Err(bad_header("not the npy magic string"));
+    }
+    //Version 1 has a header length of 2 bytes; versions 2 and 3 of 4 bytes.
+    let (header_len,header_start) = match bytes[6]
+    {
+        1 => (u16::from_le_bytes([bytes[8],bytes[9]]) as usize,10),
+        2 | 3 => (u32::from_le_bytes([bytes[8],bytes[9],bytes[10],bytes[11]]) as usize,12),
+        version => return Err(bad_header(&format!("the unsupported version {}",version))),
+    };
+    let header = bytes.get(header_start..header_start+header_len).and_then(|header|std::str::from_utf8(header).ok()).ok_or_else(||bad_header("a bad header"))?;
+    //The header is a Python dictionary such as `{'descr': '<f8', 'fortran_order': False, 'shape': (4, 4), }`.
+    let value_of = |key:&str| -> Result<&str,Error> {
+        let start = header.find(&format!("'{}':",key)).ok_or_else(||bad_header(&format!("no {} in its header",key)))? + key.len()+3;
+        Ok(header[start..].trim_start())
+    };
+    let descr = value_of("descr")?;
+    let descr = descr.get(1..).and_then(|rest|rest.find('\'').map(|end|&rest[..end])).ok_or_else(||bad_header("a bad descr"))?;
+    if value_of("fortran_order")?.starts_with("True")
+    {
+        return Err(bad_header("Fortran order, which is not supported"));
+    }
+    let shape = value_of("shape")?;
+    let shape : Vec<usize> = shape.find(')').and_then(|end|shape.get(1..end)).ok_or_else(||bad_header("a bad shape"))?.split(',')
+        .map(|dim|dim.trim()).filter(|dim|!dim.is_empty()).map(|dim|dim.parse().map_err(|_|bad_header("a bad shape"))).collect::<Result<_,_>>()?;
+    if shape.len()!=2
+    {
+        return Err(bad_header(&format!("shape {:?}, but a traffic matrix must have two dimensions",shape)));
+    }
+    let (size,read) : (usize,fn(&[u8])->f64) = match descr
+    {
+        "<f8" => (8,|b|f64::from_le_bytes(b.try_into().unwrap())),
+        "<f4" => (4,|b|f32::from_le_bytes(b.try_into().unwrap()) as f64),
+        "<i8" => (8,|b|i64::from_le_bytes(b.try_into().unwrap()) as f64),
+        "<i4" => (4,|b|i32::from_le_bytes(b.try_into().unwrap()) as f64),
+        "<u8" => (8,|b|u64::from_le_bytes(b.try_into().unwrap()) as f64),
+        "<u4" => (4,|b|u32::from_le_bytes(b.try_into().unwrap()) as f64),
+        _ => return Err(bad_header(&format!("the unsupported type {}",descr))),
+    };
+    let data = &bytes[header_start+header_len..];
+    if data.len()<shape[0]*shape[1]*size
+    {
+        return Err(bad_header(&format!("less data than its shape {:?}",shape)));
     }
+    Ok(data.chunks_exact(size).take(shape[0]*shape[1]).map(read).collect::<Vec<f64>>()
+        .chunks(shape[1].max(1)).map(|row|row.to_vec()).collect())
 }
 
 
@@ -170,26 +341,26 @@ impl Pattern for ComponentsPattern
 
 impl ComponentsPattern
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> ComponentsPattern
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<ComponentsPattern,Error>
     {
         let mut component_classes=None;
         //let mut block_pattern=None;
         let mut global_pattern=None;
-        match_object_panic!(arg.cv,"Components",value,
-			"global_pattern" => global_pattern=Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
+        match_object!(arg.cv,"Components",value,
+			"global_pattern" => global_pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
 			"component_classes" => component_classes = Some(value.as_array()
-				.expect("bad value for component_classes").iter()
+				?.iter()
 				.map(|v|v.as_f64().expect("bad value in component_classes") as usize).collect()),
 		);
-        let component_classes=component_classes.expect("There were no component_classes");
+        let component_classes=component_classes.ok_or_else(||arg.cv.ill("There were no component_classes"))?;
         //let block_pattern=block_pattern.expect("There were no block_pattern");
-        let global_pattern=global_pattern.expect("There were no global_pattern");
-        ComponentsPattern{
+        let global_pattern=global_pattern.ok_or_else(||arg.cv.ill("There were no global_pattern"))?;
+        Ok(ComponentsPattern{
             component_classes,
             //block_pattern,
             global_pattern,
             components:vec![],//filled at initialize
-        }
+        })
     }
 }
 
@@ -226,18 +397,18 @@ impl Pattern for InmediateSequencePattern
 
 impl InmediateSequencePattern
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> InmediateSequencePattern
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<InmediateSequencePattern,Error>
     {
         let mut sequence=None;
-        match_object_panic!(arg.cv,"InmediateSequencePattern",value,
-			"sequence" => sequence=Some(value.as_array().expect("bad value for patterns").iter()
+        match_object!(arg.cv,"InmediateSequencePattern",value,
+			"sequence" => sequence=Some(value.as_array()?.iter()
 				.map(|v|v.as_usize().expect("List should be of usizes")).collect()),
 		);
         let sequence = sequence.unwrap();
-        InmediateSequencePattern {
+        Ok(InmediateSequencePattern {
             sequence,
             sequences_input: RefCell::new(vec![VecDeque::new()]),
-        }
+        })
     }
 }
 
@@ -287,17 +458,17 @@ impl Pattern for ElementComposition
 
 impl ElementComposition
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> ElementComposition
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<ElementComposition,Error>
     {
         let mut pattern = None;
-        match_object_panic!(arg.cv,"ElementComposition",value,
-			"pattern" => pattern = Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
+        match_object!(arg.cv,"ElementComposition",value,
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
 		);
-        let pattern = pattern.expect("There were no pattern in configuration of ElementComposition.");
-        ElementComposition{
+        let pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern in configuration of ElementComposition."))?;
+        Ok(ElementComposition{
             pattern,
             origin_state: RefCell::new(vec![]),
-        }
+        })
     }
 }
 /**
@@ -363,11 +534,11 @@ impl Pattern for RecursiveDistanceHalving
 
 impl RecursiveDistanceHalving
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> RecursiveDistanceHalving
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<RecursiveDistanceHalving,Error>
     {
         let mut neighbours_order: Option<Vec<usize>> = None; //Array of vectors which represent the order of the neighbours
-        match_object_panic!(arg.cv,"RecursiveDistanceHalving",value,
-			"neighbours_order" => neighbours_order = Some(value.as_array().expect("bad value for neighbours_order").iter()
+        match_object!(arg.cv,"RecursiveDistanceHalving",value,
+			"neighbours_order" => neighbours_order = Some(value.as_array()?.iter()
 				.map(|n|n.as_usize().unwrap()).collect() ),
 		);
 
@@ -395,11 +566,11 @@ impl RecursiveDistanceHalving
             None
         };
 
-        RecursiveDistanceHalving{
+        Ok(RecursiveDistanceHalving{
             origin_state: RefCell::new(vec![]),
             cartesian_data: CartesianData::new(&vec![0;0]),
             neighbours_order: binary_order,
-        }
+        })
     }
 }
 
@@ -488,18 +659,18 @@ impl Pattern for BinomialTree
 
 impl BinomialTree
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> BinomialTree
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<BinomialTree,Error>
     {
         let mut upwards = None;
-        match_object_panic!(arg.cv,"BinomialTree",value,
-			"upwards" => upwards = Some(value.as_bool().expect("bad value for upwards for pattern BinomialTree")),
+        match_object!(arg.cv,"BinomialTree",value,
+			"upwards" => upwards = Some(value.as_bool()?),
 		);
-        let upwards = upwards.expect("There were no upwards in configuration of BinomialTree.");
-        BinomialTree{
+        let upwards = upwards.ok_or_else(||arg.cv.ill("There were no upwards in configuration of BinomialTree."))?;
+        Ok(BinomialTree{
             upwards,
             cartesian_data: CartesianData::new(&vec![2;2]),
             state: RefCell::new(vec![]),
-        }
+        })
     }
 }
 
@@ -563,20 +734,20 @@ impl Pattern for DebugPattern{
 }
 
 impl DebugPattern{
-    pub(crate) fn new(arg:PatternBuilderArgument) -> DebugPattern{
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<DebugPattern,Error>{
         let mut pattern = None;
         let mut check_permutation = false;
-        match_object_panic!(arg.cv,"Debug",value,
-			"pattern" => pattern = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"check_permutation" => check_permutation = value.as_bool().expect("bad value for check_permutation"),
+        match_object!(arg.cv,"Debug",value,
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"check_permutation" => check_permutation = value.as_bool()?,
 		);
-        let pattern = pattern.expect("Missing pattern in configuration of Debug.");
-        DebugPattern{
+        let pattern = pattern.ok_or_else(||arg.cv.ill("Missing pattern in configuration of Debug."))?;
+        Ok(DebugPattern{
             pattern,
             check_permutation,
             source_size:0,
             target_size:0,
-        }
+        })
     }
 }
 
@@ -585,20 +756,20 @@ impl DebugPattern{
 pub struct EncapsulatedPattern {}
 
 impl EncapsulatedPattern {
-    pub(crate) fn new(pattern: String, arg:PatternBuilderArgument) -> Box<dyn Pattern> {
+    pub(crate) fn new(pattern: String, arg:PatternBuilderArgument) -> Result<Box<dyn Pattern>,Error> {
         let pattern_cv = match pattern.as_str(){
             "Stencil" =>{
                 let mut task_space = None;
-                match_object_panic!(arg.cv,"Stencil",value,
-					"task_space" => task_space = Some(value.as_array().expect("bad value for task_space").iter()
-						.map(|v|v.as_usize().expect("bad value in task_space")).collect()),
+                match_object!(arg.cv,"Stencil",value,
+					"task_space" => task_space = Some(value.as_array()?.iter()
+						.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
 				);
-                let task_space = task_space.expect("There were no task_space in configuration of Stencil.");
+                let task_space = task_space.ok_or_else(||arg.cv.ill("There were no task_space in configuration of Stencil."))?;
                 Some(get_stencil_pattern(task_space))
             },
             _ => panic!("Pattern {} not found.",pattern),
         };
-        new_pattern(PatternBuilderArgument{cv:&pattern_cv.unwrap(),..arg})
+        try_new_pattern(PatternBuilderArgument{cv:&pattern_cv.unwrap(),..arg})
     }
 }
 
@@ -751,30 +922,30 @@ impl Pattern for MiDebugPattern {
 }
 
 impl MiDebugPattern {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> MiDebugPattern {
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<MiDebugPattern,Error> {
         let mut pattern = None;
         let mut check_permutation = false;
         let mut check_injective = false;
         let mut source_size = None;
         let mut target_size = None;
-        match_object_panic!(arg.cv,"Debug",value,
-			"patterns" => pattern = Some(value.as_array().expect("bad value for pattern").iter()
-				.map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()),
-			"check_permutation" => check_permutation = value.as_bool().expect("bad value for check_permutation"),
-			"source_size" => source_size = Some(value.as_array().expect("bad value for source_size").iter()
-				.map(|v|v.as_usize().expect("bad value in source_size")).collect()),
-			"target_size" => target_size = Some(value.as_usize().expect("bad value for target_size")),
-			"check_injective" => check_injective = value.as_bool().expect("bad value for check_injective"),
+        match_object!(arg.cv,"Debug",value,
+			"patterns" => pattern = Some(value.as_array()?.iter()
+				.map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?),
+			"check_permutation" => check_permutation = value.as_bool()?,
+			"source_size" => source_size = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"target_size" => target_size = Some(value.as_usize()?),
+			"check_injective" => check_injective = value.as_bool()?,
 		);
-        let pattern = pattern.expect("Missing pattern in configuration of Debug.");
-        let source_size = source_size.expect("Missing source_size in configuration of Debug.");
-        let target_size = target_size.expect("Missing target_size in configuration of Debug.");
-        MiDebugPattern {
+        let pattern = pattern.ok_or_else(||arg.cv.ill("Missing pattern in configuration of Debug."))?;
+        let source_size = source_size.ok_or_else(||arg.cv.ill("Missing source_size in configuration of Debug."))?;
+        let target_size = target_size.ok_or_else(||arg.cv.ill("Missing target_size in configuration of Debug."))?;
+        Ok(MiDebugPattern {
             pattern,
             check_permutation,
             check_injective,
             source_size,
             target_size,
-        }
+        })
     }
 }
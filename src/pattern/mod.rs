@@ -18,15 +18,16 @@ use crate::config_parser::ConfigurationValue;
 use crate::topology::{Topology};
 use crate::quantify::Quantifiable;
 use crate::{Plugs};
-use crate::pattern::extra::{BinomialTree, ComponentsPattern, DebugPattern, ElementComposition, EncapsulatedPattern, FileMap, InmediateSequencePattern, MiDebugPattern, RecursiveDistanceHalving};
+use crate::error::Error;
+use crate::pattern::extra::{BinomialTree, ComponentsPattern, DebugPattern, ElementComposition, EncapsulatedPattern, FileMap, InmediateSequencePattern, MiDebugPattern, RecursiveDistanceHalving, TrafficMatrix};
 use crate::pattern::operations::{CandidatesSelection, Composition, DestinationSets, IndependentRegions, Inverse, Pow, ProductPattern, RoundRobin, SubApp, Sum, Switch};
 use crate::pattern::probabilistic::{Circulant, GloballyShufflingDestinations, GroupShufflingDestinations, Hotspots, RandomMix, RestrictedMiddleUniform, UniformDistance, UniformPattern};
-use crate::pattern::transformations::{CartesianCut, CartesianEmbedding, CartesianFactor, CartesianTiling, CartesianTransform, FixedRandom, Identity, LinearTransform, RandomInvolution, RandomPermutation, RemappedNodes};
+use crate::pattern::transformations::{CartesianCut, CartesianEmbedding, CartesianFactor, CartesianTiling, CartesianTransform, FixedRandom, Identity, LinearTransform, RandomInvolution, RandomPermutation, RemappedNodes, ToServerLevel, ToSwitchLevel};
 
 /// Some things most uses of the pattern module will use.
 pub mod prelude
 {
-    pub use super::{Pattern,new_pattern,PatternBuilderArgument};
+    pub use super::{Pattern,new_pattern,try_new_pattern,PatternBuilderArgument};
 }
 
 ///A `Pattern` describes how a set of entities decides destinations into another set of entities.
@@ -106,19 +107,23 @@ GroupShufflingDestinations{
 ### UniformDistance
 
 In [UniformDistance] each message gets its destination sampled uniformly at random among the servers attached to neighbour routers.
-It may build a pattern either of servers or switches, controlled through the `switch_level` configuration flag.
 This pattern autoscales if requested a size multiple of the network size.
+It is defined over the servers; the switch-level pattern is built with [ToSwitchLevel].
 
 Example configuration:
 ```ignore
 UniformDistance{
 	///The distance at which the destination must be from the source.
 	distance: 1,
-	/// Optionally build the pattern at the switches. This should be irrelevant at direct network with the same number of servers per switch.
-	//switch_level: true,
 	legend_name: "uniform among neighbours",
 }
 ```
+A pattern among neighbour switches.
+```ignore
+ToSwitchLevel{
+	pattern: UniformDistance{distance:1},
+}
+```
 
 ### RestrictedMiddleUniform
 [RestrictedMiddleUniform] is a pattern in which the destinations are randomly sampled from the destinations for which there are some middle router satisfying some criteria. Note this is only a pattern, the actual packet route does not have to go through such middle router.
@@ -174,6 +179,15 @@ FileMap{
 }
 ```
 
+### TrafficMatrix
+With [TrafficMatrix] a weighted matrix is read from a CSV or `.npy` file, and each source selects its destinations with probability proportional to the weights in its row.
+```ignore
+TrafficMatrix{
+	filename: "/path/to/matrix.csv",
+	legend_name: "measured matrix",
+}
+```
+
 ### CartesianTransform
 With [CartesianTransform] the nodes are seen as in a n-dimensional orthohedro. Then it applies several transformations. When mapping directly servers it may be useful to use as `sides[0]` the number of servers per router.
 ```ignore
@@ -305,71 +319,93 @@ RemappedNodes{
 }
 ```
 
+### ToSwitchLevel and ToServerLevel
+
+The [ToSwitchLevel] and [ToServerLevel] adapters convert between patterns over the servers and patterns over the switches.
+[ToSwitchLevel] applies a server-level pattern from the first server of each switch and returns the switch of the destination server.
+[ToServerLevel] applies a switch-level pattern to the switch of each server and returns the server in the same position of the destination switch.
+```ignore
+ToServerLevel{
+	pattern: ToSwitchLevel{pattern:RandomPermutation},
+	/// Optional, by default the number of servers divided by the number of switches.
+	concentration: 4,
+}
+```
+
 ### CartesianCut
 
 With [CartesianCut] you see the nodes as block with an embedded block. Then you define a pattern inside the small block and another outside. See [CartesianCut] for details and examples.
  */
 pub fn new_pattern(arg:PatternBuilderArgument) -> Box<dyn Pattern>
+{
+    try_new_pattern(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///Like [new_pattern] but returning an error when the pattern cannot be built from its configuration.
+pub fn try_new_pattern(arg:PatternBuilderArgument) -> Result<Box<dyn Pattern>,Error>
 {
     if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
     {
         if let Some(builder) = arg.plugs.patterns.get(cv_name)
         {
-            return builder(arg);
+            return Ok(builder(arg));
         }
-        match cv_name.as_ref()
+        Ok(match cv_name.as_ref()
         {
-            "Identity" => Box::new(Identity::new(arg)),
-            "Uniform" => Box::new(UniformPattern::new(arg)),
-            "RandomPermutation" => Box::new(RandomPermutation::new(arg)),
-            "RandomInvolution" => Box::new(RandomInvolution::new(arg)),
-            "FileMap" => Box::new(FileMap::new(arg)),
-            "EmbeddedMap" => Box::new(FileMap::embedded(arg)),
-            "Product" => Box::new(ProductPattern::new(arg)),
-            "Components" => Box::new(ComponentsPattern::new(arg)),
-            "CartesianTransform" => Box::new(CartesianTransform::new(arg)),
-            "LinearTransform" => Box::new(LinearTransform::new(arg)),
-            "CartesianTiling" => Box::new(CartesianTiling::new(arg)),
-            "Composition" => Box::new(Composition::new(arg)),
-            "Pow" => Box::new(Pow::new(arg)),
-            "CartesianFactor" => Box::new(CartesianFactor::new(arg)),
-            "Hotspots" => Box::new(Hotspots::new(arg)),
-            "RandomMix" => Box::new(RandomMix::new(arg)),
+            "Identity" => Box::new(Identity::new(arg)?),
+            "Uniform" => Box::new(UniformPattern::new(arg)?),
+            "RandomPermutation" => Box::new(RandomPermutation::new(arg)?),
+            "RandomInvolution" => Box::new(RandomInvolution::new(arg)?),
+            "FileMap" => Box::new(FileMap::new(arg)?),
+            "EmbeddedMap" => Box::new(FileMap::embedded(arg)?),
+            "TrafficMatrix" => Box::new(TrafficMatrix::new(arg)?),
+            "Product" => Box::new(ProductPattern::new(arg)?),
+            "Components" => Box::new(ComponentsPattern::new(arg)?),
+            "CartesianTransform" => Box::new(CartesianTransform::new(arg)?),
+            "LinearTransform" => Box::new(LinearTransform::new(arg)?),
+            "CartesianTiling" => Box::new(CartesianTiling::new(arg)?),
+            "Composition" => Box::new(Composition::new(arg)?),
+            "Pow" => Box::new(Pow::new(arg)?),
+            "CartesianFactor" => Box::new(CartesianFactor::new(arg)?),
+            "Hotspots" => Box::new(Hotspots::new(arg)?),
+            "RandomMix" => Box::new(RandomMix::new(arg)?),
             "ConstantShuffle" =>
             {
                 println!("WARNING: the name ConstantShuffle is deprecated, use GloballyShufflingDestinations");
-                Box::new(GloballyShufflingDestinations::new(arg))
+                Box::new(GloballyShufflingDestinations::new(arg)?)
             }
-            "GloballyShufflingDestinations" => Box::new(GloballyShufflingDestinations::new(arg)),
-            "GroupShufflingDestinations" => Box::new(GroupShufflingDestinations::new(arg)),
-            "UniformDistance" => Box::new(UniformDistance::new(arg)),
-            "FixedRandom" => Box::new(FixedRandom::new(arg)),
-            "IndependentRegions" => Box::new(IndependentRegions::new(arg)),
-            "RestrictedMiddleUniform" => Box::new(RestrictedMiddleUniform::new(arg)),
-            "Circulant" => Box::new(Circulant::new(arg)),
-            "CartesianEmbedding" => Box::new(CartesianEmbedding::new(arg)),
-            "CartesianCut" => Box::new(CartesianCut::new(arg)),
-            "RemappedNodes" => Box::new(RemappedNodes::new(arg)),
-            "Switch" => Box::new(Switch::new(arg)),
-            "Debug" => Box::new(DebugPattern::new(arg)),
-            "MiDebugPattern" => Box::new(MiDebugPattern::new(arg)),
-            "DestinationSets" => Box::new(DestinationSets::new(arg)),
-            "ElementComposition" => Box::new(ElementComposition::new(arg)),
-            "CandidatesSelection" => Box::new(CandidatesSelection::new(arg)),
-            "Sum" => Box::new(Sum::new(arg)),
-            "RoundRobin" => Box::new(RoundRobin::new(arg)),
-            "Inverse" => Box::new(Inverse::new(arg)),
-            "SubApp" => Box::new(SubApp::new(arg)),
-            "RecursiveDistanceHalving" => Box::new(RecursiveDistanceHalving::new(arg)),
-            "BinomialTree" => Box::new(BinomialTree::new(arg)),
-            "InmediateSequencePattern" => Box::new(InmediateSequencePattern::new(arg)),
-            "Stencil" => EncapsulatedPattern::new(cv_name.clone(), arg),
-            _ => panic!("Unknown pattern {}",cv_name),
-        }
+            "GloballyShufflingDestinations" => Box::new(GloballyShufflingDestinations::new(arg)?),
+            "GroupShufflingDestinations" => Box::new(GroupShufflingDestinations::new(arg)?),
+            "UniformDistance" => Box::new(UniformDistance::new(arg)?),
+            "FixedRandom" => Box::new(FixedRandom::new(arg)?),
+            "IndependentRegions" => Box::new(IndependentRegions::new(arg)?),
+            "RestrictedMiddleUniform" => Box::new(RestrictedMiddleUniform::new(arg)?),
+            "Circulant" => Box::new(Circulant::new(arg)?),
+            "CartesianEmbedding" => Box::new(CartesianEmbedding::new(arg)?),
+            "CartesianCut" => Box::new(CartesianCut::new(arg)?),
+            "RemappedNodes" => Box::new(RemappedNodes::new(arg)?),
+            "ToSwitchLevel" => Box::new(ToSwitchLevel::new(arg)?),
+            "ToServerLevel" => Box::new(ToServerLevel::new(arg)?),
+            "Switch" => Box::new(Switch::new(arg)?),
+            "Debug" => Box::new(DebugPattern::new(arg)?),
+            "MiDebugPattern" => Box::new(MiDebugPattern::new(arg)?),
+            "DestinationSets" => Box::new(DestinationSets::new(arg)?),
+            "ElementComposition" => Box::new(ElementComposition::new(arg)?),
+            "CandidatesSelection" => Box::new(CandidatesSelection::new(arg)?),
+            "Sum" => Box::new(Sum::new(arg)?),
+            "RoundRobin" => Box::new(RoundRobin::new(arg)?),
+            "Inverse" => Box::new(Inverse::new(arg)?),
+            "SubApp" => Box::new(SubApp::new(arg)?),
+            "RecursiveDistanceHalving" => Box::new(RecursiveDistanceHalving::new(arg)?),
+            "BinomialTree" => Box::new(BinomialTree::new(arg)?),
+            "InmediateSequencePattern" => Box::new(InmediateSequencePattern::new(arg)?),
+            "Stencil" => EncapsulatedPattern::new(cv_name.clone(), arg)?,
+            _ => return Err(arg.cv.ill(&format!("Unknown pattern {}",cv_name))),
+        })
     }
     else
     {
-        panic!("Trying to create a Pattern from a non-Object");
+        Err(arg.cv.ill("Trying to create a Pattern from a non-Object"))
     }
 }
 
@@ -412,7 +448,7 @@ mod tests {
                     let cv_allow_self = if allow_self { ConfigurationValue::True } else { ConfigurationValue::False };
                     let cv = ConfigurationValue::Object("Uniform".to_string(),vec![("allow_self".to_string(),cv_allow_self)]);
                     let arg = PatternBuilderArgument{ cv:&cv, plugs:&plugs };
-                    let mut uniform = UniformPattern::new(arg);
+                    let mut uniform = UniformPattern::new(arg).unwrap();
                     uniform.initialize(origin_size,destination_size,&*dummy_topology,&mut rng);
                     let sample_size = (origin_size+destination_size)*10;
                     let origin=5;
@@ -453,7 +489,7 @@ mod tests {
             for _ in 0..sample_size
             {
                 let arg = PatternBuilderArgument{ cv:&cv, plugs:&plugs };
-                let mut with_self = FixedRandom::new(arg);
+                let mut with_self = FixedRandom::new(arg).unwrap();
                 with_self.initialize(size,size,&*dummy_topology,&mut rng);
                 let mut dests = vec![0;size];
                 for origin in 0..size
@@ -478,10 +514,122 @@ mod tests {
         {
             let arg = PatternBuilderArgument{ cv:&cv, plugs:&plugs };
             let size = 2usize.pow(logsize);
-            let mut without_self = FixedRandom::new(arg);
+            let mut without_self = FixedRandom::new(arg).unwrap();
             without_self.initialize(size,size,&*dummy_topology,&mut rng);
             let count = (0..size).filter( |&origin| origin==without_self.get_destination(origin,&*dummy_topology,&mut rng) ).count();
             assert_eq!(count, 0, "Got {} selfs at size {}.", count, size);
         }
     }
+    #[test]
+    fn traffic_matrix()
+    {
+        let plugs = Plugs::default();
+        let mut rng=StdRng::seed_from_u64(10u64);
+        use crate::topology::{new_topology,TopologyBuilderArgument};
+        let topo_cv = ConfigurationValue::Object("Hamming".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![])), ("servers_per_router".to_string(),ConfigurationValue::Number(1.0))]);
+        let dummy_topology = new_topology(TopologyBuilderArgument{cv:&topo_cv,plugs:&plugs,rng:&mut rng});
+        // The same matrix as text and as a npy array of f8.
+        let weights = [[0.0,3.0,1.0],[2.0,0.0,2.0],[0.0,0.0,5.0]];
+        let csv_path = std::env::temp_dir().join(format!("caminos_traffic_matrix_{}.csv",std::process::id()));
+        std::fs::write(&csv_path,"# weights\n0, 3, 1\n2 0 2\n\n0,0,5\n").expect("could not write the matrix");
+        let npy_path = std::env::temp_dir().join(format!("caminos_traffic_matrix_{}.npy",std::process::id()));
+        let mut header = String::from("{'descr': '<f8', 'fortran_order': False, 'shape': (3, 3), }");
+        while (10+header.len()+1)%64!=0 { header.push(' '); }
+        header.push('\n');
+        let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+        npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        npy.extend_from_slice(header.as_bytes());
+        for w in weights.iter().flatten() { npy.extend_from_slice(&f64::to_le_bytes(*w)); }
+        std::fs::write(&npy_path,&npy).expect("could not write the matrix");
+        for path in [&csv_path,&npy_path]
+        {
+            let cv = ConfigurationValue::Object("TrafficMatrix".to_string(),vec![("filename".to_string(),ConfigurationValue::Literal(path.to_str().unwrap().to_string()))]);
+            let mut matrix = new_pattern(PatternBuilderArgument{cv:&cv,plugs:&plugs});
+            matrix.initialize(3,3,&*dummy_topology,&mut rng);
+            let sample_size = 4000;
+            for (origin,row) in weights.iter().enumerate()
+            {
+                let mut counts = [0;3];
+                for _ in 0..sample_size
+                {
+                    counts[matrix.get_destination(origin,&*dummy_topology,&mut rng)]+=1;
+                }
+                let total : f64 = row.iter().sum();
+                for destination in 0..3
+                {
+                    let expected = sample_size as f64*row[destination]/total;
+                    assert!((counts[destination] as f64-expected).abs()<=0.05*sample_size as f64, "from {} to {} got {} expecting {} ({:?})",origin,destination,counts[destination],expected,path);
+                    assert!(row[destination]>0.0 || counts[destination]==0);
+                }
+            }
+            // The matrix must match the size of the pattern.
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||matrix.initialize(4,4,&*dummy_topology,&mut StdRng::seed_from_u64(0))));
+            assert!(result.is_err());
+        }
+        // Bad files are reported as errors instead of aborting.
+        std::fs::write(&csv_path,"0, 3, 1\n2 -1 2\n").expect("could not write the matrix");
+        std::fs::write(&npy_path,b"\x93NUMPY\x01\x00\x04\x00{}").expect("could not write the matrix");
+        let missing = std::env::temp_dir().join(format!("caminos_traffic_matrix_{}_missing.csv",std::process::id()));
+        for path in [&csv_path,&npy_path,&missing]
+        {
+            let cv = ConfigurationValue::Object("TrafficMatrix".to_string(),vec![("filename".to_string(),ConfigurationValue::Literal(path.to_str().unwrap().to_string()))]);
+            assert!(try_new_pattern(PatternBuilderArgument{cv:&cv,plugs:&plugs}).is_err(),"{:?} should be rejected",path);
+        }
+        let _ = std::fs::remove_file(&csv_path);
+        let _ = std::fs::remove_file(&npy_path);
+    }
+    #[test]
+    fn switch_level_adapters()
+    {
+        let plugs = Plugs::default();
+        let mut rng=StdRng::seed_from_u64(10u64);
+        use crate::topology::{new_topology,TopologyBuilderArgument};
+        let topo_cv = ConfigurationValue::Object("Hamming".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0)])), ("servers_per_router".to_string(),ConfigurationValue::Number(2.0))]);
+        let topology = new_topology(TopologyBuilderArgument{cv:&topo_cv,plugs:&plugs,rng:&mut rng});
+        let circulant = ConfigurationValue::Object("Circulant".to_string(),vec![("generators".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)]))]);
+        // A circulant among the servers moves each switch to itself or to the next one.
+        let cv = ConfigurationValue::Object("ToSwitchLevel".to_string(),vec![("pattern".to_string(),circulant.clone())]);
+        let mut to_switch = new_pattern(PatternBuilderArgument{ cv:&cv, plugs:&plugs });
+        to_switch.initialize(4,4,&*topology,&mut rng);
+        let destinations : Vec<usize> = (0..4).map(|origin|to_switch.get_destination(origin,&*topology,&mut rng)).collect();
+        assert_eq!(destinations, vec![0,1,2,3]);
+        // A circulant among the switches keeps the offset of the server inside the switch.
+        let cv = ConfigurationValue::Object("ToServerLevel".to_string(),vec![("pattern".to_string(),circulant.clone())]);
+        let mut to_server = new_pattern(PatternBuilderArgument{ cv:&cv, plugs:&plugs });
+        to_server.initialize(8,8,&*topology,&mut rng);
+        let destinations : Vec<usize> = (0..8).map(|origin|to_server.get_destination(origin,&*topology,&mut rng)).collect();
+        assert_eq!(destinations, vec![2,3,4,5,6,7,0,1]);
+        // With an explicit concentration the pattern can be applied over other sizes.
+        let cv = ConfigurationValue::Object("ToServerLevel".to_string(),vec![("pattern".to_string(),circulant.clone()),("concentration".to_string(),ConfigurationValue::Number(4.0))]);
+        let mut to_server = new_pattern(PatternBuilderArgument{ cv:&cv, plugs:&plugs });
+        to_server.initialize(8,8,&*topology,&mut rng);
+        let destinations : Vec<usize> = (0..8).map(|origin|to_server.get_destination(origin,&*topology,&mut rng)).collect();
+        assert_eq!(destinations, vec![4,5,6,7,0,1,2,3]);
+        // UniformDistance at the switches is built through ToSwitchLevel.
+        let uniform_distance = ConfigurationValue::Object("UniformDistance".to_string(),vec![("distance".to_string(),ConfigurationValue::Number(1.0))]);
+        let cv = ConfigurationValue::Object("ToSwitchLevel".to_string(),vec![("pattern".to_string(),uniform_distance)]);
+        let mut to_switch = new_pattern(PatternBuilderArgument{ cv:&cv, plugs:&plugs });
+        to_switch.initialize(4,4,&*topology,&mut rng);
+        for origin in 0..4
+        {
+            for _ in 0..20
+            {
+                let destination = to_switch.get_destination(origin,&*topology,&mut rng);
+                assert!(destination<4 && destination!=origin, "bad destination {} from switch {}", destination, origin);
+            }
+        }
+        // The removed flag is rejected instead of being silently ignored.
+        let cv = ConfigurationValue::Object("UniformDistance".to_string(),vec![("distance".to_string(),ConfigurationValue::Number(1.0)),("switch_level".to_string(),ConfigurationValue::True)]);
+        assert!(try_new_pattern(PatternBuilderArgument{ cv:&cv, plugs:&plugs }).is_err());
+        // Without an explicit concentration, switches with different amounts of servers are rejected.
+        let concentration_cv = ConfigurationValue::Object("Concentration".to_string(),vec![
+            ("topology".to_string(),ConfigurationValue::Object("Hamming".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0)])), ("servers_per_router".to_string(),ConfigurationValue::Number(0.0))])),
+            ("servers".to_string(),ConfigurationValue::Array([3.0,1.0,2.0,2.0].iter().map(|&x|ConfigurationValue::Number(x)).collect())),
+        ]);
+        let uneven = new_topology(TopologyBuilderArgument{cv:&concentration_cv,plugs:&plugs,rng:&mut rng});
+        let cv = ConfigurationValue::Object("ToServerLevel".to_string(),vec![("pattern".to_string(),circulant)]);
+        let mut to_server = new_pattern(PatternBuilderArgument{ cv:&cv, plugs:&plugs });
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||to_server.initialize(8,8,&*uneven,&mut StdRng::seed_from_u64(0))));
+        assert!(result.is_err());
+    }
 }
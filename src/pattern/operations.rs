@@ -2,10 +2,11 @@ use std::cell::{RefCell};
 use ::rand::{Rng,rngs::StdRng};
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::topology::{Topology};
-use crate::{match_object_panic};
+use crate::{match_object,error,source_location};
 use rand::{RngCore, SeedableRng};
-use crate::pattern::{new_pattern, Pattern, PatternBuilderArgument};
+use crate::pattern::{try_new_pattern, Pattern, PatternBuilderArgument};
 
 
 ///A pattern given by blocks. The elements are divided by blocks of size `block_size`. The `global_pattern` is used to describe the communication among different blocks and the `block_pattern` to describe the communication inside a block.
@@ -44,24 +45,24 @@ impl Pattern for ProductPattern
 
 impl ProductPattern
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> ProductPattern
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<ProductPattern,Error>
     {
         let mut block_size=None;
         let mut block_pattern=None;
         let mut global_pattern=None;
-        match_object_panic!(arg.cv,"Product",value,
-			"block_pattern" => block_pattern=Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"global_pattern" => global_pattern=Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"block_size" => block_size=Some(value.as_f64().expect("bad value for block_size") as usize),
+        match_object!(arg.cv,"Product",value,
+			"block_pattern" => block_pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"global_pattern" => global_pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"block_size" => block_size=Some(value.as_f64()? as usize),
 		);
-        let block_size=block_size.expect("There were no block_size");
-        let block_pattern=block_pattern.expect("There were no block_pattern");
-        let global_pattern=global_pattern.expect("There were no global_pattern");
-        ProductPattern{
+        let block_size=block_size.ok_or_else(||arg.cv.ill("There were no block_size"))?;
+        let block_pattern=block_pattern.ok_or_else(||arg.cv.ill("There were no block_pattern"))?;
+        let global_pattern=global_pattern.ok_or_else(||arg.cv.ill("There were no global_pattern"))?;
+        Ok(ProductPattern{
             block_size,
             block_pattern,
             global_pattern,
-        }
+        })
     }
 }
 
@@ -103,22 +104,22 @@ impl Pattern for Composition
 
 impl Composition
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Composition
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Composition,Error>
     {
         let mut patterns=None;
         let mut middle_sizes=None;
-        match_object_panic!(arg.cv,"Composition",value,
-			"patterns" => patterns=Some(value.as_array().expect("bad value for patterns").iter()
-				.map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()),
-			"middle_sizes" => middle_sizes = Some(value.as_array().expect("bad value for middle_sizes").iter()
-				.map(|v|v.as_usize().expect("bad value for middle_sizes")).collect()),
+        match_object!(arg.cv,"Composition",value,
+			"patterns" => patterns=Some(value.as_array()?.iter()
+				.map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?),
+			"middle_sizes" => middle_sizes = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
 		);
-        let patterns=patterns.expect("There were no patterns");
+        let patterns=patterns.ok_or_else(||arg.cv.ill("There were no patterns"))?;
         let middle_sizes = middle_sizes.unwrap_or_else(||vec![]);
-        Composition{
+        Ok(Composition{
             patterns,
             middle_sizes,
-        }
+        })
     }
 }
 
@@ -182,23 +183,23 @@ impl Pattern for Sum
 
 impl Sum
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Sum
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Sum,Error>
     {
         let mut patterns=None;
         let mut middle_sizes=None;
-        match_object_panic!(arg.cv,"Sum",value,
-			"patterns" => patterns=Some(value.as_array().expect("bad value for patterns").iter()
-				.map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()),
-			"middle_sizes" => middle_sizes = Some(value.as_array().expect("bad value for middle_sizes").iter()
-				.map(|v|v.as_usize().expect("bad value for middle_sizes")).collect()),
+        match_object!(arg.cv,"Sum",value,
+			"patterns" => patterns=Some(value.as_array()?.iter()
+				.map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?),
+			"middle_sizes" => middle_sizes = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
 		);
-        let patterns=patterns.expect("There were no patterns");
+        let patterns=patterns.ok_or_else(||arg.cv.ill("There were no patterns"))?;
         let middle_sizes = middle_sizes.unwrap_or_else(||vec![]);
-        Sum{
+        Ok(Sum{
             patterns,
             middle_sizes,
             target_size: None,
-        }
+        })
     }
 }
 
@@ -231,20 +232,20 @@ impl Pattern for Pow
 
 impl Pow
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Pow
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Pow,Error>
     {
         let mut pattern=None;
         let mut exponent=None;
-        match_object_panic!(arg.cv,"Pow",value,
-			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"exponent" => exponent=Some(value.as_f64().expect("bad value for exponent") as usize),
+        match_object!(arg.cv,"Pow",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"exponent" => exponent=Some(value.as_f64()? as usize),
 		);
-        let pattern=pattern.expect("There were no pattern");
-        let exponent=exponent.expect("There were no exponent");
-        Pow{
+        let pattern=pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
+        let exponent=exponent.ok_or_else(||arg.cv.ill("There were no exponent"))?;
+        Ok(Pow{
             pattern,
             exponent,
-        }
+        })
     }
 }
 
@@ -291,18 +292,18 @@ impl Pattern for RoundRobin
 
 impl RoundRobin
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> RoundRobin
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<RoundRobin,Error>
     {
         let mut patterns=None;
-        match_object_panic!(arg.cv,"RoundRobin",value,
-			"patterns" => patterns=Some(value.as_array().expect("bad value for patterns").iter()
-				.map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()),
+        match_object!(arg.cv,"RoundRobin",value,
+			"patterns" => patterns=Some(value.as_array()?.iter()
+				.map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?),
 		);
-        let patterns=patterns.expect("There were no patterns");
-        RoundRobin{
+        let patterns=patterns.ok_or_else(||arg.cv.ill("There were no patterns"))?;
+        Ok(RoundRobin{
             patterns,
             index: RefCell::new(Vec::new()),
-        }
+        })
     }
 }
 
@@ -363,17 +364,17 @@ impl Pattern for DestinationSets
 
 impl DestinationSets
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> DestinationSets
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<DestinationSets,Error>
     {
         let mut patterns=None;
         let mut weights: Option<Vec<usize>>=None;
-        match_object_panic!(arg.cv,"DestinationSets",value,
-			"patterns" => patterns=Some(value.as_array().expect("bad value for patterns").iter()
-				.map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()),
-			"weights" => weights=Some(value.as_array().expect("bad value for weights").iter()
+        match_object!(arg.cv,"DestinationSets",value,
+			"patterns" => patterns=Some(value.as_array()?.iter()
+				.map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?),
+			"weights" => weights=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in weights") as usize).collect()),
 		);
-        let patterns:Vec<Box<dyn Pattern>>=patterns.expect("There were no patterns");
+        let patterns:Vec<Box<dyn Pattern>>=patterns.ok_or_else(||arg.cv.ill("There were no patterns"))?;
         let weights = if let Some(ref weights)=weights
         {
             assert_eq!(patterns.len(),weights.len(),"The number of patterns must match the number of weights");
@@ -383,11 +384,11 @@ impl DestinationSets
         };
         let size = patterns.len();
 
-        DestinationSets{
+        Ok(DestinationSets{
             patterns,
             weights,
             destination_set:vec![vec![];size],//to be filled in initialization
-        }
+        })
     }
 }
 
@@ -448,20 +449,20 @@ impl Pattern for Inverse
 
 impl Inverse
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Inverse
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Inverse,Error>
     {
         let mut pattern = None;
         let mut default_destination = None;
-        match_object_panic!(arg.cv,"Inverse",value,
-			"pattern" => pattern = Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"default_destination" => default_destination = Some(value.as_usize().expect("bad value for default_destination")),
+        match_object!(arg.cv,"Inverse",value,
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"default_destination" => default_destination = Some(value.as_usize()?),
 		);
-        let pattern = pattern.expect("There were no pattern in configuration of Inverse.");
-        Inverse{
+        let pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern in configuration of Inverse."))?;
+        Ok(Inverse{
             pattern,
             inverse_values: vec![],
             default_destination,
-        }
+        })
     }
 }
 
@@ -537,31 +538,31 @@ impl Pattern for SubApp
 
 impl SubApp
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> SubApp
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<SubApp,Error>
     {
         let mut subtasks = None;
         let mut selection_pattern = None;
         let mut subapp_pattern = None;
         let mut others_pattern = None;
-        match_object_panic!(arg.cv,"SubApp",value,
-			"subtasks" => subtasks = Some(value.as_usize().expect("bad value for total_subsize")),
-			"selection_pattern" => selection_pattern = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})), //map of the application over the machine
-			"subapp_pattern" => subapp_pattern = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})), //traffic of the application
-			"others_pattern" => others_pattern = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})), //traffic of the machine
+        match_object!(arg.cv,"SubApp",value,
+			"subtasks" => subtasks = Some(value.as_usize()?),
+			"selection_pattern" => selection_pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?), //map of the application over the machine
+			"subapp_pattern" => subapp_pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?), //traffic of the application
+			"others_pattern" => others_pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?), //traffic of the machine
 		);
 
-        let subtasks = subtasks.expect("There were no tasks in configuration of SubApp.");
-        let subapp_pattern = subapp_pattern.expect("There were no subapp_pattern in configuration of SubApp.");
-        let selection_pattern = selection_pattern.expect("There were no selection_pattern in configuration of SubApp.");
-        let others_pattern = others_pattern.expect("There were no others_pattern in configuration of SubApp.");
+        let subtasks = subtasks.ok_or_else(||arg.cv.ill("There were no tasks in configuration of SubApp."))?;
+        let subapp_pattern = subapp_pattern.ok_or_else(||arg.cv.ill("There were no subapp_pattern in configuration of SubApp."))?;
+        let selection_pattern = selection_pattern.ok_or_else(||arg.cv.ill("There were no selection_pattern in configuration of SubApp."))?;
+        let others_pattern = others_pattern.ok_or_else(||arg.cv.ill("There were no others_pattern in configuration of SubApp."))?;
 
-        SubApp{
+        Ok(SubApp{
             subtasks,
             subapp_pattern,
             selection_pattern,
             others_pattern,
             selected_vec: vec![],
-        }
+        })
 
     }
 }
@@ -616,21 +617,21 @@ impl Pattern for CandidatesSelection
 
 impl CandidatesSelection
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> CandidatesSelection
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<CandidatesSelection,Error>
     {
         let mut pattern = None;
         let mut pattern_destination_size = None;
-        match_object_panic!(arg.cv,"CandidatesSelection",value,
-			"pattern" => pattern = Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"pattern_destination_size" => pattern_destination_size = Some(value.as_usize().expect("bad value for pattern_destination_size")),
+        match_object!(arg.cv,"CandidatesSelection",value,
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"pattern_destination_size" => pattern_destination_size = Some(value.as_usize()?),
 		);
-        let pattern = pattern.expect("There were no pattern in configuration of CandidatesSelection.");
-        let pattern_destination_size = pattern_destination_size.expect("There were no pattern_destination_size in configuration of CandidatesSelection.");
-        CandidatesSelection{
+        let pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern in configuration of CandidatesSelection."))?;
+        let pattern_destination_size = pattern_destination_size.ok_or_else(||arg.cv.ill("There were no pattern_destination_size in configuration of CandidatesSelection."))?;
+        Ok(CandidatesSelection{
             selected: None,
             pattern,
             pattern_destination_size,
-        }
+        })
     }
 }
 
@@ -718,32 +719,32 @@ impl Pattern for IndependentRegions
 
 impl IndependentRegions
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> IndependentRegions
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<IndependentRegions,Error>
     {
         let mut patterns : Option<Vec<_>> = None;
         let mut sizes = None;
         let mut relative_sizes = None;
-        match_object_panic!(arg.cv,"IndependentRegions",value,
-			"patterns" => patterns = Some(value.as_array().expect("bad value for patterns").iter()
-				.map(|v|new_pattern(PatternBuilderArgument{cv:v,..arg})).collect()),
+        match_object!(arg.cv,"IndependentRegions",value,
+			"patterns" => patterns = Some(value.as_array()?.iter()
+				.map(|v|try_new_pattern(PatternBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
 			"sizes" => sizes = Some(value.as_array()
-				.expect("bad value for sizes").iter()
+				?.iter()
 				.map(|v|v.as_f64().expect("bad value in sizes") as usize).collect()),
 			"relative_sizes" => relative_sizes = Some(value.as_array()
-				.expect("bad value for relative_sizes").iter()
-				.map(|v|v.as_f64().expect("bad value in relative_sizes")).collect()),
+				?.iter()
+				.map(|v|v.as_f64()).collect::<Result<_,_>>()?),
 		);
-        let patterns = patterns.expect("There was no patterns.");
+        let patterns = patterns.ok_or_else(||arg.cv.ill("There was no patterns."))?;
         assert!( matches!(sizes,None) || matches!(relative_sizes,None), "Cannot set both sizes and relative_sizes." );
         assert!( !matches!(sizes,None) || !matches!(relative_sizes,None), "Must set one of sizes or relative_sizes." );
         let sizes = sizes.unwrap_or_else(||Vec::new());
         let relative_sizes = relative_sizes.unwrap_or_else(||Vec::new());
         assert_eq!(patterns.len(), sizes.len().max(relative_sizes.len()), "Different number of entries in IndependentRegions.");
-        IndependentRegions{
+        Ok(IndependentRegions{
             patterns,
             sizes,
             relative_sizes,
-        }
+        })
     }
 }
 
@@ -865,37 +866,37 @@ impl Pattern for Switch {
 }
 
 impl Switch {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Switch
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Switch,Error>
     {
         let mut indexing = None;
         let mut patterns= None;//:Option<Vec<Box<dyn Pattern>>> = None;
         let mut expand: Option<Vec<usize>> = None;
         let mut seed = None;
 
-        match_object_panic!(arg.cv,"Switch",value,
-			"indexing" => indexing = Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"patterns" => patterns=Some( value.as_array().expect("bad value for patterns") ),
-			"expand" => expand = Some(value.as_array().expect("bad value for expand").iter()
-				.map(|v|v.as_usize().expect("bad value in expand")).collect()),
-			"seed" => seed = Some(value.as_f64().expect("bad value for seed")),
+        match_object!(arg.cv,"Switch",value,
+			"indexing" => indexing = Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"patterns" => patterns=Some( value.as_array()? ),
+			"expand" => expand = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"seed" => seed = Some(value.as_f64()?),
 		);
-        let indexing = indexing.expect("Missing indexing in Switch.");
-        let patterns = patterns.expect("Missing patterns in Switch.");
+        let indexing = indexing.ok_or_else(||arg.cv.ill("Missing indexing in Switch."))?;
+        let patterns = patterns.ok_or_else(||arg.cv.ill("Missing patterns in Switch."))?;
         let patterns = if let Some(expand) = expand {
             let mut new_patterns = vec![];
             for (index, pattern) in patterns.into_iter().enumerate() {
                 for _ in 0..expand[index] {
-                    new_patterns.push(new_pattern(PatternBuilderArgument{cv:pattern,..arg}));
+                    new_patterns.push(try_new_pattern(PatternBuilderArgument{cv:pattern,..arg})?);
                 }
             }
             new_patterns
         } else {
-            patterns.iter().map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()
+            patterns.iter().map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?
         };
-        Switch{
+        Ok(Switch{
             indexing,
             patterns,
             seed,
-        }
+        })
     }
 }
@@ -2,9 +2,10 @@ use std::cell::{RefCell};
 use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::topology::{Topology, Location};
-use crate::{match_object_panic};
-use crate::pattern::{new_pattern, Pattern, PatternBuilderArgument};
+use crate::{match_object,error,source_location};
+use crate::pattern::{try_new_pattern, Pattern, PatternBuilderArgument};
 
 
 ///Each destination request will be uniform random among all the range `0..size` minus the `origin`.
@@ -42,16 +43,16 @@ impl Pattern for UniformPattern
 
 impl UniformPattern
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> UniformPattern
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<UniformPattern,Error>
     {
         let mut allow_self = false;
-        match_object_panic!(arg.cv,"Uniform",value,
-			"allow_self" => allow_self=value.as_bool().expect("bad value for allow_self"),
+        match_object!(arg.cv,"Uniform",value,
+			"allow_self" => allow_self=value.as_bool()?,
 		);
-        UniformPattern{
+        Ok(UniformPattern{
             size:0,//to be initialized later
             allow_self,
-        }
+        })
     }
     pub fn uniform_pattern(allow_target_source: bool) -> UniformPattern
     {
@@ -97,22 +98,22 @@ impl Pattern for Hotspots
 
 impl Hotspots
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Hotspots
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Hotspots,Error>
     {
         let mut destinations=None;
         let mut extra_random_destinations=None;
-        match_object_panic!(arg.cv,"Hotspots",value,
-			"destinations" => destinations=Some(value.as_array().expect("bad value for destinations").iter()
+        match_object!(arg.cv,"Hotspots",value,
+			"destinations" => destinations=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in destinations") as usize).collect()),
 			"extra_random_destinations" => extra_random_destinations=Some(
 				value.as_f64().unwrap_or_else(|_|panic!("bad value for extra_random_destinations ({:?})",value)) as usize),
 		);
         let destinations=destinations.unwrap_or_default();
         let extra_random_destinations=extra_random_destinations.unwrap_or(0);
-        Hotspots{
+        Ok(Hotspots{
             destinations,
             extra_random_destinations,
-        }
+        })
     }
 }
 
@@ -162,23 +163,23 @@ impl Pattern for RandomMix
 
 impl RandomMix
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> RandomMix
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<RandomMix,Error>
     {
         let mut patterns=None;
         let mut weights=None;
-        match_object_panic!(arg.cv,"RandomMix",value,
-			"patterns" => patterns=Some(value.as_array().expect("bad value for patterns").iter()
-				.map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()),
-			"weights" => weights=Some(value.as_array().expect("bad value for weights").iter()
+        match_object!(arg.cv,"RandomMix",value,
+			"patterns" => patterns=Some(value.as_array()?.iter()
+				.map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?),
+			"weights" => weights=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in weights") as usize).collect()),
 		);
-        let patterns=patterns.expect("There were no patterns");
-        let weights=weights.expect("There were no weights");
-        RandomMix{
+        let patterns=patterns.ok_or_else(||arg.cv.ill("There were no patterns"))?;
+        let weights=weights.ok_or_else(||arg.cv.ill("There were no weights"))?;
+        Ok(RandomMix{
             patterns,
             weights,
             total_weight:0,//to be computed later
-        }
+        })
     }
 }
 
@@ -224,13 +225,13 @@ impl Pattern for GloballyShufflingDestinations
 
 impl GloballyShufflingDestinations
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> GloballyShufflingDestinations
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<GloballyShufflingDestinations,Error>
     {
-        match_object_panic!(arg.cv,"GloballyShufflingDestinations",_value);
-        GloballyShufflingDestinations{
+        match_object!(arg.cv,"GloballyShufflingDestinations",_value);
+        Ok(GloballyShufflingDestinations{
             size:0,//to be filled in initialization
             pending:RefCell::new(Vec::new()),//to be filled in initialization
-        }
+        })
     }
 }
 
@@ -279,14 +280,14 @@ impl Pattern for GroupShufflingDestinations
 
 impl GroupShufflingDestinations
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> GroupShufflingDestinations
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<GroupShufflingDestinations,Error>
     {
         let mut group_size = None;
         if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
         {
             if cv_name!="GroupShufflingDestinations"
             {
-                panic!("A GroupShufflingDestinations must be created from a `GroupShufflingDestinations` object not `{}`",cv_name);
+                return Err(arg.cv.ill(&format!("A GroupShufflingDestinations must be created from a `GroupShufflingDestinations` object not `{}`",cv_name)));
             }
             for &(ref name,ref value) in cv_pairs
             {
@@ -296,29 +297,30 @@ impl GroupShufflingDestinations
                     "group_size" => match value
                     {
                         &ConfigurationValue::Number(f) => group_size=Some(f as usize),
-                        _ => panic!("bad value for group_size"),
+                        _ => return Err(value.ill("bad value for group_size")),
                     }
                     "legend_name" => (),
-                    _ => panic!("Nothing to do with field {} in GroupShufflingDestinations",name),
+                    _ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in GroupShufflingDestinations",name))),
                 }
             }
         }
         else
         {
-            panic!("Trying to create a GroupShufflingDestinations from a non-Object");
+            return Err(arg.cv.ill("Trying to create a GroupShufflingDestinations from a non-Object"));
         }
-        let group_size = group_size.expect("There was no group_size");
-        GroupShufflingDestinations{
+        let group_size = group_size.ok_or_else(||arg.cv.ill("There was no group_size"))?;
+        Ok(GroupShufflingDestinations{
             group_size,
             size:0,//to be filled in initialization
             pending:vec![],//to be filled in initialization
-        }
+        })
     }
 }
 
 /**
 Each message gets its destination sampled uniformly at random among the servers attached to neighbour routers.
-It may build a pattern either of servers or switches, controlled through the `switch_level` configuration flag.
+It is a pattern over the servers. To build it over the switches use the [ToSwitchLevel](crate::pattern::transformations::ToSwitchLevel) adapter,
+which replaces the former `switch_level` flag.
 This pattern autoscales if requested a size multiple of the network size.
 
 Example configuration:
@@ -326,8 +328,6 @@ Example configuration:
 UniformDistance{
 	///The distance at which the destination must be from the source.
 	distance: 1,
-	/// Optionally build the pattern at the switches. This should be irrelevant at direct network with the same number of servers per switch.
-	//switch_level: true,
 	legend_name: "uniform among neighbours",
 }
 ```
@@ -338,11 +338,9 @@ pub struct UniformDistance
 {
     ///Distance to which destinations must chosen.
     distance: usize,
-    ///Whether the pattern is defined at the switches, or otherwise, at the servers.
-    switch_level: bool,
-    ///sources/destinations mapped to each router/server (depending on `switch_level`).
+    ///sources/destinations mapped to each server.
     concentration: usize,
-    ///`pool[i]` contains the routers at `distance` from the router `i`.
+    ///`pool[i]` contains the servers attached to routers at `distance` from the router of the server `i`.
     pool: Vec<Vec<usize>>,
 }
 
@@ -350,35 +348,22 @@ impl Pattern for UniformDistance
 {
     fn initialize(&mut self, source_size:usize, target_size:usize, topology:&dyn Topology, _rng: &mut StdRng)
     {
-        let n= if self.switch_level { topology.num_routers() } else { topology.num_servers() };
-        //assert!(n==source_size && n==target_size,"The UniformDistance pattern needs source_size({})==target_size({})==num_routers({})",source_size,target_size,n);
+        let n = topology.num_servers();
         assert_eq!(source_size, target_size, "The UniformDistance pattern needs source_size({})==target_size({})", source_size, target_size);
-        assert_eq!(source_size % n, 0, "The UniformDistance pattern needs the number of {}({}) to be a divisor of source_size({})", if self.switch_level { "routers" } else { "servers" }, n, source_size);
+        assert_eq!(source_size % n, 0, "The UniformDistance pattern needs the number of servers({}) to be a divisor of source_size({})", n, source_size);
         self.concentration = source_size/n;
+        let server_router = |server:usize| match topology.server_neighbour(server).0 {
+            Location::RouterPort{
+                router_index,
+                router_port:_,
+            } => router_index,
+            _ => panic!("unconnected server"),
+        };
         self.pool.reserve(n);
         for i in 0..n
         {
-            let source = if self.switch_level { i } else {
-                match topology.server_neighbour(i).0 {
-                    Location::RouterPort{
-                        router_index,
-                        router_port:_,
-                    } => router_index,
-                    _ => panic!("unconnected server"),
-                }
-            };
-            let mut found: Vec<usize> = (0..n).filter(|&j|{
-                let destination = if self.switch_level { j } else {
-                    match topology.server_neighbour(j).0 {
-                        Location::RouterPort{
-                            router_index,
-                            router_port:_,
-                        } => router_index,
-                        _ => panic!("unconnected server"),
-                    }
-                };
-                topology.distance(source,destination)==self.distance
-            }).collect();
+            let source = server_router(i);
+            let mut found: Vec<usize> = (0..n).filter(|&j|topology.distance(source,server_router(j))==self.distance).collect();
             found.shrink_to_fit();
             self.pool.push(found);
         }
@@ -393,21 +378,22 @@ impl Pattern for UniformDistance
 
 impl UniformDistance
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> UniformDistance
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<UniformDistance,Error>
     {
         let mut distance =  None;
-        let mut switch_level =  false;
-        match_object_panic!(arg.cv,"UniformDistance",value,
-			"distance" => distance=Some(value.as_f64().expect("bad value for distance") as usize),
-			"switch_level" => switch_level = value.as_bool().expect("bad value for switch_level"),
+        match_object!(arg.cv,"UniformDistance",value,
+			"distance" => distance=Some(value.as_f64()? as usize),
+			"switch_level" => if value.as_bool()?
+			{
+				return Err(value.ill("The switch_level flag of UniformDistance has been removed. Wrap the UniformDistance in a ToSwitchLevel pattern instead."));
+			},
 		);
-        let distance = distance.expect("There were no distance");
-        UniformDistance{
+        let distance = distance.ok_or_else(||arg.cv.ill("There were no distance"))?;
+        Ok(UniformDistance{
             distance,
-            switch_level,
             concentration:0,//to be filled on initialization
             pool: vec![],//to be filled on initialization
-        }
+        })
     }
 }
 
@@ -452,21 +438,21 @@ impl Pattern for Circulant
 
 impl Circulant
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Circulant
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Circulant,Error>
     {
         let mut generators = vec![];
-        match_object_panic!(arg.cv,"Circulant",value,
-			"generators" => generators=value.as_array().expect("bad value for generators").iter()
-				.map(|v|v.as_i32().expect("bad value in generators")).collect(),
+        match_object!(arg.cv,"Circulant",value,
+			"generators" => generators=value.as_array()?.iter()
+				.map(|v|v.as_i32()).collect::<Result<_,_>>()?,
 		);
         if generators.is_empty()
         {
-            panic!("cannot build a Circulant pattern with empty set of generators.");
+            return Err(arg.cv.ill("cannot build a Circulant pattern with empty set of generators."));
         }
-        Circulant{
+        Ok(Circulant{
             generators,
             size:0,
-        }
+        })
     }
 }
 
@@ -613,7 +599,7 @@ impl Pattern for RestrictedMiddleUniform
 
 impl RestrictedMiddleUniform
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> RestrictedMiddleUniform
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<RestrictedMiddleUniform,Error>
     {
         let mut minimum_index = None;
         let mut maximum_index = None;
@@ -622,25 +608,25 @@ impl RestrictedMiddleUniform
         let mut distances_source_to_destination = None;
         let mut else_pattern = None;
         let mut switch_level =  false;
-        match_object_panic!(arg.cv,"RestrictedMiddleUniform",value,
-			"minimum_index" => minimum_index=Some(value.as_f64().expect("bad value for minimum_index") as usize),
-			"maximum_index" => maximum_index=Some(value.as_f64().expect("bad value for maximum_index") as usize),
+        match_object!(arg.cv,"RestrictedMiddleUniform",value,
+			"minimum_index" => minimum_index=Some(value.as_f64()? as usize),
+			"maximum_index" => maximum_index=Some(value.as_f64()? as usize),
 			"distances_to_source" => distances_to_source=Some(
-				value.as_array().expect("bad value for distances_to_source").iter().map(
-				|x|x.as_f64().expect("bad value for distances_to_source") as usize
-			).collect()),
+				value.as_array()?.iter().map(
+				|x|Ok(x.as_f64()? as usize)
+			).collect::<Result<_,_>>()?),
 			"distances_to_destination" => distances_to_destination=Some(
-				value.as_array().expect("bad value for distances_to_destination").iter().map(
-				|x|x.as_f64().expect("bad value for distances_to_destination") as usize
-			).collect()),
+				value.as_array()?.iter().map(
+				|x|Ok(x.as_f64()? as usize)
+			).collect::<Result<_,_>>()?),
 			"distances_source_to_destination" => distances_source_to_destination=Some(
-				value.as_array().expect("bad value for distances_source_to_destination").iter().map(
-				|x|x.as_f64().expect("bad value for distances_source_to_destination") as usize
-			).collect()),
-			"else" => else_pattern=Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"switch_level" => switch_level = value.as_bool().expect("bad value for switch_level"),
+				value.as_array()?.iter().map(
+				|x|Ok(x.as_f64()? as usize)
+			).collect::<Result<_,_>>()?),
+			"else" => else_pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"switch_level" => switch_level = value.as_bool()?,
 		);
-        RestrictedMiddleUniform{
+        Ok(RestrictedMiddleUniform{
             minimum_index,
             maximum_index,
             distances_to_source,
@@ -650,6 +636,6 @@ impl RestrictedMiddleUniform
             switch_level,
             concentration:0,//to be filled on initialization
             pool: vec![],//to be filled on initialization
-        }
+        })
     }
 }
\ No newline at end of file
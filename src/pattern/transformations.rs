@@ -4,10 +4,11 @@ use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
 
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::topology::cartesian::CartesianData;//for CartesianTransform
-use crate::topology::{Topology};
-use crate::{match_object_panic};
-use crate::pattern::{new_pattern, Pattern, PatternBuilderArgument};
+use crate::topology::{Location, Topology};
+use crate::{match_object,error,source_location};
+use crate::pattern::{try_new_pattern, Pattern, PatternBuilderArgument};
 
 
 ///Just set `destination = origin`.
@@ -35,11 +36,11 @@ impl Pattern for Identity
 
 impl Identity
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> Identity
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<Identity,Error>
     {
-        match_object_panic!(arg.cv,"Identity",_value);
-        Identity{
-        }
+        match_object!(arg.cv,"Identity",_value);
+        Ok(Identity{
+        })
     }
 }
 
@@ -115,25 +116,25 @@ impl Pattern for LinearTransform
 
 impl LinearTransform
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> LinearTransform
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<LinearTransform,Error>
     {
         let mut source_size:Option<Vec<_>>=None;
         let mut matrix:Option<Vec<Vec<i32>>>=None;
         let mut target_size:Option<Vec<_>>=None;
         let mut check_admisible = false;
 
-        match_object_panic!(arg.cv,"LinearTransform",value,
-			"source_size" => source_size = Some(value.as_array().expect("bad value for sides").iter()
-				.map(|v|v.as_usize().expect("bad value in sides")).collect()),
-			"matrix" => matrix=Some(value.as_array().expect("bad value for matrix").iter()
+        match_object!(arg.cv,"LinearTransform",value,
+			"source_size" => source_size = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"matrix" => matrix=Some(value.as_array()?.iter()
 				.map(|v|v.as_array().expect("bad value in matrix").iter().map(|n|n.as_i32().unwrap()).collect() ).collect() ),
-			"target_size" => target_size = Some(value.as_array().expect("bad value for sides").iter()
-				.map(|v|v.as_usize().expect("bad value in sides")).collect()),
-			"check_admisible" => check_admisible = value.as_bool().expect("bad value for check_admisible"),
+			"target_size" => target_size = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"check_admisible" => check_admisible = value.as_bool()?,
 		);
-        let source_size=source_size.expect("There were no sides");
-        let matrix=matrix.expect("There were no matrix");
-        let target_size=target_size.expect("There were no sides");
+        let source_size=source_size.ok_or_else(||arg.cv.ill("There were no sides"))?;
+        let matrix=matrix.ok_or_else(||arg.cv.ill("There were no matrix"))?;
+        let target_size=target_size.ok_or_else(||arg.cv.ill("There were no sides"))?;
         //let permute=permute.expect("There were no permute");
         //let complement=complement.expect("There were no complement");
         //calculate the derminant of the matrix
@@ -146,11 +147,11 @@ impl LinearTransform
             }
         }
 
-        LinearTransform{
+        Ok(LinearTransform{
             source_size: CartesianData::new(&source_size),
             matrix,
             target_size: CartesianData::new(&target_size),
-        }
+        })
     }
 }
 
@@ -233,16 +234,16 @@ impl Pattern for RandomPermutation
 
 impl RandomPermutation
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> RandomPermutation
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<RandomPermutation,Error>
     {
         let mut rng = None;
-        match_object_panic!(arg.cv,"RandomPermutation",value,
-			"seed" => rng = Some( value.as_rng().expect("bad value for seed") ),
+        match_object!(arg.cv,"RandomPermutation",value,
+			"seed" => rng = Some( value.as_rng()? ),
 		);
-        RandomPermutation{
+        Ok(RandomPermutation{
             permutation: vec![],
             rng,
-        }
+        })
     }
 }
 
@@ -331,12 +332,12 @@ impl Pattern for RandomInvolution
 
 impl RandomInvolution
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> RandomInvolution
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<RandomInvolution,Error>
     {
-        match_object_panic!(arg.cv,"RandomInvolution",_value);
-        RandomInvolution{
+        match_object!(arg.cv,"RandomInvolution",_value);
+        Ok(RandomInvolution{
             permutation: vec![],
-        }
+        })
     }
 }
 
@@ -378,19 +379,19 @@ impl Pattern for FixedRandom
 
 impl FixedRandom
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> FixedRandom
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<FixedRandom,Error>
     {
         let mut allow_self = false;
         let mut rng = None;
-        match_object_panic!(arg.cv,"FixedRandom",value,
-			"seed" => rng = Some( value.as_rng().expect("bad value for seed") ),
-			"allow_self" => allow_self=value.as_bool().expect("bad value for allow_self"),
+        match_object!(arg.cv,"FixedRandom",value,
+			"seed" => rng = Some( value.as_rng()? ),
+			"allow_self" => allow_self=value.as_bool()?,
 		);
-        FixedRandom{
+        Ok(FixedRandom{
             map: vec![],//to be initialized
             allow_self,
             rng,
-        }
+        })
     }
 }
 
@@ -429,23 +430,23 @@ impl Pattern for CartesianFactor
 
 impl CartesianFactor
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> CartesianFactor
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<CartesianFactor,Error>
     {
         let mut sides: Option<Vec<_>>=None;
         let mut factors=None;
-        match_object_panic!(arg.cv,"CartesianFactor",value,
-			"sides" => sides=Some(value.as_array().expect("bad value for sides").iter()
+        match_object!(arg.cv,"CartesianFactor",value,
+			"sides" => sides=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in sides") as usize).collect()),
-			"factors" => factors=Some(value.as_array().expect("bad value for factors").iter()
-				.map(|v|v.as_f64().expect("bad value in factors")).collect()),
+			"factors" => factors=Some(value.as_array()?.iter()
+				.map(|v|v.as_f64()).collect::<Result<_,_>>()?),
 		);
-        let sides=sides.expect("There were no sides");
-        let factors=factors.expect("There were no factors");
-        CartesianFactor{
+        let sides=sides.ok_or_else(||arg.cv.ill("There were no sides"))?;
+        let factors=factors.ok_or_else(||arg.cv.ill("There were no factors"))?;
+        Ok(CartesianFactor{
             cartesian_data: CartesianData::new(&sides),
             factors,
             target_size:0,
-        }
+        })
     }
 }
 
@@ -564,7 +565,7 @@ impl Pattern for CartesianTransform
 
 impl CartesianTransform
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> CartesianTransform
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<CartesianTransform,Error>
     {
         let mut sides:Option<Vec<_>>=None;
         let mut shift=None;
@@ -574,28 +575,28 @@ impl CartesianTransform
         let mut project=None;
         let mut random =None;
         let mut patterns=None;
-        match_object_panic!(arg.cv,"CartesianTransform",value,
-			"sides" => sides = Some(value.as_array().expect("bad value for sides").iter()
-				.map(|v|v.as_usize().expect("bad value in sides")).collect()),
-			"multiplier" => multiplier=Some(value.as_array().expect("bad value for multiplier").iter()
+        match_object!(arg.cv,"CartesianTransform",value,
+			"sides" => sides = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"multiplier" => multiplier=Some(value.as_array()?.iter()
 				.map(|v|v.as_i32().expect("bad value in multiplier") ).collect()),
-			"shift" => shift=Some(value.as_array().expect("bad value for shift").iter()
+			"shift" => shift=Some(value.as_array()?.iter()
 				.map(|v|v.as_usize().expect("bad value in shift") ).collect()),
-			"permute" => permute=Some(value.as_array().expect("bad value for permute").iter()
+			"permute" => permute=Some(value.as_array()?.iter()
 				.map(|v|v.as_usize().expect("bad value in permute") ).collect()),
-			"complement" => complement=Some(value.as_array().expect("bad value for complement").iter()
-				.map(|v|v.as_bool().expect("bad value in complement")).collect()),
-			"project" => project=Some(value.as_array().expect("bad value for project").iter()
-				.map(|v|v.as_bool().expect("bad value in project")).collect()),
-			"random" => random=Some(value.as_array().expect("bad value for random").iter()
-				.map(|v|v.as_bool().expect("bad value in random")).collect()),
-			"patterns" => patterns=Some(value.as_array().expect("bad value for patterns").iter()
-				.map(|pcv|new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect()),
+			"complement" => complement=Some(value.as_array()?.iter()
+				.map(|v|v.as_bool()).collect::<Result<_,_>>()?),
+			"project" => project=Some(value.as_array()?.iter()
+				.map(|v|v.as_bool()).collect::<Result<_,_>>()?),
+			"random" => random=Some(value.as_array()?.iter()
+				.map(|v|v.as_bool()).collect::<Result<_,_>>()?),
+			"patterns" => patterns=Some(value.as_array()?.iter()
+				.map(|pcv|try_new_pattern(PatternBuilderArgument{cv:pcv,..arg})).collect::<Result<_,_>>()?),
 		);
-        let sides=sides.expect("There were no sides");
+        let sides=sides.ok_or_else(||arg.cv.ill("There were no sides"))?;
         //let permute=permute.expect("There were no permute");
         //let complement=complement.expect("There were no complement");
-        CartesianTransform{
+        Ok(CartesianTransform{
             cartesian_data: CartesianData::new(&sides),
             multiplier,
             shift,
@@ -604,7 +605,7 @@ impl CartesianTransform
             project,
             random,
             patterns,
-        }
+        })
     }
 }
 
@@ -656,30 +657,30 @@ impl Pattern for CartesianTiling
 
 impl CartesianTiling
 {
-    pub fn new(arg:PatternBuilderArgument) -> CartesianTiling
+    pub fn new(arg:PatternBuilderArgument) -> Result<CartesianTiling,Error>
     {
         let mut pattern = None;
         let mut sides:Option<Vec<_>>=None;
         let mut repetitions:Option<Vec<_>> = None;
-        match_object_panic!(arg.cv,"CartesianTiling",value,
-			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,..arg})),
-			"sides" => sides = Some(value.as_array().expect("bad value for sides").iter()
+        match_object!(arg.cv,"CartesianTiling",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,..arg})?),
+			"sides" => sides = Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in sides") as usize).collect()),
-			"repetitions" => repetitions = Some(value.as_array().expect("bad value for repetitions").iter()
+			"repetitions" => repetitions = Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in repetitions") as usize).collect()),
 		);
-        let pattern=pattern.expect("There were no pattern");
-        let sides=sides.expect("There were no sides");
-        let repetitions=repetitions.expect("There were no repetitions");
+        let pattern=pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
+        let sides=sides.ok_or_else(||arg.cv.ill("There were no sides"))?;
+        let repetitions=repetitions.ok_or_else(||arg.cv.ill("There were no repetitions"))?;
         let n=sides.len();
         assert_eq!(n, repetitions.len());
         let final_sides : Vec<_> = (0..n).map(|index|sides[index]*repetitions[index]).collect();
-        CartesianTiling{
+        Ok(CartesianTiling{
             pattern,
             base_cartesian_data: CartesianData::new(&sides),
             repetitions,
             final_cartesian_data: CartesianData::new(&final_sides),
-        }
+        })
     }
 }
 
@@ -726,33 +727,33 @@ impl Pattern for CartesianEmbedding
 
 impl CartesianEmbedding
 {
-    pub fn new(arg:PatternBuilderArgument) -> CartesianEmbedding
+    pub fn new(arg:PatternBuilderArgument) -> Result<CartesianEmbedding,Error>
     {
         let mut source_sides:Option<Vec<_>>=None;
         let mut destination_sides:Option<Vec<_>>=None;
-        match_object_panic!(arg.cv,"CartesianEmbedding",value,
-			"source_sides" => source_sides = Some(value.as_array().expect("bad value for source_sides").iter()
-				.map(|v|v.as_usize().expect("bad value in source_sides")).collect()),
-			"destination_sides" => destination_sides = Some(value.as_array().expect("bad value for destination_sides").iter()
-				.map(|v|v.as_usize().expect("bad value in destination_sides")).collect()),
+        match_object!(arg.cv,"CartesianEmbedding",value,
+			"source_sides" => source_sides = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"destination_sides" => destination_sides = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
 		);
-        let source_sides=source_sides.expect("There were no source_sides");
-        let destination_sides=destination_sides.expect("There were no destination_sides");
+        let source_sides=source_sides.ok_or_else(||arg.cv.ill("There were no source_sides"))?;
+        let destination_sides=destination_sides.ok_or_else(||arg.cv.ill("There were no destination_sides"))?;
         if source_sides.len() != destination_sides.len()
         {
-            panic!("Different number of dimensions in CartesianEmbedding.")
+            return Err(arg.cv.ill("Different number of dimensions in CartesianEmbedding."));
         }
         for (index,(ss, ds)) in std::iter::zip( source_sides.iter(), destination_sides.iter() ).enumerate()
         {
             if ss>ds
             {
-                panic!("Source is greater than destination at side {index}. {ss}>{ds}",index=index,ss=ss,ds=ds);
+                return Err(arg.cv.ill(&format!("Source is greater than destination at side {index}. {ss}>{ds}",index=index,ss=ss,ds=ds)));
             }
         }
-        CartesianEmbedding{
+        Ok(CartesianEmbedding{
             source_cartesian_data: CartesianData::new(&source_sides),
             destination_cartesian_data: CartesianData::new(&destination_sides),
-        }
+        })
     }
 }
 
@@ -861,7 +862,7 @@ impl Pattern for CartesianCut
 
 impl CartesianCut
 {
-    pub fn new(arg:PatternBuilderArgument) -> CartesianCut
+    pub fn new(arg:PatternBuilderArgument) -> Result<CartesianCut,Error>
     {
         let mut uncut_sides:Option<Vec<_>>=None;
         let mut cut_sides:Option<Vec<_>>=None;
@@ -869,36 +870,36 @@ impl CartesianCut
         let mut cut_strides:Option<Vec<_>>=None;
         let mut cut_pattern:Option<Box<dyn Pattern>>=None;
         let mut remainder_pattern:Option<Box<dyn Pattern>>=None;
-        match_object_panic!(arg.cv,"CartesianCut",value,
-			"uncut_sides" => uncut_sides = Some(value.as_array().expect("bad value for uncut_sides").iter()
-				.map(|v|v.as_usize().expect("bad value in uncut_sides")).collect()),
-			"cut_sides" => cut_sides = Some(value.as_array().expect("bad value for cut_sides").iter()
-				.map(|v|v.as_usize().expect("bad value in cut_sides")).collect()),
-			"cut_offsets" => cut_offsets = Some(value.as_array().expect("bad value for cut_offsets").iter()
-				.map(|v|v.as_usize().expect("bad value in cut_offsets")).collect()),
-			"cut_strides" => cut_strides = Some(value.as_array().expect("bad value for cut_strides").iter()
-				.map(|v|v.as_usize().expect("bad value in cut_strides")).collect()),
-			"cut_pattern" => cut_pattern = Some(new_pattern(arg.with_cv(value))),
-			"remainder_pattern" => remainder_pattern = Some(new_pattern(arg.with_cv(value))),
+        match_object!(arg.cv,"CartesianCut",value,
+			"uncut_sides" => uncut_sides = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"cut_sides" => cut_sides = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"cut_offsets" => cut_offsets = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"cut_strides" => cut_strides = Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"cut_pattern" => cut_pattern = Some(try_new_pattern(arg.with_cv(value))?),
+			"remainder_pattern" => remainder_pattern = Some(try_new_pattern(arg.with_cv(value))?),
 		);
-        let uncut_sides=uncut_sides.expect("There were no uncut_sides");
-        let cut_sides=cut_sides.expect("There were no cut_sides");
+        let uncut_sides=uncut_sides.ok_or_else(||arg.cv.ill("There were no uncut_sides"))?;
+        let cut_sides=cut_sides.ok_or_else(||arg.cv.ill("There were no cut_sides"))?;
         let n=uncut_sides.len();
         assert_eq!(n,cut_sides.len(),"CartesianCut: dimensions for uncut_sides and cut_sides must match.");
         let cut_offsets = cut_offsets.unwrap_or_else(||vec![0;n]);
         assert_eq!(n,cut_offsets.len(),"CartesianCut: dimensions for cut_offsets do not match.");
         let cut_strides = cut_strides.unwrap_or_else(||vec![1;n]);
         assert_eq!(n,cut_strides.len(),"CartesianCut: dimensions for cut_strides do not match.");
-        let cut_pattern = cut_pattern.expect("There were no cut_pattern");
+        let cut_pattern = cut_pattern.ok_or_else(||arg.cv.ill("There were no cut_pattern"))?;
         let remainder_pattern = remainder_pattern.unwrap_or_else(||Box::new(Identity{}));
-        CartesianCut{
+        Ok(CartesianCut{
             uncut_cartesian_data: CartesianData::new(&uncut_sides),
             cut_cartesian_data: CartesianData::new(&cut_sides),
             cut_offsets,
             cut_strides,
             cut_pattern,
             remainder_pattern,
-        }
+        })
     }
     /**
     From an index in the cut region `(0..self.cut_cartesian_data.size)` get the whole index `0..target_size`.
@@ -1021,21 +1022,170 @@ impl Pattern for RemappedNodes
 
 impl RemappedNodes
 {
-    pub(crate) fn new(arg:PatternBuilderArgument) -> RemappedNodes
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<RemappedNodes,Error>
     {
         let mut pattern = None;
         let mut map = None;
-        match_object_panic!(arg.cv, "RemappedNodes", value,
-			"pattern" => pattern = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"map" => map = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+        match_object!(arg.cv, "RemappedNodes", value,
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"map" => map = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
 		);
-        let pattern = pattern.expect("There were no pattern in configuration of RemappedNodes.");
-        let map = map.expect("There were no map in configuration of RemappedNodes.");
-        RemappedNodes{
+        let pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern in configuration of RemappedNodes."))?;
+        let map = map.ok_or_else(||arg.cv.ill("There were no map in configuration of RemappedNodes."))?;
+        Ok(RemappedNodes{
             from_base_map: vec![],
             into_base_map: vec![],
             pattern,
             map,
+        })
+    }
+}
+/**
+Adapts a pattern over the servers to be used as a pattern over the switches.
+Each origin switch uses the first server attached to it as origin of the inner `pattern`, and the destination is the switch to which the selected server is attached.
+The inner pattern is initialized with the number of servers, and this one requires source and target sizes to be the number of switches.
+Switches without servers cannot be origins.
+
+Example building a switch-level random permutation.
+```ignore
+ToSwitchLevel{
+	/// The server-level pattern.
+	pattern: RandomPermutation,
+}
+```
+ **/
+#[derive(Debug,Quantifiable)]
+pub struct ToSwitchLevel
+{
+    /// The inner pattern, over the servers.
+    pattern: Box<dyn Pattern>,
+    /// `first_server[switch]` is the first server attached to `switch`, if any.
+    first_server: Vec<Option<usize>>,
+    /// `server_switch[server]` is the switch to which `server` is attached.
+    server_switch: Vec<usize>,
+}
+
+impl Pattern for ToSwitchLevel
+{
+    fn initialize(&mut self, source_size:usize, target_size:usize, topology:&dyn Topology, rng: &mut StdRng)
+    {
+        let num_switches = topology.num_routers();
+        assert!(source_size==num_switches && target_size==num_switches, "ToSwitchLevel requires source_size({})==target_size({})==num_routers({})", source_size, target_size, num_switches);
+        let num_servers = topology.num_servers();
+        self.first_server = vec![None;num_switches];
+        self.server_switch = (0..num_servers).map(|server|{
+            match topology.server_neighbour(server).0
+            {
+                Location::RouterPort{router_index,router_port:_} => router_index,
+                _ => panic!("The server {} is not connected to a switch", server),
+            }
+        }).collect();
+        for (server,&switch) in self.server_switch.iter().enumerate().rev()
+        {
+            self.first_server[switch] = Some(server);
         }
+        self.pattern.initialize(num_servers,num_servers,topology,rng);
+    }
+    fn get_destination(&self, origin:usize, topology:&dyn Topology, rng: &mut StdRng)->usize
+    {
+        let server = self.first_server[origin].unwrap_or_else(||panic!("The switch {} has no servers to be used as origin in ToSwitchLevel", origin));
+        let destination = self.pattern.get_destination(server,topology,rng);
+        self.server_switch[destination]
+    }
+}
+
+impl ToSwitchLevel
+{
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<ToSwitchLevel,Error>
+    {
+        let mut pattern = None;
+        match_object!(arg.cv, "ToSwitchLevel", value,
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+		);
+        let pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern in configuration of ToSwitchLevel."))?;
+        Ok(ToSwitchLevel{
+            pattern,
+            first_server: vec![],
+            server_switch: vec![],
+        })
     }
-}
\ No newline at end of file
+}
+
+/**
+Adapts a pattern over the switches to be used as a pattern over the servers, considering `concentration` consecutive servers in each switch.
+The origin `s` uses the switch `s/concentration` as origin of the inner `pattern`, and the destination is the server in the same offset `s%concentration` of the selected switch.
+The inner pattern is initialized with the source and target sizes divided by the concentration, which must divide both of them.
+When `concentration` is not given it is taken as the number of servers divided by the number of switches of the topology,
+and the initialization checks that each switch has that number of servers, attached in consecutive blocks.
+Thus topologies with a different number of servers per switch, such as a [Concentration](crate::topology::operations::ConcentrationTopology) topology, are rejected.
+
+Example building a pattern among the servers of neighbour switches.
+```ignore
+ToServerLevel{
+	/// The switch-level pattern.
+	pattern: ToSwitchLevel{pattern:UniformDistance{distance:1}},
+	/// The servers for each switch.
+	concentration: 4,
+}
+```
+ **/
+#[derive(Debug,Quantifiable)]
+pub struct ToServerLevel
+{
+    /// The inner pattern, over the switches.
+    pattern: Box<dyn Pattern>,
+    /// The number of servers for each switch. To be filled in initialization if not given.
+    concentration: Option<usize>,
+}
+
+impl Pattern for ToServerLevel
+{
+    fn initialize(&mut self, source_size:usize, target_size:usize, topology:&dyn Topology, rng: &mut StdRng)
+    {
+        let concentration = match self.concentration
+        {
+            Some(concentration) => concentration,
+            None =>
+            {
+                let concentration = topology.num_servers()/topology.num_routers();
+                assert!(concentration>0, "ToServerLevel requires a positive concentration");
+                for server in 0..topology.num_servers()
+                {
+                    match topology.server_neighbour(server).0
+                    {
+                        Location::RouterPort{router_index,router_port:_} => assert_eq!(router_index, server/concentration, "ToServerLevel requires the servers to be attached to the switches in consecutive blocks of {}, but the server {} is attached to the switch {}", concentration, server, router_index),
+                        _ => panic!("The server {} is not connected to a switch", server),
+                    }
+                }
+                *self.concentration.insert(concentration)
+            }
+        };
+        assert!(concentration>0, "ToServerLevel requires a positive concentration");
+        assert!(source_size%concentration==0 && target_size%concentration==0, "ToServerLevel requires the concentration({}) to divide source_size({}) and target_size({})", concentration, source_size, target_size);
+        self.pattern.initialize(source_size/concentration,target_size/concentration,topology,rng);
+    }
+    fn get_destination(&self, origin:usize, topology:&dyn Topology, rng: &mut StdRng)->usize
+    {
+        let concentration = self.concentration.expect("ToServerLevel has not been initialized");
+        let destination = self.pattern.get_destination(origin/concentration,topology,rng);
+        destination*concentration + origin%concentration
+    }
+}
+
+impl ToServerLevel
+{
+    pub(crate) fn new(arg:PatternBuilderArgument) -> Result<ToServerLevel,Error>
+    {
+        let mut pattern = None;
+        let mut concentration = None;
+        match_object!(arg.cv, "ToServerLevel", value,
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"concentration" => concentration = Some(value.as_usize()?),
+		);
+        let pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern in configuration of ToServerLevel."))?;
+        Ok(ToServerLevel{
+            pattern,
+            concentration,
+        })
+    }
+}
@@ -4,16 +4,17 @@ A policy defined to what output queue/port to request among the ones returned as
 
 One should include always the policy `EnforceFlowControl` or equivalent at some point. To ensure at most one candidate you may use the `Random` policy.
 
-see [`new_virtual_channel_policy`](fn.new_virtual_channel_policy.html) for documentation on the configuration syntax of predefined policies.
+see [`new_virtual_channel_policy, try_new_virtual_channel_policy`](fn.new_virtual_channel_policy.html) for documentation on the configuration syntax of predefined policies.
 
 */
 
 use crate::config_parser::ConfigurationValue;
 use crate::routing::CandidateEgress;
 use crate::router::Router;
-use crate::topology::{Topology, Location, NeighbourRouterIteratorItem, new_topology, TopologyBuilderArgument};
-use crate::{Plugs,Phit,match_object_panic};
+use crate::topology::{Topology, Location, NeighbourRouterIteratorItem, try_new_topology, TopologyBuilderArgument};
+use crate::{Plugs,Phit,match_object,error,source_location};
 use crate::event::Time;
+use crate::error::{Error,SourceLocation};
 
 use std::fmt::Debug;
 use std::convert::TryInto;
@@ -21,7 +22,7 @@ use std::rc::Rc;
 
 use rand::{Rng,rngs::StdRng,SeedableRng};
 // use ::rand::{Rng,rngs::StdRng};
-use crate::pattern::{new_pattern, Pattern, PatternBuilderArgument};
+use crate::pattern::{try_new_pattern, Pattern, PatternBuilderArgument};
 use crate::topology::prelude::CartesianData;
 
 ///Extra information to be used by the policies of virtual channels.
@@ -75,6 +76,8 @@ pub trait VirtualChannelPolicy : Debug
 	fn need_server_ports(&self)->bool;
 	fn need_port_average_queue_length(&self)->bool;
 	fn need_port_last_transmission(&self)->bool;
+	///Clears all collected statistics. Called by the router when the warmup ends.
+	fn reset_statistics(&mut self,_next_cycle:Time) {}
 }
 
 #[derive(Debug)]
@@ -253,6 +256,21 @@ MapEntryVC
 }
 ```
 
+### MapClass
+
+A meta-policy applying a different policy to the candidates of each traffic class, as given by the `class` of the messages.
+It allows to partition the virtual channels among the classes, isolating the latency-sensitive traffic from the bulk traffic.
+
+```ignore
+MapClass
+{
+	//Class 0 uses the VCs 0 and 1, and class 1 the VC 2.
+	class_to_policy: [ArgumentVC{allowed:[0,1]}, ArgumentVC{allowed:[2]}],
+	//We may apply a policy to the classes over the range. By default Identity.
+	//above_policy: ArgumentVC{allowed:[2]},
+}
+```
+
 ## Hop based
 
 Policies that use the number of hops given by the packet. We have already commented on `Hops` and `WideHops`.
@@ -290,57 +308,89 @@ VOQ{
 }
 ```
 
+## Table driven
+
+### TablePolicy
+
+Relabels the candidates according to a decision table, so that policies produced by external tools can be evaluated without writing a new policy.
+Each row of the table has the key `hops link_class label occupancy_bucket virtual_channel` followed by the new label, separated by whitespace.
+`hops` is the number of hops already performed by the packet, `link_class` is the class of the link of the candidate port, and `label` is the label of the candidate.
+The occupancy is the number of phits in the candidate virtual channel of the next router, as known by the credits, plus the occupied output space
+of the candidate when `use_internal_space` is set. Its bucket is the number of values in `occupancy_buckets` that are not greater than the occupancy.
+Any key field may be `*` to match any value. When several rows match a candidate the one with fewer `*` is used, and between equally specific rows the first one.
+Candidates without a matching row are discarded, unless a `default_label` is given. Lines starting with `#` are ignored.
+Typically followed by `LowestLabel` to select the candidates with best value.
+
+```ignore
+TablePolicy{
+	//A file with lines like `0 * * 0 0 5`, or alternatively the rows written in the configuration as `entries:[[0,"*","*",0,0,5]]`.
+	filename: "/path/to/table",
+	//Bucket 0 for less than 8 phits, 1 for less than 32, and 2 otherwise.
+	occupancy_buckets: [8,32],
+	use_internal_space: false,//optional
+	default_label: 100,//optional
+}
+```
+
 */
 pub fn new_virtual_channel_policy(arg:VCPolicyBuilderArgument) -> Box<dyn VirtualChannelPolicy>
+{
+	try_new_virtual_channel_policy(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///Like [new_virtual_channel_policy] but returning an error instead of panicking on a bad configuration.
+pub fn try_new_virtual_channel_policy(arg:VCPolicyBuilderArgument) -> Result<Box<dyn VirtualChannelPolicy>,Error>
 {
 	if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
 	{
 		if let Some(builder) = arg.plugs.policies.get(cv_name)
 		{
-			return builder(arg);
+			return Ok(builder(arg));
 		}
-		match cv_name.as_ref()
+		Ok(match cv_name.as_ref()
 		{
-			"Identity" => Box::new(Identity::new(arg)),
-			"Random" => Box::new(Random::new(arg)),
-			"Shortest" => Box::new(Shortest::new(arg)),
-			"Hops" => Box::new(Hops::new(arg)),
-			"EnforceFlowControl" => Box::new(EnforceFlowControl::new(arg)),
-			"WideHops" => Box::new(WideHops::new(arg)),
-			"LowestSinghWeight" => Box::new(LowestSinghWeight::new(arg)),
-			"LowestLabel" => Box::new(LowestLabel::new(arg)),
-			"LabelSaturate" => Box::new(LabelSaturate::new(arg)),
-			"LabelTransform" => Box::new(LabelTransform::new(arg)),
-			"OccupancyFunction" => Box::new(OccupancyFunction::new(arg)),
-			"AverageOccupancyFunction" => Box::new(AverageOccupancyFunction::new(arg)),
-			"PortDiscardLabelThreshold" => Box::new(PortDiscardLabelThreshold::new(arg)),
-			"NegateLabel" => Box::new(NegateLabel::new(arg)),
-			"VecLabel" => Box::new(VecLabel::new(arg)),
-			"MapLabel" => Box::new(MapLabel::new(arg)),
-			"ShiftEntryVC" => Box::new(ShiftEntryVC::new(arg)),
-			"MapHop" => Box::new(MapHop::new(arg)),
-			"ArgumentVC" => Box::new(ArgumentVC::new(arg)),
-			"Either" => Box::new(Either::new(arg)),
-			"MapEntryVC" => Box::new(MapEntryVC::new(arg)),
-			"MapTrafficIndex" => Box::new(MapTrafficIndex::new(arg)),
+			"Identity" => Box::new(Identity::new(arg)?),
+			"Random" => Box::new(Random::new(arg)?),
+			"Shortest" => Box::new(Shortest::new(arg)?),
+			"Hops" => Box::new(Hops::new(arg)?),
+			"EnforceFlowControl" => Box::new(EnforceFlowControl::new(arg)?),
+			"WideHops" => Box::new(WideHops::new(arg)?),
+			"LowestSinghWeight" => Box::new(LowestSinghWeight::new(arg)?),
+			"LowestLabel" => Box::new(LowestLabel::new(arg)?),
+			"LabelSaturate" => Box::new(LabelSaturate::new(arg)?),
+			"LabelTransform" => Box::new(LabelTransform::new(arg)?),
+			"OccupancyFunction" => Box::new(OccupancyFunction::new(arg)?),
+			"AverageOccupancyFunction" => Box::new(AverageOccupancyFunction::new(arg)?),
+			"PortDiscardLabelThreshold" => Box::new(PortDiscardLabelThreshold::new(arg)?),
+			"NegateLabel" => Box::new(NegateLabel::new(arg)?),
+			"VecLabel" => Box::new(VecLabel::new(arg)?),
+			"MapLabel" => Box::new(MapLabel::new(arg)?),
+			"ShiftEntryVC" => Box::new(ShiftEntryVC::new(arg)?),
+			"MapHop" => Box::new(MapHop::new(arg)?),
+			"ArgumentVC" => Box::new(ArgumentVC::new(arg)?),
+			"Either" => Box::new(Either::new(arg)?),
+			"MapEntryVC" => Box::new(MapEntryVC::new(arg)?),
+			"MapTrafficIndex" => Box::new(MapTrafficIndex::new(arg)?),
+			"MapClass" => Box::new(MapClass::new(arg)?),
 			// "VCFunction" => Box::new(VCFunction::new(arg)),
-			"MapMessageSize" => Box::new(MapMessageSize::new(arg)),
-			"Chain" => Box::new(Chain::new(arg)),
-			"VOQ" => Box::new(VOQ::new(arg)),
-			"CycleIntoNetwork" => Box::new(CycleIntoNetwork::new(arg)),
-			"NextLinkLabel" => Box::new(NextLinkLabel::new(arg)),
-			"CurrentLinkLabel" => Box::new(CurrentLinkLabel::new(arg)),
-			"ChannelHop" => Box::new(ChannelHop::new(arg)),
-			"ValiantIntermediate" => Box::new(ValiantIntermediate::new(arg)),
-			"ValiantLastRouterPalmTree" => Box::new(ValiantLastRouterPalmTree::new(arg)),
-			"CartesianSpaceLabel" => Box::new(CartesianSpaceLabel::new(arg)),
-			"RRRate" => Box::new(RRRate::new(arg)),
-			_ => panic!("Unknown policy {}",cv_name),
-		}
+			"MapMessageSize" => Box::new(MapMessageSize::new(arg)?),
+			"Chain" => Box::new(Chain::new(arg)?),
+			"VOQ" => Box::new(VOQ::new(arg)?),
+			"CycleIntoNetwork" => Box::new(CycleIntoNetwork::new(arg)?),
+			"NextLinkLabel" => Box::new(NextLinkLabel::new(arg)?),
+			"CurrentLinkLabel" => Box::new(CurrentLinkLabel::new(arg)?),
+			"ChannelHop" => Box::new(ChannelHop::new(arg)?),
+			"ValiantIntermediate" => Box::new(ValiantIntermediate::new(arg)?),
+			"ValiantLastRouterPalmTree" => Box::new(ValiantLastRouterPalmTree::new(arg)?),
+			"CartesianSpaceLabel" => Box::new(CartesianSpaceLabel::new(arg)?),
+			"RRRate" => Box::new(RRRate::new(arg)?),
+			"TablePolicy" => Box::new(TablePolicy::new(arg)?),
+			_ => return Err(arg.cv.ill(&format!("Unknown policy {}",cv_name))),
+		})
 	}
 	else
 	{
-		panic!("Trying to create a policy from a non-Object\narg={:?}",arg);
+		Err(arg.cv.ill("Trying to create a policy from a non-Object"))
 	}
 }
 
@@ -373,9 +423,9 @@ impl VirtualChannelPolicy for Identity
 
 impl Identity
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> Identity
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<Identity,Error>
 	{
-		Identity{}
+		Ok(Identity{})
 	}
 }
 
@@ -410,9 +460,9 @@ impl VirtualChannelPolicy for Random
 
 impl Random
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> Random
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<Random,Error>
 	{
-		Random{}
+		Ok(Random{})
 	}
 }
 
@@ -467,9 +517,9 @@ impl VirtualChannelPolicy for Shortest
 
 impl Shortest
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> Shortest
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<Shortest,Error>
 	{
-		Shortest{}
+		Ok(Shortest{})
 	}
 }
 
@@ -513,9 +563,9 @@ impl VirtualChannelPolicy for Hops
 
 impl Hops
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> Hops
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<Hops,Error>
 	{
-		Hops{}
+		Ok(Hops{})
 	}
 }
 
@@ -550,9 +600,9 @@ impl VirtualChannelPolicy for EnforceFlowControl
 
 impl EnforceFlowControl
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> EnforceFlowControl
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<EnforceFlowControl,Error>
 	{
-		EnforceFlowControl{}
+		Ok(EnforceFlowControl{})
 	}
 }
 
@@ -602,16 +652,16 @@ impl VirtualChannelPolicy for WideHops
 
 impl WideHops
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> WideHops
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<WideHops,Error>
 	{
 		let mut width=None;
-		match_object_panic!(arg.cv,"WideHops",value,
-			"width" => width = Some(value.as_f64().expect("bad value for width") as usize),
+		match_object!(arg.cv,"WideHops",value,
+			"width" => width = Some(value.as_f64()? as usize),
 		);
-		let width=width.expect("There were no width");
-		WideHops{
+		let width=width.ok_or_else(||arg.cv.ill("There were no width"))?;
+		Ok(WideHops{
 			width
-		}
+		})
 	}
 }
 
@@ -768,7 +818,7 @@ impl VirtualChannelPolicy for LowestSinghWeight
 
 impl LowestSinghWeight
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> LowestSinghWeight
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<LowestSinghWeight,Error>
 	{
 		let mut extra_congestion=None;
 		let mut extra_distance=None;
@@ -776,28 +826,28 @@ impl LowestSinghWeight
 		let mut use_internal_space=false;
 		let mut use_neighbour_space=true;
 		let mut use_estimation=true;
-		match_object_panic!(arg.cv,"LowestSinghWeight",value,
-			"extra_congestion" => extra_congestion = Some(value.as_f64().expect("bad value for extra_congestion") as usize),
-			"extra_distance" => extra_distance= Some(value.as_f64().expect("bad value for extra_distance") as usize),
-			"aggregate" => aggregate = value.as_bool().expect("bad value for aggregate"),
+		match_object!(arg.cv,"LowestSinghWeight",value,
+			"extra_congestion" => extra_congestion = Some(value.as_f64()? as usize),
+			"extra_distance" => extra_distance= Some(value.as_f64()? as usize),
+			"aggregate" => aggregate = value.as_bool()?,
 			"aggregate_buffers" => {
 				println!("WARNING: the name `aggregate_buffers` has been deprecated in favour of just `aggregate`");
-				aggregate = value.as_bool().expect("bad value for aggregate_buffers");
+				aggregate = value.as_bool()?;
 			},
-			"use_internal_space" => use_internal_space = value.as_bool().expect("bad value for use_internal_space"),
-			"use_neighbour_space" => use_neighbour_space = value.as_bool().expect("bad value for use_neighbour_space"),
-			"use_estimation" => use_estimation = value.as_bool().expect("bad value for use_estimation"),
+			"use_internal_space" => use_internal_space = value.as_bool()?,
+			"use_neighbour_space" => use_neighbour_space = value.as_bool()?,
+			"use_estimation" => use_estimation = value.as_bool()?,
 		);
 		let extra_congestion=extra_congestion.unwrap_or(0);
 		let extra_distance=extra_distance.unwrap_or(0);
-		LowestSinghWeight{
+		Ok(LowestSinghWeight{
 			extra_congestion,
 			extra_distance,
 			aggregate,
 			use_internal_space,
 			use_neighbour_space,
 			use_estimation,
-		}
+		})
 	}
 }
 
@@ -944,7 +994,7 @@ impl VirtualChannelPolicy for AverageOccupancyFunction
 
 impl AverageOccupancyFunction
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> AverageOccupancyFunction
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<AverageOccupancyFunction,Error>
 	{
 		let mut label_coefficient=None;
 		let mut occupancy_coefficient=None;
@@ -957,28 +1007,28 @@ impl AverageOccupancyFunction
 		let mut exclude_minimal_ports=false;
 		let mut exclude_link_classes=Vec::new();
 		//let mut only_minimal_link_class=false;
-		match_object_panic!(arg.cv,"AverageOccupancyFunction",value,
-			"label_coefficient" => label_coefficient=Some(value.as_f64().expect("bad value for label_coefficient") as i32),
-			"occupancy_coefficient" => occupancy_coefficient=Some(value.as_f64().expect("bad value for occupancy_coefficient") as i32),
-			"product_coefficient" => product_coefficient=Some(value.as_f64().expect("bad value for product_coefficient") as i32),
-			"constant_coefficient" => constant_coefficient=Some(value.as_f64().expect("bad value for constant_coefficient") as i32),
-			"use_neighbour_space" => use_neighbour_space=value.as_bool().expect("bad value for use_neighbour_space"),
-			"use_internal_space" => use_internal_space=value.as_bool().expect("bad value for use_internal_space"),
-			"exclude_minimal_ports" => exclude_minimal_ports=value.as_bool().expect("bad value for exclude_minimal_ports"),
-			"virtual_channels" => virtual_channels=Some(value.as_array().expect("bad value for virtual_channels").iter()
-				.map(|v| v.as_f64().expect("bad value for virtual_channels") as usize ).collect::<Vec<_>>()),
-			"average_virtual_channels" => average_virtual_channels=value.as_bool().expect("bad value for average_virtual_channels"),
-			"exclude_link_classes" => exclude_link_classes=value.as_array().expect("bad value for exclude_link_classes").iter()
-                .map(|v| v.as_f64().expect("bad value for exclude_link_classes") as usize ).collect::<Vec<_>>(),
+		match_object!(arg.cv,"AverageOccupancyFunction",value,
+			"label_coefficient" => label_coefficient=Some(value.as_f64()? as i32),
+			"occupancy_coefficient" => occupancy_coefficient=Some(value.as_f64()? as i32),
+			"product_coefficient" => product_coefficient=Some(value.as_f64()? as i32),
+			"constant_coefficient" => constant_coefficient=Some(value.as_f64()? as i32),
+			"use_neighbour_space" => use_neighbour_space=value.as_bool()?,
+			"use_internal_space" => use_internal_space=value.as_bool()?,
+			"exclude_minimal_ports" => exclude_minimal_ports=value.as_bool()?,
+			"virtual_channels" => virtual_channels=Some(value.as_array()?.iter()
+				.map(|v| Ok(v.as_f64()? as usize) ).collect::<Result<Vec<_>,_>>()?),
+			"average_virtual_channels" => average_virtual_channels=value.as_bool()?,
+			"exclude_link_classes" => exclude_link_classes=value.as_array()?.iter()
+                .map(|v| Ok(v.as_f64()? as usize) ).collect::<Result<Vec<_>,_>>()?,
 			//"only_minimal_link_class" => only_minimal_link_class=value.as_bool().expect("bad value for only_minimal_link_class"),
 		);
-		let label_coefficient=label_coefficient.expect("There were no multiplier");
-		let occupancy_coefficient=occupancy_coefficient.expect("There were no multiplier");
-		let product_coefficient=product_coefficient.expect("There were no multiplier");
-		let constant_coefficient=constant_coefficient.expect("There were no multiplier");
-		let virtual_channels=virtual_channels.expect("There were no virtual channels");
+		let label_coefficient=label_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let occupancy_coefficient=occupancy_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let product_coefficient=product_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let constant_coefficient=constant_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let virtual_channels=virtual_channels.ok_or_else(||arg.cv.ill("There were no virtual channels"))?;
 
-		AverageOccupancyFunction{
+		Ok(AverageOccupancyFunction{
 			label_coefficient,
 			occupancy_coefficient,
 			product_coefficient,
@@ -989,7 +1039,7 @@ impl AverageOccupancyFunction
 			average_virtual_channels,
 			exclude_minimal_ports,
 			exclude_link_classes,
-		}
+		})
 	}
 }
 
@@ -1061,29 +1111,33 @@ impl VirtualChannelPolicy for PortDiscardLabelThreshold
 	{
 		false
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		self.previous_policy.reset_statistics(next_cycle);
+	}
 
 }
 
 impl PortDiscardLabelThreshold
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> PortDiscardLabelThreshold
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<PortDiscardLabelThreshold,Error>
 	{
 		let mut previous_policy=None;
 		let mut threshold=None;
 		let mut below = true;
-		match_object_panic!(arg.cv,"PortDiscardLabelThreshold",value,
-			"previous_policy" => previous_policy = Some(new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})),
-			"threshold" => threshold = Some(value.as_f64().expect("bad value for threshold") as i32),
-			"below" => below = value.as_bool().expect("bad value for below"),
+		match_object!(arg.cv,"PortDiscardLabelThreshold",value,
+			"previous_policy" => previous_policy = Some(try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?),
+			"threshold" => threshold = Some(value.as_f64()? as i32),
+			"below" => below = value.as_bool()?,
 		);
-		let previous_policy=previous_policy.expect("There were no previous_policy");
-		let threshold=threshold.expect("There were no threshold");
+		let previous_policy=previous_policy.ok_or_else(||arg.cv.ill("There were no previous_policy"))?;
+		let threshold=threshold.ok_or_else(||arg.cv.ill("There were no threshold"))?;
 
-		PortDiscardLabelThreshold{
+		Ok(PortDiscardLabelThreshold{
 			previous_policy,
 			threshold,
 			below,
-		}
+		})
 	}
 }
 
@@ -1155,16 +1209,16 @@ impl VirtualChannelPolicy for RRRate
 
 impl RRRate
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> RRRate
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<RRRate,Error>
 	{
 		let mut threshold_local_occupancy=None;
-		match_object_panic!(arg.cv,"RRRate",value,
-			"threshold_local_occupancy" => threshold_local_occupancy = Some(value.as_f64().expect("bad value for threshold_local_occupancy") as usize),
+		match_object!(arg.cv,"RRRate",value,
+			"threshold_local_occupancy" => threshold_local_occupancy = Some(value.as_f64()? as usize),
 		);
-		let threshold_local_occupancy=threshold_local_occupancy.expect("There were no threshold_local_occupancy");
-		RRRate{
+		let threshold_local_occupancy=threshold_local_occupancy.ok_or_else(||arg.cv.ill("There were no threshold_local_occupancy"))?;
+		Ok(RRRate{
 			threshold_local_occupancy,
-		}
+		})
 	}
 }
 
@@ -1257,22 +1311,26 @@ impl VirtualChannelPolicy for Minimal
 	{
 		false
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		self.policy.reset_statistics(next_cycle);
+	}
 
 }
 
 impl Minimal
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> Minimal
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<Minimal,Error>
 	{
 		let mut policy=None;
-		match_object_panic!(arg.cv,"Minimal",value,
-			"policy" => policy = Some(new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})),
+		match_object!(arg.cv,"Minimal",value,
+			"policy" => policy = Some(try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?),
 		);
-		let policy=policy.expect("There were no policies");
+		let policy=policy.ok_or_else(||arg.cv.ill("There were no policies"))?;
 
-		Minimal {
+		Ok(Minimal {
 			policy
-		}
+		})
 	}
 }
 
@@ -1328,9 +1386,9 @@ impl VirtualChannelPolicy for LowestLabel
 
 impl LowestLabel
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> LowestLabel
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<LowestLabel,Error>
 	{
-		LowestLabel{}
+		Ok(LowestLabel{})
 	}
 }
 
@@ -1400,20 +1458,20 @@ impl VirtualChannelPolicy for LabelSaturate
 
 impl LabelSaturate
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> LabelSaturate
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<LabelSaturate,Error>
 	{
 		let mut xvalue=None;
 		let mut bottom=None;
-		match_object_panic!(arg.cv,"LabelSaturate",value,
-			"value" => xvalue=Some(value.as_f64().expect("bad value for value") as i32),
-			"bottom" => bottom=Some(value.as_bool().expect("bad value for bottom")),
+		match_object!(arg.cv,"LabelSaturate",value,
+			"value" => xvalue=Some(value.as_f64()? as i32),
+			"bottom" => bottom=Some(value.as_bool()?),
 		);
-		let value=xvalue.expect("There were no value");
-		let bottom=bottom.expect("There were no bottom");
-		LabelSaturate{
+		let value=xvalue.ok_or_else(||arg.cv.ill("There were no value"))?;
+		let bottom=bottom.ok_or_else(||arg.cv.ill("There were no bottom"))?;
+		Ok(LabelSaturate{
 			value,
 			bottom,
-		}
+		})
 	}
 }
 
@@ -1500,7 +1558,7 @@ impl VirtualChannelPolicy for LabelTransform
 
 impl LabelTransform
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> LabelTransform
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<LabelTransform,Error>
 	{
 		let mut multiplier=None;
 		let mut summand=None;
@@ -1508,24 +1566,24 @@ impl LabelTransform
 		let mut saturate_top=None;
 		let mut minimum=None;
 		let mut maximum=None;
-		match_object_panic!(arg.cv,"LabelTransform",value,
-			"multiplier" => multiplier=Some(value.as_f64().expect("bad value for multiplier") as i32),
-			"summand" => summand=Some(value.as_f64().expect("bad value for summand") as i32),
-			"saturate_bottom" => saturate_bottom=Some(value.as_f64().expect("bad value for saturate_bottom") as i32),
-			"saturate_top" => saturate_top=Some(value.as_f64().expect("bad value for saturate_top") as i32),
-			"minimum" => minimum=Some(value.as_f64().expect("bad value for minimum") as i32),
-			"maximum" => maximum=Some(value.as_f64().expect("bad value for maximum") as i32),
+		match_object!(arg.cv,"LabelTransform",value,
+			"multiplier" => multiplier=Some(value.as_f64()? as i32),
+			"summand" => summand=Some(value.as_f64()? as i32),
+			"saturate_bottom" => saturate_bottom=Some(value.as_f64()? as i32),
+			"saturate_top" => saturate_top=Some(value.as_f64()? as i32),
+			"minimum" => minimum=Some(value.as_f64()? as i32),
+			"maximum" => maximum=Some(value.as_f64()? as i32),
 		);
-		let multiplier=multiplier.expect("There were no multiplier");
-		let summand=summand.expect("There were no summand");
-		LabelTransform{
+		let multiplier=multiplier.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let summand=summand.ok_or_else(||arg.cv.ill("There were no summand"))?;
+		Ok(LabelTransform{
 			multiplier,
 			summand,
 			saturate_bottom,
 			saturate_top,
 			minimum,
 			maximum,
-		}
+		})
 	}
 }
 
@@ -1651,7 +1709,7 @@ impl VirtualChannelPolicy for OccupancyFunction
 
 impl OccupancyFunction
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> OccupancyFunction
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<OccupancyFunction,Error>
 	{
 		let mut label_coefficient=None;
 		let mut occupancy_coefficient=None;
@@ -1661,23 +1719,23 @@ impl OccupancyFunction
 		let mut use_neighbour_space=false;
 		let mut aggregate=true;
 		let mut virtual_channels=None;
-		match_object_panic!(arg.cv,"OccupancyFunction",value,
-			"label_coefficient" => label_coefficient=Some(value.as_f64().expect("bad value for label_coefficient") as i32),
-			"occupancy_coefficient" => occupancy_coefficient=Some(value.as_f64().expect("bad value for occupancy_coefficient") as i32),
-			"product_coefficient" => product_coefficient=Some(value.as_f64().expect("bad value for product_coefficient") as i32),
-			"constant_coefficient" => constant_coefficient=Some(value.as_f64().expect("bad value for constant_coefficient") as i32),
-			"use_neighbour_space" => use_neighbour_space=value.as_bool().expect("bad value for use_neighbour_space"),
-			"use_internal_space" => use_internal_space=value.as_bool().expect("bad value for use_internal_space"),
-			"aggregate" => aggregate=value.as_bool().expect("bad value for aggregate"),
-			"virtual_channels" => virtual_channels=Some(value.as_array().expect("bad value for virtual channels")
+		match_object!(arg.cv,"OccupancyFunction",value,
+			"label_coefficient" => label_coefficient=Some(value.as_f64()? as i32),
+			"occupancy_coefficient" => occupancy_coefficient=Some(value.as_f64()? as i32),
+			"product_coefficient" => product_coefficient=Some(value.as_f64()? as i32),
+			"constant_coefficient" => constant_coefficient=Some(value.as_f64()? as i32),
+			"use_neighbour_space" => use_neighbour_space=value.as_bool()?,
+			"use_internal_space" => use_internal_space=value.as_bool()?,
+			"aggregate" => aggregate=value.as_bool()?,
+			"virtual_channels" => virtual_channels=Some(value.as_array()?
 				.iter().map(|a| a.as_usize().expect("It should be a number") ).collect()),
 		);
-		let label_coefficient=label_coefficient.expect("There were no multiplier");
-		let occupancy_coefficient=occupancy_coefficient.expect("There were no multiplier");
-		let product_coefficient=product_coefficient.expect("There were no multiplier");
-		let constant_coefficient=constant_coefficient.expect("There were no multiplier");
+		let label_coefficient=label_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let occupancy_coefficient=occupancy_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let product_coefficient=product_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
+		let constant_coefficient=constant_coefficient.ok_or_else(||arg.cv.ill("There were no multiplier"))?;
 
-		OccupancyFunction{
+		Ok(OccupancyFunction{
 			label_coefficient,
 			occupancy_coefficient,
 			product_coefficient,
@@ -1686,7 +1744,7 @@ impl OccupancyFunction
 			use_neighbour_space,
 			aggregate,
 			virtual_channels,
-		}
+		})
 	}
 }
 
@@ -1727,10 +1785,10 @@ impl VirtualChannelPolicy for NegateLabel
 
 impl NegateLabel
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> NegateLabel
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<NegateLabel,Error>
 	{
-		match_object_panic!(arg.cv,"NegateLabel",_value);
-		NegateLabel{}
+		match_object!(arg.cv,"NegateLabel",_value);
+		Ok(NegateLabel{})
 	}
 }
 
@@ -1790,17 +1848,17 @@ impl VirtualChannelPolicy for VecLabel
 
 impl VecLabel
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> VecLabel
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<VecLabel,Error>
 	{
 		let mut label_vector=None;
-		match_object_panic!(arg.cv,"VecLabel",value,
-			"label_vector" => label_vector=Some(value.as_array().expect("bad value for label_vector").iter()
+		match_object!(arg.cv,"VecLabel",value,
+			"label_vector" => label_vector=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in label_vector") as i32).collect()),
 		);
-		let label_vector=label_vector.expect("There were no label_vector");
-		VecLabel{
+		let label_vector=label_vector.ok_or_else(||arg.cv.ill("There were no label_vector"))?;
+		Ok(VecLabel{
 			label_vector,
-		}
+		})
 	}
 }
 
@@ -1866,28 +1924,37 @@ impl VirtualChannelPolicy for MapLabel
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.label_to_policy.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+		self.below_policy.reset_statistics(next_cycle);
+		self.above_policy.reset_statistics(next_cycle);
+	}
 
 }
 
 impl MapLabel
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> MapLabel
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<MapLabel,Error>
 	{
 		let mut label_to_policy=None;
 		let mut below_policy : Box<dyn VirtualChannelPolicy> =Box::new(Identity{});
 		let mut above_policy : Box<dyn VirtualChannelPolicy> =Box::new(Identity{});
-		match_object_panic!(arg.cv,"MapLabel",value,
-			"label_to_policy" => label_to_policy=Some(value.as_array().expect("bad value for label_to_policy").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect()),
-			"below_policy" => below_policy = new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg}),
-			"above_policy" => above_policy = new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg}),
+		match_object!(arg.cv,"MapLabel",value,
+			"label_to_policy" => label_to_policy=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
+			"below_policy" => below_policy = try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?,
+			"above_policy" => above_policy = try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?,
 		);
-		let label_to_policy=label_to_policy.expect("There were no label_to_policy");
-		MapLabel{
+		let label_to_policy=label_to_policy.ok_or_else(||arg.cv.ill("There were no label_to_policy"))?;
+		Ok(MapLabel{
 			label_to_policy,
 			below_policy,
 			above_policy,
-		}
+		})
 	}
 }
 
@@ -1951,29 +2018,110 @@ impl VirtualChannelPolicy for MapTrafficIndex
 	{
 		false
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.traffic_to_policy.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+		self.above_policy.reset_statistics(next_cycle);
+	}
 }
 
 impl MapTrafficIndex
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> MapTrafficIndex
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<MapTrafficIndex,Error>
 	{
 		let mut traffic_to_policy=None;
 		let mut above_policy : Box<dyn VirtualChannelPolicy> =Box::new(Identity{});
-		match_object_panic!(arg.cv,"MapTrafficIndex",value,
-			"traffic_to_policy" => traffic_to_policy=Some(value.as_array().expect("bad value for traffic_to_policy").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect()),
-			"above_policy" => above_policy = new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg}),
+		match_object!(arg.cv,"MapTrafficIndex",value,
+			"traffic_to_policy" => traffic_to_policy=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
+			"above_policy" => above_policy = try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?,
 		);
-		let traffic_to_policy=traffic_to_policy.expect("There were no traffic_to_policy");
-		MapTrafficIndex{
+		let traffic_to_policy=traffic_to_policy.ok_or_else(||arg.cv.ill("There were no traffic_to_policy"))?;
+		Ok(MapTrafficIndex{
 			traffic_to_policy,
 			above_policy,
-		}
+		})
 	}
 }
 
 
 
+/**
+Applies a different policy to the candidates of each traffic class, given by the `class` of the messages.
+Classes beyond the list use the `above_policy`, which is Identity by default. The candidates in the router of the destination are not filtered.
+```ignore
+MapClass{
+	class_to_policy: [ArgumentVC{allowed:[0,1]}, ArgumentVC{allowed:[2]}],
+}
+```
+**/
+#[derive(Debug)]
+pub struct MapClass
+{
+	class_to_policy: Vec<Box<dyn VirtualChannelPolicy>>,
+	above_policy: Box<dyn VirtualChannelPolicy>,
+}
+
+impl VirtualChannelPolicy for MapClass
+{
+	fn filter(&self, candidates:Vec<CandidateEgress>, router:&dyn Router, info: &RequestInfo, topology:&dyn Topology, rng: &mut StdRng) -> Vec<CandidateEgress>
+	{
+		if router.get_index().expect("we need routers with index") == info.target_router_index
+		{
+			//The candidates to the destination server are left free.
+			return candidates;
+		}
+		let class = info.phit.packet.message.class;
+		let policy = if class>=self.class_to_policy.len() { &self.above_policy } else { &self.class_to_policy[class] };
+		policy.filter(candidates,router,info,topology,rng)
+	}
+
+	fn need_server_ports(&self)->bool
+	{
+		self.class_to_policy.iter().any(|policy|policy.need_server_ports()) || self.above_policy.need_server_ports()
+	}
+
+	fn need_port_average_queue_length(&self)->bool
+	{
+		self.class_to_policy.iter().any(|policy|policy.need_port_average_queue_length()) || self.above_policy.need_port_average_queue_length()
+	}
+
+	fn need_port_last_transmission(&self)->bool
+	{
+		self.class_to_policy.iter().any(|policy|policy.need_port_last_transmission()) || self.above_policy.need_port_last_transmission()
+	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.class_to_policy.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+		self.above_policy.reset_statistics(next_cycle);
+	}
+}
+
+impl MapClass
+{
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<MapClass,Error>
+	{
+		let mut class_to_policy=None;
+		let mut above_policy : Box<dyn VirtualChannelPolicy> =Box::new(Identity{});
+		match_object!(arg.cv,"MapClass",value,
+			"class_to_policy" => class_to_policy=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
+			"above_policy" => above_policy = try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?,
+		);
+		let class_to_policy=class_to_policy.ok_or_else(||arg.cv.ill("There were no class_to_policy"))?;
+		Ok(MapClass{
+			class_to_policy,
+			above_policy,
+		})
+	}
+}
+
 ///Only allows those candidates whose vc equals their entry vc plus some `s` in `shifts`.
 #[derive(Debug)]
 pub struct ShiftEntryVC
@@ -2017,17 +2165,17 @@ impl VirtualChannelPolicy for ShiftEntryVC
 
 impl ShiftEntryVC
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> ShiftEntryVC
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<ShiftEntryVC,Error>
 	{
 		let mut shifts=None;
-		match_object_panic!(arg.cv,"ShiftEntryVC",value,
-			"shifts" => shifts=Some(value.as_array().expect("bad value for shifts").iter()
+		match_object!(arg.cv,"ShiftEntryVC",value,
+			"shifts" => shifts=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in shifts") as i32).collect()),
 		);
-		let shifts=shifts.expect("There were no shifts");
-		ShiftEntryVC{
+		let shifts=shifts.ok_or_else(||arg.cv.ill("There were no shifts"))?;
+		Ok(ShiftEntryVC{
 			shifts,
-		}
+		})
 	}
 }
 
@@ -2071,25 +2219,33 @@ impl VirtualChannelPolicy for MapHop
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.hop_to_policy.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+		self.above_policy.reset_statistics(next_cycle);
+	}
 
 }
 
 impl MapHop
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> MapHop
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<MapHop,Error>
 	{
 		let mut hop_to_policy=None;
 		let mut above_policy : Box<dyn VirtualChannelPolicy> =Box::new(Identity{});
-		match_object_panic!(arg.cv,"MapHop",value,
-			"hop_to_policy" => hop_to_policy=Some(value.as_array().expect("bad value for hop_to_policy").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect()),
-			"above_policy" => above_policy = new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg}),
+		match_object!(arg.cv,"MapHop",value,
+			"hop_to_policy" => hop_to_policy=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
+			"above_policy" => above_policy = try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?,
 		);
-		let hop_to_policy=hop_to_policy.expect("There were no hop_to_policy");
-		MapHop{
+		let hop_to_policy=hop_to_policy.ok_or_else(||arg.cv.ill("There were no hop_to_policy"))?;
+		Ok(MapHop{
 			hop_to_policy,
 			above_policy,
-		}
+		})
 	}
 }
 
@@ -2135,17 +2291,17 @@ impl VirtualChannelPolicy for ArgumentVC
 
 impl ArgumentVC
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> ArgumentVC
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<ArgumentVC,Error>
 	{
 		let mut allowed=None;
-		match_object_panic!(arg.cv,"ArgumentVC",value,
-			"allowed" => allowed=Some(value.as_array().expect("bad value for allowed").iter()
+		match_object!(arg.cv,"ArgumentVC",value,
+			"allowed" => allowed=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in allowed") as usize).collect()),
 		);
-		let allowed=allowed.expect("There were no allowed");
-		ArgumentVC{
+		let allowed=allowed.ok_or_else(||arg.cv.ill("There were no allowed"))?;
+		Ok(ArgumentVC{
 			allowed,
-		}
+		})
 	}
 }
 
@@ -2191,22 +2347,29 @@ impl VirtualChannelPolicy for Either
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.policies.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+	}
 
 }
 
 impl Either
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> Either
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<Either,Error>
 	{
 		let mut policies=None;
-		match_object_panic!(arg.cv,"Either",value,
-			"policies" => policies=Some(value.as_array().expect("bad value for policies").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect()),
+		match_object!(arg.cv,"Either",value,
+			"policies" => policies=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
 		);
-		let policies=policies.expect("There were no policies");
-		Either{
+		let policies=policies.ok_or_else(||arg.cv.ill("There were no policies"))?;
+		Ok(Either{
 			policies,
-		}
+		})
 	}
 }
 
@@ -2258,25 +2421,33 @@ impl VirtualChannelPolicy for MapEntryVC
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.vc_to_policy.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+		self.above_policy.reset_statistics(next_cycle);
+	}
 
 }
 
 impl MapEntryVC
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> MapEntryVC
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<MapEntryVC,Error>
 	{
 		let mut vc_to_policy=None;
 		let mut above_policy : Box<dyn VirtualChannelPolicy> =Box::new(Identity{});
-		match_object_panic!(arg.cv,"MapEntryVC",value,
-			"vc_to_policy" => vc_to_policy=Some(value.as_array().expect("bad value for vc_to_policy").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect()),
-			"above_policy" => above_policy = new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg}),
+		match_object!(arg.cv,"MapEntryVC",value,
+			"vc_to_policy" => vc_to_policy=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
+			"above_policy" => above_policy = try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:value,..arg})?,
 		);
-		let vc_to_policy=vc_to_policy.expect("There were no vc_to_policy");
-		MapEntryVC{
+		let vc_to_policy=vc_to_policy.ok_or_else(||arg.cv.ill("There were no vc_to_policy"))?;
+		Ok(MapEntryVC{
 			vc_to_policy,
 			above_policy,
-		}
+		})
 	}
 }
 
@@ -2328,28 +2499,35 @@ impl VirtualChannelPolicy for MapMessageSize
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.policies.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+	}
 
 }
 
 impl MapMessageSize
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> MapMessageSize
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<MapMessageSize,Error>
 	{
 		let mut policies : Option<Vec<_>> =None;
 		let mut limits : Option<Vec<_>> =None;
-		match_object_panic!(arg.cv,"MapMessageSize",value,
-			"policies" => policies=Some(value.as_array().expect("bad value for policies").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect()),
-			"limits" => limits=Some(value.as_array().expect("bad value for limits").iter()
+		match_object!(arg.cv,"MapMessageSize",value,
+			"policies" => policies=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
+			"limits" => limits=Some(value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in limits") as usize).collect()),
 		);
-		let policies=policies.expect("There were no policies");
-		let limits=limits.expect("There were no limits");
+		let policies=policies.ok_or_else(||arg.cv.ill("There were no policies"))?;
+		let limits=limits.ok_or_else(||arg.cv.ill("There were no limits"))?;
 		assert_eq!(policies.len(), limits.len() + 1, "In MapMessageSize the `policies` array must have one element more than `limits`, as the last range is unbounded.");
-		MapMessageSize{
+		Ok(MapMessageSize{
 			policies,
 			limits,
-		}
+		})
 	}
 }
 
@@ -2395,21 +2573,28 @@ impl VirtualChannelPolicy for Chain
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.policies.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+	}
 }
 
 impl Chain
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> Chain
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<Chain,Error>
 	{
 		let mut policies=None;
-		match_object_panic!(arg.cv,"Chain",value,
-			"policies" => policies=Some(value.as_array().expect("bad value for policies").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect()),
+		match_object!(arg.cv,"Chain",value,
+			"policies" => policies=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?),
 		);
-		let policies=policies.expect("There were no policies");
-		Chain{
+		let policies=policies.ok_or_else(||arg.cv.ill("There were no policies"))?;
+		Ok(Chain{
 			policies,
-		}
+		})
 	}
 }
 
@@ -2490,29 +2675,36 @@ impl VirtualChannelPolicy for VOQ
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.policies_override.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+	}
 }
 
 impl VOQ
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> VOQ
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<VOQ,Error>
 	{
 		let mut num_classes = None;
 		let mut switch_level = false;
 		let mut start_virtual_channel = 0;
 		let mut policies_override=vec![];
-		match_object_panic!(arg.cv,"VOQ",value,
-			"num_classes" => num_classes = Some(value.as_usize().expect("bad value for num_classes")),
-			"switch_level" => switch_level = value.as_bool().expect("bad value for switch_level"),
-			"start_virtual_channel" => start_virtual_channel = value.as_usize().expect("bad value for start_virtual_channel"),
-			"policies_override" => policies_override=value.as_array().expect("bad value for policies_override").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect(),
+		match_object!(arg.cv,"VOQ",value,
+			"num_classes" => num_classes = Some(value.as_usize()?),
+			"switch_level" => switch_level = value.as_bool()?,
+			"start_virtual_channel" => start_virtual_channel = value.as_usize()?,
+			"policies_override" => policies_override=value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<_,_>>()?,
 		);
-		VOQ{
+		Ok(VOQ{
 			num_classes,
 			switch_level,
 			start_virtual_channel,
 			policies_override,
-		}
+		})
 	}
 }
 
@@ -2565,14 +2757,14 @@ impl VirtualChannelPolicy for CycleIntoNetwork
 
 impl CycleIntoNetwork
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> CycleIntoNetwork
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<CycleIntoNetwork,Error>
 	{
-		match_object_panic!(arg.cv,"CycleIntoNetwork",_value,
+		match_object!(arg.cv,"CycleIntoNetwork",_value,
 
 		);
-		CycleIntoNetwork {
+		Ok(CycleIntoNetwork {
 
-		}
+		})
 	}
 }
 
@@ -2626,14 +2818,14 @@ impl VirtualChannelPolicy for CurrentLinkLabel
 
 impl CurrentLinkLabel
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> CurrentLinkLabel
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<CurrentLinkLabel,Error>
 	{
-		match_object_panic!(arg.cv,"CurrentLinkLabel",_value,
+		match_object!(arg.cv,"CurrentLinkLabel",_value,
 
 		);
-		CurrentLinkLabel {
+		Ok(CurrentLinkLabel {
 
-		}
+		})
 	}
 }
 
@@ -2687,14 +2879,14 @@ impl VirtualChannelPolicy for NextLinkLabel
 
 impl NextLinkLabel
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> NextLinkLabel
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<NextLinkLabel,Error>
 	{
-		match_object_panic!(arg.cv,"NextLinkLabel",_value,
+		match_object!(arg.cv,"NextLinkLabel",_value,
 
 		);
-		NextLinkLabel {
+		Ok(NextLinkLabel {
 
-		}
+		})
 	}
 }
 
@@ -2753,10 +2945,10 @@ impl VirtualChannelPolicy for ChannelHop
 
 impl ChannelHop
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> ChannelHop
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<ChannelHop,Error>
 	{
-		ChannelHop{
-		}
+		Ok(ChannelHop{
+		})
 	}
 }
 
@@ -2823,10 +3015,10 @@ impl VirtualChannelPolicy for ValiantIntermediate
 
 impl ValiantIntermediate
 {
-	pub fn new(_arg:VCPolicyBuilderArgument) -> ValiantIntermediate
+	pub fn new(_arg:VCPolicyBuilderArgument) -> Result<ValiantIntermediate,Error>
 	{
-		ValiantIntermediate {
-		}
+		Ok(ValiantIntermediate {
+		})
 	}
 }
 
@@ -2937,15 +3129,15 @@ impl VirtualChannelPolicy for ValiantLastRouterPalmTree
 
 impl ValiantLastRouterPalmTree
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> ValiantLastRouterPalmTree
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<ValiantLastRouterPalmTree,Error>
 	{
 		let mut global_connections_per_switch = None;
-		match_object_panic!(arg.cv,"ValiantLastRouterPalmTree",value,
-			"global_connections_per_switch" => global_connections_per_switch = Some(value.as_i32().expect("bad value for global_connections_per_switch")),
+		match_object!(arg.cv,"ValiantLastRouterPalmTree",value,
+			"global_connections_per_switch" => global_connections_per_switch = Some(value.as_i32()?),
 		);
-		ValiantLastRouterPalmTree {
+		Ok(ValiantLastRouterPalmTree {
 			global_connections_per_switch,
-		}
+		})
 	}
 }
 
@@ -3012,40 +3204,47 @@ impl VirtualChannelPolicy for CartesianSpaceLabel
 	{
 		true
 	}
+	fn reset_statistics(&mut self,next_cycle:Time)
+	{
+		for policy in self.policies.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
+	}
 
 }
 
 impl CartesianSpaceLabel
 {
-	pub fn new(arg:VCPolicyBuilderArgument) -> CartesianSpaceLabel
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<CartesianSpaceLabel,Error>
 	{
 		let mut policies=None;
 		let mut source_size=None;
 		let mut target_size=None;
 		let mut pattern = None;
 		//new_pattern(PatternBuilderArgument{cv: &ConfigurationValue::Object("Identity".to_string(), vec![]),plugs:arg.plugs});
-		match_object_panic!(arg.cv,"CartesianSpaceLabel",value,
-			"values" => policies=Some(value.as_array().expect("bad value for policies").iter()
-				.map(|v|new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Vec<Box<dyn VirtualChannelPolicy>>>()),
-			"source_size" => source_size=Some(value.as_array().expect("bad value for sizes").iter()
+		match_object!(arg.cv,"CartesianSpaceLabel",value,
+			"values" => policies=Some(value.as_array()?.iter()
+				.map(|v|try_new_virtual_channel_policy(VCPolicyBuilderArgument{cv:v,..arg})).collect::<Result<Vec<Box<dyn VirtualChannelPolicy>>,_>>()?),
+			"source_size" => source_size=Some(value.as_array()?.iter()
 				.map(|v|v.as_usize().expect("bad value in sizes")).collect::<Vec<usize>>()),
-			"target_size" => target_size=Some(value.as_array().expect("bad value for sizes").iter()
+			"target_size" => target_size=Some(value.as_array()?.iter()
 				.map(|v|v.as_usize().expect("bad value in sizes")).collect::<Vec<usize>>()),
-			"pattern" => pattern = Some(new_pattern(PatternBuilderArgument{cv: value, plugs: arg.plugs})),
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv: value, plugs: arg.plugs})?),
 		);
-		let policies=policies.expect("There were no policies");
-		let source_size=source_size.expect("There were no sizes");
+		let policies=policies.ok_or_else(||arg.cv.ill("There were no policies"))?;
+		let source_size=source_size.ok_or_else(||arg.cv.ill("There were no sizes"))?;
 		// let target_size=target_size.expect("There were no sizes");
 		if policies.len() != source_size.len()
 		{
-			panic!("The number of policies must be the same as the number of dimensions");
+			return Err(arg.cv.ill("The number of policies must be the same as the number of dimensions"));
 		}
 		let source_space = CartesianData::new(&source_size);
 		let target_space =if let Some(_) = pattern
 		{
-			 CartesianData::new(&(target_size.expect("There were no sizes")))
+			 CartesianData::new(&(target_size.ok_or_else(||arg.cv.ill("There were no sizes"))?))
 		}else{
-			pattern = Some(new_pattern(PatternBuilderArgument{cv: &ConfigurationValue::Object("Identity".to_string(), vec![]),plugs:arg.plugs}));
+			pattern = Some(try_new_pattern(PatternBuilderArgument{cv: &ConfigurationValue::Object("Identity".to_string(), vec![]),plugs:arg.plugs})?);
 			CartesianData::new(&source_size)
 		};
 		//dummy hamming
@@ -3055,18 +3254,183 @@ impl CartesianSpaceLabel
 		]);
 		let mut rng = StdRng::seed_from_u64(1);
 		let topo_builder = TopologyBuilderArgument{cv: &cv, plugs: arg.plugs, rng: &mut rng };
-		let mut pattern = pattern.expect("There were no pattern");
-		let binding = new_topology(topo_builder);
+		let mut pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
+		let binding = try_new_topology(topo_builder)?;
   		let topology = binding.as_ref();
 
 
 		pattern.initialize(source_space.size, target_space.size, topology, &mut rng);//RefCell::new(pattern.unwrap());
-		CartesianSpaceLabel{
+		Ok(CartesianSpaceLabel{
 			policies,
 			source_space,
 			// target_space,
 			pattern,
+		})
+	}
+}
+
+
+///The key of a row of a [TablePolicy]. `None` values match anything.
+type TableKey = [Option<i32>;5];
+
+/**
+	Decision table mapping `(hops, link_class, label, occupancy_bucket, virtual_channel)` into a new label.
+	See [new_virtual_channel_policy] for the format of the table.
+**/
+#[derive(Debug)]
+pub struct TablePolicy
+{
+	///For each combination of wildcards present in the table, the rows having them.
+	///Sorted from the most specific combination to the least.
+	rows: Vec<([bool;5],std::collections::HashMap<TableKey,i32>)>,
+	///Thresholds of the occupancy to determine the bucket.
+	occupancy_buckets: Vec<usize>,
+	///Whether to add the occupied output space of the current router to the occupancy.
+	use_internal_space: bool,
+	///The label given to candidates without matching row. If `None` they are discarded.
+	default_label: Option<i32>,
+}
+
+impl VirtualChannelPolicy for TablePolicy
+{
+	fn filter(&self, candidates:Vec<CandidateEgress>, router:&dyn Router, info: &RequestInfo, topology:&dyn Topology, _rng: &mut StdRng) -> Vec<CandidateEgress>
+	{
+		let router_index = router.get_index().expect("we need routers with index");
+		candidates.into_iter().filter_map(|candidate|{
+			let (_location,link_class) = topology.neighbour(router_index,candidate.port);
+			let mut occupancy = match router.get_status_at_emisor(candidate.port)
+			{
+				Some(status) => router.get_maximum_credits_towards(candidate.port,candidate.virtual_channel).expect("we need routers with maximum credits")
+					.saturating_sub(status.known_available_space_for_virtual_channel(candidate.virtual_channel).expect("remote available space is not known.")),
+				None => 0,
+			};
+			if self.use_internal_space
+			{
+				let virtual_channel_occupied_output_space=info.virtual_channel_occupied_output_space.expect("virtual_channel_occupied_output_space have not been computed for TablePolicy");
+				occupancy += virtual_channel_occupied_output_space[candidate.port][candidate.virtual_channel];
+			}
+			let bucket = self.occupancy_buckets.iter().filter(|&&threshold|threshold<=occupancy).count();
+			let key = [info.performed_hops as i32, link_class as i32, candidate.label, bucket as i32, candidate.virtual_channel as i32];
+			self.lookup(&key).or(self.default_label).map(|label|CandidateEgress{label,..candidate})
+		}).collect()
+	}
+
+	fn need_server_ports(&self)->bool
+	{
+		false
+	}
+
+	fn need_port_average_queue_length(&self)->bool
+	{
+		false
+	}
+
+	fn need_port_last_transmission(&self)->bool
+	{
+		false
+	}
+}
+
+impl TablePolicy
+{
+	pub fn new(arg:VCPolicyBuilderArgument) -> Result<TablePolicy,Error>
+	{
+		let mut filename=None;
+		let mut entries=None;
+		let mut occupancy_buckets=vec![];
+		let mut use_internal_space=false;
+		let mut default_label=None;
+		match_object!(arg.cv,"TablePolicy",value,
+			"filename" => filename = Some(value.as_str()?.to_string()),
+			"entries" => entries = Some(value.as_array()?.iter().map(|row_cv|{
+				let row = row_cv.as_array()?;
+				if row.len()!=6
+				{
+					return Err(row_cv.ill("Each row of a TablePolicy must have 5 key fields and a label."));
+				}
+				let key = Self::parse_key(row[..5].iter().map(|field|match field
+				{
+					ConfigurationValue::Literal(s) => s.clone(),
+					_ => field.as_i32().expect("bad value in entries").to_string(),
+				}));
+				Ok((key,row[5].as_i32()?))
+			}).collect::<Result<Vec<_>,_>>()?),
+			"occupancy_buckets" => occupancy_buckets = value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?,
+			"use_internal_space" => use_internal_space=value.as_bool()?,
+			"default_label" => default_label=Some(value.as_i32()?),
+		);
+		let entries = match (filename,entries)
+		{
+			(Some(filename),None) =>
+			{
+				let contents = std::fs::read_to_string(&filename).map_err(|error|arg.cv.ill(&format!("Could not open the table file {}: {}",filename,error)))?;
+				Self::parse_table(&contents)
+			},
+			(None,Some(entries)) => entries,
+			_ => return Err(arg.cv.ill("TablePolicy requires either a filename or entries.")),
+		};
+		Ok(TablePolicy{
+			rows: Self::build_rows(entries),
+			occupancy_buckets,
+			use_internal_space,
+			default_label,
+		})
+	}
+	///Parses a key from its fields as strings.
+	fn parse_key<I:Iterator<Item=String>>(fields:I) -> TableKey
+	{
+		let mut key = [None;5];
+		for (index,field) in fields.enumerate()
+		{
+			key[index] = if field=="*" { None } else { Some(field.parse::<i32>().unwrap_or_else(|_|panic!("bad key field {} in TablePolicy",field))) };
+		}
+		key
+	}
+	///Parses the rows of a table written as text.
+	fn parse_table(contents:&str) -> Vec<(TableKey,i32)>
+	{
+		contents.lines().map(|line|line.trim()).filter(|line|!line.is_empty() && !line.starts_with('#')).map(|line|{
+			let fields:Vec<&str> = line.split_whitespace().collect();
+			if fields.len()!=6
+			{
+				panic!("The line \"{}\" of a TablePolicy does not have 5 key fields and a label.",line);
+			}
+			let label = fields[5].parse::<i32>().unwrap_or_else(|_|panic!("bad label in the line \"{}\" of a TablePolicy",line));
+			(Self::parse_key(fields[..5].iter().map(|field|field.to_string())),label)
+		}).collect()
+	}
+	///Groups the rows by their wildcards, keeping the first row of each key.
+	fn build_rows(entries:Vec<(TableKey,i32)>) -> Vec<([bool;5],std::collections::HashMap<TableKey,i32>)>
+	{
+		let mut rows : Vec<([bool;5],std::collections::HashMap<TableKey,i32>)> = vec![];
+		for (key,label) in entries
+		{
+			let wildcards = [key[0].is_none(),key[1].is_none(),key[2].is_none(),key[3].is_none(),key[4].is_none()];
+			match rows.iter_mut().find(|(w,_)|*w==wildcards)
+			{
+				Some((_,map)) => { map.entry(key).or_insert(label); },
+				None => rows.push((wildcards,std::iter::once((key,label)).collect())),
+			}
 		}
+		//Stable, so that ties are resolved by the order of appearance in the table.
+		rows.sort_by_key(|(wildcards,_)|wildcards.iter().filter(|&&w|w).count());
+		rows
+	}
+	///The label of the most specific row matching the values.
+	fn lookup(&self, values:&[i32;5]) -> Option<i32>
+	{
+		self.rows.iter().find_map(|(wildcards,map)|{
+			let mut key = [None;5];
+			for index in 0..5
+			{
+				if !wildcards[index]
+				{
+					key[index] = Some(values[index]);
+				}
+			}
+			map.get(&key).copied()
+		})
 	}
 }
 
@@ -3093,4 +3457,29 @@ mod tests {
 		assert_eq!(get_index_router_connection_palmtree(4,17,0,4), 3);
 		assert_eq!(get_index_router_connection_palmtree(4,17,1,0), 0);
 	}
+
+	#[test]
+	fn table_policy_lookup() {
+		let table = TablePolicy{
+			rows: TablePolicy::build_rows(TablePolicy::parse_table("
+				# hops link_class label bucket vc label
+				* * * * * 9
+				0 * * * 0 1
+				0 * * * 1 2
+				0 * * * 0 7
+				* 1 * 2 * 3
+			")),
+			occupancy_buckets: vec![],
+			use_internal_space: false,
+			default_label: None,
+		};
+		//The first of the most specific rows.
+		assert_eq!(table.lookup(&[0,0,0,0,0]), Some(1));
+		assert_eq!(table.lookup(&[0,5,-1,3,1]), Some(2));
+		//Two wildcards less than the catch-all row.
+		assert_eq!(table.lookup(&[4,1,0,2,0]), Some(3));
+		assert_eq!(table.lookup(&[4,1,0,1,0]), Some(9));
+		let empty = TablePolicy{ rows: TablePolicy::build_rows(vec![]), occupancy_buckets: vec![], use_internal_space: false, default_label: None };
+		assert_eq!(empty.lookup(&[0,0,0,0,0]), None);
+	}
 }
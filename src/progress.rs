@@ -0,0 +1,302 @@
+/*!
+
+Reporting of the progress of a simulation while it runs.
+
+By default the simulation prints in the console the columns of the statistics every 1000 cycles, followed by the percentage of the expected cycles already simulated and an estimation of the remaining time.
+The configuration may replace this by a list of reporters in `progress_reporters`.
+```ignore
+progress_reporters: [
+	//Print the statistical columns. By default `period:1000` and `eta:true`.
+	Console{period:10000, eta:true},
+	//Write the progress into a file, replacing its previous contents.
+	ProgressFile{period:100000, path:"progress.txt"},
+],
+```
+An empty list makes the simulation silent.
+
+The `ProgressFile` writes a single object `Progress{cycle, expected_cycles, percent, elapsed_seconds, cycles_per_second, eta_seconds}` that can be read by other processes. A relative `path` is taken from the directory of the simulation.
+Since the experiments would write into the same file, a `ProgressFile` cannot be used when simulating several experiments in parallel jobs.
+Without a `path` it is written in the file given by the environment variable `CAMINOS_PROGRESS_FILE`, which the `slurm` action sets to the `local.progress` file of each experiment.
+Then the `check` action reports the percentage completed of each slurm job still running, counting as done the experiments with result.
+
+The expected cycles are the `warmup` plus `measured` cycles, so the estimation is not reliable when the measurement is extended, as with `measurement_extension`.
+
+*/
+
+use std::fs;
+use std::path::{Path,PathBuf};
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::measures::Statistics;
+use crate::Network;
+
+///The environment variable with the default path for [ProgressFile].
+pub const PROGRESS_FILE_VARIABLE: &str = "CAMINOS_PROGRESS_FILE";
+
+///The state of a running simulation, as given to the reporters.
+#[derive(Clone,Debug)]
+pub struct Progress
+{
+	///The next cycle to simulate.
+	pub cycle: Time,
+	///The cycles that the simulation is expected to run. At least `cycle`.
+	pub expected_cycles: Time,
+	///Seconds of wall time since the beginning of the simulation.
+	pub elapsed_seconds: f64,
+}
+
+impl Progress
+{
+	///The percentage of the expected cycles already simulated.
+	pub fn percent(&self) -> f64
+	{
+		if self.expected_cycles==0 { 100.0 } else { 100.0*self.cycle as f64/self.expected_cycles as f64 }
+	}
+	///The cycles simulated per second of wall time.
+	pub fn cycles_per_second(&self) -> f64
+	{
+		if self.elapsed_seconds>0.0 { self.cycle as f64/self.elapsed_seconds } else { 0.0 }
+	}
+	///The estimated seconds to complete the simulation, if the speed is known.
+	pub fn eta_seconds(&self) -> Option<f64>
+	{
+		let speed = self.cycles_per_second();
+		if speed>0.0 { Some((self.expected_cycles-self.cycle) as f64/speed) } else { None }
+	}
+	///The progress as a `Progress` object, as written by [ProgressFile]. The `eta_seconds` is missing while the speed is unknown.
+	pub fn to_configuration_value(&self) -> ConfigurationValue
+	{
+		let mut fields = vec![
+			(String::from("cycle"),ConfigurationValue::Number(self.cycle as f64)),
+			(String::from("expected_cycles"),ConfigurationValue::Number(self.expected_cycles as f64)),
+			(String::from("percent"),ConfigurationValue::Number(self.percent())),
+			(String::from("elapsed_seconds"),ConfigurationValue::Number(self.elapsed_seconds)),
+			(String::from("cycles_per_second"),ConfigurationValue::Number(self.cycles_per_second())),
+		];
+		if let Some(eta) = self.eta_seconds()
+		{
+			fields.push((String::from("eta_seconds"),ConfigurationValue::Number(eta)));
+		}
+		ConfigurationValue::Object(String::from("Progress"),fields)
+	}
+}
+
+///Formats some seconds as `hours:minutes:seconds`.
+fn format_seconds(seconds:f64) -> String
+{
+	let seconds = seconds.round() as u64;
+	format!("{}:{:02}:{:02}",seconds/3600,seconds/60%60,seconds%60)
+}
+
+///Something that informs about the progress of the simulation.
+pub trait ProgressReporter
+{
+	///Cycles between reports.
+	fn period(&self) -> Time;
+	///Called once before simulating the first cycle.
+	fn begin(&mut self, statistics:&Statistics);
+	///Called every `period` cycles.
+	fn report(&mut self, progress:&Progress, statistics:&Statistics, network:&Network);
+	///Called once the simulation has ended.
+	fn end(&mut self, progress:&Progress);
+}
+
+///Build a reporter from an element of the `progress_reporters` list.
+///The relative paths of the files are taken from `directory`, see [Simulation::new_in_directory](crate::Simulation::new_in_directory).
+pub fn new_progress_reporter(cv:&ConfigurationValue, directory:&Path) -> Result<Box<dyn ProgressReporter>,Error>
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=cv
+	{
+		match cv_name.as_ref()
+		{
+			"Console" => Ok(Box::new(ConsoleProgress::new(cv)?)),
+			"ProgressFile" => Ok(Box::new(ProgressFile::new(cv,directory)?)),
+			_ => Err(cv.ill(&format!("Unknown progress reporter {}",cv_name))),
+		}
+	}
+	else
+	{
+		Err(cv.ill("Trying to create a progress reporter from a non-Object"))
+	}
+}
+
+///Prints the statistical columns in the console, optionally followed by the estimated remaining time.
+#[derive(Debug)]
+pub struct ConsoleProgress
+{
+	period: Time,
+	///Whether to print the percentage done, the speed and the estimated remaining time.
+	eta: bool,
+}
+
+impl Default for ConsoleProgress
+{
+	fn default() -> ConsoleProgress
+	{
+		ConsoleProgress{
+			period: 1000,
+			eta: true,
+		}
+	}
+}
+
+impl ConsoleProgress
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<ConsoleProgress,Error>
+	{
+		let mut reporter = ConsoleProgress::default();
+		match_object!(cv,"Console",value,
+			"period" => reporter.period=value.as_time()?,
+			"eta" => reporter.eta=value.as_bool()?,
+		);
+		if reporter.period==0
+		{
+			return Err(cv.ill("The period of the Console progress must be positive."));
+		}
+		Ok(reporter)
+	}
+}
+
+impl ProgressReporter for ConsoleProgress
+{
+	fn period(&self) -> Time
+	{
+		self.period
+	}
+	fn begin(&mut self, statistics:&Statistics)
+	{
+		let header = statistics.header_line();
+		if self.eta
+		{
+			println!("{} progress",header);
+		}
+		else
+		{
+			println!("{}",header);
+		}
+	}
+	fn report(&mut self, progress:&Progress, statistics:&Statistics, network:&Network)
+	{
+		let line = statistics.report_line(progress.cycle,network);
+		if self.eta
+		{
+			let eta = progress.eta_seconds().map(format_seconds).unwrap_or_else(||String::from("unknown"));
+			println!("{} {:.1}% {:.0} cycles/s ETA {}",line,progress.percent(),progress.cycles_per_second(),eta);
+		}
+		else
+		{
+			println!("{}",line);
+		}
+	}
+	fn end(&mut self, _progress:&Progress)
+	{
+	}
+}
+
+///Writes the progress into a file, replacing its contents in each report.
+#[derive(Debug)]
+pub struct ProgressFile
+{
+	period: Time,
+	path: PathBuf,
+	///To warn only once when the file cannot be written.
+	failed: bool,
+}
+
+impl ProgressFile
+{
+	///A relative path, either given or from the variable [PROGRESS_FILE_VARIABLE], is taken from `directory`.
+	pub fn new(cv:&ConfigurationValue, directory:&Path) -> Result<ProgressFile,Error>
+	{
+		let mut period = 10000;
+		let mut path = None;
+		match_object!(cv,"ProgressFile",value,
+			"period" => period=value.as_time()?,
+			"path" => path=Some(PathBuf::from(value.as_str()?)),
+		);
+		if period==0
+		{
+			return Err(cv.ill("The period of the ProgressFile must be positive."));
+		}
+		let path = path.or_else(||std::env::var_os(PROGRESS_FILE_VARIABLE).map(PathBuf::from))
+			.ok_or_else(||cv.ill(&format!("The ProgressFile requires a path or the variable {} to be set.",PROGRESS_FILE_VARIABLE)))?;
+		Ok(ProgressFile{
+			period,
+			path: directory.join(path),
+			failed: false,
+		})
+	}
+	fn write(&mut self, progress:&Progress)
+	{
+		if let Err(error) = fs::write(&self.path,format!("{}\n",progress.to_configuration_value()))
+		{
+			if !self.failed
+			{
+				println!("WARNING: could not write the progress into {:?}: {}",self.path,error);
+				self.failed = true;
+			}
+		}
+	}
+}
+
+impl ProgressReporter for ProgressFile
+{
+	fn period(&self) -> Time
+	{
+		self.period
+	}
+	fn begin(&mut self, _statistics:&Statistics)
+	{
+	}
+	fn report(&mut self, progress:&Progress, _statistics:&Statistics, _network:&Network)
+	{
+		self.write(progress);
+	}
+	fn end(&mut self, progress:&Progress)
+	{
+		self.write(progress);
+	}
+}
+
+///Reads the percentage done from the contents of a file written by [ProgressFile].
+pub fn read_progress_percent(contents:&str) -> Option<f64>
+{
+	match crate::config::parse_result(contents)
+	{
+		Ok(ConfigurationValue::Object(ref name,ref fields)) if name=="Progress" =>
+			fields.iter().find(|(key,_)|key=="percent").and_then(|(_,value)|value.as_f64().ok()),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn progress_file_round_trip()
+	{
+		let progress = Progress{
+			cycle: 2500,
+			expected_cycles: 10000,
+			elapsed_seconds: 5.0,
+		};
+		assert_eq!(progress.percent(),25.0);
+		assert_eq!(progress.cycles_per_second(),500.0);
+		assert_eq!(progress.eta_seconds(),Some(15.0));
+		assert_eq!(format_seconds(3725.0),"1:02:05");
+		//The relative path is taken from the directory of the simulation.
+		let path = std::env::temp_dir().join("caminos_progress_round_trip.progress");
+		let cv = ConfigurationValue::Object(String::from("ProgressFile"),vec![
+			(String::from("path"),ConfigurationValue::Literal(String::from("caminos_progress_round_trip.progress"))),
+		]);
+		let mut reporter = ProgressFile::new(&cv,&std::env::temp_dir()).unwrap();
+		reporter.end(&progress);
+		let contents = fs::read_to_string(&path).unwrap();
+		fs::remove_file(&path).ok();
+		assert_eq!(read_progress_percent(&contents),Some(25.0));
+		assert_eq!(read_progress_percent("Result{cycle:10}"),None);
+	}
+}
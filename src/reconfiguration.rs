@@ -0,0 +1,125 @@
+/*!
+
+Changes of the topology during the simulation, to study the resilience and reconfiguration of the network.
+
+When the configuration includes `events` the topology is wrapped into a [ReconfigurableLinks] and, at the beginning of the scheduled cycles, the given links go down or up again.
+A link that goes down stops being requested by the routers, but the phits already granted to it still arrive to the next router.
+The distances of the topology are then computed again avoiding the links down, and the routing is notified by [Routing::on_topology_change](crate::routing::Routing::on_topology_change) to update any table.
+A link is given by any of its two ends, and both go down or up together.
+
+```ignore
+events: [
+	//At the beginning of cycle 2000 the link at the port 1 of the router 5 goes down.
+	LinkDown{cycle:2000, router:5, port:1},
+	//And it works again from the cycle 6000.
+	LinkUp{cycle:6000, router:5, port:1},
+],
+```
+
+The amount of events applied is included in the results as `topology_events`.
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::{match_object,error,source_location};
+use crate::topology::{Topology,Location};
+use crate::error::{Error,SourceLocation};
+
+pub use crate::topology::operations::ReconfigurableLinks;
+
+///A change of a link at some cycle.
+#[derive(Debug,Clone)]
+pub struct TopologyEvent
+{
+	///The cycle at whose beginning the change is made.
+	pub cycle: Time,
+	///Whether the link works after the event.
+	pub alive: bool,
+	///The router at one of the ends of the link.
+	pub router: usize,
+	///The port of `router` at the end of the link.
+	pub port: usize,
+}
+
+impl TopologyEvent
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<TopologyEvent,Error>
+	{
+		let mut cycle=None;
+		let mut router=None;
+		let mut port=None;
+		match_object!(cv,["LinkDown","LinkUp"],value,
+			"cycle" => cycle=Some(value.as_time()?),
+			"router" => router=Some(value.as_usize()?),
+			"port" => port=Some(value.as_usize()?),
+		);
+		let alive = matches!(cv,ConfigurationValue::Object(name,_) if name=="LinkUp");
+		Ok(TopologyEvent{
+			cycle: cycle.ok_or_else(||cv.ill("There were no cycle"))?,
+			alive,
+			router: router.ok_or_else(||cv.ill("There were no router"))?,
+			port: port.ok_or_else(||cv.ill("There were no port"))?,
+		})
+	}
+}
+
+///The events scheduled for the topology, in order of cycle. See the [module documentation](self).
+#[derive(Debug)]
+pub struct TopologyEvents
+{
+	///The events sorted by cycle. Those with the same cycle keep the order of the configuration.
+	events: Vec<TopologyEvent>,
+	///The index of the next event to apply.
+	next: usize,
+}
+
+impl TopologyEvents
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<TopologyEvents,Error>
+	{
+		let mut events : Vec<TopologyEvent> = cv.as_array()?.iter().map(TopologyEvent::new).collect::<Result<_,_>>()?;
+		events.sort_by_key(|event|event.cycle);
+		Ok(TopologyEvents{
+			events,
+			next: 0,
+		})
+	}
+	///Checks that every event refers to a link between routers of the `topology`.
+	pub fn check(&self, topology:&dyn Topology) -> Result<(),Error>
+	{
+		for event in self.events.iter()
+		{
+			if event.router>=topology.num_routers() || event.port>=topology.ports(event.router)
+			{
+				return Err(error!(bad_argument).with_message(format!("The topology event {:?} refers to a port out of the topology.",event)));
+			}
+			if let Location::RouterPort{..} = topology.neighbour(event.router,event.port).0
+			{
+				continue;
+			}
+			return Err(error!(bad_argument).with_message(format!("The topology event {:?} refers to a port that is not a link between routers.",event)));
+		}
+		Ok(())
+	}
+	///Applies to the `topology` the events scheduled up to the `cycle`. Returns whether there was any.
+	pub fn apply(&mut self, cycle:Time, topology:&mut dyn Topology) -> Result<bool,Error>
+	{
+		let first = self.next;
+		while let Some(event) = self.events.get(self.next)
+		{
+			if event.cycle>cycle
+			{
+				break;
+			}
+			topology.set_link_alive(event.router,event.port,event.alive)?;
+			self.next+=1;
+		}
+		Ok(self.next>first)
+	}
+	///The amount of events already applied.
+	pub fn applied(&self) -> usize
+	{
+		self.next
+	}
+}
@@ -0,0 +1,202 @@
+/*!
+
+Typed results of a simulation, for programs embedding CAMINOS that prefer not to navigate the [ConfigurationValue] tree returned by [Simulation::get_simulation_results](crate::Simulation::get_simulation_results).
+They are obtained with [Simulation::results](crate::Simulation::results) after running the simulation.
+
+The statistics reported by the routing, the traffic, and the routers do not have a fixed structure, so they are kept as [ConfigurationValue].
+When the crate is compiled with the `serde` feature all these structures implement `serde::Serialize`, as does [ConfigurationValue].
+An `Object` is serialized as a map from its name to the map of its fields.
+
+```ignore
+let mut simulation = Simulation::new(&cv,&plugs);
+simulation.run();
+let results = simulation.results();
+println!("accepted load {} with average delay {}",results.accepted_load,results.average_message_delay);
+```
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::error::Error;
+use crate::event::Time;
+use crate::measures::{ResourceUsage,StallCounts};
+
+///The results of a simulation over the measured cycles.
+///The fields have the same meaning as the homonymous fields of the `Result` object written by the simulation.
+#[derive(Debug,Clone,Default)]
+#[cfg_attr(feature="serde",derive(serde::Serialize))]
+pub struct SimulationResults
+{
+	///The cycle at which the simulation ended.
+	pub cycle: Time,
+	///The number of cycles measured.
+	pub measured_cycles: Time,
+	///Phits generated per cycle and server.
+	pub injected_load: f64,
+	///Phits consumed per cycle and server.
+	pub accepted_load: f64,
+	///Average number of cycles from the creation of a message until its consumption.
+	pub average_message_delay: f64,
+	///Average number of cycles from the injection of a packet into the network until its consumption.
+	pub average_packet_network_delay: f64,
+	///Jain index of the phits generated by the servers.
+	pub server_generation_jain_index: f64,
+	///Jain index of the phits consumed by the servers.
+	pub server_consumption_jain_index: f64,
+	///Average number of hops between routers of the consumed packets.
+	pub average_packet_hops: f64,
+	///`total_packet_per_hop_count[h]` is the number of consumed packets that made `h` hops.
+	pub total_packet_per_hop_count: Vec<usize>,
+	///Phits per cycle that traverse the average link between routers.
+	pub average_link_utilization: f64,
+	///Phits per cycle that traverse the most used link between routers.
+	pub maximum_link_utilization: f64,
+	///`link_utilization[router][port]` is the phits per cycle that arrive through the link at that port of the router, for the ports towards other routers.
+	pub link_utilization: Vec<Vec<f64>>,
+	///For each virtual channel, phits per cycle and link that used it.
+	pub virtual_channel_usage: Vec<f64>,
+	///The statistics of each traffic class, indexed by the class of the messages.
+	pub classes: Vec<ClassResults>,
+	///The statistics of each summand of a TrafficSum, indexed by the `id_traffic` of the messages. Empty for other traffics.
+	pub sub_traffics: Vec<ClassResults>,
+	///The names given to the summands of a TrafficSum, in the same order as `sub_traffics`. Empty when they have no names.
+	pub sub_traffic_names: Vec<String>,
+	///The statistics of each server, in the order of the servers.
+	pub servers: Vec<ServerResults>,
+	///The series of the statistics of each period of `statistics_temporal_step` cycles. Only when kept in memory, that is, without a `statistics_temporal_file`.
+	pub temporal: Option<TemporalResults>,
+	///The statistics reported by the routing.
+	pub routing_statistics: Option<ConfigurationValue>,
+	///The statistics reported by the traffic.
+	pub traffic_statistics: Option<ConfigurationValue>,
+	///The statistics aggregated over all the routers.
+	pub router_statistics: Option<ConfigurationValue>,
+	///The cycles that phits have been stalled at the head of the input buffers, by cause, summed over the routers that track them.
+	pub stall_cycles: Option<StallCounts>,
+	///The computational resources employed.
+	pub resource_usage: ResourceUsage,
+}
+
+///The results of a single server over the measured cycles.
+#[derive(Debug,Clone,Default)]
+#[cfg_attr(feature="serde",derive(serde::Serialize))]
+pub struct ServerResults
+{
+	///Phits generated per cycle.
+	pub injected_load: f64,
+	///Phits consumed per cycle.
+	pub accepted_load: f64,
+	///Average delay of the messages consumed by the server.
+	pub average_message_delay: f64,
+	///The last cycle in which the server sent a phit to its router.
+	pub cycle_last_created_phit: Time,
+	///The last cycle in which the server consumed a whole message.
+	pub cycle_last_consumed_message: Time,
+	///The number of times the traffic wanted to generate a message but it could not be stored.
+	pub missed_generations: usize,
+}
+
+///The results restricted to the messages of a traffic class, or of a sub-traffic, over the measured cycles.
+#[derive(Debug,Clone,Default)]
+#[cfg_attr(feature="serde",derive(serde::Serialize))]
+pub struct ClassResults
+{
+	///Phits of the class generated per cycle and server.
+	pub injected_load: f64,
+	///Phits of the class consumed per cycle and server.
+	pub accepted_load: f64,
+	pub average_message_delay: f64,
+	pub average_packet_network_delay: f64,
+}
+
+///Each field is a series with a value for each period of `step` cycles since the beginning of the simulation.
+#[derive(Debug,Clone,Default)]
+#[cfg_attr(feature="serde",derive(serde::Serialize))]
+pub struct TemporalResults
+{
+	///The number of cycles of each period.
+	pub step: Time,
+	pub injected_load: Vec<f64>,
+	pub accepted_load: Vec<f64>,
+	pub average_message_delay: Vec<f64>,
+	pub average_packet_network_delay: Vec<f64>,
+	pub average_packet_hops: Vec<f64>,
+	pub server_generation_jain_index: Vec<f64>,
+	pub server_consumption_jain_index: Vec<f64>,
+	pub virtual_channel_usage: Vec<Vec<f64>>,
+}
+
+/**
+The format in which the simulation writes its `Result` object, selected with `result_format` in the configuration.
+```ignore
+result_format: Configuration,//the default, the syntax of the configuration files
+result_format: JSON,//a single line of JSON, see [config_to_json](crate::config::config_to_json)
+```
+The experiments read the `local.result` files in either format.
+**/
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ResultFormat
+{
+	Configuration,
+	JSON,
+}
+
+impl ResultFormat
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<ResultFormat,Error>
+	{
+		match cv
+		{
+			ConfigurationValue::Object(name,_) if name=="Configuration" => Ok(ResultFormat::Configuration),
+			ConfigurationValue::Object(name,_) if name=="JSON" => Ok(ResultFormat::JSON),
+			_ => Err(cv.ill("bad value for result_format")),
+		}
+	}
+}
+
+#[cfg(feature="serde")]
+impl serde::Serialize for ConfigurationValue
+{
+	fn serialize<S:serde::Serializer>(&self, serializer:S) -> Result<S::Ok,S::Error>
+	{
+		use serde::ser::{SerializeMap,SerializeSeq};
+		///The fields of an object, serialized as a map.
+		struct Fields<'a>(&'a [(String,ConfigurationValue)]);
+		impl<'a> serde::Serialize for Fields<'a>
+		{
+			fn serialize<S:serde::Serializer>(&self, serializer:S) -> Result<S::Ok,S::Error>
+			{
+				let mut map = serializer.serialize_map(Some(self.0.len()))?;
+				for (name,value) in self.0.iter()
+				{
+					map.serialize_entry(name,value)?;
+				}
+				map.end()
+			}
+		}
+		match self
+		{
+			ConfigurationValue::Literal(s) => serializer.serialize_str(s),
+			ConfigurationValue::Number(x) => serializer.serialize_f64(*x),
+			ConfigurationValue::True => serializer.serialize_bool(true),
+			ConfigurationValue::False => serializer.serialize_bool(false),
+			ConfigurationValue::None => serializer.serialize_unit(),
+			ConfigurationValue::Object(name,fields) =>
+			{
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry(name,&Fields(fields))?;
+				map.end()
+			},
+			ConfigurationValue::Array(list) | ConfigurationValue::Experiments(list) | ConfigurationValue::NamedExperiments(_,list) =>
+			{
+				let mut seq = serializer.serialize_seq(Some(list.len()))?;
+				for value in list.iter()
+				{
+					seq.serialize_element(value)?;
+				}
+				seq.end()
+			},
+			ConfigurationValue::Where(..) | ConfigurationValue::Expression(..) => serializer.collect_str(self),
+		}
+	}
+}
@@ -3,30 +3,54 @@ use std::cell::RefCell;
 use std::rc::{Rc,Weak};
 use std::ops::{Deref};
 use std::mem::{size_of};
+use std::collections::BTreeMap;
 use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
 use std::convert::TryInto;
 
-use super::{Router,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,TransmissionMechanismBuilderArgument};
+use super::{Router,Switching,InputHead,BufferOccupancy,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,TransmissionMechanismBuilderArgument,WeightedTable};
 use crate::config_parser::ConfigurationValue;
 use crate::topology::{Location,Topology};
-use crate::routing::CandidateEgress;
-use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
-use crate::event::{Event,Eventful,EventGeneration,CyclePosition,Time};
+use crate::routing::{CandidateEgress,RoutingOccupancy};
+use crate::policies::{RequestInfo,VirtualChannelPolicy,try_new_virtual_channel_policy,VCPolicyBuilderArgument};
+use crate::event::{self,Event,Eventful,EventGeneration,CyclePosition,Time};
 use crate::{Phit,Packet,SimulationShared,SimulationMut};
 use crate::quantify::Quantifiable;
+use crate::measures::{StallCause,StallCounts,StallTracker,PacketDecisions};
 use crate::packet::PacketRef;
 //use crate::Plugs;
-use crate::match_object_panic;
+use crate::{match_object,error,source_location};
+use crate::error::{Error,SourceLocation};
 
 
-///Strategy for the arbitration of the output port.
+///Strategy for the arbitration of the output port, selected with `output_scheduling`.
 enum OutputArbiter
 {
-	#[allow(dead_code)]
+	///A random virtual channel, as `output_scheduling: Random`.
 	Random,
+	///Round-robin among the virtual channels, as in FSIN. The default, as `output_scheduling: RoundRobin`.
 	Token{
 		port_token: Vec<usize>,
 	},
+	///A weighted round-robin among the virtual channels, as `output_scheduling: WeightedTable{weights:[...]}`.
+	WeightedTable(WeightedTable),
+}
+
+impl OutputArbiter
+{
+	fn new(cv:&ConfigurationValue, ports:usize) -> Result<OutputArbiter,Error>
+	{
+		Ok(match cv
+		{
+			ConfigurationValue::Object(name,_) => match name.as_ref()
+			{
+				"Random" => OutputArbiter::Random,
+				"RoundRobin" => OutputArbiter::Token{port_token:vec![0;ports]},
+				"WeightedTable" => OutputArbiter::WeightedTable(WeightedTable::new(cv,ports)?),
+				_ => return Err(cv.ill(&format!("Unknown output_scheduling {}",name))),
+			},
+			_ => return Err(cv.ill("bad value for output_scheduling")),
+		})
+	}
 }
 
 ///The basic Router struct. Very similar to FSIN's router.
@@ -46,6 +70,8 @@ pub struct Basic
 	bubble: bool,
 	///Credits required in the next router's virtual port to begin the transmission
 	flit_size: usize,
+	///The switching technique. The `flit_size` already accounts for it, except for the wait of store-and-forward to receive the whole packet.
+	switching: Switching,
 	///Size of each input buffer.
 	buffer_size: usize,
 	///Give priority to in-transit packets over packets in injection queues.
@@ -55,6 +81,19 @@ pub struct Basic
 	allow_request_busy_port: bool,
 	///Use the labels provided by the routing to sort the petitions in the output arbiter.
 	output_prioritize_lowest_label: bool,
+	///Give priority in the arbitration to the packets that entered the network first, after the labels and the in-transit priority.
+	///Defaults to false.
+	oldest_first: bool,
+	///Drop the packets at the head of an input buffer when none of their candidate outputs has space for them. See [lossy](crate::lossy).
+	///Defaults to false.
+	drop_when_full: bool,
+	///Drop the packets that have waited more than these cycles at the head of an input buffer. See [lossy](crate::lossy).
+	drop_timeout: Option<Time>,
+	///The router only operates in the cycles multiple of its frequency divisor.
+	frequency_divisor: Time,
+	///The frequency divisor of the link of each port, a phit being sent only in the cycles multiple of it.
+	///Initialized at the first call to process, see [SimulationShared::port_frequency_divisor].
+	port_frequency_divisors: Vec<Time>,
 	///Whether to immediately discard candidate outputs when they are currently receiving from an input.
 	///Otherwise, these candidates are marked as impossible, but they can be processed by the `virtual_channel_policies`.
 	///In particular, [EnforceFlowControl] will filter them out.
@@ -68,9 +107,13 @@ pub struct Basic
 	// transmission_mechanism: Box<dyn AbstractTransmissionMechanism>,
 	// The router to server mechanism employed.
 	// to_server_mechanism: Box<dyn AbstractTransmissionMechanism>,
-	/// The server to router mechanism employed.
+	/// `from_server_mechanisms[port]` is the server to router mechanism employed in the port, if it goes to a server.
 	/// This will be used to build the status at the servers.
-	from_server_mechanism: Box<dyn AbstractTransmissionMechanism>,
+	from_server_mechanisms: Vec<Option<Box<dyn AbstractTransmissionMechanism>>>,
+	///The amount of virtual channels of each port. See `link_class_virtual_channels` in [new_router](super::new_router).
+	port_virtual_channels: Vec<usize>,
+	///The greatest amount of virtual channels of a port. The structures indexed by virtual channel are allocated with this size.
+	virtual_channels: usize,
 	///If 0 then there are no output buffer, if greater than 0 then the size of each of them.
 	output_buffer_size: usize,
 	///The outut buffers indexed as `[output_port][output_vc]`.
@@ -93,6 +136,8 @@ pub struct Basic
 	statistics_temporal_step: Time,
 	principal_measurement: BasicRouterMeasurement,
 	temporal_statistics: Vec<BasicRouterMeasurement>,
+	///The causes for which the phits at the head of the input buffers are stalled.
+	stall_tracker: StallTracker,
 }
 
 #[derive(Clone)]
@@ -159,7 +204,7 @@ impl Router for Basic
 	fn num_virtual_channels(&self) -> usize
 	{
 		//self.virtual_ports[0].len()
-		self.transmission_port_status[0].num_virtual_channels()
+		self.virtual_channels
 	}
 	fn virtual_port_size(&self, _port:usize, _virtual_channel:usize) -> usize
 	{
@@ -405,6 +450,11 @@ impl Router for Basic
 		{
 			*x=0f64;
 		}
+		self.stall_tracker.counts=StallCounts::default();
+		for policy in self.virtual_channel_policies.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
 	}
 	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>
 	{
@@ -412,19 +462,58 @@ impl Router for Basic
 		{
 			//let from_server_mechanism = TransmissionFromServer::new(self.num_virtual_channels(),self.buffer_size,self.flit_size);
 			//Box::new(from_server_mechanism.new_status_at_emissor())
-			self.from_server_mechanism.new_status_at_emissor()
+			self.from_server_mechanisms[port].as_ref().expect("there is no mechanism from the server").new_status_at_emissor()
 		}
 		else
 		{
 			unimplemented!()
 		}
 	}
+	fn may_drop_packets(&self) -> bool
+	{
+		self.drop_when_full || self.drop_timeout.is_some()
+	}
+	fn stall_counts(&self) -> Option<StallCounts>
+	{
+		Some(self.stall_tracker.counts.clone())
+	}
+	fn input_heads(&self) -> Option<Vec<InputHead>>
+	{
+		Some(self.reception_port_space.iter().enumerate().flat_map(|(port,space)|space.front_iter().map(move |phit|(port,phit))).map(|(port,phit)|{
+			let virtual_channel = phit.virtual_channel.borrow().expect("it should have an associated virtual channel");
+			InputHead{
+				port,
+				virtual_channel,
+				selected_output: self.selected_output[port][virtual_channel].as_ref().map(|(_packet,port,vc)|(*port,*vc)),
+				phit,
+			}
+		}).collect())
+	}
+	fn port_occupancy(&self) -> Option<Vec<f64>>
+	{
+		Some(self.transmission_port_status.iter().enumerate().map(|(port,status)|{
+			(0..self.port_virtual_channels[port]).map(|vc|{
+				//When the neighbour is a server it may have more space than the buffer size.
+				let neighbour = self.buffer_size.saturating_sub(status.known_available_space_for_virtual_channel(vc).unwrap_or(self.buffer_size));
+				let output = if self.output_buffer_size==0 { 0 } else { self.output_buffers[port][vc].len() };
+				(neighbour + output) as f64
+			}).sum()
+		}).collect())
+	}
+	fn buffer_occupancy(&self) -> Option<BufferOccupancy>
+	{
+		let input = self.reception_port_space.iter().enumerate().map(|(port,space)|
+			(0..self.port_virtual_channels[port]).map(|vc|space.occupied_dedicated_space(vc).unwrap_or(0)).collect()
+		).collect();
+		let output = if self.output_buffer_size==0 { vec![] } else { self.output_buffers.iter().map(|port|port.iter().map(|buffer|buffer.len()).collect()).collect() };
+		Some(BufferOccupancy{input,output})
+	}
 }
 
 impl Basic
 {
 	//pub fn new(router_index: usize, cv:&ConfigurationValue, plugs:&Plugs, topology:&dyn Topology, maximum_packet_size:usize) -> Rc<RefCell<Basic<SimpleVirtualChannels>>>
-	pub fn new(arg:RouterBuilderArgument) -> Rc<RefCell<Basic>>
+	pub fn new(arg:RouterBuilderArgument) -> Result<Rc<RefCell<Basic>>,Error>
 	{
 		let RouterBuilderArgument{
 			router_index,
@@ -433,6 +522,7 @@ impl Basic
 			topology,
 			maximum_packet_size,
 			statistics_temporal_step,
+			frequency_divisor,
 			..
 		} = arg;
 		//let mut servers=None;
@@ -443,19 +533,26 @@ impl Basic
 		let mut virtual_channel_policies=None;
 		let mut bubble=None;
 		let mut flit_size=None;
+		let mut switching=None;
 		let mut intransit_priority=None;
 		let mut allow_request_busy_port=None;
 		let mut output_prioritize_lowest_label=None;
 		let mut output_buffer_size=None;
 		let mut transmission_mechanism=None;
+		let mut link_class_mechanisms:BTreeMap<usize,String>=BTreeMap::new();
+		let mut link_class_virtual_channels:Vec<usize>=vec![];
 		let mut to_server_mechanism=None;
 		let mut from_server_mechanism=None;
 		let mut neglect_busy_output = false;
-		match_object_panic!(cv,"Basic",value,
+		let mut oldest_first = false;
+		let mut drop_when_full = false;
+		let mut drop_timeout = None;
+		let mut output_scheduling = None;
+		match_object!(cv,"Basic",value,
 			"virtual_channels" => match value
 			{
 				&ConfigurationValue::Number(f) => virtual_channels=Some(f as usize),
-				_ => panic!("bad value for virtual_channels"),
+				_ => return Err(value.ill("bad value for virtual_channels")),
 			},
 			//"routing" => routing=Some(new_routing(value)),
 			//"virtual_channel_policy" => virtual_channel_policy=Some(new_virtual_channel_policy(value)),
@@ -463,45 +560,46 @@ impl Basic
 			{
 				//&ConfigurationValue::Array(ref a) => virtual_channel_policies=Some(a.iter().map(|cv|new_virtual_channel_policy(cv,plugs)).collect()),
 				&ConfigurationValue::Array(ref a) => virtual_channel_policies=Some(a.iter().map(
-					|cv|new_virtual_channel_policy(VCPolicyBuilderArgument{
+					|cv|try_new_virtual_channel_policy(VCPolicyBuilderArgument{
 					cv,
 					plugs
-				})).collect()),
-				_ => panic!("bad value for permute"),
+				})).collect::<Result<_,_>>()?),
+				_ => return Err(value.ill("bad value for permute")),
 			}
 			"delay" => (),//FIXME: yet undecided if/how to implement this.
 			"buffer_size" => match value
 			{
 				&ConfigurationValue::Number(f) => buffer_size=Some(f as usize),
-				_ => panic!("bad value for buffer_size"),
+				_ => return Err(value.ill("bad value for buffer_size")),
 			},
 			"output_buffer_size" => match value
 			{
 				&ConfigurationValue::Number(f) => output_buffer_size=Some(f as usize),
-				_ => panic!("bad value for buffer_size"),
+				_ => return Err(value.ill("bad value for buffer_size")),
 			},
 			"bubble" => match value
 			{
 				&ConfigurationValue::True => bubble=Some(true),
 				&ConfigurationValue::False => bubble=Some(false),
-				_ => panic!("bad value for bubble"),
+				_ => return Err(value.ill("bad value for bubble")),
 			},
 			"flit_size" => match value
 			{
 				&ConfigurationValue::Number(f) => flit_size=Some(f as usize),
-				_ => panic!("bad value for flit_size"),
+				_ => return Err(value.ill("bad value for flit_size")),
 			},
+			"switching" => switching=Some(Switching::new(value)),
 			"intransit_priority" => match value
 			{
 				&ConfigurationValue::True => intransit_priority=Some(true),
 				&ConfigurationValue::False => intransit_priority=Some(false),
-				_ => panic!("bad value for intransit_priority"),
+				_ => return Err(value.ill("bad value for intransit_priority")),
 			},
 			"allow_request_busy_port" => match value
 			{
 				&ConfigurationValue::True => allow_request_busy_port=Some(true),
 				&ConfigurationValue::False => allow_request_busy_port=Some(false),
-				_ => panic!("bad value for allow_request_busy_port"),
+				_ => return Err(value.ill("bad value for allow_request_busy_port")),
 			},
 			"output_priorize_lowest_label" =>
 			{
@@ -510,44 +608,53 @@ impl Basic
 				{
 					&ConfigurationValue::True => output_prioritize_lowest_label=Some(true),
 					&ConfigurationValue::False => output_prioritize_lowest_label=Some(false),
-					_ => panic!("bad value for output_prioritize_lowest_label"),
+					_ => return Err(value.ill("bad value for output_prioritize_lowest_label")),
 				}
 			},
 			"output_prioritize_lowest_label" => match value
 			{
 				&ConfigurationValue::True => output_prioritize_lowest_label=Some(true),
 				&ConfigurationValue::False => output_prioritize_lowest_label=Some(false),
-				_ => panic!("bad value for output_prioritize_lowest_label"),
+				_ => return Err(value.ill("bad value for output_prioritize_lowest_label")),
 			},
-			"neglect_busy_output" => neglect_busy_output = value.as_bool().expect("bad value for neglect_busy_output"),
+			"neglect_busy_output" => neglect_busy_output = value.as_bool()?,
+			"oldest_first" => oldest_first = value.as_bool()?,
+			"drop_when_full" => drop_when_full = value.as_bool()?,
+			"drop_timeout" => drop_timeout = Some(value.as_time()?),
+			"output_scheduling" => output_scheduling = Some(value),
 			"transmission_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => transmission_mechanism = Some(s.to_string()),
-				_ => panic!("bad value for transmission_mechanism"),
+				_ => return Err(value.ill("bad value for transmission_mechanism")),
 			},
+			"link_class_mechanisms" => link_class_mechanisms = super::link_class_mechanisms(value)?,
+			"link_class_virtual_channels" => link_class_virtual_channels = value.as_array()?.iter()
+				.map(|v|v.as_usize().expect("bad value in link_class_virtual_channels")).collect(),
 			"to_server_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => to_server_mechanism = Some(s.to_string()),
-				_ => panic!("bad value for to_server_mechanism"),
+				_ => return Err(value.ill("bad value for to_server_mechanism")),
 			},
 			"from_server_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => from_server_mechanism = Some(s.to_string()),
-				_ => panic!("bad value for from_server_mechanism"),
+				_ => return Err(value.ill("bad value for from_server_mechanism")),
 			},
 		);
 		//let sides=sides.expect("There were no sides");
-		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
-		let virtual_channel_policies=virtual_channel_policies.expect("There were no virtual_channel_policies");
+		let virtual_channels=virtual_channels.ok_or_else(||cv.ill("There were no virtual_channels"))?;
+		let virtual_channel_policies=virtual_channel_policies.ok_or_else(||cv.ill("There were no virtual_channel_policies"))?;
 		//let routing=routing.expect("There were no routing");
-		let buffer_size=buffer_size.expect("There were no buffer_size");
-		let output_buffer_size=output_buffer_size.expect("There were no output_buffer_size");
-		let bubble=bubble.expect("There were no bubble");
-		let flit_size=flit_size.expect("There were no flit_size");
-		let intransit_priority=intransit_priority.expect("There were no intransit_priority");
-		let allow_request_busy_port=allow_request_busy_port.expect("There were no allow_request_busy_port");
-		let output_prioritize_lowest_label=output_prioritize_lowest_label.expect("There were no output_prioritize_lowest_label");
+		let buffer_size=buffer_size.ok_or_else(||cv.ill("There were no buffer_size"))?;
+		let output_buffer_size=output_buffer_size.ok_or_else(||cv.ill("There were no output_buffer_size"))?;
+		let bubble=bubble.ok_or_else(||cv.ill("There were no bubble"))?;
+		let (switching,flit_size)=Switching::from_fields(switching,flit_size,buffer_size,maximum_packet_size);
+		let intransit_priority=intransit_priority.ok_or_else(||cv.ill("There were no intransit_priority"))?;
+		let allow_request_busy_port=allow_request_busy_port.ok_or_else(||cv.ill("There were no allow_request_busy_port"))?;
+		let output_prioritize_lowest_label=output_prioritize_lowest_label.ok_or_else(||cv.ill("There were no output_prioritize_lowest_label"))?;
 		let input_ports=topology.ports(router_index);
+		let port_virtual_channels = super::port_virtual_channels(topology,router_index,virtual_channels,&link_class_virtual_channels);
+		let virtual_channels = port_virtual_channels.iter().copied().max().unwrap_or(virtual_channels);
 		let selected_input=(0..input_ports).map(|_|
 			(0..virtual_channels).map(|_|None).collect()
 		).collect();
@@ -563,36 +670,46 @@ impl Basic
 		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
 		//let transmission_mechanism = super::SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
 		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size};
-		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,..transmission_builder_argument});
-		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
-		//let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
-		let from_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&from_server_mechanism,..transmission_builder_argument});
+		//The mechanism with the given name, built with the virtual channels of the port.
+		let port_mechanism = |name:&str,port:usize| new_transmission_mechanism(TransmissionMechanismBuilderArgument{name,virtual_channels:port_virtual_channels[port],..transmission_builder_argument});
+		//The name of the mechanism for a router-to-router link of class `link_class`.
+		let router_link_mechanism = |link_class:usize| link_class_mechanisms.get(&link_class).unwrap_or(&transmission_mechanism);
 		let transmission_port_status:Vec<Box<dyn StatusAtEmissor>> = (0..input_ports).map(|p|
 			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
 			{
 				//let b:Box<dyn StatusAtEmissor> = Box::new(to_server_mechanism.new_status_at_emissor());
 				//b
-				to_server_mechanism.new_status_at_emissor()
+				port_mechanism(&to_server_mechanism,p).map(|mechanism|mechanism.new_status_at_emissor())
 			}
 			else
 			{
 				//Box::new(transmission_mechanism.new_status_at_emissor())
-				transmission_mechanism.new_status_at_emissor()
+				port_mechanism(router_link_mechanism(topology.neighbour(router_index,p).1),p).map(|mechanism|mechanism.new_status_at_emissor())
 			}
-		).collect();
-		let reception_port_space = (0..input_ports).map(|p|
+		).collect::<Result<_,_>>()?;
+		let from_server_mechanisms:Vec<_> = (0..input_ports).map(|p|
 			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
+			{
+				port_mechanism(&from_server_mechanism,p).map(Some)
+			}
+			else
+			{
+				Ok(None)
+			}
+		).collect::<Result<_,_>>()?;
+		let reception_port_space = (0..input_ports).map(|p|
+			if let Some(ref mechanism)=from_server_mechanisms[p]
 			{
 				//let b:Box<dyn SpaceAtReceptor> = Box::new(from_server_mechanism.new_space_at_receptor());
 				//b
-				from_server_mechanism.new_space_at_receptor()
+				Ok(mechanism.new_space_at_receptor())
 			}
 			else
 			{
 				//Box::new(transmission_mechanism.new_space_at_receptor())
-				transmission_mechanism.new_space_at_receptor()
+				port_mechanism(router_link_mechanism(topology.neighbour(router_index,p).1),p).map(|mechanism|mechanism.new_space_at_receptor())
 			}
-		).collect();
+		).collect::<Result<_,_>>()?;
 		let output_buffers= if output_buffer_size==0 {vec![]} else{
 			(0..input_ports).map(|_|
 				(0..virtual_channels).map(|_|AugmentedBuffer::new()).collect()
@@ -607,22 +724,30 @@ impl Basic
 			virtual_channel_policies,
 			bubble,
 			flit_size,
+			switching,
 			intransit_priority,
 			allow_request_busy_port,
 			output_prioritize_lowest_label,
+			oldest_first,
+			drop_when_full,
+			drop_timeout,
+			frequency_divisor,
+			port_frequency_divisors: vec![],
 			neglect_busy_output,
 			buffer_size,
 			transmission_port_status,
 			reception_port_space,
 			//transmission_mechanism,
 			//to_server_mechanism,
-			from_server_mechanism,
+			from_server_mechanisms,
+			port_virtual_channels,
+			virtual_channels,
 			output_buffer_size,
 			output_buffers,
 			selected_input,
 			selected_output,
 			time_at_input_head,
-			output_arbiter: OutputArbiter::Token{port_token: vec![0;input_ports]},
+			output_arbiter: output_scheduling.map(|cv|OutputArbiter::new(cv,input_ports)).transpose()?.unwrap_or_else(||OutputArbiter::Token{port_token: vec![0;input_ports]}),
 			maximum_packet_size,
 			//statistics_begin_cycle: 0,
 			//statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
@@ -630,15 +755,21 @@ impl Basic
 			statistics_temporal_step,
 			principal_measurement: BasicRouterMeasurement::new(virtual_channels),
 			temporal_statistics: vec![],
+			stall_tracker: StallTracker::new(input_ports,virtual_channels),
 		}));
 		//r.borrow_mut().self_rc=r.downgrade();
 		r.borrow_mut().self_rc=Rc::<_>::downgrade(&r);
-		r
+		Ok(r)
 	}
 }
 
 impl Basic
 {
+	///The cause of a stall when the phit cannot advance for lack of space.
+	fn space_stall_cause(&self) -> StallCause
+	{
+		if self.output_buffer_size==0 { StallCause::NoCredits } else { StallCause::OutputBufferFull }
+	}
 	///Whether a phit in an input buffer can advance.
 	///bubble_in_use should be true only for leading phits that require the additional space.
 	fn can_phit_advance(&self, phit:&Rc<Phit>, exit_port:usize, exit_vc:usize, bubble_in_use:bool)->bool
@@ -700,11 +831,10 @@ impl Basic
 	}
 	fn gather_cycle_statistics(&mut self, cycle:Time, cycles_span:Time)
 	{
-		let amount_virtual_channels=self.num_virtual_channels();
 		let current_temporal_index = self.get_current_temporal_measurement(cycle);
-		for port_space in self.reception_port_space.iter()
+		for (port,port_space) in self.reception_port_space.iter().enumerate()
 		{
-			for vc in 0..amount_virtual_channels
+			for vc in 0..self.port_virtual_channels[port]
 			{
 				//self.principal_measurement.reception_space_occupation_per_vc[vc]+=(port_space.occupied_dedicated_space(vc).unwrap_or(0)*cycles_span) as f64 / self.reception_port_space.len() as f64;
 				let increment = (port_space.occupied_dedicated_space(vc).unwrap_or(0)*cycles_span as usize) as f64 / self.reception_port_space.len() as f64;
@@ -766,12 +896,21 @@ impl Eventful for Basic
 			//}
 		}
 		self.last_process_at_cycle = Some(simulation.cycle);
+		if self.port_frequency_divisors.is_empty()
+		{
+			self.port_frequency_divisors = (0..self.transmission_port_status.len()).map(|port|simulation.port_frequency_divisor(self.router_index,port)).collect();
+		}
+		//Whether some phit could not be sent because its link does not accept phits in this cycle.
+		let mut waiting_link_slot = false;
 		let mut request:Vec<PortRequest>=vec![];
+		//The input buffers whose head packet is to be dropped in this cycle.
+		let mut dropped_heads:Vec<(usize,usize)>=vec![];
 		let topology = simulation.network.topology.as_ref();
 		
 		let amount_virtual_channels=self.num_virtual_channels();
 		//-- gather cycle statistics
 		self.gather_cycle_statistics(simulation.cycle,cycles_span);
+		self.stall_tracker.account(cycles_span);
 
 		//-- Precompute whatever polcies ask for.
 		let server_ports : Option<Vec<usize>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_server_ports())
@@ -817,10 +956,11 @@ impl Eventful for Basic
 		};
 		let port_average_neighbour_queue_length:Option<Vec<f32>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_port_average_queue_length())
 		{
-			Some(self.transmission_port_status.iter().map(|p|{
+			Some(self.transmission_port_status.iter().enumerate().map(|(port,p)|{
 				//let total=p.iter().map(|ref vp|self.buffer_size - vp.neighbour_credits).sum::<usize>();
 				//(total as f32) / (p.len() as f32)
-				let total=(0..amount_virtual_channels).map(|vc|{
+				let port_virtual_channels = self.port_virtual_channels[port];
+				let total=(0..port_virtual_channels).map(|vc|{
 					//self.buffer_size-p.known_available_space_for_virtual_channel(vc).expect("needs to know available space")
 					let available = p.known_available_space_for_virtual_channel(vc).expect("needs to know available space");
 					if available>self.buffer_size
@@ -834,7 +974,7 @@ impl Eventful for Basic
 						self.buffer_size - available
 					}
 				}).sum::<usize>();
-				(total as f32) / (amount_virtual_channels as f32)
+				(total as f32) / (port_virtual_channels as f32)
 			}).collect())
 		}
 		else
@@ -890,6 +1030,12 @@ impl Eventful for Basic
 			).collect())
 		};
 
+		let routing_port_occupancy = if simulation.routing.need_occupancy() { self.port_occupancy() } else { None };
+		let routing_occupancy = RoutingOccupancy{
+			port_occupancy: routing_port_occupancy.as_ref(),
+			network_occupancy: simulation.network_occupancy.as_ref(),
+		};
+
 		//-- Routing and requests.
 		let mut undecided_channels=0;//just as indicator if the router has pending work.
 		let mut moved_phits=0;//another indicator of pending work.
@@ -908,14 +1054,23 @@ impl Eventful for Basic
 					None =>
 					{
 						undecided_channels+=1;
-						let target_server=phit.packet.message.destination;
-						let (target_location,_link_class)=topology.server_neighbour(target_server);
-						let target_router=match target_location
+						let occupied = self.reception_port_space[entry_port].occupied_dedicated_space(entry_vc).unwrap_or(phit.packet.size);
+						if !self.switching.packet_ready(phit.packet.size,occupied)
 						{
-							Location::RouterPort{router_index,router_port:_} =>router_index,
-							_ => panic!("The server is not attached to a router"),
-						};
-						let routing_candidates=simulation.routing.next(phit.packet.routing_info.borrow().deref(),simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
+							//Store-and-forward waits until the whole packet is in the buffer.
+							self.stall_tracker.set(entry_port,entry_vc,StallCause::IncompletePacket);
+							continue;
+						}
+						//Only a packet wholly in the buffer may be dropped.
+						let can_drop = occupied>=phit.packet.size;
+						if can_drop && self.drop_timeout.map_or(false,|timeout|self.time_at_input_head[entry_port][entry_vc] as Time > timeout)
+						{
+							dropped_heads.push((entry_port,entry_vc));
+							continue;
+						}
+						let target_server=phit.packet.message.destination;
+						let target_router=topology.server_router_from(target_server,self.router_index);
+						let routing_candidates=simulation.routing.next_with_occupancy(phit.packet.routing_info.borrow().deref(),simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&routing_occupancy,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
 						let routing_idempotent = routing_candidates.idempotent;
 						if routing_candidates.len()==0
 						{
@@ -924,30 +1079,49 @@ impl Eventful for Basic
 								panic!("There are no choices for packet {:?} entry_port={} entry_vc={} in router {} towards server {}",phit.packet,entry_port,entry_vc,self.router_index,target_server);
 							}
 							//There are currently no good port choices, but there may be in the future.
+							self.stall_tracker.set(entry_port,entry_vc,StallCause::NoCandidates);
 							continue;
 						}
+						PacketDecisions::record_candidates(&phit.packet,&*self,topology,&routing_candidates.candidates,target_router,simulation.cycle);
+						//Whether some candidate is discarded because its output is being used by other phit.
+						let mut blocked_by_busy_output=false;
 						let mut good_ports=routing_candidates.into_iter().filter_map(|candidate|{
 							let CandidateEgress{port:f_port,virtual_channel:f_virtual_channel,..} = candidate;
+							if f_virtual_channel>=self.port_virtual_channels[f_port]
+							{
+								//The port does not have that virtual channel.
+								return None;
+							}
+							if !topology.is_link_alive(self.router_index,f_port)
+							{
+								//The link has failed, so the routing must offer other candidates.
+								return None;
+							}
 							//We analyze each candidate output port, considering whether they are in use (port or virtual channel).
 							match self.selected_input[f_port][f_virtual_channel]
 							{
 								//Some((s_port,s_virtual_channel))=> s_port==entry_port && s_virtual_channel==entry_vc,
 								//Some(_) => None,
 								// Keep these candidates until EnforceFlowControl, so policies have all information.
-								Some(_) => if self.neglect_busy_output {None} else {Some(CandidateEgress{router_allows:Some(false), ..candidate})},
+								Some(_) =>
+								{
+									blocked_by_busy_output=true;
+									if self.neglect_busy_output {None} else {Some(CandidateEgress{router_allows:Some(false), ..candidate})}
+								},
 								None =>
 								{
 									let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,f_port);
 									//if self.transmission_port_status[f_port].can_transmit(&phit,f_virtual_channel,transmit_auxiliar_info)
 									let allowed = if self.can_phit_advance(&phit,f_port,f_virtual_channel,bubble_in_use)
 									{
-										if self.allow_request_busy_port
+										if self.allow_request_busy_port || !busy_ports[f_port]
 										{
 											true
 										}
 										else
 										{
-											!busy_ports[f_port]
+											blocked_by_busy_output=true;
+											false
 										}
 									}
 									else
@@ -958,6 +1132,7 @@ impl Eventful for Basic
 								}
 							}
 						}).collect::<Vec<_>>();
+						let any_allowed=good_ports.iter().any(|candidate|candidate.router_allows==Some(true));
 						let performed_hops=phit.packet.routing_info.borrow().hops;
 						//Apply all the declared virtual channel policies in order.
 						let request_info=RequestInfo{
@@ -987,8 +1162,30 @@ impl Eventful for Basic
 						}
 						if good_ports.is_empty()
 						{
+							if can_drop && self.drop_when_full && !any_allowed && !blocked_by_busy_output
+							{
+								//Every candidate lacks space for the packet.
+								dropped_heads.push((entry_port,entry_vc));
+								continue;
+							}
+							let cause = if any_allowed
+							{
+								//The policies have discarded all the outputs that could be used.
+								StallCause::NoCandidates
+							}
+							else if blocked_by_busy_output
+							{
+								StallCause::LostAllocation
+							}
+							else
+							{
+								self.space_stall_cause()
+							};
+							self.stall_tracker.set(entry_port,entry_vc,cause);
 							continue;//There is no available port satisfying the policies. Hopefully there will in the future.
 						}
+						//It is cleared if the phit advances in this cycle.
+						self.stall_tracker.set(entry_port,entry_vc,StallCause::LostAllocation);
 						//else if good_ports.len()>=2
 						//{
 						//	//TODO: this will not be true when having true allocators.
@@ -1009,7 +1206,12 @@ impl Eventful for Basic
 							}
 						}
 					},
-					Some((ref _packet,_port,_vc)) => (),
+					Some((ref _packet,port,vc)) =>
+					{
+						//It is cleared if the phit advances in this cycle.
+						let cause = if self.can_phit_advance(&phit,port,vc,false) { StallCause::LostAllocation } else { self.space_stall_cause() };
+						self.stall_tracker.set(entry_port,entry_vc,cause);
+					},
 						//(port,vc,0),//FIXME: perhaps 0 changes into None?
 				};
 				// //FIXME: this should not call known_available_space_for_virtual_channel
@@ -1053,6 +1255,12 @@ impl Eventful for Basic
 		//for ref mut rx in request_sequence.iter_mut()
 		let captured_intransit_priority=self.intransit_priority;//to move into closure
 		let captured_router_index=self.router_index;//to move into closure
+		let captured_oldest_first=self.oldest_first;//to move into closure
+		//A stable sort after the shuffle, so that packets of the same age remain in random order.
+		let sort_by_age = |requests:&mut Vec<PortRequest>| if captured_oldest_first
+		{
+			requests.sort_by_key(|req|*req.packet.cycle_into_network.borrow());
+		};
 		let request_it = request_sequence.into_iter().flat_map(|mut rx|{
 			if captured_intransit_priority
 			{
@@ -1072,6 +1280,8 @@ impl Eventful for Basic
 				let rng=&mut mutable.rng;
 				request_transit.shuffle(rng);
 				request_injection.shuffle(rng);
+				sort_by_age(&mut request_transit);
+				sort_by_age(&mut request_injection);
 				//**rx=request_transit;
 				rx=request_transit;
 				rx.append(&mut request_injection);
@@ -1081,6 +1291,7 @@ impl Eventful for Basic
 				//shuffle has changed notably from rand-0.4 to rand-0.8
 				//mutable.rng.borrow_mut().shuffle(&mut rx);
 				rx.shuffle(&mut mutable.rng);
+				sort_by_age(&mut rx);
 			}
 			rx
 		});
@@ -1101,6 +1312,28 @@ impl Eventful for Basic
 
 		//-- For each output port decide which input actually uses it this cycle.
 		let mut events=vec![];
+		//-- Drop the packets, returning the credits of their phits.
+		for (entry_port,entry_vc) in dropped_heads
+		{
+			let packet=self.reception_port_space[entry_port].front_virtual_channel(entry_vc).expect("There were no packet to drop").packet.clone();
+			for _ in 0..packet.size
+			{
+				let (_phit,ack_message) = self.reception_port_space[entry_port].extract(entry_vc).expect("The dropped packet should be whole in the buffer");
+				if let Some(message)=ack_message
+				{
+					let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+					events.push(EventGeneration{
+						delay: simulation.link_classes[previous_link_class].acknowledge_delay(&previous_location,simulation.network.topology.as_ref()),
+						position:CyclePosition::Begin,
+						event:Event::Acknowledge{location:previous_location,receptor:Location::RouterPort{router_index:self.router_index,router_port:entry_port},message},
+					});
+				}
+			}
+			moved_phits+=packet.size;
+			self.time_at_input_head[entry_port][entry_vc]=0;
+			self.stall_tracker.clear(entry_port,entry_vc);
+			mutable.dropped_packets.push(packet);
+		}
 		for exit_port in 0..self.transmission_port_status.len()
 		{
 			let nvc=amount_virtual_channels;
@@ -1124,15 +1357,16 @@ impl Eventful for Basic
 							}
 							moved_phits+=1;
 							self.time_at_input_head[entry_port][entry_vc]=0;
+							self.stall_tracker.clear(entry_port,entry_vc);
 							*phit.virtual_channel.borrow_mut()=Some(exit_vc);
 							if let Some(message)=ack_message
 							{
 								let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
 								events.push(EventGeneration{
-									delay: simulation.link_classes[previous_link_class].delay,
+									delay: simulation.link_classes[previous_link_class].acknowledge_delay(&previous_location,simulation.network.topology.as_ref()),
 									position:CyclePosition::Begin,
 									//event:Event::Acknowledge{location:previous_location,message:AcknowledgeMessage::ack_phit_clear_from_virtual_channel(entry_vc)},
-									event:Event::Acknowledge{location:previous_location,message},
+									event:Event::Acknowledge{location:previous_location,receptor:Location::RouterPort{router_index:self.router_index,router_port:entry_port},message},
 								});
 							}
 							if let Some((ref s_exit_packet,s_exit_port,s_exit_vc))=self.selected_output[entry_port][entry_vc]
@@ -1252,6 +1486,11 @@ impl Eventful for Basic
 					}
 				}
 			}
+			if !cand.is_empty() && simulation.cycle % self.port_frequency_divisors[exit_port] != 0
+			{
+				cand.clear();
+				waiting_link_slot = true;
+			}
 			//for selected_virtual_channel in 0..nvc
 			let selected_virtual_channel = if !cand.is_empty()
 			{
@@ -1260,6 +1499,7 @@ impl Eventful for Basic
 				{
 					//OutputArbiter::Random=> cand[mutable.rng.borrow_mut().gen_range(0,cand.len())],//rand-0.4
 					OutputArbiter::Random=> cand[mutable.rng.gen_range(0..cand.len())],//rand-0.8
+					OutputArbiter::WeightedTable(ref mut table) => table.select(exit_port,&cand,cand_in_transit),
 					OutputArbiter::Token{ref mut port_token}=>
 					{
 						//Or by tokens as in fsin
@@ -1301,16 +1541,17 @@ impl Eventful for Basic
 						{
 							moved_phits+=1;
 							self.time_at_input_head[iport][entry_vc]=0;
+							self.stall_tracker.clear(iport,entry_vc);
 							//phit.virtual_channel.replace(Some(selected_virtual_channel));
 							*phit.virtual_channel.borrow_mut()=Some(selected_virtual_channel);
 							if let Some(message)=ack_message
 							{
 								let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,iport);
 								events.push(EventGeneration{
-									delay: simulation.link_classes[previous_link_class].delay,
+									delay: simulation.link_classes[previous_link_class].acknowledge_delay(&previous_location,simulation.network.topology.as_ref()),
 									position:CyclePosition::Begin,
 									//event:Event::PhitClearAcknowledge{location:previous_location,virtual_channel:entry_vc},
-									event:Event::Acknowledge{location:previous_location,message},
+									event:Event::Acknowledge{location:previous_location,receptor:Location::RouterPort{router_index:self.router_index,router_port:iport},message},
 								});
 							}
 							if phit.is_end()
@@ -1337,7 +1578,7 @@ impl Eventful for Basic
 				let (new_location,link_class)=simulation.network.topology.neighbour(self.router_index,exit_port);
 				//Send the phit to the other link endpoint.
 				events.push(EventGeneration{
-					delay: simulation.link_classes[link_class].delay,
+					delay: simulation.link_classes[link_class].phit_delay(simulation.cycle,&phit,&new_location,simulation.network.topology.as_ref()),
 					position:CyclePosition::Begin,
 					event:Event::PhitToLocation{
 						phit: phit.clone(),
@@ -1369,7 +1610,7 @@ impl Eventful for Basic
 		}
 		self.next_events.pop();//remove the event that was served.
 		//TODO: what to do with probabilistic requests???
-		if undecided_channels>0 || moved_phits>0 || !events.is_empty() || request_len>0
+		if undecided_channels>0 || moved_phits>0 || !events.is_empty() || request_len>0 || waiting_link_slot
 		//if undecided_channels>0 || moved_phits>0 || events.len()>0
 		//if true
 		{
@@ -1400,6 +1641,7 @@ impl Eventful for Basic
 	fn schedule(&mut self, current_cycle:Time, delay:Time) -> Option<EventGeneration>
 	{
 		let target = current_cycle+delay;
+		let target = event::round_to_multiple(target,self.frequency_divisor);
 		if self.next_events.is_empty() || target<*self.next_events.last().unwrap() {
 			self.next_events.push(target);
 			let event = Event::Generic(self.as_eventful().upgrade().expect("missing component"));
@@ -0,0 +1,350 @@
+/*!
+
+Dynamic voltage and frequency scaling (DVFS) of the output links and the crossbar of the routers.
+
+The time is divided in windows of `period` cycles. At the end of each window the utilization of each component in that window is compared against two thresholds.
+When it is above `upper_threshold` the component goes to the next faster level, and when it is below `lower_threshold` it goes to the next slower level.
+Each level multiplies the frequency divisor of the component, so a level with multiplier 2 operates at half the nominal frequency.
+After a change of level the component is halted during `transition_latency` cycles.
+
+The energy is accounted relative to the component operating at the nominal frequency, which spends 1 unit per cycle.
+At a level with multiplier `m` the power is `static_power + (1-static_power)*(1/m)^power_exponent`, since the voltage may be reduced together with the frequency.
+
+It is available in the [InputOutput](super::input_output::InputOutput) router through its `dvfs` field, as it is the router operating its links and crossbar at their frequency divisors.
+```ignore
+InputOutput{
+	...
+	dvfs: Dvfs{
+		period: 1000,//cycles of each utilization window.
+		transition_latency: 20,//cycles that the component is halted after changing its level.
+		multipliers: [1,2,4],//the levels, multiplying the frequency divisor. The first is the nominal one, at which the components begin.
+		upper_threshold: 0.6,//go to a faster level above this utilization.
+		lower_threshold: 0.2,//go to a slower level below this utilization.
+		power_exponent: 3,//Optional, defaults to 3.
+		static_power: 0.3,//Optional, the fraction of the power that does not scale. Defaults to 0.
+		links: true,//Optional, whether to scale the output links. Defaults to true.
+		crossbar: true,//Optional, whether to scale the crossbar. Defaults to true.
+	},
+}
+```
+The router statistics then include a `dvfs` object with the average power and frequency multiplier of the links and the crossbars, relative to the nominal ones, and the number of level transitions.
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::match_object_panic;
+
+///The configuration of the DVFS, shared by all the components of a router.
+#[derive(Debug,Clone)]
+pub struct DvfsPolicy
+{
+	///The cycles of each utilization window.
+	period: Time,
+	///The cycles that the component is halted after a change of level.
+	transition_latency: Time,
+	///The multiplier of the frequency divisor of each level, from the fastest.
+	multipliers: Vec<Time>,
+	upper_threshold: f64,
+	lower_threshold: f64,
+	power_exponent: f64,
+	static_power: f64,
+	///Whether to apply DVFS to the output links.
+	pub links: bool,
+	///Whether to apply DVFS to the crossbar.
+	pub crossbar: bool,
+}
+
+impl DvfsPolicy
+{
+	pub fn new(cv:&ConfigurationValue) -> DvfsPolicy
+	{
+		let mut period=None;
+		let mut transition_latency=None;
+		let mut multipliers=None;
+		let mut upper_threshold=None;
+		let mut lower_threshold=None;
+		let mut power_exponent=3.0;
+		let mut static_power=0.0;
+		let mut links=true;
+		let mut crossbar=true;
+		match_object_panic!(cv,"Dvfs",value,
+			"period" => period=Some(value.as_time().expect("bad value for period")),
+			"transition_latency" => transition_latency=Some(value.as_time().expect("bad value for transition_latency")),
+			"multipliers" => multipliers=Some(value.as_array().expect("bad value for multipliers").iter()
+				.map(|v|v.as_time().expect("bad value in multipliers")).collect::<Vec<Time>>()),
+			"upper_threshold" => upper_threshold=Some(value.as_f64().expect("bad value for upper_threshold")),
+			"lower_threshold" => lower_threshold=Some(value.as_f64().expect("bad value for lower_threshold")),
+			"power_exponent" => power_exponent=value.as_f64().expect("bad value for power_exponent"),
+			"static_power" => static_power=value.as_f64().expect("bad value for static_power"),
+			"links" => links=value.as_bool().expect("bad value for links"),
+			"crossbar" => crossbar=value.as_bool().expect("bad value for crossbar"),
+		);
+		let period=period.expect("There were no period");
+		let transition_latency=transition_latency.expect("There were no transition_latency");
+		let multipliers=multipliers.expect("There were no multipliers");
+		let upper_threshold=upper_threshold.expect("There were no upper_threshold");
+		let lower_threshold=lower_threshold.expect("There were no lower_threshold");
+		if period==0
+		{
+			panic!("The period of Dvfs must be positive");
+		}
+		if multipliers.is_empty() || multipliers.contains(&0)
+		{
+			panic!("The multipliers of Dvfs must be a nonempty list of positive numbers");
+		}
+		if lower_threshold > upper_threshold
+		{
+			panic!("The lower_threshold ({}) of Dvfs is greater than its upper_threshold ({})",lower_threshold,upper_threshold);
+		}
+		DvfsPolicy{
+			period,
+			transition_latency,
+			multipliers,
+			upper_threshold,
+			lower_threshold,
+			power_exponent,
+			static_power,
+			links,
+			crossbar,
+		}
+	}
+	///The power relative to the nominal frequency when operating at the given level.
+	fn power(&self, level:usize) -> f64
+	{
+		let frequency = 1.0 / self.multipliers[level] as f64;
+		self.static_power + (1.0-self.static_power)*frequency.powf(self.power_exponent)
+	}
+}
+
+///The DVFS state of a single component, either a link or a crossbar.
+#[derive(Debug,Clone,Default)]
+pub struct DvfsState
+{
+	///The current level, as index into the multipliers of the policy.
+	level: usize,
+	///The first cycle of the current utilization window.
+	window_begin: Time,
+	///The slots used in the current window.
+	used_slots: usize,
+	///The component is halted until this cycle because of a change of level.
+	transition_end: Time,
+	///The first cycle included in the statistics.
+	statistics_begin: Time,
+	///The energy spent in the closed windows since `statistics_begin`.
+	energy: f64,
+	///The sum over the cycles of the closed windows since `statistics_begin` of the frequency multiplier.
+	multiplier_cycles: f64,
+	///The changes of level since `statistics_begin`.
+	transitions: usize,
+}
+
+impl DvfsState
+{
+	///The frequency divisor of the component at its current level, being `base` the nominal one.
+	pub fn divisor(&self, policy:&DvfsPolicy, base:Time) -> Time
+	{
+		base * policy.multipliers[self.level]
+	}
+	///Record that the component has used `slots` of its slots in this cycle.
+	pub fn notify_use(&mut self, slots:usize)
+	{
+		self.used_slots += slots;
+	}
+	///The cycle at which the component may operate again, if it is currently halted by a transition.
+	pub fn halted_until(&self, cycle:Time) -> Option<Time>
+	{
+		if cycle < self.transition_end { Some(self.transition_end) } else { None }
+	}
+	///Add the time from `begin` to `end` at the current level into the statistics.
+	fn accumulate(&mut self, policy:&DvfsPolicy, begin:Time, end:Time)
+	{
+		let begin = begin.max(self.statistics_begin);
+		if end > begin
+		{
+			let cycles = (end-begin) as f64;
+			self.energy += policy.power(self.level) * cycles;
+			self.multiplier_cycles += policy.multipliers[self.level] as f64 * cycles;
+		}
+	}
+	/**
+	Close the windows ended before `cycle`, changing the level according to the utilization of each one.
+	`base` is the nominal frequency divisor and `width` the slots of the component in each of its cycles, as the ports of a crossbar.
+	Returns whether the level has changed.
+	The windows have fixed boundaries, so the result does not depend on how often this is called, as long as the uses are notified after updating to their cycle.
+	**/
+	pub fn update(&mut self, policy:&DvfsPolicy, cycle:Time, base:Time, width:usize) -> bool
+	{
+		let initial_level = self.level;
+		while self.window_begin + policy.period <= cycle
+		{
+			let window_end = self.window_begin + policy.period;
+			self.accumulate(policy,self.window_begin,window_end);
+			let capacity = policy.period as f64 / self.divisor(policy,base) as f64 * width as f64;
+			let utilization = self.used_slots as f64 / capacity;
+			let new_level = if utilization > policy.upper_threshold && self.level > 0
+			{
+				self.level-1
+			}
+			else if utilization < policy.lower_threshold && self.level+1 < policy.multipliers.len()
+			{
+				self.level+1
+			}
+			else
+			{
+				self.level
+			};
+			if new_level != self.level
+			{
+				self.level = new_level;
+				self.transition_end = window_end + policy.transition_latency;
+				if window_end >= self.statistics_begin
+				{
+					self.transitions += 1;
+				}
+			}
+			self.used_slots = 0;
+			self.window_begin = window_end;
+		}
+		self.level != initial_level
+	}
+	///Clears the statistics, that will begin at `next_cycle`.
+	pub fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.statistics_begin = next_cycle;
+		self.energy = 0.0;
+		self.multiplier_cycles = 0.0;
+		self.transitions = 0;
+	}
+	///The energy, the sum of the multipliers over the cycles, and the transitions from the beginning of the statistics until `cycle`.
+	fn statistics(&self, policy:&DvfsPolicy, cycle:Time, base:Time, width:usize) -> (f64,f64,usize)
+	{
+		let mut state = self.clone();
+		state.update(policy,cycle,base,width);
+		let window_begin = state.window_begin;
+		state.accumulate(policy,window_begin,cycle);
+		(state.energy,state.multiplier_cycles,state.transitions)
+	}
+}
+
+///The DVFS statistics aggregated over routers.
+///Before the last router the power and multiplier fields hold sums, which are divided by the number of components in the last one.
+#[derive(Debug,Clone,Default)]
+pub struct DvfsStatistics
+{
+	links: usize,
+	link_power: f64,
+	link_multiplier: f64,
+	link_transitions: usize,
+	crossbars: usize,
+	crossbar_power: f64,
+	crossbar_multiplier: f64,
+	crossbar_transitions: usize,
+}
+
+impl DvfsStatistics
+{
+	///Read the statistics aggregated by the previous routers.
+	pub fn from_config_value(cv:&ConfigurationValue) -> DvfsStatistics
+	{
+		let mut statistics = DvfsStatistics::default();
+		match_object_panic!(cv,"DvfsStatistics",value,
+			"links" => statistics.links=value.as_usize().expect("bad value for links"),
+			"average_link_power" => statistics.link_power=value.as_f64().expect("bad value for average_link_power"),
+			"average_link_multiplier" => statistics.link_multiplier=value.as_f64().expect("bad value for average_link_multiplier"),
+			"link_transitions" => statistics.link_transitions=value.as_usize().expect("bad value for link_transitions"),
+			"crossbars" => statistics.crossbars=value.as_usize().expect("bad value for crossbars"),
+			"average_crossbar_power" => statistics.crossbar_power=value.as_f64().expect("bad value for average_crossbar_power"),
+			"average_crossbar_multiplier" => statistics.crossbar_multiplier=value.as_f64().expect("bad value for average_crossbar_multiplier"),
+			"crossbar_transitions" => statistics.crossbar_transitions=value.as_usize().expect("bad value for crossbar_transitions"),
+		);
+		statistics
+	}
+	///Add a link whose statistics span `cycles` cycles.
+	pub fn add_link(&mut self, policy:&DvfsPolicy, state:&DvfsState, cycle:Time, base:Time)
+	{
+		let (energy,multiplier_cycles,transitions) = state.statistics(policy,cycle,base,1);
+		let cycles = (cycle - state.statistics_begin) as f64;
+		self.links += 1;
+		self.link_power += energy / cycles;
+		self.link_multiplier += multiplier_cycles / cycles;
+		self.link_transitions += transitions;
+	}
+	///Add a crossbar with `width` ports.
+	pub fn add_crossbar(&mut self, policy:&DvfsPolicy, state:&DvfsState, cycle:Time, base:Time, width:usize)
+	{
+		let (energy,multiplier_cycles,transitions) = state.statistics(policy,cycle,base,width);
+		let cycles = (cycle - state.statistics_begin) as f64;
+		self.crossbars += 1;
+		self.crossbar_power += energy / cycles;
+		self.crossbar_multiplier += multiplier_cycles / cycles;
+		self.crossbar_transitions += transitions;
+	}
+	///Build the object to be included in the router statistics. When `is_last` the sums are converted into averages.
+	pub fn to_config_value(&self, is_last:bool) -> ConfigurationValue
+	{
+		let average = |sum:f64,count:usize| if is_last && count>0 { sum / count as f64 } else { sum };
+		ConfigurationValue::Object(String::from("DvfsStatistics"),vec![
+			(String::from("links"),ConfigurationValue::Number(self.links as f64)),
+			(String::from("average_link_power"),ConfigurationValue::Number(average(self.link_power,self.links))),
+			(String::from("average_link_multiplier"),ConfigurationValue::Number(average(self.link_multiplier,self.links))),
+			(String::from("link_transitions"),ConfigurationValue::Number(self.link_transitions as f64)),
+			(String::from("crossbars"),ConfigurationValue::Number(self.crossbars as f64)),
+			(String::from("average_crossbar_power"),ConfigurationValue::Number(average(self.crossbar_power,self.crossbars))),
+			(String::from("average_crossbar_multiplier"),ConfigurationValue::Number(average(self.crossbar_multiplier,self.crossbars))),
+			(String::from("crossbar_transitions"),ConfigurationValue::Number(self.crossbar_transitions as f64)),
+		])
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	fn policy() -> DvfsPolicy
+	{
+		DvfsPolicy{
+			period: 100,
+			transition_latency: 10,
+			multipliers: vec![1,2,4],
+			upper_threshold: 0.6,
+			lower_threshold: 0.2,
+			power_exponent: 3.0,
+			static_power: 0.0,
+			links: true,
+			crossbar: true,
+		}
+	}
+	#[test]
+	fn idle_component_slows_down()
+	{
+		let policy = policy();
+		let mut state = DvfsState::default();
+		assert!(state.update(&policy,250,1,1));
+		//Two windows closed without use, reaching the slowest level.
+		assert_eq!(state.divisor(&policy,1),4);
+		assert_eq!(state.halted_until(205),Some(210));
+		assert_eq!(state.halted_until(210),None);
+		let (energy,multiplier_cycles,transitions) = state.statistics(&policy,300,1,1);
+		assert_eq!(transitions,2);
+		assert!((energy - (100.0 + 100.0/8.0 + 100.0/64.0)).abs() < 1e-9, "energy {}",energy);
+		assert!((multiplier_cycles - (100.0 + 200.0 + 400.0)).abs() < 1e-9);
+	}
+	#[test]
+	fn busy_component_speeds_up()
+	{
+		let policy = policy();
+		let mut state = DvfsState::default();
+		state.update(&policy,300,1,1);
+		assert_eq!(state.divisor(&policy,2),8);
+		//At divisor 4 a window has 25 slots; using 20 of them is above the upper threshold.
+		state.notify_use(20);
+		assert!(state.update(&policy,400,1,1));
+		assert_eq!(state.divisor(&policy,1),2);
+		//The statistics only count from their beginning.
+		state.reset_statistics(400);
+		let (energy,_,transitions) = state.statistics(&policy,450,1,1);
+		assert_eq!(transitions,0);
+		assert!((energy - 50.0/8.0).abs() < 1e-9, "energy {}",energy);
+	}
+}
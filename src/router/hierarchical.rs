@@ -0,0 +1,254 @@
+/*!
+
+Hierarchical, or tiled, crossbars for high-radix routers.
+
+A single crossbar with many ports is hard to build, so high-radix routers usually divide it into tiles, each a small sub-switch. The ports are grouped in
+blocks of `tile_size` consecutive ports, and the tiles form a matrix with a row for each block of input ports and a column for each block of output ports.
+Each input port broadcasts its phits through a row bus to the tiles of its row. The tile in the column of the requested output contains the sub-switch
+that forwards the phit through a column channel to the output port. The outputs merge the column channels from all the rows of their column.
+
+This is available as the `HierarchicalCrossbar` router, which is an [InputOutput](super::input_output::InputOutput) router whose crossbar is replaced by tiles.
+* Each tile has its own allocator, built from the `allocator` field, that only considers the requests between the inputs of its row and the outputs of its column.
+  The grants of the tiles are then combined, discarding those that conflict in the input or output virtual channel, as the tiles decide independently.
+* Each cycle of the crossbar, an input port can send at most `row_bus_phits` phits through its row bus and each tile can send at most `column_channel_phits` phits through the column channel to each of its outputs.
+  The phits held back by these limits stay in their input buffers until the next cycle of the crossbar.
+* Crossing the tiles adds `tile_delay` cycles to the `crossbar_delay`.
+* The `internal_speedup` makes the tiles operate that many times faster than the links, as if setting the `crossbar_frequency_divisor` to the `frequency_divisor` of the router divided by the `internal_speedup`.
+
+```ignore
+HierarchicalCrossbar{
+	//All the fields of InputOutput.
+	...
+	tile_size: 8,//number of input and output ports of each tile.
+	internal_speedup: 2,//Optional, defaults to 1.
+	row_bus_phits: 1,//Optional, defaults to 1.
+	column_channel_phits: 1,//Optional, defaults to 1.
+	tile_delay: 1,//Optional, defaults to 0.
+}
+```
+The router statistics then include a `hierarchy` object with the number of phits per cycle and router held back by the row buses and by the column channels, and the grants per cycle and router discarded when combining the tiles.
+
+*/
+
+use ::rand::rngs::StdRng;
+use ::rand::prelude::SliceRandom;
+
+use crate::allocator::{Allocator,AllocatorBuilderArgument,Request,VCARequest,new_allocator};
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::Plugs;
+
+///The tiles of a hierarchical crossbar, with their allocators and the usage of their internal links.
+pub struct TileHierarchy
+{
+	///Number of input and output ports of each tile.
+	tile_size: usize,
+	///Number of blocks of ports. The tiles are `blocks*blocks`.
+	blocks: usize,
+	///Multiplier of the frequency of the crossbar over the frequency of the links.
+	pub internal_speedup: Time,
+	///Phits that an input can send through its row bus in a cycle of the crossbar.
+	row_bus_phits: usize,
+	///Phits that a tile can send to each of its outputs in a cycle of the crossbar.
+	column_channel_phits: usize,
+	///Cycles added to the crossbar delay.
+	pub tile_delay: Time,
+	///The allocator of each tile, indexed by `row*blocks+column`.
+	allocators: Vec<Box<dyn Allocator>>,
+	///The phits sent by each input port in the current cycle of the crossbar.
+	row_bus_use: Vec<usize>,
+	///The phits sent through each column channel in the current cycle, indexed by `row*ports+output_port`.
+	column_channel_use: Vec<usize>,
+	///Accumulated statistics since the last reset.
+	statistics: HierarchyStatistics,
+}
+
+///The statistics of the tiles, summed over the routers.
+#[derive(Debug,Clone,Default)]
+pub struct HierarchyStatistics
+{
+	///Phits that could not move due to their row bus being at full use.
+	pub row_bus_stalls: f64,
+	///Phits that could not move due to their column channel being at full use.
+	pub column_channel_stalls: f64,
+	///Grants of the tiles discarded by conflicting with grants of other tiles.
+	pub discarded_grants: f64,
+}
+
+impl TileHierarchy
+{
+	///Build the tiles of a router with `ports` ports and `virtual_channels` virtual channels per port.
+	///The fields are taken from the configuration of the router. The allocator of each tile is built from `allocator`.
+	pub fn new(cv_pairs:&[(String,ConfigurationValue)], allocator:&ConfigurationValue, ports:usize, virtual_channels:usize, plugs:&Plugs, rng:&mut StdRng) -> TileHierarchy
+	{
+		let mut tile_size=None;
+		let mut internal_speedup=1;
+		let mut row_bus_phits=1;
+		let mut column_channel_phits=1;
+		let mut tile_delay=0;
+		for (name,value) in cv_pairs
+		{
+			match name.as_ref()
+			{
+				"tile_size" => tile_size=Some(value.as_usize().expect("bad value for tile_size")),
+				"internal_speedup" => internal_speedup=value.as_time().expect("bad value for internal_speedup"),
+				"row_bus_phits" => row_bus_phits=value.as_usize().expect("bad value for row_bus_phits"),
+				"column_channel_phits" => column_channel_phits=value.as_usize().expect("bad value for column_channel_phits"),
+				"tile_delay" => tile_delay=value.as_time().expect("bad value for tile_delay"),
+				_ => (),
+			}
+		}
+		let tile_size=tile_size.expect("There were no tile_size");
+		assert!(tile_size>0,"The tile_size of HierarchicalCrossbar must be positive");
+		assert!(internal_speedup>0,"The internal_speedup of HierarchicalCrossbar must be positive");
+		assert!(row_bus_phits>0 && column_channel_phits>0,"The internal links of HierarchicalCrossbar must be able to carry some phit");
+		let blocks = (ports+tile_size-1)/tile_size;
+		let allocators = (0..blocks*blocks).map(|_|new_allocator(AllocatorBuilderArgument{
+			cv:allocator,
+			num_clients:tile_size*virtual_channels,
+			num_resources:tile_size*virtual_channels,
+			plugs,
+			rng:&mut *rng,
+		})).collect();
+		TileHierarchy{
+			tile_size,
+			blocks,
+			internal_speedup,
+			row_bus_phits,
+			column_channel_phits,
+			tile_delay,
+			allocators,
+			row_bus_use: vec![0;ports],
+			column_channel_use: vec![0;blocks*ports],
+			statistics: HierarchyStatistics::default(),
+		}
+	}
+	///Whether the allocators of the tiles accept the label 0 as intransit priority.
+	pub fn support_intransit_priority(&self) -> bool
+	{
+		self.allocators.iter().all(|allocator|allocator.support_intransit_priority())
+	}
+	///Allocate the requests of a cycle of the crossbar. Each request goes to the allocator of its tile, and the grants of the different
+	///tiles are combined in random order, keeping the first grant of each input and output virtual channel.
+	pub fn allocate(&mut self, requests:&[VCARequest], virtual_channels:usize, rng:&mut StdRng) -> Vec<VCARequest>
+	{
+		let tile_size = self.tile_size;
+		for request in requests
+		{
+			let tile = (request.entry_port/tile_size)*self.blocks + request.requested_port/tile_size;
+			self.allocators[tile].add_request(Request::new(
+				(request.entry_port%tile_size)*virtual_channels+request.entry_vc,
+				(request.requested_port%tile_size)*virtual_channels+request.requested_vc,
+				if request.label<0 {None} else {Some(request.label as usize)},
+			).with_timestamp(request.timestamp));
+		}
+		let mut grants = vec![];
+		for (tile,allocator) in self.allocators.iter_mut().enumerate()
+		{
+			let row = tile/self.blocks;
+			let column = tile%self.blocks;
+			for granted in allocator.perform_allocation(rng)
+			{
+				let local = granted.to_port_request(virtual_channels);
+				grants.push(VCARequest{
+					entry_port: row*tile_size + local.entry_port,
+					requested_port: column*tile_size + local.requested_port,
+					..local
+				});
+			}
+		}
+		grants.shuffle(rng);
+		let mut result:Vec<VCARequest> = Vec::with_capacity(grants.len());
+		for grant in grants
+		{
+			let conflict = result.iter().any(|other|
+				(other.entry_port==grant.entry_port && other.entry_vc==grant.entry_vc)
+				|| (other.requested_port==grant.requested_port && other.requested_vc==grant.requested_vc)
+			);
+			if conflict
+			{
+				self.statistics.discarded_grants+=1.0;
+			}
+			else
+			{
+				result.push(grant);
+			}
+		}
+		result
+	}
+	///Begin a new cycle of the crossbar, with all the internal links free.
+	pub fn begin_cycle(&mut self)
+	{
+		self.row_bus_use.iter_mut().for_each(|x|*x=0);
+		self.column_channel_use.iter_mut().for_each(|x|*x=0);
+	}
+	///Try to reserve the row bus of `entry_port` and its column channel towards `exit_port` for a phit.
+	///Returns whether the phit can traverse the tiles in the current cycle of the crossbar.
+	pub fn reserve(&mut self, entry_port:usize, exit_port:usize) -> bool
+	{
+		let ports = self.row_bus_use.len();
+		let channel = (entry_port/self.tile_size)*ports + exit_port;
+		if self.row_bus_use[entry_port]>=self.row_bus_phits
+		{
+			self.statistics.row_bus_stalls+=1.0;
+			return false;
+		}
+		if self.column_channel_use[channel]>=self.column_channel_phits
+		{
+			self.statistics.column_channel_stalls+=1.0;
+			return false;
+		}
+		self.row_bus_use[entry_port]+=1;
+		self.column_channel_use[channel]+=1;
+		true
+	}
+	pub fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.statistics = HierarchyStatistics::default();
+		for allocator in self.allocators.iter_mut()
+		{
+			allocator.reset_statistics(next_cycle);
+		}
+	}
+	///Add the statistics of this router over `cycle_span` cycles to the accumulated ones.
+	pub fn add_statistics(&self, accumulated:&mut HierarchyStatistics, cycle_span:Time)
+	{
+		let span = cycle_span.max(1) as f64;
+		accumulated.row_bus_stalls += self.statistics.row_bus_stalls/span;
+		accumulated.column_channel_stalls += self.statistics.column_channel_stalls/span;
+		accumulated.discarded_grants += self.statistics.discarded_grants/span;
+	}
+}
+
+impl HierarchyStatistics
+{
+	pub fn from_config_value(value:&ConfigurationValue) -> HierarchyStatistics
+	{
+		let mut statistics = HierarchyStatistics::default();
+		if let ConfigurationValue::Object(_,pairs) = value
+		{
+			for (name,value) in pairs
+			{
+				let x = value.as_f64().expect("bad value in hierarchy statistics");
+				match name.as_ref()
+				{
+					"row_bus_stalls" => statistics.row_bus_stalls=x,
+					"column_channel_stalls" => statistics.column_channel_stalls=x,
+					"discarded_grants" => statistics.discarded_grants=x,
+					_ => panic!("Nothing to do with field {} in hierarchy statistics",name),
+				}
+			}
+		}
+		statistics
+	}
+	///Build the statistics object. In the last router the sums are averaged over the `total_routers`.
+	pub fn to_config_value(&self, is_last:bool, total_routers:usize) -> ConfigurationValue
+	{
+		let factor = if is_last { 1.0/total_routers as f64 } else { 1.0 };
+		ConfigurationValue::Object(String::from("Hierarchy"),vec![
+			(String::from("row_bus_stalls"),ConfigurationValue::Number(self.row_bus_stalls*factor)),
+			(String::from("column_channel_stalls"),ConfigurationValue::Number(self.column_channel_stalls*factor)),
+			(String::from("discarded_grants"),ConfigurationValue::Number(self.discarded_grants*factor)),
+		])
+	}
+}
@@ -2,28 +2,80 @@ use std::cell::RefCell;
 use std::rc::{Rc,Weak};
 use std::ops::Deref;
 use std::mem::size_of;
+use std::collections::BTreeMap;
 use ::rand::{Rng,rngs::StdRng};
-use super::{Router,AbstractTransmissionMechanism,TransmissionMechanismBuilderArgument,new_transmission_mechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage};
+use super::{Router,Switching,InputHead,BufferOccupancy,AbstractTransmissionMechanism,TransmissionMechanismBuilderArgument,new_transmission_mechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,WeightedTable};
 use crate::allocator::{Allocator,VCARequest,AllocatorBuilderArgument, new_allocator};
 use crate::config_parser::ConfigurationValue;
 use crate::router::RouterBuilderArgument;
 use crate::topology::{Location,Topology};
-use crate::routing::CandidateEgress;
-use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
+use crate::routing::{CandidateEgress,RoutingOccupancy};
+use crate::policies::{RequestInfo,VirtualChannelPolicy,try_new_virtual_channel_policy,VCPolicyBuilderArgument};
 use crate::event::{self,Event,Eventful,EventGeneration,CyclePosition,Time};
 use crate::{Phit,SimulationShared,SimulationMut};
 use crate::quantify::Quantifiable;
-use crate::match_object_panic;
+use crate::measures::{StallCause,StallCounts,StallTracker,PacketDecisions};
+use super::dvfs::{DvfsPolicy,DvfsState,DvfsStatistics};
+use super::hierarchical::{TileHierarchy,HierarchyStatistics};
+use crate::{match_object,error,source_location};
+use crate::error::{Error,SourceLocation};
 
 
-///Strategy for the arbitration of the output port.
+///Strategy for the arbitration of the output port among its virtual channels, selected with `output_scheduling`.
+///The packets already being transmitted are always preferred, and the ties are broken by the round-robin token.
 enum OutputArbiter
 {
-	#[allow(dead_code)]
+	///A random virtual channel.
 	Random,
+	///Round-robin among the virtual channels, as in FSIN. The default, as `output_scheduling: RoundRobin`.
 	Token{
 		port_token: Vec<usize>,
 	},
+	///The phit that entered the output buffers first, as `output_scheduling: Fifo`.
+	Fifo{
+		port_token: Vec<usize>,
+	},
+	///The phit whose packet entered the network first, as `output_scheduling: OldestFirst`.
+	OldestFirst{
+		port_token: Vec<usize>,
+	},
+	///The phit with the lowest label given by the routing when crossing the crossbar, as `output_scheduling: LabelPriority`.
+	LabelPriority{
+		port_token: Vec<usize>,
+	},
+	///A weighted round-robin among the virtual channels, as `output_scheduling: WeightedTable{weights:[...]}`.
+	WeightedTable(WeightedTable),
+}
+
+impl OutputArbiter
+{
+	fn new(cv:&ConfigurationValue, ports:usize) -> Result<OutputArbiter,Error>
+	{
+		let port_token = vec![0;ports];
+		Ok(match cv
+		{
+			ConfigurationValue::Object(name,_) => match name.as_ref()
+			{
+				"Random" => OutputArbiter::Random,
+				"RoundRobin" => OutputArbiter::Token{port_token},
+				"Fifo" => OutputArbiter::Fifo{port_token},
+				"OldestFirst" => OutputArbiter::OldestFirst{port_token},
+				"LabelPriority" => OutputArbiter::LabelPriority{port_token},
+				"WeightedTable" => OutputArbiter::WeightedTable(WeightedTable::new(cv,ports)?),
+				_ => return Err(cv.ill(&format!("Unknown output_scheduling {}",name))),
+			},
+			_ => return Err(cv.ill("bad value for output_scheduling")),
+		})
+	}
+	///The token of the ports, for the arbiters based on it.
+	fn port_token(&mut self) -> Option<&mut Vec<usize>>
+	{
+		match self
+		{
+			OutputArbiter::Random | OutputArbiter::WeightedTable(_) => None,
+			OutputArbiter::Token{port_token} | OutputArbiter::Fifo{port_token} | OutputArbiter::OldestFirst{port_token} | OutputArbiter::LabelPriority{port_token} => Some(port_token),
+		}
+	}
 }
 pub struct InputOutput
 {
@@ -41,6 +93,8 @@ pub struct InputOutput
 	bubble: bool,
 	///Credits required in the next router's virtual port to begin the transmission
 	flit_size: usize,
+	///The switching technique. The `flit_size` already accounts for it, except for the wait of store-and-forward to receive the whole packet.
+	switching: Switching,
 	///Size of each input buffer.
 	buffer_size: usize,
 	///Delay in cycles to traverse the crossbar. In pipeline.
@@ -59,14 +113,18 @@ pub struct InputOutput
 	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
 	/// `reception_port_space[port] = space`
 	reception_port_space: Vec<Box<dyn SpaceAtReceptor>>,
-	/// The server to router mechanism employed.
+	/// `from_server_mechanisms[port]` is the server to router mechanism employed in the port, if it goes to a server.
 	/// This will be used to build the status at the servers.
-	from_server_mechanism: Box<dyn AbstractTransmissionMechanism>,
+	from_server_mechanisms: Vec<Option<Box<dyn AbstractTransmissionMechanism>>>,
+	///The amount of virtual channels of each port. See `link_class_virtual_channels` in [new_router](super::new_router).
+	port_virtual_channels: Vec<usize>,
+	///The greatest amount of virtual channels of a port. The structures indexed by virtual channel are allocated with this size.
+	virtual_channels: usize,
 	///if greater than 0 then the size of each of them, else BAD!
 	output_buffer_size: usize,
 	///The outut buffers indexed as `[output_port][output_vc]`.
-	///Phits are stored with their `(entry_port,entry_vc)`.
-	output_buffers: Vec<Vec<AugmentedBuffer<(usize,usize)>>>,
+	///Phits are stored with their `(entry_port,entry_vc,label,cycle)`, with the label of the request granted by the allocator and the cycle they entered the buffer.
+	output_buffers: Vec<Vec<AugmentedBuffer<(usize,usize,i32,Time)>>>,
 	///Number of phits currently being traversing the crossbar towards the output buffer.
 	///Specifically, `output_buffer_phits_traversing_crossbar[output_port][output_vc]` counts phits that are going to be inserted
 	///into `output_buffers[output_port][output_vc]` at some point.
@@ -78,15 +136,32 @@ pub struct InputOutput
 	///If not None then all the phits should go through this port+virtual_channel or stored in this output buffer, since they are part of the same packet
 	///We keep the packet for debugging/check considerations.
 	selected_output: Vec<Vec<Option<(usize,usize)>>>,
+	///The label of the request granted for each output port+virtual_channel in `selected_input`.
+	selected_label: Vec<Vec<i32>>,
 	///Number of cycles that the current phit, if any, in the head of a given (port,virtual channel) input buffer the phit has been waiting.
 	time_at_input_head: Vec<Vec<usize>>,
 	///An arbiter of the physical output port.
 	output_arbiter: OutputArbiter,
+	///If set, the maximum number of phits that each output port may receive, and each input port may send, through the crossbar in each of its cycles.
+	crossbar_speedup: Option<usize>,
 	///The maximum packet size that is allowed. Only for bubble consideration, that reserves space for a given packet plus maximum packet size.
 	maximum_packet_size: usize,
 	///Divisor of the cycles in which the crossbar operates.
-	///Without other overrides, the quotient `general_frequency_divisor/crossbar_frequency_divisor` is the internal speedup.
+	///Without other overrides, the quotient `frequency_divisor/crossbar_frequency_divisor` is the internal speedup, with `frequency_divisor` the one of the router.
+	///When using DVFS it is the divisor of the current level of the crossbar.
 	crossbar_frequency_divisor: Time,
+	///The nominal `crossbar_frequency_divisor`, before the scaling by DVFS.
+	base_crossbar_frequency_divisor: Time,
+	///The dynamic voltage and frequency scaling, if any. See [dvfs](super::dvfs).
+	dvfs: Option<DvfsPolicy>,
+	///The DVFS state of the crossbar.
+	crossbar_dvfs: Option<DvfsState>,
+	///The DVFS state of each output link. Empty when not scaling the links.
+	link_dvfs: Vec<DvfsState>,
+	///The nominal frequency divisor of the link of each output port. Filled when building the `output_schedulers`.
+	link_base_frequency_divisors: Vec<Time>,
+	///The tiles replacing the crossbar in a `HierarchicalCrossbar`. See [hierarchical](super::hierarchical).
+	hierarchy: Option<TileHierarchy>,
 
 	///Metrics
 	buffer_speed_metric: Option<Vec<Vec<TimeSegmentMetric>>>,
@@ -105,6 +180,8 @@ pub struct InputOutput
 	statistics_output_buffer_occupation_per_vc: Vec<f64>,
 	///Accumulated over time, averaged per port.
 	statistics_reception_space_occupation_per_vc: Vec<f64>,
+	///The causes for which the phits at the head of the input buffers are stalled.
+	stall_tracker: StallTracker,
 }
 
 impl Router for InputOutput
@@ -141,7 +218,7 @@ impl Router for InputOutput
 	fn num_virtual_channels(&self) -> usize
 	{
 		//self.virtual_ports[0].len()
-		self.transmission_port_status[0].num_virtual_channels()
+		self.virtual_channels
 	}
 	fn virtual_port_size(&self, _port:usize, _virtual_channel:usize) -> usize
 	{
@@ -180,6 +257,8 @@ impl Router for InputOutput
 		let cycle_span = cycle - self.statistics_begin_cycle;
 		let mut reception_space_occupation_per_vc:Option<Vec<f64>> = Some(self.statistics_reception_space_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
 		let mut output_buffer_occupation_per_vc:Option<Vec<f64>> = Some(self.statistics_output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
+		let mut dvfs_statistics:Option<DvfsStatistics> = None;
+		let mut hierarchy_statistics:Option<HierarchyStatistics> = None;
 		if let Some(previous)=statistics
 		{
 			if let ConfigurationValue::Object(cv_name,previous_pairs) = previous
@@ -242,6 +321,8 @@ impl Router for InputOutput
 							}
 							_ => panic!("bad value for average_output_buffer_occupation_per_vc"),
 						},
+						"dvfs" => dvfs_statistics = Some(DvfsStatistics::from_config_value(value)),
+						"hierarchy" => hierarchy_statistics = Some(HierarchyStatistics::from_config_value(value)),
 						_ => panic!("Nothing to do with field {} in InputOutput statistics",name),
 					}
 				}
@@ -288,6 +369,25 @@ impl Router for InputOutput
 			}
 			result_content.push((String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(content.iter().map(|x|ConfigurationValue::Number(*x)).collect())));
 		}
+		if let Some(ref policy) = self.dvfs
+		{
+			let mut content = dvfs_statistics.unwrap_or_default();
+			if let Some(ref state) = self.crossbar_dvfs
+			{
+				content.add_crossbar(policy,state,cycle,self.base_crossbar_frequency_divisor,self.output_buffers.len());
+			}
+			for (state,base) in self.link_dvfs.iter().zip(self.link_base_frequency_divisors.iter())
+			{
+				content.add_link(policy,state,cycle,*base);
+			}
+			result_content.push((String::from("dvfs"),content.to_config_value(is_last)));
+		}
+		if let Some(ref hierarchy) = self.hierarchy
+		{
+			let mut content = hierarchy_statistics.unwrap_or_default();
+			hierarchy.add_statistics(&mut content,cycle_span);
+			result_content.push((String::from("hierarchy"),content.to_config_value(is_last,total_routers)));
+		}
 		Some(ConfigurationValue::Object(String::from("InputOutput"),result_content))
 	}
 
@@ -302,24 +402,73 @@ impl Router for InputOutput
 		{
 			*x=0f64;
 		}
+		self.stall_tracker.counts=StallCounts::default();
+		for state in self.crossbar_dvfs.iter_mut().chain(self.link_dvfs.iter_mut())
+		{
+			state.reset_statistics(next_cycle);
+		}
+		if let Some(hierarchy) = self.hierarchy.as_mut()
+		{
+			hierarchy.reset_statistics(next_cycle);
+		}
+		self.crossbar_allocator.reset_statistics(next_cycle);
+		for policy in self.virtual_channel_policies.iter_mut()
+		{
+			policy.reset_statistics(next_cycle);
+		}
 	}
 	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>
 	{
 		if let (Location::ServerPort(_server),_link_class)=topology.neighbour(self.router_index,port)
 		{
-			self.from_server_mechanism.new_status_at_emissor()
+			self.from_server_mechanisms[port].as_ref().expect("there is no mechanism from the server").new_status_at_emissor()
 		}
 		else
 		{
 			unimplemented!()
 		}
 	}
+	fn stall_counts(&self) -> Option<StallCounts>
+	{
+		Some(self.stall_tracker.counts.clone())
+	}
+	fn input_heads(&self) -> Option<Vec<InputHead>>
+	{
+		Some(self.reception_port_space.iter().enumerate().flat_map(|(port,space)|space.front_iter().map(move |phit|(port,phit))).map(|(port,phit)|{
+			let virtual_channel = phit.virtual_channel.borrow().expect("it should have an associated virtual channel");
+			InputHead{
+				port,
+				virtual_channel,
+				selected_output: self.selected_output[port][virtual_channel],
+				phit,
+			}
+		}).collect())
+	}
+	fn port_occupancy(&self) -> Option<Vec<f64>>
+	{
+		Some(self.transmission_port_status.iter().enumerate().map(|(port,status)|{
+			(0..self.port_virtual_channels[port]).map(|vc|{
+				//When the neighbour is a server it may have more space than the buffer size.
+				let neighbour = self.buffer_size.saturating_sub(status.known_available_space_for_virtual_channel(vc).unwrap_or(self.buffer_size));
+				let output = self.output_buffers[port][vc].len() + self.output_buffer_phits_traversing_crossbar[port][vc];
+				(neighbour + output) as f64
+			}).sum()
+		}).collect())
+	}
+	fn buffer_occupancy(&self) -> Option<BufferOccupancy>
+	{
+		let input = self.reception_port_space.iter().enumerate().map(|(port,space)|
+			(0..self.port_virtual_channels[port]).map(|vc|space.occupied_dedicated_space(vc).unwrap_or(0)).collect()
+		).collect();
+		let output = self.output_buffers.iter().map(|port|port.iter().map(|buffer|buffer.len()).collect()).collect();
+		Some(BufferOccupancy{input,output})
+	}
 }
 
 
 impl InputOutput
 {
-	pub fn new(arg:RouterBuilderArgument) -> Rc<RefCell<InputOutput>>
+	pub fn new(arg:RouterBuilderArgument) -> Result<Rc<RefCell<InputOutput>>,Error>
 	{
 		let RouterBuilderArgument{
 			router_index,
@@ -327,7 +476,7 @@ impl InputOutput
 			plugs,
 			topology,
 			maximum_packet_size,
-			general_frequency_divisor,
+			frequency_divisor,
 			..
 		} = arg;
 		//let mut servers=None;
@@ -339,29 +488,36 @@ impl InputOutput
 		let mut virtual_channel_policies=None;
 		let mut bubble=None;
 		let mut flit_size=None;
+		let mut switching=None;
 		let mut intransit_priority=None;
 		let mut allow_request_busy_port=None;
 //		let mut output_priorize_lowest_label=None;
 		let mut output_buffer_size=None;
 		let mut allocator_value=None;
 		let mut transmission_mechanism=None;
+		let mut link_class_mechanisms:BTreeMap<usize,String>=BTreeMap::new();
+		let mut link_class_virtual_channels:Vec<usize>=vec![];
 		let mut to_server_mechanism=None;
 		let mut from_server_mechanism=None;
 		let mut crossbar_delay: Time =0;
 		let mut neglect_busy_output = false;
-		let mut crossbar_frequency_divisor = general_frequency_divisor;
+		let mut crossbar_frequency_divisor = frequency_divisor;
 		let mut time_segment_metric_buffer_rate = None;
+		let mut dvfs = None;
+		let mut output_scheduling = None;
+		let mut crossbar_speedup = None;
+		let hierarchical = matches!(cv,ConfigurationValue::Object(name,_) if name=="HierarchicalCrossbar");
 
-		match_object_panic!(cv,["InputOutput","InputOutputMonocycle"],value,
+		match_object!(cv,["InputOutput","InputOutputMonocycle","HierarchicalCrossbar"],value,
 			"virtual_channels" => match value
 			{
 				&ConfigurationValue::Number(f) => virtual_channels=Some(f as usize),
-				_ => panic!("bad value for virtual_channels"),
+				_ => return Err(value.ill("bad value for virtual_channels")),
 			},
 			"injection_buffers" => match value
 			{
 				&ConfigurationValue::Number(f) => injection_buffers=Some(f as usize),
-				_ => panic!("bad value for injection_buffers"),
+				_ => return Err(value.ill("bad value for injection_buffers")),
 			},
 			//"routing" => routing=Some(new_routing(value)),
 			//"virtual_channel_policy" => virtual_channel_policy=Some(new_virtual_channel_policy(value)),
@@ -369,45 +525,46 @@ impl InputOutput
 			{
 				//&ConfigurationValue::Array(ref a) => virtual_channel_policies=Some(a.iter().map(|cv|new_virtual_channel_policy(cv,plugs)).collect()),
 				&ConfigurationValue::Array(ref a) => virtual_channel_policies=Some(a.iter().map(
-					|cv|new_virtual_channel_policy(VCPolicyBuilderArgument{
+					|cv|try_new_virtual_channel_policy(VCPolicyBuilderArgument{
 					cv,
 					plugs
-				})).collect()),
-				_ => panic!("bad value for permute"),
+				})).collect::<Result<_,_>>()?),
+				_ => return Err(value.ill("bad value for permute")),
 			}
-			"crossbar_delay" | "delay" => crossbar_delay = value.as_time().expect("bad value for crossbar_delay"),
+			"crossbar_delay" | "delay" => crossbar_delay = value.as_time()?,
 			"buffer_size" => match value
 			{
 				&ConfigurationValue::Number(f) => buffer_size=Some(f as usize),
-				_ => panic!("bad value for buffer_size"),
+				_ => return Err(value.ill("bad value for buffer_size")),
 			},
 			"output_buffer_size" => match value
 			{
 				&ConfigurationValue::Number(f) => output_buffer_size=Some(f as usize),
-				_ => panic!("bad value for buffer_size"),
+				_ => return Err(value.ill("bad value for buffer_size")),
 			},
 			"bubble" => match value
 			{
 				&ConfigurationValue::True => bubble=Some(true),
 				&ConfigurationValue::False => bubble=Some(false),
-				_ => panic!("bad value for bubble"),
+				_ => return Err(value.ill("bad value for bubble")),
 			},
 			"flit_size" => match value
 			{
 				&ConfigurationValue::Number(f) => flit_size=Some(f as usize),
-				_ => panic!("bad value for flit_size"),
+				_ => return Err(value.ill("bad value for flit_size")),
 			},
+			"switching" => switching=Some(Switching::new(value)),
 			"intransit_priority" => match value
 			{
 				&ConfigurationValue::True => intransit_priority=Some(true),
 				&ConfigurationValue::False => intransit_priority=Some(false),
-				_ => panic!("bad value for intransit_priority"),
+				_ => return Err(value.ill("bad value for intransit_priority")),
 			},
 			"allow_request_busy_port" => match value
 			{
 				&ConfigurationValue::True => allow_request_busy_port=Some(true),
 				&ConfigurationValue::False => allow_request_busy_port=Some(false),
-				_ => panic!("bad value for allow_request_busy_port"),
+				_ => return Err(value.ill("bad value for allow_request_busy_port")),
 			},
 /*					"output_priorize_lowest_label" => match value
 			{
@@ -416,49 +573,64 @@ impl InputOutput
 				_ => panic!("bad value for output_priorize_lowest_label"),
 			};
 */
-			"neglect_busy_output" => neglect_busy_output = value.as_bool().expect("bad value for neglect_busy_output"),
+			"neglect_busy_output" => neglect_busy_output = value.as_bool()?,
 			"transmission_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => transmission_mechanism = Some(s.to_string()),
-				_ => panic!("bad value for transmission_mechanism"),
+				_ => return Err(value.ill("bad value for transmission_mechanism")),
 			},
+			"link_class_mechanisms" => link_class_mechanisms = super::link_class_mechanisms(value)?,
+			"link_class_virtual_channels" => link_class_virtual_channels = value.as_array()?.iter()
+				.map(|v|v.as_usize().expect("bad value in link_class_virtual_channels")).collect(),
 			"to_server_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => to_server_mechanism = Some(s.to_string()),
-				_ => panic!("bad value for to_server_mechanism"),
+				_ => return Err(value.ill("bad value for to_server_mechanism")),
 			},
 			"from_server_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => from_server_mechanism = Some(s.to_string()),
-				_ => panic!("bad value for from_server_mechanism"),
+				_ => return Err(value.ill("bad value for from_server_mechanism")),
 			},
-			"time_segment_metric_buffer_rate" => time_segment_metric_buffer_rate = Some(value.as_usize().expect("bad value for time_segment_metric_buffer_rate")),
+			"time_segment_metric_buffer_rate" => time_segment_metric_buffer_rate = Some(value.as_usize()?),
 			"allocator" => allocator_value=Some(value.clone()),
-			"crossbar_frequency_divisor" => crossbar_frequency_divisor = value.as_time().expect("bad value for crossbar_frequency_divisor"),
+			"crossbar_frequency_divisor" => crossbar_frequency_divisor = value.as_time()?,
+			"dvfs" => dvfs = Some(DvfsPolicy::new(value)),
+			"output_scheduling" => output_scheduling = Some(value.clone()),
+			"crossbar_speedup" => crossbar_speedup = Some(value.as_usize()?),
+			"tile_size" | "internal_speedup" | "row_bus_phits" | "column_channel_phits" | "tile_delay" => assert!(hierarchical,"The fields of the tiles are only available in HierarchicalCrossbar"),
 		);
 		//let sides=sides.expect("There were no sides");
-		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
-		let injection_buffers = if let Some(i)=injection_buffers
-		{
-			i
-		}
-		else
-		{
-			virtual_channels
-		};
+		let virtual_channels=virtual_channels.ok_or_else(||cv.ill("There were no virtual_channels"))?;
 
-		let virtual_channel_policies=virtual_channel_policies.expect("There were no virtual_channel_policies");
+		let virtual_channel_policies=virtual_channel_policies.ok_or_else(||cv.ill("There were no virtual_channel_policies"))?;
 		//let routing=routing.expect("There were no routing");
-		let buffer_size=buffer_size.expect("There were no buffer_size");
-		let output_buffer_size=output_buffer_size.expect("There were no output_buffer_size");
-		let bubble=bubble.expect("There were no bubble");
-		let flit_size=flit_size.expect("There were no flit_size");
-		let intransit_priority=intransit_priority.expect("There were no intransit_priority");
-		let allow_request_busy_port=allow_request_busy_port.expect("There were no allow_request_busy_port");
+		let buffer_size=buffer_size.ok_or_else(||cv.ill("There were no buffer_size"))?;
+		let output_buffer_size=output_buffer_size.ok_or_else(||cv.ill("There were no output_buffer_size"))?;
+		let bubble=bubble.ok_or_else(||cv.ill("There were no bubble"))?;
+		let (switching,flit_size)=Switching::from_fields(switching,flit_size,buffer_size,maximum_packet_size);
+		let intransit_priority=intransit_priority.ok_or_else(||cv.ill("There were no intransit_priority"))?;
+		let allow_request_busy_port=allow_request_busy_port.ok_or_else(||cv.ill("There were no allow_request_busy_port"))?;
 //		let output_priorize_lowest_label=output_priorize_lowest_label.expect("There were no output_priorize_lowest_label");
 		let input_ports=topology.ports(router_index);
+		let port_virtual_channels = super::port_virtual_channels(topology,router_index,virtual_channels,&link_class_virtual_channels);
+		let virtual_channels = port_virtual_channels.iter().copied().max().unwrap_or(virtual_channels);
+		let allocator_value = allocator_value.ok_or_else(||cv.ill("There were no allocator"))?;
+		let output_arbiter = output_scheduling.map(|cv|OutputArbiter::new(&cv,input_ports)).transpose()?.unwrap_or_else(||OutputArbiter::Token{port_token: vec![0;input_ports]});
+		assert!(crossbar_speedup!=Some(0),"The crossbar_speedup must be positive");
+		let hierarchy = match cv
+		{
+			ConfigurationValue::Object(_,cv_pairs) if hierarchical => Some(TileHierarchy::new(cv_pairs,&allocator_value,input_ports,virtual_channels,plugs,arg.rng)),
+			_ => None,
+		};
+		if let Some(ref hierarchy) = hierarchy
+		{
+			assert!(frequency_divisor%hierarchy.internal_speedup==0,"The internal_speedup ({}) must divide the frequency_divisor of the router ({})",hierarchy.internal_speedup,frequency_divisor);
+			crossbar_frequency_divisor = frequency_divisor/hierarchy.internal_speedup;
+			crossbar_delay += hierarchy.tile_delay;
+		}
 		let allocator = new_allocator(AllocatorBuilderArgument{
-			cv:&allocator_value.expect("There were no allocator"),
+			cv:&allocator_value,
 			num_clients:input_ports * virtual_channels,
 			num_resources:input_ports * virtual_channels,
 			plugs,
@@ -470,6 +642,7 @@ impl InputOutput
 		let selected_output=(0..input_ports).map(|_|
 			(0..virtual_channels).map(|_|None).collect()
 		).collect();
+		let selected_label=vec![ vec![ 0 ; virtual_channels ] ; input_ports ];
 		let time_at_input_head=(0..input_ports).map(|_|
 			(0..virtual_channels).map(|_|0).collect()
 		).collect();
@@ -479,32 +652,44 @@ impl InputOutput
 		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
 		//let transmission_mechanism = super::SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
 		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size};
-		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,..transmission_builder_argument});
-		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
-		//let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
-		let from_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&from_server_mechanism,virtual_channels: injection_buffers,..transmission_builder_argument});
+		//The mechanism with the given name, built with the virtual channels of the port.
+		let port_mechanism = |name:&str,port:usize| new_transmission_mechanism(TransmissionMechanismBuilderArgument{name,virtual_channels:port_virtual_channels[port],..transmission_builder_argument});
+		//The name of the mechanism for a router-to-router link of class `link_class`.
+		let router_link_mechanism = |link_class:usize| link_class_mechanisms.get(&link_class).unwrap_or(&transmission_mechanism);
 		let transmission_port_status:Vec<Box<dyn StatusAtEmissor>> = (0..input_ports).map(|p|
 			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
 			{
-				to_server_mechanism.new_status_at_emissor()
+				port_mechanism(&to_server_mechanism,p).map(|mechanism|mechanism.new_status_at_emissor())
 			}
 			else
 			{
-				transmission_mechanism.new_status_at_emissor()
+				port_mechanism(router_link_mechanism(topology.neighbour(router_index,p).1),p).map(|mechanism|mechanism.new_status_at_emissor())
 			}
-		).collect();
-		let reception_port_space = (0..input_ports).map(|p|
+		).collect::<Result<_,_>>()?;
+		let from_server_mechanisms:Vec<_> = (0..input_ports).map(|p|
 			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
 			{
-				from_server_mechanism.new_space_at_receptor()
+				//let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
+				let virtual_channels = injection_buffers.unwrap_or(port_virtual_channels[p]);
+				new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&from_server_mechanism,virtual_channels,..transmission_builder_argument}).map(Some)
 			}
 			else
 			{
-				transmission_mechanism.new_space_at_receptor()
+				Ok(None)
 			}
-		).collect();
+		).collect::<Result<_,_>>()?;
+		let reception_port_space = (0..input_ports).map(|p|
+			if let Some(ref mechanism)=from_server_mechanisms[p]
+			{
+				Ok(mechanism.new_space_at_receptor())
+			}
+			else
+			{
+				port_mechanism(router_link_mechanism(topology.neighbour(router_index,p).1),p).map(|mechanism|mechanism.new_space_at_receptor())
+			}
+		).collect::<Result<_,_>>()?;
 		let output_buffers= if output_buffer_size==0 {
-			panic!("output_buffer_size must be greater than 0");
+			return Err(arg.cv.ill("output_buffer_size must be greater than 0"));
 		} else {
 			(0..input_ports).map(|_|
 				(0..virtual_channels).map(|_|AugmentedBuffer::new()).collect()
@@ -529,6 +714,7 @@ impl InputOutput
 			virtual_channel_policies,
 			bubble,
 			flit_size,
+			switching,
 			intransit_priority,
 			allow_request_busy_port,
 //			output_priorize_lowest_label,
@@ -537,26 +723,37 @@ impl InputOutput
 			crossbar_delay,
 			transmission_port_status,
 			reception_port_space,
-			from_server_mechanism,
+			from_server_mechanisms,
+			port_virtual_channels,
+			virtual_channels,
 			output_buffer_size,
 			output_buffers,
 			output_buffer_phits_traversing_crossbar,
 			output_schedulers: vec![],
 			selected_input,
 			selected_output,
+			selected_label,
 			time_at_input_head,
-			output_arbiter: OutputArbiter::Token{port_token: vec![0;input_ports]},
+			output_arbiter,
+			crossbar_speedup,
 			maximum_packet_size,
 			crossbar_frequency_divisor,
+			base_crossbar_frequency_divisor: crossbar_frequency_divisor,
+			crossbar_dvfs: dvfs.as_ref().filter(|policy|policy.crossbar).map(|_|DvfsState::default()),
+			link_dvfs: if dvfs.as_ref().map(|policy|policy.links).unwrap_or(false) { vec![DvfsState::default();input_ports] } else { vec![] },
+			link_base_frequency_divisors: vec![],
+			dvfs,
+			hierarchy,
 			buffer_speed_metric,
 			crossbar_allocator: allocator,
 			statistics_begin_cycle: 0,
 			statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
 			statistics_reception_space_occupation_per_vc: vec![0f64;virtual_channels],
+			stall_tracker: StallTracker::new(input_ports,virtual_channels),
 		}));
 		//r.borrow_mut().self_rc=r.downgrade();
 		r.borrow_mut().self_rc=Rc::<_>::downgrade(&r);
-		r
+		Ok(r)
 	}
 }
 
@@ -595,13 +792,30 @@ impl Eventful for InputOutput
 		{
 			self.output_schedulers = (0..self.output_buffers.len()).map(|exit_port|{
 				let (_location,link_class)=simulation.network.topology.neighbour(self.router_index,exit_port);
-				let link = simulation.link_classes[link_class].clone();
+				let mut link = simulation.link_classes[link_class].clone();
+				link.frequency_divisor = simulation.port_frequency_divisor(self.router_index,exit_port);
 				internal::TryLinkTraversalArgument{
 					router:self,
 					exit_port,
 					link,
 				}.into()
 			}).collect();
+			self.link_base_frequency_divisors = self.output_schedulers.iter().map(|scheduler|scheduler.borrow().base_frequency_divisor()).collect();
+		}
+		if let (Some(policy),Some(state)) = (self.dvfs.as_ref(),self.crossbar_dvfs.as_mut())
+		{
+			if state.update(policy,simulation.cycle,self.base_crossbar_frequency_divisor,self.output_buffers.len())
+			{
+				self.crossbar_frequency_divisor = state.divisor(policy,self.base_crossbar_frequency_divisor);
+			}
+			//Events scheduled before a change of level may not be aligned with the new divisor.
+			let resume = state.halted_until(simulation.cycle).unwrap_or(simulation.cycle);
+			let resume = event::round_to_multiple(resume,self.crossbar_frequency_divisor);
+			if resume > simulation.cycle
+			{
+				self.next_events.pop();//remove the event that was served.
+				return self.schedule(simulation.cycle,resume-simulation.cycle).into_iter().collect();
+			}
 		}
 		let mut cycles_span = 1;//cycles since last checked
 		if let Some(ref last)=self.last_process_at_cycle
@@ -641,6 +855,7 @@ impl Eventful for InputOutput
 				self.statistics_output_buffer_occupation_per_vc[vc]+=(buffer.len()*cycles_span as usize) as f64 / self.output_buffers.len() as f64;
 			}
 		}
+		self.stall_tracker.account(cycles_span);
 
 		//-- Precompute whatever polcies ask for.
 		let server_ports : Option<Vec<usize>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_server_ports())
@@ -692,10 +907,11 @@ impl Eventful for InputOutput
 		};
 		let port_average_neighbour_queue_length:Option<Vec<f32>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_port_average_queue_length())
 		{
-			Some(self.transmission_port_status.iter().map(|ref p|{
+			Some(self.transmission_port_status.iter().enumerate().map(|(port,p)|{
 				//let total=p.iter().map(|ref vp|self.buffer_size - vp.neighbour_credits).sum::<usize>();
 				//(total as f32) / (p.len() as f32)
-				let total=(0..amount_virtual_channels).map(|vc|{
+				let port_virtual_channels = self.port_virtual_channels[port];
+				let total=(0..port_virtual_channels).map(|vc|{
 					//self.buffer_size-p.known_available_space_for_virtual_channel(vc).expect("needs to know available space")
 					let available = p.known_available_space_for_virtual_channel(vc).expect("needs to know available space");
 					if available>self.buffer_size
@@ -709,7 +925,7 @@ impl Eventful for InputOutput
 						self.buffer_size - available
 					}
 				}).sum::<usize>();
-				(total as f32) / (amount_virtual_channels as f32)
+				(total as f32) / (port_virtual_channels as f32)
 			}).collect())
 		}
 		else
@@ -749,6 +965,12 @@ impl Eventful for InputOutput
 			).collect())
 		};
 
+		let routing_port_occupancy = if simulation.routing.need_occupancy() { self.port_occupancy() } else { None };
+		let routing_occupancy = RoutingOccupancy{
+			port_occupancy: routing_port_occupancy.as_ref(),
+			network_occupancy: simulation.network_occupancy.as_ref(),
+		};
+
 		//-- Routing and requests.
 		let mut undecided_channels=0;//just as indicator if the router has pending work.
 		let mut moved_input_phits=0;//another indicator of pending work.
@@ -766,14 +988,16 @@ impl Eventful for InputOutput
 					None =>
 					{
 						undecided_channels+=1;
-						let target_server=phit.packet.message.destination;
-						let (target_location,_link_class)=topology.server_neighbour(target_server);
-						let target_router=match target_location
+						let occupied = self.reception_port_space[entry_port].occupied_dedicated_space(entry_vc).unwrap_or(phit.packet.size);
+						if !self.switching.packet_ready(phit.packet.size,occupied)
 						{
-							Location::RouterPort{router_index,router_port:_} =>router_index,
-							_ => panic!("The server is not attached to a router"),
-						};
-						let routing_candidates=simulation.routing.next(phit.packet.routing_info.borrow().deref(),simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
+							//Store-and-forward waits until the whole packet is in the buffer.
+							self.stall_tracker.set(entry_port,entry_vc,StallCause::IncompletePacket);
+							continue;
+						}
+						let target_server=phit.packet.message.destination;
+						let target_router=topology.server_router_from(target_server,self.router_index);
+						let routing_candidates=simulation.routing.next_with_occupancy(phit.packet.routing_info.borrow().deref(),simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&routing_occupancy,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
 						let routing_idempotent = routing_candidates.idempotent;
 						if routing_candidates.len()==0
 						{
@@ -782,28 +1006,47 @@ impl Eventful for InputOutput
 								panic!("There are no choices for packet {:?} entry_port={} entry_vc={} in router {} towards server {}",phit.packet,entry_port,entry_vc,self.router_index,target_server);
 							}
 							//There are currently no good port choices, but there may be in the future.
+							self.stall_tracker.set(entry_port,entry_vc,StallCause::NoCandidates);
 							continue;
 						}
+						PacketDecisions::record_candidates(&phit.packet,&*self,topology,&routing_candidates.candidates,target_router,simulation.cycle);
+						//Whether some candidate is discarded because its output is being used by other phit.
+						let mut blocked_by_busy_output=false;
 						let mut good_ports=routing_candidates.into_iter().filter_map(|candidate|{
 							let CandidateEgress{port:f_port,virtual_channel:f_virtual_channel,..} = candidate;
+							if f_virtual_channel>=self.port_virtual_channels[f_port]
+							{
+								//The port does not have that virtual channel.
+								return None;
+							}
+							if !topology.is_link_alive(self.router_index,f_port)
+							{
+								//The link has failed, so the routing must offer other candidates.
+								return None;
+							}
 							//We analyze each candidate output port, considering whether they are in use (port or virtual channel).
 							match self.selected_input[f_port][f_virtual_channel]
 							{
 								// Keep these candidates until EnforceFlowControl, so policies have all information.
-								Some(_) => if self.neglect_busy_output {None} else {Some(CandidateEgress{router_allows:Some(false), ..candidate})},
+								Some(_) =>
+								{
+									blocked_by_busy_output=true;
+									if self.neglect_busy_output {None} else {Some(CandidateEgress{router_allows:Some(false), ..candidate})}
+								},
 								None =>
 								{
 									let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,f_port);
 									//if self.transmission_port_status[f_port].can_transmit(&phit,f_virtual_channel,transmit_auxiliar_info)
 									let allowed = if self.can_phit_advance(&phit,f_port,f_virtual_channel,bubble_in_use)
 									{
-										if self.allow_request_busy_port
+										if self.allow_request_busy_port || !busy_ports[f_port]
 										{
 											true
 										}
 										else
 										{
-											!busy_ports[f_port]
+											blocked_by_busy_output=true;
+											false
 										}
 									}
 									else
@@ -814,6 +1057,7 @@ impl Eventful for InputOutput
 								}
 							}
 						}).collect::<Vec<_>>();
+						let any_allowed=good_ports.iter().any(|candidate|candidate.router_allows==Some(true));
 						let performed_hops=phit.packet.routing_info.borrow().hops;
 						//Apply all the declared virtual channel policies in order.
 						let request_info=RequestInfo{
@@ -843,6 +1087,21 @@ impl Eventful for InputOutput
 						}
 						if good_ports.len()==0
 						{
+							let cause = if any_allowed
+							{
+								//The policies have discarded all the outputs that could be used.
+								StallCause::NoCandidates
+							}
+							else if blocked_by_busy_output
+							{
+								StallCause::LostAllocation
+							}
+							else
+							{
+								//The router checks the space in its output buffers. The lack of credits is seen by these buffers.
+								StallCause::OutputBufferFull
+							};
+							self.stall_tracker.set(entry_port,entry_vc,cause);
 							self.time_at_input_head[entry_port][entry_vc]+=1;
 							// if self.time_at_input_head[entry_port][entry_vc] > 25000
 							// {
@@ -859,17 +1118,24 @@ impl Eventful for InputOutput
 						//{
 						//	CandidateEgress{port,virtual_channel,label,estimated_remaining_hops:_,..}=>(port,virtual_channel,label),
 						//}
+						//It is cleared if the allocator grants some request and the phit advances in this cycle.
+						self.stall_tracker.set(entry_port,entry_vc,StallCause::LostAllocation);
 						for candidate in good_ports
 						{
 							simulation.routing.performed_request(&candidate,&phit.packet.routing_info,simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng);
 							let CandidateEgress{port:requested_port,virtual_channel:requested_vc,label,..} = candidate;
 //							if self.selected_input[requested_port][requested_vc].is_none()
 //							{
-								request.push( VCARequest{entry_port,entry_vc,requested_port,requested_vc,label});
+								request.push( VCARequest{entry_port,entry_vc,requested_port,requested_vc,label,timestamp:Some(*phit.packet.cycle_into_network.borrow())});
 //							}
 						}
 					},
-					Some((_port,_vc)) => (),//(port,vc,0),//FIXME: perhaps 0 changes into None?
+					Some((port,vc)) =>
+					{
+						//It is cleared if the phit advances in this cycle.
+						let cause = if self.can_phit_advance(&phit,port,vc,false) { StallCause::LostAllocation } else { StallCause::OutputBufferFull };
+						self.stall_tracker.set(entry_port,entry_vc,cause);
+					},//(port,vc,0),//FIXME: perhaps 0 changes into None?
 				};
 				//FIXME: this should not call known_available_space_for_virtual_channel
 				//In wormhole we may have a selected output but be unable to advance, but it is not clear whether makes any difference.
@@ -883,7 +1149,7 @@ impl Eventful for InputOutput
 					match self.selected_input[requested_port][requested_vc]
 					{
 						Some(_) => (),
-						None => request.push( VCARequest{entry_port,entry_vc,requested_port,requested_vc,label} ),
+						None => request.push( VCARequest{entry_port,entry_vc,requested_port,requested_vc,label,timestamp:None} ),
 					};
 				}*/
 				self.time_at_input_head[entry_port][entry_vc]+=1;
@@ -894,7 +1160,11 @@ impl Eventful for InputOutput
 		// Check if the allocator supports intransit priority.
 		if captured_intransit_priority {
 			// If the allocator supports intransit priority
-			if !self.crossbar_allocator.support_intransit_priority() {
+			let supported = match self.hierarchy {
+				Some(ref hierarchy) => hierarchy.support_intransit_priority(),
+				None => self.crossbar_allocator.support_intransit_priority(),
+			};
+			if !supported {
 				panic!("Current crossbar allocator does not support intransit priority option");
 			}
 
@@ -908,37 +1178,67 @@ impl Eventful for InputOutput
 			}).collect();
 		}
 
-		// Add all the requests to the allocator.
-		request.iter_mut().for_each(|pr| {
-			self.crossbar_allocator.add_request(pr.to_allocator_request(amount_virtual_channels));
-		});
-
-		// Perform the allocation
 		let mut requests_granted : Vec<VCARequest> = Vec::new();
-		for gr in self.crossbar_allocator.perform_allocation(&mut mutable.rng) {
-			// convert from allocator Request to VCARequest
-			requests_granted.push(gr.to_port_request(amount_virtual_channels));
+		if let Some(ref mut hierarchy) = self.hierarchy {
+			// Each tile allocates its own requests.
+			requests_granted = hierarchy.allocate(&request,amount_virtual_channels,&mut mutable.rng);
+			hierarchy.begin_cycle();
+		} else {
+			// Add all the requests to the allocator.
+			request.iter_mut().for_each(|pr| {
+				self.crossbar_allocator.add_request(pr.to_allocator_request(amount_virtual_channels));
+			});
+
+			// Perform the allocation
+			for gr in self.crossbar_allocator.perform_allocation(&mut mutable.rng) {
+				// convert from allocator Request to VCARequest
+				requests_granted.push(gr.to_port_request(amount_virtual_channels));
+			}
 		}
 	
 		let request_it = requests_granted.into_iter();
 
 		//Complete the arbitration of the requests by writing the selected_input of the output virtual ports.
 		//let request=request_sequence.concat();
-		for VCARequest{entry_port,entry_vc,requested_port,requested_vc,..} in request_it
+		for VCARequest{entry_port,entry_vc,requested_port,requested_vc,label,..} in request_it
 		{
 			self.selected_input[requested_port][requested_vc]=Some((entry_port,entry_vc));
+			self.selected_label[requested_port][requested_vc]=label;
 			self.selected_output[entry_port][entry_vc]=Some((requested_port,requested_vc));
 		}
 
 		//-- For each output port decide which input actually uses it this cycle.
 		let mut events=vec![];
-		for exit_port in 0..self.transmission_port_status.len()
+		let num_ports = self.transmission_port_status.len();
+		//With tiles the internal links may not be enough for all the selected inputs, so the first port to be considered rotates.
+		let first_port = if self.hierarchy.is_some() { (simulation.cycle/self.crossbar_frequency_divisor) as usize % num_ports } else { 0 };
+		//The phits sent from each input port and received at each output port in this cycle of the crossbar, to apply the `crossbar_speedup`.
+		let mut input_crossings = vec![0;num_ports];
+		let mut output_crossings = vec![0;num_ports];
+		for exit_port in (0..num_ports).map(|offset|(first_port+offset)%num_ports)
 		{
 			let nvc=amount_virtual_channels;
 			for exit_vc in 0..nvc
 			{
 				if let Some((entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
 				{
+					if let Some(speedup) = self.crossbar_speedup
+					{
+						if input_crossings[entry_port]>=speedup || output_crossings[exit_port]>=speedup
+						{
+							//The crossbar cannot carry more phits from that input or into that output in this cycle.
+							continue;
+						}
+					}
+					if let Some(ref mut hierarchy) = self.hierarchy
+					{
+						let has_phit = self.reception_port_space[entry_port].front_virtual_channel(entry_vc).is_some();
+						if has_phit && !hierarchy.reserve(entry_port,exit_port)
+						{
+							//The row bus or the column channel are in use. Try in the next cycle of the crossbar.
+							continue;
+						}
+					}
 					//-- Move phits into the internal output space
 					//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
 					if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
@@ -949,15 +1249,18 @@ impl Eventful for InputOutput
 							panic!("Trying to move into a full output buffer.");
 						}
 						moved_input_phits+=1;
+						input_crossings[entry_port]+=1;
+						output_crossings[exit_port]+=1;
 						self.time_at_input_head[entry_port][entry_vc]=0;
+						self.stall_tracker.clear(entry_port,entry_vc);
 						*phit.virtual_channel.borrow_mut()=Some(exit_vc);
 						if let Some(message)=ack_message
 						{
 							// If the crossbar operates at higher frequency (aka internal speedup) then it would send acks at greater rate than allowed.
 							// We allow sending several events in the same cycle of the link. Acks should have few bits and be possible to be aggregated.
-							let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
-							let event = Event::Acknowledge{location:previous_location,message};
-							events.push(simulation.schedule_link_arrival( previous_link_class, event ));
+							let (previous_location,_previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+							let event = Event::Acknowledge{location:previous_location,receptor:Location::RouterPort{router_index:self.router_index,router_port:entry_port},message};
+							events.push(simulation.schedule_port_arrival( self.router_index, entry_port, event ));
 						}
 						if phit.is_end()
 						{
@@ -969,15 +1272,18 @@ impl Eventful for InputOutput
 							self.selected_output[entry_port][entry_vc]=Some((exit_port,exit_vc));
 						}
 						if self.crossbar_delay==0 {
-							self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
+							let label=self.selected_label[exit_port][exit_vc];
+							self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc,label,simulation.cycle));
 							let mut output_scheduler = self.output_schedulers[exit_port].borrow_mut();
 							if let Some(event) = output_scheduler.schedule(simulation.cycle,0) {
 								events.push(event);
 							}
 						} else {
+							let label=self.selected_label[exit_port][exit_vc];
 							let event = Rc::<RefCell<internal::PhitToOutput>>::from(internal::PhitToOutputArgument{
 								//router: self.self_rc.upgrade().unwrap(),
 								router: self,
+								label,
 								exit_port,
 								exit_vc,
 								entry_port,
@@ -1003,6 +1309,10 @@ impl Eventful for InputOutput
 				}
 			}
 		}
+		if let Some(state) = self.crossbar_dvfs.as_mut()
+		{
+			state.notify_use(moved_input_phits);
+		}
 		self.next_events.pop();//remove the event that was served.
 		//TODO: what to do with probabilistic requests???
 		//if undecided_channels>0 || moved_phits>0 || events.len()>0 || request.len()>0
@@ -1128,6 +1438,8 @@ mod internal
 		exit_vc: usize,
 		entry_port: usize,
 		entry_vc: usize,
+		///The label of the granted request.
+		label: i32,
 		phit: Rc<Phit>,
 	}
 	pub struct PhitToOutputArgument<'a>
@@ -1137,6 +1449,7 @@ mod internal
 		pub exit_vc: usize,
 		pub entry_port: usize,
 		pub entry_vc: usize,
+		pub label: i32,
 		pub phit: Rc<Phit>,
 	}
 	impl<'a> From<PhitToOutputArgument<'a>> for Rc<RefCell<PhitToOutput>>
@@ -1152,6 +1465,7 @@ mod internal
 				exit_vc: arg.exit_vc,
 				entry_port: arg.entry_port,
 				entry_vc: arg.entry_vc,
+				label: arg.label,
 				phit: arg.phit,
 			}));
 			event.borrow_mut().self_rc=Rc::<_>::downgrade(&event);
@@ -1168,7 +1482,7 @@ mod internal
 				panic!("(PhitToOutput) Trying to move into a full output buffer.");
 			}
 			router.output_buffer_phits_traversing_crossbar[self.exit_port][self.exit_vc]-=1;
-			router.output_buffers[self.exit_port][self.exit_vc].push(self.phit.clone(),(self.entry_port,self.entry_vc));
+			router.output_buffers[self.exit_port][self.exit_vc].push(self.phit.clone(),(self.entry_port,self.entry_vc,self.label,simulation.cycle));
 			let mut output_scheduler = router.output_schedulers[self.exit_port].borrow_mut();
 			if let Some(event) = output_scheduler.schedule(simulation.cycle,0) {
 				vec![event]
@@ -1192,10 +1506,20 @@ mod internal
 		self_rc: Weak<RefCell<TryLinkTraversal>>,
 		router: Rc<RefCell<InputOutput>>,
 		exit_port: usize,
+		///The properties of the link. Its `frequency_divisor` is scaled when using DVFS.
 		link:LinkClass,
+		///The nominal frequency divisor of the link.
+		base_frequency_divisor: Time,
 		amount_virtual_channels: usize,
 		pending_event:bool,
 	}
+	impl TryLinkTraversal
+	{
+		pub fn base_frequency_divisor(&self) -> Time
+		{
+			self.base_frequency_divisor
+		}
+	}
 	pub struct TryLinkTraversalArgument<'a>
 	{
 		pub router: &'a mut InputOutput,
@@ -1211,6 +1535,7 @@ mod internal
 				self_rc: Weak::new(),
 				router: arg.router.self_rc.upgrade().unwrap(),
 				exit_port: arg.exit_port,
+				base_frequency_divisor: arg.link.frequency_divisor,
 				link: arg.link,
 				amount_virtual_channels,
 				pending_event:false,
@@ -1225,6 +1550,23 @@ mod internal
 		{
 			let mut events=vec![];
 			let mut router = self.router.borrow_mut();
+			let router_fields = &mut *router;
+			if let (Some(policy),Some(state)) = (router_fields.dvfs.as_ref(),router_fields.link_dvfs.get_mut(self.exit_port))
+			{
+				if state.update(policy,simulation.cycle,self.base_frequency_divisor,1)
+				{
+					self.link.frequency_divisor = state.divisor(policy,self.base_frequency_divisor);
+				}
+				//Events scheduled before a change of level may not be aligned with the new divisor.
+				let resume = state.halted_until(simulation.cycle).unwrap_or(simulation.cycle);
+				let resume = event::round_to_multiple(resume,self.link.frequency_divisor);
+				if resume > simulation.cycle
+				{
+					drop(router);
+					self.pending_event = false;
+					return self.schedule(simulation.cycle,resume-simulation.cycle).into_iter().collect();
+				}
+			}
 			let nvc= self.amount_virtual_channels;
 			//Gather the list of all vc that can advance
 			let mut cand=Vec::with_capacity(nvc);
@@ -1234,7 +1576,7 @@ mod internal
 			for exit_vc in 0..nvc
 			{
 				//Candidates when using output ports.
-				if let Some( (phit,(entry_port,_entry_vc))) = router.output_buffers[self.exit_port][exit_vc].front()
+				if let Some( (phit,(entry_port,_entry_vc,_label,_cycle))) = router.output_buffers[self.exit_port][exit_vc].front()
 				{
 					let bubble_in_use= router.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(router.router_index,entry_port,self.exit_port);
 					let status=&router.transmission_port_status[self.exit_port];
@@ -1289,31 +1631,45 @@ mod internal
 			if !cand.is_empty()
 			{
 				//Then select one of the vc candidates (either in input or output buffer) to actually use the physical port.
-				let selected_virtual_channel = match router.output_arbiter
+				let exit_port = self.exit_port;
+				let router_fields = &mut *router;
+				//The key by which the scheduling discipline sorts the candidates, lowest first.
+				let keys:Vec<i64> = cand.iter().map(|&vc|{
+					let (phit,(_entry_port,_entry_vc,label,cycle)) = router_fields.output_buffers[exit_port][vc].front().expect("candidate without phit");
+					match router_fields.output_arbiter
+					{
+						OutputArbiter::Fifo{..} => cycle as i64,
+						OutputArbiter::OldestFirst{..} => *phit.packet.cycle_into_network.borrow() as i64,
+						OutputArbiter::LabelPriority{..} => label as i64,
+						_ => 0,
+					}
+				}).collect();
+				let selected_virtual_channel = match router_fields.output_arbiter
 				{
 					OutputArbiter::Random=> cand[mutable.rng.gen_range(0..cand.len())],
-					OutputArbiter::Token{ref mut port_token}=>
+					OutputArbiter::WeightedTable(ref mut table) => table.select(exit_port,&cand,cand_in_transit),
+					OutputArbiter::Token{ref mut port_token} | OutputArbiter::Fifo{ref mut port_token} | OutputArbiter::OldestFirst{ref mut port_token} | OutputArbiter::LabelPriority{ref mut port_token} =>
 					{
 						//Or by tokens as in fsin
 						//let nvc=router.virtual_ports[self.exit_port].len() as i64;
 						let nvc= self.amount_virtual_channels as i64;
-						let token= port_token[self.exit_port] as i64;
+						let token= port_token[exit_port] as i64;
 						let mut best=0;
-						let mut bestd=nvc;
-						for vc in cand
+						let mut bestd=(i64::MAX,nvc);
+						for (vc,vc_key) in cand.into_iter().zip(keys)
 						{
 							let mut d:i64 = vc as i64 - token;
 							if d<0
 							{
 								d+=nvc;
 							}
-							if d<bestd
+							if (vc_key,d)<bestd
 							{
 								best=vc;
-								bestd=d;
+								bestd=(vc_key,d);
 							}
 						}
-						port_token[self.exit_port]=best;
+						port_token[exit_port]=best;
 						best
 					},
 				};
@@ -1321,14 +1677,14 @@ mod internal
 				let (phit,original_port) =
 				{
 					//If we get the phit from an output buffer there is little to do.
-					let (phit,(entry_port,_entry_vc))=router.output_buffers[self.exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
+					let (phit,(entry_port,_entry_vc,_label,_cycle))=router.output_buffers[self.exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
 					(phit,entry_port)
 				};
 				//Send the phit to the other link endpoint.
 				let (new_location,_link_class)=simulation.network.topology.neighbour(router.router_index,self.exit_port);
 				//let link = &simulation.link_classes[link_class];
 				events.push(EventGeneration{
-					delay: self.link.delay,
+					delay: self.link.phit_delay(simulation.cycle,&phit,&new_location,simulation.network.topology.as_ref()),
 					position:CyclePosition::Begin,
 					event:Event::PhitToLocation{
 						phit: phit.clone(),
@@ -1341,9 +1697,13 @@ mod internal
 				});
 				//next_delay = Some(next_delay.unwrap_or(link.frequency_divisor).min(link.frequency_divisor));
 				router.transmission_port_status[self.exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
+				if let Some(state) = router.link_dvfs.get_mut(self.exit_port)
+				{
+					state.notify_use(1);
+				}
 				if phit.is_end()
 				{
-					if let OutputArbiter::Token{ref mut port_token}=router.output_arbiter
+					if let Some(port_token)=router.output_arbiter.port_token()
 					{
 						port_token[self.exit_port]=(port_token[self.exit_port]+1)% self.amount_virtual_channels;
 					}
@@ -1,6 +1,8 @@
 
 pub mod basic;
 pub mod input_output;
+pub mod dvfs;
+pub mod hierarchical;
 
 use std::rc::{Rc};
 use std::cell::RefCell;
@@ -12,6 +14,7 @@ use ::rand::{Rng,rngs::StdRng};
 use quantifiable_derive::Quantifiable;//the derive macro
 
 use crate::{Phit,Packet,Plugs,error,source_location};
+use crate::measures::StallCounts;
 use self::basic::Basic;
 use self::input_output::InputOutput;
 use crate::config_parser::ConfigurationValue;
@@ -19,6 +22,7 @@ use crate::topology::{Topology};
 use crate::event::{Eventful,Time,EventGeneration};
 use crate::quantify::Quantifiable;
 use crate::error::{Error,SourceLocation};
+use crate::match_object;
 
 /// Those terms for modules that define a router.
 pub mod prelude
@@ -33,6 +37,31 @@ pub mod prelude
 	pub use crate::router;
 }
 
+///A phit at the head of an input buffer of a router, as given by [Router::input_heads].
+#[derive(Debug,Clone)]
+pub struct InputHead
+{
+	///The input port of the buffer.
+	pub port: usize,
+	///The virtual channel of the buffer.
+	pub virtual_channel: usize,
+	///The phit at the head of the buffer.
+	pub phit: Rc<Phit>,
+	///The output port and virtual channel already allocated to the packet of the phit, if any.
+	pub selected_output: Option<(usize,usize)>,
+}
+
+///The phits currently in the buffers of a router, indexed by `[port][virtual_channel]`.
+///See [BufferOccupancySeries](crate::measures::BufferOccupancySeries).
+#[derive(Clone,Debug,Default)]
+pub struct BufferOccupancy
+{
+	///The phits in each input buffer.
+	pub input: Vec<Vec<usize>>,
+	///The phits in each output buffer. Empty when the router has no output buffers.
+	pub output: Vec<Vec<usize>>,
+}
+
 ///The interface that a router type must follow.
 pub trait Router: Eventful + Quantifiable
 {
@@ -67,6 +96,22 @@ pub trait Router: Eventful + Quantifiable
 	///Build a status for an element that sends packets directly to the router ports.
 	///This is intended to build the status of the servers.
 	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>;
+	///Estimate the number of phits waiting to be sent through each port, to be given to the routing in a [RoutingOccupancy](crate::routing::RoutingOccupancy).
+	///It counts the phits in the output buffers and the space used in the input buffers of the neighbours, as known by the credits.
+	///Routers that do not provide this estimation return `None`.
+	fn port_occupancy(&self) -> Option<Vec<f64>> { None }
+	///The cycles that the phits at the head of the input buffers have been stalled since the last reset of statistics, by cause.
+	///Routers that do not track the causes of the stalls return `None`.
+	fn stall_counts(&self) -> Option<StallCounts> { None }
+	///The phits at the head of each input buffer, used to diagnose deadlocks. See [DeadlockWatchdog](crate::deadlock::DeadlockWatchdog).
+	///Routers that do not expose their buffers return `None`.
+	fn input_heads(&self) -> Option<Vec<InputHead>> { None }
+	///The phits currently in each of the input and output buffers.
+	///Routers that do not expose their buffers return `None`.
+	fn buffer_occupancy(&self) -> Option<BufferOccupancy> { None }
+	///Whether the router may drop packets, which requires the configuration to include [lossy](crate::lossy).
+	///Routers that never drop packets return `false`.
+	fn may_drop_packets(&self) -> bool { false }
 }
 
 #[non_exhaustive]
@@ -86,6 +131,9 @@ pub struct RouterBuilderArgument<'a>
 	///Available to the router as a default value.
 	///We do not directly receive a `SimulationShared` because it cannot have been built, as it would contain the routers.
 	pub general_frequency_divisor: Time,
+	///The frequency divisor of the router, the cycles between each of its clock ticks.
+	///It is the `general_frequency_divisor` unless the `RouterGroup` of the router sets another, see [router_frequency_divisor].
+	pub frequency_divisor: Time,
 	///The corresponding value of the `Statistics` struct.
 	///Available to the router for the case it want to use the same period.
 	pub statistics_temporal_step: Time,
@@ -93,26 +141,314 @@ pub struct RouterBuilderArgument<'a>
 	pub rng: &'a mut StdRng,
 }
 
-///Creates a router from a configuration value.
+/**
+Creates a router from a configuration value.
+
+The available routers are [Basic], an input-queued router, and [InputOutput], with buffers at both sides of the crossbar.
+The `HierarchicalCrossbar` router is an [InputOutput] whose crossbar is divided into tiles, as in high-radix routers, see [hierarchical].
+Alternatively, a `Preset` can be used to get a full configuration of these routers with sensible parameters.
+The preset name is formed as `architecture-switching-virtual_channelsVC`, where
+* the architecture is either `IQ`, for a [Basic] router, or `IOQ`, for an [InputOutput] router with a random allocator,
+* the switching is either `VCT`, for virtual cut-through, `WH`, for wormhole, or `SAF`, for store-and-forward, see [Switching],
+* and the virtual channels are any positive number, which are selected by the lowest label given by the routing.
+
+Any other field given to the preset overrides the one in the expansion. See [router_preset] for the expanded values.
+```ignore
+Preset{
+	name: "IQ-VCT-8VC",
+	buffer_size: 128,//optional override
+}
+```
+
+Both [Basic] and [InputOutput] have `virtual_channels` in every port by default. The optional field `link_class_virtual_channels` is an array indexed by link class that sets a different amount for the ports of that class, including the ports towards servers, which use the link class given to them by the topology.
+Link classes beyond the array length use `virtual_channels`. Candidates of the routing to a virtual channel not present in their port are discarded by the router.
+For example, in a dragonfly with `virtual_channels: 2` the field `link_class_virtual_channels: [2,1,1]` would keep 2 virtual channels in the local links but only 1 in the global links and the links to the servers.
+
+Both [Basic] and [InputOutput] select their switching technique with either the `switching` or the `flit_size` field, as explained in [Switching].
+
+To give priority in the crossbar to the packets that entered the network first, the [Basic] router accepts `oldest_first: true`, which sorts the requests by age after the labels and the in-transit priority.
+The [InputOutput] router gets the same with the `OldestFirst` allocator, see [new_allocator](crate::allocator::new_allocator).
+
+The [Basic] router may model a lossy network, dropping the packets that cannot advance with `drop_when_full: true` or with `drop_timeout: cycles`. See [lossy](crate::lossy) for their retransmission.
+
+The [InputOutput] router accepts two optional fields to model the microarchitecture of its crossbar and output ports.
+The `crossbar_speedup` is the number of phits that each output port may receive, and each input port may send, through the crossbar in each of its cycles. By default it is not limited.
+The `output_scheduling` selects the discipline by which each output port chooses the virtual channel to transmit among those ready, always preferring the packets already in transmission.
+It may be `RoundRobin`, the default, `Random`, `Fifo`, for the phit that entered the output buffers first, `OldestFirst`, for the packet that entered the network first, `LabelPriority`, for the lowest label given by the routing,
+or `WeightedTable`, to give each virtual channel a share of the link as in the virtual lane arbitration of InfiniBand, see [WeightedTable].
+The [Basic] router also accepts `output_scheduling`, either `RoundRobin`, the default, `Random`, or `WeightedTable`.
+```ignore
+InputOutput{
+	...
+	crossbar_speedup: 2,
+	output_scheduling: OldestFirst,
+}
+```
+
+The routers of a network need not be all equal. Instead of a single router, the configuration may give a list of `RouterGroup`s, and each router is built from the first group that matches it.
+A group selects the routers in the range `routers: [begin,end]`, with `end` excluded, and those to which the topology assigns the given `role`. A group without selectors matches every router, so it may serve as the last, default, group.
+The indirect topologies give the `leaf` role to the routers with servers and the `spine` role to the routers of the top level; the intermediate levels of a [MultiStage](crate::topology::multistage::MultiStage) are `level1`, `level2`, and so on.
+The routers at both ends of a link must agree in the virtual channels of the ports they connect.
+The `router_aggregated_statistics` of the results are then an array with the statistics aggregated over each group, or `None` for the groups without routers.
+
+A group may also set the `frequency_divisor` of its routers, putting them in their own clock domain instead of the `general_frequency_divisor` of the simulation.
+The routers then only operate in the cycles multiple of their divisor. A link class without its own `frequency_divisor` takes the largest divisor of the two endpoints of each link, using the `general_frequency_divisor` for the servers.
+```ignore
+router: [
+	RouterGroup{ role:"leaf", router:Basic{...} },
+	RouterGroup{ role:"spine", frequency_divisor:2, router:Basic{...} },
+	RouterGroup{ routers:[32,48], router:InputOutput{...} },
+	RouterGroup{ router:Preset{name:"IOQ-VCT-4VC"} },
+],
+```
+**/
 pub fn new_router(arg:RouterBuilderArgument) -> Rc<RefCell<dyn Router>>
 {
+	try_new_router(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///Like [new_router] but returning an error instead of panicking on a bad configuration.
+pub fn try_new_router(arg:RouterBuilderArgument) -> Result<Rc<RefCell<dyn Router>>,Error>
+{
+	if let ConfigurationValue::Array(groups)=arg.cv
+	{
+		let group = RouterGroup::new(&groups[router_group_index(groups,arg.router_index,arg.topology)?])?;
+		let frequency_divisor = group.frequency_divisor.unwrap_or(arg.frequency_divisor);
+		return try_new_router(RouterBuilderArgument{cv:group.router,frequency_divisor,..arg});
+	}
 	if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
 	{
 		if let Some(builder) = arg.plugs.routers.get(cv_name)
 		{
-			return builder(arg);
+			return Ok(builder(arg));
 		}
 		match cv_name.as_ref()
 		{
 			//"Basic" => Basic::<SimpleVirtualChannels>::new(arg.router_index, arg.cv, arg.plugs, arg.topology, arg.maximum_packet_size),
-			"Basic" => Basic::new(arg),
-			"InputOutput" | "InputOutputMonocycle" => InputOutput::new(arg),
-			_ => panic!("Unknown router {}",cv_name),
+			"Basic" => Ok(Basic::new(arg)?),
+			"InputOutput" | "InputOutputMonocycle" | "HierarchicalCrossbar" => Ok(InputOutput::new(arg)?),
+			"Preset" =>
+			{
+				let expanded = router_preset(arg.cv,arg.maximum_packet_size);
+				try_new_router(RouterBuilderArgument{cv:&expanded,..arg})
+			},
+			_ => Err(arg.cv.ill(&format!("Unknown router {}",cv_name))),
 		}
 	}
 	else
 	{
-		panic!("Trying to create a Router from a non-Object");
+		Err(arg.cv.ill("Trying to create a Router from a non-Object"))
+	}
+}
+
+///A `RouterGroup` of the configuration, as described in [new_router].
+struct RouterGroup<'a>
+{
+	///The range of router indices selected by the group, if any.
+	routers: Option<std::ops::Range<usize>>,
+	///The role given by the topology to the routers selected by the group, if any.
+	role: Option<&'a str>,
+	///The frequency divisor of the routers of the group, if other than the `general_frequency_divisor`.
+	frequency_divisor: Option<Time>,
+	///The configuration of the routers of the group.
+	router: &'a ConfigurationValue,
+}
+
+impl<'a> RouterGroup<'a>
+{
+	fn new(cv:&'a ConfigurationValue) -> Result<RouterGroup<'a>,Error>
+	{
+		let mut routers = None;
+		let mut role = None;
+		let mut frequency_divisor = None;
+		let mut router = None;
+		match_object!(cv,"RouterGroup",value,
+			"routers" => match value.as_array()?.as_slice()
+			{
+				[begin,end] => routers=Some(begin.as_usize()?..end.as_usize()?),
+				_ => return Err(value.ill("The routers of a RouterGroup must be a range [begin,end]")),
+			},
+			"role" => role=Some(value.as_str()?),
+			"frequency_divisor" => frequency_divisor=Some(value.as_time()?),
+			"router" => router=Some(value),
+		);
+		if frequency_divisor==Some(0)
+		{
+			return Err(cv.ill("The frequency_divisor of a RouterGroup must be positive"));
+		}
+		Ok(RouterGroup{
+			routers,
+			role,
+			frequency_divisor,
+			router: router.ok_or_else(||cv.ill("There were no router in the RouterGroup"))?,
+		})
+	}
+	fn matches(&self, router_index:usize, topology:&dyn Topology) -> bool
+	{
+		self.routers.as_ref().map(|range|range.contains(&router_index)).unwrap_or(true)
+			&& self.role.map(|role|topology.router_role(router_index).as_deref()==Some(role)).unwrap_or(true)
+	}
+}
+
+/**
+The index of the `RouterGroup` from which the router `router_index` is built, as described in [new_router].
+The first group matching the router is chosen. A group matches when the router is inside its `routers` range, if given, and when the topology gives it the `role`, if given.
+**/
+pub fn router_group_index(groups:&[ConfigurationValue], router_index:usize, topology:&dyn Topology) -> Result<usize,Error>
+{
+	for (index,group) in groups.iter().enumerate()
+	{
+		if RouterGroup::new(group)?.matches(router_index,topology)
+		{
+			return Ok(index);
+		}
+	}
+	Err(error!(unsupported_configuration).with_message(format!("There is no RouterGroup for the router {} (with role {:?})",router_index,topology.router_role(router_index))))
+}
+
+/**
+The frequency divisor of the router `router_index` built from the configuration `cv`.
+It is the one of its `RouterGroup`, if set, and the `general_frequency_divisor` otherwise.
+**/
+pub fn router_frequency_divisor(cv:&ConfigurationValue, router_index:usize, topology:&dyn Topology, general_frequency_divisor:Time) -> Result<Time,Error>
+{
+	match cv
+	{
+		ConfigurationValue::Array(groups) => Ok(RouterGroup::new(&groups[router_group_index(groups,router_index,topology)?])?.frequency_divisor.unwrap_or(general_frequency_divisor)),
+		_ => Ok(general_frequency_divisor),
+	}
+}
+
+/**
+Expands a `Preset{name, ...}` router configuration into a configuration of [Basic] or [InputOutput], as described in [new_router].
+With `p` the maximum packet size, the buffers have `4p` phits with virtual cut-through and store-and-forward and `p` phits with wormhole, and the output buffers have `2p` phits.
+The remaining fields of the preset are then written over the expansion. Overriding the `flit_size` removes the `switching` of the expansion.
+**/
+pub fn router_preset(cv:&ConfigurationValue, maximum_packet_size:usize) -> ConfigurationValue
+{
+	let fields = match cv
+	{
+		ConfigurationValue::Object(cv_name,fields) if cv_name=="Preset" => fields,
+		_ => panic!("A router preset must be created from a `Preset` object"),
+	};
+	let name = fields.iter().find(|(key,_)|key=="name").expect("There were no name").1.as_str().expect("bad value for name").to_string();
+	let overrides = fields.iter().filter(|(key,_)|key!="name" && key!="legend_name").cloned();
+	let parts:Vec<&str> = name.split('-').collect();
+	let (architecture,switching,virtual_channels) = match parts[..]
+	{
+		[architecture,switching,vcs] => (architecture,switching,vcs.strip_suffix("VC").and_then(|n|n.parse::<usize>().ok()).filter(|&n|n>0)),
+		_ => panic!("The router preset {} does not follow the form architecture-switching-virtual_channelsVC",name),
+	};
+	let virtual_channels = virtual_channels.unwrap_or_else(||panic!("Bad number of virtual channels in the router preset {}",name));
+	let (switching,buffer_size) = match switching
+	{
+		"VCT" => ("VirtualCutThrough",4*maximum_packet_size),
+		"WH" => ("Wormhole",maximum_packet_size),
+		"SAF" => ("StoreAndForward",4*maximum_packet_size),
+		_ => panic!("Unknown switching {} in the router preset {}. Use either VCT, WH, or SAF",switching,name),
+	};
+	let policies = ["EnforceFlowControl","LowestLabel","Random"].iter().map(|policy|ConfigurationValue::Object(policy.to_string(),vec![])).collect();
+	let mut fields = vec![
+		(String::from("virtual_channels"),ConfigurationValue::Number(virtual_channels as f64)),
+		(String::from("virtual_channel_policies"),ConfigurationValue::Array(policies)),
+		(String::from("buffer_size"),ConfigurationValue::Number(buffer_size as f64)),
+		(String::from("output_buffer_size"),ConfigurationValue::Number((2*maximum_packet_size) as f64)),
+		(String::from("bubble"),ConfigurationValue::False),
+		(String::from("switching"),ConfigurationValue::Object(String::from(switching),vec![])),
+		(String::from("intransit_priority"),ConfigurationValue::False),
+		(String::from("allow_request_busy_port"),ConfigurationValue::True),
+	];
+	let router_name = match architecture
+	{
+		"IQ" =>
+		{
+			fields.push((String::from("output_prioritize_lowest_label"),ConfigurationValue::True));
+			"Basic"
+		},
+		"IOQ" =>
+		{
+			fields.push((String::from("crossbar_delay"),ConfigurationValue::Number(1.0)));
+			fields.push((String::from("allocator"),ConfigurationValue::Object(String::from("Random"),vec![])));
+			"InputOutput"
+		},
+		_ => panic!("Unknown architecture {} in the router preset {}. Use either IQ or IOQ",architecture,name),
+	};
+	for (key,value) in overrides
+	{
+		match fields.iter_mut().find(|(field,_)|*field==key)
+		{
+			Some(field) => field.1=value,
+			None => fields.push((key,value)),
+		}
+	}
+	if fields.iter().any(|(key,_)|key=="flit_size")
+	{
+		fields.retain(|(key,_)|key!="switching");
+	}
+	ConfigurationValue::Object(router_name.to_string(),fields)
+}
+
+/**
+The switching technique of a router, selected in [Basic] and [InputOutput] with `switching: Wormhole`, `switching: VirtualCutThrough`, or `switching: StoreAndForward`.
+* With `Wormhole` a packet may begin to advance into the next buffer when it has space for a single phit. A blocked packet may then occupy buffers along several routers.
+* With `VirtualCutThrough` a packet may begin to advance only when the next buffer has space for the whole packet. Its phits are forwarded as soon as they arrive, so a blocked packet is kept in a single buffer.
+* With `StoreAndForward` a packet must, in addition, be completely received in the input buffer before its first phit advances. Each router then adds the serialization of the packet to its latency.
+
+Both `VirtualCutThrough` and `StoreAndForward` require input buffers of at least the maximum packet size.
+
+Instead of `switching` the routers accept the older `flit_size` field, the space in phits required in the next buffer to begin sending a packet. Thus `flit_size: 1` gives wormhole and `flit_size` equal to the maximum packet size gives virtual cut-through.
+It is an error to give both fields.
+**/
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Switching
+{
+	Wormhole,
+	VirtualCutThrough,
+	StoreAndForward,
+}
+
+impl Switching
+{
+	pub fn new(cv:&ConfigurationValue) -> Switching
+	{
+		let name = match cv
+		{
+			ConfigurationValue::Object(name,fields) if fields.is_empty() => name.as_str(),
+			ConfigurationValue::Literal(name) => name.as_str(),
+			_ => panic!("bad value for switching: {}",cv),
+		};
+		match name
+		{
+			"Wormhole" => Switching::Wormhole,
+			"VirtualCutThrough" => Switching::VirtualCutThrough,
+			"StoreAndForward" => Switching::StoreAndForward,
+			_ => panic!("Unknown switching {}. Use either Wormhole, VirtualCutThrough, or StoreAndForward",name),
+		}
+	}
+	///Gets the switching and the flit size of a router from its `switching` and `flit_size` fields.
+	///When only the `flit_size` is given the switching is virtual cut-through if it can hold the largest packets and wormhole otherwise.
+	pub fn from_fields(switching:Option<Switching>, flit_size:Option<usize>, buffer_size:usize, maximum_packet_size:usize) -> (Switching,usize)
+	{
+		match (switching,flit_size)
+		{
+			(Some(_),Some(_)) => panic!("A router cannot receive both switching and flit_size"),
+			(Some(Switching::Wormhole),None) => (Switching::Wormhole,1),
+			(Some(switching),None) =>
+			{
+				if buffer_size<maximum_packet_size
+				{
+					panic!("The switching {:?} requires buffers ({} phits) of at least the maximum packet size ({} phits)",switching,buffer_size,maximum_packet_size);
+				}
+				(switching,maximum_packet_size)
+			},
+			(None,Some(flit_size)) => (if flit_size>=maximum_packet_size {Switching::VirtualCutThrough} else {Switching::Wormhole},flit_size),
+			(None,None) => panic!("There were no switching nor flit_size"),
+		}
+	}
+	///Whether a packet whose first phit is at the head of a buffer with `occupied` phits may begin to advance.
+	pub fn packet_ready(&self, packet_size:usize, occupied:usize) -> bool
+	{
+		*self!=Switching::StoreAndForward || occupied>=packet_size
 	}
 }
 
@@ -335,6 +671,119 @@ impl<E:StatusAtEmissor+'static,R:SpaceAtReceptor+'static,T:TransmissionMechanism
 	}
 }
 
+///The amount of virtual channels of each port of the router `router_index`, given the default `virtual_channels` and the `link_class_virtual_channels` array of the router configuration.
+pub fn port_virtual_channels(topology:&dyn Topology, router_index:usize, virtual_channels:usize, link_class_virtual_channels:&[usize]) -> Vec<usize>
+{
+	(0..topology.ports(router_index)).map(|port|{
+		let (_location,link_class) = topology.neighbour(router_index,port);
+		*link_class_virtual_channels.get(link_class).unwrap_or(&virtual_channels)
+	}).collect()
+}
+
+///Reads the `link_class_mechanisms` of a router configuration into a map from the link class to the name of its transmission mechanism.
+///It may be given as an array of names indexed by link class, or as a list of `[link_class,name]` pairs with only the link classes to override.
+pub fn link_class_mechanisms(cv:&ConfigurationValue) -> Result<BTreeMap<usize,String>,Error>
+{
+	cv.as_array()?.iter().enumerate().map(|(index,entry)|{
+		match entry
+		{
+			ConfigurationValue::Literal(name) => Ok((index,name.clone())),
+			ConfigurationValue::Array(pair) => match pair.as_slice()
+			{
+				[link_class,name] => Ok((link_class.as_usize()?,name.as_str()?.to_string())),
+				_ => Err(entry.ill("The entries of link_class_mechanisms must be names or pairs [link_class,name]")),
+			},
+			_ => Err(entry.ill("bad value in link_class_mechanisms")),
+		}
+	}).collect()
+}
+
+/**
+A weighted round-robin arbitration of the virtual channels of each output port, modeled after the virtual lane arbitration tables of InfiniBand.
+The table is a cyclic list of entries `(virtual_channel,weight)`, with the weight in phits. The current entry may begin new packets while it has weight left and its virtual channel has packets ready, and then, or as soon as it has none ready, the next entry whose virtual channel is ready takes the turn.
+As in InfiniBand, a packet that has begun is completed even if it exceeds the weight left, so a weight below the packet size gives a single packet to each turn.
+Thus, with all virtual channels loaded, each one gets a share of the link proportional to its weight.
+The table can be given by a weight for each virtual channel, in which the entries follow the order of the virtual channels, or explicitly as a list of `[virtual_channel,weight]` pairs, in which a virtual channel may appear several times to interleave it with the others.
+```ignore
+output_scheduling: WeightedTable{
+	weights: [64,16],//4 phits of the virtual channel 0 for each phit of the virtual channel 1.
+	//table: [[0,32],[1,16],[0,32]],//alternatively, explicit entries.
+}
+```
+The virtual channels with weight 0 or not appearing in the table are only served when no virtual channel of the table is ready, so that the link is never left idle.
+**/
+#[derive(Debug,Clone)]
+pub struct WeightedTable
+{
+	///The entries `(virtual_channel,weight)` of the table, all with positive weight.
+	entries: Vec<(usize,usize)>,
+	///For each port, the index of the current entry.
+	current_entry: Vec<usize>,
+	///For each port, the phits that the current entry may still send.
+	remaining: Vec<usize>,
+}
+
+impl WeightedTable
+{
+	pub fn new(cv:&ConfigurationValue, ports:usize) -> Result<WeightedTable,Error>
+	{
+		let mut weights=None;
+		let mut table=None;
+		match_object!(cv,"WeightedTable",value,
+			"weights" => weights=Some(value.as_array()?.iter()
+				.map(|v|v.as_usize()).collect::<Result<Vec<usize>,_>>()?),
+			"table" => table=Some(value.as_array()?.iter().map(|entry|{
+				match entry.as_array()?.as_slice()
+				{
+					[vc,weight] => Ok((vc.as_usize()?,weight.as_usize()?)),
+					_ => Err(entry.ill("The entries of the table must be pairs [virtual_channel,weight]")),
+				}
+			}).collect::<Result<Vec<(usize,usize)>,Error>>()?),
+		);
+		let entries:Vec<(usize,usize)> = match (weights,table)
+		{
+			(Some(weights),None) => weights.into_iter().enumerate().collect(),
+			(None,Some(table)) => table,
+			_ => return Err(cv.ill("WeightedTable requires either weights or table")),
+		};
+		let entries:Vec<(usize,usize)> = entries.into_iter().filter(|&(_vc,weight)|weight>0).collect();
+		if entries.is_empty()
+		{
+			return Err(cv.ill("The WeightedTable requires some positive weight"));
+		}
+		Ok(WeightedTable{
+			entries,
+			current_entry: vec![0;ports],
+			remaining: vec![0;ports],
+		})
+	}
+	///Selects the virtual channel that sends the next phit through `port` among the non-empty `candidates`, and accounts its phit.
+	///The `in_transit` flag tells whether the candidates are continuing packets already in transmission, which the current entry completes regardless of its weight.
+	pub fn select(&mut self, port:usize, candidates:&[usize], in_transit:bool) -> usize
+	{
+		let current = self.current_entry[port];
+		if (self.remaining[port]>0 || in_transit) && candidates.contains(&self.entries[current].0)
+		{
+			self.remaining[port]=self.remaining[port].saturating_sub(1);
+			return self.entries[current].0;
+		}
+		let size = self.entries.len();
+		for offset in 1..=size
+		{
+			let index = (current+offset)%size;
+			let (vc,weight) = self.entries[index];
+			if candidates.contains(&vc)
+			{
+				self.current_entry[port]=index;
+				self.remaining[port]=weight-1;
+				return vc;
+			}
+		}
+		//Nothing in the table is ready.
+		*candidates.iter().min().expect("there are no candidates")
+	}
+}
+
 /// Helper structure to build a transmission mechanism.
 #[derive(Debug)]
 pub struct TransmissionMechanismBuilderArgument<'a>
@@ -347,7 +796,15 @@ pub struct TransmissionMechanismBuilderArgument<'a>
 }
 
 /// Creates a transmition mechanism.
-pub fn new_transmission_mechanism(arg:TransmissionMechanismBuilderArgument) -> Box<dyn AbstractTransmissionMechanism>
+///
+/// The routers select by name the mechanisms to use with the fields
+/// * `transmission_mechanism`: for the links between routers. Defaults to `"SimpleVirtualChannels"`.
+/// * `link_class_mechanisms`: optional map from link class to mechanism, overriding `transmission_mechanism` for the links between routers of that class. It is written as a list of `[link_class,name]` pairs, or as an array of names indexed by link class. Link classes not given use `transmission_mechanism`.
+/// * `to_server_mechanism`: for the links towards servers. Defaults to `"TransmissionToServer"`.
+/// * `from_server_mechanism`: for the links from servers. Defaults to `"SimpleVirtualChannels"`.
+///
+/// For example, `link_class_mechanisms: [[1,"TransmissionFromOblivious"]]`, or equivalently `link_class_mechanisms: ["SimpleVirtualChannels","TransmissionFromOblivious"]`, would use a different mechanism in the global links of a dragonfly.
+pub fn new_transmission_mechanism(arg:TransmissionMechanismBuilderArgument) -> Result<Box<dyn AbstractTransmissionMechanism>,Error>
 {
 	// if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
 	// {
@@ -369,10 +826,10 @@ pub fn new_transmission_mechanism(arg:TransmissionMechanismBuilderArgument) -> B
 	// }
 	match arg.name
 	{
-		"SimpleVirtualChannels" => Box::new(SimpleVirtualChannels::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send)),
-		"TransmissionToServer" => Box::new(TransmissionToServer() ),
-		"TransmissionFromOblivious" => Box::new(TransmissionFromOblivious::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send)),
-		x => panic!("Unknown transission mechanism {}",x),
+		"SimpleVirtualChannels" => Ok(Box::new(SimpleVirtualChannels::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send))),
+		"TransmissionToServer" => Ok(Box::new(TransmissionToServer() )),
+		"TransmissionFromOblivious" => Ok(Box::new(TransmissionFromOblivious::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send))),
+		x => Err(ConfigurationValue::Literal(x.to_string()).ill(&format!("Unknown transission mechanism {}",x))),
 	}
 }
 
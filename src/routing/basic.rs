@@ -5,17 +5,21 @@ Implementation of basic routing algorithms.
 
 * Shortest
 * Valiant
+* UGAL
 * Mindless
 * WeighedShortest
+* TableRouting
 
 */
 
 use std::cell::RefCell;
 use ::rand::{rngs::StdRng,Rng};
 
-use crate::{match_object_panic};
+use crate::{match_object,error,source_location};
+use crate::error::{Error,SourceLocation};
 use crate::config_parser::ConfigurationValue;
 use crate::routing::prelude::*;
+use crate::routing::RoutingAnnotation;
 use crate::topology::{Topology, Location};
 use crate::matrix::Matrix;
 use crate::pattern::prelude::*;
@@ -40,6 +44,8 @@ impl Routing for Shortest
 		if distance==0
 		{
 			let target_server = target_server.expect("target server was not given.");
+			//A server with several rails may be reached by several ports.
+			let mut candidates = vec![];
 			for i in 0..topology.ports(current_router)
 			{
 				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
@@ -49,11 +55,15 @@ impl Routing for Shortest
 					{
 						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
 						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
-						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+						candidates.extend((0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)));
 					}
 				}
 			}
-			unreachable!();
+			if candidates.is_empty()
+			{
+				unreachable!();
+			}
+			return Ok(RoutingNextCandidates{candidates,idempotent:true});
 		}
 		let num_ports=topology.ports(current_router);
 		let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
@@ -80,14 +90,150 @@ impl Routing for Shortest
 
 impl Shortest
 {
-	pub fn new(arg: RoutingBuilderArgument) -> Shortest
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Shortest,Error>
+	{
+		match_object!(arg.cv,"Shortest",_value);
+		Ok(Shortest{
+		})
+	}
+}
+
+/**
+Routing by a table with the output ports allowed from each router towards each destination router. All the virtual channels of those ports are candidates.
+The table is read from `filename`, which allows to replay the routes computed by external tools, such as the routing engines of a subnet manager.
+Without `filename` the table is computed once with all the minimal ports, as [Shortest] would give.
+With `dump` the table, either read or computed, is written into that file, in the same format that is read.
+
+Each line of the file has the current router, the destination router and the list of ports, separated by whitespace. Empty lines and lines starting by `#` are skipped.
+A pair of routers may appear in several lines, adding ports to its list. Pairs without any line have no route, making the simulation fail if some packet needs it.
+
+```ignore
+TableRouting{
+	filename: "routes.txt",//optional
+	dump: "minimal_routes.txt",//optional
+	legend_name: "routes from the table",
+}
+```
+**/
+#[derive(Debug)]
+pub struct TableRouting
+{
+	///The file from which to read the table. If `None` the table is computed with the minimal routes.
+	filename: Option<String>,
+	///The file in which to write the table once built.
+	dump: Option<String>,
+	///`table[current_router][target_router]` is the list of allowed ports. Built at initialization.
+	table: Vec<Vec<Vec<usize>>>,
+}
+
+impl Routing for TableRouting
+{
+	fn next(&self, _routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
-		match_object_panic!(arg.cv,"Shortest",_value);
-		Shortest{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			let candidates : Vec<CandidateEgress> = (0..topology.ports(current_router)).filter(|&port|
+				matches!(topology.neighbour(current_router,port),(Location::ServerPort(server),_) if server==target_server)
+			).flat_map(|port|(0..num_virtual_channels).map(move |vc|CandidateEgress::new(port,vc))).collect();
+			return Ok(RoutingNextCandidates{candidates,idempotent:true});
+		}
+		let ports = &self.table[current_router][target_router];
+		if ports.is_empty()
+		{
+			return Err(error!(undetermined).with_message(format!("TableRouting has no route from router {} to router {}.",current_router,target_router)));
+		}
+		let candidates = ports.iter().flat_map(|&port|(0..num_virtual_channels).map(move |vc|CandidateEgress::new(port,vc))).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		let n=topology.num_routers();
+		self.table = vec![vec![vec![];n];n];
+		match self.filename
+		{
+			Some(ref filename) =>
+			{
+				let contents = std::fs::read_to_string(filename).unwrap_or_else(|error|panic!("Could not open the routing table file {}: {}",filename,error));
+				for line in contents.lines().map(|line|line.trim()).filter(|line|!line.is_empty() && !line.starts_with('#'))
+				{
+					let fields : Vec<usize> = line.split_whitespace().map(|field|field.parse::<usize>()
+						.unwrap_or_else(|_|panic!("bad field {} in the line \"{}\" of the routing table {}",field,line,filename))).collect();
+					if fields.len()<3
+					{
+						panic!("The line \"{}\" of the routing table {} does not have a router, a destination and some port.",line,filename);
+					}
+					let (current,target) = (fields[0],fields[1]);
+					if current>=n || target>=n
+					{
+						panic!("The line \"{}\" of the routing table {} refers to a router out of the {} routers of the topology.",line,filename,n);
+					}
+					for &port in fields[2..].iter()
+					{
+						if port>=topology.ports(current) || !matches!(topology.neighbour(current,port),(Location::RouterPort{..},_))
+						{
+							panic!("The port {} of router {} in the routing table {} does not connect to another router.",port,current,filename);
+						}
+						if !self.table[current][target].contains(&port)
+						{
+							self.table[current][target].push(port);
+						}
+					}
+				}
+			},
+			None =>
+			{
+				for current in 0..n
+				{
+					for port in 0..topology.ports(current)
+					{
+						if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current,port)
+						{
+							for target in 0..n
+							{
+								if topology.distance(router_index,target)+1==topology.distance(current,target)
+								{
+									self.table[current][target].push(port);
+								}
+							}
+						}
+					}
+				}
+			},
+		}
+		if let Some(ref dump) = self.dump
+		{
+			let mut content = String::from("# current_router target_router ports\n");
+			for (current,row) in self.table.iter().enumerate()
+			{
+				for (target,ports) in row.iter().enumerate().filter(|(_,ports)|!ports.is_empty())
+				{
+					content.push_str(&format!("{} {} {}\n",current,target,ports.iter().map(|port|port.to_string()).collect::<Vec<_>>().join(" ")));
+				}
+			}
+			std::fs::write(dump,content).unwrap_or_else(|error|panic!("Could not write the routing table file {}: {}",dump,error));
 		}
 	}
 }
 
+impl TableRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<TableRouting,Error>
+	{
+		let mut filename=None;
+		let mut dump=None;
+		match_object!(arg.cv,"TableRouting",value,
+			"filename" => filename = Some(value.as_str()?.to_string()),
+			"dump" => dump = Some(value.as_str()?.to_string()),
+		);
+		Ok(TableRouting{
+			filename,
+			dump,
+			table: vec![],
+		})
+	}
+}
+
 /**
 This is Valiant's randomization scheme. Each packet to be sent from a source to a destination is routed first to a random intermediate node, and from that intermediate to destination. These randomization makes the two parts behave as if the
 traffic pattern was uniform at the cost of doubling the lengths.
@@ -109,6 +255,7 @@ Valiant{
 	// In such case, upon reaching that intermediate group the packet would change to the second fase, without having to reach the specific router.
 }
 ```
+When the message carries [RoutingHints](crate::RoutingHints) with an `intermediate` router it is used instead of a random one.
 **/
 #[derive(Debug)]
 pub struct Valiant
@@ -225,7 +372,12 @@ impl Routing for Valiant
 		//	_ => panic!("The server is not attached to a router"),
 		//};
 		let n=topology.num_routers();
-		let middle = if self.selection_exclude_indirect_routers
+		let hinted = routing_info.borrow().hints.as_ref().and_then(|hints|hints.intermediate);
+		let middle = if let Some(middle) = hinted
+		{
+			middle
+		}
+		else if self.selection_exclude_indirect_routers
 		{
 			let available : Vec<usize> = (0..n).filter(|&index|{
 				for i in 0..topology.ports(index)
@@ -331,15 +483,30 @@ impl Routing for Valiant
 			pattern.initialize(size,size,topology,rng);
 		}
 	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		self.first.check_router_configuration(topology,routers)?;
+		self.second.check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.first.on_topology_change(topology,rng);
+		self.second.on_topology_change(topology,rng);
+	}
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng)
 	{
 		//TODO: recurse over routings
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.first.advance_cycle(cycle);
+		self.second.advance_cycle(cycle);
+	}
 }
 
 impl Valiant
 {
-	pub fn new(arg: RoutingBuilderArgument) -> Valiant
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Valiant,Error>
 	{
 		//let mut order=None;
 		//let mut servers_per_router=None;
@@ -352,23 +519,23 @@ impl Valiant
 		let mut first_reserved_virtual_channels=vec![];
 		let mut second_reserved_virtual_channels=vec![];
 		let mut intermediate_bypass=None;
-		match_object_panic!(arg.cv,"Valiant",value,
-			"first" => first=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"second" => second=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"selection_exclude_indirect_routers" => selection_exclude_indirect_routers = value.as_bool().expect("bad value for selection_exclude_indirect_routers"),
-			"min_src_first" => min_src_first=value.as_bool().expect("bad value for min_src_first"),
-			"min_target_first" => min_target_first=value.as_bool().expect("bad value for min_target_first"),
-			"use_min_b" => use_min_b=value.as_bool().expect("bad value for use_min_b"),
+		match_object!(arg.cv,"Valiant",value,
+			"first" => first=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"second" => second=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"selection_exclude_indirect_routers" => selection_exclude_indirect_routers = value.as_bool()?,
+			"min_src_first" => min_src_first=value.as_bool()?,
+			"min_target_first" => min_target_first=value.as_bool()?,
+			"use_min_b" => use_min_b=value.as_bool()?,
 			"first_reserved_virtual_channels" => first_reserved_virtual_channels=value.
-				as_array().expect("bad value for first_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in first_reserved_virtual_channels") as usize).collect(),
 			"second_reserved_virtual_channels" => second_reserved_virtual_channels=value.
-				as_array().expect("bad value for second_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in second_reserved_virtual_channels") as usize).collect(),
-			"intermediate_bypass" => intermediate_bypass=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+			"intermediate_bypass" => intermediate_bypass=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
 		);
-		let first=first.expect("There were no first");
-		let second=second.expect("There were no second");
+		let first=first.ok_or_else(||arg.cv.ill("There were no first"))?;
+		let second=second.ok_or_else(||arg.cv.ill("There were no second"))?;
 		//let first_reserved_virtual_channels=first_reserved_virtual_channels.expect("There were no first_reserved_virtual_channels");
 		//let second_reserved_virtual_channels=second_reserved_virtual_channels.expect("There were no second_reserved_virtual_channels");
 		if use_min_b
@@ -376,7 +543,7 @@ impl Valiant
 			min_src_first= false;
 			min_target_first= false;
 		}
-		Valiant{
+		Ok(Valiant{
 			first,
 			second,
 			selection_exclude_indirect_routers,
@@ -385,8 +552,317 @@ impl Valiant
 			min_src_first,
 			min_target_first,
 			intermediate_bypass,
+		})
+	}
+}
+
+///The packet has not yet chosen between the minimal and the Valiant path.
+const UGAL_UNDECIDED: i32 = 0;
+///The packet goes towards its destination, either from the beginning or after visiting the intermediate router.
+const UGAL_MINIMAL: i32 = 1;
+///The packet goes towards the intermediate router.
+const UGAL_NONMINIMAL: i32 = 2;
+
+/**
+Universal Globally-Adaptive Load-balanced routing. At the source router each packet chooses between a minimal path and a Valiant path through a random intermediate router, according to the estimated congestion of both.
+
+See Singh, A. (2005). Load-balanced routing in interconnection networks. PhD thesis, Stanford University.
+
+In the local version, UGAL-L, the cost of a path is the occupancy of its first port multiplied by its number of hops, as given by the `estimated_remaining_hops` of the candidates or by the distances in the topology.
+In the global version, UGAL-G, the cost of a path is the sum of the occupancies of the ports along it, taken from every router at the beginning of the cycle.
+This path is estimated by advancing at each hop through the least occupied port among those of minimal distance.
+The minimal path is chosen when its cost does not exceed the cost of the Valiant path plus the `threshold`. The decision is made at the first request of the packet and kept afterwards.
+
+The same `routing` is employed for the minimal path and for both segments of the Valiant path. Deadlock must be avoided by other means, for example applying `ChannelsPerHop` over `UGAL`.
+```ignore
+UGAL{
+	routing: Shortest,
+	global: false,//optional, use the occupancy of every router (UGAL-G) instead of just the local one (UGAL-L).
+	//threshold: 0,//optional, bias in favour of the minimal path.
+	//selection_exclude_indirect_routers: false,//optional, as in Valiant.
+	legend_name: "UGAL-L",
+}
+```
+The results include a `UGALStatistics{minimal,nonminimal}` with the number of decisions of each kind.
+When the message carries [RoutingHints](crate::RoutingHints), a hinted `intermediate` router is used for the Valiant path and `latency_critical` messages are always routed minimally.
+**/
+#[derive(Debug)]
+pub struct UGAL
+{
+	///The routing for the minimal path and for each segment of the Valiant path.
+	routing: Box<dyn Routing>,
+	///Whether to estimate the cost of the paths with the occupancy of all the routers.
+	global: bool,
+	///Value added to the cost of the Valiant path before comparing it with the minimal path.
+	threshold: f64,
+	///Whether to avoid selecting intermediate routers without attached servers. This helps to apply it to indirect networks.
+	selection_exclude_indirect_routers: bool,
+	///The routers that may be selected as intermediate. Computed in `initialize`.
+	intermediates: Vec<usize>,
+	///The number of minimal and nonminimal decisions since the last reset.
+	decisions: RefCell<[usize;2]>,
+}
+
+impl Routing for UGAL
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,&RoutingOccupancy::default(),rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let meta = routing_info.meta.as_ref().expect("UGAL requires its routing info to be initialized");
+		let selections = routing_info.selections.as_ref().expect("UGAL requires its routing info to be initialized");
+		let intermediate = selections[1] as usize;
+		match selections[0]
+		{
+			UGAL_MINIMAL => self.routing.next_with_occupancy(&meta[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng),
+			UGAL_NONMINIMAL => self.nonminimal_candidates(&meta[1].borrow(),topology,current_router,target_router,intermediate,num_virtual_channels,occupancy,rng),
+			_ =>
+			{
+				let minimal = self.routing.next_with_occupancy(&meta[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng)?;
+				let nonminimal = self.nonminimal_candidates(&meta[1].borrow(),topology,current_router,target_router,intermediate,num_virtual_channels,occupancy,rng)?;
+				let minimal_cost = self.path_cost(&minimal.candidates,topology,current_router,target_router,None,occupancy);
+				let nonminimal_cost = self.path_cost(&nonminimal.candidates,topology,current_router,target_router,Some(intermediate),occupancy);
+				let (choice,chosen) = if minimal_cost <= nonminimal_cost + self.threshold { (UGAL_MINIMAL,minimal) } else { (UGAL_NONMINIMAL,nonminimal) };
+				let candidates = chosen.into_iter().map(|candidate|{
+					let annotation = Some(RoutingAnnotation{values:vec![choice],meta:vec![candidate.annotation.clone()]});
+					CandidateEgress{annotation,..candidate}
+				}).collect();
+				//The choice depends on the occupancy, so it may change in other calls.
+				Ok(RoutingNextCandidates{candidates,idempotent:false})
+			}
 		}
 	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let (hinted,latency_critical) = match routing_info.borrow().hints
+		{
+			Some(ref hints) => (hints.intermediate,hints.latency_critical),
+			None => (None,false),
+		};
+		let intermediate = hinted.unwrap_or_else(||self.intermediates[rng.gen_range(0..self.intermediates.len())]);
+		let mut bri = routing_info.borrow_mut();
+		bri.meta = Some(vec![RefCell::new(RoutingInfo::new()),RefCell::new(RoutingInfo::new())]);
+		let state = {
+			let meta = bri.meta.as_ref().unwrap();
+			self.routing.initialize_routing_info(&meta[0],topology,current_router,target_router,target_server,rng);
+			if latency_critical || intermediate==current_router || intermediate==target_router || current_router==target_router
+			{
+				//There is nothing to choose, or the message should not take long routes.
+				UGAL_MINIMAL
+			}
+			else
+			{
+				self.routing.initialize_routing_info(&meta[1],topology,current_router,intermediate,None,rng);
+				UGAL_UNDECIDED
+			}
+		};
+		bri.selections = Some(vec![state,intermediate as i32]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let (state,intermediate) = {
+			let selections = bri.selections.as_ref().unwrap();
+			(selections[0],selections[1] as usize)
+		};
+		if state==UGAL_NONMINIMAL
+		{
+			if current_router==intermediate
+			{
+				bri.selections = Some(vec![UGAL_MINIMAL,intermediate as i32]);
+				let meta = bri.meta.as_ref().unwrap();
+				meta[0].replace(RoutingInfo::new());
+				self.routing.initialize_routing_info(&meta[0],topology,current_router,target_router,target_server,rng);
+			}
+			else
+			{
+				let meta = bri.meta.as_mut().unwrap();
+				meta[1].borrow_mut().hops+=1;
+				self.routing.update_routing_info(&meta[1],topology,current_router,current_port,intermediate,None,rng);
+			}
+		}
+		else
+		{
+			let meta = bri.meta.as_mut().unwrap();
+			meta[0].borrow_mut().hops+=1;
+			self.routing.update_routing_info(&meta[0],topology,current_router,current_port,target_router,target_server,rng);
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+		let n = topology.num_routers();
+		self.intermediates = if self.selection_exclude_indirect_routers
+		{
+			(0..n).filter(|&router|(0..topology.ports(router)).any(|port|matches!(topology.neighbour(router,port).0,Location::ServerPort(_)))).collect()
+		}
+		else
+		{
+			(0..n).collect()
+		};
+		if self.intermediates.is_empty()
+		{
+			panic!("There are not legal intermediate routers to select in UGAL");
+		}
+	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		self.routing.check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		let (state,intermediate) = {
+			let bri = routing_info.borrow();
+			let selections = bri.selections.as_ref().unwrap();
+			(selections[0],selections[1] as usize)
+		};
+		let (state,requested) = if state==UGAL_UNDECIDED
+		{
+			let annotation = requested.annotation.as_ref().expect("UGAL candidates should be annotated");
+			let choice = annotation.values[0];
+			routing_info.borrow_mut().selections = Some(vec![choice,intermediate as i32]);
+			self.decisions.borrow_mut()[if choice==UGAL_MINIMAL {0} else {1}] += 1;
+			(choice,CandidateEgress{annotation:annotation.meta[0].clone(),..requested.clone()})
+		}
+		else
+		{
+			(state,requested.clone())
+		};
+		let bri = routing_info.borrow();
+		let meta = bri.meta.as_ref().unwrap();
+		if state==UGAL_NONMINIMAL
+		{
+			self.routing.performed_request(&requested,&meta[1],topology,current_router,intermediate,None,num_virtual_channels,rng);
+		}
+		else
+		{
+			self.routing.performed_request(&requested,&meta[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
+		}
+	}
+	fn need_occupancy(&self) -> bool { true }
+	fn need_global_occupancy(&self) -> bool { self.global }
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let decisions = self.decisions.borrow();
+		let content = vec![
+			(String::from("minimal"),ConfigurationValue::Number(decisions[0] as f64)),
+			(String::from("nonminimal"),ConfigurationValue::Number(decisions[1] as f64)),
+		];
+		Some(ConfigurationValue::Object(String::from("UGALStatistics"),content))
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.decisions.borrow_mut() = [0,0];
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle);
+	}
+}
+
+impl UGAL
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<UGAL,Error>
+	{
+		let mut routing=None;
+		let mut global=false;
+		let mut threshold=0.0;
+		let mut selection_exclude_indirect_routers=false;
+		match_object!(arg.cv,"UGAL",value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"global" => global=value.as_bool()?,
+			"threshold" => threshold=value.as_f64()?,
+			"selection_exclude_indirect_routers" => selection_exclude_indirect_routers = value.as_bool()?,
+		);
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		Ok(UGAL{
+			routing,
+			global,
+			threshold,
+			selection_exclude_indirect_routers,
+			intermediates: vec![],
+			decisions: RefCell::new([0,0]),
+		})
+	}
+	///The candidates towards the `intermediate` router, with the remaining hops including those from the intermediate to the target.
+	#[allow(clippy::too_many_arguments)]
+	fn nonminimal_candidates(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, intermediate:usize, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let second_distance = topology.distance(intermediate,target_router);
+		let base = self.routing.next_with_occupancy(routing_info,topology,current_router,intermediate,None,num_virtual_channels,occupancy,rng)?;
+		let idempotent = base.idempotent;
+		let candidates = base.into_iter().map(|mut egress|{
+			if let Some(ref mut eh)=egress.estimated_remaining_hops
+			{
+				*eh += second_distance;
+			}
+			egress
+		}).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent})
+	}
+	///The estimated cost of the best path beginning with any of the `candidates`, through the `intermediate` router if given.
+	fn path_cost(&self, candidates:&[CandidateEgress], topology:&dyn Topology, current_router:usize, target_router:usize, intermediate:Option<usize>, occupancy:&RoutingOccupancy) -> f64
+	{
+		candidates.iter().map(|candidate|{
+			let port_occupancy = occupancy.port_occupancy.map(|o|o[candidate.port]).unwrap_or(0.0);
+			let next_router = match topology.neighbour(current_router,candidate.port).0
+			{
+				Location::RouterPort{router_index,..} => router_index,
+				_ => return port_occupancy,
+			};
+			if self.global
+			{
+				let network = occupancy.network_occupancy.expect("UGAL with global:true requires the occupancy of all the routers");
+				let mut cost = port_occupancy;
+				let mut router = next_router;
+				if let Some(middle) = intermediate
+				{
+					cost += least_occupied_walk_cost(network,topology,router,middle);
+					router = middle;
+				}
+				cost + least_occupied_walk_cost(network,topology,router,target_router)
+			}
+			else
+			{
+				let hops = candidate.estimated_remaining_hops.unwrap_or_else(||{
+					1 + match intermediate
+					{
+						Some(middle) => topology.distance(next_router,middle) + topology.distance(middle,target_router),
+						None => topology.distance(next_router,target_router),
+					}
+				});
+				port_occupancy * hops as f64
+			}
+		}).fold(f64::INFINITY,f64::min)
+	}
+}
+
+///The sum of the occupancies in a minimal path from `origin` to `target` advancing at each hop through the least occupied port.
+fn least_occupied_walk_cost(network_occupancy:&[Vec<f64>], topology:&dyn Topology, origin:usize, target:usize) -> f64
+{
+	let mut cost = 0.0;
+	let mut current = origin;
+	while current!=target
+	{
+		let distance = topology.distance(current,target);
+		let (port_occupancy,next) = (0..topology.ports(current)).filter_map(|port|{
+			match topology.neighbour(current,port).0
+			{
+				Location::RouterPort{router_index,..} if topology.distance(router_index,target)+1==distance =>
+					Some((network_occupancy[current].get(port).copied().unwrap_or(0.0),router_index)),
+				_ => None,
+			}
+		}).min_by(|a,b|a.0.partial_cmp(&b.0).unwrap()).expect("there should be a neighbour closer to the target");
+		cost += port_occupancy;
+		current = next;
+	}
+	cost
 }
 
 ///Mindless routing
@@ -450,17 +926,16 @@ impl Routing for Mindless
 
 impl Mindless
 {
-	pub fn new(arg: RoutingBuilderArgument) -> Mindless
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Mindless,Error>
 	{
-		match_object_panic!(arg.cv,"Mindless",_value);
-		Mindless{
-		}
+		match_object!(arg.cv,"Mindless",_value);
+		Ok(Mindless{
+		})
 	}
 }
 
 ///Use the shortest path from origin to destination, giving a weight to each link class.
-///Note that it uses information based on BFS and not on Dijkstra, which may cause discrepancies in some topologies.
-///See the `Topology::compute_distance_matrix` and its notes on weights for more informations.
+///The distances are computed with Dijkstra's algorithm, see `Topology::compute_weighted_distance_matrix`.
 #[derive(Debug)]
 pub struct WeighedShortest
 {
@@ -528,25 +1003,29 @@ impl Routing for WeighedShortest
 	}
 	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
 	{
-		self.distance_matrix=topology.compute_distance_matrix(Some(&self.class_weight));
+		self.distance_matrix=topology.compute_weighted_distance_matrix(&self.class_weight);
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.initialize(topology,rng);
 	}
 }
 
 impl WeighedShortest
 {
-	pub fn new(arg: RoutingBuilderArgument) -> WeighedShortest
+	pub fn new(arg: RoutingBuilderArgument) -> Result<WeighedShortest,Error>
 	{
 		let mut class_weight=None;
-		match_object_panic!(arg.cv,"WeighedShortest",value,
+		match_object!(arg.cv,"WeighedShortest",value,
 			"class_weight" => class_weight = Some(value.as_array()
-				.expect("bad value for class_weight").iter()
+				?.iter()
 				.map(|v|v.as_f64().expect("bad value in class_weight") as usize).collect()),
 		);
-		let class_weight=class_weight.expect("There were no class_weight");
-		WeighedShortest{
+		let class_weight=class_weight.ok_or_else(||arg.cv.ill("There were no class_weight"))?;
+		Ok(WeighedShortest{
 			class_weight,
 			distance_matrix:Matrix::constant(0,0,0),
-		}
+		})
 	}
 }
 
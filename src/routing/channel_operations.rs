@@ -6,19 +6,23 @@ Implementations of operations on routings that modify the virtual channel to use
 * ChannelsPerHopPerLinkClass
 * ChannelMap
 * AscendantChannelsWithLinkClass
+* EscapeChannels, also as Duato
 
 */
 
 use std::cell::RefCell;
+use std::collections::{BTreeMap,BTreeSet,btree_map};
 
 use ::rand::{rngs::StdRng};
 use rand::SeedableRng;
 
-use crate::{match_object_panic, Plugs};
+use crate::{match_object, Plugs, error, source_location};
+use crate::error::{Error,SourceLocation};
 use crate::config_parser::ConfigurationValue;
-use crate::pattern::{new_pattern, PatternBuilderArgument};
-use crate::topology::{new_topology, Topology, TopologyBuilderArgument};
+use crate::pattern::{try_new_pattern, PatternBuilderArgument};
+use crate::topology::{try_new_topology, Topology, TopologyBuilderArgument, Location};
 use crate::routing::prelude::*;
+use crate::routing::RoutingAnnotation;
 
 ///Set the virtual channels to use in each hop.
 ///Sometimes the same can be achieved by the router policy `Hops`.
@@ -35,10 +39,14 @@ pub struct ChannelsPerHop
 impl Routing for ChannelsPerHop
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,&RoutingOccupancy::default(),rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//println!("{}",topology.diameter());
 		let vcs = &self.channels[routing_info.hops];
-		let candidates = self.routing.next(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
+		let candidates = self.routing.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng)?;
 		let idempotent = candidates.idempotent;
 		let r = candidates.into_iter().filter(|c|vcs.contains(&c.virtual_channel)).collect();
 		Ok(RoutingNextCandidates{candidates:r,idempotent})
@@ -55,6 +63,24 @@ impl Routing for ChannelsPerHop
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		let virtual_channels = routers.minimum_virtual_channels();
+		if let Some(&vc) = self.channels.iter().flat_map(|vcs|vcs.iter()).max()
+		{
+			if vc >= virtual_channels
+			{
+				return Err(error!(unsupported_configuration).with_message(format!("ChannelsPerHop uses the virtual channel {} but some router has only {} virtual channels.",vc,virtual_channels)));
+			}
+		}
+		self.routing.check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
+	fn need_occupancy(&self) -> bool { self.routing.need_occupancy() }
+	fn need_global_occupancy(&self) -> bool { self.routing.need_global_occupancy() }
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -67,28 +93,32 @@ impl Routing for ChannelsPerHop
 	{
 		self.routing.reset_statistics(next_cycle)
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle)
+	}
 }
 
 impl ChannelsPerHop
 {
-	pub fn new(arg: RoutingBuilderArgument) -> ChannelsPerHop
+	pub fn new(arg: RoutingBuilderArgument) -> Result<ChannelsPerHop,Error>
 	{
 		let mut routing =None;
 		let mut channels =None;
-		match_object_panic!(arg.cv,"ChannelsPerHop",value,
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+		match_object!(arg.cv,"ChannelsPerHop",value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
 			"channels" => channels=Some(value.as_array().expect("bad value in channels").iter()
 				.map(|vcs_this_hop| vcs_this_hop.as_array().expect("bad value in channels").iter()
 					.map(|vc| vc.as_f64().expect("bad value in channels") as usize).collect()
 				).collect()
 			),
 		);
-		let routing=routing.expect("There were no routing");
-		let channels=channels.expect("There were no channels");
-		ChannelsPerHop{
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let channels=channels.ok_or_else(||arg.cv.ill("There were no channels"))?;
+		Ok(ChannelsPerHop{
 			routing,
 			channels,
-		}
+		})
 	}
 }
 
@@ -109,9 +139,13 @@ pub struct ChannelsPerHopPerLinkClass
 impl Routing for ChannelsPerHopPerLinkClass
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,&RoutingOccupancy::default(),rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//println!("{}",topology.diameter());
-		let candidates = self.routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
+		let candidates = self.routing.next_with_occupancy(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng)?;
 		let idempotent = candidates.idempotent;
 		let hops = &routing_info.selections.as_ref().unwrap();
 		let r = candidates.into_iter().filter(|c|{
@@ -161,6 +195,24 @@ impl Routing for ChannelsPerHopPerLinkClass
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		let virtual_channels = routers.minimum_virtual_channels();
+		if let Some(&vc) = self.channels.iter().flat_map(|per_hop|per_hop.iter()).flat_map(|vcs|vcs.iter()).max()
+		{
+			if vc >= virtual_channels
+			{
+				return Err(error!(unsupported_configuration).with_message(format!("ChannelsPerHopPerLinkClass uses the virtual channel {} but some router has only {} virtual channels.",vc,virtual_channels)));
+			}
+		}
+		self.routing.check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
+	fn need_occupancy(&self) -> bool { self.routing.need_occupancy() }
+	fn need_global_occupancy(&self) -> bool { self.routing.need_global_occupancy() }
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -173,22 +225,26 @@ impl Routing for ChannelsPerHopPerLinkClass
 	{
 		self.routing.reset_statistics(next_cycle)
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle)
+	}
 }
 
 impl ChannelsPerHopPerLinkClass
 {
-	pub fn new(arg: RoutingBuilderArgument) -> ChannelsPerHopPerLinkClass
+	pub fn new(arg: RoutingBuilderArgument) -> Result<ChannelsPerHopPerLinkClass,Error>
 	{
 		let mut routing =None;
 		let mut channels =None;
 		let mut use_total_hops = false;
-		match_object_panic!(arg.cv,"ChannelsPerHopPerLinkClass",value,
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+		match_object!(arg.cv,"ChannelsPerHopPerLinkClass",value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
 			"use_total_hops" => use_total_hops=match value
             {
 				&ConfigurationValue::True => true,
 				&ConfigurationValue::False => false,
-                _ => panic!("bad value for use_total_hops"),
+                _ => return Err(value.ill("bad value for use_total_hops")),
             },
 			"channels" => match value
 			{
@@ -202,16 +258,16 @@ impl ChannelsPerHopPerLinkClass
 					}).collect(),
 					_ => panic!("bad value in channels"),
 				}).collect()),
-				_ => panic!("bad value for channels"),
+				_ => return Err(value.ill("bad value for channels")),
 			}
 		);
-		let routing=routing.expect("There were no routing");
-		let channels=channels.expect("There were no channels");
-		ChannelsPerHopPerLinkClass{
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let channels=channels.ok_or_else(||arg.cv.ill("There were no channels"))?;
+		Ok(ChannelsPerHopPerLinkClass{
 			routing,
 			channels,
 			use_total_hops,
-		}
+		})
 	}
 }
 
@@ -226,9 +282,13 @@ pub struct AscendantChannelsWithLinkClass
 impl Routing for AscendantChannelsWithLinkClass
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,&RoutingOccupancy::default(),rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//println!("{}",topology.diameter());
-		let candidates = self.routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
+		let candidates = self.routing.next_with_occupancy(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng)?;
 		let idempotent = candidates.idempotent;
 		let hops_since = &routing_info.selections.as_ref().unwrap();
 		let r = candidates.into_iter().filter(|c|{
@@ -273,6 +333,22 @@ impl Routing for AscendantChannelsWithLinkClass
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		let virtual_channels = routers.minimum_virtual_channels();
+		let required:usize = self.bases.iter().product();
+		if required > virtual_channels
+		{
+			return Err(error!(unsupported_configuration).with_message(format!("AscendantChannelsWithLinkClass with bases {:?} requires {} virtual channels but some router has only {}.",self.bases,required,virtual_channels)));
+		}
+		self.routing.check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
+	fn need_occupancy(&self) -> bool { self.routing.need_occupancy() }
+	fn need_global_occupancy(&self) -> bool { self.routing.need_global_occupancy() }
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -285,26 +361,30 @@ impl Routing for AscendantChannelsWithLinkClass
 	{
 		self.routing.reset_statistics(next_cycle)
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle)
+	}
 }
 
 impl AscendantChannelsWithLinkClass
 {
-	pub fn new(arg: RoutingBuilderArgument) -> AscendantChannelsWithLinkClass
+	pub fn new(arg: RoutingBuilderArgument) -> Result<AscendantChannelsWithLinkClass,Error>
 	{
 		let mut routing =None;
 		let mut bases =None;
-		match_object_panic!(arg.cv,"AscendantChannelsWithLinkClass",value,
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+		match_object!(arg.cv,"AscendantChannelsWithLinkClass",value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
 			"bases" => bases = Some(value.as_array()
-				.expect("bad value for bases").iter()
+				?.iter()
 				.map(|v|v.as_f64().expect("bad value in bases") as usize).collect()),
 		);
-		let routing=routing.expect("There were no routing");
-		let bases=bases.expect("There were no bases");
-		AscendantChannelsWithLinkClass{
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let bases=bases.ok_or_else(||arg.cv.ill("There were no bases"))?;
+		Ok(AscendantChannelsWithLinkClass{
 			routing,
 			bases,
-		}
+		})
 	}
 }
 
@@ -319,11 +399,15 @@ pub struct ChannelMap
 
 impl Routing for ChannelMap
 {
-	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, _num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,&RoutingOccupancy::default(),rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, _num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//println!("{}",topology.diameter());
 		//let vcs = &self.channels[routing_info.hops];
-		let candidates = self.routing.next(routing_info,topology,current_router,target_router,target_server,self.map.len(),rng)?;
+		let candidates = self.routing.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,self.map.len(),occupancy,rng)?;
 		let idempotent = candidates.idempotent;
 		//candidates.into_iter().filter(|c|vcs.contains(&c.virtual_channel)).collect()
 		let mut r=Vec::with_capacity(candidates.len());
@@ -352,6 +436,25 @@ impl Routing for ChannelMap
 
 		self.routing.initialize(topology,rng);
 	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		let virtual_channels = routers.minimum_virtual_channels();
+		if let Some(&vc) = self.map.iter().flat_map(|vcs|vcs.iter()).max()
+		{
+			if vc >= virtual_channels
+			{
+				return Err(error!(unsupported_configuration).with_message(format!("ChannelMap maps into the virtual channel {} but some router has only {} virtual channels.",vc,virtual_channels)));
+			}
+		}
+		//The inner routing sees as many channels as entries in the map.
+		self.routing.check_router_configuration(topology,&routers.with_virtual_channels(self.map.len()))
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
+	fn need_occupancy(&self) -> bool { self.routing.need_occupancy() }
+	fn need_global_occupancy(&self) -> bool { self.routing.need_global_occupancy() }
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_router,target_server,self.map.len(),rng);
@@ -364,19 +467,23 @@ impl Routing for ChannelMap
 	{
 		self.routing.reset_statistics(next_cycle)
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle)
+	}
 }
 
 impl ChannelMap
 {
-	pub fn new(arg: RoutingBuilderArgument) -> ChannelMap
+	pub fn new(arg: RoutingBuilderArgument) -> Result<ChannelMap,Error>
 	{
 		let mut routing =None;
 		let mut map =None;
 		let mut physical_to_logical =None; //pattern
 		let mut logical_size = None; //the size of the logical channels
 		let mut physical_size = None; //the size of the physical channels
-		match_object_panic!(arg.cv,"ChannelMap",value,
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+		match_object!(arg.cv,"ChannelMap",value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
 			"map" => match value
 			{
 				&ConfigurationValue::Array(ref hoplist) => map=Some(hoplist.iter().map(|v|match v{
@@ -386,42 +493,45 @@ impl ChannelMap
 					}).collect(),
 					_ => panic!("bad value in map"),
 				}).collect()),
-				_ => panic!("bad value for map"),
+				_ => return Err(value.ill("bad value for map")),
 			},
-			"physical_to_logical" => physical_to_logical = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"logical_size" => logical_size = Some(value.as_f64().expect("bad value for logical_size") as usize),
-			"physical_size" => physical_size = Some(value.as_f64().expect("bad value for physical_size") as usize),
+			"physical_to_logical" => physical_to_logical = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"logical_size" => logical_size = Some(value.as_f64()? as usize),
+			"physical_size" => physical_size = Some(value.as_f64()? as usize),
 		);
 
-		let map = map.unwrap_or_else(||{
-			//TODO: clearly we need some constructors for internal use that do not use plugs.
-			let plugs = Plugs::default();
-			let mut rng=StdRng::seed_from_u64(10u64);
-			let topo_cv = ConfigurationValue::Object("Hamming".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![])), ("servers_per_router".to_string(),ConfigurationValue::Number(1.0))]);
-
-			let dummy_topology = new_topology(TopologyBuilderArgument{cv:&topo_cv,plugs:&plugs,rng:&mut rng});
-
-			let physical_to_logical = physical_to_logical.expect("There were no physical_to_logical");
-			let logical_size = logical_size.expect("There were no logical_size");
-			let physical_size = physical_size.expect("There were no physical_size");
-			let mut map = vec![ vec![];logical_size ];
-			for i in 0..logical_size{
-				for j in 0..physical_size{
-					if physical_to_logical.get_destination(j, &*dummy_topology,&mut rng ) == i
-					{
-						map[i].push(j);
+		let map = match map
+		{
+			Some(map) => map,
+			None => {
+				//TODO: clearly we need some constructors for internal use that do not use plugs.
+				let plugs = Plugs::default();
+				let mut rng=StdRng::seed_from_u64(10u64);
+				let topo_cv = ConfigurationValue::Object("Hamming".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![])), ("servers_per_router".to_string(),ConfigurationValue::Number(1.0))]);
+
+				let dummy_topology = try_new_topology(TopologyBuilderArgument{cv:&topo_cv,plugs:&plugs,rng:&mut rng})?;
+
+				let physical_to_logical = physical_to_logical.ok_or_else(||arg.cv.ill("There were no physical_to_logical"))?;
+				let logical_size = logical_size.ok_or_else(||arg.cv.ill("There were no logical_size"))?;
+				let physical_size = physical_size.ok_or_else(||arg.cv.ill("There were no physical_size"))?;
+				let mut map = vec![ vec![];logical_size ];
+				for i in 0..logical_size{
+					for j in 0..physical_size{
+						if physical_to_logical.get_destination(j, &*dummy_topology,&mut rng ) == i
+						{
+							map[i].push(j);
+						}
 					}
 				}
-			}
-			map
-
-		});
+				map
+			},
+		};
 
-		let routing=routing.expect("There were no routing");
-		ChannelMap{
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		Ok(ChannelMap{
 			routing,
 			map,
-		}
+		})
 	}
 }
 
@@ -429,3 +539,479 @@ impl ChannelMap
 
 
 
+
+/**
+Reserve some virtual channels to a deadlock-free `escape` routing, such as `DOR` or `UpDown`, and let the main `routing` use the rest freely.
+At each hop a packet in the adaptive channels is offered both the candidates of the main routing in the adaptive channels and the candidates of the escape routing, towards the target from the current router, in the escape channels.
+Once a packet has taken a hop through the escape channels it keeps using the escape routing until its destination, unless `return_to_adaptive` is set, in which case it may come back to the adaptive channels in the next router.
+The escape routing is initialized again each time a packet enters the escape channels, so it should not depend on the source of the packet.
+```ignore
+EscapeChannels{
+	routing: Shortest,
+	escape: DOR{order:[0,1]},
+	escape_virtual_channels: [0],
+	adaptive_virtual_channels: [1,2],//optional, by default those that are not for escape
+	escape_extra_label: 10,//optional, added to the labels of the escape candidates, 0 by default
+	return_to_adaptive: false,//optional, false by default
+	enabled_statistics: true,//optional, to report the hops given in each kind of channel
+	verify_deadlock_freedom: true,//optional, false by default
+}
+```
+With `verify_deadlock_freedom` the routing is checked before simulating to satisfy Duato's condition, failing otherwise with a cycle of channels as diagnostic. See [EscapeChannels::verify_deadlock_freedom].
+The same routing may be written as `Duato`, in which the `routing` is by default `Shortest`, the fully adaptive minimal routing, and `verify_deadlock_freedom` is true by default.
+```ignore
+Duato{
+	escape: DOR{order:[0,1]},
+	escape_virtual_channels: [0],
+}
+```
+**/
+//routing_info.selections uses
+//* [mode] with mode 0 while the packet may use both routings and 1 once it is restricted to the escape routing.
+//* [mode,requested] once a request by the routing `requested` has been made in the current router.
+#[derive(Debug)]
+pub struct EscapeChannels
+{
+	///The routing using freely the adaptive channels.
+	routing: Box<dyn Routing>,
+	///The deadlock-free routing using the escape channels.
+	escape: Box<dyn Routing>,
+	///The virtual channels reserved to the escape routing.
+	escape_virtual_channels: Vec<usize>,
+	///The virtual channels of the main routing. If `None` all those not reserved to escape.
+	adaptive_virtual_channels: Option<Vec<usize>>,
+	///Added to the label of the escape candidates.
+	escape_extra_label: i32,
+	///Whether packets in the escape channels may go back to the adaptive ones.
+	return_to_adaptive: bool,
+	enabled_statistics: bool,
+	///When capturing statistics track the hops given through the adaptive and escape channels.
+	tracked_hops: RefCell<[i64;2]>,
+	///Whether to check Duato's condition in [Routing::check_router_configuration].
+	verify_deadlock_freedom: bool,
+}
+
+///An output channel, given by the router, its port and virtual channel.
+type Channel = (usize,usize,usize);
+
+///A dependency of the extended channel dependency graph: the next channel, the target router of the packets creating it, and whether it is indirect, through adaptive channels.
+type Dependency = (Channel,usize,bool);
+
+impl Routing for EscapeChannels
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,&RoutingOccupancy::default(),rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let meta = routing_info.meta.as_ref().expect("EscapeChannels requires its routing info");
+		let escaped = routing_info.selections.as_ref().expect("EscapeChannels requires its routing info")[0]==1;
+		let mut r = vec![];
+		if !escaped
+		{
+			let adaptive_virtual_channels = self.adaptive_virtual_channels(num_virtual_channels);
+			let candidates = self.routing.next_with_occupancy(&meta[0].borrow(),topology,current_router,target_router,target_server,adaptive_virtual_channels.len(),occupancy,rng)?;
+			r.extend(candidates.into_iter().map(|candidate| CandidateEgress{virtual_channel:adaptive_virtual_channels[candidate.virtual_channel],annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate}));
+		}
+		let candidates = self.escape.next_with_occupancy(&meta[1].borrow(),topology,current_router,target_router,target_server,self.escape_virtual_channels.len(),occupancy,rng)?;
+		r.extend(candidates.into_iter().map(|candidate| CandidateEgress{virtual_channel:self.escape_virtual_channels[candidate.virtual_channel],label:candidate.label+self.escape_extra_label,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![candidate.annotation]}),..candidate}));
+		Ok(RoutingNextCandidates{candidates:r,idempotent:false})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut info = routing_info.borrow_mut();
+		let mut main_info = RoutingInfo::new();
+		main_info.source_server = info.source_server;
+		let mut escape_info = RoutingInfo::new();
+		escape_info.source_server = info.source_server;
+		info.meta = Some(vec![RefCell::new(main_info),RefCell::new(escape_info)]);
+		info.selections = Some(vec![0]);
+		let meta = info.meta.as_ref().unwrap();
+		self.routing.initialize_routing_info(&meta[0],topology,current_router,target_router,target_server,rng);
+		self.escape.initialize_routing_info(&meta[1],topology,current_router,target_router,target_server,rng);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut info = routing_info.borrow_mut();
+		let selections = info.selections.as_ref().expect("EscapeChannels requires its routing info");
+		//Once escaped only the escape routing is requested.
+		let hop_kind = *selections.last().unwrap() as usize;
+		if self.enabled_statistics
+		{
+			self.tracked_hops.borrow_mut()[hop_kind]+=1;
+		}
+		let source_server = info.source_server;
+		let meta = info.meta.as_mut().unwrap();
+		meta[hop_kind].borrow_mut().hops+=1;
+		let routing = if hop_kind==0 { &self.routing } else { &self.escape };
+		routing.update_routing_info(&meta[hop_kind],topology,current_router,current_port,target_router,target_server,rng);
+		//Prepare the routings not followed in this hop to start from the current router.
+		let mut fresh = |index:usize, routing:&dyn Routing, rng:&mut StdRng| {
+			let mut new_info = RoutingInfo::new();
+			new_info.source_server = source_server;
+			meta[index] = RefCell::new(new_info);
+			routing.initialize_routing_info(&meta[index],topology,current_router,target_router,target_server,rng);
+		};
+		let mode = if hop_kind==0
+		{
+			fresh(1,self.escape.as_ref(),rng);
+			0
+		}
+		else if self.return_to_adaptive
+		{
+			fresh(0,self.routing.as_ref(),rng);
+			fresh(1,self.escape.as_ref(),rng);
+			0
+		}
+		else
+		{
+			1
+		};
+		info.selections = Some(vec![mode]);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+		self.escape.initialize(topology,rng);
+	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		let virtual_channels = routers.minimum_virtual_channels();
+		let adaptive_virtual_channels = self.adaptive_virtual_channels(virtual_channels);
+		if let Some(&vc) = self.escape_virtual_channels.iter().chain(adaptive_virtual_channels.iter()).max()
+		{
+			if vc >= virtual_channels
+			{
+				return Err(error!(unsupported_configuration).with_message(format!("EscapeChannels uses the virtual channel {} but some router has only {} virtual channels.",vc,virtual_channels)));
+			}
+		}
+		if let Some(vc) = self.escape_virtual_channels.iter().find(|vc|adaptive_virtual_channels.contains(vc))
+		{
+			return Err(error!(unsupported_configuration).with_message(format!("EscapeChannels uses the virtual channel {} both for escape and for the adaptive routing.",vc)));
+		}
+		if self.escape_virtual_channels.is_empty() || adaptive_virtual_channels.is_empty()
+		{
+			return Err(error!(unsupported_configuration).with_message(format!("EscapeChannels requires both escape and adaptive virtual channels, but there are {} and {}.",self.escape_virtual_channels.len(),adaptive_virtual_channels.len())));
+		}
+		self.routing.check_router_configuration(topology,&routers.with_virtual_channels(adaptive_virtual_channels.len()))?;
+		self.escape.check_router_configuration(topology,&routers.with_virtual_channels(self.escape_virtual_channels.len()))?;
+		if self.verify_deadlock_freedom
+		{
+			self.verify_deadlock_freedom(topology,virtual_channels)?;
+		}
+		Ok(())
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+		self.escape.on_topology_change(topology,rng);
+	}
+	fn need_occupancy(&self) -> bool { self.routing.need_occupancy() || self.escape.need_occupancy() }
+	fn need_global_occupancy(&self) -> bool { self.routing.need_global_occupancy() || self.escape.need_global_occupancy() }
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		let mut info = routing_info.borrow_mut();
+		if let Some(annotation) = requested.annotation.as_ref()
+		{
+			let kind = annotation.values[0];
+			let mode = info.selections.as_ref().expect("EscapeChannels requires its routing info")[0];
+			info.selections = Some(vec![mode,kind]);
+			let mut sub_requested = requested.clone();
+			sub_requested.annotation = annotation.meta[0].clone();
+			let meta = info.meta.as_ref().unwrap();
+			if kind==0
+			{
+				let sub_num_vc = self.adaptive_virtual_channels(num_virtual_channels).len();
+				self.routing.performed_request(&sub_requested,&meta[0],topology,current_router,target_router,target_server,sub_num_vc,rng);
+			}
+			else
+			{
+				self.escape.performed_request(&sub_requested,&meta[1],topology,current_router,target_router,target_server,self.escape_virtual_channels.len(),rng);
+			}
+		}
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		if self.enabled_statistics {
+			let tracked_hops = self.tracked_hops.borrow();
+			let mut content = vec![
+				(String::from("adaptive_hops"),ConfigurationValue::Number(tracked_hops[0] as f64)),
+				(String::from("escape_hops"),ConfigurationValue::Number(tracked_hops[1] as f64)),
+			];
+			if let Some(inner)=self.routing.statistics(cycle)
+			{
+				content.push( (String::from("routing_statistics"),inner) );
+			}
+			if let Some(inner)=self.escape.statistics(cycle)
+			{
+				content.push( (String::from("escape_statistics"),inner) );
+			}
+			Some(ConfigurationValue::Object(String::from("EscapeChannelsStatistics"),content))
+		} else {
+			None
+		}
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		*self.tracked_hops.borrow_mut() = [0,0];
+		self.routing.reset_statistics(next_cycle);
+		self.escape.reset_statistics(next_cycle);
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle);
+		self.escape.advance_cycle(cycle);
+	}
+}
+
+impl EscapeChannels
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<EscapeChannels,Error>
+	{
+		let mut routing = None;
+		let mut escape = None;
+		let mut escape_virtual_channels = None;
+		let mut adaptive_virtual_channels = None;
+		let mut escape_extra_label = 0i32;
+		let mut return_to_adaptive = false;
+		let mut enabled_statistics = false;
+		let duato = matches!(arg.cv,ConfigurationValue::Object(name,_) if name=="Duato");
+		let mut verify_deadlock_freedom = duato;
+		match_object!(arg.cv,["EscapeChannels","Duato"],value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"escape" => escape=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"escape_virtual_channels" => escape_virtual_channels = Some(value.as_array()
+				?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"adaptive_virtual_channels" => adaptive_virtual_channels = Some(value.as_array()
+				?.iter()
+				.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"escape_extra_label" => escape_extra_label = value.as_i32()?,
+			"return_to_adaptive" => return_to_adaptive = value.as_bool()?,
+			"enabled_statistics" => enabled_statistics = value.as_bool()?,
+			"verify_deadlock_freedom" => verify_deadlock_freedom = value.as_bool()?,
+		);
+		if duato && routing.is_none()
+		{
+			routing=Some(try_new_routing(RoutingBuilderArgument{cv:&ConfigurationValue::Object(String::from("Shortest"),vec![]),..arg})?);
+		}
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let escape=escape.ok_or_else(||arg.cv.ill("There were no escape"))?;
+		let escape_virtual_channels=escape_virtual_channels.ok_or_else(||arg.cv.ill("There were no escape_virtual_channels"))?;
+		Ok(EscapeChannels{
+			routing,
+			escape,
+			escape_virtual_channels,
+			adaptive_virtual_channels,
+			escape_extra_label,
+			return_to_adaptive,
+			enabled_statistics,
+			tracked_hops: RefCell::new([0,0]),
+			verify_deadlock_freedom,
+		})
+	}
+	///The virtual channels given to the main routing when there are `num_virtual_channels` in total.
+	fn adaptive_virtual_channels(&self, num_virtual_channels:usize) -> Vec<usize>
+	{
+		match self.adaptive_virtual_channels
+		{
+			Some(ref vcs) => vcs.clone(),
+			None => (0..num_virtual_channels).filter(|vc|!self.escape_virtual_channels.contains(vc)).collect(),
+		}
+	}
+	/**
+	Verifies Duato's condition for the deadlock freedom of the routing in the `topology`, with `num_virtual_channels` in each port.
+	The escape routing must offer some candidate from every router towards every destination, as the packets may enter the escape channels anywhere,
+	and the extended channel dependency graph of the escape channels must be acyclic.
+	Without `return_to_adaptive` the graph contains the dependencies between the consecutive escape channels of the packets.
+	With `return_to_adaptive` a packet starts the escape routing again after each hop, and the graph also contains the indirect dependencies from each escape channel to the
+	first escape channels of the routers that the main routing reaches from it.
+
+	The candidates are explored from every router towards every destination, replaying the hops of the packet to reach each channel so that the routings may keep state in their routing info.
+	Each channel is expanded once for each destination, from the first starting router that reaches it, so the dependencies created only by some other state of the routing info at that channel are missed.
+	Similarly, the routers reached by the main routing are computed once for each router and destination.
+	On failure the error describes a cycle of dependencies, or the router without escape candidates.
+	**/
+	pub fn verify_deadlock_freedom(&self, topology:&dyn Topology, num_virtual_channels:usize) -> Result<(),Error>
+	{
+		let num_routers = topology.num_routers();
+		let escape_channel = |(router,port,vc):Channel| (router,port,self.escape_virtual_channels[vc]);
+		let adaptive_virtual_channels = self.adaptive_virtual_channels(num_virtual_channels);
+		let mut dependencies : BTreeMap<Channel,BTreeSet<Dependency>> = BTreeMap::new();
+		for target in 0..num_routers
+		{
+			let target_server = (0..topology.ports(target)).find_map(|port|match topology.neighbour(target,port).0
+			{
+				Location::ServerPort(server) => Some(server),
+				_ => None,
+			});
+			//The escape channels that may be taken first at each router.
+			let mut first_escape : Vec<Vec<Channel>> = vec![vec![];num_routers];
+			//The escape channels already expanded towards this target, from any starting router.
+			let mut explored = BTreeSet::new();
+			for start in 0..num_routers
+			{
+				for (previous,channel) in explore_channels(self.escape.as_ref(),topology,start,target,target_server,self.escape_virtual_channels.len(),true,&mut explored)?
+				{
+					match previous
+					{
+						None => first_escape[start].push(escape_channel(channel)),
+						Some(previous) => if !self.return_to_adaptive
+						{
+							dependencies.entry(escape_channel(previous)).or_default().insert((escape_channel(channel),target,false));
+						},
+					}
+				}
+			}
+			if self.return_to_adaptive
+			{
+				//The routers that the main routing reaches from each router towards this target.
+				let mut reachable_from : BTreeMap<usize,BTreeSet<usize>> = BTreeMap::new();
+				for start in 0..num_routers
+				{
+					for &channel in first_escape[start].iter()
+					{
+						let next_router = match topology.neighbour(channel.0,channel.1).0
+						{
+							Location::RouterPort{router_index,..} => router_index,
+							_ => continue,
+						};
+						let entry = dependencies.entry(channel).or_default();
+						entry.extend(first_escape[next_router].iter().map(|&next|(next,target,false)));
+						if let btree_map::Entry::Vacant(vacant) = reachable_from.entry(next_router)
+						{
+							let reachable = explore_channels(self.routing.as_ref(),topology,next_router,target,target_server,adaptive_virtual_channels.len(),false,&mut BTreeSet::new())?
+								.into_iter().filter_map(|(_,(router,port,_))|match topology.neighbour(router,port).0
+								{
+									Location::RouterPort{router_index,..} => Some(router_index),
+									_ => None,
+								}).collect();
+							vacant.insert(reachable);
+						}
+						for &router in reachable_from[&next_router].iter()
+						{
+							entry.extend(first_escape[router].iter().map(|&next|(next,target,true)));
+						}
+					}
+				}
+			}
+		}
+		if let Some(cycle) = find_dependency_cycle(&dependencies)
+		{
+			let description : Vec<String> = cycle.iter().map(|&((router,port,vc),target,indirect)|
+				format!("router {} port {} vc {} {}towards router {}",router,port,vc,if indirect {"(then adaptive hops) "} else {""},target)
+			).collect();
+			return Err(error!(unsupported_configuration).with_message(format!("The escape channels do not satisfy Duato's condition, their extended channel dependency graph has the cycle:\n{}\nback to the first channel.",description.join(" ->\n"))));
+		}
+		Ok(())
+	}
+}
+
+/**
+Explores the candidates of the `routing` from the router `start` towards the router `target`, following every candidate towards another router.
+Returns the pairs of consecutive channels taken by the packets, with `None` as the previous of the channels taken at `start`. The virtual channels are the ones seen by the routing.
+Each channel is expanded once, replaying the hops of the packet from `start`, as done by the simulation, with a fixed seed for the random choices of the routing.
+The channels in `explored` are not expanded again, and those expanded are added to it, so that it can be shared among several starting routers towards the same target.
+With `complete` it is an error that the routing offers no candidate in a router other than the target.
+**/
+#[allow(clippy::too_many_arguments)]
+fn explore_channels(routing:&dyn Routing, topology:&dyn Topology, start:usize, target:usize, target_server:Option<usize>, num_virtual_channels:usize, complete:bool, explored:&mut BTreeSet<Channel>) -> Result<Vec<(Option<Channel>,Channel)>,Error>
+{
+	let mut pairs = vec![];
+	//Each pending path is the list of the channels taken from `start`, with their candidates.
+	let mut pending : Vec<Vec<(Channel,CandidateEgress)>> = vec![vec![]];
+	while let Some(path) = pending.pop()
+	{
+		let mut rng = StdRng::seed_from_u64(0);
+		let info = RefCell::new(RoutingInfo::new());
+		routing.initialize_routing_info(&info,topology,start,target,target_server,&mut rng);
+		let mut current = start;
+		for ((router,port,_),candidate) in path.iter()
+		{
+			routing.next(&info.borrow(),topology,*router,target,target_server,num_virtual_channels,&mut rng)?;
+			routing.performed_request(candidate,&info,topology,*router,target,target_server,num_virtual_channels,&mut rng);
+			if let (Location::RouterPort{router_index,router_port},_) = topology.neighbour(*router,*port)
+			{
+				info.borrow_mut().hops+=1;
+				routing.update_routing_info(&info,topology,router_index,router_port,target,target_server,&mut rng);
+				current = router_index;
+			}
+		}
+		let previous = path.last().map(|(channel,_)|*channel);
+		let candidates = routing.next(&info.borrow(),topology,current,target,target_server,num_virtual_channels,&mut rng)?.candidates;
+		let mut towards_routers = 0;
+		for candidate in candidates
+		{
+			if let (Location::RouterPort{..},_) = topology.neighbour(current,candidate.port)
+			{
+				towards_routers += 1;
+				let channel = (current,candidate.port,candidate.virtual_channel);
+				pairs.push((previous,channel));
+				if explored.insert(channel)
+				{
+					let mut next = path.clone();
+					next.push((channel,candidate));
+					pending.push(next);
+				}
+			}
+		}
+		if complete && towards_routers==0 && current!=target
+		{
+			return Err(error!(unsupported_configuration).with_message(format!("The escape routing offers no candidate at router {} towards router {}, for packets entering the escape channels at router {}.",current,target,start)));
+		}
+	}
+	Ok(pairs)
+}
+
+///Searches a cycle in the graph of `dependencies`. Each element of the cycle is a channel followed by the dependency towards the next element.
+fn find_dependency_cycle(dependencies:&BTreeMap<Channel,BTreeSet<Dependency>>) -> Option<Vec<(Channel,usize,bool)>>
+{
+	//The channels in the current branch of the search are `false`; those completely explored are `true`.
+	let mut completed : BTreeMap<Channel,bool> = BTreeMap::new();
+	for &root in dependencies.keys()
+	{
+		if completed.contains_key(&root)
+		{
+			continue;
+		}
+		completed.insert(root,false);
+		//Each channel in the branch with the dependencies still to explore, and the last one explored.
+		let mut branch : Vec<(Channel,Vec<Dependency>,Option<Dependency>)> = vec![(root,dependencies[&root].iter().rev().copied().collect(),None)];
+		while let Some((_,remaining,last)) = branch.last_mut()
+		{
+			match remaining.pop()
+			{
+				Some(dependency) =>
+				{
+					*last = Some(dependency);
+					let next = dependency.0;
+					match completed.get(&next)
+					{
+						None =>
+						{
+							completed.insert(next,false);
+							let next_dependencies = dependencies.get(&next).map(|set|set.iter().rev().copied().collect()).unwrap_or_default();
+							branch.push((next,next_dependencies,None));
+						},
+						Some(false) =>
+						{
+							let position = branch.iter().position(|(channel,_,_)|*channel==next).unwrap();
+							return Some(branch[position..].iter().map(|(channel,_,last)|{
+								let (_,target,indirect) = last.unwrap();
+								(*channel,target,indirect)
+							}).collect());
+						},
+						Some(true) => (),
+					}
+				},
+				None =>
+				{
+					let (channel,_,_) = branch.pop().unwrap();
+					completed.insert(channel,true);
+				},
+			}
+		}
+	}
+	None
+}
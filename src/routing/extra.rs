@@ -5,21 +5,23 @@ Extra implementations of routing operations
 * Sum (struct SumRouting)
 * Stubborn
 * EachLengthSourceAdaptiveRouting
+* Convergence
+* ChoiceRegret
 
 */
 
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
 use std::ops::Deref;
 
 use ::rand::{rngs::StdRng,Rng};
-use rand::SeedableRng;
 
-use crate::match_object_panic;
+use crate::{match_object,error,source_location};
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::matrix::Matrix;
-use crate::pattern::{new_pattern, Pattern, PatternBuilderArgument};
+use crate::pattern::{try_new_pattern, Pattern, PatternBuilderArgument};
 use crate::routing::*;
 use crate::topology::prelude::*;
 //use crate::topology::{Topology,Location};
@@ -270,6 +272,16 @@ impl Routing for SumRouting
 		self.routing[0].initialize(topology,rng);
 		self.routing[1].initialize(topology,rng);
 	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		self.routing[0].check_router_configuration(topology,routers)?;
+		self.routing[1].check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing[0].on_topology_change(topology,rng);
+		self.routing[1].on_topology_change(topology,rng);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		use sum_routing_internal::{SumRoutingSelection,SumRoutingCase::*};
@@ -333,14 +345,21 @@ impl Routing for SumRouting
 			None
 		}
 	}
-	fn reset_statistics(&mut self, _next_cycle:Time)
+	fn reset_statistics(&mut self, next_cycle:Time)
 	{
+		self.routing[0].reset_statistics(next_cycle);
+		self.routing[1].reset_statistics(next_cycle);
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing[0].advance_cycle(cycle);
+		self.routing[1].advance_cycle(cycle);
 	}
 }
 
 impl SumRouting
 {
-	pub fn new(arg: RoutingBuilderArgument) -> SumRouting
+	pub fn new(arg: RoutingBuilderArgument) -> Result<SumRouting,Error>
 	{
 		let mut policy=None;
 		let mut first_routing=None;
@@ -350,26 +369,26 @@ impl SumRouting
 		let mut first_extra_label=0i32;
 		let mut second_extra_label=0i32;
 		let mut enabled_statistics=false;
-		match_object_panic!(arg.cv,"Sum",value,
+		match_object!(arg.cv,"Sum",value,
 			"policy" => policy=Some(new_sum_routing_policy(value)),
-			"first_routing" => first_routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"second_routing" => second_routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"first_routing" => first_routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"second_routing" => second_routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
 			"first_allowed_virtual_channels" => first_allowed_virtual_channels = Some(value.as_array()
-				.expect("bad value for first_allowed_virtual_channels").iter()
+				?.iter()
 				.map(|v|v.as_f64().expect("bad value in first_allowed_virtual_channels") as usize).collect()),
 			"second_allowed_virtual_channels" => second_allowed_virtual_channels = Some(value.as_array()
-				.expect("bad value for second_allowed_virtual_channels").iter()
+				?.iter()
 				.map(|v|v.as_f64().expect("bad value in second_allowed_virtual_channels") as usize).collect()),
-			"first_extra_label" => first_extra_label = value.as_f64().expect("bad value for first_extra_label") as i32,
-			"second_extra_label" => second_extra_label = value.as_f64().expect("bad value for second_extra_label") as i32,
-			"enabled_statistics" => enabled_statistics = value.as_bool().expect("bad value for enabled_statistics"),
+			"first_extra_label" => first_extra_label = value.as_f64()? as i32,
+			"second_extra_label" => second_extra_label = value.as_f64()? as i32,
+			"enabled_statistics" => enabled_statistics = value.as_bool()?,
 		);
-		let policy=policy.expect("There were no policy");
-		let first_routing=first_routing.expect("There were no first_routing");
-		let second_routing=second_routing.expect("There were no second_routing");
-		let first_allowed_virtual_channels=first_allowed_virtual_channels.expect("There were no first_allowed_virtual_channels");
-		let second_allowed_virtual_channels=second_allowed_virtual_channels.expect("There were no second_allowed_virtual_channels");
-		SumRouting{
+		let policy=policy.ok_or_else(||arg.cv.ill("There were no policy"))?;
+		let first_routing=first_routing.ok_or_else(||arg.cv.ill("There were no first_routing"))?;
+		let second_routing=second_routing.ok_or_else(||arg.cv.ill("There were no second_routing"))?;
+		let first_allowed_virtual_channels=first_allowed_virtual_channels.ok_or_else(||arg.cv.ill("There were no first_allowed_virtual_channels"))?;
+		let second_allowed_virtual_channels=second_allowed_virtual_channels.ok_or_else(||arg.cv.ill("There were no second_allowed_virtual_channels"))?;
+		Ok(SumRouting{
 			policy,
 			//first_routing,
 			//second_routing,
@@ -382,7 +401,7 @@ impl SumRouting
 			extra_label: [first_extra_label, second_extra_label],
 			enabled_statistics,
 			tracked_hops: RefCell::new([0,0]),
-		}
+		})
 	}
 }
 
@@ -498,6 +517,14 @@ impl Routing for Stubborn
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		self.routing.check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		let &CandidateEgress{port,virtual_channel,ref annotation,..} = requested;
@@ -515,20 +542,24 @@ impl Routing for Stubborn
 		}
 		//otherwise it is direct to server
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle);
+	}
 }
 
 impl Stubborn
 {
-	pub fn new(arg: RoutingBuilderArgument) -> Stubborn
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Stubborn,Error>
 	{
 		let mut routing=None;
-		match_object_panic!(arg.cv,"Stubborn",value,
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+		match_object!(arg.cv,"Stubborn",value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
 		);
-		let routing=routing.expect("There were no routing");
-		Stubborn{
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		Ok(Stubborn{
 			routing,
-		}
+		})
 	}
 }
 
@@ -542,6 +573,21 @@ pub struct EachLengthSourceAdaptiveRouting
 	pub routing: Box<dyn InstantiableSourceRouting>,
 }
 
+impl EachLengthSourceAdaptiveRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<EachLengthSourceAdaptiveRouting,Error>
+	{
+		let mut routing=None;
+		match_object!(arg.cv,"EachLengthSourceAdaptive",value,
+			"routing" => routing=Some(try_new_source_routing(RoutingBuilderArgument{cv:value,..arg})?),
+		);
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		Ok(EachLengthSourceAdaptiveRouting{
+			routing,
+		})
+	}
+}
+
 impl Routing for EachLengthSourceAdaptiveRouting
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
@@ -671,6 +717,10 @@ impl Routing for EachLengthSourceAdaptiveRouting
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+	}
 }
 
 
@@ -744,31 +794,67 @@ impl Routing for AdaptiveStart
 			}
 		}
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.routing.advance_cycle(cycle);
+	}
 }
 
 impl AdaptiveStart
 {
-	pub fn new(arg: RoutingBuilderArgument) -> AdaptiveStart
+	pub fn new(arg: RoutingBuilderArgument) -> Result<AdaptiveStart,Error>
 	{
 		let mut adaptive_hops = None;
 		let mut routing = None;
 		let mut adaptive_label = 0i32;
-		match_object_panic!(arg.cv,"AdaptiveStart",value,
-			"adaptive_hops" => adaptive_hops = Some(value.as_usize().expect("bad value for adaptive_hops")),
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"adaptive_label" => adaptive_label = value.as_i32().expect("bad value for adaptive_label"),
+		match_object!(arg.cv,"AdaptiveStart",value,
+			"adaptive_hops" => adaptive_hops = Some(value.as_usize()?),
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"adaptive_label" => adaptive_label = value.as_i32()?,
 		);
-		let adaptive_hops = adaptive_hops.expect("missing adaptive_hops");
+		let adaptive_hops = adaptive_hops.ok_or_else(||arg.cv.ill("missing adaptive_hops"))?;
 		if adaptive_hops == 0 {
-			panic!("adaptive_hops cannot be 0.");
+			return Err(arg.cv.ill("adaptive_hops cannot be 0."));
 		}
-		let routing = routing.expect("missing routing");
-		AdaptiveStart{
+		let routing = routing.ok_or_else(||arg.cv.ill("missing routing"))?;
+		Ok(AdaptiveStart{
 			adaptive_hops,
 			routing,
 			adaptive_label,
+		})
+	}
+}
+
+/**
+A topology declared inside a routing configuration. It is built when the routing is initialized, so that any randomness
+in its construction is taken from the generator of the simulation, which is seeded with its `random_seed`.
+**/
+#[derive(Debug)]
+struct DeferredTopology
+{
+	cv: ConfigurationValue,
+	plugs: Plugs,
+	topology: Option<Box<dyn Topology>>,
+}
+
+impl DeferredTopology
+{
+	fn new(cv:&ConfigurationValue, plugs:&Plugs) -> DeferredTopology
+	{
+		DeferredTopology{
+			cv: cv.clone(),
+			plugs: plugs.clone(),
+			topology: None,
 		}
 	}
+	fn build(&mut self, rng:&mut StdRng)
+	{
+		self.topology = Some(new_topology(TopologyBuilderArgument{cv:&self.cv,plugs:&self.plugs,rng}));
+	}
+	fn get(&self) -> &dyn Topology
+	{
+		self.topology.as_deref().expect("The topology is built when the routing is initialized")
+	}
 }
 
 /**
@@ -795,7 +881,7 @@ SubTopologyRouting{
 #[derive(Debug)]
 pub struct SubTopologyRouting
 {
-	logical_topology: Box<dyn Topology>,
+	logical_topology: DeferredTopology,
 	map: Box<dyn Pattern>,
 	physical_to_logical: Vec<usize>,
 	logical_to_physical: Vec<usize>,
@@ -825,11 +911,11 @@ impl Routing for SubTopologyRouting
 
 		let logical_current = self.physical_to_logical[current_router];
 		let logical_target  = self.physical_to_logical[ target_router];
-		let logical_candidates = self.logical_routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(), self.logical_topology.as_ref(), logical_current, logical_target, None, num_virtual_channels, rng)?;
+		let logical_candidates = self.logical_routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(), self.logical_topology.get(), logical_current, logical_target, None, num_virtual_channels, rng)?;
 		let mut candidates =vec![];
 		for CandidateEgress{port,virtual_channel,label: _,annotation,..} in logical_candidates.candidates
 		{
-			let Location::RouterPort{router_index: next_physical_router, .. } = self.logical_topology.neighbour(logical_current, port).0 else { panic!("There should be a port") };
+			let Location::RouterPort{router_index: next_physical_router, .. } = self.logical_topology.get().neighbour(logical_current, port).0 else { panic!("There should be a port") };
 			let physical_port = topology.neighbour_router_iter(current_router).find(|item| item.neighbour_router == next_physical_router).expect("port not found").port_index;
 			//let physical_label = label;
 
@@ -855,7 +941,7 @@ impl Routing for SubTopologyRouting
 			{
 				let physical_neighbour = neighbour.neighbour_router;
 				let logical_neighbour = self.physical_to_logical[physical_neighbour];
-				if self.logical_topology.distance(logical_neighbour, logical_target) < self.logical_topology.distance(logical_current, logical_target)
+				if self.logical_topology.get().distance(logical_neighbour, logical_target) < self.logical_topology.get().distance(logical_current, logical_target)
 					&& *self.logical_topology_connections.get(current_router, physical_neighbour) == 0
 				{
 					let physical_port = neighbour.port_index;
@@ -887,7 +973,7 @@ impl Routing for SubTopologyRouting
 		let mut bri = routing_info.borrow_mut();
 		bri.meta = Some(vec![ RefCell::new(RoutingInfo::new()) ]);
 		let bri_sub = &bri.meta.as_ref().unwrap()[0];
-		self.logical_routing.initialize_routing_info(bri_sub, self.logical_topology.as_ref(), logical_current, logical_target, None, rng);
+		self.logical_routing.initialize_routing_info(bri_sub, self.logical_topology.get(), logical_current, logical_target, None, rng);
 	}
 
 	fn update_routing_info(&self, routing_info: &RefCell<RoutingInfo>, topology: &dyn Topology, current_router: usize, current_port: usize, target_router: usize, _target_server: Option<usize>, rng: &mut StdRng) {
@@ -900,16 +986,16 @@ impl Routing for SubTopologyRouting
 		let mut routing_info = routing_info.borrow_mut();
 		if let Location::RouterPort{router_index: previous_physical_router,..} = previous_physical_router_loc {
 			let prev_logical_router = self.physical_to_logical[previous_physical_router];
-			if let Some(a) = self.logical_topology.neighbour_router_iter(logical_current)
+			if let Some(a) = self.logical_topology.get().neighbour_router_iter(logical_current)
 				.find(|item| item.neighbour_router == prev_logical_router)
 			{
 				let logical_port  = a.port_index;
-				self.logical_routing.update_routing_info(&(routing_info.meta.as_ref().unwrap()[0]), self.logical_topology.as_ref(), logical_current, logical_port, logical_target, None, rng);
+				self.logical_routing.update_routing_info(&(routing_info.meta.as_ref().unwrap()[0]), self.logical_topology.get(), logical_current, logical_port, logical_target, None, rng);
 			}else{
 				let routing_info_sub = RefCell::new(RoutingInfo::new());
 				routing_info.meta = Some(vec![routing_info_sub]);
 
-				self.logical_routing.initialize_routing_info(&routing_info.meta.as_ref().unwrap()[0], self.logical_topology.as_ref(), logical_current, logical_target, None, rng);
+				self.logical_routing.initialize_routing_info(&routing_info.meta.as_ref().unwrap()[0], self.logical_topology.get(), logical_current, logical_target, None, rng);
 			}
 		}else {
 			panic!("!!")
@@ -917,9 +1003,13 @@ impl Routing for SubTopologyRouting
 	}
 
 	fn initialize(&mut self, topology: &dyn Topology, rng: &mut StdRng) {
-
-		self.map.initialize(self.logical_topology.num_routers(), self.logical_topology.num_routers(), self.logical_topology.as_ref(), rng);
-		for i in 0..self.logical_topology.num_routers() {
+		self.logical_topology.build(rng);
+		let logical_topology = self.logical_topology.get();
+		let logical_routers = logical_topology.num_routers();
+		self.physical_to_logical = vec![0; logical_routers];
+		self.logical_to_physical = vec![0; logical_routers];
+		self.map.initialize(logical_routers, logical_routers, logical_topology, rng);
+		for i in 0..logical_routers {
 			let physical = self.map.get_destination(i, topology, rng);
 			self.logical_to_physical[i] = physical;
 			self.physical_to_logical[physical] = i;
@@ -928,9 +1018,9 @@ impl Routing for SubTopologyRouting
 		//Check that neighbours in the logical topology are neighbours in the physical topology
 		self.logical_topology_connections = Matrix::constant(0,topology.num_routers(), topology.num_routers()); //physical matrix but logical connections
 		//TODO: Reduce the complexity of this operation.
-		for i in 0..self.logical_topology.num_routers() {
+		for i in 0..logical_routers {
 			let physical_i = self.logical_to_physical[i];
-			for NeighbourRouterIteratorItem{neighbour_router,..} in self.logical_topology.neighbour_router_iter(i) {
+			for NeighbourRouterIteratorItem{neighbour_router,..} in logical_topology.neighbour_router_iter(i) {
 				let physical_neighbour = self.logical_to_physical[neighbour_router];
 				let neighbour = topology.neighbour_router_iter(physical_i).find(|item| item.neighbour_router == physical_neighbour).is_some();
 				assert!(neighbour);
@@ -940,47 +1030,45 @@ impl Routing for SubTopologyRouting
 		}
 		// println!("logical_topology_connections={:?}",self.logical_topology_connections);
 
-		self.logical_routing.initialize(self.logical_topology.as_ref(), rng);
+		self.logical_routing.initialize(logical_topology, rng);
 	}
 
 	fn statistics(&self, _cycle: Time) -> Option<ConfigurationValue> {
 		None
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.logical_routing.advance_cycle(cycle);
+	}
 }
 
 impl SubTopologyRouting
 {
-	pub fn new(arg: RoutingBuilderArgument) -> SubTopologyRouting
+	pub fn new(arg: RoutingBuilderArgument) -> Result<SubTopologyRouting,Error>
 	{
 		let mut logical_topology = None;
 		let mut map = None;
 		let mut logical_routing = None;
 		let mut opportunistic_hops = false;
-		//new rng for the subtopology
-		let rng =  &mut StdRng::from_entropy();
-		match_object_panic!(arg.cv,"SubTopologyRouting",value,
-			"logical_topology" => logical_topology = Some(new_topology(TopologyBuilderArgument{cv:value, rng, plugs: arg.plugs})),
-			"map" => map = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})), //map of the application over the machine
-			"logical_routing" => logical_routing = Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"opportunistic_hops" => opportunistic_hops = value.as_bool().expect("bad value for opportunistic_hops"),
+		match_object!(arg.cv,"SubTopologyRouting",value,
+			"logical_topology" => logical_topology = Some(DeferredTopology::new(value,arg.plugs)),
+			"map" => map = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?), //map of the application over the machine
+			"logical_routing" => logical_routing = Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"opportunistic_hops" => opportunistic_hops = value.as_bool()?,
 		);
-		let logical_topology = logical_topology.expect("missing topology");
-		let map = map.expect("missing physical_to_logical");
-		let logical_routing = logical_routing.expect("missing routing");
-
-		let physical_to_logical = vec![0; logical_topology.num_routers()];
-		let logical_to_physical = vec![0; logical_topology.num_routers()];
+		let logical_topology = logical_topology.ok_or_else(||arg.cv.ill("missing topology"))?;
+		let map = map.ok_or_else(||arg.cv.ill("missing physical_to_logical"))?;
+		let logical_routing = logical_routing.ok_or_else(||arg.cv.ill("missing routing"))?;
 
-
-		SubTopologyRouting {
+		Ok(SubTopologyRouting {
 			logical_topology,
 			map,
-			physical_to_logical,
-			logical_to_physical,
+			physical_to_logical: vec![],
+			logical_to_physical: vec![],
 			logical_topology_connections: Matrix::constant(0,0,0),
 			logical_routing,
 			opportunistic_hops,
-		}
+		})
 	}
 }
 
@@ -1042,7 +1130,7 @@ pub struct RegionRouting
 	selected_region_size: Vec<usize>,
 	physical_to_logical_vector: Vec<Vec<usize>>,
 	logical_to_physical_vector: Vec<Vec<usize>>,
-	region_logical_topology: Vec<Box<dyn Topology>>,
+	region_logical_topology: Vec<DeferredTopology>,
 	routings: Vec<Box<dyn Routing>>,
 	extra_label_selection: i32,
 	default_routing: Box<dyn Routing>,
@@ -1071,7 +1159,7 @@ impl Routing for RegionRouting
 		let default_bri = routing_info.meta.as_ref().unwrap()[0].borrow();
 		let next = self.default_routing.next(default_bri.deref(), topology, current_router, target_router, target_server, num_virtual_channels, rng)?;
 		let mut candidates = vec![];
-		let mut selections = HashSet::new();
+		let mut selections = BTreeSet::new();
 		'outer: for CandidateEgress{port, virtual_channel, label, annotation, router_allows, estimated_remaining_hops} in next.candidates
 		{
 			let Location::RouterPort {router_index: next_router, router_port:_} = topology.neighbour(current_router, port).0 else { panic!("There should be a port")};
@@ -1094,10 +1182,10 @@ impl Routing for RegionRouting
 			let target_logical = self.physical_to_logical_vector[i][target_router];
 			if current_logical != target_logical
 			{
-				let next = self.routings[i].next(selected_bri.borrow().deref(), self.region_logical_topology[i].as_ref(), current_logical, target_logical, None, num_virtual_channels, rng)?;
+				let next = self.routings[i].next(selected_bri.borrow().deref(), self.region_logical_topology[i].get(), current_logical, target_logical, None, num_virtual_channels, rng)?;
 				for CandidateEgress{port, virtual_channel, label, annotation, router_allows, estimated_remaining_hops} in next.candidates
 				{
-					let Location::RouterPort {router_index: next_router, router_port:_port_logical} = self.region_logical_topology[i].neighbour(current_logical, port).0 else { panic!("There should be a port")};
+					let Location::RouterPort {router_index: next_router, router_port:_port_logical} = self.region_logical_topology[i].get().neighbour(current_logical, port).0 else { panic!("There should be a port")};
 					let next_physical = self.logical_to_physical_vector[i][next_router];
 					let physical_port = topology.neighbour_router_iter(current_router).find(|item| item.neighbour_router == next_physical).expect("port not found").port_index;
 					candidates.push(CandidateEgress{port:physical_port, virtual_channel, label: label + self.extra_label_selection, annotation, router_allows, estimated_remaining_hops});
@@ -1118,7 +1206,7 @@ impl Routing for RegionRouting
 			let routing_info = RefCell::new(RoutingInfo::new());
 			let current_logical = self.physical_to_logical_vector[i][current_router];
 			let target_logical = self.physical_to_logical_vector[i][target_router];
-			routing.initialize_routing_info(&routing_info, self.region_logical_topology[i].as_ref(), current_logical, target_logical, target_server, rng);
+			routing.initialize_routing_info(&routing_info, self.region_logical_topology[i].get(), current_logical, target_logical, target_server, rng);
 			all_routing_info.push(routing_info);
 		}
 		routing_info.borrow_mut().meta = Some(all_routing_info);
@@ -1147,7 +1235,7 @@ impl Routing for RegionRouting
 				let routing_bri = &(bri.meta.as_ref().unwrap()[i + 1]);
 				let current_logical = self.physical_to_logical_vector[i][current_router];
 				let target_logical = self.physical_to_logical_vector[i][target_router];
-				self.routings[i].initialize_routing_info(routing_bri, self.region_logical_topology[i].as_ref(), current_logical, target_logical, target_server, rng);
+				self.routings[i].initialize_routing_info(routing_bri, self.region_logical_topology[i].get(), current_logical, target_logical, target_server, rng);
 			}
 		}
 
@@ -1159,10 +1247,10 @@ impl Routing for RegionRouting
 			let Location::RouterPort {router_index: previous_physical_router, router_port:_} = topology.neighbour(current_router, current_port).0 else { panic!("There should be a port")};
 			let previous_logical_router = self.physical_to_logical_vector[pattern][previous_physical_router];
 			//now get the logical port iterating the logical neighbours
-			let logical_port = self.region_logical_topology[pattern].neighbour_router_iter(current_logical).find(|item| item.neighbour_router == previous_logical_router).expect("port not found").port_index;
+			let logical_port = self.region_logical_topology[pattern].get().neighbour_router_iter(current_logical).find(|item| item.neighbour_router == previous_logical_router).expect("port not found").port_index;
 
 			let routing_bri = &(bri.meta.as_ref().unwrap()[pattern + 1]);
-			self.routings[pattern].update_routing_info(routing_bri, self.region_logical_topology[pattern].as_ref(), current_logical, logical_port, target_logical, target_server, rng);
+			self.routings[pattern].update_routing_info(routing_bri, self.region_logical_topology[pattern].get(), current_logical, logical_port, target_logical, target_server, rng);
 			// if  current_router != target_router {
 			// 	//if the next in the default routing is not giving more candidates for this pattern in the current router, update the default routing
 			// 	let bri_default = bri.meta.as_ref().unwrap()[0].borrow();
@@ -1190,6 +1278,9 @@ impl Routing for RegionRouting
 	}
 
 	fn initialize(&mut self, topology: &dyn Topology, rng: &mut StdRng) {
+		for logical_topology in self.region_logical_topology.iter_mut() {
+			logical_topology.build(rng);
+		}
 		for (i, pat) in self.physical_to_logical.iter_mut().enumerate() {
 			pat.initialize(topology.num_routers(), self.selected_region_size[i], topology, rng);
 			let mut physical_to_logical = vec![0; topology.num_routers()];
@@ -1216,11 +1307,26 @@ impl Routing for RegionRouting
 			routing.initialize(topology, rng);
 		}
 	}
+	fn check_router_configuration(&self, topology: &dyn Topology, routers: &RouterConfigurationSummary) -> Result<(),Error> {
+		self.default_routing.check_router_configuration(topology, routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.default_routing.on_topology_change(topology,rng);
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		for routing in self.routings.iter_mut()
+		{
+			routing.advance_cycle(cycle);
+		}
+		self.default_routing.advance_cycle(cycle);
+	}
 }
 
 impl RegionRouting
 {
-	pub fn new(arg: RoutingBuilderArgument) -> RegionRouting
+	pub fn new(arg: RoutingBuilderArgument) -> Result<RegionRouting,Error>
 	{
 		let mut physical_to_logical = vec![];
 		let mut logical_to_physical = vec![];
@@ -1229,32 +1335,32 @@ impl RegionRouting
 		let mut routings = vec![];
 		let mut default_routing = None;
 		let mut extra_label_selection = 0;
-		match_object_panic!(arg.cv,"RegionRouting",value,
-			"physical_to_logical" => physical_to_logical = value.as_array().expect("bad value for selection_patterns").iter().map(|v|new_pattern(PatternBuilderArgument{cv:v,plugs:arg.plugs})).collect(),
-			"logical_to_physical" => logical_to_physical = value.as_array().expect("bad value for map_region").iter().map(|v|new_pattern(PatternBuilderArgument{cv:v,plugs:arg.plugs})).collect(),
-			"selected_region_size" => selected_region_size = value.as_array().expect("bad value for selected_size").iter().map(|v|v.as_usize().expect("bad value in selected_size")).collect(),
-			"region_logical_topology" => region_logical_topology = value.as_array().expect("bad value for region_logical_topology").iter().map(|v|new_topology(TopologyBuilderArgument{cv:v,plugs:arg.plugs,rng: &mut StdRng::from_entropy()})).collect(),
-			"routings" => routings = value.as_array().expect("bad value for routings").iter().map(|v|new_routing(RoutingBuilderArgument{cv:v,plugs:arg.plugs})).collect(),
-			"extra_label_selection" => extra_label_selection = value.as_i32().expect("bad value for extra_label_selection"),
-			"default_routing" => default_routing = Some(new_routing(RoutingBuilderArgument{cv:value,plugs:arg.plugs})),
+		match_object!(arg.cv,"RegionRouting",value,
+			"physical_to_logical" => physical_to_logical = value.as_array()?.iter().map(|v|try_new_pattern(PatternBuilderArgument{cv:v,plugs:arg.plugs})).collect::<Result<_,_>>()?,
+			"logical_to_physical" => logical_to_physical = value.as_array()?.iter().map(|v|try_new_pattern(PatternBuilderArgument{cv:v,plugs:arg.plugs})).collect::<Result<_,_>>()?,
+			"selected_region_size" => selected_region_size = value.as_array()?.iter().map(|v|v.as_usize()).collect::<Result<_,_>>()?,
+			"region_logical_topology" => region_logical_topology = value.as_array()?.iter().map(|v|DeferredTopology::new(v,arg.plugs)).collect(),
+			"routings" => routings = value.as_array()?.iter().map(|v|try_new_routing(RoutingBuilderArgument{cv:v,plugs:arg.plugs})).collect::<Result<_,_>>()?,
+			"extra_label_selection" => extra_label_selection = value.as_i32()?,
+			"default_routing" => default_routing = Some(try_new_routing(RoutingBuilderArgument{cv:value,plugs:arg.plugs})?),
 		);
-		let default_routing = default_routing.expect("missing default_routing");
+		let default_routing = default_routing.ok_or_else(||arg.cv.ill("missing default_routing"))?;
 		//Check that the sizes are correct
 		if physical_to_logical.len() != selected_region_size.len() {
-			panic!("The number of selection_patterns and selected_size must be the same.");
+			return Err(arg.cv.ill("The number of selection_patterns and selected_size must be the same."));
 		}
 		if physical_to_logical.len() != routings.len() {
-			panic!("The number of selection_patterns and routings must be the same.");
+			return Err(arg.cv.ill("The number of selection_patterns and routings must be the same."));
 		}
 		if logical_to_physical.len() != physical_to_logical.len() {
-			panic!("The number of map_region and selection_patterns must be the same.");
+			return Err(arg.cv.ill("The number of map_region and selection_patterns must be the same."));
 		}
 		if region_logical_topology.len() != logical_to_physical.len() {
-			panic!("The number of region_logical_topology and map_region must be the same.");
+			return Err(arg.cv.ill("The number of region_logical_topology and map_region must be the same."));
 		}
 
 		let len = physical_to_logical.len();
-		RegionRouting {
+		Ok(RegionRouting {
 			physical_to_logical,
 			logical_to_physical,
 			selected_region_size,
@@ -1264,6 +1370,446 @@ impl RegionRouting
 			routings,
 			extra_label_selection,
 			default_routing,
+		})
+	}
+}
+
+/**
+Simulates the convergence of the control plane after a change in the topology, instead of assuming instant global knowledge.
+Two instances of `routing` are built. One is initialized with the `stale_topology`, which is the topology as it was known before the faults,
+and the other is initialized with the actual topology. The faults are considered to happen at cycle 0 and during the first `convergence_delay`
+cycles packets are routed with the stale tables. Afterwards new packets use the recomputed ones, and packets in flight change to them at their next hop.
+
+A router knows the state of its own links, so stale candidates towards a removed link are discarded. When no stale candidate remains the packet
+is deflected through any alive link if `deflect` is true, which may make it loop, or it waits for the convergence otherwise.
+
+Since the stale topology is built by the routing itself it should not depend on the random generator of the simulation.
+
+```ignore
+Convergence{
+	routing: Shortest,
+	stale_topology: Hamming{ sides:[6,6], servers_per_router:6 },
+	convergence_delay: 2000,
+	deflect: true,//optional, defaults to true
+	enable_statistics: true,//optional, defaults to false
+	legend_name: "minimal with 2000 cycles of convergence",
+}
+```
+**/
+#[derive(Debug)]
+pub struct Convergence
+{
+	///The routing using the tables for the current topology.
+	routing: Box<dyn Routing>,
+	///The routing using the tables computed before the change.
+	stale_routing: Box<dyn Routing>,
+	///The topology as known before the change.
+	stale_topology: DeferredTopology,
+	///Cycles after the change during which the stale tables are used.
+	convergence_delay: Time,
+	///Whether to send packets through any alive link when the stale tables only give removed links.
+	deflect: bool,
+	///The current cycle, as given by `advance_cycle`.
+	cycle: Time,
+	enabled_statistics: bool,
+	///Requests made with the stale tables, including deflections.
+	stale_requests: RefCell<usize>,
+	///Requests made by deflecting the packet.
+	deflected_requests: RefCell<usize>,
+}
+
+impl Routing for Convergence
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let meta_info = &routing_info.meta.as_ref().unwrap()[0];
+		let is_stale = routing_info.selections.as_ref().map(|s|s[0]==0).unwrap_or(false);
+		if !is_stale
+		{
+			return self.routing.next(&meta_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng);
+		}
+		let alive = |port:usize| !matches!(topology.neighbour(current_router,port).0, Location::None);
+		let candidates : Vec<CandidateEgress> = self.stale_routing.next(&meta_info.borrow(),self.stale_topology.get(),current_router,target_router,target_server,num_virtual_channels,rng)?
+			.into_iter().filter(|candidate|alive(candidate.port))
+			.map(|candidate|CandidateEgress{annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate}).collect();
+		if !candidates.is_empty() || !self.deflect
+		{
+			//The candidates will change when converging, so they are never idempotent.
+			return Ok(RoutingNextCandidates{candidates,idempotent:false});
+		}
+		let candidates = topology.neighbour_router_iter(current_router).filter(|item|alive(item.port_index)).flat_map(|item|
+			(0..num_virtual_channels).map(move |vc|CandidateEgress{label:1,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![]}),..CandidateEgress::new(item.port_index,vc)})
+		).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent:false})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let meta_routing_info=RefCell::new(RoutingInfo::new());
+		let mut bri = routing_info.borrow_mut();
+		if self.is_converged()
+		{
+			self.routing.initialize_routing_info(&meta_routing_info, topology, current_router, target_router, target_server, rng);
+			bri.selections = Some(vec![1]);
+		}
+		else
+		{
+			self.stale_routing.initialize_routing_info(&meta_routing_info, self.stale_topology.get(), current_router, target_router, target_server, rng);
+			bri.selections = Some(vec![0]);
+		}
+		bri.meta = Some(vec![meta_routing_info]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let is_stale = routing_info.borrow().selections.as_ref().map(|s|s[0]==0).unwrap_or(false);
+		if is_stale && self.is_converged()
+		{
+			//The packet was routed with the stale tables and continues with the recomputed ones from here.
+			self.initialize_routing_info(routing_info, topology, current_router, target_router, target_server, rng);
+			return;
+		}
+		let bri = routing_info.borrow();
+		let meta_info = &bri.meta.as_ref().unwrap()[0];
+		if is_stale
+		{
+			self.stale_routing.update_routing_info(meta_info, self.stale_topology.get(), current_router, current_port, target_router, target_server, rng);
+		}
+		else
+		{
+			self.routing.update_routing_info(meta_info, topology, current_router, current_port, target_router, target_server, rng);
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.stale_topology.build(rng);
+		if topology.num_routers() != self.stale_topology.get().num_routers()
+		{
+			panic!("The stale topology of Convergence has {} routers, but the network has {}.",self.stale_topology.get().num_routers(),topology.num_routers());
+		}
+		self.routing.initialize(topology,rng);
+		self.stale_routing.initialize(self.stale_topology.get(),rng);
+	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		self.routing.check_router_configuration(topology,routers)?;
+		self.stale_routing.check_router_configuration(self.stale_topology.get(),routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		let bri = routing_info.borrow();
+		let meta_info = &bri.meta.as_ref().unwrap()[0];
+		let is_stale = bri.selections.as_ref().map(|s|s[0]==0).unwrap_or(false);
+		if !is_stale
+		{
+			self.routing.performed_request(requested,meta_info,topology,current_router,target_router,target_server,num_virtual_channels,rng);
+			return;
+		}
+		*self.stale_requests.borrow_mut() += 1;
+		if let Some(annotation) = requested.annotation.as_ref()
+		{
+			if annotation.values[0]==1
+			{
+				*self.deflected_requests.borrow_mut() += 1;
+			}
+			else
+			{
+				let meta_requested = CandidateEgress{annotation:annotation.meta[0].clone(),..*requested};
+				self.stale_routing.performed_request(&meta_requested,meta_info,self.stale_topology.get(),current_router,target_router,target_server,num_virtual_channels,rng);
+			}
+		}
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.cycle = cycle;
+		self.routing.advance_cycle(cycle);
+		self.stale_routing.advance_cycle(cycle);
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		if self.enabled_statistics {
+			let mut content = vec![
+				(String::from("stale_requests"),ConfigurationValue::Number(*self.stale_requests.borrow() as f64)),
+				(String::from("deflected_requests"),ConfigurationValue::Number(*self.deflected_requests.borrow() as f64)),
+			];
+			if let Some(inner)=self.routing.statistics(cycle)
+			{
+				content.push( (String::from("routing_statistics"),inner) );
+			}
+			Some(ConfigurationValue::Object(String::from("ConvergenceStatistics"),content))
+		} else {
+			None
+		}
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		*self.stale_requests.borrow_mut() = 0;
+		*self.deflected_requests.borrow_mut() = 0;
+		self.routing.reset_statistics(next_cycle);
+		self.stale_routing.reset_statistics(next_cycle);
+	}
+}
+
+impl Convergence
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Convergence,Error>
+	{
+		let mut routing = None;
+		let mut stale_routing = None;
+		let mut stale_topology = None;
+		let mut convergence_delay = None;
+		let mut deflect = true;
+		let mut enabled_statistics = false;
+		match_object!(arg.cv,"Convergence",value,
+			"routing" => {
+				routing = Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?);
+				stale_routing = Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?);
+			},
+			"stale_topology" => stale_topology = Some(DeferredTopology::new(value,arg.plugs)),
+			"convergence_delay" => convergence_delay = Some(value.as_time()?),
+			"deflect" => deflect = value.as_bool()?,
+			"enable_statistics" => enabled_statistics = value.as_bool()?,
+		);
+		let routing = routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let stale_routing = stale_routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let stale_topology = stale_topology.ok_or_else(||arg.cv.ill("There were no stale_topology"))?;
+		let convergence_delay = convergence_delay.ok_or_else(||arg.cv.ill("There were no convergence_delay"))?;
+		Ok(Convergence{
+			routing,
+			stale_routing,
+			stale_topology,
+			convergence_delay,
+			deflect,
+			cycle: 0,
+			enabled_statistics,
+			stale_requests: RefCell::new(0),
+			deflected_requests: RefCell::new(0),
+		})
+	}
+	///Whether the routing tables have been already recomputed.
+	pub fn is_converged(&self) -> bool
+	{
+		self.cycle >= self.convergence_delay
+	}
+}
+
+/**
+Instrumentation of the adaptive decisions of a `routing`, to quantify how good its congestion estimations are.
+Each time the candidates of the routing offer several ports, the latency of each port is estimated as its occupancy plus `hop_latency` cycles for each remaining hop,
+taking the hops from `estimated_remaining_hops` of the candidates or from the distance otherwise. The occupancy is the one given by the router, see [RoutingOccupancy].
+When the packet arrives at its target router, the actual latency since the decision of the port it took is compared against the estimation of the best alternative port.
+The regret of a decision is that actual latency minus the estimation of the best alternative, so that positive values indicate that another port looked better a posteriori.
+
+The statistics include the amount of `decisions`, the `average_latency` since them, the `average_chosen_estimate` and `average_best_alternative_estimate`,
+the `average_regret`, and the `regretted_fraction` of the decisions with positive regret.
+The difference between `average_latency` and `average_chosen_estimate` indicates how accurate the estimations are.
+The decisions made within the router of the destination are not considered.
+
+```ignore
+ChoiceRegret{
+	routing: UGAL{},
+	//Expected cycles per hop without congestion. By default 1.
+	hop_latency: 3,
+	legend_name: "UGAL",
+}
+```
+**/
+#[derive(Debug)]
+pub struct ChoiceRegret
+{
+	routing: Box<dyn Routing>,
+	hop_latency: f64,
+	///The current cycle, as given by `advance_cycle`.
+	cycle: Time,
+	decisions: RefCell<usize>,
+	regretted_decisions: RefCell<usize>,
+	total_latency: RefCell<f64>,
+	total_chosen_estimate: RefCell<f64>,
+	total_best_alternative_estimate: RefCell<f64>,
+}
+
+///The decisions of a packet, kept in the `auxiliar` field of its routing info.
+#[derive(Debug,Default)]
+struct ChoiceRegretDecisions
+{
+	///The router, cycle, and estimation of each port of the last candidates offered with several ports.
+	last: Option<(usize,Time,Vec<(usize,f64)>)>,
+	///The cycle, the estimation of the chosen port, and the estimation of the best alternative of each decision already taken.
+	pending: Vec<(Time,f64,f64)>,
+}
+
+impl Routing for ChoiceRegret
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let meta_info = &routing_info.meta.as_ref().unwrap()[0];
+		self.routing.next(&meta_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let meta_info = &routing_info.meta.as_ref().unwrap()[0];
+		let candidates = self.routing.next_with_occupancy(&meta_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng)?;
+		if current_router==target_router
+		{
+			return Ok(candidates);
+		}
+		if let Some(port_occupancy) = occupancy.port_occupancy
+		{
+			let mut estimates : Vec<(usize,f64)> = vec![];
+			for candidate in candidates.candidates.iter()
+			{
+				let hops = match candidate.estimated_remaining_hops
+				{
+					Some(hops) => hops,
+					None => match topology.neighbour(current_router,candidate.port).0
+					{
+						Location::RouterPort{router_index,..} => 1+topology.distance(router_index,target_router),
+						_ => continue,
+					},
+				};
+				let estimate = port_occupancy[candidate.port] + self.hop_latency*hops as f64;
+				match estimates.iter_mut().find(|(port,_)|*port==candidate.port)
+				{
+					Some((_,value)) => *value = value.min(estimate),
+					None => estimates.push((candidate.port,estimate)),
+				}
+			}
+			let mut auxiliar = routing_info.auxiliar.borrow_mut();
+			if let Some(decisions) = auxiliar.as_mut().and_then(|a|a.downcast_mut::<ChoiceRegretDecisions>())
+			{
+				decisions.last = if estimates.len()>=2 { Some((current_router,self.cycle,estimates)) } else { None };
+			}
 		}
+		Ok(candidates)
+	}
+	fn need_occupancy(&self) -> bool { true }
+	fn need_global_occupancy(&self) -> bool { self.routing.need_global_occupancy() }
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let meta_routing_info=RefCell::new(RoutingInfo::new());
+		self.routing.initialize_routing_info(&meta_routing_info, topology, current_router, target_router, target_server, rng);
+		let mut bri = routing_info.borrow_mut();
+		bri.meta = Some(vec![meta_routing_info]);
+		bri.auxiliar = RefCell::new(Some(Box::new(ChoiceRegretDecisions::default())));
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let bri = routing_info.borrow();
+		let meta_info = &bri.meta.as_ref().unwrap()[0];
+		self.routing.update_routing_info(meta_info, topology, current_router, current_port, target_router, target_server, rng);
+		let mut auxiliar = bri.auxiliar.borrow_mut();
+		let decisions = match auxiliar.as_mut().and_then(|a|a.downcast_mut::<ChoiceRegretDecisions>())
+		{
+			Some(decisions) => decisions,
+			None => return,
+		};
+		//Find the port taken in the previous router to see whether it was a decision.
+		if let (Location::RouterPort{router_index:previous_router,router_port:previous_port},_) = topology.neighbour(current_router,current_port)
+		{
+			if let Some((router,cycle,estimates)) = decisions.last.take()
+			{
+				if router==previous_router
+				{
+					if let Some(&(_,chosen)) = estimates.iter().find(|(port,_)|*port==previous_port)
+					{
+						let best_alternative = estimates.iter().filter(|(port,_)|*port!=previous_port).map(|(_,estimate)|*estimate).fold(f64::INFINITY,f64::min);
+						decisions.pending.push((cycle,chosen,best_alternative));
+					}
+				}
+			}
+		}
+		if current_router==target_router
+		{
+			for (cycle,chosen,best_alternative) in decisions.pending.drain(..)
+			{
+				let latency = (self.cycle-cycle) as f64;
+				*self.decisions.borrow_mut() += 1;
+				*self.total_latency.borrow_mut() += latency;
+				*self.total_chosen_estimate.borrow_mut() += chosen;
+				*self.total_best_alternative_estimate.borrow_mut() += best_alternative;
+				if latency > best_alternative
+				{
+					*self.regretted_decisions.borrow_mut() += 1;
+				}
+			}
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+	}
+	fn check_router_configuration(&self, topology:&dyn Topology, routers:&RouterConfigurationSummary) -> Result<(),Error>
+	{
+		self.routing.check_router_configuration(topology,routers)
+	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.on_topology_change(topology,rng);
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		let bri = routing_info.borrow();
+		let meta_info = &bri.meta.as_ref().unwrap()[0];
+		self.routing.performed_request(requested,meta_info,topology,current_router,target_router,target_server,num_virtual_channels,rng);
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.cycle = cycle;
+		self.routing.advance_cycle(cycle);
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		let decisions = *self.decisions.borrow();
+		let average = |total:f64| if decisions==0 { 0.0 } else { total/decisions as f64 };
+		let total_latency = *self.total_latency.borrow();
+		let total_best_alternative_estimate = *self.total_best_alternative_estimate.borrow();
+		let mut content = vec![
+			(String::from("decisions"),ConfigurationValue::Number(decisions as f64)),
+			(String::from("average_latency"),ConfigurationValue::Number(average(total_latency))),
+			(String::from("average_chosen_estimate"),ConfigurationValue::Number(average(*self.total_chosen_estimate.borrow()))),
+			(String::from("average_best_alternative_estimate"),ConfigurationValue::Number(average(total_best_alternative_estimate))),
+			(String::from("average_regret"),ConfigurationValue::Number(average(total_latency-total_best_alternative_estimate))),
+			(String::from("regretted_fraction"),ConfigurationValue::Number(average(*self.regretted_decisions.borrow() as f64))),
+		];
+		if let Some(inner)=self.routing.statistics(cycle)
+		{
+			content.push( (String::from("routing_statistics"),inner) );
+		}
+		Some(ConfigurationValue::Object(String::from("ChoiceRegretStatistics"),content))
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		*self.decisions.borrow_mut() = 0;
+		*self.regretted_decisions.borrow_mut() = 0;
+		*self.total_latency.borrow_mut() = 0.0;
+		*self.total_chosen_estimate.borrow_mut() = 0.0;
+		*self.total_best_alternative_estimate.borrow_mut() = 0.0;
+		self.routing.reset_statistics(next_cycle);
+	}
+}
+
+impl ChoiceRegret
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<ChoiceRegret,Error>
+	{
+		let mut routing = None;
+		let mut hop_latency = 1.0;
+		match_object!(arg.cv,"ChoiceRegret",value,
+			"routing" => routing = Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"hop_latency" => hop_latency = value.as_f64()?,
+		);
+		let routing = routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		Ok(ChoiceRegret{
+			routing,
+			hop_latency,
+			cycle: 0,
+			decisions: RefCell::new(0),
+			regretted_decisions: RefCell::new(0),
+			total_latency: RefCell::new(0.0),
+			total_chosen_estimate: RefCell::new(0.0),
+			total_best_alternative_estimate: RefCell::new(0.0),
+		})
 	}
 }
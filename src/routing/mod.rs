@@ -3,34 +3,39 @@
 
 A Routing defines the ways to select a next router to eventually reach the destination.
 
-see [`new_routing`](fn.new_routing.html) for documentation on the configuration syntax of predefined routings.
+see [`new_routing, try_new_routing`](fn.new_routing.html) for documentation on the configuration syntax of predefined routings.
 
 */
 
 /// Contains Shortest, Valiant, Mindless, WeighedShortest.
 pub mod basic;
-/// Contains Sum, Stubborn, EachLengthSourceAdaptiveRouting
+/// Contains Sum, Stubborn, EachLengthSourceAdaptiveRouting, Convergence, ChoiceRegret
 pub mod extra;
-/// Contains ChannelsPerHop, ChannelsPerHopPerLinkClass, ChannelMap, AscendantChannelsWithLinkClass
+/// Contains ChannelsPerHop, ChannelsPerHopPerLinkClass, ChannelMap, AscendantChannelsWithLinkClass, EscapeChannels
 pub mod channel_operations;
-/// Contains UpDown, UpDownStar.
+/// Contains UpDown, DmodK, UpDownStar.
 pub mod updown;
 pub mod polarized;
+/// Contains KShortestPaths.
+pub mod source;
 
 use crate::topology::dragonfly::DragonflyDirect;
 use std::cell::RefCell;
+use std::rc::Rc;
 use std::fmt::Debug;
 use std::convert::TryFrom;
 
 use ::rand::{rngs::StdRng,Rng,prelude::SliceRandom};
 
 use crate::config_parser::ConfigurationValue;
-use crate::topology::cartesian::{DOR, O1TURN, ValiantDOR, OmniDimensionalDeroute, DimWAR, GENERALTURN, Valiant4Hamming, AdaptiveValiantClos};
-use crate::topology::dragonfly::{PAR, Valiant4Dragonfly};
+use crate::error::SourceLocation;
+use crate::topology::cartesian::{DOR, O1TURN, ValiantDOR, OmniDimensionalDeroute, DimWAR, GENERALTURN, ConfigurableTurnModel, Valiant4Hamming, AdaptiveValiantClos};
+use crate::topology::dragonfly::{PAR, PiggybackPAR, Valiant4Dragonfly};
 use crate::topology::{Topology,Location};
 pub use crate::event::Time;
 use quantifiable_derive::Quantifiable;//the derive macro
-use crate::{Plugs};
+use crate::{Plugs,match_object,error,source_location};
+use crate::packet::RoutingHints;
 pub use crate::error::Error;
 use crate::topology::megafly::MegaflyAD;
 use crate::topology::multistage::UpDownDerouting;
@@ -40,10 +45,11 @@ pub use self::extra::*;
 pub use self::channel_operations::*;
 pub use self::updown::*;
 pub use self::polarized::Polarized;
+pub use self::source::{KShortestPaths,new_source_routing,try_new_source_routing};
 
 pub mod prelude
 {
-	pub use super::{new_routing,Routing,RoutingInfo,RoutingNextCandidates,CandidateEgress,RoutingBuilderArgument,Error,Time};
+	pub use super::{new_routing,try_new_routing,Routing,RoutingInfo,RoutingNextCandidates,RoutingOccupancy,RouterConfigurationSummary,CandidateEgress,RoutingBuilderArgument,Error,Time};
 }
 
 ///Information stored in the packet for the `Routing` algorithms to operate.
@@ -69,6 +75,9 @@ pub struct RoutingInfo
 	pub auxiliar: RefCell<Option<Box<dyn std::any::Any>>>,
 	///Source server index, optional.
 	pub source_server: Option<usize>,
+	///The hints given by the traffic to the message of the packet, if any.
+	///The meta routing infos built by the routings for their sub-routings do not receive them.
+	pub hints: Option<Rc<RoutingHints>>,
 }
 
 impl RoutingInfo
@@ -84,6 +93,7 @@ impl RoutingInfo
 			meta: None,
 			auxiliar: RefCell::new(None),
 			source_server: None,
+			hints: None,
 		}
 	}
 }
@@ -174,9 +184,59 @@ impl RoutingNextCandidates
 	}
 }
 
+/**
+Estimations of the congestion given by the router to the routing when computing candidates with [Routing::next_with_occupancy].
+The occupancy of a port is the number of phits waiting to be sent through it, counting both the output buffer of the router and the space used in the input buffer of the neighbour, as known by the credits.
+**/
+#[derive(Debug,Default)]
+pub struct RoutingOccupancy<'a>
+{
+	///The occupancy of each port of the router performing the routing. `None` if the router does not provide it.
+	pub port_occupancy: Option<&'a Vec<f64>>,
+	///The occupancy of each port of each router, as `network_occupancy[router][port]`. It is taken at the beginning of the cycle.
+	///Only available when the routing declares [Routing::need_global_occupancy].
+	pub network_occupancy: Option<&'a Vec<Vec<f64>>>,
+}
+
+/**
+A summary of the configuration of the routers, for the routings to check in [Routing::check_router_configuration] that it suffices for them.
+It is built by the simulation once the routers have been created.
+**/
+#[derive(Debug,Clone,Default)]
+pub struct RouterConfigurationSummary
+{
+	///`virtual_channels[router][port]` is the number of virtual channels of that port of the router.
+	pub virtual_channels: Vec<Vec<usize>>,
+	///`buffer_sizes[router][port][virtual_channel]` is the number of phits that fit in the input buffer of that virtual channel.
+	pub buffer_sizes: Vec<Vec<Vec<usize>>>,
+}
+
+impl RouterConfigurationSummary
+{
+	///The least number of virtual channels of any port in the network.
+	pub fn minimum_virtual_channels(&self) -> usize
+	{
+		self.virtual_channels.iter().flat_map(|ports|ports.iter()).copied().min().unwrap_or(0)
+	}
+	///The least size of any buffer in the network.
+	pub fn minimum_buffer_size(&self) -> usize
+	{
+		self.buffer_sizes.iter().flat_map(|ports|ports.iter()).flat_map(|sizes|sizes.iter()).copied().min().unwrap_or(0)
+	}
+	///The same summary with `virtual_channels` channels in each port, as seen by a routing below another that remaps the channels.
+	pub fn with_virtual_channels(&self, virtual_channels:usize) -> RouterConfigurationSummary
+	{
+		RouterConfigurationSummary{
+			virtual_channels: self.virtual_channels.iter().map(|ports|vec![virtual_channels;ports.len()]).collect(),
+			buffer_sizes: self.buffer_sizes.clone(),
+		}
+	}
+}
+
 ///A routing algorithm to provide candidate routes when the `Router` requires.
 ///It may store/use information in the RoutingInfo.
-///A `Routing` does not receive information about the state of buffers or similar. Such a mechanism should be given as a `VirtualChannelPolicy`.
+///Most routings do not use the state of the buffers, which is usually considered in the `VirtualChannelPolicy`.
+///Those that need it declare it with [Routing::need_occupancy] and receive a [RoutingOccupancy] in [Routing::next_with_occupancy].
 pub trait Routing : Debug
 {
 	/// Compute the list of allowed exits.
@@ -187,6 +247,17 @@ pub trait Routing : Debug
 	/// `num_virtual_channels` is the number of virtual channels dedicated to this routing.
 	/// `rng` is the global generator of random numbers.
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>;
+	///Compute the list of allowed exits with the congestion estimations given by the router.
+	///It is what the routers call. By default it ignores the `occupancy` and calls [Routing::next].
+	#[allow(clippy::too_many_arguments)]
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, _occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,rng)
+	}
+	///Whether the routing uses the occupancy of the ports of the current router. Otherwise the router may skip computing it.
+	fn need_occupancy(&self) -> bool { false }
+	///Whether the routing uses the occupancy of the ports of every router. Otherwise the simulation does not collect it.
+	fn need_global_occupancy(&self) -> bool { false }
 	//fn initialize_routing_info(&self, routing_info:&mut RoutingInfo, topology:&dyn Topology, current_router:usize, target_server:usize);
 	///Initialize the routing info of the packet. Called when the first phit of the packet leaves the server and enters a router.
 	fn initialize_routing_info(&self, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_touter:usize, _target_server:Option<usize>, _rng: &mut StdRng) {}
@@ -194,12 +265,22 @@ pub trait Routing : Debug
 	fn update_routing_info(&self, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _current_port:usize, _target_router:usize, _target_server:Option<usize>,_rng: &mut StdRng) {}
 	///Prepares the routing to be utilized. Perhaps by precomputing routing tables.
 	fn initialize(&mut self, _topology:&dyn Topology, _rng: &mut StdRng) {}
+	///Checks that the routers, as summarized in `routers`, have enough resources for the routing, such as virtual channels.
+	///Called by the simulation after [Routing::initialize] and building the routers, so that a misconfiguration fails before simulating instead of deadlocking later.
+	///Routings that contain others must forward the check. By default any configuration is accepted.
+	fn check_router_configuration(&self, _topology:&dyn Topology, _routers:&RouterConfigurationSummary) -> Result<(),Error> { Ok(()) }
+	///Called by the simulation when some link of the topology goes down or up, as scheduled in its `events`. See [reconfiguration](crate::reconfiguration).
+	///Routings keeping tables computed in [Routing::initialize] should compute them again. Routings that contain others must forward the notification.
+	///By default nothing is done, which is right for the routings that query the distances of the topology, as these already avoid the links down.
+	fn on_topology_change(&mut self, _topology:&dyn Topology, _rng: &mut StdRng) {}
 	///To be called by the router when one of the candidates is requested.
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng) {}
 	///To optionally write routing statistics into the simulation output.
 	fn statistics(&self,_cycle:Time) -> Option<ConfigurationValue>{ None }
 	///Clears all collected statistics
 	fn reset_statistics(&mut self,_next_cycle:Time) {}
+	///Called by the simulation at the beginning of each cycle. For routings whose behaviour changes along the time. Routings that contain others must forward it.
+	fn advance_cycle(&mut self,_cycle:Time) {}
 }
 
 ///The argument of a builder function for `Routings`.
@@ -239,6 +320,18 @@ Valiant{
 }
 ```
 
+The [UGAL] routing chooses at the source router between the minimal path and a Valiant path, according to the occupancy of the ports. With `global: true` it uses the occupancy of all the routers along the paths (UGAL-G) instead of only the local ports (UGAL-L).
+
+```ignore
+UGAL{
+	routing: Shortest,
+	global: false,
+	legend_name: "UGAL-L",
+}
+```
+
+For Dragonflies, [PiggybackPAR](crate::topology::dragonfly::PiggybackPAR) makes a similar choice with the congestion of the global links of each group, as exchanged among the routers with some latency.
+
 As a routing that gives both short, long routes, and many intermediates we have the Polarized routing. It is recommended to have some mechanism to select among those routes based on network measures such as queue occupation.
 
 - Camarero, C., Martínez, C., & Beivide, R. (2021, August). Polarized routing: an efficient and versatile algorithm for large direct networks. In 2021 IEEE Symposium on High-Performance Interconnects (HOTI) (pp. 52-59). IEEE.
@@ -280,8 +373,44 @@ UpDown{
 }
 ```
 
+or its deterministic [DmodK] version, which selects a single up port by the destination modulo the number of up ports.
+```ignore
+DmodK{
+	legend_name: "D mod k",
+}
+```
+
+To replay the routes computed by external tools, [TableRouting] reads from a file the ports to use from each router towards each destination router.
+Without a file it computes the minimal routes, which may be written with `dump` to be edited.
+```ignore
+TableRouting{
+	filename: "routes.txt",
+	legend_name: "routes of the subnet manager",
+}
+```
+
 There is a `Mindless` routing without parameters that includes all neighbours as candidates until reaching destination. Can be though as a random walk, if additionally the router would make its decisions randomly.
 
+## Source routings
+
+A source routing builds the whole path at the source router. [KShortestPaths] follows one of the `k` shortest paths between the source and destination routers, chosen at random.
+```ignore
+KShortestPaths{
+	k: 4,
+	legend_name: "4 shortest paths",
+}
+```
+
+To adapt at the routers, a source routing may store several of its paths in the packet with `SourceAdaptive`, which offers the next hop of each stored path that is still possible.
+`EachLengthSourceAdaptive` stores instead a path of each length, labelled by their additional length.
+```ignore
+SourceAdaptive{
+	routing: KShortestPaths{k:8},
+	amount: 2,//paths to store in each packet
+	legend_name: "2 out of the 8 shortest paths",
+}
+```
+
 ## Operations
 
 ### Sum
@@ -347,6 +476,46 @@ AscendantChannelsWithLinkClass{
 }
 ```
 
+### EscapeChannels
+Reserve some virtual channels to a deadlock-free escape routing and let the main routing use the rest freely. A packet may take the escape channels at any hop, and then it continues through them until its destination, unless `return_to_adaptive` is set. See [EscapeChannels] for details.
+```ignore
+EscapeChannels{
+	routing: Shortest,
+	escape: DOR{order:[0,1]},
+	escape_virtual_channels: [0],
+	escape_extra_label: 10,//optional, to give priority to the adaptive channels
+	return_to_adaptive: false,//optional
+	verify_deadlock_freedom: true,//optional, to check Duato's condition before simulating
+}
+```
+The `Duato` routing is the same, with the fully adaptive minimal `Shortest` as default `routing` and verifying by default that the escape channels are deadlock-free according to Duato's theory.
+```ignore
+Duato{
+	escape: DOR{order:[0,1]},
+	escape_virtual_channels: [0],
+}
+```
+
+### Convergence
+Uses stale routing tables for some cycles after the faults of the topology, to study the convergence of the control plane. See [Convergence] for details.
+```ignore
+Convergence{
+	routing: Shortest,
+	stale_topology: Hamming{ sides:[6,6], servers_per_router:6 },
+	convergence_delay: 2000,
+	legend_name: "minimal with 2000 cycles of convergence",
+}
+```
+
+### ChoiceRegret
+Measures how good the adaptive decisions of a routing are, comparing the latency of the chosen ports against the estimations of the best alternatives. See [ChoiceRegret] for details.
+```ignore
+ChoiceRegret{
+	routing: UGAL{},
+	hop_latency: 3,
+}
+```
+
 ### Stubborn makes a routing to calculate candidates just once. If that candidate is not accepted is trying again every cycle.
 ```ignore
 Stubborn{
@@ -369,6 +538,17 @@ DOR{
 ```
 
 
+### ConfigurableTurnModel
+
+Minimal adaptive routing restricted by a table of forbidden turns between directions, to define turn models such as west-first or odd-even. See [ConfigurableTurnModel].
+
+```ignore
+ConfigurableTurnModel{
+	forbidden_turns: [["+1","-0"],["-1","-0"]],
+	legend_name: "west-first",
+}
+```
+
 ### O1TURN
 O1TURN is a pair of DOR to balance the usage of the links.
 
@@ -408,59 +588,75 @@ ValiantDOR{
 
 */
 pub fn new_routing(arg: RoutingBuilderArgument) -> Box<dyn Routing>
+{
+	try_new_routing(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///Like [new_routing] but returning an error instead of panicking on a bad configuration.
+pub fn try_new_routing(arg:RoutingBuilderArgument) -> Result<Box<dyn Routing>,Error>
 {
 	if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
 	{
 		match arg.plugs.routings.get(cv_name)
 		{
-			Some(builder) => return builder(arg),
+			Some(builder) => return Ok(builder(arg)),
 			_ => (),
 		};
-		match cv_name.as_ref()
+		Ok(match cv_name.as_ref()
 		{
-			"DOR" => Box::new(DOR::new(arg)),
-			"O1TURN" => Box::new(O1TURN::new(arg)),
-			"GeneralTurn" => Box::new(GENERALTURN::new(arg)),
-			"OmniDimensionalDeroute" => Box::new(OmniDimensionalDeroute::new(arg)),
-			"DimWAR" => Box::new(DimWAR::new(arg)),
-			"Valiant4Hamming" => Box::new(Valiant4Hamming::new(arg)),
-			"AdaptiveValiantClos" => Box::new(AdaptiveValiantClos::new(arg)),
-			"Valiant4Dragonfly" => Box::new(Valiant4Dragonfly::new(arg)),
-			"PAR" => Box::new(PAR::new(arg)),
-			"Shortest" => Box::new(Shortest::new(arg)),
-			"Valiant" => Box::new(Valiant::new(arg)),
-			"ValiantDOR" => Box::new(ValiantDOR::new(arg)),
-			"Polarized" => Box::new(Polarized::new(arg)),
-			"Sum" => Box::new(SumRouting::new(arg)),
-			"Mindless" => Box::new(Mindless::new(arg)),
-			"WeighedShortest" => Box::new(WeighedShortest::new(arg)),
-			"Stubborn" => Box::new(Stubborn::new(arg)),
-			"UpDown" => Box::new(UpDown::new(arg)),
-			"UpDownStar" => Box::new(ExplicitUpDown::new(arg)),
-			"ChannelsPerHop" => Box::new(ChannelsPerHop::new(arg)),
-			"ChannelsPerHopPerLinkClass" => Box::new(ChannelsPerHopPerLinkClass::new(arg)),
-			"AscendantChannelsWithLinkClass" => Box::new(AscendantChannelsWithLinkClass::new(arg)),
-			"ChannelMap" => Box::new(ChannelMap::new(arg)),
-			"Dragonfly2Colors" => Box::new(crate::topology::dragonfly::Dragonfly2ColorsRouting::new(arg)),
-			"UpDownDerouting" => Box::new(UpDownDerouting::new(arg)),
-			"MegaflyAD" => Box::new(MegaflyAD::new(arg)),
-			"AdaptiveStart" => Box::new(AdaptiveStart::new(arg)),
-			"DragonflyDirect" => Box::new(DragonflyDirect::new(arg)),
-			"SubTopologyRouting" => Box::new(SubTopologyRouting::new(arg)),
-			"RegionRouting" => Box::new(RegionRouting::new(arg)),
-			_ => panic!("Unknown Routing {}",cv_name),
-		}
+			"DOR" => Box::new(DOR::new(arg)?),
+			"O1TURN" => Box::new(O1TURN::new(arg)?),
+			"GeneralTurn" => Box::new(GENERALTURN::new(arg)?),
+			"ConfigurableTurnModel" => Box::new(ConfigurableTurnModel::new(arg)?),
+			"OmniDimensionalDeroute" => Box::new(OmniDimensionalDeroute::new(arg)?),
+			"DimWAR" => Box::new(DimWAR::new(arg)?),
+			"Valiant4Hamming" => Box::new(Valiant4Hamming::new(arg)?),
+			"AdaptiveValiantClos" => Box::new(AdaptiveValiantClos::new(arg)?),
+			"Valiant4Dragonfly" => Box::new(Valiant4Dragonfly::new(arg)?),
+			"PAR" => Box::new(PAR::new(arg)?),
+			"PiggybackPAR" => Box::new(PiggybackPAR::new(arg)?),
+			"Shortest" => Box::new(Shortest::new(arg)?),
+			"TableRouting" => Box::new(TableRouting::new(arg)?),
+			"Valiant" => Box::new(Valiant::new(arg)?),
+			"UGAL" => Box::new(UGAL::new(arg)?),
+			"ValiantDOR" => Box::new(ValiantDOR::new(arg)?),
+			"Polarized" => Box::new(Polarized::new(arg)?),
+			"Sum" => Box::new(SumRouting::new(arg)?),
+			"Mindless" => Box::new(Mindless::new(arg)?),
+			"WeighedShortest" => Box::new(WeighedShortest::new(arg)?),
+			"Stubborn" => Box::new(Stubborn::new(arg)?),
+			"UpDown" => Box::new(UpDown::new(arg)?),
+			"DmodK" => Box::new(DmodK::new(arg)?),
+			"UpDownStar" => Box::new(ExplicitUpDown::new(arg)?),
+			"ChannelsPerHop" => Box::new(ChannelsPerHop::new(arg)?),
+			"ChannelsPerHopPerLinkClass" => Box::new(ChannelsPerHopPerLinkClass::new(arg)?),
+			"AscendantChannelsWithLinkClass" => Box::new(AscendantChannelsWithLinkClass::new(arg)?),
+			"ChannelMap" => Box::new(ChannelMap::new(arg)?),
+			"EscapeChannels" | "Duato" => Box::new(EscapeChannels::new(arg)?),
+			"Dragonfly2Colors" => Box::new(crate::topology::dragonfly::Dragonfly2ColorsRouting::new(arg)?),
+			"UpDownDerouting" => Box::new(UpDownDerouting::new(arg)?),
+			"MegaflyAD" => Box::new(MegaflyAD::new(arg)?),
+			"AdaptiveStart" => Box::new(AdaptiveStart::new(arg)?),
+			"DragonflyDirect" => Box::new(DragonflyDirect::new(arg)?),
+			"SubTopologyRouting" => Box::new(SubTopologyRouting::new(arg)?),
+			"RegionRouting" => Box::new(RegionRouting::new(arg)?),
+			"Convergence" => Box::new(Convergence::new(arg)?),
+			"ChoiceRegret" => Box::new(ChoiceRegret::new(arg)?),
+			"KShortestPaths" => Box::new(KShortestPaths::new(arg)?),
+			"SourceAdaptive" => Box::new(SourceAdaptiveRouting::new(arg)?),
+			"EachLengthSourceAdaptive" => Box::new(EachLengthSourceAdaptiveRouting::new(arg)?),
+			_ => return Err(arg.cv.ill(&format!("Unknown Routing {}",cv_name))),
+		})
 	}
 	else
 	{
-		panic!("Trying to create a Routing from a non-Object");
+		Err(arg.cv.ill("Trying to create a Routing from a non-Object"))
 	}
 }
 
 
 ///Trait for `Routing`s that build the whole route at source.
-///This includes routings such as K-shortest paths. But I have all my implementations depending on a private algorithm, so they are not yet here.
-///They will all be released when the dependency is formally published.
+///This includes routings such as [K-shortest paths](KShortestPaths). See [new_source_routing] for the ones in the crate.
 pub trait SourceRouting
 {
 	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng);
@@ -549,6 +745,11 @@ impl<R:SourceRouting+Debug> Routing for R
 	{
 		self.initialize(topology,rng);
 	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		//The paths are computed again over the links alive.
+		SourceRouting::initialize(self,topology,rng);
+	}
 }
 
 
@@ -566,6 +767,25 @@ pub struct SourceAdaptiveRouting
 	pub amount: usize,
 }
 
+impl SourceAdaptiveRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<SourceAdaptiveRouting,Error>
+	{
+		let mut routing=None;
+		let mut amount=None;
+		match_object!(arg.cv,"SourceAdaptive",value,
+			"routing" => routing=Some(try_new_source_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"amount" => amount=Some(value.as_usize()?),
+		);
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let amount=amount.ok_or_else(||arg.cv.ill("There were no amount"))?;
+		Ok(SourceAdaptiveRouting{
+			routing,
+			amount,
+		})
+	}
+}
+
 impl Routing for SourceAdaptiveRouting
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
@@ -690,6 +910,10 @@ impl Routing for SourceAdaptiveRouting
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn on_topology_change(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+	}
 }
 
 
@@ -13,7 +13,8 @@ use std::cell::RefCell;
 use std::any::Any;
 
 use crate::routing::*;
-use crate::match_object_panic;
+use crate::{match_object,error,source_location};
+use crate::error::{Error,SourceLocation};
 
 /**
 The Polarized routing algorithm.
@@ -253,21 +254,21 @@ impl Routing for Polarized
 
 impl Polarized
 {
-	pub fn new(arg:RoutingBuilderArgument) -> Polarized
+	pub fn new(arg:RoutingBuilderArgument) -> Result<Polarized,Error>
 	{
 		let mut include_labels = None;
 		let mut strong = None;
 		//let mut strong_link_classes = None;
 		let mut panic_on_empty = true;
 		let mut enable_statistics = false;
-		match_object_panic!(arg.cv,"Polarized", value,
-			"include_labels" => include_labels=Some(value.as_bool().expect("bad value for include_labels")),
-			"strong" => strong=Some(value.as_bool().expect("bad value for strong")),
+		match_object!(arg.cv,"Polarized", value,
+			"include_labels" => include_labels=Some(value.as_bool()?),
+			"strong" => strong=Some(value.as_bool()?),
 			//"strong_link_classes" => strong_link_classes=Some(value.as_array().expect("bad value for strong_link_classes").iter()
 			//	.map(|item|item.as_bool().expect("bad value for strong_link_classes")
 			//).collect()),
-			"panic_on_empty" => panic_on_empty=value.as_bool().expect("bad value for panic_on_empty"),
-			"enable_statistics" => enable_statistics=value.as_bool().expect("bad value for enable_statistics"),
+			"panic_on_empty" => panic_on_empty=value.as_bool()?,
+			"enable_statistics" => enable_statistics=value.as_bool()?,
 		);
 		//if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
 		//{
@@ -318,10 +319,10 @@ impl Polarized
 		//{
 		//	panic!("Trying to create a Polarized from a non-Object");
 		//}
-		let include_labels=include_labels.expect("There were no include_labels");
+		let include_labels=include_labels.ok_or_else(||arg.cv.ill("There were no include_labels"))?;
 		let strong=strong.unwrap_or_else(||false);
 		//let strong_link_classes = strong_link_classes.unwrap_or_else(||Vec::new());
-		Polarized{
+		Ok(Polarized{
 			include_labels,
 			strong,
 			//strong_link_classes,
@@ -329,7 +330,7 @@ impl Polarized
 			enable_statistics,
 			empty_count: if enable_statistics {Some(RefCell::new(0))} else {None},
 			best_count: if enable_statistics {Some(RefCell::new([0,0,0]))} else {None},
-		}
+		})
 	}
 }
 
@@ -0,0 +1,215 @@
+/*!
+Source routings, which build the whole path at the source router.
+
+* KShortestPaths
+
+See [SourceRouting](super::SourceRouting), and [SourceAdaptiveRouting](super::SourceAdaptiveRouting) and [EachLengthSourceAdaptiveRouting](super::EachLengthSourceAdaptiveRouting) to store several paths in the packets.
+*/
+
+use std::collections::{BTreeSet,VecDeque};
+
+use ::rand::rngs::StdRng;
+
+use crate::{match_object,error,source_location};
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::topology::{Topology,NeighbourRouterIteratorItem};
+use super::{SourceRouting,InstantiableSourceRouting,RoutingBuilderArgument};
+
+///Build a new source routing, to be used inside [SourceAdaptiveRouting](super::SourceAdaptiveRouting) or [EachLengthSourceAdaptiveRouting](super::EachLengthSourceAdaptiveRouting).
+pub fn new_source_routing(arg: RoutingBuilderArgument) -> Box<dyn InstantiableSourceRouting>
+{
+	try_new_source_routing(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///Like [new_source_routing] but returning an error instead of panicking on a bad configuration.
+pub fn try_new_source_routing(arg: RoutingBuilderArgument) -> Result<Box<dyn InstantiableSourceRouting>,Error>
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=arg.cv
+	{
+		Ok(match cv_name.as_ref()
+		{
+			"KShortestPaths" => Box::new(KShortestPaths::new(arg)?),
+			_ => return Err(arg.cv.ill(&format!("Unknown source routing {}",cv_name))),
+		})
+	}
+	else
+	{
+		Err(arg.cv.ill("Trying to create a source routing from a non-Object"))
+	}
+}
+
+/**
+The `k` shortest loopless paths between each pair of routers, computed by Yen's algorithm over the hop count.
+Each packet follows one of the paths of its pair, chosen at random at the source.
+The paths of each pair are sorted by length, with ties broken by the sequence of routers, so they are the same in every execution.
+All the pairs are computed at the initialization, which takes some time in large topologies.
+
+```ignore
+KShortestPaths{
+	k: 4,
+	legend_name: "4 shortest paths",
+}
+```
+**/
+#[derive(Debug)]
+pub struct KShortestPaths
+{
+	///Maximum number of paths per pair of routers.
+	k: usize,
+	///`paths[source][target]` are the paths from `source` to `target`, as the sequence of routers, including both.
+	paths: Vec<Vec<Vec<Vec<usize>>>>,
+}
+
+impl SourceRouting for KShortestPaths
+{
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		let n=topology.num_routers();
+		self.paths=(0..n).map(|source|(0..n).map(|target|{
+			if source==target { vec![] } else { yen_k_shortest_paths(topology,source,target,self.k) }
+		}).collect()).collect();
+	}
+	fn get_paths(&self, source:usize, target:usize) -> &Vec<Vec<usize>>
+	{
+		&self.paths[source][target]
+	}
+}
+
+impl KShortestPaths
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<KShortestPaths,Error>
+	{
+		let mut k=None;
+		match_object!(arg.cv,"KShortestPaths",value,
+			"k" => k=Some(value.as_usize()?),
+		);
+		let k=k.ok_or_else(||arg.cv.ill("There were no k"))?;
+		if k==0
+		{
+			return Err(arg.cv.ill("KShortestPaths requires k>0"));
+		}
+		Ok(KShortestPaths{
+			k,
+			paths: vec![],
+		})
+	}
+}
+
+///A shortest path from `source` to `target` by breadth first search, without visiting the `removed_routers` nor using the `removed_links`.
+///The links are given as pairs of routers in the direction of the path.
+fn shortest_path_avoiding(topology:&dyn Topology, source:usize, target:usize, removed_routers:&[bool], removed_links:&BTreeSet<(usize,usize)>) -> Option<Vec<usize>>
+{
+	let n=topology.num_routers();
+	let mut previous=vec![None;n];
+	let mut visited=vec![false;n];
+	visited[source]=true;
+	let mut queue=VecDeque::new();
+	queue.push_back(source);
+	while let Some(current)=queue.pop_front()
+	{
+		if current==target
+		{
+			let mut path=vec![target];
+			let mut router=target;
+			while let Some(before)=previous[router]
+			{
+				path.push(before);
+				router=before;
+			}
+			path.reverse();
+			return Some(path);
+		}
+		for NeighbourRouterIteratorItem{neighbour_router,..} in topology.neighbour_router_iter(current)
+		{
+			if visited[neighbour_router] || removed_routers[neighbour_router] || removed_links.contains(&(current,neighbour_router))
+			{
+				continue;
+			}
+			visited[neighbour_router]=true;
+			previous[neighbour_router]=Some(current);
+			queue.push_back(neighbour_router);
+		}
+	}
+	None
+}
+
+///Yen's algorithm for the `k` shortest loopless paths from `source` to `target`, counting hops.
+///Returns fewer paths when there are not `k` of them.
+pub fn yen_k_shortest_paths(topology:&dyn Topology, source:usize, target:usize, k:usize) -> Vec<Vec<usize>>
+{
+	let n=topology.num_routers();
+	let first=match shortest_path_avoiding(topology,source,target,&vec![false;n],&BTreeSet::new())
+	{
+		Some(path) => path,
+		None => return vec![],
+	};
+	let mut paths=vec![first];
+	//The candidates are sorted by length and then by routers, to pick always the same.
+	let mut candidates : BTreeSet<(usize,Vec<usize>)> = BTreeSet::new();
+	while paths.len()<k
+	{
+		let last=paths.last().unwrap().clone();
+		for spur_index in 0..last.len()-1
+		{
+			let spur=last[spur_index];
+			let root=&last[..=spur_index];
+			//Remove the links used by the known paths sharing this root, so that the spur path is new.
+			let removed_links : BTreeSet<(usize,usize)> = paths.iter()
+				.filter(|path|path.len()>spur_index+1 && &path[..=spur_index]==root)
+				.map(|path|(path[spur_index],path[spur_index+1]))
+				.collect();
+			//Remove the routers of the root, to keep the paths loopless.
+			let mut removed_routers=vec![false;n];
+			for &router in &root[..spur_index]
+			{
+				removed_routers[router]=true;
+			}
+			if let Some(spur_path)=shortest_path_avoiding(topology,spur,target,&removed_routers,&removed_links)
+			{
+				let mut path=root[..spur_index].to_vec();
+				path.extend(spur_path);
+				if !paths.contains(&path)
+				{
+					candidates.insert((path.len(),path));
+				}
+			}
+		}
+		match candidates.iter().next().cloned()
+		{
+			Some(best) =>
+			{
+				candidates.remove(&best);
+				paths.push(best.1);
+			},
+			None => break,
+		}
+	}
+	paths
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config_parser;
+	use crate::Plugs;
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+	use rand::SeedableRng;
+	#[test]
+	fn k_shortest_paths()
+	{
+		let cv = match config_parser::parse("Hamming{sides:[4],servers_per_router:1}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		// In the complete graph of 4 routers there are the direct link, 2 paths of two hops, and 2 of three hops.
+		let paths = yen_k_shortest_paths(topology.as_ref(),0,1,10);
+		assert_eq!(paths,vec![vec![0,1],vec![0,2,1],vec![0,3,1],vec![0,2,3,1],vec![0,3,2,1]]);
+		let paths = yen_k_shortest_paths(topology.as_ref(),0,1,2);
+		assert_eq!(paths,vec![vec![0,1],vec![0,2,1]]);
+	}
+}
@@ -3,15 +3,18 @@
 Implementation of general Up/Down-like routings.
 
 * UpDown
+* DmodK
 * UpDownStar (struct ExplicitUpDown)
 
 */
 
+use std::cell::RefCell;
 use ::rand::{rngs::StdRng};
-use crate::pattern::{new_pattern};
+use crate::pattern::{try_new_pattern};
 use crate::PatternBuilderArgument;
-use crate::match_object_panic;
+use crate::{match_object,error,source_location};
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::routing::prelude::*;
 use crate::topology::{Topology,NeighbourRouterIteratorItem,Location};
 use crate::matrix::Matrix;
@@ -178,32 +181,138 @@ impl Routing for UpDown
 
 impl UpDown
 {
-	pub fn new(arg: RoutingBuilderArgument) -> UpDown
+	pub fn new(arg: RoutingBuilderArgument) -> Result<UpDown,Error>
 	{
 		let mut routing_up_stage_patterns = None;
 		let mut port_pattern = None;
 		let mut target_sizes = None;
 		let mut port_pattern_source_sizes = None;
-		match_object_panic!(arg.cv,"UpDown",value,
-			"routing_up_stage_patterns" => routing_up_stage_patterns = Some(value.as_array().expect("bad value for routing_up_stage_patterns").iter().map(|x|{
-				new_pattern(PatternBuilderArgument{cv:x,plugs:arg.plugs})
-			}).collect()),
-			"port_pattern" => port_pattern = Some(value.as_array().expect("bad value for port_pattern").iter().map(|x|{
-				new_pattern(PatternBuilderArgument{cv:x,plugs:arg.plugs})
-			}).collect()),
-			"upwards_sizes" => target_sizes = Some(value.as_array().expect("bad value for up_sizes").iter().map(|x|{
-				x.as_usize().expect("bad value for up_sizes")
-			}).collect()),
-			"port_pattern_source_sizes" => port_pattern_source_sizes = Some(value.as_array().expect("bad value for port_pattern_source_sizes").iter().map(|x|{
-				x.as_usize().expect("bad value for port_pattern_source_sizes")
-			}).collect()),
+		match_object!(arg.cv,"UpDown",value,
+			"routing_up_stage_patterns" => routing_up_stage_patterns = Some(value.as_array()?.iter().map(|x|{
+				try_new_pattern(PatternBuilderArgument{cv:x,plugs:arg.plugs})
+			}).collect::<Result<_,_>>()?),
+			"port_pattern" => port_pattern = Some(value.as_array()?.iter().map(|x|{
+				try_new_pattern(PatternBuilderArgument{cv:x,plugs:arg.plugs})
+			}).collect::<Result<_,_>>()?),
+			"upwards_sizes" => target_sizes = Some(value.as_array()?.iter().map(|x|{
+				x.as_usize()
+			}).collect::<Result<_,_>>()?),
+			"port_pattern_source_sizes" => port_pattern_source_sizes = Some(value.as_array()?.iter().map(|x|{
+				x.as_usize()
+			}).collect::<Result<_,_>>()?),
 		);
-		UpDown{
+		Ok(UpDown{
 			routing_up_stage_patterns,
 			port_pattern,
 			target_sizes,
 			port_pattern_source_sizes,
+		})
+	}
+}
+
+/**
+Deterministic D-mod-K routing for multistage topologies, as used in InfiniBand fat-trees. The up/down paths are understood as provided by `Topology::up_down_distance`.
+While the packet can go upwards it selects, among the ports reducing the up distance sorted by index, the one at position `(D/P) mod k`,
+where `D` is the destination server, `k` is the number of such ports and `P` is the product of the number of options in the previous hops.
+In the downward phase the same rule is applied to the ports reducing the down distance, which in trees is usually a single one.
+This spreads the flows towards different destinations over the upper stages in the same way in every run.
+With `source_based` the source server is used instead of the destination, giving S-mod-K routing.
+```ignore
+DmodK{
+	source_based: false,//optional, defaults to false
+	legend_name: "D mod k",
+}
+```
+**/
+#[derive(Debug)]
+pub struct DmodK
+{
+	///Whether to use the source server as key instead of the destination server.
+	source_based: bool,
+}
+
+impl Routing for DmodK
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let target_server = target_server.expect("target server was not given.");
+		if current_router==target_router
+		{
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let ports = self.candidate_ports(topology,current_router,target_router);
+		if ports.is_empty()
+		{
+			panic!("The topology does not provide an up/down path from {} to {}",current_router,target_router);
 		}
+		let key = if self.source_based { routing_info.source_server.expect("DmodK requires the source server") } else { target_server };
+		let divisor = routing_info.selections.as_ref().map(|s|s[0] as usize).unwrap_or(1);
+		let port = ports[(key/divisor)%ports.len()];
+		Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(port,vc)).collect(),idempotent:true})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		routing_info.borrow_mut().selections=Some(vec![1]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let previous_router = match topology.neighbour(current_router,current_port).0
+		{
+			Location::RouterPort{router_index,..} => router_index,
+			_ => panic!("The packet came from a non-router"),
+		};
+		let options = self.candidate_ports(topology,previous_router,target_router).len();
+		let mut info = routing_info.borrow_mut();
+		let selections = info.selections.as_mut().expect("The routing info of DmodK has not been initialized");
+		selections[0] = selections[0].saturating_mul(options.max(1) as i32);
+	}
+}
+
+impl DmodK
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<DmodK,Error>
+	{
+		let mut source_based = false;
+		match_object!(arg.cv,"DmodK",value,
+			"source_based" => source_based = value.as_bool()?,
+		);
+		Ok(DmodK{
+			source_based,
+		})
+	}
+	///The ports of `current_router` going upwards towards `target_router`, or if none, the ones going downwards. Sorted by index.
+	fn candidate_ports(&self, topology:&dyn Topology, current_router:usize, target_router:usize) -> Vec<usize>
+	{
+		let (up_distance, down_distance) = topology.up_down_distance(current_router,target_router).unwrap_or_else(||panic!("The topology does not provide an up/down path from {} to {}",current_router,target_router));
+		let mut up_ports = vec![];
+		let mut down_ports = vec![];
+		for NeighbourRouterIteratorItem{port_index,neighbour_router,..} in topology.neighbour_router_iter(current_router)
+		{
+			if let Some((new_u, new_d)) = topology.up_down_distance(neighbour_router,target_router)
+			{
+				if new_u<up_distance && new_d<=down_distance
+				{
+					up_ports.push(port_index);
+				}
+				else if new_u<=up_distance && new_d<down_distance
+				{
+					down_ports.push(port_index);
+				}
+			}
+		}
+		let mut ports = if up_ports.is_empty() { down_ports } else { up_ports };
+		ports.sort_unstable();
+		ports
 	}
 }
 
@@ -483,7 +592,7 @@ impl Routing for ExplicitUpDown
 
 impl ExplicitUpDown
 {
-	pub fn new(arg: RoutingBuilderArgument) -> ExplicitUpDown
+	pub fn new(arg: RoutingBuilderArgument) -> Result<ExplicitUpDown,Error>
 	{
 		let mut root = None;
 		let mut branch_crossings_downwards = false;
@@ -492,22 +601,22 @@ impl ExplicitUpDown
 		let mut label_up = 0i32;
 		let mut label_horizontal_vec = vec![];
 		let mut label_horizontal_otherwise = 0i32;
-		match_object_panic!(arg.cv,"UpDownStar",value,
-			"root" => root=Some(value.as_f64().expect("bad value for root") as usize),
+		match_object!(arg.cv,"UpDownStar",value,
+			"root" => root=Some(value.as_f64()? as usize),
 			"branch_crossings" => {
-				branch_crossings_upwards = value.as_bool().expect("bad value for branch_crossings");
+				branch_crossings_upwards = value.as_bool()?;
 				branch_crossings_downwards = branch_crossings_upwards;
 			},
-			"branch_crossings_upwards" => branch_crossings_upwards=value.as_bool().expect("bad value for branch_crossings_upwards"),
-			"branch_crossings_downwards" => branch_crossings_downwards=value.as_bool().expect("bad value for branch_crossings_downwards"),
-			"label_up" | "label_upwards" => label_up = value.as_i32().expect("bad value for label_up"),
-			"label_down" | "label_downwards" => label_down = value.as_i32().expect("bad value for label_down"),
-			"label_horizontal_vec" => label_horizontal_vec = value.as_array().expect("bad value for label_horizontal_vec").iter().map(|x|{
-				x.as_i32().expect("bad value for label_horizontal_vec entry")
-			}).collect(),
-			"label_horizontal_otherwise" => label_horizontal_otherwise = value.as_i32().expect("bad value for label_horizontal_otherwise"),
+			"branch_crossings_upwards" => branch_crossings_upwards=value.as_bool()?,
+			"branch_crossings_downwards" => branch_crossings_downwards=value.as_bool()?,
+			"label_up" | "label_upwards" => label_up = value.as_i32()?,
+			"label_down" | "label_downwards" => label_down = value.as_i32()?,
+			"label_horizontal_vec" => label_horizontal_vec = value.as_array()?.iter().map(|x|{
+				x.as_i32()
+			}).collect::<Result<_,_>>()?,
+			"label_horizontal_otherwise" => label_horizontal_otherwise = value.as_i32()?,
 		);
-		ExplicitUpDown{
+		Ok(ExplicitUpDown{
 			root,
 			up_down_distances: Matrix::constant(None,0,0),
 			down_distances: Matrix::constant(None,0,0),
@@ -518,7 +627,7 @@ impl ExplicitUpDown
 			label_up,
 			label_horizontal_vec,
 			label_horizontal_otherwise,
-		}
+		})
 	}
 }
 
@@ -534,13 +643,13 @@ mod tests {
 		let plugs = Plugs::default();
 		let uds_cv = ConfigurationValue::Object("UpDownStar".to_string(),vec![("root".to_string(),ConfigurationValue::Number(5.0))]);
 		let uds_arg = RoutingBuilderArgument{cv:&uds_cv,plugs:&plugs};
-		let mut uds = ExplicitUpDown::new(uds_arg);
+		let mut uds = ExplicitUpDown::new(uds_arg).unwrap();
 		let mut rng=StdRng::seed_from_u64(10u64);
 		let hamming_cv = ConfigurationValue::Object("Hamming".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![
 			ConfigurationValue::Number(8.0),
 			ConfigurationValue::Number(8.0),
 		])),("servers_per_router".to_string(),ConfigurationValue::Number(8.0))]);
-		let topology = Hamming::new(&hamming_cv);
+		let topology = Hamming::new(&hamming_cv,&mut rng).unwrap();
 		uds.initialize(&topology,&mut rng);
 		let n = topology.num_routers();
 		for origin in 0..n
@@ -562,5 +671,61 @@ mod tests {
 			}
 		}
 	}
+	#[test]
+	fn dmodk()
+	{
+		use crate::topology::{new_topology,TopologyBuilderArgument};
+		let plugs = Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let topology_cv = ConfigurationValue::Object("XGFT".to_string(),vec![
+			("height".to_string(),ConfigurationValue::Number(2.0)),
+			("down".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+			("up".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+			("servers_per_leaf".to_string(),ConfigurationValue::Number(4.0)),
+		]);
+		let topology = new_topology(TopologyBuilderArgument{cv:&topology_cv,plugs:&plugs,rng:&mut rng});
+		let routing_cv = ConfigurationValue::Object("DmodK".to_string(),vec![]);
+		let mut routing = DmodK::new(RoutingBuilderArgument{cv:&routing_cv,plugs:&plugs}).unwrap();
+		routing.initialize(&*topology,&mut rng);
+		let router_of = |server:usize| match topology.server_neighbour(server).0 {
+			Location::RouterPort{router_index,..} => router_index,
+			_ => panic!("unconnected server"),
+		};
+		let n = topology.num_servers();
+		for source in 0..n
+		{
+			let source_router = router_of(source);
+			let mut first_ports = vec![];
+			for destination in 0..n
+			{
+				let target_router = router_of(destination);
+				let (up,down) = topology.up_down_distance(source_router,target_router).expect("missing up/down distance");
+				let info = RefCell::new(RoutingInfo::new());
+				info.borrow_mut().source_server = Some(source);
+				routing.initialize_routing_info(&info,&*topology,source_router,target_router,Some(destination),&mut rng);
+				let mut current = source_router;
+				let mut hops = 0;
+				while current!=target_router
+				{
+					let candidates = routing.next(&info.borrow(),&*topology,current,target_router,Some(destination),1,&mut rng).expect("no candidates").candidates;
+					assert_eq!(candidates.len(),1,"DmodK must be deterministic");
+					let port = candidates[0].port;
+					if hops==0 { first_ports.push(port); }
+					let (next_router,next_port) = match topology.neighbour(current,port).0 {
+						Location::RouterPort{router_index,router_port} => (router_index,router_port),
+						_ => panic!("left the network"),
+					};
+					routing.update_routing_info(&info,&*topology,next_router,next_port,target_router,Some(destination),&mut rng);
+					current = next_router;
+					hops += 1;
+					assert!(hops<=up+down,"non-minimal path from {} to {}",source,destination);
+				}
+			}
+			// Consecutive destinations in other leaves must be spread over all the up ports.
+			first_ports.sort_unstable();
+			first_ports.dedup();
+			assert_eq!(first_ports.len(),4,"the destinations from server {} are not spread",source);
+		}
+	}
 }
 
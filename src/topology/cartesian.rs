@@ -4,18 +4,18 @@ use std::cell::RefCell;
 use ::rand::{Rng,rngs::StdRng};
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
-//use topology::{Topology,Location,NeighbourRouterIteratorItem,TopologyBuilderArgument,new_topology};
+use crate::error::{Error,SourceLocation};
+//use topology::{Topology,Location,NeighbourRouterIteratorItem,TopologyBuilderArgument,try_new_topology};
 use super::prelude::*;
 //use crate::routing::{RoutingInfo,Routing,CandidateEgress,RoutingBuilderArgument,RoutingNextCandidates};
 use crate::routing::prelude::*;
 use crate::matrix::Matrix;
-use crate::match_object_panic;
+use crate::{match_object,error,source_location};
 use crate::routing::RoutingAnnotation;
 use crate::pattern::*; //For Valiant
 
 //extern crate itertools;
 use itertools::Itertools;
-use rand::SeedableRng;
 use crate::quantify::Quantifiable;
 
 ///A Cartesian ortahedral region of arbitrary dimension.
@@ -75,15 +75,58 @@ impl CartesianData
 	}
 }
 
+///Reads the `link_multiplicity` of a [Mesh], [Torus], or [Hamming], the number of parallel links between each pair of adjacent routers in each dimension.
+///It defaults to a single link in every dimension.
+fn check_link_multiplicity(link_multiplicity:Option<Vec<usize>>, dimensions:usize) -> Vec<usize>
+{
+	match link_multiplicity
+	{
+		None => vec![1;dimensions],
+		Some(link_multiplicity) =>
+		{
+			assert_eq!(link_multiplicity.len(),dimensions,"link_multiplicity must have an entry for each dimension");
+			assert!(link_multiplicity.iter().all(|&k|k>0),"link_multiplicity must be positive");
+			link_multiplicity
+		},
+	}
+}
+
+///For a [Mesh] or [Torus], the dimension, the index among the parallel links, and whether it goes towards increasing coordinates of the link at a port.
+///The ports of each dimension come in pairs, one pair for each parallel link, with first the port towards decreasing coordinates.
+///Returns `None` for the ports of the servers.
+fn mesh_port_link(link_multiplicity:&[usize], port:usize) -> Option<(usize,usize,bool)>
+{
+	let mut offset=port;
+	for (dimension,&multiplicity) in link_multiplicity.iter().enumerate()
+	{
+		if offset<2*multiplicity
+		{
+			return Some((dimension,offset/2,offset%2==1));
+		}
+		offset-=2*multiplicity;
+	}
+	None
+}
+
+///The inverse of [mesh_port_link].
+fn mesh_port_index(link_multiplicity:&[usize], dimension:usize, trunk:usize, positive:bool) -> usize
+{
+	2*link_multiplicity[..dimension].iter().sum::<usize>() + 2*trunk + if positive { 1 } else { 0 }
+}
+
 ///The mesh topology, a rectangle with corners.
 ///Its maximum_degree is the double of the dimension, with boundary routers having less degree.
 ///The ports that would go outside the mesh have `None` as neighbour.
+///With `link_multiplicity: [2,1]` there are two parallel links between the adjacent routers of the first dimension, so the routings may balance the load among them.
+///The parallel links of a dimension have the same link class, the dimension. The routings computing the dimension from the port index, such as [O1TURN], require a single link per dimension.
 #[derive(Quantifiable)]
 #[derive(Debug)]
 pub struct Mesh
 {
 	cartesian_data: CartesianData,
 	servers_per_router: usize,
+	///The number of parallel links between adjacent routers in each dimension.
+	link_multiplicity: Vec<usize>,
 }
 
 //impl Quantifiable for Mesh
@@ -119,10 +162,9 @@ impl Topology for Mesh
 	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
 	{
 		let m=self.cartesian_data.sides.len();
-		if port<2*m
+		if let Some((dimension,trunk,positive))=mesh_port_link(&self.link_multiplicity,port)
 		{
-			let dimension=port/2;
-			let delta=if port%2==0 { -1i32 as usize } else { 1 };
+			let delta=if positive { 1 } else { -1i32 as usize };
 			let mut coordinates=self.cartesian_data.unpack(router_index);
 
 			//mesh
@@ -137,24 +179,17 @@ impl Topology for Mesh
 			//coordinates[dimension]=(coordinates[dimension]+side.wrapping_add(delta))%side;
 
 			let n_index=self.cartesian_data.pack(&coordinates);
-			let n_port= if delta==1
-			{
-				dimension*2
-			}
-			else
-			{
-				dimension*2+1
-			};
+			let n_port=mesh_port_index(&self.link_multiplicity,dimension,trunk,!positive);
 			return (Location::RouterPort{router_index:n_index, router_port:n_port},dimension);
 		}
-		(Location::ServerPort(port-2*m + router_index*self.servers_per_router),m)
+		(Location::ServerPort(port-self.maximum_degree() + router_index*self.servers_per_router),m)
 	}
 	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
 	{
 		let m=self.cartesian_data.sides.len();
 		(Location::RouterPort{
 			router_index: server_index/self.servers_per_router,
-			router_port: 2*m+server_index%self.servers_per_router,
+			router_port: self.maximum_degree()+server_index%self.servers_per_router,
 		},m)
 	}
 	fn diameter(&self) -> usize
@@ -178,28 +213,28 @@ impl Topology for Mesh
 	}
 	fn maximum_degree(&self) -> usize
 	{
-		2*self.cartesian_data.sides.len()
+		2*self.link_multiplicity.iter().sum::<usize>()
 	}
 	fn minimum_degree(&self) -> usize
 	{
-		self.cartesian_data.sides.len()
+		self.link_multiplicity.iter().sum()
 	}
 	fn degree(&self, router_index: usize) -> usize
 	{
 		let coordinates=self.cartesian_data.unpack(router_index);
-		let mut d=coordinates.len();
+		let mut d=self.minimum_degree();
 		for (i,c) in coordinates.iter().enumerate()
 		{
 			if *c!=0 && *c!=self.cartesian_data.sides[i]-1
 			{
-				d+=1;
+				d+=self.link_multiplicity[i];
 			}
 		}
 		d
 	}
 	fn ports(&self, _router_index: usize) -> usize
 	{
-		2*self.cartesian_data.sides.len()+self.servers_per_router
+		self.maximum_degree()+self.servers_per_router
 	}
 	fn cartesian_data(&self) -> Option<&CartesianData>
 	{
@@ -212,7 +247,8 @@ impl Topology for Mesh
 	}
 	fn is_direction_change(&self, _router_index:usize, input_port: usize, output_port: usize) -> bool
 	{
-		input_port/2 != output_port/2
+		let dimension = |port| mesh_port_link(&self.link_multiplicity,port).map(|(dimension,_,_)|dimension);
+		dimension(input_port) != dimension(output_port)
 	}
 	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
 	{
@@ -222,15 +258,16 @@ impl Topology for Mesh
 
 impl Mesh
 {
-	pub fn new(cv:&ConfigurationValue) -> Mesh
+	pub fn new(cv:&ConfigurationValue) -> Result<Mesh,Error>
 	{
 		let mut sides:Option<Vec<_>>=None;
 		let mut servers_per_router=None;
+		let mut link_multiplicity=None;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
 		{
 			if cv_name!="Mesh"
 			{
-				panic!("A Mesh must be created from a `Mesh` object not `{}`",cv_name);
+				return Err(cv.ill(&format!("A Mesh must be created from a `Mesh` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -239,42 +276,49 @@ impl Mesh
 					"sides" => match value
 					{
 						&ConfigurationValue::Array(ref a) => sides=Some(a.iter().map(|v|match v{
-							&ConfigurationValue::Number(f) => f as usize,
-							_ => panic!("bad value in sides"),
-						}).collect()),
-						_ => panic!("bad value for sides"),
+							&ConfigurationValue::Number(f) => Ok(f as usize),
+							_ => Err(v.ill("bad value in sides")),
+						}).collect::<Result<_,_>>()?),
+						_ => return Err(value.ill("bad value for sides")),
 					}
 					"servers_per_router" => match value
 					{
 						&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
-						_ => panic!("bad value for servers_per_router"),
+						_ => return Err(value.ill("bad value for servers_per_router")),
 					}
+					"link_multiplicity" => link_multiplicity=Some(value.as_array()?.iter()
+						.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in Mesh",name),
+					_ => return Err(cv.ill(&format!("Nothing to do with field {} in Mesh",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a Mesh from a non-Object");
+			return Err(cv.ill("Trying to create a Mesh from a non-Object"));
 		}
-		let sides=sides.expect("There were no sides");
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
+		let sides=sides.ok_or_else(||cv.ill("There were no sides"))?;
+		let servers_per_router=servers_per_router.ok_or_else(||cv.ill("There were no servers_per_router"))?;
+		let link_multiplicity=check_link_multiplicity(link_multiplicity,sides.len());
 		//println!("servers_per_router={}",servers_per_router);
-		Mesh{
+		Ok(Mesh{
 			cartesian_data: CartesianData::new(&sides),
 			servers_per_router,
-		}
+			link_multiplicity,
+		})
 	}
 }
 
 ///As the mesh but with 'wrap-around' links. This is a regular topology and there is no port to `None`.
+///It also accepts `link_multiplicity` to have several parallel links between adjacent routers, as in the [Mesh].
 #[derive(Quantifiable)]
 #[derive(Debug)]
 pub struct Torus
 {
 	cartesian_data: CartesianData,
 	servers_per_router: usize,
+	///The number of parallel links between adjacent routers in each dimension.
+	link_multiplicity: Vec<usize>,
 }
 
 //impl Quantifiable for Torus
@@ -314,10 +358,9 @@ impl Topology for Torus
 	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
 	{
 		let m=self.cartesian_data.sides.len();
-		if port<2*m
+		if let Some((dimension,trunk,positive))=mesh_port_link(&self.link_multiplicity,port)
 		{
-			let dimension=port/2;
-			let delta=if port%2==0 { -1i32 as usize } else { 1 };
+			let delta=if positive { 1 } else { -1i32 as usize };
 			let mut coordinates=self.cartesian_data.unpack(router_index);
 			//coordinates[dimension]=coordinates[dimension].wrapping_add(delta);
 			//if coordinates[dimension]>=self.cartesian_data.sides[dimension]
@@ -328,24 +371,17 @@ impl Topology for Torus
 			//coordinates[dimension]=(coordinates[dimension]+side+delta)%side;
 			coordinates[dimension]=(coordinates[dimension]+side.wrapping_add(delta))%side;
 			let n_index=self.cartesian_data.pack(&coordinates);
-			let n_port= if delta==1
-			{
-				dimension*2
-			}
-			else
-			{
-				dimension*2+1
-			};
+			let n_port=mesh_port_index(&self.link_multiplicity,dimension,trunk,!positive);
 			return (Location::RouterPort{router_index:n_index, router_port:n_port},dimension);
 		}
-		(Location::ServerPort(port-2*m + router_index*self.servers_per_router),m)
+		(Location::ServerPort(port-self.maximum_degree() + router_index*self.servers_per_router),m)
 	}
 	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
 	{
 		let m=self.cartesian_data.sides.len();
 		(Location::RouterPort{
 			router_index: server_index/self.servers_per_router,
-			router_port: 2*m+server_index%self.servers_per_router,
+			router_port: self.maximum_degree()+server_index%self.servers_per_router,
 		},m)
 	}
 	fn diameter(&self) -> usize
@@ -369,19 +405,19 @@ impl Topology for Torus
 	}
 	fn maximum_degree(&self) -> usize
 	{
-		2*self.cartesian_data.sides.len()
+		2*self.link_multiplicity.iter().sum::<usize>()
 	}
 	fn minimum_degree(&self) -> usize
 	{
-		2*self.cartesian_data.sides.len()
+		self.maximum_degree()
 	}
 	fn degree(&self, _router_index: usize) -> usize
 	{
-		2*self.cartesian_data.sides.len()
+		self.maximum_degree()
 	}
 	fn ports(&self, _router_index: usize) -> usize
 	{
-		2*self.cartesian_data.sides.len()+self.servers_per_router
+		self.maximum_degree()+self.servers_per_router
 	}
 	fn cartesian_data(&self) -> Option<&CartesianData>
 	{
@@ -413,7 +449,8 @@ impl Topology for Torus
 	}
 	fn is_direction_change(&self, _router_index:usize, input_port: usize, output_port: usize) -> bool
 	{
-		input_port/2 != output_port/2
+		let dimension = |port| mesh_port_link(&self.link_multiplicity,port).map(|(dimension,_,_)|dimension);
+		dimension(input_port) != dimension(output_port)
 	}
 	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
 	{
@@ -423,15 +460,16 @@ impl Topology for Torus
 
 impl Torus
 {
-	pub fn new(cv:&ConfigurationValue) -> Torus
+	pub fn new(cv:&ConfigurationValue) -> Result<Torus,Error>
 	{
 		let mut sides:Option<Vec<_>>=None;
 		let mut servers_per_router=None;
+		let mut link_multiplicity=None;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
 		{
 			if cv_name!="Torus"
 			{
-				panic!("A Torus must be created from a `Torus` object not `{}`",cv_name);
+				return Err(cv.ill(&format!("A Torus must be created from a `Torus` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -440,37 +478,43 @@ impl Torus
 					"sides" => match value
 					{
 						&ConfigurationValue::Array(ref a) => sides=Some(a.iter().map(|v|match v{
-							&ConfigurationValue::Number(f) => f as usize,
-							_ => panic!("bad value in sides"),
-						}).collect()),
-						_ => panic!("bad value for sides"),
+							&ConfigurationValue::Number(f) => Ok(f as usize),
+							_ => Err(v.ill("bad value in sides")),
+						}).collect::<Result<_,_>>()?),
+						_ => return Err(value.ill("bad value for sides")),
 					}
 					"servers_per_router" => match value
 					{
 						&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
-						_ => panic!("bad value for servers_per_router"),
+						_ => return Err(value.ill("bad value for servers_per_router")),
 					}
+					"link_multiplicity" => link_multiplicity=Some(value.as_array()?.iter()
+						.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in Torus",name),
+					_ => return Err(cv.ill(&format!("Nothing to do with field {} in Torus",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a Torus from a non-Object");
+			return Err(cv.ill("Trying to create a Torus from a non-Object"));
 		}
-		let sides=sides.expect("There were no sides");
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
+		let sides=sides.ok_or_else(||cv.ill("There were no sides"))?;
+		let servers_per_router=servers_per_router.ok_or_else(||cv.ill("There were no servers_per_router"))?;
+		let link_multiplicity=check_link_multiplicity(link_multiplicity,sides.len());
 		//println!("servers_per_router={}",servers_per_router);
-		Torus{
+		Ok(Torus{
 			cartesian_data: CartesianData::new(&sides),
 			servers_per_router,
-		}
+			link_multiplicity,
+		})
 	}
 }
 
 ///The Hamming graph, the Cartesian product of complete graphs.
 ///Networks based on Hamming graphs have been called flattened butterflies and Hyper X.
+///With `link_multiplicity: [2,1]` each pair of routers differing in the first coordinate is joined by two parallel links, as the trunking of HyperX, so the routings may balance the load among them.
+///Each parallel link of dimension `d` adds `sides[d]-1` ports after the ones of the previous parallel link, all of them with link class `d`.
 #[derive(Quantifiable)]
 #[derive(Debug)]
 pub struct Hamming
@@ -478,6 +522,8 @@ pub struct Hamming
 	cartesian_data: CartesianData,
 	servers_per_router: usize,
 	wiring: Vec<Box<dyn CompleteGraphWiring>>,
+	///The number of parallel links between adjacent routers in each dimension.
+	link_multiplicity: Vec<usize>,
 }
 
 impl Topology for Hamming
@@ -503,13 +549,15 @@ impl Topology for Hamming
 		let m=self.cartesian_data.sides.len();
 		let mut dimension=0;
 		let mut offset=port;
-		while dimension<m && offset>=self.cartesian_data.sides[dimension]-1
+		while dimension<m && offset>=self.link_multiplicity[dimension]*(self.cartesian_data.sides[dimension]-1)
 		{
-			offset-=self.cartesian_data.sides[dimension]-1;
+			offset-=self.link_multiplicity[dimension]*(self.cartesian_data.sides[dimension]-1);
 			dimension+=1;
 		}
 		if dimension<m
 		{
+			//The offset within the ports of the same parallel link.
+			let offset=offset%(self.cartesian_data.sides[dimension]-1);
 			let mut coordinates=self.cartesian_data.unpack(router_index);
 			let (dest_switch_dim, dest_port)= self.wiring[dimension].map(coordinates[dimension], offset);
 			coordinates[dimension]=dest_switch_dim;
@@ -564,7 +612,7 @@ impl Topology for Hamming
 	}
 	fn maximum_degree(&self) -> usize
 	{
-		self.cartesian_data.sides.iter().fold(0usize,|accumulator,x|accumulator+x-1)
+		self.cartesian_data.sides.iter().zip(self.link_multiplicity.iter()).fold(0usize,|accumulator,(x,k)|accumulator+k*(x-1))
 	}
 	fn minimum_degree(&self) -> usize
 	{
@@ -600,16 +648,17 @@ impl Topology for Hamming
 
 impl Hamming
 {
-	pub fn new(cv:&ConfigurationValue) -> Hamming
+	pub fn new(cv:&ConfigurationValue, rng:&mut StdRng) -> Result<Hamming,Error>
 	{
 		let mut sides:Option<Vec<_>>=None;
 		let mut servers_per_router=None;
+		let mut link_multiplicity=None;
 		let mut wiring_str= ConfigurationValue::Object("CompleteGraphRelative".to_string(), vec![]); // Box::new(CompleteGraphRelative::default());
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
 		{
 			if cv_name!="Hamming"
 			{
-				panic!("A Hamming must be created from a `Hamming` object not `{}`",cv_name);
+				return Err(cv.ill(&format!("A Hamming must be created from a `Hamming` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -618,51 +667,574 @@ impl Hamming
 					"sides" => match value
 					{
 						&ConfigurationValue::Array(ref a) => sides=Some(a.iter().map(|v|match v{
-							&ConfigurationValue::Number(f) => f as usize,
-							_ => panic!("bad value in sides"),
-						}).collect()),
-						_ => panic!("bad value for sides"),
+							&ConfigurationValue::Number(f) => Ok(f as usize),
+							_ => Err(v.ill("bad value in sides")),
+						}).collect::<Result<_,_>>()?),
+						_ => return Err(value.ill("bad value for sides")),
 					}
 					"servers_per_router" => match value
 					{
 						&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
-						_ => panic!("bad value for servers_per_router"),
+						_ => return Err(value.ill("bad value for servers_per_router")),
 					}
 					"wiring" => match value
 					{
 						&ConfigurationValue::Object(ref cv_name, ref _cv_pairs) => wiring_str= ConfigurationValue::Object(cv_name.clone(),cv_pairs.clone()),//new_complete_graph_wiring(ConfigurationValue::Object(cv_name.clone(),cv_pairs.clone())),
 						_ => todo!(),
 					}
+					"link_multiplicity" => link_multiplicity=Some(value.as_array()?.iter()
+						.map(|v|v.as_usize()).collect::<Result<_,_>>()?),
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in Hamming",name),
+					_ => return Err(cv.ill(&format!("Nothing to do with field {} in Hamming",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a Hamming from a non-Object");
+			return Err(cv.ill("Trying to create a Hamming from a non-Object"));
 		}
-		let sides=sides.expect("There were no sides");
+		let sides=sides.ok_or_else(||cv.ill("There were no sides"))?;
 		//TODO if sides are of different sizes
 		// assert_eq!(sides.iter().unique().count(),1,"All sides must be the same");
 
 		let cartesian_data=CartesianData::new(&sides);
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
+		let servers_per_router=servers_per_router.ok_or_else(||cv.ill("There were no servers_per_router"))?;
+		let link_multiplicity=check_link_multiplicity(link_multiplicity,sides.len());
 		let wiring = (0..cartesian_data.sides.len()).map(|i|
 			{
 				let mut w=new_complete_graph_wiring(wiring_str.clone());
-				w.initialize(cartesian_data.sides[i], &mut StdRng::from_entropy());
+				w.initialize(cartesian_data.sides[i], rng);
 				w
 			}
 		).collect();
 		// wiring.initialize(cartesian_data.sides[0], &mut StdRng::from_entropy());
 		//println!("servers_per_router={}",servers_per_router);
-		Hamming{
+		Ok(Hamming{
 			cartesian_data,
 			servers_per_router,
 			wiring,
+			link_multiplicity,
+		})
+	}
+}
+
+/**
+A mesh or torus in which, besides the links to the adjacent routers, every router is connected to the routers at some other distances along each dimension.
+These longer links are called express links or express channels and reduce the diameter of the network at the cost of a greater degree.
+The hop lengths of the express links are given for each dimension in `express`. Each length of each dimension is an express level.
+
+The ports `0..2*m` are the links to adjacent routers as in the [Mesh], with `m` the number of dimensions. Then follow the express links, two ports per level,
+ordered by dimension and then by level, going first towards decreasing coordinates. The servers are connected to the last ports.
+In a mesh the ports that would go outside the block have `None` as neighbour. With `wrap_around:true` the links go around as in a [Torus].
+
+The links to adjacent routers in dimension `d` have link class `d`. Each express level gets its own link class, from `m` onwards in the order of the ports,
+and the servers have the next link class. For example, with `express:[[4],[2,4]]` there are the link classes 0 and 1 for adjacent routers,
+2 for the level of length 4 in dimension 0, 3 and 4 for the levels of length 2 and 4 in dimension 1, and 5 for servers.
+Hence `link_classes` must have an entry for each of them.
+
+The routing record gives the difference of coordinates, as in the [Mesh] and [Torus]. The [DOR] routing greedily uses the longest link that does not overshoot the target coordinate,
+which is not always a shortest route. The `distance` is computed exactly.
+
+```ignore
+ExpressMesh{
+	sides: [8,8],
+	express: [[4],[2,4]],
+	//wrap_around: false,//optional, to use a torus as base
+	servers_per_router: 1,
+	legend_name: "8x8 mesh with express links",
+}
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct ExpressMesh
+{
+	cartesian_data: CartesianData,
+	servers_per_router: usize,
+	///For each dimension the hop lengths of its express levels.
+	express: Vec<Vec<usize>>,
+	///Whether the links go around the sides as in the torus.
+	wrap_around: bool,
+	///For each dimension the offset of its express levels in the list of all of them.
+	level_offset: Vec<usize>,
+	///The total number of express levels.
+	total_levels: usize,
+	///For each dimension the matrix of distances between coordinates along it.
+	dimension_distance: Vec<Matrix<usize>>,
+}
+
+impl Topology for ExpressMesh
+{
+	fn num_routers(&self) -> usize
+	{
+		self.cartesian_data.size
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.cartesian_data.size*self.servers_per_router
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		let router_ports=2*(m+self.total_levels);
+		if port>=router_ports
+		{
+			return (Location::ServerPort(port-router_ports + router_index*self.servers_per_router),m+self.total_levels);
+		}
+		let (dimension,length,link_class) = self.port_link(port);
+		let side=self.cartesian_data.sides[dimension];
+		let mut coordinates=self.cartesian_data.unpack(router_index);
+		let coordinate=coordinates[dimension];
+		coordinates[dimension] = if self.wrap_around
+		{
+			if port%2==0 { (coordinate+side-length)%side } else { (coordinate+length)%side }
+		}
+		else
+		{
+			let next = if port%2==0 { coordinate.wrapping_sub(length) } else { coordinate+length };
+			if next>=side
+			{
+				return (Location::None,0);
+			}
+			next
+		};
+		let n_index=self.cartesian_data.pack(&coordinates);
+		//The link arrives through the port of the same level in the opposite direction.
+		(Location::RouterPort{router_index:n_index, router_port:port^1},link_class)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		(Location::RouterPort{
+			router_index: server_index/self.servers_per_router,
+			router_port: 2*(m+self.total_levels)+server_index%self.servers_per_router,
+		},m+self.total_levels)
+	}
+	fn diameter(&self) -> usize
+	{
+		self.dimension_distance.iter().map(|matrix|{
+			let side=matrix.get_columns();
+			(0..side).flat_map(|a|(0..side).map(move |b|*matrix.get(a,b))).max().unwrap_or(0)
+		}).sum()
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		let coord_origin=self.cartesian_data.unpack(origin);
+		let coord_destination=self.cartesian_data.unpack(destination);
+		self.dimension_distance.iter().enumerate().map(|(dimension,matrix)|*matrix.get(coord_origin[dimension],coord_destination[dimension])).sum()
+	}
+	fn amount_shortest_paths(&self,_origin:usize,_destination:usize) -> usize
+	{
+		unimplemented!();
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		unimplemented!();
+	}
+	fn degree(&self, router_index: usize) -> usize
+	{
+		self.neighbour_router_iter(router_index).count()
+	}
+	fn ports(&self, _router_index: usize) -> usize
+	{
+		2*(self.cartesian_data.sides.len()+self.total_levels)+self.servers_per_router
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		Some(&self.cartesian_data)
+	}
+	fn cartesian_link_dimension(&self, link_class:usize) -> usize
+	{
+		let m=self.cartesian_data.sides.len();
+		if link_class<m
+		{
+			return link_class;
+		}
+		let level=link_class-m;
+		(0..m).rev().find(|&dimension|self.level_offset[dimension]<=level).expect("invalid link class")
+	}
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], _rng: Option<&mut StdRng>)->Vec<i32>
+	{
+		(0..coordinates_a.len()).map(|i|{
+			let difference=coordinates_b[i] as i32-coordinates_a[i] as i32;
+			if self.wrap_around
+			{
+				//Go in the direction with fewer hops, using the shortest difference in case of tie.
+				let side=self.cartesian_data.sides[i] as i32;
+				let forward=(difference+side)%side;
+				let backward=(side-forward)%side;
+				let forward_hops=self.greedy_hops(i,forward as usize);
+				let backward_hops=self.greedy_hops(i,backward as usize);
+				if forward_hops<backward_hops || (forward_hops==backward_hops && forward<=backward) { forward } else { -backward }
+			}
+			else
+			{
+				difference
+			}
+		}).collect()
+	}
+	fn is_direction_change(&self, _router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		let m=self.cartesian_data.sides.len();
+		let router_ports=2*(m+self.total_levels);
+		if input_port>=router_ports || output_port>=router_ports
+		{
+			return true;
+		}
+		self.port_link(input_port).0 != self.port_link(output_port).0
+	}
+	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
+	{
+		None
+	}
+}
+
+impl ExpressMesh
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<ExpressMesh,Error>
+	{
+		let mut sides:Option<Vec<usize>>=None;
+		let mut express:Option<Vec<Vec<usize>>>=None;
+		let mut wrap_around=false;
+		let mut servers_per_router=None;
+		match_object!(cv,"ExpressMesh",value,
+			"sides" => sides=Some(value.as_array()?.iter().map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"express" => express=Some(value.as_array()?.iter().map(|lengths|
+				lengths.as_array()?.iter().map(|v|v.as_usize()).collect()
+			).collect::<Result<_,_>>()?),
+			"wrap_around" => wrap_around=value.as_bool()?,
+			"servers_per_router" => servers_per_router=Some(value.as_usize()?),
+		);
+		let sides=sides.ok_or_else(||cv.ill("There were no sides"))?;
+		let express=express.ok_or_else(||cv.ill("There were no express"))?;
+		let servers_per_router=servers_per_router.ok_or_else(||cv.ill("There were no servers_per_router"))?;
+		assert_eq!(sides.len(),express.len(),"ExpressMesh requires a list of express lengths for each dimension.");
+		let mut level_offset=Vec::with_capacity(sides.len());
+		let mut total_levels=0;
+		for (dimension,lengths) in express.iter().enumerate()
+		{
+			for (index,&length) in lengths.iter().enumerate()
+			{
+				assert!(length>1 && length<sides[dimension],"The express length {} in dimension {} must be greater than 1 and lower than the side {}.",length,dimension,sides[dimension]);
+				assert!(!lengths[..index].contains(&length),"The express length {} is repeated in dimension {}.",length,dimension);
+			}
+			level_offset.push(total_levels);
+			total_levels+=lengths.len();
+		}
+		let mut topology=ExpressMesh{
+			cartesian_data: CartesianData::new(&sides),
+			servers_per_router,
+			express,
+			wrap_around,
+			level_offset,
+			total_levels,
+			dimension_distance: vec![],
+		};
+		topology.dimension_distance=(0..sides.len()).map(|dimension|topology.compute_dimension_distance(dimension)).collect();
+		Ok(topology)
+	}
+	///The dimension, the hop length, and the link class of a port towards other router.
+	fn port_link(&self, port:usize) -> (usize,usize,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		if port<2*m
+		{
+			return (port/2,1,port/2);
+		}
+		let level=(port-2*m)/2;
+		let dimension=(0..m).rev().find(|&dimension|self.level_offset[dimension]<=level).expect("invalid port");
+		(dimension,self.express[dimension][level-self.level_offset[dimension]],m+level)
+	}
+	///The distances between coordinates along a dimension, by a breadth first search on the path or cycle with the express links.
+	fn compute_dimension_distance(&self, dimension:usize) -> Matrix<usize>
+	{
+		let side=self.cartesian_data.sides[dimension];
+		let lengths:Vec<usize>=std::iter::once(1).chain(self.express[dimension].iter().cloned()).collect();
+		let mut matrix=Matrix::constant(usize::MAX,side,side);
+		for origin in 0..side
+		{
+			*matrix.get_mut(origin,origin)=0;
+			let mut queue=vec![origin];
+			let mut queue_read_index=0;
+			while queue_read_index<queue.len()
+			{
+				let current=queue[queue_read_index];
+				queue_read_index+=1;
+				let alt=*matrix.get(origin,current)+1;
+				for &length in lengths.iter()
+				{
+					let nexts = if self.wrap_around
+					{
+						vec![(current+length)%side,(current+side-length)%side]
+					}
+					else
+					{
+						vec![current+length,current.wrapping_sub(length)].into_iter().filter(|&next|next<side).collect()
+					};
+					for next in nexts
+					{
+						if *matrix.get(origin,next)==usize::MAX
+						{
+							*matrix.get_mut(origin,next)=alt;
+							queue.push(next);
+						}
+					}
+				}
+			}
+		}
+		matrix
+	}
+	///The hops required to advance `amount` in a dimension always in the same direction, using greedily the longest links, as done by [DOR].
+	fn greedy_hops(&self, dimension:usize, mut amount:usize) -> usize
+	{
+		let mut lengths:Vec<usize>=std::iter::once(1).chain(self.express[dimension].iter().cloned()).collect();
+		lengths.sort_unstable();
+		let mut hops=0;
+		for &length in lengths.iter().rev()
+		{
+			hops+=amount/length;
+			amount%=length;
+		}
+		hops
+	}
+}
+
+/**
+A torus in which the wrap-around links of some dimensions arrive shifted in the preceding dimensions, known as a twisted torus.
+Crossing the wrap-around of dimension `j` in the increasing direction advances `twists[j][k]` positions in each dimension `k<j`,
+and crossing it in the decreasing direction goes back the same amount. Hence `twists` has an entry for each dimension,
+with at most as many values as preceding dimensions, the missing ones being 0. With all twists null it is the [Torus].
+A twisted torus has a smaller diameter and average distance than the torus of the same sides when these are different.
+
+The ports, link classes, and servers are as in the [Torus]. The routing record gives the displacement in each dimension of a shortest route,
+which may cross the twisted wrap-around links. As these links may arrive at a coordinate also reachable in the opposite direction,
+the ports tell their [displacement](Topology::cartesian_link_displacement), so the [DOR] and [ValiantDOR] routings follow shortest routes in any order of the dimensions.
+
+The rectangular twisted torus of `2a×a` routers and the prismatic doubly twisted torus of `2a×a×a` routers can be built directly.
+```ignore
+TwistedTorus{
+	sides: [8,4],
+	//Going around the second dimension shifts the first one by 4.
+	twists: [[],[4]],
+	servers_per_router: 1,
+	legend_name: "8x4 rectangular twisted torus",
+}
+//RectangularTwistedTorus{ side:4, servers_per_router:1 },//the same as above
+//PrismaticDoublyTwistedTorus{ side:4, servers_per_router:1 },//sides [8,4,4] with twists [[],[4],[4,0]]
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct TwistedTorus
+{
+	cartesian_data: CartesianData,
+	servers_per_router: usize,
+	///For each dimension `j` the displacement equivalent to a null one, that is, `sides[j]` in the dimension `j` and minus the twists in the preceding ones.
+	///The twists are normalized to be within the sides.
+	wraps: Vec<Vec<i32>>,
+	diameter: usize,
+}
+
+impl Topology for TwistedTorus
+{
+	fn num_routers(&self) -> usize
+	{
+		self.cartesian_data.size
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.cartesian_data.size*self.servers_per_router
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		if port<2*m
+		{
+			let dimension=port/2;
+			let mut coordinates:Vec<i32>=self.cartesian_data.unpack(router_index).iter().map(|&c|c as i32).collect();
+			coordinates[dimension]+=if port%2==0 { -1 } else { 1 };
+			let coordinates:Vec<usize>=self.reduce(coordinates).iter().map(|&c|c as usize).collect();
+			let n_index=self.cartesian_data.pack(&coordinates);
+			//The link arrives through the port of the same dimension in the opposite direction.
+			return (Location::RouterPort{router_index:n_index, router_port:port^1},dimension);
+		}
+		(Location::ServerPort(port-2*m + router_index*self.servers_per_router),m)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		(Location::RouterPort{
+			router_index: server_index/self.servers_per_router,
+			router_port: 2*m+server_index%self.servers_per_router,
+		},m)
+	}
+	fn diameter(&self) -> usize
+	{
+		self.diameter
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		let coord_origin=self.cartesian_data.unpack(origin);
+		let coord_destination=self.cartesian_data.unpack(destination);
+		let rr=self.coordinated_routing_record(&coord_origin,&coord_destination,None);
+		rr.iter().map(|x|x.unsigned_abs() as usize).sum()
+	}
+	fn amount_shortest_paths(&self,_origin:usize,_destination:usize) -> usize
+	{
+		unimplemented!();
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		unimplemented!();
+	}
+	fn maximum_degree(&self) -> usize
+	{
+		2*self.cartesian_data.sides.len()
+	}
+	fn minimum_degree(&self) -> usize
+	{
+		2*self.cartesian_data.sides.len()
+	}
+	fn degree(&self, _router_index: usize) -> usize
+	{
+		2*self.cartesian_data.sides.len()
+	}
+	fn ports(&self, _router_index: usize) -> usize
+	{
+		2*self.cartesian_data.sides.len()+self.servers_per_router
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		Some(&self.cartesian_data)
+	}
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng: Option<&mut StdRng>)->Vec<i32>
+	{
+		//The displacements leading from `a` to `b` are the difference plus any combination of the wraps.
+		//Once normalized, a shortest one uses each wrap at most twice in each direction.
+		let difference=self.reduce(coordinates_b.iter().zip(coordinates_a.iter()).map(|(&b,&a)|b as i32-a as i32).collect());
+		let m=difference.len();
+		if m==0
+		{
+			return vec![];
+		}
+		let mut best_length=i32::MAX;
+		let mut best=vec![];
+		//The null combination goes first, so that ties are broken towards increasing coordinates when there is no rng.
+		for factors in (0..m).map(|_|[0i32,-1,1,-2,2].iter().cloned()).multi_cartesian_product()
+		{
+			let mut record=difference.clone();
+			for (wrap,factor) in self.wraps.iter().zip(factors.iter())
+			{
+				for (value,w) in record.iter_mut().zip(wrap.iter())
+				{
+					*value+=factor*w;
+				}
+			}
+			let length:i32=record.iter().map(|x|x.abs()).sum();
+			if length<best_length
+			{
+				best_length=length;
+				best.clear();
+			}
+			if length==best_length
+			{
+				best.push(record);
+			}
+		}
+		let index=match rng
+		{
+			Some(rng) => rng.gen_range(0..best.len()),
+			None => 0,
+		};
+		best.swap_remove(index)
+	}
+	fn cartesian_link_displacement(&self, _router_index:usize, port:usize) -> Option<i32>
+	{
+		//A twisted wrap-around link may arrive at a coordinate also reachable in the opposite direction.
+		if port<2*self.cartesian_data.sides.len()
+		{
+			Some(if port%2==0 { -1 } else { 1 })
+		}
+		else
+		{
+			None
 		}
 	}
+	fn is_direction_change(&self, _router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		input_port/2 != output_port/2
+	}
+	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
+	{
+		None
+	}
+}
+
+impl TwistedTorus
+{
+	pub fn new(cv:&ConfigurationValue) -> Result<TwistedTorus,Error>
+	{
+		let mut sides:Option<Vec<usize>>=None;
+		let mut twists:Option<Vec<Vec<usize>>>=None;
+		let mut side=None;
+		let mut servers_per_router=None;
+		match_object!(cv,["TwistedTorus","RectangularTwistedTorus","PrismaticDoublyTwistedTorus"],value,
+			"sides" => sides=Some(value.as_array()?.iter().map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"twists" => twists=Some(value.as_array()?.iter().map(|shifts|
+				shifts.as_array()?.iter().map(|v|v.as_usize()).collect()
+			).collect::<Result<_,_>>()?),
+			"side" => side=Some(value.as_usize()?),
+			"servers_per_router" => servers_per_router=Some(value.as_usize()?),
+		);
+		let (sides,twists) = match cv
+		{
+			ConfigurationValue::Object(name,_) if name=="RectangularTwistedTorus" =>
+			{
+				let a=side.ok_or_else(||cv.ill("There were no side"))?;
+				(vec![2*a,a],vec![vec![],vec![a]])
+			},
+			ConfigurationValue::Object(name,_) if name=="PrismaticDoublyTwistedTorus" =>
+			{
+				let a=side.ok_or_else(||cv.ill("There were no side"))?;
+				(vec![2*a,a,a],vec![vec![],vec![a],vec![a,0]])
+			},
+			_ => (sides.ok_or_else(||cv.ill("There were no sides"))?,twists.ok_or_else(||cv.ill("There were no twists"))?),
+		};
+		let servers_per_router=servers_per_router.ok_or_else(||cv.ill("There were no servers_per_router"))?;
+		assert_eq!(sides.len(),twists.len(),"TwistedTorus requires a list of twists for each dimension.");
+		assert!(sides.iter().all(|&s|s>0),"The sides of a TwistedTorus must be positive.");
+		let mut topology=TwistedTorus{
+			cartesian_data: CartesianData::new(&sides),
+			servers_per_router,
+			wraps: vec![],
+			diameter: 0,
+		};
+		for (dimension,shifts) in twists.iter().enumerate()
+		{
+			assert!(shifts.len()<=dimension,"The dimension {} of a TwistedTorus can only be twisted in the {} preceding dimensions.",dimension,dimension);
+			let mut wrap:Vec<i32>=(0..sides.len()).map(|k|if k<shifts.len() { -(shifts[k] as i32) } else { 0 }).collect();
+			//Normalize the twist by the wraps of the preceding dimensions.
+			wrap=topology.reduce(wrap);
+			wrap[dimension]=sides[dimension] as i32;
+			topology.wraps.push(wrap);
+		}
+		//The graph is vertex-transitive, so the diameter is the greatest distance from any router.
+		topology.diameter=(0..topology.cartesian_data.size).map(|router|topology.distance(0,router)).max().unwrap_or(0);
+		Ok(topology)
+	}
+	///The coordinates within the sides equivalent to a displacement, by subtracting the wraps from the last dimension to the first.
+	fn reduce(&self, mut coordinates:Vec<i32>) -> Vec<i32>
+	{
+		for (dimension,wrap) in self.wraps.iter().enumerate().rev()
+		{
+			let quotient=coordinates[dimension].div_euclid(wrap[dimension]);
+			for (value,w) in coordinates.iter_mut().zip(wrap.iter())
+			{
+				*value-=quotient*w;
+			}
+		}
+		coordinates
+	}
 }
 
 pub trait CompleteGraphWiring : Quantifiable + core::fmt::Debug
@@ -740,6 +1312,7 @@ impl Topology for AsCartesianTopology
 	fn num_servers(&self) -> usize { self.topology.num_servers() }
 	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize) { self.topology.neighbour(router_index,port) }
 	fn server_neighbour(&self, server_index:usize) -> (Location,usize) { self.topology.server_neighbour(server_index) }
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)> { self.topology.server_neighbours(server_index) }
 	fn diameter(&self) -> usize { self.topology.diameter() }
 	fn distance(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
 	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
@@ -760,14 +1333,21 @@ impl Topology for AsCartesianTopology
 		todo!()
 		// This seems it should be implemented
 	}
+	fn is_link_alive(&self, router_index:usize, port:usize) -> bool { self.topology.is_link_alive(router_index,port) }
 	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
 	{ self.topology.up_down_distance(origin,destination) }
 	fn dragonfly_size(&self) -> Option<crate::topology::dragonfly::ArrangementSize>
 	{ self.topology.dragonfly_size() }
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{ self.topology.router_role(router_index) }
 	fn bfs(&self, origin:usize, class_weight:Option<&[usize]>) -> Vec<usize>
 	{ self.topology.bfs(origin,class_weight) }
 	fn compute_distance_matrix(&self, class_weight:Option<&[usize]>) -> Matrix<usize>
 	{ self.topology.compute_distance_matrix(class_weight) }
+	fn dijkstra(&self, origin:usize, class_weight:&[usize]) -> Vec<usize>
+	{ self.topology.dijkstra(origin,class_weight) }
+	fn compute_weighted_distance_matrix(&self, class_weight:&[usize]) -> Matrix<usize>
+	{ self.topology.compute_weighted_distance_matrix(class_weight) }
 	fn compute_amount_shortest_paths(&self) -> (Matrix<usize>,Matrix<usize>)
 	{ self.topology.compute_amount_shortest_paths() }
 	fn components(&self,allowed_classes:&[bool]) -> Vec<Vec<usize>>
@@ -780,23 +1360,23 @@ impl Topology for AsCartesianTopology
 
 impl AsCartesianTopology
 {
-	pub fn new(mut arg:TopologyBuilderArgument) -> AsCartesianTopology
+	pub fn new(mut arg:TopologyBuilderArgument) -> Result<AsCartesianTopology,Error>
 	{
 		let mut sides:Option<Vec<_>>=None;
 		let mut topology:Option<Box<dyn Topology>> = None;
-		match_object_panic!(arg.cv,"AsCartesianTopology",value,
-			"sides" => sides = Some(value.as_array().expect("bad value for sides").iter().map(|v|v.as_usize().expect("bad value in sides")).collect()),
-			"topology" => topology = Some(new_topology(arg.with_cv(value))),
+		match_object!(arg.cv,"AsCartesianTopology",value,
+			"sides" => sides = Some(value.as_array()?.iter().map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"topology" => topology = Some(try_new_topology(arg.with_cv(value))?),
 		);
-		let sides=sides.expect("There were no sides");
-		let topology=topology.expect("There were no topology");
+		let sides=sides.ok_or_else(||arg.cv.ill("There were no sides"))?;
+		let topology=topology.ok_or_else(||arg.cv.ill("There were no topology"))?;
 		let nr = topology.num_routers();
 		let sm = sides.iter().product();
 		assert_eq!(nr,sm,"size of the topology does not match the given sides.");
-		AsCartesianTopology{
+		Ok(AsCartesianTopology{
 			cartesian_data: CartesianData::new(&sides),
 			topology,
-		}
+		})
 	}
 }
 
@@ -805,6 +1385,84 @@ impl AsCartesianTopology
 //	coordinates: Vec<usize>,
 //}
 
+///The ports of `current_router` in the dimension `dimension` advancing the most towards the target of the `routing_record` without going beyond it.
+///The parallel links of a [Mesh], [Torus] or [Hamming] with `link_multiplicity` are all included.
+fn dimension_ports(topology:&dyn Topology, current_router:usize, routing_record:&[i32], dimension:usize) -> Vec<usize>
+{
+	let cartesian_data=topology.cartesian_data().expect("requires a Cartesian topology");
+	let up_current=cartesian_data.unpack(current_router);
+	let mut best=vec![];
+	let mut best_amount=0;
+	let limit=routing_record[dimension].unsigned_abs() as usize;
+	let side=cartesian_data.sides[dimension];
+	for j in 0..topology.ports(current_router)
+	{
+		if let (Location::RouterPort{router_index: next_router, router_port:_},next_link_class)=topology.neighbour(current_router,j)
+		{
+			if topology.cartesian_link_dimension(next_link_class)==dimension
+			{
+				let up_next=cartesian_data.unpack(next_router);
+				let amount=match topology.cartesian_link_displacement(current_router,j)
+				{
+					Some(displacement) => if (displacement<0)==(routing_record[dimension]<0) { displacement.unsigned_abs() as usize } else { continue },
+					None => (if routing_record[dimension]<0
+					{
+						side+up_current[dimension]-up_next[dimension]
+					}
+					else
+					{
+						side+up_next[dimension]-up_current[dimension]
+					})%side,
+				};
+				if amount<=limit
+				{
+					if amount>best_amount
+					{
+						best_amount=amount;
+						best=vec![j];
+					}
+					else if amount==best_amount
+					{
+						best.push(j);
+					}
+				}
+			}
+		}
+	}
+	best
+}
+
+///The dimension and the change in the `routing_record` of a packet that has arrived to `current_router` through its port `current_port`.
+fn arrival_displacement(topology:&dyn Topology, current_router:usize, current_port:usize, routing_record:&[i32]) -> (usize,i32)
+{
+	let cartesian_data=topology.cartesian_data().expect("requires a Cartesian topology");
+	if let (Location::RouterPort{router_index: previous_router, router_port:_},link_class)=topology.neighbour(current_router,current_port)
+	{
+		let dimension=topology.cartesian_link_dimension(link_class);
+		let up_current=cartesian_data.unpack(current_router);
+		let up_previous=cartesian_data.unpack(previous_router);
+		let side=cartesian_data.sides[dimension] as i32;
+		//The link back to the previous router undoes the displacement.
+		let delta:i32=match topology.cartesian_link_displacement(current_router,current_port)
+		{
+			Some(displacement) => displacement,
+			None => if routing_record[dimension]<0
+			{
+				(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
+			}
+			else
+			{
+				-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
+			},
+		};
+		(dimension,delta)
+	}
+	else
+	{
+		panic!("The packet has not arrived from a router");
+	}
+}
+
 ///A shortest routing for Cartesian topologies employing links in a predefined order.
 ///This is, if `order=[0,1]` the packet will go first by links changing the 0-dimension and then it will use the links in the 1-dimension until destination.
 ///The amount of links in each dimension is stored in `routing_info.routing_record` when the packet reaches the first routing and it is updated each hop.
@@ -846,81 +1504,41 @@ impl Routing for DOR
 			for i in 0..topology.ports(current_router)
 			{
 				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
-				{
-					if server==target_server
-					{
-						//return vec![i];
-						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
-						let r= (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
-						return Ok(RoutingNextCandidates{candidates:r,idempotent:true});
-					}
-				}
-			}
-			panic!("The server {} is not attached to this router ({}) but the routing record is {:?}",target_server,current_router,routing_record);
-		}
-		else
-		{
-			i=self.order[i];
-			//Go in dimension i
-			// //WARNING: This assumes ports in a mesh-like configuration!
-			// if routing_record[i]<0
-			// {
-			// 	//return vec![2*i];
-			// 	return (0..num_virtual_channels).map(|vc|(2*i,vc)).collect();
-			// }
-			// else
-			// {
-			// 	//return vec![2*i+1];
-			// 	return (0..num_virtual_channels).map(|vc|(2*i+1,vc)).collect();
-			// }
-			//let (target_location,_link_class)=topology.server_neighbour(target_server);
-			//let target_router=match target_location
-			//{
-			//	Location::RouterPort{router_index,router_port:_} =>router_index,
-			//	_ => panic!("The server is not attached to a router"),
-			//};
-			let cartesian_data=topology.cartesian_data().expect("DOR requires a Cartesian topology");
-			let up_current=cartesian_data.unpack(current_router);
-			//let up_target=cartesian_data.unpack(target_router);
-			let mut best=vec![];
-			let mut best_amount=0;
-			let limit=routing_record[i].abs() as usize;
-			let side=cartesian_data.sides[i];
-			for j in 0..topology.ports(current_router)
-			{
-				if let (Location::RouterPort{router_index: next_router, router_port:_},next_link_class)=topology.neighbour(current_router,j)
-				{
-					if next_link_class==i
-					{
-						let up_next=cartesian_data.unpack(next_router);
-						//if up_target[i]==up_next[i]
-						//{
-						// 	return (0..num_virtual_channels).map(|vc|(j,vc)).collect();
-						//}
-						let amount=(if routing_record[i]<0
-						{
-							up_current[i]-up_next[i]
-						}
-						else
-						{
-							up_next[i]-up_current[i]
-						}+side)%side;
-						if amount<=limit
-						{
-							if amount>best_amount
-							{
-								best_amount=amount;
-								best=vec![j];
-							}
-							else if amount==best_amount
-							{
-								best.push(j);
-							}
-						}
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						//return vec![i];
+						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
+						let r= (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
+						return Ok(RoutingNextCandidates{candidates:r,idempotent:true});
 					}
 				}
 			}
+			panic!("The server {} is not attached to this router ({}) but the routing record is {:?}",target_server,current_router,routing_record);
+		}
+		else
+		{
+			i=self.order[i];
+			//Go in dimension i
+			// //WARNING: This assumes ports in a mesh-like configuration!
+			// if routing_record[i]<0
+			// {
+			// 	//return vec![2*i];
+			// 	return (0..num_virtual_channels).map(|vc|(2*i,vc)).collect();
+			// }
+			// else
+			// {
+			// 	//return vec![2*i+1];
+			// 	return (0..num_virtual_channels).map(|vc|(2*i+1,vc)).collect();
+			// }
+			//let (target_location,_link_class)=topology.server_neighbour(target_server);
+			//let target_router=match target_location
+			//{
+			//	Location::RouterPort{router_index,router_port:_} =>router_index,
+			//	_ => panic!("The server is not attached to a router"),
+			//};
+			let best=dimension_ports(topology,current_router,routing_record,i);
 			if best.is_empty()
 			{
 				panic!("No links improving {} dimension\n",i);
@@ -952,43 +1570,15 @@ impl Routing for DOR
 	{
 		//let dimension=current_port/2;
 		//let delta=if current_port%2==0 { -1i32 } else { 1i32 };
-		let cartesian_data=topology.cartesian_data().expect("DOR requires a Cartesian topology");
-		if let (Location::RouterPort{router_index: previous_router, router_port:_},dimension)=topology.neighbour(current_router,current_port)
+		match routing_info.borrow_mut().routing_record
 		{
-			let up_current=cartesian_data.unpack(current_router);
-			let up_previous=cartesian_data.unpack(previous_router);
-			let side=cartesian_data.sides[dimension] as i32;
-			match routing_info.borrow_mut().routing_record
+			Some(ref mut rr) =>
 			{
-				Some(ref mut rr) =>
-				{
-					let delta:i32=if rr[dimension]<0
-					{
-						(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
-					}
-					else
-					{
-						-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
-					};
-					rr[dimension]+=delta;
-					// --- DEBUG vvv
-					//let (target_location,_link_class)=topology.server_neighbour(target_server);
-					//let target_router=match target_location
-					//{
-					//	Location::RouterPort{router_index,router_port:_} =>router_index,
-					//	_ => panic!("The server is not attached to a router"),
-					//};
-					//let up_target=cartesian_data.unpack(target_router);
-					//println!("new routing record. current_router={}({:?}, current_port={} previous_router={}({:?}), delta={}, rr={:?}, target_server={} target_router={}({:?})",current_router,up_current,current_port,previous_router,up_previous,delta,rr,target_server,target_router,up_target);
-					// --- DEBUG ^^^
-				},
-				None => panic!("trying to update without routing_record"),
-			};
-		}
-		else
-		{
-			panic!("!!");
-		}
+				let (dimension,delta)=arrival_displacement(topology,current_router,current_port,rr);
+				rr[dimension]+=delta;
+			},
+			None => panic!("trying to update without routing_record"),
+		};
 	}
 	fn initialize(&mut self, _topology:&dyn Topology, _rng: &mut StdRng)
 	{
@@ -1007,7 +1597,7 @@ impl Routing for DOR
 
 impl DOR
 {
-	pub fn new(arg:RoutingBuilderArgument) -> DOR
+	pub fn new(arg:RoutingBuilderArgument) -> Result<DOR,Error>
 	{
 		let mut order=None;
 		//let mut servers_per_router=None;
@@ -1015,7 +1605,7 @@ impl DOR
 		{
 			if cv_name!="DOR"
 			{
-				panic!("A DOR must be created from a `DOR` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A DOR must be created from a `DOR` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -1027,7 +1617,7 @@ impl DOR
 							&ConfigurationValue::Number(f) => f as usize,
 							_ => panic!("bad value in order"),
 						}).collect()),
-						_ => panic!("bad value for order"),
+						_ => return Err(value.ill("bad value for order")),
 					}
 					//"servers_per_router" => match value
 					//{
@@ -1035,20 +1625,20 @@ impl DOR
 					//	_ => panic!("bad value for servers_per_router"),
 					//}
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in DOR",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in DOR",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a DOR from a non-Object");
+			return Err(arg.cv.ill("Trying to create a DOR from a non-Object"));
 		}
 		//let sides=sides.expect("There were no sides");
 		//let servers_per_router=servers_per_router.expect("There were no servers_per_router");
-		let order=order.expect("There were no order");
-		DOR{
+		let order=order.ok_or_else(||arg.cv.ill("There were no order"))?;
+		Ok(DOR{
 			order,
-		}
+		})
 	}
 }
 
@@ -1171,21 +1761,25 @@ impl Routing for ValiantDOR
 			{
 				if let (Location::RouterPort{router_index: next_router, router_port:_},next_link_class)=topology.neighbour(current_router,j)
 				{
-					if next_link_class==dim
+					if topology.cartesian_link_dimension(next_link_class)==dim
 					{
 						let up_next=cartesian_data.unpack(next_router);
 						//if up_target[dim]==up_next[dim]
 						//{
 						// 	return (0..num_virtual_channels).map(|vc|(j,vc)).collect();
 						//}
-						let amount=(if target_amount<0
+						let amount=match topology.cartesian_link_displacement(current_router,j)
 						{
-							up_current[dim]-up_next[dim]
-						}
-						else
-						{
-							up_next[dim]-up_current[dim]
-						}+side)%side;
+							Some(displacement) => if (displacement<0)==(target_amount<0) { displacement.unsigned_abs() as usize } else { continue },
+							None => (if target_amount<0
+							{
+								side+up_current[dim]-up_next[dim]
+							}
+							else
+							{
+								side+up_next[dim]-up_current[dim]
+							})%side,
+						};
 						if amount<=limit
 						{
 							if amount>best_amount
@@ -1274,8 +1868,9 @@ impl Routing for ValiantDOR
 		//let dimension=current_port/2;
 		//let delta=if current_port%2==0 { -1i32 } else { 1i32 };
 		let cartesian_data=topology.cartesian_data().expect("ValiantDOR requires a Cartesian topology");
-		if let (Location::RouterPort{router_index: previous_router, router_port:_},dimension)=topology.neighbour(current_router,current_port)
+		if let (Location::RouterPort{router_index: previous_router, router_port:_},link_class)=topology.neighbour(current_router,current_port)
 		{
+			let dimension=topology.cartesian_link_dimension(link_class);
 			let up_current=cartesian_data.unpack(current_router);
 			let up_previous=cartesian_data.unpack(previous_router);
 			let side=cartesian_data.sides[dimension] as i32;
@@ -1284,13 +1879,18 @@ impl Routing for ValiantDOR
 			{
 				Some(ref mut rr) =>
 				{
-					let delta:i32=if rr[dimension]<0
-					{
-						(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
-					}
-					else
+					//The link back to the previous router undoes the displacement.
+					let delta:i32=match topology.cartesian_link_displacement(current_router,current_port)
 					{
-						-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
+						Some(displacement) => displacement,
+						None => if rr[dimension]<0
+						{
+							(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
+						}
+						else
+						{
+							-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
+						},
 					};
 					rr[dimension]+=delta;
 					// --- DEBUG vvv
@@ -1317,13 +1917,18 @@ impl Routing for ValiantDOR
 					{
 						panic!("Incorrect dimension while randomizing");
 					}
-					let delta:i32=if r<0
-					{
-						(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
-					}
-					else
+					//The link back to the previous router undoes the displacement.
+					let delta:i32=match topology.cartesian_link_displacement(current_router,current_port)
 					{
-						-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
+						Some(displacement) => displacement,
+						None => if r<0
+						{
+							(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
+						}
+						else
+						{
+							-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
+						},
 					};
 					r+=delta;
 					let target_router = if r!=0 { None } else
@@ -1386,7 +1991,7 @@ impl Routing for ValiantDOR
 
 impl ValiantDOR
 {
-	pub fn new(arg:RoutingBuilderArgument) -> ValiantDOR
+	pub fn new(arg:RoutingBuilderArgument) -> Result<ValiantDOR,Error>
 	{
 		let mut randomized=None;
 		let mut shortest=None;
@@ -1397,7 +2002,7 @@ impl ValiantDOR
 		{
 			if cv_name!="ValiantDOR"
 			{
-				panic!("A ValiantDOR must be created from a `ValiantDOR` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A ValiantDOR must be created from a `ValiantDOR` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -1409,7 +2014,7 @@ impl ValiantDOR
 							&ConfigurationValue::Number(f) => f as usize,
 							_ => panic!("bad value in randomized"),
 						}).collect()),
-						_ => panic!("bad value for randomized"),
+						_ => return Err(value.ill("bad value for randomized")),
 					}
 					"shortest" => match value
 					{
@@ -1417,7 +2022,7 @@ impl ValiantDOR
 							&ConfigurationValue::Number(f) => f as usize,
 							_ => panic!("bad value in shortest"),
 						}).collect()),
-						_ => panic!("bad value for shortest"),
+						_ => return Err(value.ill("bad value for shortest")),
 					}
 					"randomized_reserved_virtual_channels" => match value
 					{
@@ -1425,7 +2030,7 @@ impl ValiantDOR
 							&ConfigurationValue::Number(f) => f as usize,
 							_ => panic!("bad value in randomized_reserved_virtual_channels"),
 						}).collect()),
-						_ => panic!("bad value for randomized_reserved_virtual_channels"),
+						_ => return Err(value.ill("bad value for randomized_reserved_virtual_channels")),
 					}
 					"shortest_reserved_virtual_channels" => match value
 					{
@@ -1433,7 +2038,7 @@ impl ValiantDOR
 							&ConfigurationValue::Number(f) => f as usize,
 							_ => panic!("bad value in shortest_reserved_virtual_channels"),
 						}).collect()),
-						_ => panic!("bad value for shortest_reserved_virtual_channels"),
+						_ => return Err(value.ill("bad value for shortest_reserved_virtual_channels")),
 					}
 					//"servers_per_router" => match value
 					//{
@@ -1441,24 +2046,24 @@ impl ValiantDOR
 					//	_ => panic!("bad value for servers_per_router"),
 					//}
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in ValiantDOR",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in ValiantDOR",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a ValiantDOR from a non-Object");
+			return Err(arg.cv.ill("Trying to create a ValiantDOR from a non-Object"));
 		}
-		let randomized=randomized.expect("There were no randomized");
-		let shortest=shortest.expect("There were no shortest");
-		let randomized_reserved_virtual_channels=randomized_reserved_virtual_channels.expect("There were no randomized_reserved_virtual_channels");
-		let shortest_reserved_virtual_channels=shortest_reserved_virtual_channels.expect("There were no shortest_reserved_virtual_channels");
-		ValiantDOR{
+		let randomized=randomized.ok_or_else(||arg.cv.ill("There were no randomized"))?;
+		let shortest=shortest.ok_or_else(||arg.cv.ill("There were no shortest"))?;
+		let randomized_reserved_virtual_channels=randomized_reserved_virtual_channels.ok_or_else(||arg.cv.ill("There were no randomized_reserved_virtual_channels"))?;
+		let shortest_reserved_virtual_channels=shortest_reserved_virtual_channels.ok_or_else(||arg.cv.ill("There were no shortest_reserved_virtual_channels"))?;
+		Ok(ValiantDOR{
 			randomized,
 			shortest,
 			randomized_reserved_virtual_channels,
 			shortest_reserved_virtual_channels,
-		}
+		})
 	}
 }
 
@@ -1534,18 +2139,13 @@ impl Routing for O1TURN
 		else
 		{
 			i=order[i];
-			//Go in dimension i
-			//WARNING: This assumes ports in a mesh-like configuration!
-			let p=if routing_record[i]<0
+			//Go in dimension i, by any of its parallel links.
+			let best=dimension_ports(topology,current_router,routing_record,i);
+			if best.is_empty()
 			{
-				2*i
+				panic!("No links improving {} dimension\n",i);
 			}
-			else
-			{
-				2*i+1
-			};
-			//return vec![CandidateEgress::new(p,s)];
-			let r= available_virtual_channels.map(|vc| CandidateEgress::new(p,vc)).collect();
+			let r= available_virtual_channels.flat_map(|vc| best.iter().map(move |&p|CandidateEgress::new(p,vc))).collect();
 			return Ok(RoutingNextCandidates{candidates:r,idempotent:true});
 		}
 	}
@@ -1570,14 +2170,13 @@ impl Routing for O1TURN
 			rng.gen_range(0..2)
 		}]);
 	}
-	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
 	{
-		let dimension=current_port/2;
-		let delta=if current_port%2==0 { -1i32 } else { 1i32 };
 		match routing_info.borrow_mut().routing_record
 		{
 			Some(ref mut rr) =>
 			{
+				let (dimension,delta)=arrival_displacement(topology,current_router,current_port,rr);
 				rr[dimension]+=delta;
 				//println!("new routing record at ({},{}) is {:?}",current_router,current_port,rr);
 			},
@@ -1601,7 +2200,7 @@ impl Routing for O1TURN
 
 impl O1TURN
 {
-	pub fn new(arg:RoutingBuilderArgument) -> O1TURN
+	pub fn new(arg:RoutingBuilderArgument) -> Result<O1TURN,Error>
 	{
 		//let mut order=None;
 		//let mut servers_per_router=None;
@@ -1611,7 +2210,7 @@ impl O1TURN
 		{
 			if cv_name!="O1TURN"
 			{
-				panic!("A O1TURN must be created from a `O1TURN` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A O1TURN must be created from a `O1TURN` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -1632,7 +2231,7 @@ impl O1TURN
 							&ConfigurationValue::Number(f) => f as usize,
 							_ => panic!("bad value in reserved_virtual_channels_order01"),
 						}).collect()),
-						_ => panic!("bad value for reserved_virtual_channels_order01"),
+						_ => return Err(value.ill("bad value for reserved_virtual_channels_order01")),
 					}
 					"reserved_virtual_channels_order10" => match value
 					{
@@ -1640,24 +2239,24 @@ impl O1TURN
 							&ConfigurationValue::Number(f) => f as usize,
 							_ => panic!("bad value in reserved_virtual_channels_order10"),
 						}).collect()),
-						_ => panic!("bad value for reserved_virtual_channels_order10"),
+						_ => return Err(value.ill("bad value for reserved_virtual_channels_order10")),
 					}
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in O1TURN",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in O1TURN",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a O1TURN from a non-Object");
+			return Err(arg.cv.ill("Trying to create a O1TURN from a non-Object"));
 		}
 		//let order=order.expect("There were no order");
 		let reserved_virtual_channels_order01=reserved_virtual_channels_order01.unwrap_or_else(||vec![0]);
 		let reserved_virtual_channels_order10=reserved_virtual_channels_order10.unwrap_or_else(||vec![1]);
-		O1TURN{
+		Ok(O1TURN{
 			reserved_virtual_channels_order01,
 			reserved_virtual_channels_order10,
-		}
+		})
 	}
 }
 
@@ -1888,7 +2487,7 @@ impl Routing for GENERALTURN
 
 impl GENERALTURN
 {
-	pub fn new(arg:RoutingBuilderArgument) -> GENERALTURN
+	pub fn new(arg:RoutingBuilderArgument) -> Result<GENERALTURN,Error>
 	{
 		//let mut order=None;
 		//let mut servers_per_router=None;
@@ -1900,7 +2499,7 @@ impl GENERALTURN
 		{
 			if cv_name!="GeneralTurn"
 			{
-				panic!("A GeneralTurn must be created from a `GeneralTurn` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A GeneralTurn must be created from a `GeneralTurn` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -1926,7 +2525,7 @@ impl GENERALTURN
                             }).collect(),
                             _ => panic!("bad value in orders"),
                         }).collect()),
-						_ => panic!("bad value for orders"),
+						_ => return Err(value.ill("bad value for orders")),
 					}
 					"virtual_channels" => match value
                     {
@@ -1939,28 +2538,224 @@ impl GENERALTURN
                             }).collect(),
                             _ => panic!("bad value in virtual_channels"),
                         }).collect()),
-                        _ => panic!("bad value for virtual_channels"),
+                        _ => return Err(value.ill("bad value for virtual_channels")),
                     }
 					"policy" => policy=Some(new_general_turn_policy(value)),
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in GENERALTURN",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in GENERALTURN",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a GENERALTURN from a non-Object");
+			return Err(arg.cv.ill("Trying to create a GENERALTURN from a non-Object"));
 		}
 
-		let orders=orders.expect("There were no orders");
-		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
-		let policy=policy.expect("There were no policy");
+		let orders=orders.ok_or_else(||arg.cv.ill("There were no orders"))?;
+		let virtual_channels=virtual_channels.ok_or_else(||arg.cv.ill("There were no virtual_channels"))?;
+		let policy=policy.ok_or_else(||arg.cv.ill("There were no policy"))?;
 
-		GENERALTURN{
+		Ok(GENERALTURN{
             orders,
             virtual_channels,
             policy,
-        }
+        })
+	}
+}
+
+/**
+Minimal adaptive routing for Cartesian topologies restricted by a table of forbidden turns, which allows to define turn models such as west-first, north-last, negative-first or odd-even.
+The directions are written as `"+d"` or `"-d"` for increasing or decreasing the coordinate of dimension `d`. In a 2D mesh east is `"+0"`, west `"-0"`, north `"+1"` and south `"-1"`.
+Each forbidden turn is a pair `[from,to]` of the direction of the last hop and the direction of the next hop. It may have a dimension and a parity, as `[from,to,dimension,parity]`, to forbid it only in the routers whose coordinate in that dimension has that parity.
+Going straight is allowed unless forbidden explicitly, and the first hop is never restricted.
+A candidate is only offered if the rest of the route can be completed with minimal hops and allowed turns, so the packets never get stuck. It panics at initialization if some pair of routers is left without route.
+The turn models are deadlock-free in meshes; in tori the wrap-around links require additional virtual channels.
+
+```ignore
+//west-first
+ConfigurableTurnModel{ forbidden_turns: [["+1","-0"],["-1","-0"]] }
+//north-last
+ConfigurableTurnModel{ forbidden_turns: [["+1","+0"],["+1","-0"]] }
+//negative-first
+ConfigurableTurnModel{ forbidden_turns: [["+0","-1"],["+1","-0"]] }
+//odd-even: no east to north/south turns in even columns and no north/south to west turns in odd columns.
+ConfigurableTurnModel{ forbidden_turns: [["+0","+1",0,0],["+0","-1",0,0],["+1","-0",0,1],["-1","-0",0,1]] }
+```
+**/
+#[derive(Debug)]
+pub struct ConfigurableTurnModel
+{
+	///The forbidden turns as `(from,to,condition)`, with directions `2*d` for `"+d"` and `2*d+1` for `"-d"`.
+	///If the condition is `Some((dimension,parity))` the turn is only forbidden in routers with that parity in that dimension.
+	forbidden_turns: Vec<(usize,usize,Option<(usize,usize)>)>,
+	///`port_directions[router][port]` is the direction of the hop through that port, if it goes to another router.
+	port_directions: Vec<Vec<Option<usize>>>,
+	///Bit `s` of `feasible[target][router]` tells whether `target` can be reached from `router` having arrived in state `s`,
+	///which is the direction of the last hop or `2*dimensions` at injection.
+	feasible: Vec<Vec<u8>>,
+	///The state at injection, `2*dimensions`.
+	injection_state: usize,
+}
+
+impl Routing for ConfigurableTurnModel
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let distance=topology.distance(current_router,target_router);
+		if distance==0
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true})
+					}
+				}
+			}
+			unreachable!();
+		}
+		let state = routing_info.selections.as_ref().map_or(self.injection_state,|selections|selections[0] as usize);
+		let cartesian_data=topology.cartesian_data().expect("ConfigurableTurnModel requires a Cartesian topology");
+		let coordinates = cartesian_data.unpack(current_router);
+		let mut r = vec![];
+		for (port,direction) in self.port_directions[current_router].iter().enumerate()
+		{
+			if let (Some(direction),(Location::RouterPort{router_index,router_port:_},_link_class)) = (direction,topology.neighbour(current_router,port))
+			{
+				if topology.distance(router_index,target_router)+1==distance && !self.is_forbidden(state,*direction,&coordinates)
+					&& self.feasible[target_router][router_index] & (1<<direction) != 0
+				{
+					r.extend((0..num_virtual_channels).map(|vc|{
+						let mut egress = CandidateEgress::new(port,vc);
+						egress.estimated_remaining_hops = Some(distance);
+						egress
+					}));
+				}
+			}
+		}
+		Ok(RoutingNextCandidates{candidates:r,idempotent:true})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		routing_info.borrow_mut().selections=None;
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if let (Location::RouterPort{router_index:previous_router,router_port:previous_port},_link_class)=topology.neighbour(current_router,current_port)
+		{
+			let direction = self.port_directions[previous_router][previous_port].expect("the hop should be between routers");
+			routing_info.borrow_mut().selections=Some(vec![direction as i32]);
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		let cartesian_data=topology.cartesian_data().expect("ConfigurableTurnModel requires a Cartesian topology");
+		let dimensions = cartesian_data.sides.len();
+		if dimensions>3
+		{
+			panic!("ConfigurableTurnModel supports up to 3 dimensions.");
+		}
+		for &(from,to,condition) in self.forbidden_turns.iter()
+		{
+			if from>=2*dimensions || to>=2*dimensions || condition.map_or(false,|(dimension,_)|dimension>=dimensions)
+			{
+				panic!("ConfigurableTurnModel has a forbidden turn outside the {} dimensions of the topology.",dimensions);
+			}
+		}
+		self.injection_state = 2*dimensions;
+		let n=topology.num_routers();
+		self.port_directions = (0..n).map(|router|{
+			let coordinates = cartesian_data.unpack(router);
+			(0..topology.ports(router)).map(|port|match topology.neighbour(router,port)
+			{
+				(Location::RouterPort{router_index,router_port:_},_link_class) =>
+				{
+					let other = cartesian_data.unpack(router_index);
+					let dimension = (0..dimensions).find(|&d|coordinates[d]!=other[d]).expect("a link between routers should change some coordinate");
+					//Increasing by one or wrapping around from the last coordinate to the first is the positive direction.
+					let positive = other[dimension]==(coordinates[dimension]+1)%cartesian_data.sides[dimension];
+					Some(2*dimension + if positive {0} else {1})
+				},
+				_ => None,
+			}).collect()
+		}).collect();
+		let all_states : u8 = (1<<(2*dimensions+1))-1;
+		self.feasible = (0..n).map(|target|{
+			let mut feasible = vec![0u8;n];
+			feasible[target] = all_states;
+			let mut routers : Vec<usize> = (0..n).filter(|&router|router!=target).collect();
+			routers.sort_by_key(|&router|topology.distance(router,target));
+			for router in routers
+			{
+				let coordinates = cartesian_data.unpack(router);
+				let distance = topology.distance(router,target);
+				for state in 0..=2*dimensions
+				{
+					let reachable = self.port_directions[router].iter().enumerate().any(|(port,direction)|match (direction,topology.neighbour(router,port))
+					{
+						(Some(direction),(Location::RouterPort{router_index,router_port:_},_)) => topology.distance(router_index,target)+1==distance
+							&& !self.is_forbidden(state,*direction,&coordinates) && feasible[router_index] & (1<<direction) != 0,
+						_ => false,
+					});
+					if reachable
+					{
+						feasible[router] |= 1<<state;
+					}
+				}
+				if feasible[router] & (1<<self.injection_state) == 0
+				{
+					panic!("ConfigurableTurnModel forbids every minimal route from router {} to router {}.",router,target);
+				}
+			}
+			feasible
+		}).collect();
+	}
+}
+
+impl ConfigurableTurnModel
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<ConfigurableTurnModel,Error>
+	{
+		let mut forbidden_turns=None;
+		let direction = |value:&ConfigurationValue| -> Result<usize,Error> {
+			let name = value.as_str()?;
+			let bad_direction = ||value.ill(&format!("bad direction {}, it should be as \"+0\" or \"-1\"",name));
+			let dimension : usize = name[1..].parse().map_err(|_|bad_direction())?;
+			match &name[..1]
+			{
+				"+" => Ok(2*dimension),
+				"-" => Ok(2*dimension+1),
+				_ => Err(bad_direction()),
+			}
+		};
+		match_object!(arg.cv,"ConfigurableTurnModel",value,
+			"forbidden_turns" => forbidden_turns=Some(value.as_array()?.iter().map(|turn_cv|{
+				let turn = turn_cv.as_array()?;
+				let condition = match turn.len()
+				{
+					2 => None,
+					4 => Some((turn[2].as_usize()?,turn[3].as_usize()?)),
+					_ => return Err(turn_cv.ill("Each forbidden turn must be [from,to] or [from,to,dimension,parity].")),
+				};
+				Ok((direction(&turn[0])?,direction(&turn[1])?,condition))
+			}).collect::<Result<_,_>>()?),
+		);
+		Ok(ConfigurableTurnModel{
+			forbidden_turns: forbidden_turns.ok_or_else(||arg.cv.ill("There were no forbidden_turns"))?,
+			port_directions: vec![],
+			feasible: vec![],
+			injection_state: 0,
+		})
+	}
+	///Whether going in `direction` after arriving in `state` is forbidden in the router at `coordinates`.
+	fn is_forbidden(&self, state:usize, direction:usize, coordinates:&[usize]) -> bool
+	{
+		state!=self.injection_state && self.forbidden_turns.iter().any(|&(from,to,condition)|
+			from==state && to==direction && condition.map_or(true,|(dimension,parity)|coordinates[dimension]%2==parity)
+		)
 	}
 }
 
@@ -2119,7 +2914,7 @@ impl Routing for OmniDimensionalDeroute
 
 impl OmniDimensionalDeroute
 {
-	pub fn new(arg:RoutingBuilderArgument) -> OmniDimensionalDeroute
+	pub fn new(arg:RoutingBuilderArgument) -> Result<OmniDimensionalDeroute,Error>
 	{
 		let mut allowed_deroutes=None;
 		let mut include_labels=None;
@@ -2127,7 +2922,7 @@ impl OmniDimensionalDeroute
 		{
 			if cv_name!="OmniDimensionalDeroute"
 			{
-				panic!("A OmniDimensionalDeroute must be created from a `OmniDimensionalDeroute` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A OmniDimensionalDeroute must be created from a `OmniDimensionalDeroute` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -2137,29 +2932,29 @@ impl OmniDimensionalDeroute
 					"allowed_deroutes" => match value
 					{
 						&ConfigurationValue::Number(f) => allowed_deroutes=Some(f as usize),
-						_ => panic!("bad value for allowed_deroutes"),
+						_ => return Err(value.ill("bad value for allowed_deroutes")),
 					}
 					"include_labels" => match value
 					{
 						&ConfigurationValue::True => include_labels=Some(true),
 						&ConfigurationValue::False => include_labels=Some(false),
-						_ => panic!("bad value for include_labels"),
+						_ => return Err(value.ill("bad value for include_labels")),
 					}
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in OmniDimensionalDeroute",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in OmniDimensionalDeroute",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a OmniDimensionalDeroute from a non-Object");
+			return Err(arg.cv.ill("Trying to create a OmniDimensionalDeroute from a non-Object"));
 		}
-		let allowed_deroutes=allowed_deroutes.expect("There were no allowed_deroutes");
-		let include_labels=include_labels.expect("There were no include_labels");
-		OmniDimensionalDeroute{
+		let allowed_deroutes=allowed_deroutes.ok_or_else(||arg.cv.ill("There were no allowed_deroutes"))?;
+		let include_labels=include_labels.ok_or_else(||arg.cv.ill("There were no include_labels"))?;
+		Ok(OmniDimensionalDeroute{
 			allowed_deroutes,
 			include_labels,
-		}
+		})
 	}
 }
 
@@ -2349,7 +3144,7 @@ impl Routing for DimWAR
 
 impl DimWAR
 {
-	pub fn new(arg:RoutingBuilderArgument) -> DimWAR
+	pub fn new(arg:RoutingBuilderArgument) -> Result<DimWAR,Error>
 	{
 		let mut order= None;
 
@@ -2357,36 +3152,36 @@ impl DimWAR
 		{
 			if cv_name!="DimWAR"
 			{
-				panic!("A DimWAR must be created from a `DimWAR` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A DimWAR must be created from a `DimWAR` object not `{}`",cv_name)));
 			}
-			for &(ref name,ref _value) in cv_pairs
+			for &(ref name,ref value) in cv_pairs
 			{
 				//match name.as_ref()
 				match AsRef::<str>::as_ref(&name)
 				{
-					"order" => order = match _value
+					"order" => order = match value
 					{
 						&ConfigurationValue::Array(ref v) => Some(v.iter().map(|cv|match cv
 						{
-							&ConfigurationValue::Number(f) => f as usize,
-							_ => panic!("bad value for order"),
-						}).collect()),
-						_ => panic!("bad value for order"),
+							&ConfigurationValue::Number(f) => Ok(f as usize),
+							_ => Err(cv.ill("bad value for order")),
+						}).collect::<Result<_,_>>()?),
+						_ => return Err(value.ill("bad value for order")),
 					},
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in OmniDimensionalDeroute",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in OmniDimensionalDeroute",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a OmniDimensionalDeroute from a non-Object");
+			return Err(arg.cv.ill("Trying to create a OmniDimensionalDeroute from a non-Object"));
 		}
-		let order=order.expect("There were no order");
+		let order=order.ok_or_else(||arg.cv.ill("There were no order"))?;
 
-		DimWAR{
+		Ok(DimWAR{
 			order
-		}
+		})
 	}
 }
 
@@ -2688,11 +3483,16 @@ impl Routing for Valiant4Hamming
 				}
 		};
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.first.advance_cycle(cycle);
+		self.second.advance_cycle(cycle);
+	}
 }
 
 impl Valiant4Hamming
 {
-	pub fn new(arg: RoutingBuilderArgument) -> Valiant4Hamming
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Valiant4Hamming,Error>
 	{
 		//let mut order=None;
 		//let mut servers_per_router=None;
@@ -2705,29 +3505,29 @@ impl Valiant4Hamming
 		let mut remove_source_dimensions_aligment = vec![];
 		let mut allow_unaligned = true;
 
-		match_object_panic!(arg.cv,"Valiant4Hamming",value,
-			"first" => first=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"second" => second=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-		    "pattern" => pattern= Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})).expect("pattern not valid for Valiant4Hamming"),
+		match_object!(arg.cv,"Valiant4Hamming",value,
+			"first" => first=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"second" => second=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+		    "pattern" => pattern= try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?,
 			"first_reserved_virtual_channels" => first_reserved_virtual_channels=value.
-				as_array().expect("bad value for first_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in first_reserved_virtual_channels") as usize).collect(),
 			"second_reserved_virtual_channels" => second_reserved_virtual_channels=value.
-				as_array().expect("bad value for second_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in second_reserved_virtual_channels") as usize).collect(),
 			"remove_target_dimensions_aligment" => remove_target_dimensions_aligment=value.
-				as_array().expect("bad value for remove_dimension_aligment").iter().map(|v|v.as_array()
+				as_array()?.iter().map(|v|v.as_array()
 				.expect("bad value in remove_dimension_aligment").iter().map(|v|v.as_f64().expect("bad value in remove_dimension_aligment") as usize).collect()).collect(),
 			"remove_source_dimensions_aligment" => remove_source_dimensions_aligment=value.
-				as_array().expect("bad value for remove_source_dimensions_aligment").iter()
+				as_array()?.iter()
 			.map(|v|v.as_array().expect(" bad value in remove_source_dimensions_aligment").iter().map(|v|v.as_f64().expect("bad value in remove_source_dimensions_aligment") as usize).collect()).collect(),
-			"allow_unaligned" => allow_unaligned=value.as_bool().expect("bad value for allow_unaligned"),
+			"allow_unaligned" => allow_unaligned=value.as_bool()?,
 
 		);
-		let first=first.expect("There were no first");
-		let second=second.expect("There were no second");
+		let first=first.ok_or_else(||arg.cv.ill("There were no first"))?;
+		let second=second.ok_or_else(||arg.cv.ill("There were no second"))?;
 
-		Valiant4Hamming{
+		Ok(Valiant4Hamming{
 			first,
 			second,
 			pattern,
@@ -2736,7 +3536,7 @@ impl Valiant4Hamming
 			remove_target_dimensions_aligment,
 			remove_source_dimensions_aligment,
 			allow_unaligned,
-		}
+		})
 	}
 }
 
@@ -2944,11 +3744,16 @@ impl Routing for AdaptiveValiantClos
 	{
 		//TODO: recurse over routings
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.first.advance_cycle(cycle);
+		self.second.advance_cycle(cycle);
+	}
 }
 
 impl AdaptiveValiantClos
 {
-	pub fn new(arg: RoutingBuilderArgument) -> AdaptiveValiantClos
+	pub fn new(arg: RoutingBuilderArgument) -> Result<AdaptiveValiantClos,Error>
 	{
 		//let mut order=None;
 		//let mut servers_per_router=None;
@@ -2956,36 +3761,284 @@ impl AdaptiveValiantClos
 		// let mut selection_exclude_indirect_routers=true; //this was false...
 		let mut first_reserved_virtual_channels=vec![];
 		let mut second_reserved_virtual_channels=vec![];
-		match_object_panic!(arg.cv,"AdaptiveValiantClos",value,
+		match_object!(arg.cv,"AdaptiveValiantClos",value,
 			// "first" => first=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
 			// "second" => second=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"order" => order=Some(value.as_array().expect("bad value for order").iter().map(|v|v.as_f64().expect("bad value in order") as usize).collect()),
+			"order" => order=Some(value.as_array()?.iter().map(|v|v.as_f64().expect("bad value in order") as usize).collect()),
 			// "selection_exclude_indirect_routers" => selection_exclude_indirect_routers = value.as_bool().expect("bad value for selection_exclude_indirect_routers"),
 			"first_reserved_virtual_channels" => first_reserved_virtual_channels=value.
-				as_array().expect("bad value for first_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in first_reserved_virtual_channels") as usize).collect(),
 			"second_reserved_virtual_channels" => second_reserved_virtual_channels=value.
-				as_array().expect("bad value for second_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in second_reserved_virtual_channels") as usize).collect(),
 		);
 
-		let order:Vec<usize>= order.expect("There were no order");
+		let order:Vec<usize>= order.ok_or_else(||arg.cv.ill("There were no order"))?;
 		let first= ConfigurationValue::Object("DOR".to_string(), vec![("order".to_string(), ConfigurationValue::Array( order.iter().map(|&a| ConfigurationValue::Number(a as f64)).collect() ))]);
 		let second= ConfigurationValue::Object("DOR".to_string(), vec![("order".to_string(), ConfigurationValue::Array( order.iter().map(|&a| ConfigurationValue::Number(a as f64)).collect() ))]);
 
-		let first=new_routing(RoutingBuilderArgument{cv: &first,..arg});
-		let second=new_routing(RoutingBuilderArgument{cv: &second,..arg});
+		let first=try_new_routing(RoutingBuilderArgument{cv: &first,..arg})?;
+		let second=try_new_routing(RoutingBuilderArgument{cv: &second,..arg})?;
 		// let first=first.expect("There were no first");
 		// let second=second.expect("There were no second");
 		//let first_reserved_virtual_channels=first_reserved_virtual_channels.expect("There were no first_reserved_virtual_channels");
 		//let second_reserved_virtual_channels=second_reserved_virtual_channels.expect("There were no second_reserved_virtual_channels");
 
-		AdaptiveValiantClos{
+		Ok(AdaptiveValiantClos{
 			first,
 			second,
 			// selection_exclude_indirect_routers,
 			first_reserved_virtual_channels,
 			second_reserved_virtual_channels,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::SeedableRng;
+	use crate::config_parser;
+	/// The links of the ExpressMesh must be consistent and its distances must agree with a breadth first search.
+	#[test]
+	fn express_mesh()
+	{
+		for wrap_around in ["false","true"]
+		{
+			let cv = match config_parser::parse(&format!("ExpressMesh{{sides:[8,5],express:[[4],[2,3]],wrap_around:{},servers_per_router:2}}",wrap_around))
+			{
+				Ok(config_parser::Token::Value(value)) => value,
+				_ => panic!("could not parse the topology"),
+			};
+			let topology = ExpressMesh::new(&cv).unwrap();
+			topology.check_adjacency_consistency(Some(6));
+			let n = topology.num_routers();
+			let mut diameter = 0;
+			for origin in 0..n
+			{
+				let distances = topology.bfs(origin,None);
+				for target in 0..n
+				{
+					assert_eq!(topology.distance(origin,target),distances[target],"distance from {} to {}",origin,target);
+					diameter = diameter.max(distances[target]);
+				}
+			}
+			assert_eq!(topology.diameter(),diameter);
+			for port in 0..topology.ports(0)-2
+			{
+				if let (Location::RouterPort{router_index,..},link_class) = topology.neighbour(0,port)
+				{
+					let dimension = topology.cartesian_link_dimension(link_class);
+					let a = topology.cartesian_data.unpack(0);
+					let b = topology.cartesian_data.unpack(router_index);
+					assert!((0..2).all(|d|(d==dimension)==(a[d]!=b[d])),"link from 0 by port {} goes to {:?}",port,b);
+				}
+			}
+		}
+	}
+	/// The parallel links of `link_multiplicity` must be consistent, join the same routers, and be offered by DOR as alternatives.
+	#[test]
+	fn link_multiplicity()
+	{
+		let configurations : [(&str,Box<dyn Fn(&ConfigurationValue)->Box<dyn Topology>>);3] = [
+			("Mesh{sides:[4,3],link_multiplicity:[2,3],servers_per_router:2}",Box::new(|cv|Box::new(Mesh::new(cv).unwrap()))),
+			("Torus{sides:[4,3],link_multiplicity:[2,3],servers_per_router:2}",Box::new(|cv|Box::new(Torus::new(cv).unwrap()))),
+			("Hamming{sides:[4,3],link_multiplicity:[2,3],servers_per_router:2}",Box::new(|cv|Box::new(Hamming::new(cv,&mut StdRng::seed_from_u64(0)).unwrap()))),
+		];
+		let plugs = crate::Plugs::default();
+		let dor_cv = match config_parser::parse("DOR{order:[0,1]}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the routing"),
+		};
+		let dor = DOR::new(RoutingBuilderArgument{cv:&dor_cv,plugs:&plugs}).unwrap();
+		let mut rng = StdRng::seed_from_u64(0);
+		for (configuration,build) in configurations.iter()
+		{
+			let cv = match config_parser::parse(configuration)
+			{
+				Ok(config_parser::Token::Value(value)) => value,
+				_ => panic!("could not parse the topology"),
+			};
+			let topology = build(&cv);
+			topology.check_adjacency_consistency(Some(3));
+			let cartesian_data = topology.cartesian_data().unwrap();
+			for router in 0..topology.num_routers()
+			{
+				//Count the links towards each neighbour.
+				let mut links = vec![0;topology.num_routers()];
+				for port in 0..topology.ports(router)
+				{
+					if let (Location::RouterPort{router_index,..},link_class) = topology.neighbour(router,port)
+					{
+						links[router_index] += 1;
+						let a = cartesian_data.unpack(router);
+						let b = cartesian_data.unpack(router_index);
+						assert!((0..2).all(|d|(d==link_class)==(a[d]!=b[d])),"{}: link from {} by port {} goes to {:?}",configuration,router,port,b);
+					}
+				}
+				for (neighbour,&amount) in links.iter().enumerate()
+				{
+					if amount>0
+					{
+						let a = cartesian_data.unpack(router);
+						let b = cartesian_data.unpack(neighbour);
+						let dimension = (0..2).find(|&d|a[d]!=b[d]).unwrap();
+						assert_eq!(amount,[2,3][dimension],"{}: links from {} to {}",configuration,router,neighbour);
+					}
+				}
+			}
+			//From router 0 to its neighbour in the first dimension DOR offers both parallel links.
+			let target = cartesian_data.pack(&[1,0]);
+			let routing_info = RefCell::new(RoutingInfo::new());
+			dor.initialize_routing_info(&routing_info,topology.as_ref(),0,target,None,&mut rng);
+			let candidates = dor.next(&routing_info.borrow(),topology.as_ref(),0,target,None,1,&mut rng).unwrap().candidates;
+			assert_eq!(candidates.len(),2,"{}: DOR candidates {:?}",configuration,candidates);
+			assert!(candidates.iter().all(|candidate|matches!(topology.neighbour(0,candidate.port).0,Location::RouterPort{router_index,..} if router_index==target)));
+		}
+	}
+	/// O1TURN follows any of the parallel links of the dimension it is routing, and updates its routing record by the dimension of the link.
+	#[test]
+	fn o1turn_link_multiplicity()
+	{
+		let plugs = crate::Plugs::default();
+		let parse = |text:&str| match config_parser::parse(text)
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse {}",text),
+		};
+		let o1turn = O1TURN::new(RoutingBuilderArgument{cv:&parse("O1TURN{}"),plugs:&plugs}).unwrap();
+		let topologies : [Box<dyn Topology>;2] = [
+			Box::new(Mesh::new(&parse("Mesh{sides:[4,3],link_multiplicity:[2,3],servers_per_router:1}")).unwrap()),
+			Box::new(Torus::new(&parse("Torus{sides:[4,3],link_multiplicity:[2,3],servers_per_router:1}")).unwrap()),
+		];
+		let mut rng = StdRng::seed_from_u64(0);
+		for topology in topologies.iter()
+		{
+			let cartesian_data = topology.cartesian_data().unwrap();
+			let target = cartesian_data.pack(&[2,1]);
+			for _ in 0..10
+			{
+				let routing_info = RefCell::new(RoutingInfo::new());
+				o1turn.initialize_routing_info(&routing_info,topology.as_ref(),0,target,None,&mut rng);
+				let mut current = 0;
+				let mut hops = 0;
+				while current!=target
+				{
+					let candidates = o1turn.next(&routing_info.borrow(),topology.as_ref(),current,target,None,2,&mut rng).unwrap().candidates;
+					//A single virtual channel of each order, through each of the parallel links.
+					assert!(candidates.len()==2 || candidates.len()==3,"candidates {:?}",candidates);
+					let (next,next_port) = match topology.neighbour(current,candidates[candidates.len()-1].port).0
+					{
+						Location::RouterPort{router_index,router_port} => (router_index,router_port),
+						_ => panic!("O1TURN went to a server before reaching the target"),
+					};
+					o1turn.update_routing_info(&routing_info,topology.as_ref(),next,next_port,target,None,&mut rng);
+					current = next;
+					hops += 1;
+					assert!(hops<=3,"O1TURN did not follow a shortest route");
+				}
+				assert_eq!(routing_info.borrow().routing_record,Some(vec![0,0]));
+			}
+		}
+	}
+	/// The twisted tori must be consistent, with distances agreeing with a breadth first search, and their routing records must lead to the target.
+	#[test]
+	fn twisted_torus()
+	{
+		let configurations = ["TwistedTorus{sides:[6,4],twists:[[],[3]],servers_per_router:1}","RectangularTwistedTorus{side:4,servers_per_router:1}","PrismaticDoublyTwistedTorus{side:3,servers_per_router:1}","TwistedTorus{sides:[5,3,4],twists:[[],[2],[1,2]],servers_per_router:1}"];
+		for configuration in configurations
+		{
+			let cv = match config_parser::parse(configuration)
+			{
+				Ok(config_parser::Token::Value(value)) => value,
+				_ => panic!("could not parse the topology"),
+			};
+			let topology = TwistedTorus::new(&cv).unwrap();
+			topology.check_adjacency_consistency(None);
+			let n = topology.num_routers();
+			let mut diameter = 0;
+			for origin in 0..n
+			{
+				let distances = topology.bfs(origin,None);
+				for target in 0..n
+				{
+					assert_eq!(topology.distance(origin,target),distances[target],"distance from {} to {} in {}",origin,target,configuration);
+					diameter = diameter.max(distances[target]);
+					//Following the record one dimension at a time must arrive at the target.
+					let a = topology.cartesian_data.unpack(origin);
+					let b = topology.cartesian_data.unpack(target);
+					let mut current = origin;
+					for (dimension,&amount) in topology.coordinated_routing_record(&a,&b,None).iter().enumerate()
+					{
+						let port = if amount<0 { 2*dimension } else { 2*dimension+1 };
+						for _ in 0..amount.abs()
+						{
+							current = match topology.neighbour(current,port).0
+							{
+								Location::RouterPort{router_index,..} => router_index,
+								_ => panic!("the torus has no boundary"),
+							};
+						}
+					}
+					assert_eq!(current,target,"the record from {} to {} in {}",origin,target,configuration);
+				}
+			}
+			assert_eq!(topology.diameter(),diameter);
+		}
+		// The rectangular twisted torus of 8x4 routers has diameter 4, while the 8x4 torus has diameter 6.
+		let cv = match config_parser::parse("RectangularTwistedTorus{side:4,servers_per_router:1}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		assert_eq!(TwistedTorus::new(&cv).unwrap().diameter(),4);
+	}
+	/// DOR must follow shortest routes in mixed-radix twisted tori whatever the order of the dimensions, as moving in a dimension may cross a twisted wrap-around link.
+	#[test]
+	fn twisted_torus_dor()
+	{
+		let cv = match config_parser::parse("TwistedTorus{sides:[5,3,4],twists:[[],[2],[1,2]],servers_per_router:1}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let topology = TwistedTorus::new(&cv).unwrap();
+		let plugs = crate::Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let n = topology.num_routers();
+		for order in (0..3).permutations(3)
+		{
+			let dor_cv = ConfigurationValue::Object("DOR".to_string(),vec![
+				("order".to_string(),ConfigurationValue::Array(order.iter().map(|&d|ConfigurationValue::Number(d as f64)).collect())),
+			]);
+			let dor = DOR::new(RoutingBuilderArgument{cv:&dor_cv,plugs:&plugs}).unwrap();
+			for origin in 0..n
+			{
+				for target in 0..n
+				{
+					let routing_info = RefCell::new(RoutingInfo::new());
+					dor.initialize_routing_info(&routing_info,&topology,origin,target,None,&mut rng);
+					let mut current = origin;
+					let mut hops = 0;
+					while routing_info.borrow().routing_record.as_ref().unwrap().iter().any(|&x|x!=0)
+					{
+						let candidates = dor.next(&routing_info.borrow(),&topology,current,target,None,1,&mut rng).unwrap().candidates;
+						let (next_router,arrival_port) = match topology.neighbour(current,candidates[0].port).0
+						{
+							Location::RouterPort{router_index,router_port} => (router_index,router_port),
+							_ => panic!("the torus has no boundary"),
+						};
+						current = next_router;
+						hops += 1;
+						dor.update_routing_info(&routing_info,&topology,current,arrival_port,target,None,&mut rng);
+						assert!(hops<=topology.diameter(),"DOR {:?} from {} to {} does not arrive",order,origin,target);
+					}
+					assert_eq!(current,target,"DOR {:?} from {} to {}",order,origin,target);
+					assert_eq!(hops,topology.distance(origin,target),"DOR {:?} from {} to {} is not minimal",order,origin,target);
+				}
+			}
 		}
 	}
 }
@@ -1,12 +1,13 @@
 use crate::pattern::probabilistic::UniformPattern;
-use ::rand::{rngs::StdRng};
+use ::rand::{Rng,rngs::StdRng};
 use super::prelude::*;
 use super::cartesian::CartesianData;
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::matrix::Matrix;
 use crate::quantify::Quantifiable;
-use crate::match_object_panic;
+use crate::{match_object,error,source_location};
 use crate::pattern::prelude::*;
 use crate::pattern::Pattern;
 
@@ -206,7 +207,7 @@ impl Topology for Dragonfly
 
 impl Dragonfly
 {
-	pub fn new(arg:TopologyBuilderArgument) -> Dragonfly
+	pub fn new(arg:TopologyBuilderArgument) -> Result<Dragonfly,Error>
 	{
 		let mut global_ports_per_router=None;
 		let mut servers_per_router=None;
@@ -214,16 +215,16 @@ impl Dragonfly
 		let mut group_size=None;
 		let mut number_of_groups = None;
 		let mut lag =1;
-		match_object_panic!(arg.cv,["Dragonfly", "CanonicDragonfly"],value,
-			"global_ports_per_router" => global_ports_per_router=Some(value.as_f64().expect("bad value for global_ports_per_router")as usize),
-			"servers_per_router" => servers_per_router=Some(value.as_f64().expect("bad value for servers_per_router")as usize),
+		match_object!(arg.cv,["Dragonfly", "CanonicDragonfly"],value,
+			"global_ports_per_router" => global_ports_per_router=Some(value.as_f64()? as usize),
+			"servers_per_router" => servers_per_router=Some(value.as_f64()? as usize),
 			"global_arrangement" => global_arrangement=Some(new_arrangement(value.into())),
-			"group_size" => group_size=Some(value.as_usize().expect("bad value for group_size")),
-			"number_of_groups" => number_of_groups=Some(value.as_usize().expect("bad value for number_of_groups")),
-			"lag" | "global_lag" => lag=value.as_usize().expect("bad value for lag"),
+			"group_size" => group_size=Some(value.as_usize()?),
+			"number_of_groups" => number_of_groups=Some(value.as_usize()?),
+			"lag" | "global_lag" => lag=value.as_usize()?,
 		);
-		let global_ports_per_router=global_ports_per_router.expect("There were no global_ports_per_router");
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
+		let global_ports_per_router=global_ports_per_router.ok_or_else(||arg.cv.ill("There were no global_ports_per_router"))?;
+		let servers_per_router=servers_per_router.ok_or_else(||arg.cv.ill("There were no servers_per_router"))?;
 		let group_size = group_size.unwrap_or_else(||2*global_ports_per_router);
 		let number_of_groups = number_of_groups.unwrap_or_else(||group_size*global_ports_per_router + 1);
 		let mut global_arrangement = global_arrangement.unwrap_or_else(||Box::new(Palmtree::default()));
@@ -245,7 +246,7 @@ impl Dragonfly
 		};
 		let (distance_matrix,_amount_matrix)=topo.compute_amount_shortest_paths();
 		topo.distance_matrix=distance_matrix.map(|x|*x as u8);
-		topo
+		Ok(topo)
 	}
 	/**
 	 Unpack a switch index into `(group_offset, group_index)` coordinates.
@@ -293,12 +294,12 @@ impl ArrangementSize
 		&& (0..self.number_of_ports).contains(&point.port_index)
 	}
 	/// Like the method in [Dragonfly].
-	fn unpack(&self, router_index: usize) -> (usize,usize)
+	pub fn unpack(&self, router_index: usize) -> (usize,usize)
 	{
 		(router_index%self.group_size,router_index/self.group_size)
 	}
 	/// Like the method in [Dragonfly].
-	fn pack(&self, coordinates:(usize,usize)) -> usize
+	pub fn pack(&self, coordinates:(usize,usize)) -> usize
 	{
 		coordinates.0+coordinates.1*self.group_size
 	}
@@ -523,6 +524,7 @@ pub fn new_arrangement(arg:ArrangementBuilderArgument) -> Box<dyn Arrangement>
 
 use crate::routing::prelude::*;
 use crate::routing::{RoutingAnnotation};
+use std::collections::VecDeque;
 
 /**
 With the switches colored in {0,1} with a global arrangement such that global links connect only switches of the same color, the global link is labelled by that color.
@@ -656,10 +658,10 @@ impl Routing for Dragonfly2ColorsRouting
 
 impl Dragonfly2ColorsRouting
 {
-	pub fn new(arg: RoutingBuilderArgument) -> Dragonfly2ColorsRouting
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Dragonfly2ColorsRouting,Error>
 	{
-		match_object_panic!(arg.cv,"Dragonfly2Colors",_value);
-		Dragonfly2ColorsRouting{}
+		match_object!(arg.cv,"Dragonfly2Colors",_value);
+		Ok(Dragonfly2ColorsRouting{})
 	}
 	fn get_color(&self,size:ArrangementSize, switch_local:usize) -> u8
 	{
@@ -968,11 +970,16 @@ impl Routing for Valiant4Dragonfly
 				}
 		};
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.first.advance_cycle(cycle);
+		self.second.advance_cycle(cycle);
+	}
 }
 
 impl Valiant4Dragonfly
 {
-	pub fn new(arg: RoutingBuilderArgument) -> Valiant4Dragonfly
+	pub fn new(arg: RoutingBuilderArgument) -> Result<Valiant4Dragonfly,Error>
 	{
 		//let mut order=None;
 		//let mut servers_per_router=None;
@@ -986,26 +993,26 @@ impl Valiant4Dragonfly
 		let mut local_missrouting=false;
 		let mut intermediate_bypass=None;
 		let mut dragonfly_bypass=false;
-		match_object_panic!(arg.cv,"Valiant4Dragonfly",value,
-			"first" => first=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"second" => second=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
-			"pattern" => pattern= Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})).expect("pattern not valid for Valiant4Dragonfly"),
-			"distance_middle_destination" => distance_middle_destination=value.as_f64().expect("bad value for distance_middle_destination") as usize,
+		match_object!(arg.cv,"Valiant4Dragonfly",value,
+			"first" => first=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"second" => second=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"pattern" => pattern= Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?).expect("pattern not valid for Valiant4Dragonfly"),
+			"distance_middle_destination" => distance_middle_destination=value.as_f64()? as usize,
 			// "exclude_h_groups"=> exclude_h_groups=value.as_bool().expect("bad value for exclude_h_groups"),
 			"first_reserved_virtual_channels" => first_reserved_virtual_channels=value.
-				as_array().expect("bad value for first_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in first_reserved_virtual_channels") as usize).collect(),
 			"second_reserved_virtual_channels" => second_reserved_virtual_channels=value.
-				as_array().expect("bad value for second_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in second_reserved_virtual_channels") as usize).collect(),
-			"intermediate_bypass" => intermediate_bypass=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"local_missrouting" => local_missrouting=value.as_bool().expect("bad value for local_missrouting"),
-			"dragonfly_bypass" => dragonfly_bypass=value.as_bool().expect("bad value for dragonfly_bypass"),
+			"intermediate_bypass" => intermediate_bypass=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"local_missrouting" => local_missrouting=value.as_bool()?,
+			"dragonfly_bypass" => dragonfly_bypass=value.as_bool()?,
 		);
-		let first=first.expect("There were no first");
-		let second=second.expect("There were no second");
+		let first=first.ok_or_else(||arg.cv.ill("There were no first"))?;
+		let second=second.ok_or_else(||arg.cv.ill("There were no second"))?;
 
-		Valiant4Dragonfly{
+		Ok(Valiant4Dragonfly{
 			first,
 			second,
 			pattern,
@@ -1015,7 +1022,7 @@ impl Valiant4Dragonfly
 			intermediate_bypass,
 			local_missrouting,
 			dragonfly_bypass,
-		}
+		})
 	}
 }
 
@@ -1228,11 +1235,16 @@ impl Routing for PAR
 			}
 		}
 	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.first.advance_cycle(cycle);
+		self.second.advance_cycle(cycle);
+	}
 }
 
 impl PAR
 {
-	pub fn new(arg: RoutingBuilderArgument) -> PAR
+	pub fn new(arg: RoutingBuilderArgument) -> Result<PAR,Error>
 	{
 		// //let mut order=None;
 		// //let mut servers_per_router=None;
@@ -1245,7 +1257,7 @@ impl PAR
 		// let mut local_missrouting=false;
 		// let mut intermediate_bypass=None;
 		// let mut dragonfly_bypass=false;
-		match_object_panic!(arg.cv,"PAR",_value,
+		match_object!(arg.cv,"PAR",_value,
 			// "first" => first=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
 			// "second" => second=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
 			// "pattern" => pattern= Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})).expect("pattern not valid for PAR"),
@@ -1267,15 +1279,388 @@ impl PAR
 			("second".to_string(),first.clone()),
 		]);
 
-		let first = new_routing(RoutingBuilderArgument{cv: &first,..arg});
-		let second = new_routing(RoutingBuilderArgument{cv: &second,..arg});
+		let first = try_new_routing(RoutingBuilderArgument{cv: &first,..arg})?;
+		let second = try_new_routing(RoutingBuilderArgument{cv: &second,..arg})?;
 
-		PAR{
+		Ok(PAR{
 			first,
 			second,
 			valiant_extra_label:1,
+		})
+	}
+}
+
+///The packet has not yet chosen between the minimal and the Valiant path.
+const PIGGYBACK_UNDECIDED: i32 = 0;
+///The packet goes towards its destination, either from the beginning or after visiting the intermediate router.
+const PIGGYBACK_MINIMAL: i32 = 1;
+///The packet goes towards the intermediate router.
+const PIGGYBACK_NONMINIMAL: i32 = 2;
+
+/**
+Congestion summaries of the groups of a Dragonfly, as exchanged among its routers by [PiggybackPAR].
+Each group summarizes the occupancy of its global links towards each other group. A summary is known by the routers of the same group
+after `local_latency` cycles and by the routers of other groups after `global_latency` cycles, as if it were piggybacked on the packets through the links.
+The summaries are recorded from the occupancy of the ports of every router, at the cycles in which some packet is routed.
+**/
+#[derive(Debug,Default)]
+pub struct GroupTelemetry
+{
+	///The group of each router.
+	router_group: Vec<usize>,
+	///`global_ports[g][h]` are the pairs `(router,port)` of the links from the group `g` to the group `h`.
+	global_ports: Vec<Vec<Vec<(usize,usize)>>>,
+	///Cycles for a summary to be known in its own group.
+	local_latency: Time,
+	///Cycles for a summary to be known in the other groups.
+	global_latency: Time,
+	///The recorded summaries with their cycle, the oldest first. In each summary, `[g][h]` is the average occupancy of the links from `g` to `h`.
+	history: VecDeque<(Time,Vec<Vec<f64>>)>,
+}
+
+impl GroupTelemetry
+{
+	pub fn new(topology:&dyn Topology, local_latency:Time, global_latency:Time) -> GroupTelemetry
+	{
+		let size = topology.dragonfly_size().expect("GroupTelemetry requires a Dragonfly-like topology");
+		let router_group:Vec<usize> = (0..topology.num_routers()).map(|router|size.unpack(router).1).collect();
+		let mut global_ports = vec![vec![vec![];size.number_of_groups];size.number_of_groups];
+		for router in 0..topology.num_routers()
+		{
+			for port in 0..topology.degree(router)
+			{
+				if let (Location::RouterPort{router_index,..},_link_class) = topology.neighbour(router,port)
+				{
+					let (group,target_group) = (router_group[router],router_group[router_index]);
+					if group!=target_group
+					{
+						global_ports[group][target_group].push((router,port));
+					}
+				}
+			}
+		}
+		GroupTelemetry{
+			router_group,
+			global_ports,
+			local_latency,
+			global_latency,
+			history: VecDeque::new(),
+		}
+	}
+	///The group of a router.
+	pub fn group(&self, router:usize) -> usize
+	{
+		self.router_group[router]
+	}
+	///Records the summaries of the groups at `cycle`, unless they have already been recorded. The older ones that can no longer be employed are dropped.
+	pub fn record(&mut self, cycle:Time, network_occupancy:&[Vec<f64>])
+	{
+		if self.history.back().map(|(last,_)|*last>=cycle).unwrap_or(false)
+		{
+			return;
+		}
+		let summary = self.global_ports.iter().map(|group_ports|group_ports.iter().map(|ports|{
+			if ports.is_empty()
+			{
+				f64::INFINITY
+			}
+			else
+			{
+				ports.iter().map(|&(router,port)|network_occupancy[router].get(port).copied().unwrap_or(0.0)).sum::<f64>() / ports.len() as f64
+			}
+		}).collect()).collect();
+		self.history.push_back((cycle,summary));
+		let oldest_needed = cycle.saturating_sub(self.local_latency.max(self.global_latency));
+		while self.history.len()>=2 && self.history[1].0<=oldest_needed
+		{
+			self.history.pop_front();
 		}
 	}
+	///The occupancy of the links from `group` to `target_group` as known at `cycle` by the routers in `observer_group`.
+	///It is `None` while no summary is old enough.
+	pub fn congestion(&self, observer_group:usize, group:usize, target_group:usize, cycle:Time) -> Option<f64>
+	{
+		let latency = if observer_group==group { self.local_latency } else { self.global_latency };
+		let known_cycle = cycle.checked_sub(latency)?;
+		self.history.iter().rev().find(|(recorded,_)|*recorded<=known_cycle).map(|(_,summary)|summary[group][target_group])
+	}
+}
+
+/**
+A progressive adaptive routing for Dragonflies in the manner of the piggybacking mechanisms of Jiang et al. '09, and of the adaptive routing of Omni-Path and Slingshot.
+The routers exchange congestion summaries of their groups with some latency, see [GroupTelemetry]. At injection the packet chooses between the minimal path and a Valiant path through a random router
+of another group, comparing the occupancy of the first port plus the known occupancy of the global links of each path, multiplied by the length of the path.
+The minimal path is chosen when its cost does not exceed the cost of the Valiant path plus the `threshold`.
+With `progressive` a packet that chose the minimal path reconsiders the choice once at the next router of the source group, as in PAR.
+
+The same `routing` is employed for the minimal path and for both segments of the Valiant path. Deadlock must be avoided by other means, for example applying `ChannelsPerHop`.
+A Valiant path has up to 6 hops, and up to 7 when it is taken after a progressive reconsideration.
+```ignore
+ChannelsPerHop{
+	routing: PiggybackPAR{
+		routing: Shortest,
+		local_latency: 2,//cycles for the summary of a group to be known inside it.
+		global_latency: 20,//cycles for the summary of a group to be known by other groups.
+		//threshold: 0,//optional, bias in favour of the minimal path.
+		//progressive: true,//optional, reconsider at the second router of the source group.
+	},
+	channels: [[0],[1],[2],[3],[4],[5],[6],[7]],
+}
+```
+The results include a `PiggybackPARStatistics{minimal,nonminimal,revised}` with the number of decisions at injection of each kind and the number of packets that changed to the Valiant path after a local hop.
+ **/
+#[derive(Debug)]
+pub struct PiggybackPAR
+{
+	///The routing for the minimal path and for each segment of the Valiant path.
+	routing: Box<dyn Routing>,
+	///Cycles for a summary to be known in its own group.
+	local_latency: Time,
+	///Cycles for a summary to be known in the other groups.
+	global_latency: Time,
+	///Value added to the cost of the Valiant path before comparing it with the minimal path.
+	threshold: f64,
+	///Whether to reconsider the minimal path at the second router of the source group.
+	progressive: bool,
+	///The congestion summaries known by the routers. Built in `initialize`.
+	telemetry: RefCell<GroupTelemetry>,
+	///The current cycle, as given by `advance_cycle`.
+	cycle: Time,
+	///The number of minimal and nonminimal decisions at injection and of revised decisions since the last reset.
+	decisions: RefCell<[usize;3]>,
+}
+
+impl Routing for PiggybackPAR
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_occupancy(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,&RoutingOccupancy::default(),rng)
+	}
+	fn next_with_occupancy(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, occupancy:&RoutingOccupancy, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let meta = routing_info.meta.as_ref().expect("PiggybackPAR requires its routing info to be initialized");
+		let selections = routing_info.selections.as_ref().expect("PiggybackPAR requires its routing info to be initialized");
+		let intermediate = selections[1] as usize;
+		match selections[0]
+		{
+			PIGGYBACK_MINIMAL => self.routing.next_with_occupancy(&meta[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng),
+			PIGGYBACK_NONMINIMAL => self.routing.next_with_occupancy(&meta[1].borrow(),topology,current_router,intermediate,None,num_virtual_channels,occupancy,rng),
+			_ =>
+			{
+				if let Some(network_occupancy) = occupancy.network_occupancy
+				{
+					self.telemetry.borrow_mut().record(self.cycle,network_occupancy);
+				}
+				let minimal = self.routing.next_with_occupancy(&meta[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,occupancy,rng)?;
+				let nonminimal = self.routing.next_with_occupancy(&meta[1].borrow(),topology,current_router,intermediate,None,num_virtual_channels,occupancy,rng)?;
+				let first_occupancy = |candidates:&[CandidateEgress]| -> f64 {
+					match occupancy.port_occupancy
+					{
+						Some(ports) => candidates.iter().map(|candidate|ports.get(candidate.port).copied().unwrap_or(0.0)).fold(f64::INFINITY,f64::min),
+						None => 0.0,
+					}
+				};
+				let telemetry = self.telemetry.borrow();
+				let group = telemetry.group(current_router);
+				let congestion = |from:usize, to:usize| telemetry.congestion(group,telemetry.group(from),telemetry.group(to),self.cycle).unwrap_or(0.0);
+				let minimal_cost = (first_occupancy(&minimal.candidates) + congestion(current_router,target_router)) * topology.distance(current_router,target_router) as f64;
+				let nonminimal_cost = (first_occupancy(&nonminimal.candidates) + congestion(current_router,intermediate) + congestion(intermediate,target_router))
+					* (topology.distance(current_router,intermediate) + topology.distance(intermediate,target_router)) as f64;
+				let (choice,chosen) = if minimal_cost <= nonminimal_cost + self.threshold { (PIGGYBACK_MINIMAL,minimal) } else { (PIGGYBACK_NONMINIMAL,nonminimal) };
+				let candidates = chosen.into_iter().map(|candidate|{
+					let annotation = Some(RoutingAnnotation{values:vec![choice],meta:vec![candidate.annotation.clone()]});
+					CandidateEgress{annotation,..candidate}
+				}).collect();
+				//The choice depends on the occupancy, so it may change in other calls.
+				Ok(RoutingNextCandidates{candidates,idempotent:false})
+			}
+		}
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let (source_group,target_group) = {
+			let telemetry = self.telemetry.borrow();
+			(telemetry.group(current_router),telemetry.group(target_router))
+		};
+		let size = topology.dragonfly_size().expect("PiggybackPAR requires a Dragonfly-like topology");
+		let mut bri = routing_info.borrow_mut();
+		bri.meta = Some(vec![RefCell::new(RoutingInfo::new()),RefCell::new(RoutingInfo::new())]);
+		let meta = bri.meta.as_ref().unwrap();
+		self.routing.initialize_routing_info(&meta[0],topology,current_router,target_router,target_server,rng);
+		let selections = if source_group==target_group || size.number_of_groups<=2
+		{
+			//There is nothing to choose.
+			vec![PIGGYBACK_MINIMAL,current_router as i32,0]
+		}
+		else
+		{
+			//A router in a group other than the source and target groups.
+			let mut intermediate_group = rng.gen_range(0..size.number_of_groups-2);
+			for group in [source_group.min(target_group),source_group.max(target_group)]
+			{
+				if intermediate_group>=group
+				{
+					intermediate_group += 1;
+				}
+			}
+			let intermediate = size.pack((rng.gen_range(0..size.group_size),intermediate_group));
+			self.routing.initialize_routing_info(&meta[1],topology,current_router,intermediate,None,rng);
+			vec![PIGGYBACK_UNDECIDED,intermediate as i32,if self.progressive {1} else {0}]
+		};
+		bri.selections = Some(selections);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let (state,intermediate,revisable) = {
+			let selections = bri.selections.as_ref().unwrap();
+			(selections[0],selections[1] as usize,selections[2])
+		};
+		if state==PIGGYBACK_NONMINIMAL
+		{
+			if current_router==intermediate
+			{
+				bri.selections = Some(vec![PIGGYBACK_MINIMAL,intermediate as i32,0]);
+				let meta = bri.meta.as_ref().unwrap();
+				meta[0].replace(RoutingInfo::new());
+				self.routing.initialize_routing_info(&meta[0],topology,current_router,target_router,target_server,rng);
+			}
+			else
+			{
+				let meta = bri.meta.as_mut().unwrap();
+				meta[1].borrow_mut().hops+=1;
+				self.routing.update_routing_info(&meta[1],topology,current_router,current_port,intermediate,None,rng);
+			}
+		}
+		else
+		{
+			{
+				let meta = bri.meta.as_mut().unwrap();
+				meta[0].borrow_mut().hops+=1;
+				self.routing.update_routing_info(&meta[0],topology,current_router,current_port,target_router,target_server,rng);
+			}
+			if state==PIGGYBACK_MINIMAL && revisable==1
+			{
+				let local_hop = match topology.neighbour(current_router,current_port).0
+				{
+					Location::RouterPort{router_index,..} => { let telemetry = self.telemetry.borrow(); telemetry.group(router_index)==telemetry.group(current_router) },
+					_ => false,
+				};
+				if local_hop && current_router!=target_router
+				{
+					//Reconsider the choice, marking it as a revision.
+					let meta = bri.meta.as_ref().unwrap();
+					meta[1].replace(RoutingInfo::new());
+					self.routing.initialize_routing_info(&meta[1],topology,current_router,intermediate,None,rng);
+					bri.selections = Some(vec![PIGGYBACK_UNDECIDED,intermediate as i32,2]);
+				}
+				else
+				{
+					bri.selections = Some(vec![PIGGYBACK_MINIMAL,intermediate as i32,0]);
+				}
+			}
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+		self.telemetry = RefCell::new(GroupTelemetry::new(topology,self.local_latency,self.global_latency));
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		let (state,intermediate,revisable) = {
+			let bri = routing_info.borrow();
+			let selections = bri.selections.as_ref().unwrap();
+			(selections[0],selections[1] as usize,selections[2])
+		};
+		let (state,requested) = if state==PIGGYBACK_UNDECIDED
+		{
+			let annotation = requested.annotation.as_ref().expect("PiggybackPAR candidates should be annotated");
+			let choice = annotation.values[0];
+			let mut decisions = self.decisions.borrow_mut();
+			if revisable==2
+			{
+				if choice==PIGGYBACK_NONMINIMAL
+				{
+					decisions[2] += 1;
+				}
+			}
+			else
+			{
+				decisions[if choice==PIGGYBACK_MINIMAL {0} else {1}] += 1;
+			}
+			let revisable = if choice==PIGGYBACK_MINIMAL && revisable==1 {1} else {0};
+			routing_info.borrow_mut().selections = Some(vec![choice,intermediate as i32,revisable]);
+			(choice,CandidateEgress{annotation:annotation.meta[0].clone(),..requested.clone()})
+		}
+		else
+		{
+			(state,requested.clone())
+		};
+		let bri = routing_info.borrow();
+		let meta = bri.meta.as_ref().unwrap();
+		if state==PIGGYBACK_NONMINIMAL
+		{
+			self.routing.performed_request(&requested,&meta[1],topology,current_router,intermediate,None,num_virtual_channels,rng);
+		}
+		else
+		{
+			self.routing.performed_request(&requested,&meta[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
+		}
+	}
+	fn need_occupancy(&self) -> bool { true }
+	fn need_global_occupancy(&self) -> bool { true }
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let decisions = self.decisions.borrow();
+		let content = vec![
+			(String::from("minimal"),ConfigurationValue::Number(decisions[0] as f64)),
+			(String::from("nonminimal"),ConfigurationValue::Number(decisions[1] as f64)),
+			(String::from("revised"),ConfigurationValue::Number(decisions[2] as f64)),
+		];
+		Some(ConfigurationValue::Object(String::from("PiggybackPARStatistics"),content))
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.decisions.borrow_mut() = [0,0,0];
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.cycle = cycle;
+		self.routing.advance_cycle(cycle);
+	}
+}
+
+impl PiggybackPAR
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Result<PiggybackPAR,Error>
+	{
+		let mut routing=None;
+		let mut local_latency=None;
+		let mut global_latency=None;
+		let mut threshold=0.0;
+		let mut progressive=true;
+		match_object!(arg.cv,"PiggybackPAR",value,
+			"routing" => routing=Some(try_new_routing(RoutingBuilderArgument{cv:value,..arg})?),
+			"local_latency" => local_latency=Some(value.as_time()?),
+			"global_latency" => global_latency=Some(value.as_time()?),
+			"threshold" => threshold=value.as_f64()?,
+			"progressive" => progressive=value.as_bool()?,
+		);
+		let routing=routing.ok_or_else(||arg.cv.ill("There were no routing"))?;
+		let local_latency=local_latency.ok_or_else(||arg.cv.ill("There were no local_latency"))?;
+		let global_latency=global_latency.ok_or_else(||arg.cv.ill("There were no global_latency"))?;
+		Ok(PiggybackPAR{
+			routing,
+			local_latency,
+			global_latency,
+			threshold,
+			progressive,
+			telemetry: RefCell::new(GroupTelemetry::default()),
+			cycle: 0,
+			decisions: RefCell::new([0,0,0]),
+		})
+	}
 }
 
 
@@ -1417,7 +1802,7 @@ impl Routing for DragonflyDirect
 
 impl DragonflyDirect
 {
-	pub fn new(_arg: RoutingBuilderArgument) -> DragonflyDirect
+	pub fn new(_arg: RoutingBuilderArgument) -> Result<DragonflyDirect,Error>
 	{
 		let class_weight=vec![1, 100];
 		let total_max_weight_distance= 120;
@@ -1432,14 +1817,14 @@ impl DragonflyDirect
 		// 		.map(|v|v.as_f64().expect("bad value in local_max_weight_distance") as usize).collect(),
 		// );
 		// let class_weight=class_weight.expect("There were no class_weight");
-		DragonflyDirect {
+		Ok(DragonflyDirect {
 			class_weight,
 			distance_matrix:Matrix::constant(0, 0, 0),
 			local_matrix:Matrix::constant(0, 0, 0),
 			group_matrix:Matrix::constant(0,0,0),
 			total_max_weight_distance,
 			max_hops_per_class,
-		}
+		})
 	}
 }
 
@@ -1508,6 +1893,44 @@ mod tests {
 			}
 		}
 	}
+	/// The weighted distances must satisfy the optimality conditions of shortest paths.
+	#[test]
+	fn weighted_distances()
+	{
+		use crate::topology::{new_topology,TopologyBuilderArgument,NeighbourRouterIteratorItem};
+		use crate::config_parser::ConfigurationValue;
+		let plugs = crate::Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let cv = ConfigurationValue::Object("Dragonfly".to_string(),vec![
+			("global_ports_per_router".to_string(),ConfigurationValue::Number(2.0)),
+			("servers_per_router".to_string(),ConfigurationValue::Number(1.0)),
+		]);
+		let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		let n = topology.num_routers();
+		for class_weight in [vec![1,1],vec![1,100],vec![3,2]]
+		{
+			let matrix = topology.compute_weighted_distance_matrix(&class_weight);
+			let bfs_matrix = topology.compute_distance_matrix(Some(&class_weight));
+			for source in 0..n
+			{
+				assert_eq!(*matrix.get(source,source),0);
+				for router in 0..n
+				{
+					let distance = *matrix.get(source,router);
+					assert!(distance <= *bfs_matrix.get(source,router), "Dijkstra gave a longer distance than BFS");
+					let mut has_predecessor = router==source;
+					for NeighbourRouterIteratorItem{link_class,neighbour_router,..} in topology.neighbour_router_iter(router)
+					{
+						let previous = *matrix.get(source,neighbour_router);
+						let weight = class_weight[link_class];
+						assert!(distance <= previous+weight, "A link can shorten the distance from {} to {}", source, router);
+						if previous+weight==distance { has_predecessor=true; }
+					}
+					assert!(has_predecessor, "The distance from {} to {} is not achieved", source, router);
+				}
+			}
+		}
+	}
 }
 
 
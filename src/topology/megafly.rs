@@ -7,10 +7,11 @@ use super::prelude::*;
 use crate::matrix::Matrix;
 use super::dragonfly::{Arrangement,ArrangementPoint,ArrangementSize,Palmtree,new_arrangement};
 use crate::config_parser::ConfigurationValue;
-use crate::match_object_panic;
 use crate::pattern::Pattern;
 use crate::routing::{CandidateEgress, Error, Routing, RoutingBuilderArgument, RoutingInfo, RoutingNextCandidates};
 use crate::topology::NeighbourRouterIteratorItem;
+use crate::{match_object,error,source_location};
+use crate::error::SourceLocation;
 
 /**
 Implementation of the so called Megafly or Dragonfly+ interconnection network topology.
@@ -197,12 +198,17 @@ impl Topology for Megafly
 	{
 		todo!()
 	}
+	///The first level of switches are `leaf` and the second `spine`.
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		if router_index < self.number_of_groups*self.group_size { Some(String::from("leaf")) } else { Some(String::from("spine")) }
+	}
 }
 
 
 impl Megafly
 {
-	pub fn new(arg:TopologyBuilderArgument) -> Megafly
+	pub fn new(arg:TopologyBuilderArgument) -> Result<Megafly,Error>
 	{
 		let mut global_ports_per_spine=None;
 		let mut servers_per_leaf=None;
@@ -210,18 +216,18 @@ impl Megafly
 		let mut number_of_groups=None;
 		let mut global_arrangement=None;
 		let mut lag=1;
-		match_object_panic!(arg.cv,"Megafly",value,
-			"global_ports_per_spine" => global_ports_per_spine=Some(value.as_f64().expect("bad value for global_ports_per_spine")as usize),
-			"servers_per_leaf" => servers_per_leaf=Some(value.as_f64().expect("bad value for servers_per_leaf")as usize),
-			"group_size" => group_size=Some(value.as_f64().expect("bad value for group_size")as usize),
-			"number_of_groups" => number_of_groups=Some(value.as_f64().expect("bad value for number_of_groups")as usize),
+		match_object!(arg.cv,"Megafly",value,
+			"global_ports_per_spine" => global_ports_per_spine=Some(value.as_f64()? as usize),
+			"servers_per_leaf" => servers_per_leaf=Some(value.as_f64()? as usize),
+			"group_size" => group_size=Some(value.as_f64()? as usize),
+			"number_of_groups" => number_of_groups=Some(value.as_f64()? as usize),
 			"global_arrangement" => global_arrangement=Some(new_arrangement(value.into())),
-			"lag" | "global_lag" => lag=value.as_usize().expect("bad value for lag"),
+			"lag" | "global_lag" => lag=value.as_usize()?,
 		);
-		let global_ports_per_spine=global_ports_per_spine.expect("There were no global_ports_per_spine");
-		let servers_per_leaf=servers_per_leaf.expect("There were no servers_per_leaf");
-		let group_size=group_size.expect("There were no group_size");
-		let number_of_groups=number_of_groups.expect("There were no number_of_groups");
+		let global_ports_per_spine=global_ports_per_spine.ok_or_else(||arg.cv.ill("There were no global_ports_per_spine"))?;
+		let servers_per_leaf=servers_per_leaf.ok_or_else(||arg.cv.ill("There were no servers_per_leaf"))?;
+		let group_size=group_size.ok_or_else(||arg.cv.ill("There were no group_size"))?;
+		let number_of_groups=number_of_groups.ok_or_else(||arg.cv.ill("There were no number_of_groups"))?;
 		let mut global_arrangement = global_arrangement.unwrap_or_else(||Box::new(Palmtree::default()));
 		global_arrangement.initialize(ArrangementSize{
 			number_of_groups,
@@ -242,7 +248,7 @@ impl Megafly
 		};
 		let (distance_matrix,_amount_matrix)=topo.compute_amount_shortest_paths();
 		topo.distance_matrix=distance_matrix.map(|x|*x as u8);
-		topo
+		Ok(topo)
 	}
 	/**
 	 Unpack a switch index into `(group_offset, group_index, level)` coordinates.
@@ -688,7 +694,7 @@ impl Routing for MegaflyAD
 
 impl MegaflyAD
 {
-	pub fn new(arg: RoutingBuilderArgument) -> MegaflyAD
+	pub fn new(arg: RoutingBuilderArgument) -> Result<MegaflyAD,Error>
 	{
 		// //let mut order=None;
 		// //let mut servers_per_router=None;
@@ -708,44 +714,44 @@ impl MegaflyAD
 		// let mut intermediate_source_minimal_pattern=None;
 		// let mut intermediate_target_minimal_pattern=None;
 		// let mut intermediate_leaf_switch_pattern :Box<dyn Pattern> = new_pattern(PatternBuilderArgument{cv: &ConfigurationValue::Object("Identity".to_string(), vec![]),plugs:arg.plugs});
-		match_object_panic!(arg.cv,"MegaflyAD",value,
+		match_object!(arg.cv,"MegaflyAD",value,
 
 			"first_allowed_virtual_channels" => first_allowed_virtual_channels=value.
-				as_array().expect("bad value for first_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in first_reserved_virtual_channels") as usize).collect(),
 			"second_allowed_virtual_channels" => second_allowed_virtual_channels=value.
-				as_array().expect("bad value for second_reserved_virtual_channels").iter()
+				as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in second_reserved_virtual_channels") as usize).collect(),
-			"minimal_to_deroute" => minimal_to_deroute=value.as_array().expect("bad value for minimal_to_deroute").iter()
+			"minimal_to_deroute" => minimal_to_deroute=value.as_array()?.iter()
 				.map(|v|v.as_f64().expect("bad value in minimal_to_deroute") as usize).collect(),
 			// "local_pattern_per_hop" => local_pattern_per_hop=value.as_array().expect("bad value for local_pattern_per_hop").iter()
 			// 	.map(|v|v.as_array().expect("bad value for local_pattern_per_hop").iter()
 			// 	.map(|p|new_optional_pattern(PatternBuilderArgument{cv:p,plugs:arg.plugs})).collect()
 			// ).collect(),
-			"source_group_pattern" => source_group_pattern=value.as_array().expect("bad value for source_group_pattern").iter()
+			"source_group_pattern" => source_group_pattern=value.as_array()?.iter()
 				.map(|v|v.as_array().expect("bad value for source_group_pattern").iter()
 				.map(|p|new_optional_pattern(PatternBuilderArgument{cv:p,plugs:arg.plugs})).collect()
 			).collect(),
-			"intermediate_group_pattern" => intermediate_group_pattern=value.as_array().expect("bad value for intermediate_group_pattern").iter()
+			"intermediate_group_pattern" => intermediate_group_pattern=value.as_array()?.iter()
 				.map(|v|v.as_array().expect("bad value for intermediate_group_pattern").iter()
 				.map(|p|new_optional_pattern(PatternBuilderArgument{cv:p,plugs:arg.plugs})).collect()
 			).collect(),
-			"destination_group_pattern" => destination_group_pattern=value.as_array().expect("bad value for destination_group_pattern").iter()
+			"destination_group_pattern" => destination_group_pattern=value.as_array()?.iter()
 				.map(|v|v.as_array().expect("bad value for destination_group_pattern").iter()
 				.map(|p|new_optional_pattern(PatternBuilderArgument{cv:p,plugs:arg.plugs})).collect()
 			).collect(),
-			"global_pattern_per_hop" => global_pattern_per_hop=value.as_array().expect("bad value for global_pattern_per_hop").iter()
+			"global_pattern_per_hop" => global_pattern_per_hop=value.as_array()?.iter()
 				.map(|v|v.as_array().expect("bad value for global_pattern_per_hop").iter()
 				.map(|p|new_optional_pattern(PatternBuilderArgument{cv:p,plugs:arg.plugs})).collect()
 			).collect(),
-			"consume_same_channel" => consume_same_channel=value.as_bool().expect("bad value for consume_same_channel"),
-			"set_global_minimal_channel" => set_global_minimal_channel=value.as_bool().expect("bad value for set_global_minimal_channel"),
+			"consume_same_channel" => consume_same_channel=value.as_bool()?,
+			"set_global_minimal_channel" => set_global_minimal_channel=value.as_bool()?,
 			// "intermediate_source_minimal_pattern" => intermediate_source_minimal_pattern=new_optional_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs}),
 			// "intermediate_target_minimal_pattern" => intermediate_target_minimal_pattern=new_optional_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs}),
 			// "intermediate_leaf_switch_pattern" => intermediate_leaf_switch_pattern=new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs}),
 		);
 
-		MegaflyAD{
+		Ok(MegaflyAD{
 			first_allowed_virtual_channels,
 			second_allowed_virtual_channels,
 			minimal_to_deroute,
@@ -755,6 +761,6 @@ impl MegaflyAD
 			global_pattern_per_hop,
 			consume_same_channel,
 			set_global_minimal_channel,
-		}
+		})
 	}
 }
\ No newline at end of file
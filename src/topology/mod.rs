@@ -17,11 +17,13 @@ pub mod multistage;
 pub mod megafly;
 
 use std::fs::File;
-use ::rand::{rngs::StdRng};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use ::rand::{rngs::StdRng,Rng};
 use std::io::{Write};
 
 use quantifiable_derive::Quantifiable;//the derive macro
-use self::cartesian::{Mesh,Torus,CartesianData,Hamming,AsCartesianTopology};
+use self::cartesian::{Mesh,Torus,CartesianData,Hamming,ExpressMesh,TwistedTorus,AsCartesianTopology};
 use self::neighbourslists::NeighboursLists;
 use self::dragonfly::Dragonfly;
 use self::projective::{Projective,LeviProjective};
@@ -31,18 +33,20 @@ use crate::config_parser::ConfigurationValue;
 use crate::matrix::Matrix;
 use crate::quantify::Quantifiable;
 use crate::Plugs;
+use crate::error::{Error,SourceLocation};
+use crate::{error,source_location};
 
 /// Some things most uses of the topology module will use.
 pub mod prelude
 {
-	pub use super::{Topology,Location,cartesian::CartesianData,TopologyBuilderArgument,new_topology,NeighbourRouterIteratorItem};
+	pub use super::{Topology,Location,cartesian::CartesianData,TopologyBuilderArgument,new_topology,try_new_topology,NeighbourRouterIteratorItem};
 	pub use std::cell::{RefCell};
 	pub use ::rand::rngs::StdRng;
 }
 
 ///A location where a phit can be inserted.
 ///None is used for disconnected ports, for example in the `Mesh` topology.
-#[derive(Clone,Debug,Quantifiable,Hash,Eq,PartialEq)]
+#[derive(Clone,Debug,Quantifiable,Hash,Eq,PartialEq,PartialOrd,Ord)]
 pub enum Location
 {
 	RouterPort{
@@ -67,6 +71,45 @@ pub struct NeighbourRouterIteratorItem
 	pub neighbour_port: usize,
 }
 
+///A quantity estimated by sampling, given by its mean and the half width of its 95% confidence interval.
+#[derive(Clone,Copy,Debug)]
+pub struct SampledEstimate
+{
+	pub mean: f64,
+	///The true value is within `mean±half_width` with 95% confidence. It is 0 when the value is exact.
+	pub half_width: f64,
+}
+
+impl SampledEstimate
+{
+	///Builds the estimate from the values observed in independent samples. Set `exact` when the samples cover the whole population.
+	pub fn from_samples(values:&[f64], exact:bool) -> SampledEstimate
+	{
+		let k = values.len() as f64;
+		let mean = values.iter().sum::<f64>()/k;
+		let half_width = if exact { 0.0 } else if values.len()<2 { f64::INFINITY } else {
+			let variance = values.iter().map(|x|(x-mean)*(x-mean)).sum::<f64>()/(k-1.0);
+			1.96*(variance/k).sqrt()
+		};
+		SampledEstimate{mean,half_width}
+	}
+}
+
+///Path diversity metrics of a topology, estimated from the shortest paths starting at some sampled routers.
+///See [Topology::estimate_path_diversity].
+#[derive(Clone,Copy,Debug)]
+pub struct PathDiversityEstimate
+{
+	///The number of origin routers sampled.
+	pub samples: usize,
+	///Average distance between different routers.
+	pub average_distance: SampledEstimate,
+	///Average number of shortest paths between different routers. The sampled counterpart of [Topology::average_amount_shortest_paths].
+	pub average_amount_shortest_paths: SampledEstimate,
+	///Fraction of pairs of different routers joined by more than one shortest path.
+	pub multiple_paths_fraction: SampledEstimate,
+}
+
 ///A topology describes how routers and servers are connected.
 ///The router `index` has `ports(index)` neighbours. The first `degree(index)` must be other routers.
 pub trait Topology : Quantifiable + std::fmt::Debug
@@ -81,6 +124,22 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 	///The neighbour of a server: Location+link class index
 	//FIXME: What to do with BCube and similar?
 	fn server_neighbour(&self, server_index:usize) -> (Location,usize);
+	///All the neighbours of a server, for servers with several ports, such as multi-rail NICs. See [MultiRail](operations::MultiRailTopology).
+	///The first one must be the one returned by [Topology::server_neighbour], which is where the packets to the server are routed to.
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		vec![self.server_neighbour(server_index)]
+	}
+	///The router attached to the server `server_index` towards which a packet at `current_router` must be routed.
+	///By default it is the router of [Topology::server_neighbour]. Topologies with independent planes, such as [MultiPlane](operations::MultiPlaneTopology), give the one in the plane of `current_router`.
+	fn server_router_from(&self, server_index:usize, _current_router:usize) -> usize
+	{
+		match self.server_neighbour(server_index).0
+		{
+			Location::RouterPort{router_index,..} => router_index,
+			_ => panic!("The server is not attached to a router"),
+		}
+	}
 	//diameter is only used in private projects...
 	///the greatest distance from server to server
 	fn diameter(&self) -> usize;
@@ -146,9 +205,24 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 	{
 		unimplemented!()
 	}
+	///Specific for Cartesian topologies. The dimension whose coordinate changes when traversing a link of class `link_class`.
+	///Routings such as [DOR](cartesian::DOR) use it to find the links of each dimension. By default the link class is the dimension, as in [Mesh], [Torus], and [Hamming].
+	fn cartesian_link_dimension(&self, link_class:usize) -> usize { link_class }
+	///Specific for Cartesian topologies. The signed change in the coordinate of its dimension when traversing the link at the port `port` of the router `router_index`, as counted by the [coordinated_routing_record](Topology::coordinated_routing_record).
+	///By default `None`, and routings such as [DOR](cartesian::DOR) deduce it from the coordinates of both routers, which is ambiguous in the twisted wrap-around links of a [TwistedTorus](cartesian::TwistedTorus).
+	fn cartesian_link_displacement(&self, _router_index:usize, _port:usize) -> Option<i32> { None }
 	///Specific for some topologies, but must be checkable for anyone
 	/// Indicates if going from input_port to output_port implies a direction change. Used for the bubble routing.
 	fn is_direction_change(&self, _router_index:usize, _input_port: usize, _output_port: usize) -> bool { false }
+	///Whether the link at the port `port` of the router `router_index` works. Topologies with faults give `false` for their removed links, which have `None` as neighbour.
+	///The routers discard the routing candidates through links that are not alive, so the adaptive routings go around the faults.
+	fn is_link_alive(&self, _router_index:usize, _port:usize) -> bool { true }
+	///Sets whether the link at the port `port` of the router `router_index` works, changing both of its ends, during the simulation.
+	///Only the topologies wrapped into [ReconfigurableLinks](operations::ReconfigurableLinks) support it, as done by the simulation when there are topology `events`. By default it is an error.
+	fn set_link_alive(&mut self, router_index:usize, port:usize, _alive:bool) -> Result<(),Error>
+	{
+		Err(error!(unsupported_configuration).with_message(format!("The topology cannot change the link at the port {} of the router {}.",port,router_index)))
+	}
 	///For topologies containing the so called up/down paths. Other topologies should return always `None`.
 	///If the return is `Some((u,d))` it means there is an initial up sub-path of length `u` followed by a down sub-path of length `d` starting at `origin` and ending at `destination`. A return value of `None` means there is no up/down path from `origin` to `destination`.
 	///Some general guidelines, although it is not clear if they must hold always:
@@ -163,9 +237,13 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>;
 	/// Information for Dragonfly-like networks.
 	fn dragonfly_size(&self) -> Option<dragonfly::ArrangementSize> { None }
+	///The role of the router in topologies with several kinds of routers, such as `leaf` and `spine` in indirect networks.
+	///It allows to select a different router model for each role. By default `None`, as all routers are alike.
+	fn router_role(&self, _router_index:usize) -> Option<String> { None }
 
 	///Breadth First Search to compute distances from a router to all others.
 	///It may use weights, but it there are multiple paths with different distances it may give a non-minimal distance, since it is not Dijkstra.
+	///Use [Topology::dijkstra] for weighted distances.
 	fn bfs(&self, origin:usize, class_weight:Option<&[usize]>) -> Vec<usize>
 	{
 		//Adapted from my code for other software.
@@ -262,6 +340,41 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 		return R;
 	}
 	
+	///Dijkstra's algorithm to compute the weighted distances from a router to all others.
+	///The link of class `c` weighs `class_weight[c]`. Links whose class is outside `class_weight` or with weight `usize::MAX` are not used.
+	///Unreachable routers get distance `usize::MAX`.
+	fn dijkstra(&self, origin:usize, class_weight:&[usize]) -> Vec<usize>
+	{
+		let n=self.num_routers();
+		let mut distance=vec![usize::MAX;n];
+		distance[origin]=0;
+		let mut heap=BinaryHeap::new();
+		heap.push(Reverse((0,origin)));
+		while let Some(Reverse((current_distance,current)))=heap.pop()
+		{
+			if current_distance>distance[current]
+			{
+				//An outdated entry.
+				continue;
+			}
+			for NeighbourRouterIteratorItem{link_class,neighbour_router,..} in self.neighbour_router_iter(current)
+			{
+				let weight = match class_weight.get(link_class)
+				{
+					Some(&w) if w!=usize::MAX => w,
+					_ => continue,
+				};
+				let alt=current_distance.saturating_add(weight);
+				if alt<distance[neighbour_router]
+				{
+					distance[neighbour_router]=alt;
+					heap.push(Reverse((alt,neighbour_router)));
+				}
+			}
+		}
+		distance
+	}
+
 	/**
 	Computes the diameter by checking all switch pairs.
 	**/
@@ -300,6 +413,23 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 		return matrix;
 	}
 
+	///Computes the matrix of weighted distances between routers by running [Topology::dijkstra] from each of them.
+	///Contrary to [Topology::compute_distance_matrix], the distances are minimal for any positive weights.
+	fn compute_weighted_distance_matrix(&self, class_weight:&[usize]) -> Matrix<usize>
+	{
+		let n=self.num_routers();
+		let mut matrix=Matrix::constant(0,n,n);
+		for i in 0..n
+		{
+			let d=self.dijkstra(i,class_weight);
+			for j in 0..n
+			{
+				*matrix.get_mut(i,j)=d[j];
+			}
+		}
+		matrix
+	}
+
 	fn floyd(&self) -> Matrix<usize>
 	{
 		// Implements Floyd–Warshall algorithm. This was adapted from a previous code for another software.
@@ -454,6 +584,142 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 		(distance_matrix,amount_matrix)
 	}
 
+	///Return a pair of vectors `(D,A)` with `D[j]` being the distance from `origin` to `j` and `A[j]` the number of paths of length `D[j]`.
+	///Unreachable routers get a distance of `usize::MAX`. It takes time and memory linear in the size of the topology, and the amounts are
+	///given as floating point to avoid overflows in large topologies.
+	fn amount_shortest_paths_from(&self, origin:usize) -> (Vec<usize>,Vec<f64>)
+	{
+		let n=self.num_routers();
+		let mut distance=vec![usize::MAX;n];
+		let mut amount=vec![0f64;n];
+		let mut queue=Vec::with_capacity(n);
+		distance[origin]=0;
+		amount[origin]=1.0;
+		queue.push(origin);
+		let mut queue_read_index=0;
+		while queue_read_index<queue.len()
+		{
+			let current=queue[queue_read_index];
+			queue_read_index+=1;
+			let alt=distance[current]+1;
+			let current_amount=amount[current];
+			for NeighbourRouterIteratorItem{neighbour_router,..} in self.neighbour_router_iter(current)
+			{
+				if alt<distance[neighbour_router]
+				{
+					distance[neighbour_router]=alt;
+					amount[neighbour_router]=current_amount;
+					queue.push(neighbour_router);
+				}
+				else if alt==distance[neighbour_router]
+				{
+					amount[neighbour_router]+=current_amount;
+				}
+			}
+		}
+		(distance,amount)
+	}
+
+	/**
+	Estimate path diversity metrics by computing the shortest paths from `samples` origin routers chosen at random.
+	Each sample costs a BFS, so it is usable in topologies too large for [compute_amount_shortest_paths] or [average_amount_shortest_paths].
+	When `samples` is at least the number of routers every router is taken as origin once and the result is exact.
+	Only pairs of routers connected by some path are considered.
+	**/
+	fn estimate_path_diversity(&self, samples:usize, rng:&mut StdRng) -> PathDiversityEstimate
+	{
+		let n=self.num_routers();
+		let exact = samples>=n;
+		let origins : Vec<usize> = if exact { (0..n).collect() } else { (0..samples).map(|_|rng.gen_range(0..n)).collect() };
+		let mut distances=Vec::with_capacity(origins.len());
+		let mut amounts=Vec::with_capacity(origins.len());
+		let mut multiple=Vec::with_capacity(origins.len());
+		for &origin in origins.iter()
+		{
+			let (distance,amount)=self.amount_shortest_paths_from(origin);
+			let mut reached=0;
+			let mut distance_sum=0;
+			let mut amount_sum=0.0;
+			let mut multiple_count=0;
+			for target in 0..n
+			{
+				if target!=origin && distance[target]!=usize::MAX
+				{
+					reached+=1;
+					distance_sum+=distance[target];
+					amount_sum+=amount[target];
+					if amount[target]>1.0
+					{
+						multiple_count+=1;
+					}
+				}
+			}
+			if reached>0
+			{
+				distances.push(distance_sum as f64/reached as f64);
+				amounts.push(amount_sum/reached as f64);
+				multiple.push(multiple_count as f64/reached as f64);
+			}
+		}
+		PathDiversityEstimate{
+			samples: origins.len(),
+			average_distance: SampledEstimate::from_samples(&distances,exact),
+			average_amount_shortest_paths: SampledEstimate::from_samples(&amounts,exact),
+			multiple_paths_fraction: SampledEstimate::from_samples(&multiple,exact),
+		}
+	}
+
+	/**
+	Partition the routers into classes that contain the orbits of the automorphisms of the topology, by colour refinement.
+	Routers start coloured by their number of servers and the link classes of their ports, and are refined by the multiset of the colours of their neighbours until it stabilizes.
+	Two routers in different classes are never symmetric. Routers in the same class are symmetric in most practical topologies, but not necessarily, so the partition is an approximation of the orbits from above.
+	In particular, a single class is obtained for every vertex-transitive topology.
+	Returns `class[router]`, with the classes numbered by their first router.
+	**/
+	fn symmetry_classes(&self) -> Vec<usize>
+	{
+		let n=self.num_routers();
+		let relabel = |signatures:Vec<Vec<usize>>| -> (Vec<usize>,usize) {
+			let mut labels = std::collections::BTreeMap::new();
+			let canonical : Vec<usize> = signatures.iter().map(|signature|{ let next=labels.len(); *labels.entry(signature).or_insert(next) }).collect();
+			(canonical,labels.len())
+		};
+		let initial : Vec<Vec<usize>> = (0..n).map(|router|{
+			let mut servers=0;
+			let mut classes=vec![];
+			for port in 0..self.ports(router)
+			{
+				match self.neighbour(router,port)
+				{
+					(Location::RouterPort{..},link_class) => classes.push(link_class),
+					(Location::ServerPort(_),_) => servers+=1,
+					_ => (),
+				}
+			}
+			classes.sort_unstable();
+			classes.push(servers);
+			classes
+		}).collect();
+		let (mut class,mut amount) = relabel(initial);
+		loop
+		{
+			let signatures : Vec<Vec<usize>> = (0..n).map(|router|{
+				let mut neighbours : Vec<usize> = self.neighbour_router_iter(router).map(|item|item.link_class*n+class[item.neighbour_router]).collect();
+				neighbours.sort_unstable();
+				neighbours.push(class[router]);
+				neighbours
+			}).collect();
+			let (refined,refined_amount) = relabel(signatures);
+			class=refined;
+			if refined_amount==amount
+			{
+				break;
+			}
+			amount=refined_amount;
+		}
+		class
+	}
+
 	/// Find the components of the subtopology induced via the allowed links.
 	/// Returns vector `ret` with `ret[k]` containing the vertices in the `k`-th component.
 	fn components(&self,allowed_classes:&[bool]) -> Vec<Vec<usize>>
@@ -623,7 +889,11 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 					},
 					Location::ServerPort(server_index) =>
 					{
-						let (rev_location, rev_link_class) = self.server_neighbour(server_index);
+						//With several rails the port must be one of the neighbours of the server.
+						let rails = self.server_neighbours(server_index);
+						let (rev_location, rev_link_class) = rails.iter().find(|(location,_)|matches!(location,
+							Location::RouterPort{router_index: rev_router,router_port: rev_port} if *rev_router==router_index && *rev_port==port_index
+						)).cloned().unwrap_or_else(||rails[0].clone());
 						match rev_location
 						{
 							Location::RouterPort{
@@ -671,19 +941,74 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 			}
 		}
 	}
-	///Dump the adjacencies into a file.
-	///You may use NeighboursLists::file_adj to load them.
-	fn write_adjacencies_to_file(&self, file:&mut File, _format:usize)->Result<(),std::io::Error>
+	///Dump the adjacencies into a file, in the given `format`.
+	///* 0: the plain format of CAMINOS, which `NeighboursLists::file_adj` loads.
+	///* 1: GraphML, which the `File` topology can load back with `format:1`. Each node has a `servers` data with its number of servers.
+	///* 2: Graphviz DOT, as an undirected graph in which each node has a `servers` attribute.
+	///
+	///In GraphML and DOT each link appears once, with its `link_class`, and multiple links between the same routers are kept.
+	fn write_adjacencies_to_file(&self, file:&mut File, format:usize)->Result<(),std::io::Error>
 	{
 		let n=self.num_routers();
-		writeln!(file,"NODOS {}",n)?;
-		writeln!(file,"GRADO {}",self.maximum_degree())?;
-		//for (router_index,neighbour_list) in self.list.iter().enumerate()
-		for router_index in 0..n
+		//The links, each once, as `(router,neighbour,link_class)`.
+		let links = || (0..n).flat_map(move |router_index|self.neighbour_router_iter(router_index).filter_map(move |item|{
+			if router_index<item.neighbour_router || (router_index==item.neighbour_router && item.port_index<item.neighbour_port)
+			{
+				Some((router_index,item.neighbour_router,item.link_class))
+			}
+			else
+			{
+				None
+			}
+		}));
+		match format
 		{
-			writeln!(file,"N {}",router_index)?;
-			let neighbour_string=self.neighbour_router_iter(router_index).map(|item|item.neighbour_router.to_string()).collect::<Vec<String>>().join(" ");
-			writeln!(file,"{}",neighbour_string)?;
+			0 =>
+			{
+				writeln!(file,"NODOS {}",n)?;
+				writeln!(file,"GRADO {}",self.maximum_degree())?;
+				//for (router_index,neighbour_list) in self.list.iter().enumerate()
+				for router_index in 0..n
+				{
+					writeln!(file,"N {}",router_index)?;
+					let neighbour_string=self.neighbour_router_iter(router_index).map(|item|item.neighbour_router.to_string()).collect::<Vec<String>>().join(" ");
+					writeln!(file,"{}",neighbour_string)?;
+				}
+			},
+			1 =>
+			{
+				writeln!(file,"<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+				writeln!(file,"<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+				writeln!(file,"  <key id=\"servers\" for=\"node\" attr.name=\"servers\" attr.type=\"int\"/>")?;
+				writeln!(file,"  <key id=\"link_class\" for=\"edge\" attr.name=\"link_class\" attr.type=\"int\"/>")?;
+				writeln!(file,"  <graph id=\"G\" edgedefault=\"undirected\">")?;
+				for router_index in 0..n
+				{
+					let servers=self.ports(router_index)-self.degree(router_index);
+					writeln!(file,"    <node id=\"{}\"><data key=\"servers\">{}</data></node>",router_index,servers)?;
+				}
+				for (router_index,neighbour,link_class) in links()
+				{
+					writeln!(file,"    <edge source=\"{}\" target=\"{}\"><data key=\"link_class\">{}</data></edge>",router_index,neighbour,link_class)?;
+				}
+				writeln!(file,"  </graph>")?;
+				writeln!(file,"</graphml>")?;
+			},
+			2 =>
+			{
+				writeln!(file,"graph G {{")?;
+				for router_index in 0..n
+				{
+					let servers=self.ports(router_index)-self.degree(router_index);
+					writeln!(file,"\t{} [servers={}];",router_index,servers)?;
+				}
+				for (router_index,neighbour,link_class) in links()
+				{
+					writeln!(file,"\t{} -- {} [link_class={}];",router_index,neighbour,link_class)?;
+				}
+				writeln!(file,"}}")?;
+			},
+			_ => panic!("Unknown format {} to write the adjacencies. Use 0 for the plain format, 1 for GraphML, or 2 for DOT.",format),
 		}
 		Ok(())
 	}
@@ -748,6 +1073,31 @@ Hamming{
 }
 ```
 
+### ExpressMesh example
+An [ExpressMesh] is a mesh with additional links joining routers at greater distances along each dimension. Here a 8x8 mesh with links of length 4 in the first dimension and of lengths 2 and 4 in the second one.
+Each express level has its own link class, so this example requires 6 link classes.
+```ignore
+ExpressMesh{
+	sides: [8,8],
+	express: [[4],[2,4]],
+	//wrap_around: true,//to use a torus as base
+	servers_per_router:1,
+	legend_name: "A 8x8 mesh with express links",
+}
+```
+
+### TwistedTorus example
+A [TwistedTorus] is a torus whose wrap-around links arrive shifted in the preceding dimensions. Here the rectangular twisted torus of 8x4 routers, in which going around the second dimension advances 4 positions in the first one.
+It has diameter 4 instead of the 6 of the 8x4 torus. `RectangularTwistedTorus{side:4,servers_per_router:1}` builds the same, and `PrismaticDoublyTwistedTorus{side:4,servers_per_router:1}` builds a 8x4x4 one twisted in two dimensions.
+```ignore
+TwistedTorus{
+	sides: [8,4],
+	twists: [[],[4]],
+	servers_per_router:1,
+	legend_name: "A 8x4 twisted torus",
+}
+```
+
 
 ## Topologies given by lists of neighbours.
 
@@ -764,15 +1114,86 @@ RandomRegularGraph{
 
 ### File example
 A [file](NeighboursLists) can be load as topology. This can be useful to keep a specific random graph without need to care about using the same RNG seed. It can also be used to simulate topologies generated by other software.
+The `format` is 0 for the plain format written by [Topology::write_adjacencies_to_file] and 1 for GraphML, which allows to exchange topologies with external graph tools.
+In GraphML the nodes are numbered in their order of appearance and the edges are undirected. When `servers_per_router` is not given, the number of servers of each node is taken from its `servers` data.
 ```ignore
 File{
 	filename: "/path/to/my/topology/file",
-	format: 0,//TODO: this needs documentation...
+	format: 0,
 	servers_per_router: 5,
 	legend_name: "some network in the device",
 }
 ```
 
+### Classic graphs
+Some classic graphs are built as [lists of neighbours](NeighboursLists), mainly to serve as analytic baselines.
+The directed Kautz and de Bruijn graphs are made undirected by merging pairs of opposite arcs and removing loops, so some routers have less than `2*degree` neighbours.
+```ignore
+CompleteBipartite{
+	left: 8,
+	right: 8,
+	servers_per_router: 4,
+	legend_name: "K_{8,8}",
+}
+Kautz{
+	degree: 3,//out-degree of the directed graph
+	length: 3,//length of the words, which is the directed diameter
+	servers_per_router: 3,
+	legend_name: "Kautz K(3,3)",
+}
+DeBruijn{
+	degree: 2,
+	length: 6,
+	servers_per_router: 2,
+	legend_name: "de Bruijn B(2,6)",
+}
+```
+
+### Irregular random graphs
+Some models of random graphs are built as [lists of neighbours](NeighboursLists), to research on irregular topologies.
+`RandomErdosRenyi` links each pair of routers with a given `probability`. `WattsStrogatz` builds a small-world graph by rewiring with some probability the links of a ring lattice of a given even `degree`.
+`BarabasiAlbert` builds a scale-free graph by adding routers with `links` links each, chosen with preferential attachment.
+They are generated again while they are not connected, up to `connectivity_retries` times.
+By default each router has `servers_per_router` servers, but the `server_attachment` can attach them `Proportional` to the degree of the routers, or fill the ports up to a given `Radix{radix}`.
+```ignore
+RandomErdosRenyi{
+	routers: 100,
+	probability: 0.1,
+	servers_per_router: 4,
+	connectivity_retries: 10,//optional, by default 100
+	legend_name: "Erdos-Renyi G(100,0.1)",
+}
+WattsStrogatz{
+	routers: 100,
+	degree: 8,
+	rewiring: 0.2,
+	servers_per_router: 4,
+	server_attachment: Proportional,//optional, by default Uniform
+	legend_name: "small-world graph",
+}
+BarabasiAlbert{
+	routers: 100,
+	links: 3,
+	server_attachment: Radix{radix:24},
+	legend_name: "scale-free graph",
+}
+```
+
+### Jellyfish
+A [Jellyfish](NeighboursLists) network links the routers at random, as in a random regular graph, but each router may have a different number of ports towards other routers and it may be expanded incrementally.
+The `degrees` give the ports towards routers of each router, or alternatively `routers` and `degree` give the same degree to all of them. The ports of each router for servers are given apart, by `server_ports`, or by `servers_per_router` together with the `server_attachment`.
+The routers in `expansion` are added one at a time after the initial construction, each one breaking random links to attach to their endpoints. This models the growth of a deployed network, in which most of the cables are kept.
+With a `seed` the graph is built with its own random generator, so it does not depend on the simulation seed, and an expanded network keeps the links of the network without the expansion except those broken by the new routers.
+```ignore
+Jellyfish{
+	degrees: [10,10,10,10,12,12,12,12,...],//or routers: 100, degree: 10,
+	expansion: [10,10,10,10],//optional, four routers added incrementally
+	server_ports: [6,6,6,6,4,4,4,4,...],//or servers_per_router: 5, with one entry for each router, including the expansion.
+	seed: 7,//optional
+	legend_name: "Jellyfish",
+}
+```
+
 ## Dragonfly networks.
 The `global_ports_per_router` was denotated `h` in the original article of the [Dragonfly].
 The number of servers per router can be varied, but recommended to the same value as `global_ports_per_router`.
@@ -826,6 +1247,7 @@ The [SlimFly] is the MMS (Mirka--Miller--Siran) graph. For `prime=5` it is the H
 Has Paley graphs as subgraph, or similar depending on whether the prime is congruent to what modulo 4. has diameter 2.
 Note the links in the (quasi)-Paley graph (which we can call local links) are used in a slightly different amount to the other links.
 This slightly reduces the delivered throughput.
+The field may be of size `q=prime^exponent`, with the optional `exponent` defaulting to 1. Prime powers use polynomial arithmetic over GF(prime^exponent), which allows, for example, sizes `q=2^k`.
 ```ignore
 SlimFly{
 	prime: 19,
@@ -833,6 +1255,12 @@ SlimFly{
 	servers_per_router:9,
 	legend_name: "Slimfly MMS over q=19",
 }
+SlimFly{
+	prime: 2,
+	exponent: 3,//The field has 8 elements.
+	servers_per_router:6,
+	legend_name: "Slimfly MMS over q=8",
+}
 ```
 
 ## Multi-stage networks.
@@ -912,6 +1340,39 @@ RemappedServers{
 }
 ```
 
+### Concentration
+
+[ConcentrationTopology](operations::ConcentrationTopology) sets the number of servers of each router of a base topology, which may differ among routers.
+```ignore
+Concentration{
+	topology: Hamming{sides:[2,2],servers_per_router:0},
+	servers: [4,2,2,0],
+}
+```
+
+### MultiRail
+
+[MultiRailTopology](operations::MultiRailTopology) gives each server several ports, or rails, by grouping the servers of a base topology.
+With `Contiguous` grouping the rails of a server are consecutive base servers, so usually in the same router. With `Interleaved` they are spread among distant routers.
+```ignore
+MultiRail{
+	topology: Hamming{sides:[4,4],servers_per_router:4},
+	rails: 2,
+	grouping: Interleaved,//optional, defaults to Contiguous
+}
+```
+
+### MultiPlane
+
+[MultiPlaneTopology](operations::MultiPlaneTopology) builds several independent copies, or planes, of a base topology, with each server having a rail into each plane.
+The plane of each packet is chosen at injection according to the `rail_selection` of the configuration.
+```ignore
+MultiPlane{
+	plane: Hamming{sides:[4,4],servers_per_router:4},
+	planes: 2,
+}
+```
+
 ## AsCartesianTopology
 [AsCartesianTopology] provides a topology with a given representation as a block with Cartesian coordinates.
 
@@ -925,34 +1386,45 @@ AsCartesianTopology{
 
 */
 pub fn new_topology(arg:TopologyBuilderArgument) -> Box<dyn Topology>
+{
+	try_new_topology(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///Like [new_topology] but returning an error instead of panicking when the topology cannot be built from its configuration.
+pub fn try_new_topology(arg:TopologyBuilderArgument) -> Result<Box<dyn Topology>,Error>
 {
 	if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
 	{
 		if let Some(builder) = arg.plugs.topologies.get(cv_name)
 		{
-			return builder(arg);
+			return Ok(builder(arg));
 		}
-		match cv_name.as_ref()
+		Ok(match cv_name.as_ref()
 		{
-			"Mesh" => Box::new(Mesh::new(arg.cv)),
-			"Torus" => Box::new(Torus::new(arg.cv)),
-			"RandomRegularGraph" | "File" => Box::new(NeighboursLists::new_cfg(arg.cv,arg.rng)),
-			"Hamming" => Box::new(Hamming::new(arg.cv)),
-			"Dragonfly" | "CanonicDragonfly" => Box::new(Dragonfly::new(arg)),
-			"Projective" => Box::new(Projective::new(arg)),
-			"LeviProjective" => Box::new(LeviProjective::new(arg)),
-			"SlimFly" => Box::new(SlimFly::new(arg)),
-			"MultiStage" | "XGFT" | "OFT" | "RFC" => Box::new(MultiStage::new(arg)),
-			"Megafly" => Box::new(megafly::Megafly::new(arg)),
-			"RemappedServers" => Box::new(operations::RemappedServersTopology::new(arg)),
-			"AsCartesianTopology" => Box::new(AsCartesianTopology::new(arg)),
-			"RandomLinkFaults" => Box::new(operations::RandomLinkFaults::new(arg)),
-			_ => panic!("Unknown topology {}",cv_name),
-		}
+			"Mesh" => Box::new(Mesh::new(arg.cv)?),
+			"Torus" => Box::new(Torus::new(arg.cv)?),
+			"RandomRegularGraph" | "File" | "CompleteBipartite" | "Kautz" | "DeBruijn" | "RandomErdosRenyi" | "WattsStrogatz" | "BarabasiAlbert" | "Jellyfish" => Box::new(NeighboursLists::new_cfg(arg.cv,arg.rng)?),
+			"Hamming" => Box::new(Hamming::new(arg.cv,arg.rng)?),
+			"ExpressMesh" => Box::new(ExpressMesh::new(arg.cv)?),
+			"TwistedTorus" | "RectangularTwistedTorus" | "PrismaticDoublyTwistedTorus" => Box::new(TwistedTorus::new(arg.cv)?),
+			"Dragonfly" | "CanonicDragonfly" => Box::new(Dragonfly::new(arg)?),
+			"Projective" => Box::new(Projective::new(arg)?),
+			"LeviProjective" => Box::new(LeviProjective::new(arg)?),
+			"SlimFly" => Box::new(SlimFly::new(arg)?),
+			"MultiStage" | "XGFT" | "OFT" | "RFC" => Box::new(MultiStage::new(arg)?),
+			"Megafly" => Box::new(megafly::Megafly::new(arg)?),
+			"RemappedServers" => Box::new(operations::RemappedServersTopology::new(arg)?),
+			"Concentration" => Box::new(operations::ConcentrationTopology::new(arg)?),
+			"MultiRail" => Box::new(operations::MultiRailTopology::new(arg)?),
+			"MultiPlane" => Box::new(operations::MultiPlaneTopology::new(arg)?),
+			"AsCartesianTopology" => Box::new(AsCartesianTopology::new(arg)?),
+			"RandomLinkFaults" => Box::new(operations::RandomLinkFaults::new(arg)?),
+			_ => return Err(arg.cv.ill(&format!("Unknown topology {}",cv_name))),
+		})
 	}
 	else
 	{
-		panic!("Trying to create a topology from a non-Object");
+		Err(arg.cv.ill("Trying to create a topology from a non-Object"))
 	}
 }
 
@@ -1326,6 +1326,23 @@ impl Topology for MultiStage
 		//*self.up_down_distances.get(origin,destination)
 		self.up_down_distances.get(origin,destination).map(|(u,d)|(u.into(),d.into()))
 	}
+	///The routers in the level 0 are `leaf`, those in the topmost level are `spine`, and those in the middle `level1`, `level2`, and so on.
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		let (level,_offset) = self.unpack(router_index);
+		if level==0
+		{
+			Some(String::from("leaf"))
+		}
+		else if level==self.stages.len()
+		{
+			Some(String::from("spine"))
+		}
+		else
+		{
+			Some(format!("level{}",level))
+		}
+	}
 }
 
 impl MultiStage
@@ -1471,7 +1488,7 @@ impl MultiStage
 	{
 		offset + self.routers_per_level.iter().take(level_index).sum::<usize>()
 	}
-	pub fn new(arg:TopologyBuilderArgument) -> MultiStage
+	pub fn new(arg:TopologyBuilderArgument) -> Result<MultiStage,Error>
 	{
 		let stages;
 		let mut servers_per_leaf=None;
@@ -1489,18 +1506,18 @@ impl MultiStage
 							"stages" => match value
 							{
 								&ConfigurationValue::Array(ref a) => got_stages=Some(a.iter().map(|cv|new_stage(StageBuilderArgument{cv,plugs:arg.plugs,rng:arg.rng})).collect()),
-								_ => panic!("bad value for stages"),
+								_ => return Err(value.ill("bad value for stages")),
 							},
 							"servers_per_leaf" => match value
 							{
 								&ConfigurationValue::Number(f) => servers_per_leaf=Some(f as usize),
-								_ => panic!("bad value for servers_per_leaf"),
+								_ => return Err(value.ill("bad value for servers_per_leaf")),
 							},
 							"legend_name" => (),
-							_ => panic!("Nothing to do with field {} in MultiStage",name),
+							_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in MultiStage",name))),
 						}
 					}
-					stages=got_stages.expect("There were no stages");
+					stages=got_stages.ok_or_else(||arg.cv.ill("There were no stages"))?;
 				},
 				"XGFT" =>
 				{
@@ -1514,7 +1531,7 @@ impl MultiStage
 							"height" => match value
 							{
 								&ConfigurationValue::Number(f) => height=Some(f as usize),
-								_ => panic!("bad value for height"),
+								_ => return Err(value.ill("bad value for height")),
 							},
 							"down" => match value
 							{
@@ -1523,7 +1540,7 @@ impl MultiStage
 									&ConfigurationValue::Number(f) => f as usize,
 									_ => panic!("bad value in down"),
 								}).collect()),
-								_ => panic!("bad value for down"),
+								_ => return Err(value.ill("bad value for down")),
 							},
 							"up" => match value
 							{
@@ -1531,27 +1548,27 @@ impl MultiStage
 									&ConfigurationValue::Number(f) => f as usize,
 									_ => panic!("bad value in up"),
 								}).collect()),
-								_ => panic!("bad value for up"),
+								_ => return Err(value.ill("bad value for up")),
 							},
 							"servers_per_leaf" => match value
 							{
 								&ConfigurationValue::Number(f) => servers_per_leaf=Some(f as usize),
-								_ => panic!("bad value for servers_per_leaf"),
+								_ => return Err(value.ill("bad value for servers_per_leaf")),
 							},
 							"legend_name" => (),
-							_ => panic!("Nothing to do with field {} in XGFT",name),
+							_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in XGFT",name))),
 						}
 					}
-					let height=height.expect("There were no height");
-					let down=down.expect("There were no down");
-					let up=up.expect("There were no up");
+					let height=height.ok_or_else(||arg.cv.ill("There were no height"))?;
+					let down=down.ok_or_else(||arg.cv.ill("There were no down"))?;
+					let up=up.ok_or_else(||arg.cv.ill("There were no up"))?;
 					if height!=down.len()
 					{
-						panic!("down does not match length");
+						return Err(arg.cv.ill("down does not match length"));
 					}
 					if height!=up.len()
 					{
-						panic!("up does not match length");
+						return Err(arg.cv.ill("up does not match length"));
 					}
 					stages=(0..height).map(|index|Box::new(FatStage{bottom_factor:down[index],top_factor:up[index]}) as Box<dyn Stage>).collect();
 				}
@@ -1568,35 +1585,35 @@ impl MultiStage
 							"height" => match value
 							{
 								&ConfigurationValue::Number(f) => height=Some(f as usize),
-								_ => panic!("bad value for height"),
+								_ => return Err(value.ill("bad value for height")),
 							},
 							"prime" => match value
 							{
 								&ConfigurationValue::Number(f) => prime=Some(f as usize),
-								_ => panic!("bad value for prime"),
+								_ => return Err(value.ill("bad value for prime")),
 							},
 							"servers_per_leaf" => match value
 							{
 								&ConfigurationValue::Number(f) => servers_per_leaf=Some(f as usize),
-								_ => panic!("bad value for servers_per_leaf"),
+								_ => return Err(value.ill("bad value for servers_per_leaf")),
 							},
 							"double_topmost_level" => match value
 							{
 								&ConfigurationValue::True => double_topmost_level=true,
 								&ConfigurationValue::False => double_topmost_level=false,
-								_ => panic!("bad value for double_topmost_level"),
+								_ => return Err(value.ill("bad value for double_topmost_level")),
 							},
 							"redundant" => match value
 							{
 								&ConfigurationValue::Number(f) => redundant=f as usize,
-								_ => panic!("bad value for redundant"),
+								_ => return Err(value.ill("bad value for redundant")),
 							},
 							"legend_name" => (),
-							_ => panic!("Nothing to do with field {} in OFT",name),
+							_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in OFT",name))),
 						}
 					}
-					let height=height.expect("There were no height");
-					let prime=prime.expect("There were no prime");
+					let height=height.ok_or_else(||arg.cv.ill("There were no height"))?;
+					let prime=prime.ok_or_else(||arg.cv.ill("There were no prime"))?;
 					// let redundant = redundant.expect("There was no redundant");
 
 					stages=(0..height).map(|index|{
@@ -1632,25 +1649,25 @@ impl MultiStage
 							"k" => match value
 							{
 								&ConfigurationValue::Number(f) => k=Some(f as usize),
-								_ => panic!("bad value for k"),
+								_ => return Err(value.ill("bad value for k")),
 							},
 							"pods" => match value
 							{
 								&ConfigurationValue::Number(f) => pods=Some(f as usize),
-								_ => panic!("bad value for pods"),
+								_ => return Err(value.ill("bad value for pods")),
 							},
 							"servers_per_leaf" => match value
 							{
 								&ConfigurationValue::Number(f) => servers_per_leaf=Some(f as usize),
-								_ => panic!("bad value for servers_per_leaf"),
+								_ => return Err(value.ill("bad value for servers_per_leaf")),
 							},
 							"legend_name" => (),
-							_ => panic!("Nothing to do with field {} in MLFM",name),
+							_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in MLFM",name))),
 						}
 					}
 
-					let k=k.expect("There were no k");
-					let pods=pods.expect("There were no pods");
+					let k=k.ok_or_else(||arg.cv.ill("There were no k"))?;
+					let pods=pods.ok_or_else(||arg.cv.ill("There were no pods"))?;
 					//let servers_per_leaf=servers_per_leaf.expect("Not working");
 
 					let stage = FMStage{
@@ -1692,7 +1709,7 @@ impl MultiStage
 							"height" => match value
 							{
 								&ConfigurationValue::Number(f) => height=Some(f as usize),
-								_ => panic!("bad value for height"),
+								_ => return Err(value.ill("bad value for height")),
 							},
 							"sizes" => match value
 							{
@@ -1701,7 +1718,7 @@ impl MultiStage
 									&ConfigurationValue::Number(f) => f as usize,
 									_ => panic!("bad value in sizes"),
 								}).collect()),
-								_ => panic!("bad value for sizes"),
+								_ => return Err(value.ill("bad value for sizes")),
 							},
 							"down" => match value
 							{
@@ -1710,7 +1727,7 @@ impl MultiStage
 									&ConfigurationValue::Number(f) => f as usize,
 									_ => panic!("bad value in down"),
 								}).collect()),
-								_ => panic!("bad value for down"),
+								_ => return Err(value.ill("bad value for down")),
 							},
 							"up" => match value
 							{
@@ -1718,32 +1735,32 @@ impl MultiStage
 									&ConfigurationValue::Number(f) => f as usize,
 									_ => panic!("bad value in up"),
 								}).collect()),
-								_ => panic!("bad value for up"),
+								_ => return Err(value.ill("bad value for up")),
 							},
 							"servers_per_leaf" => match value
 							{
 								&ConfigurationValue::Number(f) => servers_per_leaf=Some(f as usize),
-								_ => panic!("bad value for servers_per_leaf"),
+								_ => return Err(value.ill("bad value for servers_per_leaf")),
 							},
 							"legend_name" => (),
-							_ => panic!("Nothing to do with field {} in RFC",name),
+							_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in RFC",name))),
 						}
 					}
-					let height=height.expect("There were no height");
-					let sizes=sizes.expect("There were no sizes");
-					let down=down.expect("There were no down");
-					let up=up.expect("There were no up");
+					let height=height.ok_or_else(||arg.cv.ill("There were no height"))?;
+					let sizes=sizes.ok_or_else(||arg.cv.ill("There were no sizes"))?;
+					let down=down.ok_or_else(||arg.cv.ill("There were no down"))?;
+					let up=up.ok_or_else(||arg.cv.ill("There were no up"))?;
 					if height!=down.len()
 					{
-						panic!("down does not match length");
+						return Err(arg.cv.ill("down does not match length"));
 					}
 					if height!=up.len()
 					{
-						panic!("up does not match length");
+						return Err(arg.cv.ill("up does not match length"));
 					}
 					if height+1!=sizes.len()
 					{
-						panic!("sizes does not match length+1");
+						return Err(arg.cv.ill("sizes does not match length+1"));
 					}
 					stages=(0..height).map(|index|{
 						let bottom_size=sizes[index];
@@ -1759,9 +1776,9 @@ impl MultiStage
 		}
 		else
 		{
-			panic!("Trying to create a MultiStage from a non-Object");
+			return Err(arg.cv.ill("Trying to create a MultiStage from a non-Object"));
 		}
-		let servers_per_leaf=servers_per_leaf.expect("There were no servers_per_leaf");
+		let servers_per_leaf=servers_per_leaf.ok_or_else(||arg.cv.ill("There were no servers_per_leaf"))?;
 		let mut network = MultiStage{
 			stages,
 			servers_per_leaf,
@@ -1773,7 +1790,7 @@ impl MultiStage
 			flat_distance_matrix: Matrix::constant(0,0,0),
 		};
 		network.initialize();
-		network
+		Ok(network)
 	}
 }
 
@@ -1994,7 +2011,7 @@ impl Routing for UpDownDerouting
 
 impl UpDownDerouting
 {
-	pub fn new(arg:RoutingBuilderArgument) -> UpDownDerouting
+	pub fn new(arg:RoutingBuilderArgument) -> Result<UpDownDerouting,Error>
 	{
 		let mut allowed_updowns=None;
 		let mut stages = 1usize;
@@ -2005,7 +2022,7 @@ impl UpDownDerouting
 		{
 			if cv_name!="UpDownDerouting"
 			{
-				panic!("A UpDownDerouting must be created from a `UpDownDerouting` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A UpDownDerouting must be created from a `UpDownDerouting` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -2015,7 +2032,7 @@ impl UpDownDerouting
 					"allowed_updowns" => match value
 					{
 						&ConfigurationValue::Number(f) => allowed_updowns =Some(f as usize),
-						_ => panic!("bad value for allowed_deroutes"),
+						_ => return Err(value.ill("bad value for allowed_deroutes")),
 					}
 					"stages" => match value {
 						&ConfigurationValue::Number(f) => stages = f as usize,
@@ -2032,15 +2049,15 @@ impl UpDownDerouting
 						_ => panic!("bad value for include_labels"),
 					}*/
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in UpDownDerouting",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in UpDownDerouting",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a UpDownDerouting from a non-Object");
+			return Err(arg.cv.ill("Trying to create a UpDownDerouting from a non-Object"));
 		}
-		let allowed_updowns= allowed_updowns.expect("There were no allowed_deroutes");
+		let allowed_updowns= allowed_updowns.ok_or_else(||arg.cv.ill("There were no allowed_deroutes"))?;
 
 		let virtual_channels = match virtual_channels {
 			Some( v) => v,
@@ -2052,10 +2069,10 @@ impl UpDownDerouting
 
 
 		//let include_labels=include_labels.expect("There were no include_labels");
-		UpDownDerouting {
+		Ok(UpDownDerouting {
 			allowed_updowns,
 			virtual_channels,
 			stages
-		}
+		})
 	}
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 
 use std::collections::BTreeSet;
 use std::fs::File;
-use std::io::{BufRead,BufReader};
+use std::io::{BufRead,BufReader,Read};
 
 use ::rand::{Rng,rngs::StdRng};
 use quantifiable_derive::Quantifiable;//the derive macro
@@ -9,11 +9,15 @@ use super::{Topology,Location};
 use super::cartesian::CartesianData;
 use crate::config_parser::ConfigurationValue;
 use crate::matrix::Matrix;
+use crate::error::Error;
 
 ///A topology based on having sotred the list of neighbours to each router.
 ///It is used
 ///* to load a topology from a file (topology=File)
-///* and to create a topology with random links (topology=RandomRegularGraph).
+///* to create a topology with random links (topology=RandomRegularGraph)
+///* to create some classic graphs (topology=CompleteBipartite, topology=Kautz, topology=DeBruijn)
+///* to create irregular random graphs (topology=RandomErdosRenyi, topology=WattsStrogatz, topology=BarabasiAlbert).
+///* and to create Jellyfish networks, possibly with non-uniform degrees and expanded incrementally (topology=Jellyfish).
 #[derive(Quantifiable)]
 #[derive(Debug)]
 pub struct NeighboursLists
@@ -317,6 +321,311 @@ impl NeighboursLists
 		}
 		adj
 	}
+	///Build the adjacencies of the complete bipartite graph `K_{left,right}`.
+	///The first `left` routers are only connected to the last `right` routers.
+	pub fn new_complete_bipartite_adj(left:usize, right:usize) -> Vec<Vec<usize>>
+	{
+		(0..left+right).map(|router|
+			if router<left { (left..left+right).collect() } else { (0..left).collect() }
+		).collect()
+	}
+	///Build the adjacencies of the Kautz graph `K(degree,length)`. Its vertices are the words of `length` symbols from an alphabet of `degree+1` symbols,
+	///with no two consecutive symbols being equal. There is an arc from `s_1 s_2 ... s_D` to `s_2 ... s_D x` for each `x != s_D`.
+	///Each arc becomes a link, by merging pairs of opposite arcs. Hence each router has at most `2*degree` neighbours.
+	pub fn new_kautz_adj(degree:usize, length:usize) -> Vec<Vec<usize>>
+	{
+		assert!(degree>=1 && length>=1, "Kautz graphs require positive degree and length.");
+		let alphabet = degree+1;
+		//All the words of the given length, with their index being the router index.
+		let mut words : Vec<Vec<usize>> = (0..alphabet).map(|x|vec![x]).collect();
+		for _ in 1..length
+		{
+			words = words.into_iter().flat_map(|word|{
+				let last = *word.last().unwrap();
+				(0..alphabet).filter(move |&x|x!=last).map(move |x|{
+					let mut next = word.clone();
+					next.push(x);
+					next
+				})
+			}).collect();
+		}
+		let index : std::collections::HashMap<Vec<usize>,usize> = words.iter().enumerate().map(|(i,w)|(w.clone(),i)).collect();
+		let arcs = words.iter().map(|word|{
+			let last = *word.last().unwrap();
+			(0..alphabet).filter(|&x|x!=last).map(|x|{
+				let mut next : Vec<usize> = word[1..].to_vec();
+				next.push(x);
+				index[&next]
+			}).collect()
+		}).collect();
+		Self::undirected_adj(arcs)
+	}
+	///Build the adjacencies of the de Bruijn graph `B(degree,length)`. Its vertices are the `degree^length` words of `length` symbols from an alphabet of `degree` symbols.
+	///There is an arc from `s_1 s_2 ... s_D` to `s_2 ... s_D x` for each symbol `x`.
+	///Each arc becomes a link, by merging pairs of opposite arcs and removing loops. Hence each router has at most `2*degree` neighbours.
+	pub fn new_de_bruijn_adj(degree:usize, length:usize) -> Vec<Vec<usize>>
+	{
+		assert!(degree>=2 && length>=1, "de Bruijn graphs require degree at least 2 and positive length.");
+		let n = degree.pow(length as u32);
+		let arcs = (0..n).map(|word|
+			(0..degree).map(|x|(word*degree+x)%n).collect()
+		).collect();
+		Self::undirected_adj(arcs)
+	}
+	///Build the adjacencies of an Erdős–Rényi graph `G(routers,probability)`, in which each pair of routers is linked with the given probability.
+	pub fn new_erdos_renyi_adj(routers:usize, probability:f64, rng: &mut StdRng) -> Vec<Vec<usize>>
+	{
+		assert!((0.0..=1.0).contains(&probability), "The probability of RandomErdosRenyi must be in [0,1].");
+		let mut adj=vec![vec![];routers];
+		for origin in 0..routers
+		{
+			for target in origin+1..routers
+			{
+				if rng.gen::<f64>() < probability
+				{
+					adj[origin].push(target);
+					adj[target].push(origin);
+				}
+			}
+		}
+		adj
+	}
+	///Build the adjacencies of a Watts–Strogatz small-world graph. It begins with a ring in which each router is linked to the `degree/2` nearest routers at each side.
+	///Then each of these links is rewired with probability `rewiring`, replacing one of its endpoints by a random router, avoiding loops and repeated links.
+	pub fn new_watts_strogatz_adj(routers:usize, degree:usize, rewiring:f64, rng: &mut StdRng) -> Vec<Vec<usize>>
+	{
+		assert!(degree%2==0 && degree<routers, "WattsStrogatz requires an even degree lower than the number of routers.");
+		assert!((0.0..=1.0).contains(&rewiring), "The rewiring of WattsStrogatz must be in [0,1].");
+		let mut adj=vec![BTreeSet::new();routers];
+		for origin in 0..routers
+		{
+			for offset in 1..=degree/2
+			{
+				let target=(origin+offset)%routers;
+				adj[origin].insert(target);
+				adj[target].insert(origin);
+			}
+		}
+		for offset in 1..=degree/2
+		{
+			for origin in 0..routers
+			{
+				let target=(origin+offset)%routers;
+				if adj[origin].len()+1>=routers || rng.gen::<f64>() >= rewiring
+				{
+					continue;
+				}
+				let new_target = loop
+				{
+					let candidate=rng.gen_range(0..routers);
+					if candidate!=origin && !adj[origin].contains(&candidate)
+					{
+						break candidate;
+					}
+				};
+				adj[origin].remove(&target);
+				adj[target].remove(&origin);
+				adj[origin].insert(new_target);
+				adj[new_target].insert(origin);
+			}
+		}
+		adj.into_iter().map(|set|set.into_iter().collect()).collect()
+	}
+	///Build the adjacencies of a Barabási–Albert scale-free graph. It begins with a complete graph of `links+1` routers.
+	///Then each new router is linked to `links` different previous routers, chosen with probability proportional to their degree (preferential attachment).
+	pub fn new_barabasi_albert_adj(routers:usize, links:usize, rng: &mut StdRng) -> Vec<Vec<usize>>
+	{
+		assert!(links>=1 && links<routers, "BarabasiAlbert requires a positive number of links lower than the number of routers.");
+		let mut adj=vec![vec![];routers];
+		//Each router appears once for each of its links, so sampling from it is proportional to the degree.
+		let mut endpoints=vec![];
+		for origin in 0..=links
+		{
+			for target in origin+1..=links
+			{
+				adj[origin].push(target);
+				adj[target].push(origin);
+				endpoints.push(origin);
+				endpoints.push(target);
+			}
+		}
+		for origin in links+1..routers
+		{
+			let mut targets=Vec::with_capacity(links);
+			while targets.len()<links
+			{
+				let candidate=endpoints[rng.gen_range(0..endpoints.len())];
+				if !targets.contains(&candidate)
+				{
+					targets.push(candidate);
+				}
+			}
+			for target in targets
+			{
+				adj[origin].push(target);
+				adj[target].push(origin);
+				endpoints.push(origin);
+				endpoints.push(target);
+			}
+		}
+		adj
+	}
+	///Build the adjacencies of a Jellyfish network, a random graph in which each router `i` has `degrees[i]` ports towards other routers.
+	///Random pairs of routers with free ports are linked while possible. Then each router left with at least two free ports takes them by breaking a random link `x--y`,
+	///between routers not yet linked to it, and linking both `x` and `y` to itself. Hence a router may only be left with a single free port.
+	///With all the degrees equal this gives a random regular graph.
+	///Afterwards, the routers with the degrees in `expansion` are added one at a time, see [jellyfish_expand](Self::jellyfish_expand).
+	pub fn new_jellyfish_adj(degrees:&[usize], expansion:&[usize], rng: &mut StdRng) -> Vec<Vec<usize>>
+	{
+		let mut degrees=degrees.to_vec();
+		let mut adj=vec![vec![];degrees.len()];
+		//The links are also kept in a vector to select one at random in constant time.
+		let mut links=vec![];
+		//The routers with free ports that cannot be rewired anymore.
+		let mut exhausted=BTreeSet::new();
+		loop
+		{
+			let open:Vec<usize> = (0..adj.len()).filter(|&router|adj[router].len()<degrees[router]).collect();
+			let mut pair=None;
+			//Random attempts are enough until the last few links.
+			for _ in 0..4*open.len()
+			{
+				let u=open[rng.gen_range(0..open.len())];
+				let v=open[rng.gen_range(0..open.len())];
+				if u!=v && !adj[u].contains(&v)
+				{
+					pair=Some((u,v));
+					break;
+				}
+			}
+			if pair.is_none()
+			{
+				let pairs:Vec<(usize,usize)> = open.iter().enumerate().flat_map(|(index,&u)|open[index+1..].iter().map(move |&v|(u,v)))
+					.filter(|&(u,v)|!adj[u].contains(&v)).collect();
+				if !pairs.is_empty()
+				{
+					pair=Some(pairs[rng.gen_range(0..pairs.len())]);
+				}
+			}
+			if let Some((u,v))=pair
+			{
+				Self::jellyfish_link(&mut adj,&mut links,u,v);
+				continue;
+			}
+			let router = open.iter().copied().find(|&router|degrees[router]-adj[router].len()>=2 && !exhausted.contains(&router));
+			match router
+			{
+				Some(router) => if !Self::jellyfish_rewire(&mut adj,&mut links,router,rng)
+				{
+					exhausted.insert(router);
+				},
+				None => break,
+			}
+		}
+		for &degree in expansion
+		{
+			Self::jellyfish_expand(&mut adj,&mut links,&mut degrees,degree,rng);
+		}
+		adj
+	}
+	///Adds a router with `degree` ports towards other routers to a Jellyfish network, as in its incremental expansion.
+	///First it is linked to random routers with free ports, and then it breaks random links `x--y` to link itself to both `x` and `y`, until it has less than two free ports.
+	///All the other links are kept, so the network can be expanded without rewiring most of its cables.
+	pub fn jellyfish_expand(adj:&mut Vec<Vec<usize>>, links:&mut Vec<(usize,usize)>, degrees:&mut Vec<usize>, degree:usize, rng: &mut StdRng)
+	{
+		let new=adj.len();
+		adj.push(vec![]);
+		degrees.push(degree);
+		loop
+		{
+			if adj[new].len()>=degree
+			{
+				return;
+			}
+			let open:Vec<usize> = (0..new).filter(|&router|adj[router].len()<degrees[router] && !adj[new].contains(&router)).collect();
+			if open.is_empty()
+			{
+				break;
+			}
+			let router=open[rng.gen_range(0..open.len())];
+			Self::jellyfish_link(adj,links,new,router);
+		}
+		while degree-adj[new].len()>=2
+		{
+			if !Self::jellyfish_rewire(adj,links,new,rng)
+			{
+				break;
+			}
+		}
+	}
+	///Breaks a random link `x--y` between routers not linked to `router` and links both `x` and `y` to `router`.
+	///Returns false if there is no such link.
+	fn jellyfish_rewire(adj:&mut [Vec<usize>], links:&mut Vec<(usize,usize)>, router:usize, rng: &mut StdRng) -> bool
+	{
+		let candidates:Vec<usize> = (0..links.len()).filter(|&index|{
+			let (x,y)=links[index];
+			x!=router && y!=router && !adj[router].contains(&x) && !adj[router].contains(&y)
+		}).collect();
+		if candidates.is_empty()
+		{
+			return false;
+		}
+		let (x,y)=links.swap_remove(candidates[rng.gen_range(0..candidates.len())]);
+		adj[x].retain(|&neighbour|neighbour!=y);
+		adj[y].retain(|&neighbour|neighbour!=x);
+		Self::jellyfish_link(adj,links,router,x);
+		Self::jellyfish_link(adj,links,router,y);
+		true
+	}
+	///Links the routers `u` and `v` of a Jellyfish network under construction.
+	fn jellyfish_link(adj:&mut [Vec<usize>], links:&mut Vec<(usize,usize)>, u:usize, v:usize)
+	{
+		adj[u].push(v);
+		adj[v].push(u);
+		links.push((u,v));
+	}
+	///Whether the graph given by the adjacencies is connected.
+	pub fn is_connected_adj(adj:&[Vec<usize>]) -> bool
+	{
+		if adj.is_empty()
+		{
+			return true;
+		}
+		let mut reached=vec![false;adj.len()];
+		reached[0]=true;
+		let mut pending=vec![0];
+		let mut count=1;
+		while let Some(current)=pending.pop()
+		{
+			for &neighbour in adj[current].iter()
+			{
+				if !reached[neighbour]
+				{
+					reached[neighbour]=true;
+					count+=1;
+					pending.push(neighbour);
+				}
+			}
+		}
+		count==adj.len()
+	}
+	///Transforms a list of arcs into a symmetric list of adjacencies, removing loops and repeated links.
+	fn undirected_adj(arcs:Vec<Vec<usize>>) -> Vec<Vec<usize>>
+	{
+		let mut adj = vec![BTreeSet::new();arcs.len()];
+		for (origin,targets) in arcs.into_iter().enumerate()
+		{
+			for target in targets
+			{
+				if origin!=target
+				{
+					adj[origin].insert(target);
+					adj[target].insert(origin);
+				}
+			}
+		}
+		adj.into_iter().map(|set|set.into_iter().collect()).collect()
+	}
 	///Get the adjancecies from a given file.
 	pub fn file_adj(file:&File, _format:usize) -> Vec<Vec<usize>>
 	{
@@ -362,23 +671,125 @@ impl NeighboursLists
 		}
 		adj
 	}
+	///Get the adjacencies from a GraphML file, together with the number of servers of each node when all of them have a `servers` data.
+	///The nodes are numbered in their order of appearance and the edges are taken as undirected.
+	pub fn graphml_adj(file:&File) -> (Vec<Vec<usize>>,Option<Vec<usize>>)
+	{
+		let mut content=String::new();
+		BufReader::new(file).read_to_string(&mut content).expect("Some problem when reading the topology.");
+		let mut node_index=std::collections::HashMap::new();
+		let mut servers=vec![];
+		let mut edges=vec![];
+		//The key of the `servers` data, which may differ from its name.
+		let mut servers_key=String::from("servers");
+		let mut rest=content.as_str();
+		while let Some(start)=rest.find('<')
+		{
+			rest=&rest[start+1..];
+			let end=rest.find('>').expect("Unclosed tag in GraphML file");
+			let tag=&rest[..end];
+			rest=&rest[end+1..];
+			let name=tag.split(|c:char|c.is_whitespace()||c=='/').next().unwrap_or("");
+			match name
+			{
+				"key" if graphml_attribute(tag,"attr.name").as_deref()==Some("servers") =>
+				{
+					servers_key=graphml_attribute(tag,"id").expect("GraphML key without id");
+				},
+				"node" =>
+				{
+					let id=graphml_attribute(tag,"id").expect("GraphML node without id");
+					let index=node_index.len();
+					node_index.insert(id,index);
+					servers.push(None);
+				},
+				"data" if !servers.is_empty() && graphml_attribute(tag,"key").as_ref()==Some(&servers_key) =>
+				{
+					let value=&rest[..rest.find('<').expect("Unclosed data in GraphML file")];
+					*servers.last_mut().unwrap()=Some(value.trim().parse::<usize>().expect("bad value for servers in GraphML file"));
+				},
+				"edge" =>
+				{
+					let source=graphml_attribute(tag,"source").expect("GraphML edge without source");
+					let target=graphml_attribute(tag,"target").expect("GraphML edge without target");
+					edges.push((source,target));
+				},
+				"/graph" => break,
+				_ => (),
+			}
+		}
+		let mut adj=vec![vec![];node_index.len()];
+		for (source,target) in edges
+		{
+			let source=*node_index.get(&source).unwrap_or_else(||panic!("GraphML edge from unknown node {}",source));
+			let target=*node_index.get(&target).unwrap_or_else(||panic!("GraphML edge to unknown node {}",target));
+			adj[source].push(target);
+			if source!=target
+			{
+				adj[target].push(source);
+			}
+		}
+		(adj,servers.into_iter().collect())
+	}
 	///Build a new NeighboursLists from a ConfigurationValue.
 	/// * severs_per_router
 	/// * legend_name: optionally for generating output.
 	///File topologies use
 	/// * filename: for importing from a file
-	/// * format: format of the improted filename.
+	/// * format: format of the improted filename. 0 for the plain format and 1 for GraphML.
 	///RandomRegularGraph topologies use
 	/// * routers: the total number of routers.
 	/// * degree: the degree, ports towards other routers.
-	pub fn new_cfg(cv:&ConfigurationValue, rng: &mut StdRng) -> NeighboursLists
+	///
+	///CompleteBipartite topologies use
+	/// * left, right: the number of routers in each part.
+	///
+	///Kautz and DeBruijn topologies use
+	/// * degree: the out-degree of the directed graph.
+	/// * length: the length of the words representing the vertices, which is the diameter of the directed graph.
+	///
+	///RandomErdosRenyi topologies use
+	/// * routers: the total number of routers.
+	/// * probability: the probability of each pair of routers to be linked.
+	///
+	///WattsStrogatz topologies use
+	/// * routers: the total number of routers.
+	/// * degree: the even degree of the initial ring lattice.
+	/// * rewiring: the probability of rewiring each link.
+	///
+	///BarabasiAlbert topologies use
+	/// * routers: the total number of routers.
+	/// * links: the number of links of each router added to the graph.
+	///
+	///Jellyfish topologies use
+	/// * either `routers` and `degree`, or `degrees`: the ports towards other routers of each router, which may be non-uniform.
+	/// * expansion: optionally, the degrees of the routers to add incrementally after the initial construction.
+	/// * server_ports: optionally, instead of `servers_per_router`, the number of servers of each router, including the added ones.
+	/// * seed: optionally, to build the graph with its own random generator instead of the one of the simulation.
+	///
+	///The random graphs RandomErdosRenyi, WattsStrogatz, BarabasiAlbert, and Jellyfish are generated again while they are not connected, up to `connectivity_retries` times (100 by default).
+	///
+	///All of them accept a `server_attachment`, see [ServerAttachment].
+	pub fn new_cfg(cv:&ConfigurationValue, rng: &mut StdRng) -> Result<NeighboursLists,Error>
 	{
 		let mut routers=None;
 		let mut degree=None;
 		let mut servers_per_router=None;
 		let mut filename=None;
 		let mut format=None;
-		enum Kind { RandomRegularGraph, File }
+		let mut left=None;
+		let mut right=None;
+		let mut length=None;
+		let mut probability=None;
+		let mut rewiring=None;
+		let mut links=None;
+		let mut connectivity_retries=100;
+		let mut degrees=None;
+		let mut expansion=vec![];
+		let mut server_ports=None;
+		let mut seed=None;
+		let mut server_attachment=ServerAttachment::Uniform;
+		enum Kind { RandomRegularGraph, File, CompleteBipartite, Kautz, DeBruijn, RandomErdosRenyi, WattsStrogatz, BarabasiAlbert, Jellyfish }
 		let kind;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=cv
 		{
@@ -390,7 +801,14 @@ impl NeighboursLists
 			{
 				"RandomRegularGraph" => Kind::RandomRegularGraph,
 				"File" => Kind::File,
-				_ => panic!("Unknown topology {}",cv_name),
+				"CompleteBipartite" => Kind::CompleteBipartite,
+				"Kautz" => Kind::Kautz,
+				"DeBruijn" => Kind::DeBruijn,
+				"RandomErdosRenyi" => Kind::RandomErdosRenyi,
+				"WattsStrogatz" => Kind::WattsStrogatz,
+				"BarabasiAlbert" => Kind::BarabasiAlbert,
+				"Jellyfish" => Kind::Jellyfish,
+				_ => return Err(cv.ill(&format!("Unknown topology {}",cv_name))),
 			};
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -399,53 +817,151 @@ impl NeighboursLists
 					"routers" => match value
 					{
 						&ConfigurationValue::Number(f) => routers=Some(f as usize),
-						_ => panic!("bad value for routers"),
+						_ => return Err(value.ill("bad value for routers")),
 					},
 					"degree" => match value
 					{
 						&ConfigurationValue::Number(f) => degree=Some(f as usize),
-						_ => panic!("bad value for degree"),
+						_ => return Err(value.ill("bad value for degree")),
 					},
 					"servers_per_router" => match value
 					{
 						&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
-						_ => panic!("bad value for servers_per_router"),
+						_ => return Err(value.ill("bad value for servers_per_router")),
 					},
 					"filename" => match value
 					{
 						&ConfigurationValue::Literal(ref s) => filename=Some(s.to_string()),
-						_ => panic!("bad value for filename"),
+						_ => return Err(value.ill("bad value for filename")),
 					},
 					"format" => match value
 					{
 						&ConfigurationValue::Number(f) => format=Some(f as usize),
-						_ => panic!("bad value for format"),
+						_ => return Err(value.ill("bad value for format")),
 					},
+					"left" => left=Some(value.as_usize()?),
+					"right" => right=Some(value.as_usize()?),
+					"length" => length=Some(value.as_usize()?),
+					"probability" => probability=Some(value.as_f64()?),
+					"rewiring" => rewiring=Some(value.as_f64()?),
+					"links" => links=Some(value.as_usize()?),
+					"connectivity_retries" => connectivity_retries=value.as_usize()?,
+					"degrees" => degrees=Some(value.as_array()?.iter()
+						.map(|v|v.as_usize().expect("bad value in degrees")).collect::<Vec<usize>>()),
+					"expansion" => expansion=value.as_array()?.iter()
+						.map(|v|v.as_usize()).collect::<Result<_,_>>()?,
+					"server_ports" => server_ports=Some(value.as_array()?.iter()
+						.map(|v|v.as_usize().expect("bad value in server_ports")).collect::<Vec<usize>>()),
+					"seed" => seed=Some(value.as_usize()? as u64),
+					"server_attachment" => server_attachment=ServerAttachment::new(value),
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in RandomRegularGraph",name),
+					_ => return Err(cv.ill(&format!("Nothing to do with field {} in {}",name,cv_name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a NeighboursLists from a non-Object");
+			return Err(cv.ill("Trying to create a NeighboursLists from a non-Object"));
 		}
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
+		//The servers given by the file or by `server_ports`, if any.
+		let mut file_servers=None;
+		//Generates the graph of a random model again until it is connected.
+		let mut connected_adj = |generate:&mut dyn FnMut(&mut StdRng)->Vec<Vec<usize>>| {
+			for _ in 0..=connectivity_retries
+			{
+				let adj=generate(&mut *rng);
+				if Self::is_connected_adj(&adj)
+				{
+					return adj;
+				}
+			}
+			panic!("Could not generate a connected graph in {} retries. Consider more links.",connectivity_retries);
+		};
 
 		let adj = match kind
 		{
 			Kind::RandomRegularGraph =>
 			{
-				let routers=routers.expect("There were no routers");
-				let degree=degree.expect("There were no degree");
+				let routers=routers.ok_or_else(||cv.ill("There were no routers"))?;
+				let degree=degree.ok_or_else(||cv.ill("There were no degree"))?;
 				Self::new_rrg_adj(routers,degree,rng)
 			},
 			Kind::File =>
 			{
-				let filename=filename.expect("There were no filename");
-				let format=format.expect("There were no format");
+				let filename=filename.ok_or_else(||cv.ill("There were no filename"))?;
+				let format=format.ok_or_else(||cv.ill("There were no format"))?;
 				let file=File::open(&filename).expect("could not open topology file.");
-				Self::file_adj(&file,format)
+				match format
+				{
+					0 => Self::file_adj(&file,format),
+					1 =>
+					{
+						let (adj,servers)=Self::graphml_adj(&file);
+						file_servers=servers;
+						adj
+					},
+					_ => return Err(cv.ill(&format!("Unknown format {} for a topology File. Use 0 for the plain format or 1 for GraphML.",format))),
+				}
+			},
+			Kind::CompleteBipartite =>
+			{
+				let left=left.ok_or_else(||cv.ill("There were no left"))?;
+				let right=right.ok_or_else(||cv.ill("There were no right"))?;
+				Self::new_complete_bipartite_adj(left,right)
+			},
+			Kind::Kautz =>
+			{
+				let degree=degree.ok_or_else(||cv.ill("There were no degree"))?;
+				let length=length.ok_or_else(||cv.ill("There were no length"))?;
+				Self::new_kautz_adj(degree,length)
+			},
+			Kind::DeBruijn =>
+			{
+				let degree=degree.ok_or_else(||cv.ill("There were no degree"))?;
+				let length=length.ok_or_else(||cv.ill("There were no length"))?;
+				Self::new_de_bruijn_adj(degree,length)
+			},
+			Kind::RandomErdosRenyi =>
+			{
+				let routers=routers.ok_or_else(||cv.ill("There were no routers"))?;
+				let probability=probability.ok_or_else(||cv.ill("There were no probability"))?;
+				connected_adj(&mut |rng|Self::new_erdos_renyi_adj(routers,probability,rng))
+			},
+			Kind::WattsStrogatz =>
+			{
+				let routers=routers.ok_or_else(||cv.ill("There were no routers"))?;
+				let degree=degree.ok_or_else(||cv.ill("There were no degree"))?;
+				let rewiring=rewiring.ok_or_else(||cv.ill("There were no rewiring"))?;
+				connected_adj(&mut |rng|Self::new_watts_strogatz_adj(routers,degree,rewiring,rng))
+			},
+			Kind::BarabasiAlbert =>
+			{
+				let routers=routers.ok_or_else(||cv.ill("There were no routers"))?;
+				let links=links.ok_or_else(||cv.ill("There were no links"))?;
+				connected_adj(&mut |rng|Self::new_barabasi_albert_adj(routers,links,rng))
+			},
+			Kind::Jellyfish =>
+			{
+				let degrees=match (degrees,routers,degree)
+				{
+					(Some(degrees),None,None) => degrees,
+					(None,Some(routers),Some(degree)) => vec![degree;routers],
+					_ => return Err(cv.ill("Jellyfish requires either degrees or both routers and degree")),
+				};
+				if let Some(server_ports)=server_ports
+				{
+					if servers_per_router.is_some()
+					{
+						return Err(cv.ill("Jellyfish cannot receive both server_ports and servers_per_router"));
+					}
+					if server_ports.len()!=degrees.len()+expansion.len()
+					{
+						return Err(cv.ill("There must be server_ports for each router, including the expansion."));
+					}
+					file_servers=Some(server_ports);
+				}
+				let mut own_rng=seed.map(<StdRng as rand::SeedableRng>::seed_from_u64);
+				connected_adj(&mut |rng|Self::new_jellyfish_adj(&degrees,&expansion,own_rng.as_mut().unwrap_or(rng)))
 			},
 		};
 		//return new NeighboursLists(adj);
@@ -465,8 +981,276 @@ impl NeighboursLists
 			})).collect()
 		).collect();
 		//let servers=vec![servers_per_router;routers];
-		let servers=vec![servers_per_router;adj.len()];
-		NeighboursLists::new(list,servers)
+		let servers=match (file_servers,servers_per_router)
+		{
+			(Some(servers),None) => servers,
+			(_,Some(servers_per_router)) => server_attachment.servers(&adj,servers_per_router),
+			(None,None) => match server_attachment
+			{
+				ServerAttachment::Radix(_) => server_attachment.servers(&adj,0),
+				_ => return Err(cv.ill("There were no servers_per_router")),
+			},
+		};
+		Ok(NeighboursLists::new(list,servers))
 	}
 }
 
+///The value of the attribute `name` in a XML tag, quoted with either double or single quotes.
+fn graphml_attribute(tag:&str, name:&str) -> Option<String>
+{
+	let mut rest=tag;
+	while let Some(position)=rest.find(name)
+	{
+		let preceded=rest[..position].chars().last().map(|c|c.is_whitespace()).unwrap_or(false);
+		rest=&rest[position+name.len()..];
+		let after=rest.trim_start();
+		if preceded && after.starts_with('=')
+		{
+			let value=after[1..].trim_start();
+			let quote=value.chars().next()?;
+			if quote=='"' || quote=='\''
+			{
+				let value=&value[1..];
+				return value.find(quote).map(|end|value[..end].to_string());
+			}
+		}
+	}
+	None
+}
+
+///How many servers are attached to each router of a [NeighboursLists], selected with `server_attachment`.
+///* `Uniform` attaches `servers_per_router` servers to each router. This is the default.
+///* `Proportional` attaches to each router a number of servers proportional to its degree, rounded, with `servers_per_router` servers for the routers of average degree.
+///* `Radix{radix}` fills the remaining ports of routers with `radix` ports, attaching `radix-degree` servers to each router. It does not need `servers_per_router`.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ServerAttachment
+{
+	Uniform,
+	Proportional,
+	Radix(usize),
+}
+
+impl ServerAttachment
+{
+	pub fn new(cv:&ConfigurationValue) -> ServerAttachment
+	{
+		match cv
+		{
+			ConfigurationValue::Object(name,pairs) => match name.as_ref()
+			{
+				"Uniform" => ServerAttachment::Uniform,
+				"Proportional" => ServerAttachment::Proportional,
+				"Radix" =>
+				{
+					let mut radix=None;
+					for (key,value) in pairs
+					{
+						match key.as_ref()
+						{
+							"radix" => radix=Some(value.as_usize().expect("bad value for radix")),
+							_ => panic!("Nothing to do with field {} in Radix",key),
+						}
+					}
+					ServerAttachment::Radix(radix.expect("There were no radix"))
+				},
+				_ => panic!("Unknown server_attachment {}",name),
+			},
+			_ => panic!("bad value for server_attachment"),
+		}
+	}
+	///The number of servers of each router of the graph given by the adjacencies.
+	pub fn servers(&self, adj:&[Vec<usize>], servers_per_router:usize) -> Vec<usize>
+	{
+		match *self
+		{
+			ServerAttachment::Uniform => vec![servers_per_router;adj.len()],
+			ServerAttachment::Proportional =>
+			{
+				let links:usize = adj.iter().map(|neighbours|neighbours.len()).sum();
+				let average_degree = links as f64 / adj.len() as f64;
+				adj.iter().map(|neighbours|(servers_per_router as f64*neighbours.len() as f64/average_degree).round() as usize).collect()
+			},
+			ServerAttachment::Radix(radix) => adj.iter().map(|neighbours|{
+				assert!(neighbours.len()<=radix, "A router has degree {} over the radix {}.",neighbours.len(),radix);
+				radix-neighbours.len()
+			}).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn classic_graphs()
+	{
+		let bipartite = NeighboursLists::new_complete_bipartite_adj(3,5);
+		assert_eq!(bipartite.len(),8);
+		assert!(bipartite[0..3].iter().all(|adj|adj.len()==5));
+		assert!(bipartite[3..8].iter().all(|adj|adj.len()==3));
+		// K(2,3) has (d+1)d^(D-1) = 12 vertices.
+		let kautz = NeighboursLists::new_kautz_adj(2,3);
+		assert_eq!(kautz.len(),12);
+		// B(2,3) has d^D = 8 vertices.
+		let de_bruijn = NeighboursLists::new_de_bruijn_adj(2,3);
+		assert_eq!(de_bruijn.len(),8);
+		for adj in [bipartite,kautz,de_bruijn]
+		{
+			for (router,neighbours) in adj.iter().enumerate()
+			{
+				assert!(neighbours.len()<=8);
+				for &neighbour in neighbours
+				{
+					assert_ne!(router,neighbour);
+					assert!(adj[neighbour].contains(&router));
+				}
+			}
+		}
+	}
+	#[test]
+	fn random_graph_models()
+	{
+		use rand::SeedableRng;
+		let mut rng=StdRng::seed_from_u64(3u64);
+		let erdos_renyi = NeighboursLists::new_erdos_renyi_adj(50,0.2,&mut rng);
+		let watts_strogatz = NeighboursLists::new_watts_strogatz_adj(50,4,0.3,&mut rng);
+		let barabasi_albert = NeighboursLists::new_barabasi_albert_adj(50,2,&mut rng);
+		// A ring lattice with no rewiring keeps its regular degree.
+		let lattice = NeighboursLists::new_watts_strogatz_adj(10,4,0.0,&mut rng);
+		assert!(lattice.iter().all(|adj|adj.len()==4));
+		// Rewiring keeps the number of links.
+		assert_eq!(watts_strogatz.iter().map(|adj|adj.len()).sum::<usize>(),50*4);
+		// Each new router adds `links` links to those of the initial complete graph.
+		assert_eq!(barabasi_albert.iter().map(|adj|adj.len()).sum::<usize>(),2*(3+47*2));
+		assert!(barabasi_albert.iter().all(|adj|adj.len()>=2));
+		for adj in [erdos_renyi,watts_strogatz,barabasi_albert,lattice]
+		{
+			assert!(NeighboursLists::is_connected_adj(&adj));
+			for (router,neighbours) in adj.iter().enumerate()
+			{
+				for &neighbour in neighbours
+				{
+					assert_ne!(router,neighbour);
+					assert!(adj[neighbour].contains(&router));
+					assert_eq!(neighbours.iter().filter(|&&n|n==neighbour).count(),1);
+				}
+			}
+		}
+		assert!(!NeighboursLists::is_connected_adj(&[vec![1],vec![0],vec![]]));
+		let cv = ConfigurationValue::Object("RandomErdosRenyi".to_string(),vec![
+			("routers".to_string(),ConfigurationValue::Number(30.0)),
+			("probability".to_string(),ConfigurationValue::Number(0.15)),
+			("server_attachment".to_string(),ConfigurationValue::Object("Radix".to_string(),vec![("radix".to_string(),ConfigurationValue::Number(16.0))])),
+		]);
+		let topology = NeighboursLists::new_cfg(&cv,&mut rng).unwrap();
+		for router in 0..topology.num_routers()
+		{
+			assert_eq!(topology.ports(router),16);
+		}
+	}
+	#[test]
+	fn jellyfish()
+	{
+		use rand::SeedableRng;
+		let degrees = (0..40).map(|router|if router<20 {4} else {6}).collect::<Vec<usize>>();
+		let initial = NeighboursLists::new_jellyfish_adj(&degrees,&[],&mut StdRng::seed_from_u64(5u64));
+		let expanded = NeighboursLists::new_jellyfish_adj(&degrees,&[4,6,6],&mut StdRng::seed_from_u64(5u64));
+		assert_eq!(expanded.len(),43);
+		for adj in [&initial,&expanded]
+		{
+			for (router,neighbours) in adj.iter().enumerate()
+			{
+				// Each router uses its ports except at most one.
+				let degree = if router<20 || router==40 {4} else {6};
+				assert!(neighbours.len()<=degree && neighbours.len()+1>=degree);
+				for &neighbour in neighbours
+				{
+					assert_ne!(router,neighbour);
+					assert!(adj[neighbour].contains(&router));
+					assert_eq!(neighbours.iter().filter(|&&n|n==neighbour).count(),1);
+				}
+			}
+		}
+		// Each added router breaks at most degree/2 of the initial links.
+		let kept:usize = (0..40).map(|router|initial[router].iter().filter(|n|expanded[router].contains(n)).count()).sum();
+		let total:usize = initial.iter().map(|adj|adj.len()).sum();
+		assert!(kept+2*(2+3+3)>=total);
+		let cv = ConfigurationValue::Object("Jellyfish".to_string(),vec![
+			("degrees".to_string(),ConfigurationValue::Array(degrees.iter().map(|&d|ConfigurationValue::Number(d as f64)).collect())),
+			("expansion".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0)])),
+			("server_ports".to_string(),ConfigurationValue::Array((0..41).map(|router|ConfigurationValue::Number((router%3) as f64)).collect())),
+			("seed".to_string(),ConfigurationValue::Number(5.0)),
+		]);
+		// The own seed makes the graph independent of the simulation generator.
+		let first = NeighboursLists::new_cfg(&cv,&mut StdRng::seed_from_u64(1u64)).unwrap();
+		let second = NeighboursLists::new_cfg(&cv,&mut StdRng::seed_from_u64(2u64)).unwrap();
+		assert_eq!(first.num_routers(),41);
+		for router in 0..41
+		{
+			assert_eq!(first.ports(router)-first.degree(router),router%3);
+			assert_eq!(first.degree(router),second.degree(router));
+			for port in 0..first.degree(router)
+			{
+				assert_eq!(first.neighbour(router,port).0,second.neighbour(router,port).0);
+			}
+		}
+	}
+	#[test]
+	fn graphml_round_trip()
+	{
+		use rand::SeedableRng;
+		let mut rng=StdRng::seed_from_u64(4u64);
+		let adj = NeighboursLists::new_barabasi_albert_adj(20,2,&mut rng);
+		let list = adj.iter().enumerate().map(|(current,neighbours)|
+			neighbours.iter().map(|&neigh|(neigh,adj[neigh].iter().position(|&v|v==current).unwrap())).collect()
+		).collect();
+		let servers = (0..20).map(|router|router%3).collect::<Vec<usize>>();
+		let topology = NeighboursLists::new(list,servers.clone());
+		let path = std::env::temp_dir().join("caminos_graphml_round_trip.graphml");
+		let mut file = File::create(&path).unwrap();
+		topology.write_adjacencies_to_file(&mut file,1).unwrap();
+		drop(file);
+		let (read_adj,read_servers) = NeighboursLists::graphml_adj(&File::open(&path).unwrap());
+		std::fs::remove_file(&path).ok();
+		assert_eq!(read_servers,Some(servers));
+		assert_eq!(read_adj.len(),adj.len());
+		for (read,original) in read_adj.iter().zip(adj.iter())
+		{
+			let mut read=read.clone();
+			let mut original=original.clone();
+			read.sort_unstable();
+			original.sort_unstable();
+			assert_eq!(read,original);
+		}
+		//Files from other tools may use other node identifiers and single quotes.
+		let external = "<graphml><graph edgedefault='undirected'><node id='a'/><node id='b'/><node id='c'/><edge source='a' target='b'/><edge target='c' source='b'/></graph></graphml>";
+		let path = std::env::temp_dir().join("caminos_graphml_external.graphml");
+		std::fs::write(&path,external).unwrap();
+		let (read_adj,read_servers) = NeighboursLists::graphml_adj(&File::open(&path).unwrap());
+		std::fs::remove_file(&path).ok();
+		assert_eq!(read_adj,vec![vec![1],vec![0,2],vec![1]]);
+		assert_eq!(read_servers,None);
+	}
+	#[test]
+	fn sampled_path_diversity()
+	{
+		use rand::SeedableRng;
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let cv = ConfigurationValue::Object("RandomRegularGraph".to_string(),vec![
+			("routers".to_string(),ConfigurationValue::Number(100.0)),
+			("degree".to_string(),ConfigurationValue::Number(4.0)),
+			("servers_per_router".to_string(),ConfigurationValue::Number(1.0)),
+		]);
+		let topology = NeighboursLists::new_cfg(&cv,&mut rng).unwrap();
+		let exact = topology.estimate_path_diversity(100,&mut rng);
+		assert_eq!(exact.average_amount_shortest_paths.half_width,0.0);
+		assert!((exact.average_amount_shortest_paths.mean-topology.average_amount_shortest_paths() as f64).abs()<1e-4);
+		let sampled = topology.estimate_path_diversity(30,&mut rng);
+		assert_eq!(sampled.samples,30);
+		for (estimate,value) in [(sampled.average_distance,exact.average_distance.mean),(sampled.average_amount_shortest_paths,exact.average_amount_shortest_paths.mean)]
+		{
+			assert!(estimate.half_width>0.0);
+			assert!((estimate.mean-value).abs()<=2.0*estimate.half_width,"estimate {:?} too far from {}",estimate,value);
+		}
+	}
+}
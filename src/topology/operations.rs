@@ -3,12 +3,14 @@ use super::prelude::*;
 use super::NeighbourRouterIteratorItem;
 use crate::pattern::prelude::*;
 use crate::matrix::Matrix;
-use crate::match_object_panic;
+use crate::match_object;
 use crate::config_parser::ConfigurationValue;
 use quantifiable_derive::Quantifiable;//the derive macro
 
 use rand::prelude::SliceRandom;
-use std::collections::{HashMap,HashSet};
+use std::collections::{BTreeSet,HashMap,HashSet};
+use crate::error::{Error,SourceLocation};
+use crate::{error,source_location};
 
 /**
 Transforms the server indices of a base topology. This does not change the indices of routers.
@@ -56,6 +58,10 @@ impl Topology for RemappedServersTopology
 		}
 		(loc,link_class)
 	}
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		self.topology.server_neighbours(self.into_base_map[server_index])
+	}
 	fn diameter(&self) -> usize { self.topology.diameter() }
 	fn distance(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
 	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize { self.topology.amount_shortest_paths(origin,destination) }
@@ -69,6 +75,7 @@ impl Topology for RemappedServersTopology
 		self.topology.neighbour_router_iter(router_index)
 	}
 	fn cartesian_data(&self) -> Option<&CartesianData> { self.topology.cartesian_data() }
+	fn cartesian_link_dimension(&self, link_class:usize) -> usize { self.topology.cartesian_link_dimension(link_class) }
 	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng:Option<&mut StdRng>)->Vec<i32>
 	{
 		self.topology.coordinated_routing_record(coordinates_a,coordinates_b,rng)
@@ -77,6 +84,14 @@ impl Topology for RemappedServersTopology
 	{
 		self.topology.is_direction_change(router_index,input_port,output_port)
 	}
+	fn is_link_alive(&self, router_index:usize, port:usize) -> bool
+	{
+		self.topology.is_link_alive(router_index,port)
+	}
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		self.topology.router_role(router_index)
+	}
 	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
 	{
 		self.topology.up_down_distance(origin,destination)
@@ -92,6 +107,16 @@ impl Topology for RemappedServersTopology
 		self.topology.compute_distance_matrix(class_weight)
 	}
 	// Noone really overrides this...
+	fn dijkstra(&self, origin:usize, class_weight:&[usize]) -> Vec<usize>
+	{
+		self.topology.dijkstra(origin,class_weight)
+	}
+	// Noone really overrides this...
+	fn compute_weighted_distance_matrix(&self, class_weight:&[usize]) -> Matrix<usize>
+	{
+		self.topology.compute_weighted_distance_matrix(class_weight)
+	}
+	// Noone really overrides this...
 	fn floyd(&self) -> Matrix<usize>
 	{
 		self.topology.floyd()
@@ -123,17 +148,17 @@ impl Topology for RemappedServersTopology
 
 impl RemappedServersTopology
 {
-	pub fn new(mut arg:TopologyBuilderArgument) -> RemappedServersTopology
+	pub fn new(mut arg:TopologyBuilderArgument) -> Result<RemappedServersTopology,Error>
 	{
 		let mut topology = None;
 		let mut pattern = None;
-		match_object_panic!(arg.cv, "RemappedServers", value,
-			"topology" => topology = Some(new_topology(TopologyBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"pattern" => pattern = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+		match_object!(arg.cv, "RemappedServers", value,
+			"topology" => topology = Some(try_new_topology(TopologyBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
 		);
-		let topology = topology.expect("There were no topology in configuration of RemappedServersTopology.");
+		let topology = topology.ok_or_else(||arg.cv.ill("There were no topology in configuration of RemappedServersTopology."))?;
 		let n = topology.num_servers();
-		let mut pattern = pattern.expect("There were no pattern in configuration of RemappedServersTopology.");
+		let mut pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern in configuration of RemappedServersTopology."))?;
 		pattern.initialize(n,n,&*topology,arg.rng);
 		let from_base_map : Vec<usize> = (0..n).map(|server_inside|{
 			pattern.get_destination(server_inside,&*topology,arg.rng)
@@ -148,11 +173,11 @@ impl RemappedServersTopology
 			}
 		}
 		let into_base_map = into_base_map.iter().map(|x|x.expect("server not mapped")).collect();
-		RemappedServersTopology{
+		Ok(RemappedServersTopology{
 			from_base_map,
 			into_base_map,
 			topology,
-		}
+		})
 	}
 	// never called?
 	pub fn map_location_into_base(&self,location:Location) -> Location
@@ -192,6 +217,22 @@ topology: RandomLinkFaults{
 	seed: 0,
 },
 ```
+
+The faults can also be targeted. The links joining the pairs of routers in `links` are always removed, in addition to the random ones.
+The random candidates can be restricted to some `link_classes`, and their amount can be given as a `fraction` of the candidates instead of `amount`.
+With `keep_connected` the random links whose removal would disconnect the routers are skipped, so that the shortest routings always find a route.
+The following example removes 10% of the global links of a Megafly, besides the local link between the leaf 0 and the spine 68.
+```ignore
+topology: RandomLinkFaults{
+	topology: Megafly{ global_ports_per_spine:4, servers_per_leaf:4, group_size:4, number_of_groups:17 },
+	link_classes: [1],
+	fraction: 0.1,
+	links: [[0,68]],
+	keep_connected: true,
+},
+```
+The routers discard the candidates given by the routing through removed links, see [Topology::is_link_alive]. Hence adaptive routings can go around the faults,
+while a routing insisting in a removed link would leave its packets waiting forever.
 **/
 #[derive(Debug,Quantifiable)]
 pub struct RandomLinkFaults
@@ -224,6 +265,10 @@ impl Topology for RandomLinkFaults
 	{
 		self.topology.server_neighbour(server_index)
 	}
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		self.topology.server_neighbours(server_index)
+	}
 	fn diameter(&self) -> usize { self.compute_diameter() }
 	fn distance(&self,origin:usize,destination:usize) -> usize {
 		*self.distance_matrix.get(origin,destination)
@@ -243,6 +288,7 @@ impl Topology for RandomLinkFaults
 	}
 	fn ports(&self, router_index: usize) -> usize { self.topology.ports(router_index) }
 	fn cartesian_data(&self) -> Option<&CartesianData> { self.topology.cartesian_data() }
+	fn cartesian_link_dimension(&self, link_class:usize) -> usize { self.topology.cartesian_link_dimension(link_class) }
 	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng:Option<&mut StdRng>)->Vec<i32>
 	{
 		// XXX what happens with broken links?
@@ -252,6 +298,14 @@ impl Topology for RandomLinkFaults
 	{
 		self.topology.is_direction_change(router_index,input_port,output_port)
 	}
+	fn is_link_alive(&self, router_index:usize, port:usize) -> bool
+	{
+		!self.removed_links.contains_key( &Location::RouterPort{router_index,router_port:port} ) && self.topology.is_link_alive(router_index,port)
+	}
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		self.topology.router_role(router_index)
+	}
 	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
 	{
 		// XXX what happens with broken links?
@@ -262,24 +316,62 @@ impl Topology for RandomLinkFaults
 
 impl RandomLinkFaults
 {
-	pub fn new(mut arg:TopologyBuilderArgument) -> RandomLinkFaults
+	pub fn new(mut arg:TopologyBuilderArgument) -> Result<RandomLinkFaults,Error>
 	{
 		let mut topology = None;
 		let mut amount = None;
+		let mut fraction = None;
 		let mut rng = None;
 		let mut switch_pattern = None;
 		let mut switch_pattern_input_size = None;
-		match_object_panic!(arg.cv, "RandomLinkFaults", value,
-			"topology" => topology = Some(new_topology(TopologyBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"amount" => amount = Some( value.as_i32().expect("bad value for amount") ),
-			"seed" => rng = Some( value.as_rng().expect("bad value for seed") ),
-			"switch_pattern" => switch_pattern = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"switch_pattern_input_size" => switch_pattern_input_size = Some( value.as_usize().expect("bad value for amount") ),
+		let mut link_classes : Option<Vec<usize>> = None;
+		let mut links : Vec<(usize,usize)> = vec![];
+		let mut keep_connected = false;
+		match_object!(arg.cv, "RandomLinkFaults", value,
+			"topology" => topology = Some(try_new_topology(TopologyBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"amount" => amount = Some( value.as_i32()? ),
+			"fraction" => fraction = Some( value.as_f64()? ),
+			"seed" => rng = Some( value.as_rng()? ),
+			"switch_pattern" => switch_pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"switch_pattern_input_size" => switch_pattern_input_size = Some( value.as_usize()? ),
+			"link_classes" => link_classes = Some(value.as_array()?.iter().map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"links" => links = value.as_array()?.iter().map(|pair|{
+				let pair = pair.as_array().expect("bad value in links");
+				if pair.len()!=2 { panic!("Each entry of links must be a pair of routers."); }
+				(pair[0].as_usize().expect("bad value in links"),pair[1].as_usize().expect("bad value in links"))
+			}).collect(),
+			"keep_connected" => keep_connected = value.as_bool()?,
 		);
-		let topology = topology.expect("There were no topology in configuration of RemappedServersTopology.");
-		let amount = amount.expect("Missing field amount in RandomLinkFaults.");
+		let topology = topology.ok_or_else(||arg.cv.ill("There were no topology in configuration of RemappedServersTopology."))?;
+		if amount.is_some() && fraction.is_some() {
+			return Err(arg.cv.ill("RandomLinkFaults accepts either amount or fraction, but not both."));
+		}
+		if amount.is_none() && fraction.is_none() && links.is_empty() {
+			return Err(arg.cv.ill("Missing field amount in RandomLinkFaults."));
+		}
 		let rng = rng.as_mut().unwrap_or(arg.rng);
 		let n = topology.num_routers();
+		let mut removed_links = HashMap::new();
+		// The explicit links are removed first, all those joining each pair.
+		for &(left_router,right_router) in links.iter()
+		{
+			let mut found = false;
+			for left_port in 0..topology.ports(left_router)
+			{
+				if let (Location::RouterPort{router_index,router_port},_link_class) = topology.neighbour(left_router,left_port) {
+					if router_index == right_router {
+						let left_loc = Location::RouterPort{router_index:left_router, router_port:left_port};
+						let right_loc = Location::RouterPort{router_index, router_port};
+						removed_links.insert( left_loc.clone(), right_loc.clone() );
+						removed_links.insert( right_loc, left_loc );
+						found = true;
+					}
+				}
+			}
+			if !found {
+				panic!("There is no link between the routers {} and {} to remove.",left_router,right_router);
+			}
+		}
 		let switch_set : Option<HashSet<usize>> = if let Some(mut pattern) = switch_pattern {
 			let input_size = switch_pattern_input_size.unwrap_or(n);
 			pattern.initialize(input_size,n,&*topology,rng);
@@ -300,26 +392,42 @@ impl RandomLinkFaults
 			if switch_set.as_ref().is_some_and(|set|set.get(&left_router).is_none()) { continue; }
 			for left_port in 0..topology.ports(left_router)
 			{
-				let (right_loc,_link_class) = topology.neighbour(left_router,left_port);
+				let (right_loc,link_class) = topology.neighbour(left_router,left_port);
+				if link_classes.as_ref().map_or(false,|classes|!classes.contains(&link_class)) { continue; }
 				if let Location::RouterPort{router_index,..} = right_loc {
 					if switch_set.as_ref().is_some_and(|set|set.get(&router_index).is_none()) { continue; }
-					if left_router < router_index {
-						let left_loc = Location::RouterPort{router_index:left_router, router_port:left_port};
+					let left_loc = Location::RouterPort{router_index:left_router, router_port:left_port};
+					if left_router < router_index && !removed_links.contains_key(&left_loc) {
 						link_pool.push( (left_loc,right_loc) );
 					}
 				}
 			}
 		}
-		if link_pool.len() < amount as usize {
+		let amount = match fraction {
+			Some(fraction) => (fraction*link_pool.len() as f64).round() as usize,
+			None => amount.unwrap_or(0) as usize,
+		};
+		if link_pool.len() < amount {
 			panic!("Not enough link candidates to remove. {} candidates, {} asked to remove.",link_pool.len(),amount);
 		}
 		// We delete amount links.
 		// It is simple to shuffle the array and get the first ones. A bit inefficient, but no relevant.
 		link_pool.shuffle(rng);
-		let mut removed_links = HashMap::new();
-		for (left_loc,right_loc) in link_pool.into_iter().take(amount as usize) {
+		let mut removed_amount = 0;
+		for (left_loc,right_loc) in link_pool.into_iter() {
+			if removed_amount == amount { break; }
 			removed_links.insert( left_loc.clone(), right_loc.clone() );
-			removed_links.insert( right_loc, left_loc );
+			removed_links.insert( right_loc.clone(), left_loc.clone() );
+			if keep_connected && !is_connected(&*topology,&removed_links) {
+				// This link is required for the connectivity, so we keep it.
+				removed_links.remove(&left_loc);
+				removed_links.remove(&right_loc);
+				continue;
+			}
+			removed_amount += 1;
+		}
+		if removed_amount < amount {
+			panic!("Only {} links can be removed while keeping the network connected, {} asked to remove.",removed_amount,amount);
 		}
 		let mut topo = RandomLinkFaults{
 			topology,
@@ -355,9 +463,778 @@ impl RandomLinkFaults
 			//return (double)r/(double)count;
 			r as f32/count as f32
 		};
-		topo
+		Ok(topo)
+	}
+}
+
+///Whether all routers are reachable from the router 0 without using the `removed_links`.
+fn is_connected(topology:&dyn Topology, removed_links:&HashMap<Location,Location>) -> bool
+{
+	let n = topology.num_routers();
+	if n == 0 { return true; }
+	let mut reached = vec![false;n];
+	reached[0] = true;
+	let mut queue = vec![0];
+	while let Some(current) = queue.pop()
+	{
+		for NeighbourRouterIteratorItem{port_index,neighbour_router,..} in topology.neighbour_router_iter(current)
+		{
+			if !reached[neighbour_router] && !removed_links.contains_key(&Location::RouterPort{router_index:current,router_port:port_index})
+			{
+				reached[neighbour_router] = true;
+				queue.push(neighbour_router);
+			}
+		}
+	}
+	reached.into_iter().all(|r|r)
+}
+
+
+
+
+/**
+Sets the number of servers attached to each router of a base topology, allowing some routers to have more servers than others.
+The links between routers are those of the base topology, and its servers are replaced. The servers are numbered consecutively by router.
+This allows to model, for example, multistage networks in which some routers of upper levels also host servers.
+
+The amount of servers of each router can be given explicitly with `servers`, with an entry for each router.
+```ignore
+Concentration{
+	topology: Hamming{sides:[2,2],servers_per_router:0},
+	servers: [4,2,2,0],
+}
+```
+Alternatively a `pattern` can map each router into its amount of servers, where `maximum_servers` is the target size of the pattern.
+```ignore
+Concentration{
+	topology: XGFT{height:2, down:[4,4], up:[4,4], servers_per_leaf:4},
+	//The 16 leaves keep 4 servers, and each of the 32 routers at the levels above gets 1 server.
+	pattern: Switch{
+		indexing: LinearTransform{source_size:[16,3], target_size:[3], matrix:[[0,1]]},
+		patterns:[Hotspots{destinations:[4]},Hotspots{destinations:[1]},Hotspots{destinations:[1]}],
+	},
+	maximum_servers: 5,
+}
+```
+The link class of the server links is that of the base topology, or one more than the greatest link class when the base topology has no servers.
+**/
+#[derive(Debug,Quantifiable)]
+pub struct ConcentrationTopology
+{
+	/// The base topology.
+	topology: Box<dyn Topology>,
+	/// The number of ports towards other routers for each router, as in the base topology.
+	router_ports: Vec<usize>,
+	/// The index of the first server of each router. With an additional entry with the total number of servers.
+	server_offset: Vec<usize>,
+	/// The link class of the links to servers.
+	server_link_class: usize,
+}
+
+impl Topology for ConcentrationTopology
+{
+	fn num_routers(&self) -> usize { self.topology.num_routers() }
+	fn num_servers(&self) -> usize { self.server_offset[self.topology.num_routers()] }
+	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize)
+	{
+		let router_ports = self.router_ports[router_index];
+		if port<router_ports
+		{
+			self.topology.neighbour(router_index,port)
+		}
+		else
+		{
+			(Location::ServerPort(self.server_offset[router_index]+port-router_ports),self.server_link_class)
+		}
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		//The router is the last one whose first server is not greater than `server_index`.
+		let router_index = self.server_offset.partition_point(|&offset|offset<=server_index) - 1;
+		(Location::RouterPort{
+			router_index,
+			router_port: self.router_ports[router_index]+server_index-self.server_offset[router_index],
+		},self.server_link_class)
+	}
+	fn diameter(&self) -> usize { self.topology.diameter() }
+	fn distance(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize { self.topology.amount_shortest_paths(origin,destination) }
+	fn average_amount_shortest_paths(&self) -> f32 { self.topology.average_amount_shortest_paths() }
+	fn maximum_degree(&self) -> usize { self.topology.maximum_degree() }
+	fn minimum_degree(&self) -> usize { self.topology.minimum_degree() }
+	fn degree(&self, router_index: usize) -> usize { self.topology.degree(router_index) }
+	fn ports(&self, router_index: usize) -> usize
+	{
+		self.router_ports[router_index] + self.server_offset[router_index+1] - self.server_offset[router_index]
+	}
+	fn neighbour_router_iter<'a>(&'a self, router_index:usize) -> Box<dyn Iterator<Item=NeighbourRouterIteratorItem> + 'a>
+	{
+		self.topology.neighbour_router_iter(router_index)
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData> { self.topology.cartesian_data() }
+	fn cartesian_link_dimension(&self, link_class:usize) -> usize { self.topology.cartesian_link_dimension(link_class) }
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng:Option<&mut StdRng>)->Vec<i32>
+	{
+		self.topology.coordinated_routing_record(coordinates_a,coordinates_b,rng)
+	}
+	fn is_direction_change(&self, router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.topology.is_direction_change(router_index,input_port,output_port)
+	}
+	fn is_link_alive(&self, router_index:usize, port:usize) -> bool
+	{
+		self.topology.is_link_alive(router_index,port)
+	}
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		self.topology.router_role(router_index)
+	}
+	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
+	{
+		self.topology.up_down_distance(origin,destination)
+	}
+	fn dragonfly_size(&self) -> Option<crate::topology::dragonfly::ArrangementSize>
+	{
+		self.topology.dragonfly_size()
+	}
+}
+
+impl ConcentrationTopology
+{
+	pub fn new(mut arg:TopologyBuilderArgument) -> Result<ConcentrationTopology,Error>
+	{
+		let mut topology = None;
+		let mut servers : Option<Vec<usize>> = None;
+		let mut pattern = None;
+		let mut maximum_servers = None;
+		match_object!(arg.cv, "Concentration", value,
+			"topology" => topology = Some(try_new_topology(arg.with_cv(value))?),
+			"servers" => servers = Some(value.as_array()?.iter().map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+			"pattern" => pattern = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"maximum_servers" => maximum_servers = Some(value.as_usize()?),
+		);
+		let topology = topology.ok_or_else(||arg.cv.ill("There were no topology in configuration of Concentration."))?;
+		let n = topology.num_routers();
+		let servers = match (servers,pattern)
+		{
+			(Some(servers),None) => servers,
+			(None,Some(mut pattern)) =>
+			{
+				let maximum_servers = maximum_servers.ok_or_else(||arg.cv.ill("There were no maximum_servers in configuration of Concentration."))?;
+				pattern.initialize(n,maximum_servers,&*topology,arg.rng);
+				(0..n).map(|router|pattern.get_destination(router,&*topology,arg.rng)).collect()
+			},
+			_ => return Err(arg.cv.ill("Concentration requires either servers or pattern.")),
+		};
+		assert_eq!(servers.len(),n,"Concentration requires the amount of servers of each of the {} routers.",n);
+		let router_ports : Vec<usize> = (0..n).map(|router|{
+			let base_servers = (0..topology.ports(router)).filter(|&port|matches!(topology.neighbour(router,port).0,Location::ServerPort(_))).count();
+			topology.ports(router)-base_servers
+		}).collect();
+		let server_link_class = if topology.num_servers()>0
+		{
+			topology.server_neighbour(0).1
+		}
+		else
+		{
+			(0..n).flat_map(|router|topology.neighbour_router_iter(router).map(|item|item.link_class).collect::<Vec<usize>>()).max().map(|class|class+1).unwrap_or(0)
+		};
+		let mut server_offset = Vec::with_capacity(n+1);
+		let mut offset = 0;
+		for amount in servers
+		{
+			server_offset.push(offset);
+			offset += amount;
+		}
+		server_offset.push(offset);
+		Ok(ConcentrationTopology{
+			topology,
+			router_ports,
+			server_offset,
+			server_link_class,
+		})
 	}
 }
 
+/**
+Gives each server several ports, called rails, to model multi-rail NICs or servers connected to several routers.
+Each server of this topology takes `rails` servers of the base topology, and the base topology must have a multiple of `rails` servers.
+With `grouping: Contiguous`, the default, the server `s` takes the base servers `s*rails+k`, which are usually in the same router.
+With `grouping: Interleaved` it takes the base servers `s+k*n`, with `n` the number of servers, so that the rails reach distant routers.
+The first rail is the one given by `server_neighbour`, to which the packets destined to the server are routed.
+The servers send each packet through one of its rails, alternating among the rails that are free.
+```ignore
+MultiRail{
+	topology: Hamming{sides:[4,4],servers_per_router:4},
+	rails: 2,
+	grouping: Interleaved,
+}
+```
+**/
+#[derive(Debug,Quantifiable)]
+pub struct MultiRailTopology
+{
+	/// The base topology.
+	topology: Box<dyn Topology>,
+	/// The number of rails of each server.
+	rails: usize,
+	/// Whether the rails of a server are spread among the base servers.
+	interleaved: bool,
+}
 
+impl MultiRailTopology
+{
+	pub fn new(mut arg:TopologyBuilderArgument) -> Result<MultiRailTopology,Error>
+	{
+		let mut topology = None;
+		let mut rails = None;
+		let mut interleaved = false;
+		match_object!(arg.cv, "MultiRail", value,
+			"topology" => topology = Some(try_new_topology(arg.with_cv(value))?),
+			"rails" => rails = Some(value.as_usize()?),
+			"grouping" => interleaved = match value
+			{
+				ConfigurationValue::Object(name,_) if name=="Contiguous" => false,
+				ConfigurationValue::Object(name,_) if name=="Interleaved" => true,
+				_ => return Err(value.ill("bad value for grouping")),
+			},
+		);
+		let topology = topology.ok_or_else(||arg.cv.ill("There were no topology in configuration of MultiRail."))?;
+		let rails = rails.ok_or_else(||arg.cv.ill("There were no rails in configuration of MultiRail."))?;
+		if rails==0 || topology.num_servers()%rails!=0
+		{
+			return Err(arg.cv.ill(&format!("MultiRail requires the {} servers of the base topology to be a multiple of the {} rails.",topology.num_servers(),rails)));
+		}
+		Ok(MultiRailTopology{
+			topology,
+			rails,
+			interleaved,
+		})
+	}
+	/// The base server of the rail `rail` of the server `server_index`.
+	pub fn base_server(&self, server_index:usize, rail:usize) -> usize
+	{
+		if self.interleaved { server_index + rail*self.num_servers() } else { server_index*self.rails + rail }
+	}
+	/// The server owning the base server `base_server`.
+	pub fn server_of_base(&self, base_server:usize) -> usize
+	{
+		if self.interleaved { base_server % self.num_servers() } else { base_server / self.rails }
+	}
+}
 
+impl Topology for MultiRailTopology
+{
+	fn num_routers(&self) -> usize { self.topology.num_routers() }
+	fn num_servers(&self) -> usize { self.topology.num_servers()/self.rails }
+	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize)
+	{
+		match self.topology.neighbour(router_index,port)
+		{
+			(Location::ServerPort(base_server),link_class) => (Location::ServerPort(self.server_of_base(base_server)),link_class),
+			x => x,
+		}
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		self.topology.server_neighbour(self.base_server(server_index,0))
+	}
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		(0..self.rails).map(|rail|self.topology.server_neighbour(self.base_server(server_index,rail))).collect()
+	}
+	fn diameter(&self) -> usize { self.topology.diameter() }
+	fn distance(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize { self.topology.amount_shortest_paths(origin,destination) }
+	fn average_amount_shortest_paths(&self) -> f32 { self.topology.average_amount_shortest_paths() }
+	fn maximum_degree(&self) -> usize { self.topology.maximum_degree() }
+	fn minimum_degree(&self) -> usize { self.topology.minimum_degree() }
+	fn degree(&self, router_index: usize) -> usize { self.topology.degree(router_index) }
+	fn ports(&self, router_index: usize) -> usize { self.topology.ports(router_index) }
+	fn neighbour_router_iter<'a>(&'a self, router_index:usize) -> Box<dyn Iterator<Item=NeighbourRouterIteratorItem> + 'a>
+	{
+		self.topology.neighbour_router_iter(router_index)
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData> { self.topology.cartesian_data() }
+	fn cartesian_link_dimension(&self, link_class:usize) -> usize { self.topology.cartesian_link_dimension(link_class) }
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng:Option<&mut StdRng>)->Vec<i32>
+	{
+		self.topology.coordinated_routing_record(coordinates_a,coordinates_b,rng)
+	}
+	fn is_direction_change(&self, router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.topology.is_direction_change(router_index,input_port,output_port)
+	}
+	fn is_link_alive(&self, router_index:usize, port:usize) -> bool
+	{
+		self.topology.is_link_alive(router_index,port)
+	}
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		self.topology.router_role(router_index)
+	}
+	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
+	{
+		self.topology.up_down_distance(origin,destination)
+	}
+	fn dragonfly_size(&self) -> Option<crate::topology::dragonfly::ArrangementSize>
+	{
+		self.topology.dragonfly_size()
+	}
+}
+
+/**
+Builds several independent copies, or planes, of a base topology, with each server attached to all of them.
+The router `r` of the plane `p` gets the index `p*n+r`, with `n` the number of routers of the base topology, and there are no links between planes.
+Each server has a rail into each plane, in order, so the first rail is the one in the plane 0. Which plane takes each packet is decided at injection by the `rail_selection` of the simulation.
+The packets are routed towards the router of their destination server in the same plane they were injected into, see [Topology::server_router_from].
+```ignore
+MultiPlane{
+	plane: Hamming{sides:[4,4],servers_per_router:4},
+	planes: 2,
+}
+```
+**/
+#[derive(Debug,Quantifiable)]
+pub struct MultiPlaneTopology
+{
+	/// The topology of each plane.
+	plane: Box<dyn Topology>,
+	/// The number of planes.
+	planes: usize,
+}
+
+impl MultiPlaneTopology
+{
+	pub fn new(mut arg:TopologyBuilderArgument) -> Result<MultiPlaneTopology,Error>
+	{
+		let mut plane = None;
+		let mut planes = None;
+		match_object!(arg.cv, "MultiPlane", value,
+			"plane" => plane = Some(try_new_topology(arg.with_cv(value))?),
+			"planes" => planes = Some(value.as_usize()?),
+		);
+		let plane = plane.ok_or_else(||arg.cv.ill("There were no plane in configuration of MultiPlane."))?;
+		let planes = planes.ok_or_else(||arg.cv.ill("There were no planes in configuration of MultiPlane."))?;
+		if planes==0
+		{
+			panic!("MultiPlane requires at least one plane.");
+		}
+		Ok(MultiPlaneTopology{
+			plane,
+			planes,
+		})
+	}
+	/// The plane of a router and its index inside the plane.
+	pub fn plane_router(&self, router_index:usize) -> (usize,usize)
+	{
+		let n = self.plane.num_routers();
+		(router_index/n, router_index%n)
+	}
+	/// The location in the plane `plane` corresponding to a location of the base topology.
+	fn location_in_plane(&self, plane:usize, location:Location) -> Location
+	{
+		match location
+		{
+			Location::RouterPort{router_index,router_port} => Location::RouterPort{router_index:plane*self.plane.num_routers()+router_index,router_port},
+			x => x,
+		}
+	}
+}
+
+impl Topology for MultiPlaneTopology
+{
+	fn num_routers(&self) -> usize { self.plane.num_routers()*self.planes }
+	fn num_servers(&self) -> usize { self.plane.num_servers() }
+	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize)
+	{
+		let (plane,local) = self.plane_router(router_index);
+		let (location,link_class) = self.plane.neighbour(local,port);
+		(self.location_in_plane(plane,location),link_class)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		self.plane.server_neighbour(server_index)
+	}
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		let (location,link_class) = self.plane.server_neighbour(server_index);
+		(0..self.planes).map(|plane|(self.location_in_plane(plane,location.clone()),link_class)).collect()
+	}
+	fn server_router_from(&self, server_index:usize, current_router:usize) -> usize
+	{
+		let (plane,_) = self.plane_router(current_router);
+		match self.plane.server_neighbour(server_index).0
+		{
+			Location::RouterPort{router_index,..} => plane*self.plane.num_routers()+router_index,
+			_ => panic!("The server {} is not attached to a router",server_index),
+		}
+	}
+	fn diameter(&self) -> usize { self.plane.diameter() }
+	/// The distance inside the plane of `origin`. Routers in different planes are not connected, and they give the distance between their counterparts in the same plane.
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		self.plane.distance(self.plane_router(origin).1,self.plane_router(destination).1)
+	}
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		self.plane.amount_shortest_paths(self.plane_router(origin).1,self.plane_router(destination).1)
+	}
+	fn average_amount_shortest_paths(&self) -> f32 { self.plane.average_amount_shortest_paths() }
+	fn maximum_degree(&self) -> usize { self.plane.maximum_degree() }
+	fn minimum_degree(&self) -> usize { self.plane.minimum_degree() }
+	fn degree(&self, router_index: usize) -> usize { self.plane.degree(self.plane_router(router_index).1) }
+	fn ports(&self, router_index: usize) -> usize { self.plane.ports(self.plane_router(router_index).1) }
+	fn neighbour_router_iter<'a>(&'a self, router_index:usize) -> Box<dyn Iterator<Item=NeighbourRouterIteratorItem> + 'a>
+	{
+		let (plane,local) = self.plane_router(router_index);
+		let offset = plane*self.plane.num_routers();
+		Box::new(self.plane.neighbour_router_iter(local).map(move |item|NeighbourRouterIteratorItem{neighbour_router:offset+item.neighbour_router,..item}))
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData> { None }
+	fn is_direction_change(&self, router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.plane.is_direction_change(self.plane_router(router_index).1,input_port,output_port)
+	}
+	fn is_link_alive(&self, router_index:usize, port:usize) -> bool
+	{
+		self.plane.is_link_alive(self.plane_router(router_index).1,port)
+	}
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		self.plane.router_role(self.plane_router(router_index).1)
+	}
+	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
+	{
+		let (origin_plane,origin) = self.plane_router(origin);
+		let (destination_plane,destination) = self.plane_router(destination);
+		if origin_plane!=destination_plane { None } else { self.plane.up_down_distance(origin,destination) }
+	}
+}
+
+/**
+Wraps a topology to allow taking its links down and up again during the simulation, as scheduled by the topology `events` of the configuration.
+It is not built from a configuration, but by the simulation when there are such events, see [reconfiguration](crate::reconfiguration).
+
+A link that is down keeps its wiring, so the phits already granted to it still arrive, but it is no longer alive, so the routers do not request it anymore.
+The distances and the diameter avoid the links that are down, which is enough for the routings based on distances.
+Routings with tables are notified by [Routing::on_topology_change](crate::routing::Routing::on_topology_change).
+The amount of shortest paths and the other properties are those of the base topology.
+**/
+#[derive(Debug,Quantifiable)]
+pub struct ReconfigurableLinks
+{
+	/// The base topology.
+	topology: Box<dyn Topology>,
+	///The ports `(router,port)` whose links are down, including both ends of each link.
+	down_ports: BTreeSet<(usize,usize)>,
+	///The distances avoiding the links that are down, or `None` while all of them are up.
+	distance_matrix: Option<Matrix<usize>>,
+	///The diameter avoiding the links that are down.
+	diameter: usize,
+}
+
+impl Topology for ReconfigurableLinks
+{
+	fn num_routers(&self) -> usize { self.topology.num_routers() }
+	fn num_servers(&self) -> usize { self.topology.num_servers() }
+	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize)
+	{
+		self.topology.neighbour(router_index,port)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		self.topology.server_neighbour(server_index)
+	}
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		self.topology.server_neighbours(server_index)
+	}
+	fn server_router_from(&self, server_index:usize, current_router:usize) -> usize
+	{
+		self.topology.server_router_from(server_index,current_router)
+	}
+	fn neighbour_router_iter<'a>(&'a self, router_index:usize) -> Box<dyn Iterator<Item=NeighbourRouterIteratorItem> + 'a>
+	{
+		Box::new(self.topology.neighbour_router_iter(router_index).filter(move |item|!self.down_ports.contains(&(router_index,item.port_index))))
+	}
+	fn diameter(&self) -> usize
+	{
+		if self.distance_matrix.is_some() { self.diameter } else { self.topology.diameter() }
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		match self.distance_matrix
+		{
+			Some(ref matrix) => *matrix.get(origin,destination),
+			None => self.topology.distance(origin,destination),
+		}
+	}
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		self.topology.amount_shortest_paths(origin,destination)
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		self.topology.average_amount_shortest_paths()
+	}
+	fn maximum_degree(&self) -> usize { self.topology.maximum_degree() }
+	fn minimum_degree(&self) -> usize { self.topology.minimum_degree() }
+	fn degree(&self, router_index: usize) -> usize
+	{
+		self.neighbour_router_iter(router_index).count()
+	}
+	fn ports(&self, router_index: usize) -> usize { self.topology.ports(router_index) }
+	fn cartesian_data(&self) -> Option<&CartesianData> { self.topology.cartesian_data() }
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng:Option<&mut StdRng>)->Vec<i32>
+	{
+		self.topology.coordinated_routing_record(coordinates_a,coordinates_b,rng)
+	}
+	fn cartesian_link_dimension(&self, link_class:usize) -> usize { self.topology.cartesian_link_dimension(link_class) }
+	fn cartesian_link_displacement(&self, router_index:usize, port:usize) -> Option<i32>
+	{
+		self.topology.cartesian_link_displacement(router_index,port)
+	}
+	fn is_direction_change(&self, router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.topology.is_direction_change(router_index,input_port,output_port)
+	}
+	fn is_link_alive(&self, router_index:usize, port:usize) -> bool
+	{
+		!self.down_ports.contains(&(router_index,port)) && self.topology.is_link_alive(router_index,port)
+	}
+	fn set_link_alive(&mut self, router_index:usize, port:usize, alive:bool) -> Result<(),Error>
+	{
+		if router_index>=self.num_routers() || port>=self.ports(router_index)
+		{
+			return Err(error!(bad_argument).with_message(format!("There is no port {} in the router {}.",port,router_index)));
+		}
+		let (neighbour_router,neighbour_port) = match self.topology.neighbour(router_index,port).0
+		{
+			Location::RouterPort{router_index:neighbour_router,router_port:neighbour_port} => (neighbour_router,neighbour_port),
+			_ => return Err(error!(bad_argument).with_message(format!("The port {} of the router {} is not a link between routers.",port,router_index))),
+		};
+		if alive
+		{
+			self.down_ports.remove(&(router_index,port));
+			self.down_ports.remove(&(neighbour_router,neighbour_port));
+		}
+		else
+		{
+			self.down_ports.insert((router_index,port));
+			self.down_ports.insert((neighbour_router,neighbour_port));
+		}
+		if self.down_ports.is_empty()
+		{
+			self.distance_matrix = None;
+		}
+		else
+		{
+			//The breadth first searches only see the links alive. The routers disconnected get `usize::MAX`.
+			let matrix = self.compute_distance_matrix(None);
+			let n = self.num_routers();
+			self.diameter = (0..n).flat_map(|origin|(0..n).map(move |target|(origin,target))).map(|(origin,target)|*matrix.get(origin,target)).max().unwrap_or(0);
+			self.distance_matrix = Some(matrix);
+		}
+		Ok(())
+	}
+	fn router_role(&self, router_index:usize) -> Option<String>
+	{
+		self.topology.router_role(router_index)
+	}
+	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
+	{
+		self.topology.up_down_distance(origin,destination)
+	}
+}
+
+impl ReconfigurableLinks
+{
+	pub fn new(topology:Box<dyn Topology>) -> ReconfigurableLinks
+	{
+		ReconfigurableLinks{
+			topology,
+			down_ports: BTreeSet::new(),
+			distance_matrix: None,
+			diameter: 0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config_parser;
+	use crate::Plugs;
+	use rand::SeedableRng;
+	#[test]
+	fn concentration()
+	{
+		let cv = match config_parser::parse("Concentration{topology:Hamming{sides:[2,2],servers_per_router:1},servers:[4,2,0,1]}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		topology.check_adjacency_consistency(None);
+		assert_eq!(topology.num_servers(),7);
+		let routers : Vec<usize> = (0..7).map(|server|match topology.server_neighbour(server).0
+		{
+			Location::RouterPort{router_index,..} => router_index,
+			_ => panic!("server {} is not attached to a router",server),
+		}).collect();
+		assert_eq!(routers,vec![0,0,0,0,1,1,3]);
+		assert_eq!((0..4).map(|router|topology.ports(router)).collect::<Vec<usize>>(),vec![6,4,2,3]);
+		assert_eq!(topology.server_neighbour(6).1,topology.server_neighbour(0).1);
+		// Each router has a different number of servers, so no two routers are symmetric.
+		assert_eq!(topology.symmetry_classes(),vec![0,1,2,3]);
+	}
+	#[test]
+	fn multi_rail()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let mut rail_routers = |text:&str| {
+			let cv = match config_parser::parse(text)
+			{
+				Ok(config_parser::Token::Value(value)) => value,
+				_ => panic!("could not parse the topology"),
+			};
+			let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+			topology.check_adjacency_consistency(None);
+			(0..topology.num_servers()).map(|server|topology.server_neighbours(server).iter().map(|(location,_)|match location
+			{
+				Location::RouterPort{router_index,..} => *router_index,
+				_ => panic!("server {} is not attached to a router",server),
+			}).collect()).collect::<Vec<Vec<usize>>>()
+		};
+		assert_eq!(rail_routers("MultiRail{topology:Hamming{sides:[4],servers_per_router:2},rails:2}"),vec![vec![0,0],vec![1,1],vec![2,2],vec![3,3]]);
+		assert_eq!(rail_routers("MultiRail{topology:Hamming{sides:[4],servers_per_router:2},rails:2,grouping:Interleaved}"),vec![vec![0,2],vec![0,2],vec![1,3],vec![1,3]]);
+	}
+	#[test]
+	fn multi_plane()
+	{
+		let cv = match config_parser::parse("MultiPlane{plane:Hamming{sides:[4],servers_per_router:2},planes:3}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		topology.check_adjacency_consistency(None);
+		assert_eq!(topology.num_routers(),12);
+		assert_eq!(topology.num_servers(),8);
+		let planes : Vec<usize> = topology.server_neighbours(5).iter().map(|(location,_)|match location
+		{
+			Location::RouterPort{router_index,..} => *router_index,
+			_ => panic!("server 5 is not attached to a router"),
+		}).collect();
+		assert_eq!(planes,vec![2,6,10]);
+		assert!(topology.neighbour_router_iter(5).all(|item|(4..8).contains(&item.neighbour_router)));
+		assert_eq!(topology.server_router_from(5,0),2);
+		assert_eq!(topology.server_router_from(5,9),10);
+	}
+	#[test]
+	fn symmetry_classes()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let mut classes = |text:&str| {
+			let cv = match config_parser::parse(text)
+			{
+				Ok(config_parser::Token::Value(value)) => value,
+				_ => panic!("could not parse the topology"),
+			};
+			new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng}).symmetry_classes()
+		};
+		assert_eq!(classes("Hamming{sides:[4,3],servers_per_router:2}"),vec![0;12]);
+		assert_eq!(classes("Torus{sides:[5],servers_per_router:1}"),vec![0;5]);
+		// The ends of a path, the routers next to them, and the middle one.
+		assert_eq!(classes("Mesh{sides:[5],servers_per_router:1}"),vec![0,1,2,1,0]);
+		// Routers in the same column are symmetric, but the columns are not.
+		assert_eq!(classes("Mesh{sides:[3,2],servers_per_router:1}"),vec![0,1,0,0,1,0]);
+	}
+	#[test]
+	fn targeted_link_faults()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let base_cv = match config_parser::parse("Hamming{sides:[4,4],servers_per_router:1}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let base = new_topology(TopologyBuilderArgument{cv:&base_cv,plugs:&plugs,rng:&mut rng});
+		let cv = match config_parser::parse("RandomLinkFaults{topology:Hamming{sides:[4,4],servers_per_router:1},link_classes:[0],fraction:0.5,links:[[0,1]],keep_connected:true,seed:1}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		topology.check_adjacency_consistency(None);
+		let mut dead_ports = 0;
+		for router in 0..topology.num_routers()
+		{
+			for port in 0..topology.ports(router)
+			{
+				if !topology.is_link_alive(router,port)
+				{
+					dead_ports += 1;
+					assert!(matches!(topology.neighbour(router,port).0,Location::None));
+					// Only links of the first dimension are removed.
+					assert_eq!(base.neighbour(router,port).1,0);
+				}
+			}
+		}
+		// The explicit link and half of the other 23 links of the first dimension, two ports each.
+		assert_eq!(dead_ports,2*(1+12));
+		let port_to_1 = (0..base.ports(0)).find(|&port|matches!(base.neighbour(0,port).0,Location::RouterPort{router_index:1,..})).unwrap();
+		assert!(!topology.is_link_alive(0,port_to_1));
+		assert!(topology.diameter()<=base.num_routers());
+		// In a ring only one link can be removed while keeping it connected.
+		let ring_cv = match config_parser::parse("RandomLinkFaults{topology:Torus{sides:[6],servers_per_router:1},amount:2,keep_connected:true}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||new_topology(TopologyBuilderArgument{cv:&ring_cv,plugs:&plugs,rng:&mut StdRng::seed_from_u64(0)})));
+		assert!(result.is_err());
+	}
+	#[test]
+	fn reconfigurable_links()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let cv = match config_parser::parse("Torus{sides:[8],servers_per_router:1}")
+		{
+			Ok(config_parser::Token::Value(value)) => value,
+			_ => panic!("could not parse the topology"),
+		};
+		let mut topology = ReconfigurableLinks::new(new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng}));
+		let port_to_1 = (0..topology.ports(0)).find(|&port|matches!(topology.neighbour(0,port).0,Location::RouterPort{router_index:1,..})).unwrap();
+		let port_to_0 = match topology.neighbour(0,port_to_1).0
+		{
+			Location::RouterPort{router_port,..} => router_port,
+			_ => unreachable!(),
+		};
+		assert_eq!(topology.diameter(),4);
+		topology.set_link_alive(0,port_to_1,false).expect("the link could not go down");
+		// Both ends go down and the ring becomes a path.
+		assert!(!topology.is_link_alive(0,port_to_1));
+		assert!(!topology.is_link_alive(1,port_to_0));
+		assert_eq!(topology.degree(0),1);
+		assert_eq!(topology.distance(0,1),7);
+		assert_eq!(topology.diameter(),7);
+		// The link may be given by its other end.
+		topology.set_link_alive(1,port_to_0,true).expect("the link could not go up");
+		assert!(topology.is_link_alive(0,port_to_1));
+		assert_eq!(topology.distance(0,1),1);
+		assert_eq!(topology.diameter(),4);
+		// Ports towards servers are not links between routers.
+		let server_port = (0..topology.ports(0)).find(|&port|matches!(topology.neighbour(0,port).0,Location::ServerPort(_))).unwrap();
+		assert!(topology.set_link_alive(0,server_port,false).is_err());
+	}
+}
@@ -435,7 +435,7 @@ impl Topology for Projective
 
 impl Projective
 {
-	pub fn new(arg:TopologyBuilderArgument) -> Projective
+	pub fn new(arg:TopologyBuilderArgument) -> Result<Projective,Error>
 	{
 		let mut prime=None;
 		let mut servers_per_router=None;
@@ -443,7 +443,7 @@ impl Projective
 		{
 			if cv_name!="Projective"
 			{
-				panic!("A Projective must be created from a `Projective` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A Projective must be created from a `Projective` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -452,28 +452,28 @@ impl Projective
 					"prime" => match value
 					{
 						&ConfigurationValue::Number(f) => prime=Some(f as usize),
-						_ => panic!("bad value for prime"),
+						_ => return Err(value.ill("bad value for prime")),
 					},
 					"servers_per_router" => match value
 					{
 						&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
-						_ => panic!("bad value for servers_per_router"),
+						_ => return Err(value.ill("bad value for servers_per_router")),
 					},
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in RandomRegularGraph",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in RandomRegularGraph",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a NeighboursLists from a non-Object");
+			return Err(arg.cv.ill("Trying to create a NeighboursLists from a non-Object"));
 		}
-		let prime=prime.expect("There were no prime");
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
-		Projective{
+		let prime=prime.ok_or_else(||arg.cv.ill("There were no prime"))?;
+		let servers_per_router=servers_per_router.ok_or_else(||arg.cv.ill("There were no servers_per_router"))?;
+		Ok(Projective{
 			plane: FlatGeometryCache::new_prime(prime).unwrap_or_else(|_|panic!("{} is not prime, which is required for the Projective topology",prime)),
 			servers_per_router,
-		}
+		})
 	}
 }
 
@@ -653,7 +653,7 @@ impl Topology for LeviProjective
 
 impl LeviProjective
 {
-	pub fn new(arg:TopologyBuilderArgument) -> LeviProjective
+	pub fn new(arg:TopologyBuilderArgument) -> Result<LeviProjective,Error>
 	{
 		let mut prime=None;
 		let mut servers_per_router=None;
@@ -661,7 +661,7 @@ impl LeviProjective
 		{
 			if cv_name!="LeviProjective"
 			{
-				panic!("A LeviProjective must be created from a `LeviProjective` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A LeviProjective must be created from a `LeviProjective` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -670,28 +670,28 @@ impl LeviProjective
 					"prime" => match value
 					{
 						&ConfigurationValue::Number(f) => prime=Some(f as usize),
-						_ => panic!("bad value for prime"),
+						_ => return Err(value.ill("bad value for prime")),
 					},
 					"servers_per_router" => match value
 					{
 						&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
-						_ => panic!("bad value for servers_per_router"),
+						_ => return Err(value.ill("bad value for servers_per_router")),
 					},
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in RandomRegularGraph",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in RandomRegularGraph",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a NeighboursLists from a non-Object");
+			return Err(arg.cv.ill("Trying to create a NeighboursLists from a non-Object"));
 		}
-		let prime=prime.expect("There were no prime");
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
-		LeviProjective{
+		let prime=prime.ok_or_else(||arg.cv.ill("There were no prime"))?;
+		let servers_per_router=servers_per_router.ok_or_else(||arg.cv.ill("There were no servers_per_router"))?;
+		Ok(LeviProjective{
 			plane: FlatGeometryCache::new_prime(prime).unwrap_or_else(|_|panic!("{} is not prime, which is required for the Projective topology",prime)),
 			servers_per_router,
-		}
+		})
 	}
 }
 
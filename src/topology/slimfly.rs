@@ -10,6 +10,7 @@ use crate::{
 	config_parser::ConfigurationValue,
 	quantify::Quantifiable,
 };
+use crate::error::Error;
 
 //For the slimfly we need for x,y in the field, i integer
 //pow(x,i)
@@ -118,6 +119,162 @@ impl FlatRing for IntegerIdealRing
 	}
 }
 
+/**
+The finite field GF(p^k) of `q=p^k` elements, with `p` prime.
+Each element is a polynomial of degree lower than `k` with coefficients in the integers modulo `p`, and it is indexed as the number written with these coefficients as digits in base `p`, with the constant term as least significant digit.
+The products are reduced modulo a primitive polynomial, which is searched when building the field.
+**/
+#[derive(Debug,Quantifiable)]
+struct PolynomialField
+{
+	prime: usize,
+	exponent: usize,
+	///The coefficients of the monic polynomial modulo which the products are reduced, from the constant term to the leading 1.
+	modulus: Vec<usize>,
+}
+
+impl PolynomialField
+{
+	fn new(prime:usize, exponent:usize) -> PolynomialField
+	{
+		if exponent==0
+		{
+			panic!("The exponent of the field size must be at least 1");
+		}
+		let size = prime.pow(exponent as u32);
+		//Search for a polynomial for which `x` is primitive. Such a polynomial is irreducible, as every nonzero element is a power of `x`.
+		for lower in 0..size
+		{
+			let mut modulus = PolynomialField::digits(lower,prime,exponent);
+			if modulus[0]==0
+			{
+				continue;
+			}
+			modulus.push(1);
+			let field = PolynomialField{prime,exponent,modulus};
+			let generator = field.index_of_x();
+			let mut current = generator;
+			let mut order = 1;
+			while current!=1 && order<size
+			{
+				current = field.mul(current,generator);
+				order+=1;
+			}
+			if current==1 && order==size-1
+			{
+				return field;
+			}
+		}
+		panic!("Could not find a primitive polynomial of degree {} over the integers modulo {}. Is {} prime?",exponent,prime,prime);
+	}
+	///The coefficients of the element with the given index.
+	fn digits(index:usize, prime:usize, exponent:usize) -> Vec<usize>
+	{
+		let mut rem = index;
+		(0..exponent).map(|_|{
+			let digit = rem % prime;
+			rem /= prime;
+			digit
+		}).collect()
+	}
+	fn pack(&self, digits:&[usize]) -> usize
+	{
+		digits.iter().rev().fold(0,|acc,digit|acc*self.prime+digit)
+	}
+	///The index of the polynomial `x`, which is primitive in the field.
+	fn index_of_x(&self) -> usize
+	{
+		if self.exponent==1
+		{
+			//`x` is the only root of the modulus, so its value is minus the constant term.
+			(self.prime - self.modulus[0]) % self.prime
+		}
+		else
+		{
+			self.prime
+		}
+	}
+}
+
+impl FlatRing for PolynomialField
+{
+	fn size(&self) -> usize
+	{
+		self.prime.pow(self.exponent as u32)
+	}
+	fn index_from_i32(&self, integer: i32) -> usize
+	{
+		integer.rem_euclid(self.prime as i32) as usize
+	}
+	fn add(&self, a:usize, b:usize) -> usize
+	{
+		let a = PolynomialField::digits(a,self.prime,self.exponent);
+		let b = PolynomialField::digits(b,self.prime,self.exponent);
+		let sum:Vec<usize> = a.iter().zip(b.iter()).map(|(x,y)|(x+y)%self.prime).collect();
+		self.pack(&sum)
+	}
+	fn sub(&self, a:usize, b:usize) -> usize
+	{
+		let a = PolynomialField::digits(a,self.prime,self.exponent);
+		let b = PolynomialField::digits(b,self.prime,self.exponent);
+		let difference:Vec<usize> = a.iter().zip(b.iter()).map(|(x,y)|(self.prime+x-y)%self.prime).collect();
+		self.pack(&difference)
+	}
+	fn mul(&self, a:usize, b:usize) -> usize
+	{
+		let k = self.exponent;
+		let a = PolynomialField::digits(a,self.prime,k);
+		let b = PolynomialField::digits(b,self.prime,k);
+		let mut product = vec![0;2*k-1];
+		for (i,x) in a.iter().enumerate()
+		{
+			for (j,y) in b.iter().enumerate()
+			{
+				product[i+j] = (product[i+j] + x*y) % self.prime;
+			}
+		}
+		//Reduce the terms of degree k or greater, beginning with the greatest.
+		for degree in (k..2*k-1).rev()
+		{
+			let coefficient = product[degree];
+			if coefficient!=0
+			{
+				for i in 0..k
+				{
+					let target = degree-k+i;
+					product[target] = (product[target] + self.prime - coefficient*self.modulus[i]%self.prime) % self.prime;
+				}
+				product[degree]=0;
+			}
+		}
+		self.pack(&product[0..k])
+	}
+	fn is_primitive(&self, a:usize) -> bool
+	{
+		if a==0
+		{
+			return false;
+		}
+		//The order of `a` is `size-1` when no power by `(size-1)/r` gives 1, for each prime factor `r` of `size-1`.
+		let order = self.size()-1;
+		let mut rem = order;
+		let mut factor = 2;
+		let mut factors = vec![];
+		while rem>1
+		{
+			if rem%factor==0
+			{
+				factors.push(factor);
+				while rem%factor==0
+				{
+					rem/=factor;
+				}
+			}
+			factor+=1;
+		}
+		factors.into_iter().all(|r|self.pow(a,(order/r) as u32)!=1)
+	}
+}
 
 struct SlimFlyCoordinates
 {
@@ -338,16 +495,17 @@ impl Topology for SlimFly
 
 impl SlimFly
 {
-	pub fn new(arg:TopologyBuilderArgument) -> SlimFly
+	pub fn new(arg:TopologyBuilderArgument) -> Result<SlimFly,Error>
 	{
 		let mut prime=None;
+		let mut exponent=1;
 		let mut primitive=None;
 		let mut servers_per_router=None;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
 		{
 			if cv_name!="SlimFly"
 			{
-				panic!("A SlimFly must be created from a `SlimFly` object not `{}`",cv_name);
+				return Err(arg.cv.ill(&format!("A SlimFly must be created from a `SlimFly` object not `{}`",cv_name)));
 			}
 			for &(ref name,ref value) in cv_pairs
 			{
@@ -356,58 +514,74 @@ impl SlimFly
 					"prime" => match value
 					{
 						&ConfigurationValue::Number(f) => prime=Some(f as usize),
-						_ => panic!("bad value for prime"),
+						_ => return Err(value.ill("bad value for prime")),
+					},
+					"exponent" => match value
+					{
+						&ConfigurationValue::Number(f) => exponent=f as usize,
+						_ => return Err(value.ill("bad value for exponent")),
 					},
 					"primitive" => match value
 					{
 						&ConfigurationValue::Number(f) => primitive=Some(f as usize),
-						_ => panic!("bad value for primitive"),
+						_ => return Err(value.ill("bad value for primitive")),
 					},
 					"servers_per_router" => match value
 					{
 						&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
-						_ => panic!("bad value for servers_per_router"),
+						_ => return Err(value.ill("bad value for servers_per_router")),
 					},
 					"legend_name" => (),
-					_ => panic!("Nothing to do with field {} in RandomRegularGraph",name),
+					_ => return Err(arg.cv.ill(&format!("Nothing to do with field {} in RandomRegularGraph",name))),
 				}
 			}
 		}
 		else
 		{
-			panic!("Trying to create a NeighboursLists from a non-Object");
+			return Err(arg.cv.ill("Trying to create a NeighboursLists from a non-Object"));
 		}
-		let prime=prime.expect("There were no prime");
-		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
-		let field = IntegerIdealRing{modulo:prime};
+		let prime=prime.ok_or_else(||arg.cv.ill("There were no prime"))?;
+		let servers_per_router=servers_per_router.ok_or_else(||arg.cv.ill("There were no servers_per_router"))?;
+		let field:Box<dyn FlatRing> = if exponent==1
+		{
+			Box::new(IntegerIdealRing{modulo:prime})
+		}
+		else
+		{
+			Box::new(PolynomialField::new(prime,exponent))
+		};
+		//The size of the field, `q=prime^exponent`.
+		let q = field.size();
 		let primitive=primitive.unwrap_or_else(||{
-			let n=field.size();
-			(2..n).find(|x|field.is_primitive(*x)).unwrap_or_else(||panic!("Could not find a primtive element in the ring {:?}",field))
+			(2..q).find(|x|field.is_primitive(*x)).unwrap_or_else(||panic!("Could not find a primtive element in the ring {:?}",field))
 		});
-		let epsilon:i32 = {
-			let p4 = prime % 4;
-			if p4==3 {-1} else {1}
+		let epsilon:i32 = match q % 4
+		{
+			3 => -1,
+			0 => 0,
+			2 => return Err(arg.cv.ill(&format!("There is no SlimFly for q={}",q))),
+			_ => 1,
 		};
 		let paley_set:Vec<usize>=match epsilon
 		{
 			1 =>
 			{
-				let limit :u32 = (prime as u32-1)/2;
+				let limit :u32 = (q as u32-1)/2;
 				(0..limit).map(|k|field.pow(primitive,2*k)).collect()
 			},
 			-1 =>
 			{
-				let limit :u32= (prime as u32-3)/4;
-				(0..=limit).map(|k|2*k).chain( (0..=limit).map(|k|(prime as u32-1)/2 + 2*k) ).map(|exp|field.pow(primitive,exp)).collect()
+				let limit :u32= (q as u32-3)/4;
+				(0..=limit).map(|k|2*k).chain( (0..=limit).map(|k|(q as u32-1)/2 + 2*k) ).map(|exp|field.pow(primitive,exp)).collect()
 			},
 			0 =>
 			{
-				let limit :u32= prime as u32/2;
+				let limit :u32= q as u32/2;
 				(0..limit).map(|k|field.pow(primitive,2*k)).collect()
 			},
-			_ => panic!("{} cannot be a prime",prime),
+			_ => panic!("{} cannot be a prime power",q),
 		};
-		println!("primitive={} paley_set={:?} len={} (q-eps)/2={}",primitive,paley_set,paley_set.len(),(prime as i32-epsilon)/2);
+		println!("primitive={} paley_set={:?} len={} (q-eps)/2={}",primitive,paley_set,paley_set.len(),(q as i32-epsilon)/2);
 		let second_paley_set=paley_set.iter().map(|x|field.mul(*x,primitive)).collect();
 		let paley_sets:[Vec<usize>;2]=[paley_set,second_paley_set];
 		//let neg_paley_sets=(0..=1).map(|b|(0..paley_sets[b].len()).map(|k|{
@@ -425,15 +599,77 @@ impl SlimFly
 			}).collect()};
 			[builder(0),builder(1)]
 		};
-		SlimFly{
-			field: Box::new(field),
+		Ok(SlimFly{
+			field,
 			primitive,
 			servers_per_router,
 			paley_sets,
 			neg_paley_sets,
-		}
+		})
 	}
 }
 
 
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::SeedableRng;
+	use crate::topology::new_topology;
+	#[test]
+	fn polynomial_field()
+	{
+		for (prime,exponent) in [(2,2),(2,3),(3,2),(5,2),(3,3)]
+		{
+			let field = PolynomialField::new(prime,exponent);
+			let q = field.size();
+			for a in 0..q
+			{
+				assert_eq!(field.sub(field.add(a,7%q),7%q),a);
+				if a!=0
+				{
+					assert!( (1..q).any(|b|field.mul(a,b)==1), "{} has no inverse in GF({}^{})",a,prime,exponent );
+				}
+				for b in 0..q
+				{
+					assert_eq!(field.mul(a,b),field.mul(b,a));
+					let c = (a+b+1)%q;
+					assert_eq!(field.mul(a,field.add(b,c)),field.add(field.mul(a,b),field.mul(a,c)), "not distributive in GF({}^{})",prime,exponent);
+				}
+			}
+			let primitive = (2..q).find(|&x|field.is_primitive(x)).expect("no primitive element");
+			let mut powers:Vec<usize> = (0..q-1).map(|e|field.pow(primitive,e as u32)).collect();
+			powers.sort();
+			assert_eq!(powers,(1..q).collect::<Vec<_>>());
+		}
+	}
+	/// The SlimFly over prime powers must be consistent, regular of degree `(3q-epsilon)/2` and of diameter 2.
+	#[test]
+	fn slimfly_prime_power()
+	{
+		let plugs = crate::Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		for (prime,exponent,degree) in [(2,2,6),(2,3,12),(3,2,13),(5,1,7),(7,1,11)]
+		{
+			let cv = ConfigurationValue::Object("SlimFly".to_string(),vec![
+				("prime".to_string(),ConfigurationValue::Number(prime as f64)),
+				("exponent".to_string(),ConfigurationValue::Number(exponent as f64)),
+				("servers_per_router".to_string(),ConfigurationValue::Number(1.0)),
+			]);
+			let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+			topology.check_adjacency_consistency(Some(3));
+			assert_eq!(topology.maximum_degree(),degree);
+			let n = topology.num_routers();
+			let distances = topology.compute_distance_matrix(None);
+			for origin in 0..n
+			{
+				for destination in 0..n
+				{
+					assert!(*distances.get(origin,destination)<=2, "diameter greater than 2 for q={}^{}",prime,exponent);
+					assert_eq!(topology.distance(origin,destination),*distances.get(origin,destination), "wrong distance for q={}^{}",prime,exponent);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,203 @@
+/*!
+
+Arrival processes, deciding the cycles in which the tasks of a traffic generate their messages.
+
+A traffic with a `load` given as a fraction of the link capacity and messages of `message_size` phits has an average rate of `load/message_size` messages per cycle.
+The [HomogeneousTraffic](super::new_traffic) selects how these messages arrive with its optional `arrival` field.
+```ignore
+HomogeneousTraffic{
+	pattern: Uniform,
+	tasks: 1000,
+	load: 0.5,
+	message_size: 16,
+	arrival: Poisson,//or Bernoulli, Deterministic, or MarkovOnOff{p_on:0.01, p_off:0.03}
+}
+```
+* `Bernoulli` generates a message each cycle with probability equal to the rate. It is the default.
+* `Poisson` generates the messages with exponentially distributed inter-arrival times. When several arrivals fall in the same cycle they are generated in the following cycles, one per cycle.
+* `Deterministic` generates the messages with a fixed inter-arrival time of the inverse of the rate. Each task begins at a random phase, so that the tasks are not synchronized.
+* `MarkovOnOff{p_on, p_off, load_on}` alternates each task between an ON and an OFF state. An OFF task switches to ON with probability `p_on` each cycle and an ON task switches to OFF with probability `p_off`.
+  While ON, the task generates as a Bernoulli process with `load_on`; while OFF it does not generate. Thus the average load is `load_on*p_on/(p_on+p_off)`.
+  Instead of `load_on` the traffic `load` may be given, from which `load_on` is computed to get that average.
+
+In every case the process is open-loop: the tasks do not wait for the network. The messages generated when the queue of the server is full are lost and counted as missed generations, so the `server_queue_size` of the simulation bounds the queue.
+
+*/
+
+use quantifiable_derive::Quantifiable;//the derive macro
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::match_object_panic;
+
+///The kind of an [ArrivalProcess] with its parameters.
+#[derive(Debug,Clone,Quantifiable)]
+pub enum ArrivalKind
+{
+	///A message each cycle with some probability.
+	Bernoulli,
+	///Exponential inter-arrival times.
+	Poisson,
+	///Fixed inter-arrival times.
+	Deterministic,
+	///Bernoulli while ON, nothing while OFF. The probabilities of switching from OFF to ON and from ON to OFF.
+	MarkovOnOff{p_on:f64,p_off:f64},
+}
+
+/**
+The arrival process of the messages of the tasks of a traffic, as described in the [arrival](self) module.
+It keeps the state of each task.
+**/
+#[derive(Debug,Quantifiable)]
+pub struct ArrivalProcess
+{
+	kind: ArrivalKind,
+	///The load offered by a generating task, in phits per cycle. For `MarkovOnOff` this is the load while ON.
+	load: f32,
+	///The size of the messages, to convert the load into messages per cycle.
+	message_size: usize,
+	///For `Poisson` and `Deterministic`, the instant of the next arrival of each task.
+	next_arrival: Vec<f64>,
+	///For `MarkovOnOff`, whether each task is ON.
+	on: Vec<bool>,
+}
+
+impl ArrivalProcess
+{
+	/**
+	Builds the arrival process from the `arrival` field of a traffic, or a Bernoulli process when it is not given.
+	The `load` is the one of the traffic, which is required except for a `MarkovOnOff` with its own `load_on`.
+	**/
+	pub fn new(cv:Option<&ConfigurationValue>, load:Option<f64>, message_size:usize, tasks:usize, rng:&mut StdRng) -> ArrivalProcess
+	{
+		let name = match cv
+		{
+			None => "Bernoulli",
+			Some(ConfigurationValue::Object(name,_)) => name.as_str(),
+			Some(other) => panic!("bad value for arrival: {}",other),
+		};
+		let (kind,load) = match name
+		{
+			"Bernoulli" | "Poisson" | "Deterministic" =>
+			{
+				if let Some(cv) = cv
+				{
+					match_object_panic!(cv,[name],_value,);
+				}
+				let kind = match name
+				{
+					"Bernoulli" => ArrivalKind::Bernoulli,
+					"Poisson" => ArrivalKind::Poisson,
+					_ => ArrivalKind::Deterministic,
+				};
+				(kind,load.expect("There were no load"))
+			},
+			"MarkovOnOff" =>
+			{
+				let mut p_on=None;
+				let mut p_off=None;
+				let mut load_on=None;
+				match_object_panic!(cv.unwrap(),"MarkovOnOff",value,
+					"p_on" => p_on=Some(value.as_f64().expect("bad value for p_on")),
+					"p_off" => p_off=Some(value.as_f64().expect("bad value for p_off")),
+					"load_on" => load_on=Some(value.as_f64().expect("bad value for load_on")),
+				);
+				let p_on=p_on.expect("There were no p_on");
+				let p_off=p_off.expect("There were no p_off");
+				assert!(0.0<p_on && p_on<=1.0 && 0.0<p_off && p_off<=1.0,"The probabilities of MarkovOnOff must be in (0,1]");
+				let load_on = match (load_on,load)
+				{
+					(Some(_),Some(_)) => panic!("Give either the load of the traffic or the load_on of MarkovOnOff, but not both"),
+					(Some(load_on),None) => load_on,
+					(None,Some(load)) => load*(p_on+p_off)/p_on,
+					(None,None) => panic!("There were no load nor load_on"),
+				};
+				(ArrivalKind::MarkovOnOff{p_on,p_off},load_on)
+			},
+			_ => panic!("Unknown arrival process {}",name),
+		};
+		let load = load as f32;
+		let mut process = ArrivalProcess{
+			kind,
+			load,
+			message_size,
+			next_arrival: vec![],
+			on: vec![],
+		};
+		let rate = process.rate_while_generating();
+		match process.kind
+		{
+			ArrivalKind::Bernoulli => (),
+			ArrivalKind::Poisson => process.next_arrival = (0..tasks).map(|_|exponential(rate,rng)).collect(),
+			ArrivalKind::Deterministic => process.next_arrival = (0..tasks).map(|_|rng.gen_range(0f64..1f64)/rate).collect(),
+			ArrivalKind::MarkovOnOff{p_on,p_off} =>
+			{
+				//Begin at the stationary distribution.
+				let probability_on = p_on/(p_on+p_off);
+				process.on = (0..tasks).map(|_|rng.gen_bool(probability_on)).collect();
+			},
+		}
+		process
+	}
+	///The probability of generating in a cycle of a Bernoulli process, or of an ON task in `MarkovOnOff`.
+	fn bernoulli_probability(&self) -> f32
+	{
+		let r=self.load/self.message_size as f32;
+		if r>1.0 { 1.0 } else { r }
+	}
+	///The messages per cycle of a task while it is generating.
+	fn rate_while_generating(&self) -> f64
+	{
+		self.load as f64/self.message_size as f64
+	}
+	///The average messages per cycle of each task.
+	pub fn average_rate(&self) -> f64
+	{
+		let rate = self.rate_while_generating();
+		match self.kind
+		{
+			ArrivalKind::MarkovOnOff{p_on,p_off} => rate*p_on/(p_on+p_off),
+			_ => rate,
+		}
+	}
+	///Whether the `task` generates a message in this `cycle`. To be called once each cycle.
+	pub fn should_generate(&mut self, task:usize, cycle:Time, rng:&mut StdRng) -> bool
+	{
+		match self.kind
+		{
+			ArrivalKind::Bernoulli => rng.gen_range(0f32..1f32)<self.bernoulli_probability(),
+			ArrivalKind::Poisson | ArrivalKind::Deterministic =>
+			{
+				if self.next_arrival[task] > cycle as f64
+				{
+					return false;
+				}
+				let rate = self.rate_while_generating();
+				self.next_arrival[task] += match self.kind
+				{
+					ArrivalKind::Poisson => exponential(rate,rng),
+					_ => 1.0/rate,
+				};
+				true
+			},
+			ArrivalKind::MarkovOnOff{p_on,p_off} =>
+			{
+				let on = &mut self.on[task];
+				let switch = rng.gen_bool(if *on { p_off } else { p_on });
+				if switch
+				{
+					*on = !*on;
+				}
+				*on && rng.gen_range(0f32..1f32)<self.bernoulli_probability()
+			},
+		}
+	}
+}
+
+///A sample of the exponential distribution with the given `rate`.
+fn exponential(rate:f64, rng:&mut StdRng) -> f64
+{
+	-(1.0-rng.gen_range(0f64..1f64)).ln()/rate
+}
@@ -1,18 +1,19 @@
 use crate::AsMessage;
-use crate::new_traffic;
-use crate::pattern::{new_pattern, PatternBuilderArgument};
+use crate::try_new_traffic;
+use crate::pattern::{try_new_pattern, PatternBuilderArgument};
 use std::collections::{BTreeSet, VecDeque};
 use std::convert::TryInto;
 use std::rc::Rc;
 use quantifiable_derive::Quantifiable;
 use rand::prelude::StdRng;
-use rand::Rng;
-use crate::{match_object_panic, Message, Time};
+use crate::{match_object, error, source_location, Message, Time};
 use crate::pattern::Pattern;
 use crate::topology::Topology;
 use crate::traffic::{TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
-use crate::traffic::TaskTrafficState::{Finished, FinishedGenerating, Generating, UnspecifiedWait};
+use crate::traffic::arrival::ArrivalProcess;
+use crate::traffic::TaskTrafficState::{Finished, FinishedGenerating, Generating, UnspecifiedWait, WaitingData};
 use crate::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 
 /**
 Traffic in which all messages have same size, follow the same pattern, and there is no change with time.
@@ -23,6 +24,8 @@ HomogeneousTraffic{
 	tasks:1000,
 	load: 0.9,
 	message_size: 16,
+	class: 1,//optional, the traffic class of the messages. By default 0.
+	arrival: Poisson,//optional, the arrival process of the messages. By default Bernoulli. See the arrival module.
 }
 ```
 **/
@@ -36,8 +39,10 @@ pub struct Homogeneous
 	pattern: Box<dyn Pattern>,
 	///The size of each sent message.
 	message_size: usize,
-	///The load offered to the network. Proportion of the cycles that should be injecting phits.
-	load: f32,
+	///The process generating the messages, with the load offered to the network.
+	arrival: ArrivalProcess,
+	///The traffic class of the messages.
+	class: usize,
 	///Set of generated messages.
 	generated_messages: BTreeSet<u128>,
     ///The id of the next message to generate.
@@ -67,6 +72,9 @@ impl Traffic for Homogeneous
 			creation_cycle: cycle,
 			payload: id.to_le_bytes().into(),
             id_traffic: None,
+            routing_hints: None,
+            tag: None,
+            class: self.class,
         });
 		//self.generated_messages.insert(message.as_ref() as *const Message);
 		self.generated_messages.insert(id);
@@ -74,8 +82,7 @@ impl Traffic for Homogeneous
 	}
 	fn probability_per_cycle(&self, _task:usize) -> f32
 	{
-		let r=self.load/self.message_size as f32;
-		//println!("load={} r={} size={}",self.load,r,self.message_size);
+		let r=self.arrival.average_rate() as f32;
 		if r>1.0
 		{
 			1.0
@@ -97,17 +104,8 @@ impl Traffic for Homogeneous
     {
         false
     }
-    fn should_generate(&mut self, task: usize, _cycle: Time, rng: &mut StdRng) -> bool {
-        let rate= self.probability_per_cycle(task);
-        if rate>1.0
-        {
-            true
-        }
-        else
-        {
-            let random= rng.gen_range(0f32..1f32);
-            random<rate
-        }
+    fn should_generate(&mut self, task: usize, cycle: Time, rng: &mut StdRng) -> bool {
+        self.arrival.should_generate(task,cycle,rng)
     }
 	fn task_state(&self, _task:usize, _cycle:Time) -> Option<TaskTrafficState>
 	{
@@ -121,31 +119,36 @@ impl Traffic for Homogeneous
 
 impl Homogeneous
 {
-	pub fn new(arg:TrafficBuilderArgument) -> Homogeneous
+	pub fn new(arg:TrafficBuilderArgument) -> Result<Homogeneous,Error>
 	{
 		let mut tasks=None;
 		let mut load=None;
 		let mut pattern=None;
 		let mut message_size=None;
-		match_object_panic!(arg.cv,"HomogeneousTraffic",value,
-			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"tasks" | "servers" => tasks=Some(value.as_f64().expect("bad value for tasks") as usize),
-			"load" => load=Some(value.as_f64().expect("bad value for load") as f32),
-			"message_size" => message_size=Some(value.as_f64().expect("bad value for message_size") as usize),
+		let mut class=0;
+		let mut arrival=None;
+		match_object!(arg.cv,"HomogeneousTraffic",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"tasks" | "servers" => tasks=Some(value.as_f64()? as usize),
+			"load" => load=Some(value.as_f64()?),
+			"message_size" => message_size=Some(value.as_f64()? as usize),
+			"class" => class=value.as_usize()?,
+			"arrival" => arrival=Some(value),
 		);
-		let tasks=tasks.expect("There were no tasks");
-		let message_size=message_size.expect("There were no message_size");
-		let load=load.expect("There were no load");
-		let mut pattern=pattern.expect("There were no pattern");
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let message_size=message_size.ok_or_else(||arg.cv.ill("There were no message_size"))?;
+		let mut pattern=pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
 		pattern.initialize(tasks, tasks, arg.topology, arg.rng);
-		Homogeneous{
+		let arrival=ArrivalProcess::new(arrival,load,message_size,tasks,arg.rng);
+		Ok(Homogeneous{
 			tasks,
 			pattern,
 			message_size,
-			load,
+			arrival,
+			class,
 			generated_messages: BTreeSet::new(),
 			next_id: 0,
-		}
+		})
 	}
 }
 
@@ -160,6 +163,7 @@ Burst{
 	messages_per_task:200,
 	message_size: 16,
     expected_messages_to_consume_per_task (optional): 200, //To have tasks statistics
+    class (optional): 1, //The traffic class of the messages. By default 0.
 }
 ```
  **/
@@ -183,6 +187,8 @@ pub struct Burst
     total_consumed_per_task: Vec<usize>,
     ///The id of the next message to generate.
     next_id: u128,
+    ///The traffic class of the messages.
+    class: usize,
 }
 
 impl Traffic for Burst
@@ -209,6 +215,9 @@ impl Traffic for Burst
             creation_cycle: cycle,
             payload: id.to_le_bytes().into(),
             id_traffic: None,
+            routing_hints: None,
+            tag: None,
+            class: self.class,
         });
         self.generated_messages.insert(id);
         Ok(message)
@@ -274,29 +283,31 @@ impl Traffic for Burst
 
 impl Burst
 {
-    pub fn new(arg:TrafficBuilderArgument) -> Burst
+    pub fn new(arg:TrafficBuilderArgument) -> Result<Burst,Error>
     {
         let mut tasks=None;
         let mut messages_per_task=None;
         let mut pattern=None;
         let mut message_size=None;
         let mut expected_messages_to_consume = None;
-        match_object_panic!(arg.cv,"Burst",value,
-			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"tasks" | "servers" => tasks=Some(value.as_f64().expect("bad value for tasks") as usize),
-			"messages_per_task" | "messages_per_server" => messages_per_task=Some(value.as_f64().expect("bad value for messages_per_task") as usize),
-			"message_size" => message_size=Some(value.as_f64().expect("bad value for message_size") as usize),
-			"expected_messages_to_consume_per_task" => expected_messages_to_consume=Some(value.as_f64().expect("bad value for expected_messages_to_consume") as usize),
+        let mut class = 0;
+        match_object!(arg.cv,"Burst",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"tasks" | "servers" => tasks=Some(value.as_f64()? as usize),
+			"messages_per_task" | "messages_per_server" => messages_per_task=Some(value.as_f64()? as usize),
+			"message_size" => message_size=Some(value.as_f64()? as usize),
+			"expected_messages_to_consume_per_task" => expected_messages_to_consume=Some(value.as_f64()? as usize),
+			"class" => class = value.as_usize()?,
 		);
-        let tasks=tasks.expect("There were no tasks");
-        let message_size=message_size.expect("There were no message_size");
-        let messages_per_task=messages_per_task.expect("There were no messages_per_task");
-        let mut pattern=pattern.expect("There were no pattern");
+        let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+        let message_size=message_size.ok_or_else(||arg.cv.ill("There were no message_size"))?;
+        let messages_per_task=messages_per_task.ok_or_else(||arg.cv.ill("There were no messages_per_task"))?;
+        let mut pattern=pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
         pattern.initialize(tasks, tasks, arg.topology, arg.rng);
 
         let pending_messages = vec![messages_per_task;tasks];
 
-        Burst{
+        Ok(Burst{
             tasks,
             pattern,
             message_size,
@@ -305,7 +316,8 @@ impl Burst
             expected_messages_to_consume,
             total_consumed_per_task: vec![0;tasks],
             next_id: 0,
-        }
+            class,
+        })
     }
 }
 
@@ -434,27 +446,35 @@ impl Traffic for TrafficMessages
     fn number_tasks(&self) -> usize {
         self.tasks
     }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        self.traffic.advance_cycle(cycle);
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        self.traffic.reset_statistics(next_cycle);
+    }
 }
 
 impl TrafficMessages
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> TrafficMessages
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<TrafficMessages,Error>
     {
         let mut tasks=None;
         let mut traffic = None;
         let mut num_messages = None;
         let mut messages_per_task = None;
         let mut expected_messages_to_consume = None;
-        match_object_panic!(arg.cv,"Messages",value,
-			"traffic" => traffic=Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"tasks" | "servers" => tasks=Some(value.as_usize().expect("bad value for tasks")),
-			"num_messages" => num_messages=Some(value.as_usize().expect("bad value for num_messages")),
-			"messages_per_task" | "messages_per_server" => messages_per_task=Some(value.as_usize().expect("bad value for messages_per_task")),
-			"expected_messages_to_consume_per_task" => expected_messages_to_consume=Some(value.as_usize().expect("bad value for expected_messages_to_consume")),
+        match_object!(arg.cv,"Messages",value,
+			"traffic" => traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"num_messages" => num_messages=Some(value.as_usize()?),
+			"messages_per_task" | "messages_per_server" => messages_per_task=Some(value.as_usize()?),
+			"expected_messages_to_consume_per_task" => expected_messages_to_consume=Some(value.as_usize()?),
 		);
-        let tasks=tasks.expect("There were no tasks");
-        let num_messages=num_messages.expect("There were no num_messages");
-        let traffic=traffic.expect("There were no traffic");
+        let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+        let num_messages=num_messages.ok_or_else(||arg.cv.ill("There were no num_messages"))?;
+        let traffic=traffic.ok_or_else(||arg.cv.ill("There were no traffic"))?;
 
         let messages_per_task = if messages_per_task.is_some() {
             let mpt = messages_per_task.unwrap();
@@ -467,7 +487,7 @@ impl TrafficMessages
             None
         };
 
-        TrafficMessages{
+        Ok(TrafficMessages{
             tasks,
             traffic,
             num_messages,
@@ -476,7 +496,7 @@ impl TrafficMessages
             messages_per_task,
             expected_messages_to_consume,
             total_consumed_per_task: vec![0; tasks],
-        }
+        })
     }
 }
 
@@ -566,22 +586,22 @@ impl Traffic for Sleep
 
 impl Sleep
 {
-    pub fn new(arg:TrafficBuilderArgument) -> Sleep
+    pub fn new(arg:TrafficBuilderArgument) -> Result<Sleep,Error>
     {
         let mut cycle_to_wake=None;
         let mut tasks=None;
 
-        match_object_panic!(arg.cv,"Sleep",value,
-			"cycle_to_wake" => cycle_to_wake=Some(value.as_time().expect("bad value for cycle_to_wake")),
-			"tasks" | "servers" => tasks=Some(value.as_f64().expect("bad value for tasks") as usize),
+        match_object!(arg.cv,"Sleep",value,
+			"cycle_to_wake" => cycle_to_wake=Some(value.as_time()?),
+			"tasks" | "servers" => tasks=Some(value.as_f64()? as usize),
 		);
-        let cycle_to_wake=cycle_to_wake.expect("There were no cycle_to_wake");
-        let tasks=tasks.expect("There were no tasks");
-        Sleep {
+        let cycle_to_wake=cycle_to_wake.ok_or_else(||arg.cv.ill("There were no cycle_to_wake"))?;
+        let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+        Ok(Sleep {
             cycle_to_wake,
             tasks,
             finished: false,
-        }
+        })
     }
 }
 
@@ -649,6 +669,9 @@ impl Traffic for PeriodicBurst
             creation_cycle: cycle,
             payload: id.to_le_bytes().into(),
             id_traffic: None,
+            routing_hints: None,
+            tag: None,
+            class: 0,
         });
         self.generated_messages.insert(id);
         Ok(message)
@@ -731,7 +754,7 @@ impl Traffic for PeriodicBurst
 
 impl PeriodicBurst
 {
-    pub fn new(arg:TrafficBuilderArgument) -> PeriodicBurst
+    pub fn new(arg:TrafficBuilderArgument) -> Result<PeriodicBurst,Error>
     {
         let mut pattern=None;
         let mut period=None;
@@ -740,27 +763,27 @@ impl PeriodicBurst
         let mut tasks=None;
         let mut messages_per_task_per_period=None;
         let mut message_size=None;
-        match_object_panic!(arg.cv,"PeriodicBurst",value,
-			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+        match_object!(arg.cv,"PeriodicBurst",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
 			"period" => period = Some(value.as_usize().expect("bad value in period")),
 			"offset" => offset = Some(value.as_usize().expect("bad value in offset")),
-			"finish" => finish=Some(value.as_usize().expect("bad value for finish")),
-			"tasks" => tasks=Some(value.as_f64().expect("bad value for tasks") as usize),
-			"messages_per_task_per_period" => messages_per_task_per_period=Some(value.as_f64().expect("bad value for messages_per_task_per_period") as usize),
-			"message_size" => message_size=Some(value.as_f64().expect("bad value for message_size") as usize),
+			"finish" => finish=Some(value.as_usize()?),
+			"tasks" => tasks=Some(value.as_f64()? as usize),
+			"messages_per_task_per_period" => messages_per_task_per_period=Some(value.as_f64()? as usize),
+			"message_size" => message_size=Some(value.as_f64()? as usize),
 		);
-        let mut pattern =pattern.expect("There were no pattern");
-        let period=period.expect("There were no period");
-        let offset=offset.expect("There were no offset");
-        let finish=finish.expect("There were no finish");
-        let tasks=tasks.expect("There were no tasks");
-        let message_size=message_size.expect("There were no message_size");
-        let messages_per_task_per_period=messages_per_task_per_period.expect("There were no messages_per_task_per_period");
+        let mut pattern =pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
+        let period=period.ok_or_else(||arg.cv.ill("There were no period"))?;
+        let offset=offset.ok_or_else(||arg.cv.ill("There were no offset"))?;
+        let finish=finish.ok_or_else(||arg.cv.ill("There were no finish"))?;
+        let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+        let message_size=message_size.ok_or_else(||arg.cv.ill("There were no message_size"))?;
+        let messages_per_task_per_period=messages_per_task_per_period.ok_or_else(||arg.cv.ill("There were no messages_per_task_per_period"))?;
 
         let times_to_generate = VecDeque::from((0..((finish-offset)/period +1)).into_iter().map(|i| (i*period + offset) as Time).collect::<Vec<Time>>());
         println!("times_to_generate: {:?}", times_to_generate);
         pattern.initialize(tasks, tasks, arg.topology, arg.rng);
-        PeriodicBurst {
+        Ok(PeriodicBurst {
             pattern,
             times_to_generate,
             tasks,
@@ -769,7 +792,7 @@ impl PeriodicBurst
             pending_messages: vec![0;tasks],
             generated_messages: BTreeSet::new(),
             next_id: 0,
-        }
+        })
     }
 }
 
@@ -830,29 +853,37 @@ impl Traffic for SubRangeTraffic
     fn number_tasks(&self) -> usize {
         self.traffic.number_tasks()
     }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        self.traffic.advance_cycle(cycle);
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        self.traffic.reset_statistics(next_cycle);
+    }
 }
 
 impl SubRangeTraffic
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> SubRangeTraffic
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<SubRangeTraffic,Error>
     {
         let mut start=None;
         let mut end=None;
         let mut traffic=None;
-        match_object_panic!(arg.cv,"SubRangeTraffic",value,
-			"traffic" => traffic=Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"start" => start=Some(value.as_f64().expect("bad value for start") as usize),
-			"end" => end=Some(value.as_f64().expect("bad value for end") as usize),
+        match_object!(arg.cv,"SubRangeTraffic",value,
+			"traffic" => traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"start" => start=Some(value.as_f64()? as usize),
+			"end" => end=Some(value.as_f64()? as usize),
 		);
-        let start=start.expect("There were no start");
-        let end=end.expect("There were no end");
-        let traffic=traffic.expect("There were no traffic");
-        SubRangeTraffic{
+        let start=start.ok_or_else(||arg.cv.ill("There were no start"))?;
+        let end=end.ok_or_else(||arg.cv.ill("There were no end"))?;
+        let traffic=traffic.ok_or_else(||arg.cv.ill("There were no traffic"))?;
+        Ok(SubRangeTraffic{
             start,
             end,
             traffic,
             //generated_messages: BTreeMap::new(),
-        }
+        })
     }
 }
 
@@ -950,25 +981,283 @@ impl Traffic for Reactive
         // Both traffics have the same number of tasks
         self.action_traffic.number_tasks()
     }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        self.action_traffic.advance_cycle(cycle);
+        self.reaction_traffic.advance_cycle(cycle);
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        self.action_traffic.reset_statistics(next_cycle);
+        self.reaction_traffic.reset_statistics(next_cycle);
+    }
 }
 
 impl Reactive
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> Reactive
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<Reactive,Error>
     {
         let mut action_traffic=None;
         let mut reaction_traffic=None;
-        match_object_panic!(arg.cv,"Reactive",value,
-			"action_traffic" => action_traffic=Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"reaction_traffic" => reaction_traffic=Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
+        match_object!(arg.cv,"Reactive",value,
+			"action_traffic" => action_traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"reaction_traffic" => reaction_traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
 		);
-        let action_traffic=action_traffic.expect("There were no action_traffic");
-        let reaction_traffic=reaction_traffic.expect("There were no reaction_traffic");
+        let action_traffic=action_traffic.ok_or_else(||arg.cv.ill("There were no action_traffic"))?;
+        let reaction_traffic=reaction_traffic.ok_or_else(||arg.cv.ill("There were no reaction_traffic"))?;
         assert_eq!( action_traffic.number_tasks() , reaction_traffic.number_tasks(), "In Reactive both subtraffics should involve the same number of tasks." );
-        Reactive{
+        Ok(Reactive{
             action_traffic,
             reaction_traffic,
             pending_messages:vec![],
+        })
+    }
+}
+/**
+Round-trip time measurement. Each task `t` with `pattern(t)!=t` initiates a conversation with `pattern(t)`: it sends a single ping message and waits for the pong reply before sending the next ping.
+This is repeated `iterations` times for each pair. The first `warmup_iterations` round trips of each pair are not recorded, nor those completed during the warmup of the simulation.
+Tasks mapped to themselves by the pattern only reply to the pings they receive.
+Replies have priority over pings, but they still have to wait in the server queue, so its effect is included in the round-trip time.
+
+The round trip times are reported into `traffic_extra_statistics`, aggregated and for each pair.
+
+```ignore
+PingPong{
+	tasks: 1000,
+	pattern: RandomPermutation,
+	iterations: 100,
+	warmup_iterations: 10,//optional, defaults to 0.
+	message_size: 16,
+}
+```
+ **/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct PingPong
+{
+    ///Number of tasks applying this traffic.
+    tasks: usize,
+    ///For each task, the task to which it sends pings, if any.
+    partner: Vec<Option<usize>>,
+    ///The size of each sent message.
+    message_size: usize,
+    ///The number of round trips of each pair.
+    iterations: usize,
+    ///The number of round trips of each pair that are not recorded.
+    warmup_iterations: usize,
+    ///The number of pings each initiator has pending to send.
+    pending_pings: Vec<usize>,
+    ///The cycle in which the ping of each initiator has been generated, if it is awaiting its pong.
+    ping_cycle: Vec<Option<Time>>,
+    ///The pongs that each task has to send, by the initiator that sent the ping.
+    pending_pongs: Vec<VecDeque<usize>>,
+    ///For each task, the number of initiators sending pings to it that have not finished.
+    active_initiators: Vec<usize>,
+    ///The recorded round trip times of each initiator.
+    round_trip_times: Vec<Vec<Time>>,
+    ///Set of generated messages.
+    generated_messages: BTreeSet<u128>,
+    ///The id of the next message to generate.
+    next_id: u128,
+}
+
+impl Traffic for PingPong
+{
+    fn generate_message(&mut self, origin:usize, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+    {
+        if origin>=self.tasks
+        {
+            return Err(TrafficError::OriginOutsideTraffic);
         }
+        let (destination,kind) = if let Some(initiator) = self.pending_pongs[origin].pop_front()
+        {
+            (initiator,1u8)
+        }
+        else
+        {
+            let destination = self.partner[origin].expect("This task has nothing to send");
+            assert!(self.pending_pings[origin]>0 && self.ping_cycle[origin].is_none(),"This task has no ping to send");
+            self.pending_pings[origin]-=1;
+            self.ping_cycle[origin]=Some(cycle);
+            (destination,0u8)
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut payload : Vec<u8> = id.to_le_bytes().into();
+        payload.push(kind);
+        let message=Rc::new(Message{
+            origin,
+            destination,
+            size:self.message_size,
+            creation_cycle: cycle,
+            payload,
+            id_traffic: None,
+            routing_hints: None,
+            tag: None,
+            class: 0,
+        });
+        self.generated_messages.insert(id);
+        Ok(message)
     }
-}
\ No newline at end of file
+    fn probability_per_cycle(&self, task:usize) -> f32
+    {
+        if self.has_message_to_send(task) { 1.0 } else { 0.0 }
+    }
+    fn should_generate(&mut self, task:usize, _cycle:Time, _rng: &mut StdRng) -> bool
+    {
+        self.has_message_to_send(task)
+    }
+    fn consume(&mut self, task:usize, message: &dyn AsMessage, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> bool
+    {
+        let payload = message.payload();
+        let id = u128::from_le_bytes(payload[0..16].try_into().expect("bad payload"));
+        if !self.generated_messages.remove(&id)
+        {
+            return false;
+        }
+        let origin = message.origin();
+        if payload[16]==0
+        {
+            //A ping. Reply to the initiator.
+            self.pending_pongs[task].push_back(origin);
+        }
+        else
+        {
+            //A pong. The round trip is completed.
+            let ping_cycle = self.ping_cycle[task].take().expect("received a pong without having sent a ping");
+            let round_trips_done = self.iterations - self.pending_pings[task];
+            if round_trips_done>self.warmup_iterations
+            {
+                self.round_trip_times[task].push(cycle-ping_cycle);
+            }
+            if self.pending_pings[task]==0
+            {
+                self.active_initiators[origin]-=1;
+            }
+        }
+        true
+    }
+    fn is_finished(&self) -> bool
+    {
+        self.generated_messages.is_empty() && self.pending_pings.iter().all(|&p|p==0) && self.pending_pongs.iter().all(|q|q.is_empty())
+    }
+    fn task_state(&self, task:usize, _cycle:Time) -> Option<TaskTrafficState>
+    {
+        if self.has_message_to_send(task)
+        {
+            Some(Generating)
+        }
+        else if self.ping_cycle[task].is_some() || self.pending_pings[task]>0 || self.active_initiators[task]>0
+        {
+            Some(WaitingData)
+        }
+        else
+        {
+            Some(Finished)
+        }
+    }
+    fn number_tasks(&self) -> usize
+    {
+        self.tasks
+    }
+    fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+    {
+        let summary = |samples:&[Time]| -> Vec<(String,ConfigurationValue)> {
+            let mut sorted = samples.to_vec();
+            sorted.sort_unstable();
+            let count = sorted.len();
+            let average = if count==0 { 0f64 } else { sorted.iter().sum::<Time>() as f64 / count as f64 };
+            let deviation = if count==0 { 0f64 } else { (sorted.iter().map(|&x|(x as f64-average).powi(2)).sum::<f64>() / count as f64).sqrt() };
+            let percentile = |p:f64| if count==0 { 0f64 } else { sorted[((p*count as f64).ceil() as usize).clamp(1,count)-1] as f64 };
+            vec![
+                (String::from("count"),ConfigurationValue::Number(count as f64)),
+                (String::from("average"),ConfigurationValue::Number(average)),
+                (String::from("standard_deviation"),ConfigurationValue::Number(deviation)),
+                (String::from("minimum"),ConfigurationValue::Number(percentile(0.0))),
+                (String::from("median"),ConfigurationValue::Number(percentile(0.5))),
+                (String::from("percentile_99"),ConfigurationValue::Number(percentile(0.99))),
+                (String::from("maximum"),ConfigurationValue::Number(percentile(1.0))),
+            ]
+        };
+        let pairs = (0..self.tasks).filter_map(|task|{
+            let partner = self.partner[task]?;
+            let mut content = vec![
+                (String::from("origin"),ConfigurationValue::Number(task as f64)),
+                (String::from("destination"),ConfigurationValue::Number(partner as f64)),
+            ];
+            content.extend(summary(&self.round_trip_times[task]));
+            Some(ConfigurationValue::Object(String::from("PingPongPair"),content))
+        }).collect();
+        let all : Vec<Time> = self.round_trip_times.iter().flatten().cloned().collect();
+        let mut content = summary(&all);
+        content.push((String::from("pairs"),ConfigurationValue::Array(pairs)));
+        Some(ConfigurationValue::Object(String::from("PingPongStatistics"),content))
+    }
+    fn reset_statistics(&mut self, _next_cycle:Time)
+    {
+        for times in self.round_trip_times.iter_mut()
+        {
+            times.clear();
+        }
+    }
+}
+
+impl PingPong
+{
+    pub fn new(arg:TrafficBuilderArgument) -> Result<PingPong,Error>
+    {
+        let mut tasks=None;
+        let mut pattern=None;
+        let mut iterations=None;
+        let mut warmup_iterations=0;
+        let mut message_size=None;
+        match_object!(arg.cv,"PingPong",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"iterations" => iterations=Some(value.as_usize()?),
+			"warmup_iterations" => warmup_iterations=value.as_usize()?,
+			"message_size" => message_size=Some(value.as_usize()?),
+		);
+        let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+        let iterations=iterations.ok_or_else(||arg.cv.ill("There were no iterations"))?;
+        let message_size=message_size.ok_or_else(||arg.cv.ill("There were no message_size"))?;
+        let mut pattern=pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
+        pattern.initialize(tasks, tasks, arg.topology, arg.rng);
+        let partner : Vec<Option<usize>> = (0..tasks).map(|task|{
+            let destination = pattern.get_destination(task,arg.topology,arg.rng);
+            if destination==task { None } else { Some(destination) }
+        }).collect();
+        let mut active_initiators = vec![0;tasks];
+        let mut pending_pings = vec![0;tasks];
+        for (task,partner) in partner.iter().enumerate()
+        {
+            if let Some(partner) = *partner
+            {
+                if iterations>0
+                {
+                    active_initiators[partner]+=1;
+                    pending_pings[task]=iterations;
+                }
+            }
+        }
+        Ok(PingPong{
+            tasks,
+            partner,
+            message_size,
+            iterations,
+            warmup_iterations,
+            pending_pings,
+            ping_cycle: vec![None;tasks],
+            pending_pongs: vec![VecDeque::new();tasks],
+            active_initiators,
+            round_trip_times: vec![vec![];tasks],
+            generated_messages: BTreeSet::new(),
+            next_id: 0,
+        })
+    }
+    ///Whether the task has either a pong or a ping ready to be sent.
+    fn has_message_to_send(&self, task:usize) -> bool
+    {
+        !self.pending_pongs[task].is_empty() || (self.pending_pings[task]>0 && self.ping_cycle[task].is_none())
+    }
+}
@@ -1,17 +1,19 @@
 use crate::pattern::extra::get_cartesian_transform;
 use crate::pattern::extra::get_candidates_selection;
 use crate::AsMessage;
+use std::convert::TryInto;
 use std::rc::Rc;
 use quantifiable_derive::Quantifiable;
 use rand::prelude::StdRng;
 use crate::config_parser::ConfigurationValue;
-use crate::{match_object_panic, Message, Time};
+use crate::error::{Error,SourceLocation};
+use crate::{match_object, error, source_location, Message, MessageTag, Time};
 use crate::topology::Topology;
-use crate::traffic::{new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
+use crate::traffic::{try_new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
 use crate::traffic::basic::{build_message_cv, BuildMessageCVArgs};
 use crate::traffic::mini_apps::{BuildTrafficCreditCVArgs, get_traffic_credit};
 use crate::traffic::sequences::{BuilderMessageTaskSequenceCVArgs, get_traffic_message_task_sequence};
-use crate::traffic::TaskTrafficState::{UnspecifiedWait, WaitingData};
+use crate::traffic::TaskTrafficState::{FinishedGenerating, Generating, UnspecifiedWait, WaitingData};
 
 
 
@@ -111,31 +113,39 @@ impl Traffic for MessageBarrier
     fn number_tasks(&self) -> usize {
         self.tasks
     }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        self.traffic.advance_cycle(cycle);
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        self.traffic.reset_statistics(next_cycle);
+    }
 }
 
 impl MessageBarrier
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> MessageBarrier
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<MessageBarrier,Error>
     {
         let mut tasks=None;
         let mut traffic = None;
         let mut messages_per_task_to_wait = None;
         let mut expected_messages_to_consume_to_wait = None;
-        match_object_panic!(arg.cv,"MessageBarrier",value,
-			"traffic" => traffic=Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"tasks" | "servers" => tasks=Some(value.as_usize().expect("bad value for tasks")),
-			"messages_per_task_to_wait" => messages_per_task_to_wait=Some(value.as_usize().expect("bad value for messages_per_task_to_wait")),
-			"expected_messages_to_consume_to_wait" => expected_messages_to_consume_to_wait=Some(value.as_usize().expect("bad value for expected_messages_to_consume_to_wait")),
+        match_object!(arg.cv,"MessageBarrier",value,
+			"traffic" => traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"messages_per_task_to_wait" => messages_per_task_to_wait=Some(value.as_usize()?),
+			"expected_messages_to_consume_to_wait" => expected_messages_to_consume_to_wait=Some(value.as_usize()?),
 		);
-        let tasks=tasks.expect("There were no tasks");
-        let traffic=traffic.expect("There were no traffic");
-        let messages_per_task_to_wait=messages_per_task_to_wait.expect("There were no messages_per_task_to_wait");
+        let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+        let traffic=traffic.ok_or_else(||arg.cv.ill("There were no traffic"))?;
+        let messages_per_task_to_wait=messages_per_task_to_wait.ok_or_else(||arg.cv.ill("There were no messages_per_task_to_wait"))?;
 
         if traffic.number_tasks() != tasks {
-            panic!("The number of tasks in the traffic and the number of tasks in the barrier are different.");
+            return Err(arg.cv.ill("The number of tasks in the traffic and the number of tasks in the barrier are different."));
         }
 
-        MessageBarrier {
+        Ok(MessageBarrier {
             tasks,
             traffic,
             messages_per_task_to_wait,
@@ -144,7 +154,7 @@ impl MessageBarrier
             total_consumed: 0,
             total_consumed_per_task: vec![0; tasks],
             expected_messages_to_consume_to_wait,
-        }
+        })
     }
 }
 
@@ -172,20 +182,40 @@ pub fn build_message_barrier_cv(args: BuildMessageBarrierCVArgs) -> Configuratio
 
 
 /**
-MPI collectives implementations based on TrafficCredit
+MPI collectives implementations. The ScatterReduce and AllGather are built as a [CollectiveSchedule], while AllReduce and All2All are based on TrafficCredit.
+
+The ScatterReduce and AllGather accept the `algorithm`:
+* `Ring`: `tasks-1` phases in which each task sends a block of `data_size/tasks` to the next task.
+* `Hypercube`, also called `RecursiveHalving` for the ScatterReduce and `RecursiveDoubling` for the AllGather: `log2(tasks)` phases exchanging with the hypercube neighbours.
+  The ScatterReduce halves the data in each phase and the AllGather doubles it. The number of tasks must be a power of 2.
+* `Bruck`: `ceil(log2(tasks))` phases. In the phase `k` of the AllGather each task `i` sends `min(2^k,tasks-2^k)` blocks to the task `i-2^k`.
+  The ScatterReduce follows the same phases in reverse order and direction. Any number of tasks is allowed.
+
+With `chunk_size` each message is split into messages of at most that size, which are injected together at the beginning of its phase.
+
+The All2All accepts the `algorithm`:
+* `Default`: all the messages are sent at once, built on TrafficCredit.
+* `Pairwise`: `tasks-1` phases. In the phase `k` each task `i` sends its block for the task `i+k`.
+* `Ring`: `tasks-1` phases. In the phase `k` each task sends to the next task the `tasks-k` blocks that have not reached their destination yet.
+* `Bruck`: `ceil(log2(tasks))` phases. In the phase `k` each task `i` sends to the task `i+2^k` the blocks whose distance to their destination has the bit `k` set.
+
+Except with the `Default` algorithm, the All2All is built as a [CollectiveSchedule] with a barrier between phases, so that no task starts a phase until all the tasks have completed the previous one.
+It can be disabled with `phase_barrier: false`. The `chunk_size` may be given as in the other collectives.
 
 ```ignore
-Allgather{
+AllGather{
     tasks: 64,
     data_size: 1000, //The total data size to all-gather. Each task starts with a data slice of size data_size/tasks.
     algorithm: "Hypercube",
     neighbours_order: [32, 16, 8, 4, 2, 1], //Optional, the order to iter hypercube neighbours
+    //chunk_size: 16, //Optional, the maximum size of the messages.
 }
 
 ScatterReduce{
     tasks: 64,
     data_size: 1000, //The total data size to scatter-reduce. Each task ends with a data slice reduced of size data_size/tasks.
-    algorithm: "Hypercube",
+    algorithm: "Bruck",
+    //chunk_size: 16, //Optional, the maximum size of the messages.
 }
 
 Allreduce{
@@ -198,6 +228,9 @@ Allreduce{
 All2All{
     tasks: 64,
     data_size: 1000, //The total data size to all2all. Each task sends a data slice of size data_size/tasks to all the other tasks.
+    //algorithm: "Bruck", //Optional, `Default` by default.
+    //phase_barrier: true, //Optional, whether to wait for all the tasks at the end of each phase.
+    //chunk_size: 16, //Optional, the maximum size of the messages.
 }
 ```
  **/
@@ -207,75 +240,360 @@ pub struct MPICollective {}
 
 impl MPICollective
 {
-    pub fn new(traffic: String, mut arg:TrafficBuilderArgument) ->  Box<dyn Traffic>
+    pub fn new(traffic: String, mut arg:TrafficBuilderArgument) ->  Result<Box<dyn Traffic>,Error>
     {
         let traffic_cv = match traffic.as_str() {
-            "ScatterReduce" =>{
-                let mut tasks = None;
-                let mut data_size = None;
-                let mut algorithm = "Hypercube";
-                match_object_panic!(arg.cv,"ScatterReduce",value,
-					"tasks" => tasks = Some(value.as_f64().expect("bad value for tasks") as usize),
-					"algorithm" => algorithm = value.as_str().expect("bad value for algorithm"),
-					"data_size" => data_size = Some(value.as_f64().expect("bad value for data_size") as usize),
-				);
-                match algorithm {
-                    "Hypercube" => Some(get_scatter_reduce_hypercube(tasks.expect("There were no tasks"), data_size.expect("There were no data_size"))),
-                    "Ring" => Some(ring_iteration(tasks.expect("There were no tasks"), data_size.expect("There were no data_size"))),
-                    _ => panic!("Unknown algorithm: {}", algorithm),
-                }
-            },
-            "AllGather" =>{
+            "ScatterReduce" | "AllGather" =>{
                 let mut tasks = None;
                 let mut data_size = None;
                 let mut algorithm = "Hypercube";
                 let mut neighbours_order = None;
-                match_object_panic!(arg.cv,"AllGather",value,
-					"tasks" => tasks = Some(value.as_f64().expect("bad value for tasks") as usize),
-					"algorithm" => algorithm = value.as_str().expect("bad value for algorithm"),
-					"data_size" => data_size = Some(value.as_f64().expect("bad value for data_size") as usize),
-					"neighbours_order" => neighbours_order = Some(value),
+                let mut chunk_size = None;
+                match_object!(arg.cv,["ScatterReduce","AllGather"],value,
+					"tasks" => tasks = Some(value.as_f64()? as usize),
+					"algorithm" => algorithm = value.as_str()?,
+					"data_size" => data_size = Some(value.as_f64()? as usize),
+					"neighbours_order" => neighbours_order = Some(value.as_array()?.iter()
+						.map(|v|v.as_usize().expect("bad value in neighbours_order")).collect::<Vec<usize>>()),
+					"chunk_size" => chunk_size = Some(value.as_usize()?),
 				);
-                match algorithm {
-                    "Hypercube" => Some(get_all_gather_hypercube(tasks.expect("There were no tasks"), data_size.expect("There were no data_size"), neighbours_order)),
-                    "Ring" => Some(ring_iteration(tasks.expect("There were no tasks"), data_size.expect("There were no data_size"))),
-                    _ => panic!("Unknown algorithm: {}", algorithm),
+                let tasks = tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+                let data_size = data_size.ok_or_else(||arg.cv.ill("There were no data_size"))?;
+                let all_gather = traffic == "AllGather";
+                if neighbours_order.is_some() && !all_gather
+                {
+                    panic!("neighbours_order is only supported in AllGather");
                 }
+                let phases = match algorithm {
+                    "Ring" => ring_phases(tasks, data_size),
+                    "Hypercube" | "RecursiveHalving" | "RecursiveDoubling" => hypercube_phases(tasks, data_size, all_gather, neighbours_order),
+                    "Bruck" => bruck_phases(tasks, data_size, all_gather),
+                    _ => panic!("Unknown algorithm: {}", algorithm),
+                };
+                return Ok(Box::new(CollectiveSchedule::new(tasks, phases, chunk_size)));
             },
             "AllReduce" =>{
                 let mut tasks = None;
                 let mut data_size = None;
                 let mut algorithm = "Optimal";
                 let mut neighbours_order = None;
-                match_object_panic!(arg.cv,"AllReduce",value,
-					"tasks" => tasks = Some(value.as_f64().expect("bad value for tasks") as usize),
-					"algorithm" => algorithm = value.as_str().expect("bad value for algorithm"),
-					"data_size" => data_size = Some(value.as_f64().expect("bad value for data_size") as usize),
+                match_object!(arg.cv,"AllReduce",value,
+					"tasks" => tasks = Some(value.as_f64()? as usize),
+					"algorithm" => algorithm = value.as_str()?,
+					"data_size" => data_size = Some(value.as_f64()? as usize),
 					"all_gather_neighbours_order" => neighbours_order = Some(value),
 				);
 
                 match algorithm {
-                    "Optimal" => Some(get_all_reduce_optimal(tasks.expect("There were no tasks"), data_size.expect("There were no data_size"), neighbours_order)),
-                    "Ring" => Some(get_all_reduce_ring(tasks.expect("There were no tasks"), data_size.expect("There were no data_size"))),
+                    "Optimal" => Some(get_all_reduce_optimal(tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?, data_size.ok_or_else(||arg.cv.ill("There were no data_size"))?, neighbours_order)),
+                    "Ring" => Some(get_all_reduce_ring(tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?, data_size.ok_or_else(||arg.cv.ill("There were no data_size"))?)),
                     _ => panic!("Unknown algorithm: {}", algorithm),
                 }
             },
             "All2All" =>{
                 let mut tasks = None;
                 let mut data_size = None;
-                match_object_panic!(arg.cv,"All2All",value,
-					"tasks" => tasks = Some(value.as_f64().expect("bad value for tasks") as usize),
-					"data_size" => data_size = Some(value.as_f64().expect("bad value for data_size") as usize),
+                let mut algorithm = "Default";
+                let mut phase_barrier = true;
+                let mut chunk_size = None;
+                match_object!(arg.cv,"All2All",value,
+					"tasks" => tasks = Some(value.as_f64()? as usize),
+					"data_size" => data_size = Some(value.as_f64()? as usize),
+					"algorithm" => algorithm = value.as_str()?,
+					"phase_barrier" => phase_barrier = value.as_bool()?,
+					"chunk_size" => chunk_size = Some(value.as_usize()?),
 				);
-
-                Some(get_all2all(tasks.expect("There were no tasks"), data_size.expect("There were no data_size")))
+                let tasks = tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+                let data_size = data_size.ok_or_else(||arg.cv.ill("There were no data_size"))?;
+                let phases = match algorithm {
+                    "Default" => None,
+                    "Pairwise" => Some(all2all_pairwise_phases(tasks, data_size)),
+                    "Ring" => Some(all2all_ring_phases(tasks, data_size)),
+                    "Bruck" => Some(all2all_bruck_phases(tasks, data_size)),
+                    _ => panic!("Unknown algorithm: {}", algorithm),
+                };
+                match phases {
+                    Some(phases) => return Ok(Box::new(CollectiveSchedule::new(tasks, phases, chunk_size).with_phase_barrier(phase_barrier))),
+                    None => Some(get_all2all(tasks, data_size)),
+                }
             },
 
             _ => panic!("Unknown traffic type: {}", traffic),
         };
 
-        new_traffic(TrafficBuilderArgument{cv:&traffic_cv.expect("There should be a CV"),rng:&mut arg.rng,..arg})
+        try_new_traffic(TrafficBuilderArgument{cv:&traffic_cv.expect("There should be a CV"),rng:&mut arg.rng,..arg})
+    }
+}
+
+/**
+A collective communication given as a sequence of phases. In each phase each task sends some messages and receives some others.
+A task sends the messages of a phase once it has received all the messages of the previous phases, and it goes into the next phase when it has sent and received all the messages of the current one.
+This models the data dependencies of the usual algorithms for collectives, in which the data sent in a phase includes the data received in the previous ones.
+It is built by [MPICollective] for the ScatterReduce and AllGather, and for the All2All with an explicit algorithm.
+With a phase barrier a task does not send the messages of a phase until all the tasks have completed the previous one.
+
+The messages are tagged with their phase, see [MessageTag](crate::MessageTag).
+The statistics include the cycle in which each phase was completed by all the tasks.
+ **/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct CollectiveSchedule
+{
+    ///Number of tasks applying this traffic.
+    tasks: usize,
+    ///For each phase and task the messages to send, as pairs `(destination,size)`.
+    sends: Vec<Vec<Vec<(usize,usize)>>>,
+    ///For each phase and task the number of messages to receive.
+    receives: Vec<Vec<usize>>,
+    ///For each phase and task the number of messages received.
+    received: Vec<Vec<usize>>,
+    ///The current phase of each task.
+    phase: Vec<usize>,
+    ///The number of messages sent by each task in its current phase.
+    sent: Vec<usize>,
+    ///For each phase the number of tasks that have completed it.
+    completed_tasks: Vec<usize>,
+    ///For each phase the cycle in which the last task completed it.
+    completion_cycle: Vec<Option<Time>>,
+    ///Whether all the tasks must complete a phase before any task starts the next one.
+    phase_barrier: bool,
+}
+
+impl Traffic for CollectiveSchedule
+{
+    fn generate_message(&mut self, origin:usize, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+    {
+        if origin>=self.tasks
+        {
+            return Err(TrafficError::OriginOutsideTraffic);
+        }
+        let phase = self.phase[origin];
+        let (destination,size) = self.sends[phase][origin][self.sent[origin]];
+        self.sent[origin] += 1;
+        let message = Rc::new(Message{
+            origin,
+            destination,
+            size,
+            creation_cycle: cycle,
+            payload: (phase as u64).to_le_bytes().into(),
+            id_traffic: None,
+            routing_hints: None,
+            tag: Some(Rc::new(MessageTag{phase:Some(phase),..MessageTag::default()})),
+            class: 0,
+        });
+        self.advance_phases(origin, cycle);
+        Ok(message)
+    }
+    fn probability_per_cycle(&self, task:usize) -> f32
+    {
+        if self.is_sending(task) { 1.0 } else { 0.0 }
+    }
+    fn should_generate(&mut self, task:usize, _cycle:Time, _rng: &mut StdRng) -> bool
+    {
+        self.is_sending(task)
+    }
+    fn consume(&mut self, task:usize, message: &dyn AsMessage, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> bool
+    {
+        let phase = u64::from_le_bytes(message.payload()[0..8].try_into().expect("bad payload")) as usize;
+        if phase>=self.sends.len() || self.received[phase][task]>=self.receives[phase][task]
+        {
+            return false;
+        }
+        self.received[phase][task] += 1;
+        self.advance_phases(task, cycle);
+        true
+    }
+    fn is_finished(&self) -> bool
+    {
+        self.phase.iter().all(|&phase|phase==self.sends.len())
+    }
+    fn task_state(&self, task:usize, _cycle:Time) -> Option<TaskTrafficState>
+    {
+        if self.phase[task]==self.sends.len()
+        {
+            Some(FinishedGenerating)
+        }
+        else if self.is_sending(task)
+        {
+            Some(Generating)
+        }
+        else
+        {
+            Some(WaitingData)
+        }
+    }
+    fn number_tasks(&self) -> usize
+    {
+        self.tasks
+    }
+    fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+    {
+        let completion = self.completion_cycle.iter().map(|cycle|match cycle{
+            Some(cycle) => ConfigurationValue::Number(*cycle as f64),
+            None => ConfigurationValue::None,
+        }).collect();
+        let content = vec![
+            (String::from("phases"),ConfigurationValue::Number(self.sends.len() as f64)),
+            (String::from("phase_completion_cycles"),ConfigurationValue::Array(completion)),
+        ];
+        Some(ConfigurationValue::Object(String::from("CollectiveScheduleStatistics"),content))
+    }
+}
+
+impl CollectiveSchedule
+{
+    ///Builds the schedule from the messages `sends[phase][task]` of each phase, splitting them into messages of at most `chunk_size`.
+    pub fn new(tasks: usize, sends: Vec<Vec<Vec<(usize,usize)>>>, chunk_size: Option<usize>) -> CollectiveSchedule
+    {
+        let sends : Vec<Vec<Vec<(usize,usize)>>> = sends.into_iter().map(|phase|phase.into_iter().map(|messages|
+            messages.into_iter().flat_map(|(destination,size)|{
+                let size = size.max(1);
+                let chunk = chunk_size.unwrap_or(size).max(1);
+                let chunks = (size+chunk-1)/chunk;
+                (0..chunks).map(move |index|(destination,if index+1<chunks { chunk } else { size-chunk*(chunks-1) }))
+            }).collect()
+        ).collect()).collect();
+        let mut receives = vec![vec![0;tasks];sends.len()];
+        for (phase,phase_sends) in sends.iter().enumerate()
+        {
+            assert_eq!(phase_sends.len(),tasks,"the phase {} has not messages for each task",phase);
+            for (origin,messages) in phase_sends.iter().enumerate()
+            {
+                for &(destination,_size) in messages
+                {
+                    assert_ne!(origin,destination,"the phase {} has a self message in task {}",phase,origin);
+                    receives[phase][destination] += 1;
+                }
+            }
+        }
+        let phases = sends.len();
+        let mut schedule = CollectiveSchedule{
+            tasks,
+            sends,
+            receives,
+            received: vec![vec![0;tasks];phases],
+            phase: vec![0;tasks],
+            sent: vec![0;tasks],
+            completed_tasks: vec![0;phases],
+            completion_cycle: vec![None;phases],
+            phase_barrier: false,
+        };
+        for task in 0..tasks
+        {
+            schedule.advance_phases(task,0);
+        }
+        schedule
+    }
+    ///Set whether all the tasks must complete a phase before any task starts the next one.
+    pub fn with_phase_barrier(mut self, phase_barrier:bool) -> Self
+    {
+        self.phase_barrier = phase_barrier;
+        self
+    }
+    ///Whether the `task` has messages to send in its current phase.
+    fn is_sending(&self, task:usize) -> bool
+    {
+        let phase = self.phase[task];
+        if phase>=self.sends.len() || self.sent[task]>=self.sends[phase][task].len()
+        {
+            return false;
+        }
+        !self.phase_barrier || phase==0 || self.completed_tasks[phase-1]==self.tasks
+    }
+    ///Move the `task` into the following phases while it has completed the current one.
+    fn advance_phases(&mut self, task:usize, cycle:Time)
+    {
+        while self.phase[task]<self.sends.len()
+        {
+            let phase = self.phase[task];
+            if self.sent[task]<self.sends[phase][task].len() || self.received[phase][task]<self.receives[phase][task]
+            {
+                break;
+            }
+            self.completed_tasks[phase] += 1;
+            if self.completed_tasks[phase]==self.tasks
+            {
+                self.completion_cycle[phase] = Some(cycle);
+            }
+            self.phase[task] += 1;
+            self.sent[task] = 0;
+        }
+    }
+}
+
+///The phases of a ScatterReduce or AllGather in a ring. Each task sends a block to the next one in each phase.
+fn ring_phases(tasks: usize, data_size: usize) -> Vec<Vec<Vec<(usize,usize)>>>
+{
+    let block = data_size/tasks;
+    (1..tasks).map(|_|(0..tasks).map(|task|vec![((task+1)%tasks,block)]).collect()).collect()
+}
+
+///The phases of a ScatterReduce by recursive halving or of an AllGather by recursive doubling. The neighbour in each phase is the task index xor the corresponding value in `neighbours_order`, by default the increasing powers of 2.
+fn hypercube_phases(tasks: usize, data_size: usize, all_gather: bool, neighbours_order: Option<Vec<usize>>) -> Vec<Vec<Vec<(usize,usize)>>>
+{
+    if !tasks.is_power_of_two()
+    {
+        panic!("The number of tasks must be a power of 2");
+    }
+    let steps = tasks.trailing_zeros() as usize;
+    let order = neighbours_order.unwrap_or_else(||(0..steps).map(|step|1<<step).collect());
+    assert_eq!(order.len(),steps,"neighbours_order must have log2(tasks) elements");
+    (0..steps).map(|step|{
+        //The ScatterReduce halves the data in each step, starting with the half. The AllGather does it in reverse.
+        let size = if all_gather { data_size >> (steps-step) } else { data_size >> (step+1) };
+        (0..tasks).map(|task|vec![(task^order[step],size)]).collect()
+    }).collect()
+}
+
+///The phases of the Bruck algorithm for an AllGather, or its reverse for a ScatterReduce.
+fn bruck_phases(tasks: usize, data_size: usize, all_gather: bool) -> Vec<Vec<Vec<(usize,usize)>>>
+{
+    let block = data_size/tasks;
+    let mut phases : Vec<Vec<Vec<(usize,usize)>>> = vec![];
+    let mut distance = 1;
+    while distance<tasks
+    {
+        let size = block*distance.min(tasks-distance);
+        phases.push((0..tasks).map(|task|{
+            let destination = if all_gather { (task+tasks-distance)%tasks } else { (task+distance)%tasks };
+            vec![(destination,size)]
+        }).collect());
+        distance *= 2;
+    }
+    if !all_gather
+    {
+        phases.reverse();
+    }
+    phases
+}
+
+///The phases of a pairwise exchange All2All. In the phase `k` each task sends its block to the task at distance `k`.
+fn all2all_pairwise_phases(tasks: usize, data_size: usize) -> Vec<Vec<Vec<(usize,usize)>>>
+{
+    let block = data_size/tasks;
+    (1..tasks).map(|distance|(0..tasks).map(|task|vec![((task+distance)%tasks,block)]).collect()).collect()
+}
+
+///The phases of an All2All in a ring. Each task forwards to the next one the blocks whose destination has not been reached.
+fn all2all_ring_phases(tasks: usize, data_size: usize) -> Vec<Vec<Vec<(usize,usize)>>>
+{
+    let block = data_size/tasks;
+    (1..tasks).map(|phase|(0..tasks).map(|task|vec![((task+1)%tasks,block*(tasks-phase))]).collect()).collect()
+}
+
+///The phases of the Bruck algorithm for an All2All. In the phase `k` the blocks at a distance with the bit `k` set advance `2^k` tasks.
+fn all2all_bruck_phases(tasks: usize, data_size: usize) -> Vec<Vec<Vec<(usize,usize)>>>
+{
+    let block = data_size/tasks;
+    let mut phases : Vec<Vec<Vec<(usize,usize)>>> = vec![];
+    let mut distance = 1;
+    while distance<tasks
+    {
+        let size = block*(1..tasks).filter(|offset|offset&distance!=0).count();
+        phases.push((0..tasks).map(|task|vec![((task+distance)%tasks,size)]).collect());
+        distance *= 2;
     }
+    phases
 }
 
 //Scater-reduce or all-gather in a ring
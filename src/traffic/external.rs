@@ -0,0 +1,245 @@
+/*!
+
+A traffic driven by an external process, to use CAMINOS as the network timing engine of a co-simulation.
+
+*/
+
+use std::collections::{BTreeSet, VecDeque};
+use std::convert::TryInto;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::mem::size_of;
+use std::net::TcpStream;
+use std::rc::Rc;
+use rand::prelude::StdRng;
+use crate::{match_object_panic, match_object, error, source_location, AsMessage, Message, Time};
+use crate::config_parser::{self, ConfigurationValue};
+use crate::error::{Error,SourceLocation};
+use crate::quantify::Quantifiable;
+use crate::topology::Topology;
+use crate::traffic::{TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
+
+/**
+A traffic whose messages are injected by an external process connected through a TCP socket. The simulator connects to the given `address` at creation.
+Both sides exchange objects written in the configuration syntax, one per line. The simulation advances in lockstep with the external process:
+1. At a synchronization point the simulator sends a `Delivered{id, origin, destination, size, creation_cycle, cycle}` for each message consumed since the previous one, followed by `Cycle{cycle}` with the current cycle.
+2. The external process replies with any number of `Inject{id, origin, destination, size}`, which are made available to the servers in the current cycle,
+   which must be between different tasks, and then either `Advance{until}`, to simulate until the next synchronization point at cycle `until`, or `Finish`, to stop synchronizing.
+3. After a `Finish` the simulator keeps sending a `Delivered` for each consumed message, and the traffic finishes when all of them have been consumed. Then the connection is closed.
+
+The `id` is chosen by the external process and returned in the `Delivered` object. Remember to set `measured` large enough to cover the whole co-simulation.
+
+```ignore
+External{
+	address: "127.0.0.1:7000",
+	tasks: 1000,
+}
+```
+**/
+#[derive(Debug)]
+pub struct External
+{
+	///Number of tasks applying this traffic.
+	tasks: usize,
+	///The connection to read orders from.
+	reader: BufReader<TcpStream>,
+	///The connection to write notifications to.
+	writer: BufWriter<TcpStream>,
+	///The next cycle in which to synchronize with the external process. `None` after a `Finish`.
+	next_sync: Option<Time>,
+	///The messages injected by the external process waiting for each task to generate them. Given as `(id,destination,size)`.
+	pending_messages: Vec<VecDeque<(u128,usize,usize)>>,
+	///The ids of the messages generated but not consumed.
+	generated_messages: BTreeSet<u128>,
+}
+
+impl Quantifiable for External
+{
+	fn total_memory(&self) -> usize
+	{
+		size_of::<External>() + self.pending_messages.total_memory() + self.generated_messages.total_memory()
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
+
+impl Traffic for External
+{
+	fn generate_message(&mut self, origin:usize, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+	{
+		if origin>=self.tasks
+		{
+			return Err(TrafficError::OriginOutsideTraffic);
+		}
+		let (id,destination,size) = self.pending_messages[origin].pop_front().expect("There is no message to generate");
+		let message=Rc::new(Message{
+			origin,
+			destination,
+			size,
+			creation_cycle: cycle,
+			payload: id.to_le_bytes().into(),
+			id_traffic: None,
+			routing_hints: None,
+			tag: None,
+			class: 0,
+		});
+		self.generated_messages.insert(id);
+		Ok(message)
+	}
+	fn probability_per_cycle(&self, task:usize) -> f32
+	{
+		if self.pending_messages[task].is_empty() { 0.0 } else { 1.0 }
+	}
+	fn should_generate(&mut self, task:usize, _cycle:Time, _rng: &mut StdRng) -> bool
+	{
+		!self.pending_messages[task].is_empty()
+	}
+	fn consume(&mut self, _task:usize, message: &dyn AsMessage, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> bool
+	{
+		let id = u128::from_le_bytes(message.payload()[0..16].try_into().expect("bad payload"));
+		if !self.generated_messages.remove(&id)
+		{
+			return false;
+		}
+		writeln!(self.writer,"Delivered{{id:{},origin:{},destination:{},size:{},creation_cycle:{},cycle:{}}}",id,message.origin(),message.destination(),message.size(),message.creation_cycle(),cycle).expect("Could not write to the external process");
+		if self.next_sync.is_none()
+		{
+			self.writer.flush().expect("Could not write to the external process");
+		}
+		true
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.next_sync.is_none() && self.generated_messages.is_empty() && self.pending_messages.iter().all(|p|p.is_empty())
+	}
+	fn task_state(&self, task:usize, _cycle:Time) -> Option<TaskTrafficState>
+	{
+		if !self.pending_messages[task].is_empty()
+		{
+			Some(TaskTrafficState::Generating)
+		}
+		else if self.next_sync.is_some()
+		{
+			Some(TaskTrafficState::UnspecifiedWait)
+		}
+		else
+		{
+			Some(TaskTrafficState::FinishedGenerating)
+		}
+	}
+	fn number_tasks(&self) -> usize
+	{
+		self.tasks
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		match self.next_sync
+		{
+			Some(sync) if sync<=cycle => self.synchronize(cycle),
+			_ => (),
+		}
+	}
+}
+
+impl External
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Result<External,Error>
+	{
+		let mut tasks=None;
+		let mut address=None;
+		match_object!(arg.cv,"External",value,
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"address" => address=Some(value.as_str()?.to_string()),
+		);
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let address=address.ok_or_else(||arg.cv.ill("There were no address"))?;
+		let stream = TcpStream::connect(&address).unwrap_or_else(|error|panic!("Could not connect to the external process at {}: {}",address,error));
+		let reader = BufReader::new(stream.try_clone().expect("Could not clone the connection to the external process"));
+		let writer = BufWriter::new(stream);
+		Ok(External{
+			tasks,
+			reader,
+			writer,
+			next_sync: Some(0),
+			pending_messages: vec![VecDeque::new();tasks],
+			generated_messages: BTreeSet::new(),
+		})
+	}
+	///Notify the current `cycle` and process orders until one allows to continue the simulation.
+	fn synchronize(&mut self, cycle:Time)
+	{
+		writeln!(self.writer,"Cycle{{cycle:{}}}",cycle).expect("Could not write to the external process");
+		self.writer.flush().expect("Could not write to the external process");
+		loop
+		{
+			let mut line = String::new();
+			let read = self.reader.read_line(&mut line).expect("Could not read from the external process");
+			if read==0
+			{
+				//The connection has been closed. Consider it as a Finish.
+				self.next_sync=None;
+				return;
+			}
+			let line = line.trim();
+			if line.is_empty()
+			{
+				continue;
+			}
+			let order = match config_parser::parse(line)
+			{
+				Ok(config_parser::Token::Value(value)) => value,
+				Ok(token) => panic!("The external process sent something that is not a value: {:?}",token),
+				Err(error) => panic!("Could not parse the order {} of the external process: {:?}",line,error),
+			};
+			let name = match order
+			{
+				ConfigurationValue::Object(ref name, _) => name.clone(),
+				_ => panic!("The external process sent a non-Object: {}",line),
+			};
+			match name.as_ref()
+			{
+				"Inject" =>
+				{
+					let mut id=None;
+					let mut origin=None;
+					let mut destination=None;
+					let mut size=None;
+					match_object_panic!(&order,"Inject",value,
+						"id" => id=Some(value.as_usize().expect("bad value for id") as u128),
+						"origin" => origin=Some(value.as_usize().expect("bad value for origin")),
+						"destination" => destination=Some(value.as_usize().expect("bad value for destination")),
+						"size" => size=Some(value.as_usize().expect("bad value for size")),
+					);
+					let origin=origin.expect("There were no origin");
+					let destination=destination.expect("There were no destination");
+					assert!(origin<self.tasks,"The external process injected from task {} but there are only {} tasks",origin,self.tasks);
+					assert!(destination<self.tasks,"The external process injected to task {} but there are only {} tasks",destination,self.tasks);
+					assert!(origin!=destination,"The external process injected a message from task {} to itself",origin);
+					self.pending_messages[origin].push_back((id.expect("There were no id"),destination,size.expect("There were no size")));
+				},
+				"Advance" =>
+				{
+					let mut until=None;
+					match_object_panic!(&order,"Advance",value,
+						"until" => until=Some(value.as_time().expect("bad value for until")),
+					);
+					let until=until.expect("There were no until");
+					assert!(until>cycle,"The external process requested to advance to cycle {}, which is not after the current cycle {}",until,cycle);
+					self.next_sync=Some(until);
+					return;
+				},
+				"Finish" =>
+				{
+					self.next_sync=None;
+					return;
+				},
+				_ => panic!("Unknown order {} from the external process",name),
+			}
+		}
+	}
+}
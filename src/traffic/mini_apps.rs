@@ -5,17 +5,18 @@ use std::rc::Rc;
 use quantifiable_derive::Quantifiable;
 
 use rand::prelude::StdRng;
-use crate::{match_object_panic, Message, Time};
+use crate::{match_object, error, source_location, Message, Time};
 
 use crate::Pattern;
 use crate::AsMessage;
 use crate::config_parser::ConfigurationValue;
-use crate::pattern::{new_pattern, PatternBuilderArgument};
+use crate::error::{Error,SourceLocation};
+use crate::pattern::{try_new_pattern, PatternBuilderArgument};
 use crate::topology::Topology;
-use crate::traffic::{new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
+use crate::traffic::{try_new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
 use crate::traffic::basic::{build_burst_cv, build_message_cv, BuildBurstCVArgs, BuildMessageCVArgs};
 use crate::traffic::collectives::{build_message_barrier_cv, BuildMessageBarrierCVArgs};
-use crate::traffic::TaskTrafficState::{Generating, UnspecifiedWait};
+use crate::traffic::TaskTrafficState::{Finished, Generating, UnspecifiedWait, WaitingCycle, WaitingData};
 use crate::pattern::extra::{get_candidates_selection,get_cartesian_transform};
 
 
@@ -95,6 +96,9 @@ impl Traffic for TrafficCredit
 			creation_cycle: cycle,
 			payload: id.to_le_bytes().into(),
 			id_traffic: None,
+			routing_hints: None,
+			tag: None,
+			class: 0,
 		});
 		self.generated_messages.insert(id);
 
@@ -177,7 +181,7 @@ impl Traffic for TrafficCredit
 
 impl TrafficCredit
 {
-	pub fn new(arg:TrafficBuilderArgument) -> TrafficCredit
+	pub fn new(arg:TrafficBuilderArgument) -> Result<TrafficCredit,Error>
 	{
 		let mut tasks=None;
 		let mut pattern =None;
@@ -188,24 +192,24 @@ impl TrafficCredit
 		let mut initial_credits=None;
 		let mut message_size_pattern = None;
 
-		match_object_panic!(arg.cv,"TrafficCredit",value,
-			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"tasks" | "servers" => tasks=Some(value.as_usize().expect("bad value for tasks")),
-			"credits_to_activate" => credits_to_activate=Some(value.as_usize().expect("bad value for credits_to_activate")),
-			"credits_per_received_message" => credits_per_received_message=Some(value.as_usize().expect("bad value for credits_per_received_message")),
-			"message_size" => message_size=Some(value.as_usize().expect("bad value for message_size") ),
-			"messages_per_transition" => messages_per_transition=Some(value.as_usize().expect("bad value for messages_per_transition")),
-			"initial_credits" => initial_credits=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"message_size_pattern" => message_size_pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
+		match_object!(arg.cv,"TrafficCredit",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"credits_to_activate" => credits_to_activate=Some(value.as_usize()?),
+			"credits_per_received_message" => credits_per_received_message=Some(value.as_usize()?),
+			"message_size" => message_size=Some(value.as_usize()? ),
+			"messages_per_transition" => messages_per_transition=Some(value.as_usize()?),
+			"initial_credits" => initial_credits=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"message_size_pattern" => message_size_pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
 		);
 
-		let tasks=tasks.expect("There were no tasks");
-		let mut pattern = pattern.expect("There were no pattern");
-		let credits_to_activate=credits_to_activate.expect("There were no credits_to_activate");
-		let credits_per_received_message=credits_per_received_message.expect("There were no credits_per_received_message");
-		let message_size=message_size.expect("There were no message_size");
-		let messages_per_transition=messages_per_transition.expect("There were no messages_per_transition");
-		let mut initial_credits=initial_credits.expect("There were no initial_credits");
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let mut pattern = pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
+		let credits_to_activate=credits_to_activate.ok_or_else(||arg.cv.ill("There were no credits_to_activate"))?;
+		let credits_per_received_message=credits_per_received_message.ok_or_else(||arg.cv.ill("There were no credits_per_received_message"))?;
+		let message_size=message_size.ok_or_else(||arg.cv.ill("There were no message_size"))?;
+		let messages_per_transition=messages_per_transition.ok_or_else(||arg.cv.ill("There were no messages_per_transition"))?;
+		let mut initial_credits=initial_credits.ok_or_else(||arg.cv.ill("There were no initial_credits"))?;
 
 		pattern.initialize(tasks, tasks, arg.topology, arg.rng);
 		initial_credits.initialize(tasks, tasks, arg.topology, arg.rng);
@@ -216,7 +220,7 @@ impl TrafficCredit
 		if let Some(patron) = message_size_pattern.as_mut(){
 			patron.initialize(tasks, tasks, arg.topology, arg.rng);
 		}
-		TrafficCredit{
+		Ok(TrafficCredit{
 			tasks,
 			pattern,
 			credits_to_activate,
@@ -228,7 +232,7 @@ impl TrafficCredit
 			pending_messages,
 			generated_messages: BTreeSet::new(),
 			next_id: 0,
-		}
+		})
 	}
 }
 
@@ -299,7 +303,7 @@ pub struct MiniApp {}
 
 impl MiniApp {
 
-    pub fn new(traffic: String, arg:TrafficBuilderArgument) -> Box<dyn Traffic> {
+    pub fn new(traffic: String, arg:TrafficBuilderArgument) -> Result<Box<dyn Traffic>,Error> {
 
         let traffic_cv = match traffic.as_str() {
 
@@ -308,15 +312,15 @@ impl MiniApp {
                 let mut data_size = None;
                 let mut num_messages = None;
 
-                match_object_panic!(arg.cv, "Wavefront", value,
-                    "task_space" => task_space = Some(value.as_array().expect("Bad task_space value").iter().map(|v| v.as_f64().expect("Bad task_space value") as usize).collect()),
-                    "data_size" => data_size = Some(value.as_f64().expect("Bad data_size value") as usize),
-                    "num_messages" => num_messages = Some(value.as_f64().expect("Bad num_messages value") as usize),
+                match_object!(arg.cv, "Wavefront", value,
+                    "task_space" => task_space = Some(value.as_array()?.iter().map(|v| Ok(v.as_f64()? as usize)).collect::<Result<_,Error>>()?),
+                    "data_size" => data_size = Some(value.as_f64()? as usize),
+                    "num_messages" => num_messages = Some(value.as_f64()? as usize),
                 );
 
-                let task_space = task_space.expect("task_space is required");
-                let data_size = data_size.expect("data_size is required");
-                let num_messages = num_messages.expect("num_messages is required");
+                let task_space = task_space.ok_or_else(||arg.cv.ill("task_space is required"))?;
+                let data_size = data_size.ok_or_else(||arg.cv.ill("data_size is required"))?;
+                let num_messages = num_messages.ok_or_else(||arg.cv.ill("num_messages is required"))?;
 
                 get_wavefront(task_space, data_size, num_messages)
             },
@@ -325,21 +329,22 @@ impl MiniApp {
 				let mut message_size = None;
 				let mut barriers_at_each_dimension = None;
 
-				match_object_panic!(arg.cv, "Stencil", value,
-					"task_space" => task_space = Some(value.as_array().expect("Bad task_space value").iter().map(|v| v.as_f64().expect("Bad task_space value") as usize).collect()),
-					"message_size" => message_size = Some(value.as_f64().expect("Bad message_size value") as usize),
-					"synchronization_at_each_dimension" => barriers_at_each_dimension = Some(value.as_bool().expect("Bad barriers_at_each_dimension value")),
+				match_object!(arg.cv, "Stencil", value,
+					"task_space" => task_space = Some(value.as_array()?.iter().map(|v| Ok(v.as_f64()? as usize)).collect::<Result<_,Error>>()?),
+					"message_size" => message_size = Some(value.as_f64()? as usize),
+					"synchronization_at_each_dimension" => barriers_at_each_dimension = Some(value.as_bool()?),
 				);
 
-				let task_space = task_space.expect("task_space is required");
-				let message_size = message_size.expect("message_size is required");
-				let barriers_at_each_dimension = barriers_at_each_dimension.expect("barriers_at_each_dimension is required");
+				let task_space = task_space.ok_or_else(||arg.cv.ill("task_space is required"))?;
+				let message_size = message_size.ok_or_else(||arg.cv.ill("message_size is required"))?;
+				let barriers_at_each_dimension = barriers_at_each_dimension.ok_or_else(||arg.cv.ill("barriers_at_each_dimension is required"))?;
 
 				get_stencil(task_space, message_size, barriers_at_each_dimension)
 			},
-            _ => panic!("Unknown traffic type: {}", traffic),
+
+            _ => return Err(arg.cv.ill(&format!("Unknown traffic type: {}", traffic))),
         };
-        new_traffic(TrafficBuilderArgument{cv: &traffic_cv, ..arg})
+        try_new_traffic(TrafficBuilderArgument{cv: &traffic_cv, ..arg})
     }
 
 }
@@ -429,4 +434,270 @@ fn get_stencil(task_space: Vec<usize>, message_size: usize, barriers_at_each_dim
 	}else{
 		build_burst_cv(traffic_message_cv_builder)
 	}
-}
\ No newline at end of file
+}
+
+/**
+Iterative halo exchange over a grid of processes, as done by 3D stencil codes.
+In each iteration every task computes for `compute_cycles` cycles and then sends a message of `message_size` to each of its neighbours in the `process_grid`, the +1 and -1 in each dimension.
+A task begins the next iteration once it has sent its halos and received those of all its neighbours. The `process_grid` may have any number of dimensions, although it is usually 3D.
+When `periodic` is true the grid wraps around, otherwise the tasks in the borders have fewer neighbours.
+Messages from neighbours that are one iteration ahead are kept for the next iteration.
+
+The cycle in which the last task completes each iteration is reported in `traffic_extra_statistics`, together with the duration of each iteration.
+
+```ignore
+Stencil3D{
+	process_grid: [4,4,4],//the number of tasks is the product
+	iterations: 10,
+	message_size: 64,
+	compute_cycles: 1000,
+	periodic: true,//optional, defaults to true.
+}
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct Stencil3D
+{
+	///Number of tasks applying this traffic.
+	tasks: usize,
+	///The neighbours of each task, to which it sends a halo each iteration and from which it receives one.
+	neighbours: Vec<Vec<usize>>,
+	///The size of each halo message.
+	message_size: usize,
+	///The number of iterations to perform.
+	iterations: usize,
+	///The cycles of computation before each exchange.
+	compute_cycles: Time,
+	///The current iteration of each task.
+	iteration: Vec<usize>,
+	///The cycle in which each task ends the computation of its current iteration. None if it has not started.
+	compute_until: Vec<Option<Time>>,
+	///The number of halos sent by each task in its current iteration.
+	sent: Vec<usize>,
+	///The number of halos received by each task for its current iteration and for the next one.
+	received: Vec<(usize,usize)>,
+	///The cycle in which the first task started.
+	begin_cycle: Option<Time>,
+	///The cycle in which each iteration has been completed by the last task, as far as known.
+	completion_cycles: Vec<Time>,
+	///The number of tasks that have completed each iteration.
+	completed_tasks: Vec<usize>,
+	///Set of generated messages.
+	generated_messages: BTreeSet<u128>,
+	///The id of the next message to generate.
+	next_id: u128,
+}
+
+impl Traffic for Stencil3D
+{
+	fn generate_message(&mut self, origin:usize, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+	{
+		if origin>=self.tasks
+		{
+			return Err(TrafficError::OriginOutsideTraffic);
+		}
+		let destination = *self.neighbours[origin].get(self.sent[origin]).expect("This task has no halo to send");
+		self.sent[origin]+=1;
+		let id = self.next_id;
+		self.next_id += 1;
+		let mut payload : Vec<u8> = id.to_le_bytes().into();
+		payload.extend_from_slice(&(self.iteration[origin] as u64).to_le_bytes());
+		let message=Rc::new(Message{
+			origin,
+			destination,
+			size:self.message_size,
+			creation_cycle: cycle,
+			payload,
+			id_traffic: None,
+			routing_hints: None,
+			tag: None,
+			class: 0,
+		});
+		self.generated_messages.insert(id);
+		self.try_complete(origin,cycle);
+		Ok(message)
+	}
+	fn probability_per_cycle(&self, task:usize) -> f32
+	{
+		if self.iteration[task]<self.iterations && self.sent[task]<self.neighbours[task].len() { 1.0 } else { 0.0 }
+	}
+	fn should_generate(&mut self, task:usize, cycle:Time, _rng: &mut StdRng) -> bool
+	{
+		if self.iteration[task]>=self.iterations
+		{
+			return false;
+		}
+		let compute_until = match self.compute_until[task]
+		{
+			Some(compute_until) => compute_until,
+			None =>
+			{
+				if self.begin_cycle.is_none()
+				{
+					self.begin_cycle = Some(cycle);
+				}
+				self.compute_until[task] = Some(cycle+self.compute_cycles);
+				cycle+self.compute_cycles
+			},
+		};
+		if cycle<compute_until
+		{
+			return false;
+		}
+		//Tasks without neighbours complete here their iterations.
+		self.try_complete(task,cycle);
+		self.iteration[task]<self.iterations && self.sent[task]<self.neighbours[task].len() && self.compute_until[task].map_or(false,|c|c<=cycle)
+	}
+	fn consume(&mut self, task:usize, message: &dyn AsMessage, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> bool
+	{
+		let payload = message.payload();
+		let id = u128::from_le_bytes(payload[0..16].try_into().expect("bad payload"));
+		if !self.generated_messages.remove(&id)
+		{
+			return false;
+		}
+		let iteration = u64::from_le_bytes(payload[16..24].try_into().expect("bad payload")) as usize;
+		if iteration==self.iteration[task]
+		{
+			self.received[task].0+=1;
+		}
+		else if iteration==self.iteration[task]+1
+		{
+			self.received[task].1+=1;
+		}
+		else
+		{
+			panic!("Task {} at iteration {} received a halo of iteration {}",task,self.iteration[task],iteration);
+		}
+		self.try_complete(task,cycle);
+		true
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.generated_messages.is_empty() && self.iteration.iter().all(|&i|i>=self.iterations)
+	}
+	fn task_state(&self, task:usize, cycle:Time) -> Option<TaskTrafficState>
+	{
+		if self.iteration[task]>=self.iterations
+		{
+			return Some(Finished);
+		}
+		match self.compute_until[task]
+		{
+			None => Some(UnspecifiedWait),
+			Some(compute_until) if compute_until>cycle => Some(WaitingCycle{cycle:compute_until}),
+			Some(_) => if self.sent[task]<self.neighbours[task].len() { Some(Generating) } else { Some(WaitingData) },
+		}
+	}
+	fn number_tasks(&self) -> usize
+	{
+		self.tasks
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let completed = self.completed_tasks.iter().take_while(|&&count|count==self.tasks).count();
+		let completion_cycles = &self.completion_cycles[..completed];
+		let begin = self.begin_cycle.unwrap_or(0);
+		let iteration_times : Vec<Time> = completion_cycles.iter().enumerate().map(|(index,&c)| c - if index==0 { begin } else { completion_cycles[index-1] }).collect();
+		let average = if completed==0 { 0f64 } else { iteration_times.iter().sum::<Time>() as f64 / completed as f64 };
+		let to_array = |values:&[Time]| ConfigurationValue::Array(values.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect());
+		Some(ConfigurationValue::Object(String::from("Stencil3DStatistics"),vec![
+			(String::from("completed_iterations"),ConfigurationValue::Number(completed as f64)),
+			(String::from("begin_cycle"),ConfigurationValue::Number(begin as f64)),
+			(String::from("completion_cycles"),to_array(completion_cycles)),
+			(String::from("iteration_times"),to_array(&iteration_times)),
+			(String::from("average_iteration_time"),ConfigurationValue::Number(average)),
+		]))
+	}
+}
+
+impl Stencil3D
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Result<Stencil3D,Error>
+	{
+		let mut process_grid=None;
+		let mut iterations=None;
+		let mut message_size=None;
+		let mut compute_cycles=None;
+		let mut periodic=true;
+		match_object!(arg.cv,"Stencil3D",value,
+			"process_grid" => process_grid=Some(value.as_array()?.iter().map(|v|v.as_usize().expect("bad value in process_grid")).collect::<Vec<usize>>()),
+			"iterations" => iterations=Some(value.as_usize()?),
+			"message_size" => message_size=Some(value.as_usize()?),
+			"compute_cycles" => compute_cycles=Some(value.as_time()?),
+			"periodic" => periodic=value.as_bool()?,
+		);
+		let process_grid=process_grid.ok_or_else(||arg.cv.ill("There were no process_grid"))?;
+		let iterations=iterations.ok_or_else(||arg.cv.ill("There were no iterations"))?;
+		let message_size=message_size.ok_or_else(||arg.cv.ill("There were no message_size"))?;
+		let compute_cycles=compute_cycles.ok_or_else(||arg.cv.ill("There were no compute_cycles"))?;
+		let tasks : usize = process_grid.iter().product();
+		let neighbours = (0..tasks).map(|task|{
+			let mut coordinates = Vec::with_capacity(process_grid.len());
+			let mut rest = task;
+			for &side in process_grid.iter()
+			{
+				coordinates.push(rest%side);
+				rest/=side;
+			}
+			let mut list = vec![];
+			let mut stride = 1;
+			for (dimension,&side) in process_grid.iter().enumerate()
+			{
+				let coordinate = coordinates[dimension];
+				let candidates = if periodic
+				{
+					vec![(coordinate+1)%side, (coordinate+side-1)%side]
+				}
+				else
+				{
+					let mut candidates = vec![];
+					if coordinate+1<side { candidates.push(coordinate+1) }
+					if coordinate>0 { candidates.push(coordinate-1) }
+					candidates
+				};
+				for other in candidates
+				{
+					if other!=coordinate
+					{
+						list.push(task + other*stride - coordinate*stride);
+					}
+				}
+				stride*=side;
+			}
+			list
+		}).collect();
+		Ok(Stencil3D{
+			tasks,
+			neighbours,
+			message_size,
+			iterations,
+			compute_cycles,
+			iteration: vec![0;tasks],
+			compute_until: vec![None;tasks],
+			sent: vec![0;tasks],
+			received: vec![(0,0);tasks],
+			begin_cycle: None,
+			completion_cycles: vec![0;iterations],
+			completed_tasks: vec![0;iterations],
+			generated_messages: BTreeSet::new(),
+			next_id: 0,
+		})
+	}
+	///Advances the iteration of the `task` while it has exchanged all its halos.
+	fn try_complete(&mut self, task:usize, cycle:Time)
+	{
+		let expected = self.neighbours[task].len();
+		while self.iteration[task]<self.iterations && self.compute_until[task].map_or(false,|c|c<=cycle) && self.sent[task]==expected && self.received[task].0==expected
+		{
+			let iteration = self.iteration[task];
+			self.completion_cycles[iteration] = self.completion_cycles[iteration].max(cycle);
+			self.completed_tasks[iteration]+=1;
+			self.iteration[task]+=1;
+			self.sent[task]=0;
+			self.received[task]=(self.received[task].1,0);
+			self.compute_until[task]=Some(cycle+self.compute_cycles);
+		}
+	}
+}
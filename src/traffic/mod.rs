@@ -3,7 +3,7 @@
 
 A [Traffic] defines the way their tasks generate load. In the root traffic of a simulation there should be as many tasks as servers. Traffics with other number of tasks can be combined into a such main traffic.
 
-see [`new_traffic`](fn.new_traffic.html) for documentation on the configuration syntax of predefined traffics.
+see [`new_traffic, try_new_traffic`](fn.new_traffic.html) for documentation on the configuration syntax of predefined traffics.
 
 */
 
@@ -12,9 +12,15 @@ mod sequences;
 mod mini_apps;
 mod basic;
 mod operations;
+mod external;
+mod trace;
+mod task_graph;
+pub mod arrival;
+
+pub use trace::InjectionRecord;
 
 use crate::AsMessage;
-use crate::traffic::mini_apps::{MiniApp, TrafficCredit};
+use crate::traffic::mini_apps::{MiniApp, Stencil3D, TrafficCredit};
 use crate::traffic::collectives::MessageBarrier;
 use crate::traffic::collectives::MPICollective;
 use crate::traffic::sequences::MessageTaskSequence;
@@ -29,12 +35,16 @@ use ::rand::{rngs::StdRng};
 
 use crate::config_parser::ConfigurationValue;
 use crate::{Message,Plugs};
+use crate::error::Error;
 use crate::topology::Topology;
 use crate::event::Time;
 use crate::measures::TrafficStatistics;
 use crate::quantify::Quantifiable;
-use crate::traffic::basic::{Burst, Homogeneous, PeriodicBurst, Reactive, Sleep, SubRangeTraffic, TrafficMessages};
-use crate::traffic::operations::{BoundedDifference, ProductTraffic, Shifted, Sum, TrafficMap};
+use crate::traffic::basic::{Burst, Homogeneous, PeriodicBurst, PingPong, Reactive, Sleep, SubRangeTraffic, TrafficMessages};
+use crate::traffic::operations::{AdmissionControl, BoundedDifference, ProductTraffic, Shifted, Sum, Tagged, TrafficMap, WithRoutingHints};
+use crate::traffic::external::External;
+use crate::traffic::trace::TraceReplay;
+use crate::traffic::task_graph::TaskGraph;
 
 ///Possible errors when trying to generate a message with a `Traffic`.
 #[derive(Debug)]
@@ -98,6 +108,17 @@ pub trait Traffic : Quantifiable + Debug
 	fn get_statistics(&self) -> Option<TrafficStatistics> {
 		None
 	}
+	///To optionally write statistics specific to the traffic into the simulation output.
+	fn statistics(&self,_cycle:Time) -> Option<ConfigurationValue>{ None }
+	///The names given to the summands of a `TrafficSum`, to label their statistics. Empty when they have no names.
+	///Traffics wrapping another must forward it.
+	fn sub_traffic_names(&self) -> Vec<String> { vec![] }
+	///Called by the simulation at the beginning of each cycle, before any task generates or consumes.
+	///Meta-traffics must forward it to their inner traffics.
+	fn advance_cycle(&mut self,_cycle:Time) {}
+	///Clears all collected statistics, called by the simulation when the warmup ends.
+	///Meta-traffics must forward it to their inner traffics.
+	fn reset_statistics(&mut self,_next_cycle:Time) {}
 }
 
 #[derive(Debug)]
@@ -120,7 +141,8 @@ pub struct TrafficBuilderArgument<'a>
 ### Homogeneous
 [Homogeneous] is a traffic where all tasks behave equally and uniform in time. Some `pattern` is generated
 by `tasks` number of involved tasks along the whole simulation. Each task tries to use its link toward the network a `load`
-fraction of the cycles. The generated messages has a size in phits of `message_size`. The generation is the typical Bernoulli process.
+fraction of the cycles. The generated messages has a size in phits of `message_size`. The generation is the typical Bernoulli process,
+unless other process is selected with the optional `arrival` field, see the [arrival] module.
 
 Example configuration.
 ```ignore
@@ -129,6 +151,7 @@ HomogeneousTraffic{
 	tasks:1000,
 	load: 0.9,
 	message_size: 16,
+	arrival: Poisson,//optional, by default Bernoulli
 }
 ```
 
@@ -155,6 +178,66 @@ Reactive{
 }
 ```
 
+### PingPong
+
+In the [PingPong] traffic each task `t` with `pattern(t)!=t` sends a single message to `pattern(t)` and waits for its reply before sending the next one, for a number of `iterations`.
+The round-trip times are reported for each pair in `traffic_extra_statistics`. It is intended to measure the latency at zero or low load.
+```ignore
+PingPong{
+	pattern: RandomPermutation,
+	tasks: 1000,
+	iterations: 100,
+	warmup_iterations: 10,
+	message_size: 16,
+}
+```
+
+### External
+
+The [External] traffic receives its messages from an external process through a TCP socket, advancing the simulation in lockstep with it.
+This allows to couple CAMINOS as the network timing engine of other simulators. See its documentation for the protocol.
+```ignore
+External{
+	address: "127.0.0.1:7000",
+	tasks: 1000,
+}
+```
+
+### TraceReplay
+
+The [TraceReplay] traffic replays the messages recorded in a trace file, such as those obtained from the execution of a real application.
+Each message may indicate the earliest cycle to be generated and the messages that must be received before. See its documentation for the file formats.
+```ignore
+TraceReplay{
+	tasks: 64,
+	filename: "/path/to/trace.csv",
+}
+```
+
+### TaskGraph
+
+The [TaskGraph] traffic executes a directed acyclic graph of tasks read from a file. Each node is some cycles of computation in a task and each edge a message of some size, sent when its origin node has been computed.
+The `makespan` of the graph is reported in `traffic_extra_statistics`, together with its `critical_path`.
+```ignore
+TaskGraph{
+	tasks: 64,
+	filename: "/path/to/graph.csv",
+}
+```
+
+### Stencil3D
+
+The [Stencil3D] mini-app performs iterations of halo exchange between the neighbours of a grid of processes, with some cycles of computation before each exchange.
+The cycle in which each iteration is completed is reported in `traffic_extra_statistics`.
+```ignore
+Stencil3D{
+	process_grid: [4,4,4],
+	iterations: 10,
+	message_size: 64,
+	compute_cycles: 1000,
+}
+```
+
 ## Operations
 
 ### TrafficSum
@@ -223,6 +306,41 @@ Sequence{
 }
 ```
 
+### AdmissionControl
+
+[AdmissionControl] caps the injection rate of each task of a traffic. The rates are discovered during the first `adaptation_cycles` by an AIMD controller against the message delay, and then they are kept fixed.
+The final rates are reported in the traffic statistics, allowing to study how much admission control can recover performance under adversarial patterns.
+```ignore
+AdmissionControl{
+	tasks: 1000,
+	traffic: HomogeneousTraffic{...},
+	target_delay: 200,
+	adaptation_cycles: 20000,
+}
+```
+
+### WithRoutingHints
+
+[WithRoutingHints] attaches [RoutingHints](crate::RoutingHints) to the messages of a traffic, which the routing may read to make application-informed decisions.
+For example, [UGAL](crate::routing::basic::UGAL) routes minimally the latency critical messages, and both UGAL and [Valiant](crate::routing::basic::Valiant) use the hinted intermediate instead of a random one.
+```ignore
+WithRoutingHints{
+	traffic: HomogeneousTraffic{...},
+	latency_critical: true,
+	intermediate: RandomPermutation,
+}
+```
+
+### Tagged
+
+[Tagged] sets the `collective`, `phase`, or `event` of the [MessageTag](crate::MessageTag) of the messages of a traffic, so that the defined statistics and the packet trace can group them.
+```ignore
+Tagged{
+	traffic: AllReduce{...},
+	collective: 0,
+}
+```
+
 ## Meta traffics
 
 ### TrafficMap
@@ -241,40 +359,54 @@ A [TrafficMap] also can map the set of tasks into a greater set. This is, a smal
 
 */
 pub fn new_traffic(arg:TrafficBuilderArgument) -> Box<dyn Traffic>
+{
+	try_new_traffic(arg).unwrap_or_else(|error|panic!("{}",error))
+}
+
+///Like [new_traffic] but returning an error when the traffic cannot be built, for example because of an unknown field.
+pub fn try_new_traffic(arg:TrafficBuilderArgument) -> Result<Box<dyn Traffic>,Error>
 {
 	if let &ConfigurationValue::Object(ref cv_name, ref _cv_pairs)=arg.cv
 	{
 		if let Some(builder) = arg.plugs.traffics.get(cv_name)
 		{
-			return builder(arg);
+			return Ok(builder(arg));
 		}
-		match cv_name.as_ref()
+		Ok(match cv_name.as_ref()
 		{
-			"HomogeneousTraffic" => Box::new(Homogeneous::new(arg)),
-			"TrafficSum" => Box::new(Sum::new(arg)),
-			"ShiftedTraffic" => Box::new(Shifted::new(arg)),
-			"ProductTraffic" => Box::new(ProductTraffic::new(arg)),
-			"SubRangeTraffic" => Box::new(SubRangeTraffic::new(arg)),
-			"Burst" => Box::new(Burst::new(arg)),
-			"MultimodalBurst" => Box::new(MultimodalBurst::new(arg)),
-			"Reactive" => Box::new(Reactive::new(arg)),
-			"TimeSequenced" => Box::new(TimeSequenced::new(arg)),
-			"Sequence" => Box::new(Sequence::new(arg)),
-			"BoundedDifference" => Box::new(BoundedDifference::new(arg)),
-			"TrafficMap" => Box::new(TrafficMap::new(arg)),
-			"PeriodicBurst" => Box::new(PeriodicBurst::new(arg)),
-			"Sleep" => Box::new(Sleep::new(arg)),
-			"TrafficCredit" => Box::new(TrafficCredit::new(arg)),
-			"Messages" => Box::new(TrafficMessages::new(arg)),
-			"MessageTaskSequence" => Box::new(MessageTaskSequence::new(arg)),
-			"MessageBarrier" => Box::new(MessageBarrier::new(arg)),
-			"AllReduce" | "ScatterReduce" | "AllGather" | "All2All" => MPICollective::new(cv_name.clone(), arg),
-			"Wavefront" | "Stencil" => MiniApp::new(cv_name.clone(), arg),
-			_ => panic!("Unknown traffic {}",cv_name),
-		}
+			"HomogeneousTraffic" => Box::new(Homogeneous::new(arg)?),
+			"TrafficSum" => Box::new(Sum::new(arg)?),
+			"ShiftedTraffic" => Box::new(Shifted::new(arg)?),
+			"ProductTraffic" => Box::new(ProductTraffic::new(arg)?),
+			"SubRangeTraffic" => Box::new(SubRangeTraffic::new(arg)?),
+			"Burst" => Box::new(Burst::new(arg)?),
+			"MultimodalBurst" => Box::new(MultimodalBurst::new(arg)?),
+			"Reactive" => Box::new(Reactive::new(arg)?),
+			"PingPong" => Box::new(PingPong::new(arg)?),
+			"External" => Box::new(External::new(arg)?),
+			"TraceReplay" => Box::new(TraceReplay::new(arg)?),
+			"TaskGraph" => Box::new(TaskGraph::new(arg)?),
+			"TimeSequenced" => Box::new(TimeSequenced::new(arg)?),
+			"Sequence" => Box::new(Sequence::new(arg)?),
+			"BoundedDifference" => Box::new(BoundedDifference::new(arg)?),
+			"AdmissionControl" => Box::new(AdmissionControl::new(arg)?),
+			"WithRoutingHints" => Box::new(WithRoutingHints::new(arg)?),
+			"Tagged" => Box::new(Tagged::new(arg)?),
+			"TrafficMap" => Box::new(TrafficMap::new(arg)?),
+			"PeriodicBurst" => Box::new(PeriodicBurst::new(arg)?),
+			"Sleep" => Box::new(Sleep::new(arg)?),
+			"TrafficCredit" => Box::new(TrafficCredit::new(arg)?),
+			"Stencil3D" => Box::new(Stencil3D::new(arg)?),
+			"Messages" => Box::new(TrafficMessages::new(arg)?),
+			"MessageTaskSequence" => Box::new(MessageTaskSequence::new(arg)?),
+			"MessageBarrier" => Box::new(MessageBarrier::new(arg)?),
+			"AllReduce" | "ScatterReduce" | "AllGather" | "All2All" => MPICollective::new(cv_name.clone(), arg)?,
+			"Wavefront" | "Stencil" => MiniApp::new(cv_name.clone(), arg)?,
+			_ => return Err(arg.cv.ill(&format!("Unknown traffic {}",cv_name))),
+		})
 	}
 	else
 	{
-		panic!("Trying to create a traffic from a non-Object");
+		Err(arg.cv.ill("Trying to create a traffic from a non-Object"))
 	}
-}
\ No newline at end of file
+}
@@ -4,14 +4,15 @@ use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::convert::TryInto;
 use std::rc::Rc;
 use quantifiable_derive::Quantifiable;
-use rand::prelude::{SliceRandom, StdRng};
-use crate::{match_object_panic, Message, Time};
+use rand::prelude::{Rng, SliceRandom, StdRng};
+use crate::{match_object, error, source_location, Message, MessageTag, RoutingHints, Time};
 use crate::measures::TrafficStatistics;
-use crate::pattern::{new_pattern, Pattern, PatternBuilderArgument};
-use crate::topology::Topology;
-use crate::traffic::{new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
+use crate::pattern::{try_new_pattern, Pattern, PatternBuilderArgument};
+use crate::topology::{Location,Topology};
+use crate::traffic::{try_new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
 use crate::traffic::TaskTrafficState::{Generating, WaitingData};
 use crate::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 
 
 /**
@@ -126,6 +127,9 @@ impl Traffic for TrafficMap
                 creation_cycle: app_message.creation_cycle,
                 payload: app_message.payload().into(),
                 id_traffic: app_message.id_traffic,
+                routing_hints: app_message.routing_hints.clone(),
+                tag: app_message.tag.clone(),
+                class: app_message.class,
             }
         );
         Ok(message)
@@ -148,7 +152,7 @@ impl Traffic for TrafficMap
         let task_app = self.from_machine_to_app[task].expect("There was no origin for the message");
         let mut app_message = ReferredPayload::from(message);
         app_message.destination = self.from_machine_to_app[app_message.destination].expect("There was no destination for the message");
-        app_message.origin = task_app;
+        app_message.origin = self.from_machine_to_app[app_message.origin].expect("There was no origin for the message");
 
         // try to consume the message in the application
         self.application.consume(task_app, &app_message, cycle, topology, rng)
@@ -189,25 +193,39 @@ impl Traffic for TrafficMap
     fn get_statistics(&self) -> Option<TrafficStatistics> {
         self.application.get_statistics()
     }
+    fn statistics(&self, cycle:Time) -> Option<ConfigurationValue> {
+        self.application.statistics(cycle)
+    }
+    fn sub_traffic_names(&self) -> Vec<String> {
+        self.application.sub_traffic_names()
+    }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        self.application.advance_cycle(cycle);
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        self.application.reset_statistics(next_cycle);
+    }
 }
 
 
 impl TrafficMap
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> TrafficMap
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<TrafficMap,Error>
     {
         let mut application = None;
         let mut map = None;
         let mut number_tasks = None;
-        match_object_panic!(arg.cv,"TrafficMap",value,
-			"tasks" => number_tasks=Some(value.as_f64().expect("bad value for tasks") as usize),
-			"application" => application = Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})), //traffic of the application
-			"map" => map = Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})), //map of the application over the machine
+        match_object!(arg.cv,"TrafficMap",value,
+			"tasks" => number_tasks=Some(value.as_f64()? as usize),
+			"application" => application = Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?), //traffic of the application
+			"map" => map = Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?), //map of the application over the machine
 		);
 
-        let number_tasks = number_tasks.expect("There were no tasks in configuration of TrafficMap.");
-        let application = application.expect("There were no application in configuration of TrafficMap.");
-        let mut map = map.expect("There were no map in configuration of TrafficMap.");
+        let number_tasks = number_tasks.ok_or_else(||arg.cv.ill("There were no tasks in configuration of TrafficMap."))?;
+        let application = application.ok_or_else(||arg.cv.ill("There were no application in configuration of TrafficMap."))?;
+        let mut map = map.ok_or_else(||arg.cv.ill("There were no map in configuration of TrafficMap."))?;
 
         let app_tasks = application.number_tasks();
         map.initialize(app_tasks, number_tasks, arg.topology, arg.rng);
@@ -223,14 +241,14 @@ impl TrafficMap
             from_machine_to_app[from_app_to_machine[i]] = Some(i);
         }
 
-        TrafficMap
+        Ok(TrafficMap
         {
             application,
             from_machine_to_app,
             from_app_to_machine,
             number_tasks,
             map,
-        }
+        })
     }
 }
 
@@ -248,6 +266,27 @@ TrafficSum{
 	box_size: 1000, //group results for the messages histogram.
 	finish_when: [0, 1] // (Optional) finish when the first and second subtraffics are finished. It waits for all by default
     server_task_isolation: false //(Optional) if true, a server can be assigned more than one task. Default is false.
+    weights: [3, 1], //(Optional) the share of the injection opportunities of each subtraffic.
+    names: ["victim", "aggressor"], //(Optional) the names of the subtraffics in the results.
+}
+```
+
+With `weights` each cycle a single subtraffic, chosen with probability proportional to its weight, is offered to generate a message.
+Thus each subtraffic injects its own rate times its share of the weights, which allows to split the injection of the servers among several tenants.
+Without `weights` every subtraffic is offered to generate in every cycle.
+
+The result of the simulation includes the `sub_traffic_statistics` with the loads and delays of the messages of each subtraffic, labelled by its `index` in the list and by its name, if given.
+The `traffic_extra_statistics` of the subtraffics that report them, such as `PingPong`, are gathered into the `summands` of a `TrafficSumStatistics`, labelled in the same way.
+This allows to measure a foreground traffic under the interference of a background one, as in the following, where the `victim` ends the simulation while the `aggressor` is endless.
+```ignore
+TrafficSum{
+	list: [
+		HomogeneousTraffic{pattern:Uniform, servers:64, load:0.2, message_size:16},
+		HomogeneousTraffic{pattern:Hotspots{destinations:[0]}, servers:64, load:0.5, message_size:16},
+	],
+	names: ["victim", "aggressor"],
+	finish_when: [0],
+	...
 }
 ```
  **/
@@ -267,6 +306,10 @@ pub struct Sum
     finish_when: Vec<usize>,
     ///Indicate if only one task should be generating messages at a time in the server.
     server_task_isolation: bool,
+    ///The accumulated weights of the subtraffics, normalized to end in 1, if they should share the injection opportunities.
+    cumulative_weights: Option<Vec<f64>>,
+    ///The names of the subtraffics, or empty if not given.
+    names: Vec<String>,
 }
 
 impl Traffic for Sum
@@ -299,6 +342,9 @@ impl Traffic for Sum
                     creation_cycle: message.creation_cycle(),
                     payload,
                     id_traffic: Some(index),
+                    routing_hints: message.routing_hints.clone(),
+                    tag: message.tag.clone(),
+                    class: message.class,
                 }
             ))
 
@@ -310,7 +356,14 @@ impl Traffic for Sum
 
     fn probability_per_cycle(&self,task:usize) -> f32
     {
-        self.list.iter().map(|t|t.probability_per_cycle(task)).sum()
+        match self.cumulative_weights
+        {
+            Some(ref cumulative) => self.list.iter().enumerate().map(|(index,t)|{
+                let share = cumulative[index] - if index==0 {0.0} else {cumulative[index-1]};
+                t.probability_per_cycle(task) * share as f32
+            }).sum(),
+            None => self.list.iter().map(|t|t.probability_per_cycle(task)).sum(),
+        }
     }
     fn consume(&mut self, task:usize, message: &dyn AsMessage, cycle:Time, topology:&dyn Topology, rng: &mut StdRng) -> bool
     {
@@ -345,8 +398,21 @@ impl Traffic for Sum
             return true;
         }
 
-        let mut indexes = (0..self.list.len()).collect::<Vec<usize>>();
-        indexes.shuffle(rng);
+        let indexes = match self.cumulative_weights
+        {
+            Some(ref cumulative) =>
+            {
+                let r = rng.gen::<f64>();
+                let chosen = cumulative.iter().position(|&c|r<c).unwrap_or(cumulative.len()-1);
+                vec![chosen]
+            },
+            None =>
+            {
+                let mut indexes = (0..self.list.len()).collect::<Vec<usize>>();
+                indexes.shuffle(rng);
+                indexes
+            },
+        };
 
         for index in indexes.iter(){
             if self.list[*index].should_generate(task,cycle,rng){
@@ -399,11 +465,48 @@ impl Traffic for Sum
     fn get_statistics(&self) -> Option<TrafficStatistics> {
         Some(self.statistics.clone())
     }
+    fn sub_traffic_names(&self) -> Vec<String> {
+        self.names.clone()
+    }
+    fn statistics(&self, cycle:Time) -> Option<ConfigurationValue> {
+        //The extra statistics of each summand that has them, labelled as in `sub_traffic_statistics`.
+        let summands : Vec<ConfigurationValue> = self.list.iter().enumerate().filter_map(|(index,traffic)|{
+            let statistics = traffic.statistics(cycle)?;
+            let mut content = vec![ (String::from("index"),ConfigurationValue::Number(index as f64)) ];
+            if let Some(name) = self.names.get(index)
+            {
+                content.push( (String::from("name"),ConfigurationValue::Literal(name.clone())) );
+            }
+            content.push( (String::from("statistics"),statistics) );
+            Some(ConfigurationValue::Object(String::from("SubTrafficExtraStatistics"),content))
+        }).collect();
+        if summands.is_empty()
+        {
+            None
+        } else {
+            Some(ConfigurationValue::Object(String::from("TrafficSumStatistics"),vec![ (String::from("summands"),ConfigurationValue::Array(summands)) ]))
+        }
+    }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        for traffic in self.list.iter_mut()
+        {
+            traffic.advance_cycle(cycle);
+        }
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        self.statistics.reset(next_cycle);
+        for traffic in self.list.iter_mut()
+        {
+            traffic.reset_statistics(next_cycle);
+        }
+    }
 }
 
 impl Sum
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> Sum
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<Sum,Error>
     {
         let mut list : Option<Vec<_>> =None;
         let mut temporal_step = 0;
@@ -411,16 +514,20 @@ impl Sum
         let mut tasks = None;
         let mut finish_when = None;
         let mut server_task_isolation = true;
-        match_object_panic!(arg.cv,"TrafficSum",value,
-			"list" => list = Some(value.as_array().expect("bad value for list").iter()
-				.map(|v|new_traffic(TrafficBuilderArgument{cv:v,rng:&mut arg.rng,..arg})).collect()),
-			"statistics_temporal_step" => temporal_step = value.as_f64().expect("bad value for statistics_temporal_step") as Time,
-			"tasks" => tasks = Some(value.as_f64().expect("bad value for tasks") as usize),
-			"box_size" => box_size = value.as_f64().expect("bad value for box_size") as usize,
-			"finish_when" => finish_when = Some(value.as_array().expect("bad value for finish_when").iter().map(|v|v.as_usize().expect("bad value for finish_when")).collect()),
-            "server_task_isolation" => server_task_isolation = value.as_bool().expect("bad value for server_task_isolation"),
+        let mut weights : Option<Vec<f64>> = None;
+        let mut names : Vec<String> = vec![];
+        match_object!(arg.cv,"TrafficSum",value,
+			"list" => list = Some(value.as_array()?.iter()
+				.map(|v|try_new_traffic(TrafficBuilderArgument{cv:v,rng:&mut arg.rng,..arg})).collect::<Result<_,_>>()?),
+			"statistics_temporal_step" => temporal_step = value.as_f64()? as Time,
+			"tasks" => tasks = Some(value.as_f64()? as usize),
+			"box_size" => box_size = value.as_f64()? as usize,
+			"finish_when" => finish_when = Some(value.as_array()?.iter().map(|v|v.as_usize()).collect::<Result<_,_>>()?),
+            "server_task_isolation" => server_task_isolation = value.as_bool()?,
+            "weights" => weights = Some(value.as_array()?.iter().map(|v|v.as_f64()).collect::<Result<_,_>>()?),
+            "names" => names = value.as_array()?.iter().map(|v|Ok(v.as_str()?.to_string())).collect::<Result<_,Error>>()?,
         );
-        let list=list.expect("There were no list");
+        let list=list.ok_or_else(||arg.cv.ill("There were no list"))?;
         assert!( !list.is_empty() , "cannot sum 0 traffics" );
         let size = list[0].number_tasks();
         for traffic in list.iter().skip(1)
@@ -428,19 +535,33 @@ impl Sum
             assert_eq!( traffic.number_tasks(), size , "In SumTraffic all sub-traffics must involve the same number of tasks." );
         }
         let finish_when = finish_when.unwrap_or_else(|| (0..list.len()).collect()); //default wait for all
+        if !names.is_empty() && names.len()!=list.len()
+        {
+            return Err(arg.cv.ill("In SumTraffic there must be a name for each sub-traffic."));
+        }
+        let cumulative_weights = weights.map(|weights|{
+            assert_eq!( weights.len(), list.len(), "In SumTraffic there must be a weight for each sub-traffic." );
+            assert!( weights.iter().all(|&w|w>=0.0), "In SumTraffic the weights cannot be negative." );
+            let total : f64 = weights.iter().sum();
+            assert!( total>0.0, "In SumTraffic some weight must be positive." );
+            let mut accumulated = 0.0;
+            weights.iter().map(|w|{ accumulated += w/total; accumulated }).collect()
+        });
         let tasks = tasks.unwrap();
         let list_statistics = list.iter().map(|_| TrafficStatistics::new(tasks,temporal_step, box_size, None)).collect();
         let statistics = TrafficStatistics::new(tasks,temporal_step, box_size, Some(list_statistics));
         //Debug and print the traffic list
         //println!("TrafficSum list: {:?}", list);
-        Sum{
+        Ok(Sum{
             list,
             index_to_generate: vec![VecDeque::new(); tasks ],
             statistics,
             tasks,
             finish_when,
             server_task_isolation,
-        }
+            cumulative_weights,
+            names,
+        })
     }
 }
 
@@ -495,6 +616,9 @@ impl Traffic for ProductTraffic
 			creation_cycle: cycle,
 			payload,
             id_traffic: None,
+            routing_hints: inner_message.routing_hints.clone(),
+            tag: inner_message.tag.clone(),
+            class: inner_message.class,
         });
 		//self.generated_messages.insert(outer_message.as_ref() as *const Message,inner_message);
 		Ok(outer_message)
@@ -537,33 +661,45 @@ impl Traffic for ProductTraffic
 	fn number_tasks(&self) -> usize {
 		self.block_traffic.number_tasks() * self.global_size
 	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		self.block_traffic.statistics(cycle)
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.block_traffic.advance_cycle(cycle);
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.block_traffic.reset_statistics(next_cycle);
+	}
 }
 
 impl ProductTraffic
 {
-	pub fn new(mut arg:TrafficBuilderArgument) -> ProductTraffic
+	pub fn new(mut arg:TrafficBuilderArgument) -> Result<ProductTraffic,Error>
 	{
 		let mut block_size=None;
 		let mut block_traffic=None;
 		let mut global_pattern=None;
-		match_object_panic!(arg.cv,"ProductTraffic",value,
-			"block_traffic" => block_traffic=Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"global_pattern" => global_pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"block_size" => block_size=Some(value.as_f64().expect("bad value for block_size") as usize),
+		match_object!(arg.cv,"ProductTraffic",value,
+			"block_traffic" => block_traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"global_pattern" => global_pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"block_size" => block_size=Some(value.as_f64()? as usize),
 		);
-		let block_size=block_size.expect("There were no block_size");
-		let block_traffic=block_traffic.expect("There were no block_traffic");
-		let mut global_pattern=global_pattern.expect("There were no global_pattern");
+		let block_size=block_size.ok_or_else(||arg.cv.ill("There were no block_size"))?;
+		let block_traffic=block_traffic.ok_or_else(||arg.cv.ill("There were no block_traffic"))?;
+		let mut global_pattern=global_pattern.ok_or_else(||arg.cv.ill("There were no global_pattern"))?;
 		// TODO: should receive a `global_size` argument. When missing, fall back to use topology size.
 		// TODO: Also check for divisibility.
 		let global_size=arg.topology.num_servers()/block_size;
 		global_pattern.initialize(global_size,global_size,arg.topology,arg.rng);
-		ProductTraffic{
+		Ok(ProductTraffic{
 			block_size,
 			block_traffic,
 			global_pattern,
 			global_size,
-		}
+		})
 	}
 }
 
@@ -620,6 +756,9 @@ impl Traffic for BoundedDifference
 			creation_cycle: cycle,
 			payload: id.to_le_bytes().into(),
             id_traffic: None,
+            routing_hints: None,
+            tag: None,
+            class: 0,
         });
 		self.generated_messages.insert(id);
 		Ok(message)
@@ -664,31 +803,35 @@ impl Traffic for BoundedDifference
 	fn number_tasks(&self) -> usize {
 		self.tasks
 	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		//There are no statistics nor inner traffics. The allowances are state of the traffic and must survive the warmup.
+	}
 }
 
 impl BoundedDifference
 {
-	pub fn new(arg:TrafficBuilderArgument) -> BoundedDifference
+	pub fn new(arg:TrafficBuilderArgument) -> Result<BoundedDifference,Error>
 	{
 		let mut tasks=None;
 		let mut load=None;
 		let mut pattern=None;
 		let mut message_size=None;
 		let mut bound=None;
-		match_object_panic!(arg.cv,"BoundedDifference",value,
-			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})),
-			"tasks" | "servers" => tasks=Some(value.as_f64().expect("bad value for tasks") as usize),
-			"load" => load=Some(value.as_f64().expect("bad value for load") as f32),
-			"message_size" => message_size=Some(value.as_f64().expect("bad value for message_size") as usize),
-			"bound" => bound=Some(value.as_f64().expect("bad value for bound") as usize),
+		match_object!(arg.cv,"BoundedDifference",value,
+			"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+			"tasks" | "servers" => tasks=Some(value.as_f64()? as usize),
+			"load" => load=Some(value.as_f64()? as f32),
+			"message_size" => message_size=Some(value.as_f64()? as usize),
+			"bound" => bound=Some(value.as_f64()? as usize),
 		);
-		let tasks=tasks.expect("There were no tasks");
-		let message_size=message_size.expect("There were no message_size");
-		let bound=bound.expect("There were no bound");
-		let load=load.expect("There were no load");
-		let mut pattern=pattern.expect("There were no pattern");
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let message_size=message_size.ok_or_else(||arg.cv.ill("There were no message_size"))?;
+		let bound=bound.ok_or_else(||arg.cv.ill("There were no bound"))?;
+		let load=load.ok_or_else(||arg.cv.ill("There were no load"))?;
+		let mut pattern=pattern.ok_or_else(||arg.cv.ill("There were no pattern"))?;
 		pattern.initialize(tasks, tasks, arg.topology, arg.rng);
-		BoundedDifference{
+		Ok(BoundedDifference{
 			tasks,
 			pattern,
 			message_size,
@@ -697,7 +840,7 @@ impl BoundedDifference
 			generated_messages: BTreeSet::new(),
 			allowance: vec![bound;tasks],
 			next_id: 0,
-		}
+		})
 	}
 }
 
@@ -745,6 +888,9 @@ impl Traffic for Shifted
             creation_cycle: cycle,
             payload: inner_message.payload.clone(),
             id_traffic: None,
+            routing_hints: inner_message.routing_hints.clone(),
+            tag: inner_message.tag.clone(),
+            class: inner_message.class,
         });
         //self.generated_messages.insert(outer_message.as_ref() as *const Message,inner_message);
         Ok(outer_message)
@@ -776,24 +922,456 @@ impl Traffic for Shifted
         // TODO: think if this is correct.
         self.traffic.number_tasks()
     }
+    fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+    {
+        self.traffic.statistics(cycle)
+    }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        self.traffic.advance_cycle(cycle);
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        self.traffic.reset_statistics(next_cycle);
+    }
 }
 
 impl Shifted
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> Shifted
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<Shifted,Error>
     {
         let mut shift=None;
         let mut traffic=None;
-        match_object_panic!(arg.cv,"ShiftedTraffic",value,
-			"traffic" => traffic=Some(new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})),
-			"shift" => shift=Some(value.as_f64().expect("bad value for shift") as usize),
+        match_object!(arg.cv,"ShiftedTraffic",value,
+			"traffic" => traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut arg.rng,..arg})?),
+			"shift" => shift=Some(value.as_f64()? as usize),
 		);
-        let shift=shift.expect("There were no shift");
-        let traffic=traffic.expect("There were no traffic");
-        Shifted{
+        let shift=shift.ok_or_else(||arg.cv.ill("There were no shift"))?;
+        let traffic=traffic.ok_or_else(||arg.cv.ill("There were no traffic"))?;
+        Ok(Shifted{
             shift,
             traffic,
             generated_messages: BTreeMap::new(),
-        }
+        })
     }
 }
+
+
+/**
+Applies admission control to a traffic, capping the rate at which each task may inject. The rates are discovered during the first `adaptation_cycles` by an AIMD controller against the delay of the messages, and then they are kept fixed.
+Each `period` cycles of the adaptation, a task whose messages consumed in the period have an average delay, measured since their generation, over `target_delay` gets its rate multiplied by `decrease`. Otherwise it gets its rate incremented by `increase`.
+Only the messages generated after the last decrease of the rate of their task are considered, to not react to congestion already reacted upon.
+Tasks without such consumed messages in the period keep their rate. Rates are measured in phits per cycle and start at `initial_rate`.
+A task is allowed to generate a message when it has accumulated a positive number of phits with its rate; the inner traffic is ignored in the cycles the task is not allowed.
+
+The statistics of the traffic include the final rate of each task and its Jain index, which can be seen as the fair share achieved by the admission control.
+Set `adaptation_cycles` to the warmup of the simulation to adapt during warmup and measure the network with the fixed rates.
+```ignore
+AdmissionControl{
+	tasks: 1000,
+	traffic: HomogeneousTraffic{...},
+	target_delay: 200,
+	adaptation_cycles: 20000,
+	period: 100,//optional, by default 100
+	increase: 0.01,//optional, by default 0.01
+	decrease: 0.5,//optional, by default 0.5
+	initial_rate: 1.0,//optional, by default 1.0
+}
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct AdmissionControl
+{
+	///Number of tasks applying this traffic.
+	tasks: usize,
+	///The traffic being controlled.
+	traffic: Box<dyn Traffic>,
+	///The average delay above which the rates are decreased.
+	target_delay: f64,
+	///The number of cycles in which the rates are adapted.
+	adaptation_cycles: Time,
+	///The number of cycles between rate updates.
+	period: Time,
+	///Additive increase of the rate.
+	increase: f64,
+	///Multiplicative decrease of the rate.
+	decrease: f64,
+	///The current rate of each task, in phits per cycle.
+	rates: Vec<f64>,
+	///The phits each task may inject. A task can generate when positive.
+	tokens: Vec<f64>,
+	///The sum of the delays of the messages of each task consumed in the current period.
+	delay_sum: Vec<Time>,
+	///The number of messages of each task consumed in the current period.
+	delay_count: Vec<usize>,
+	///The cycle of the last decrease of the rate of each task.
+	last_decrease: Vec<Time>,
+}
+
+impl Traffic for AdmissionControl
+{
+	fn generate_message(&mut self, origin:usize, cycle:Time, topology:&dyn Topology, rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+	{
+		if origin>=self.tasks
+		{
+			return Err(TrafficError::OriginOutsideTraffic);
+		}
+		let message = self.traffic.generate_message(origin,cycle,topology,rng)?;
+		self.tokens[origin] -= message.size as f64;
+		Ok(message)
+	}
+	fn probability_per_cycle(&self, task:usize) -> f32
+	{
+		self.traffic.probability_per_cycle(task).min(self.rates[task] as f32)
+	}
+	fn should_generate(&mut self, task:usize, cycle:Time, rng: &mut StdRng) -> bool
+	{
+		//Ask the inner traffic only when it can generate, so that it does not lose the opportunity.
+		self.tokens[task]>0.0 && self.traffic.should_generate(task,cycle,rng)
+	}
+	fn consume(&mut self, task:usize, message: &dyn AsMessage, cycle:Time, topology:&dyn Topology, rng: &mut StdRng) -> bool
+	{
+		if !self.traffic.consume(task,message,cycle,topology,rng)
+		{
+			return false;
+		}
+		let origin = message.origin();
+		if cycle < self.adaptation_cycles && origin<self.tasks && message.creation_cycle()>=self.last_decrease[origin]
+		{
+			self.delay_sum[origin] += cycle - message.creation_cycle();
+			self.delay_count[origin] += 1;
+		}
+		true
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.traffic.is_finished()
+	}
+	fn task_state(&self, task:usize, cycle:Time) -> Option<TaskTrafficState>
+	{
+		self.traffic.task_state(task,cycle)
+	}
+	fn number_tasks(&self) -> usize
+	{
+		self.tasks
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let sum : f64 = self.rates.iter().sum();
+		let square_sum : f64 = self.rates.iter().map(|rate|rate*rate).sum();
+		let jain_index = if square_sum>0.0 { sum*sum/square_sum/self.tasks as f64 } else { 1.0 };
+		let content = vec![
+			(String::from("rates"),ConfigurationValue::Array(self.rates.iter().map(|&rate|ConfigurationValue::Number(rate)).collect())),
+			(String::from("average_rate"),ConfigurationValue::Number(sum/self.tasks as f64)),
+			(String::from("jain_index"),ConfigurationValue::Number(jain_index)),
+		];
+		Some(ConfigurationValue::Object(String::from("AdmissionControlStatistics"),content))
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.traffic.advance_cycle(cycle);
+		if cycle>0 && cycle<=self.adaptation_cycles && cycle%self.period==0
+		{
+			for task in 0..self.tasks
+			{
+				if self.delay_count[task]>0
+				{
+					let average_delay = self.delay_sum[task] as f64 / self.delay_count[task] as f64;
+					if average_delay > self.target_delay
+					{
+						self.rates[task] *= self.decrease;
+						self.last_decrease[task] = cycle;
+					}
+					else
+					{
+						self.rates[task] = (self.rates[task]+self.increase).min(1.0);
+					}
+				}
+				self.delay_sum[task]=0;
+				self.delay_count[task]=0;
+			}
+		}
+		for task in 0..self.tasks
+		{
+			//Do not let idle tasks accumulate more than a phit, to keep the rate as a cap.
+			self.tokens[task] = (self.tokens[task]+self.rates[task]).min(1.0);
+		}
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.traffic.reset_statistics(next_cycle);
+	}
+}
+
+impl AdmissionControl
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Result<AdmissionControl,Error>
+	{
+		let mut tasks=None;
+		let mut traffic=None;
+		let mut target_delay=None;
+		let mut adaptation_cycles=None;
+		let mut period=100;
+		let mut increase=0.01;
+		let mut decrease=0.5;
+		let mut initial_rate=1.0;
+		match_object!(arg.cv,"AdmissionControl",value,
+			"traffic" => traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:arg.rng,..arg})?),
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"target_delay" => target_delay=Some(value.as_f64()?),
+			"adaptation_cycles" => adaptation_cycles=Some(value.as_time()?),
+			"period" => period=value.as_time()?,
+			"increase" => increase=value.as_f64()?,
+			"decrease" => decrease=value.as_f64()?,
+			"initial_rate" => initial_rate=value.as_f64()?,
+		);
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let traffic=traffic.ok_or_else(||arg.cv.ill("There were no traffic"))?;
+		let target_delay=target_delay.ok_or_else(||arg.cv.ill("There were no target_delay"))?;
+		let adaptation_cycles=adaptation_cycles.ok_or_else(||arg.cv.ill("There were no adaptation_cycles"))?;
+		assert!(period>0,"The period of AdmissionControl must be positive");
+		assert!(decrease>0.0 && decrease<1.0,"The decrease of AdmissionControl must be in (0,1)");
+		Ok(AdmissionControl{
+			tasks,
+			traffic,
+			target_delay,
+			adaptation_cycles,
+			period,
+			increase,
+			decrease,
+			rates: vec![initial_rate;tasks],
+			tokens: vec![0.0;tasks],
+			delay_sum: vec![0;tasks],
+			delay_count: vec![0;tasks],
+			last_decrease: vec![0;tasks],
+		})
+	}
+}
+
+/**
+Attaches [RoutingHints] to the messages of a traffic, so that the routing can read them through the `hints` field of the [RoutingInfo](crate::routing::RoutingInfo) of the packets.
+The hints of the messages of the inner traffic are replaced. The `intermediate` pattern gives for each origin task a server, whose router is the hinted intermediate.
+```ignore
+WithRoutingHints{
+	traffic: HomogeneousTraffic{...},
+	latency_critical: true,//optional, by default false.
+	//plane: 1,//optional.
+	//intermediate: RandomPermutation,//optional.
+}
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct WithRoutingHints
+{
+	///The traffic whose messages receive the hints.
+	traffic: Box<dyn Traffic>,
+	///The hints common to all the messages.
+	hints: RoutingHints,
+	///A pattern from the origin tasks to the servers whose routers are the intermediates.
+	intermediate: Option<Box<dyn Pattern>>,
+}
+
+impl Traffic for WithRoutingHints
+{
+	fn generate_message(&mut self, origin:usize, cycle:Time, topology:&dyn Topology, rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+	{
+		let message = self.traffic.generate_message(origin,cycle,topology,rng)?;
+		let mut hints = self.hints.clone();
+		if let Some(pattern) = self.intermediate.as_ref()
+		{
+			let server = pattern.get_destination(origin,topology,rng);
+			hints.intermediate = match topology.server_neighbour(server).0
+			{
+				Location::RouterPort{router_index,..} => Some(router_index),
+				_ => panic!("The server is not attached to a router"),
+			};
+		}
+		Ok(Rc::new(Message{
+			origin: message.origin,
+			destination: message.destination,
+			size: message.size,
+			creation_cycle: message.creation_cycle,
+			payload: message.payload.clone(),
+			id_traffic: message.id_traffic,
+			routing_hints: Some(Rc::new(hints)),
+			tag: message.tag.clone(),
+			class: message.class,
+		}))
+	}
+	fn probability_per_cycle(&self, task:usize) -> f32
+	{
+		self.traffic.probability_per_cycle(task)
+	}
+	fn should_generate(&mut self, task:usize, cycle:Time, rng: &mut StdRng) -> bool
+	{
+		self.traffic.should_generate(task,cycle,rng)
+	}
+	fn consume(&mut self, task:usize, message: &dyn AsMessage, cycle:Time, topology:&dyn Topology, rng: &mut StdRng) -> bool
+	{
+		self.traffic.consume(task,message,cycle,topology,rng)
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.traffic.is_finished()
+	}
+	fn task_state(&self, task:usize, cycle:Time) -> Option<TaskTrafficState>
+	{
+		self.traffic.task_state(task,cycle)
+	}
+	fn number_tasks(&self) -> usize
+	{
+		self.traffic.number_tasks()
+	}
+	fn get_statistics(&self) -> Option<TrafficStatistics>
+	{
+		self.traffic.get_statistics()
+	}
+	fn sub_traffic_names(&self) -> Vec<String>
+	{
+		self.traffic.sub_traffic_names()
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		self.traffic.statistics(cycle)
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.traffic.advance_cycle(cycle)
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.traffic.reset_statistics(next_cycle);
+	}
+}
+
+impl WithRoutingHints
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Result<WithRoutingHints,Error>
+	{
+		let mut traffic=None;
+		let mut hints=RoutingHints::default();
+		let mut intermediate=None;
+		match_object!(arg.cv,"WithRoutingHints",value,
+			"traffic" => traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut *arg.rng,..arg})?),
+			"latency_critical" => hints.latency_critical=value.as_bool()?,
+			"plane" => hints.plane=Some(value.as_usize()?),
+			"intermediate" => intermediate=Some(try_new_pattern(PatternBuilderArgument{cv:value,plugs:arg.plugs})?),
+		);
+		let traffic=traffic.ok_or_else(||arg.cv.ill("There were no traffic"))?;
+		if let Some(pattern) = intermediate.as_mut()
+		{
+			pattern.initialize(traffic.number_tasks(),arg.topology.num_servers(),arg.topology,arg.rng);
+		}
+		Ok(WithRoutingHints{
+			traffic,
+			hints,
+			intermediate,
+		})
+	}
+}
+
+/**
+Sets the [MessageTag](crate::MessageTag) of the messages of a traffic, to group their statistics. The fields not given here keep the value set by the inner traffic, if any.
+For example, to tell apart two collectives running at the same time.
+```ignore
+Sum{ list: [
+	Tagged{ traffic: AllReduce{...}, collective: 0 },
+	Tagged{ traffic: All2All{...}, collective: 1 },
+]}
+```
+Then `statistics_message_definitions: [ [[=tag.collective,=tag.phase],[=delay]] ]` gives the average delay of the messages of each phase of each collective.
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct Tagged
+{
+	///The traffic whose messages are tagged.
+	traffic: Box<dyn Traffic>,
+	///The fields to set in the tags.
+	tag: MessageTag,
+}
+
+impl Traffic for Tagged
+{
+	fn generate_message(&mut self, origin:usize, cycle:Time, topology:&dyn Topology, rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+	{
+		let message = self.traffic.generate_message(origin,cycle,topology,rng)?;
+		Ok(Rc::new(Message{
+			origin: message.origin,
+			destination: message.destination,
+			size: message.size,
+			creation_cycle: message.creation_cycle,
+			payload: message.payload.clone(),
+			id_traffic: message.id_traffic,
+			routing_hints: message.routing_hints.clone(),
+			tag: Some(Rc::new(self.tag.or(message.tag.as_deref()))),
+			class: message.class,
+		}))
+	}
+	fn probability_per_cycle(&self, task:usize) -> f32
+	{
+		self.traffic.probability_per_cycle(task)
+	}
+	fn should_generate(&mut self, task:usize, cycle:Time, rng: &mut StdRng) -> bool
+	{
+		self.traffic.should_generate(task,cycle,rng)
+	}
+	fn consume(&mut self, task:usize, message: &dyn AsMessage, cycle:Time, topology:&dyn Topology, rng: &mut StdRng) -> bool
+	{
+		self.traffic.consume(task,message,cycle,topology,rng)
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.traffic.is_finished()
+	}
+	fn task_state(&self, task:usize, cycle:Time) -> Option<TaskTrafficState>
+	{
+		self.traffic.task_state(task,cycle)
+	}
+	fn number_tasks(&self) -> usize
+	{
+		self.traffic.number_tasks()
+	}
+	fn get_statistics(&self) -> Option<TrafficStatistics>
+	{
+		self.traffic.get_statistics()
+	}
+	fn sub_traffic_names(&self) -> Vec<String>
+	{
+		self.traffic.sub_traffic_names()
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		self.traffic.statistics(cycle)
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		self.traffic.advance_cycle(cycle)
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.traffic.reset_statistics(next_cycle);
+	}
+}
+
+impl Tagged
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Result<Tagged,Error>
+	{
+		let mut traffic=None;
+		let mut tag=MessageTag::default();
+		match_object!(arg.cv,"Tagged",value,
+			"traffic" => traffic=Some(try_new_traffic(TrafficBuilderArgument{cv:value,rng:&mut *arg.rng,..arg})?),
+			"collective" => tag.collective=Some(value.as_usize()?),
+			"phase" => tag.phase=Some(value.as_usize()?),
+			"event" => tag.event=Some(value.as_usize()?),
+		);
+		let traffic=traffic.ok_or_else(||arg.cv.ill("There were no traffic"))?;
+		Ok(Tagged{
+			traffic,
+			tag,
+		})
+	}
+}
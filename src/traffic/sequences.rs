@@ -1,17 +1,18 @@
 use crate::AsMessage;
-use crate::pattern::{new_pattern, PatternBuilderArgument};
+use crate::pattern::{try_new_pattern, PatternBuilderArgument};
 use std::collections::{BTreeSet};
 use std::convert::TryInto;
 use std::rc::Rc;
 use quantifiable_derive::Quantifiable;
 use rand::prelude::StdRng;
-use crate::{match_object_panic, Message, Time};
+use crate::{match_object, error, source_location, Message, MessageTag, Time};
 use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
 use crate::measures::TrafficStatistics;
 use crate::packet::ReferredPayload;
 use crate::pattern::Pattern;
 use crate::topology::Topology;
-use crate::traffic::{new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
+use crate::traffic::{try_new_traffic, TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
 use crate::traffic::TaskTrafficState::{Finished, FinishedGenerating, UnspecifiedWait, WaitingCycle};
 
 /**
@@ -107,23 +108,37 @@ impl Traffic for Sequence
         // every traffic has the same number of tasks
         self.traffics[0].number_tasks()
     }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        for traffic in self.traffics.iter_mut()
+        {
+            traffic.advance_cycle(cycle);
+        }
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        for traffic in self.traffics.iter_mut()
+        {
+            traffic.reset_statistics(next_cycle);
+        }
+    }
 }
 
 impl Sequence
 {
-	pub fn new(arg:TrafficBuilderArgument) -> Sequence
+	pub fn new(arg:TrafficBuilderArgument) -> Result<Sequence,Error>
 	{
 		let mut traffics_args =None;
 		let mut period_number=1usize;
-		match_object_panic!(arg.cv,"Sequence",value,
-			"traffics" => traffics_args = Some(value.as_array().expect("bad value for traffics")),
-			"period_number" => period_number=value.as_f64().expect("bad value for period_number") as usize,
+		match_object!(arg.cv,"Sequence",value,
+			"traffics" => traffics_args = Some(value.as_array()?),
+			"period_number" => period_number=value.as_f64()? as usize,
 		);
-		let traffics_args=traffics_args.expect("There were no traffics");
+		let traffics_args=traffics_args.ok_or_else(||arg.cv.ill("There were no traffics"))?;
 		let TrafficBuilderArgument{plugs,topology,rng, ..} = arg;
 		let traffics : Vec<_> = (0..period_number).flat_map(|_ip| traffics_args.iter().map(
-			|v|new_traffic(TrafficBuilderArgument{cv:v,plugs,topology,rng:&mut *rng})
-		).collect::<Vec<_>>() ).collect();
+			|v|try_new_traffic(TrafficBuilderArgument{cv:v,plugs,topology,rng:&mut *rng})
+		).collect::<Vec<_>>() ).collect::<Result<_,_>>()?;
 		//let mut traffics = Vec::with_capacity(period_number*traffics_args.len());
 		//for _ip in 0..period_number
 		//{
@@ -139,16 +154,17 @@ impl Sequence
 		{
 			assert_eq!( traffic.number_tasks(), size , "In Sequence all sub-traffics must involve the same number of tasks." );
 		}
-		Sequence{
+		Ok(Sequence{
 			traffics,
 			current_traffic:0,
 			//current_period:0,
-		}
+		})
 	}
 }
 
 /**
 A sequence of traffics. Each task independently sends/consumes a number of messages before moving to the next traffic.
+The messages are tagged with the index of their traffic as phase, unless their traffic has already set a phase. See [MessageTag](crate::MessageTag).
 ```ignore
 MessageTaskSequence{
     tasks: 1000,
@@ -195,6 +211,8 @@ impl Traffic for MessageTaskSequence
                 let vec_payload = bytemuck::bytes_of(bytes_argument);
                 payload.extend_from_slice(&vec_payload);
                 payload.extend_from_slice(message.payload());
+                //The index of the traffic is the phase, unless the inner traffic has set one.
+                let tag = message.tag.as_deref().cloned().unwrap_or_default().or(Some(&MessageTag{phase:Some(i),..MessageTag::default()}));
 
                 let message = Rc::new(Message {
                     origin,
@@ -203,6 +221,9 @@ impl Traffic for MessageTaskSequence
                     creation_cycle: message.creation_cycle(),
                     payload,
                     id_traffic: None,
+                    routing_hints: message.routing_hints.clone(),
+                    tag: Some(Rc::new(tag)),
+                    class: message.class,
                 });
 
                 messages_sent[i] += 1;
@@ -318,34 +339,46 @@ impl Traffic for MessageTaskSequence
     fn get_statistics(&self) -> Option<TrafficStatistics> {
         None
     }
+    fn advance_cycle(&mut self, cycle:Time) {
+        for traffic in self.traffics.iter_mut()
+        {
+            traffic.advance_cycle(cycle);
+        }
+    }
+    fn reset_statistics(&mut self, next_cycle:Time) {
+        for traffic in self.traffics.iter_mut()
+        {
+            traffic.reset_statistics(next_cycle);
+        }
+    }
 }
 
 impl MessageTaskSequence
 {
-    pub fn new(arg: TrafficBuilderArgument) -> MessageTaskSequence
+    pub fn new(arg: TrafficBuilderArgument) -> Result<MessageTaskSequence,Error>
     {
         let mut traffics_args = None;
         let mut messages_to_send_per_traffic = None;
         let mut messages_to_consume_per_traffic = None;
         let mut tasks= None;
-        match_object_panic!(arg.cv, "MessageTaskSequence", value,
-			"tasks" => tasks = Some(value.as_usize().expect("Number of tasks for MessageTaskSequence wrong")),
-			"traffics" => traffics_args = Some(value.as_array().expect("bad value for traffics")),
-			"messages_to_send_per_traffic" => messages_to_send_per_traffic = Some(value.as_array().expect("bad value for messages_to_send_per_traffic").iter().map(|v| v.as_f64().expect("bad value in messages_to_send_per_traffic") as usize).collect()),
-			"messages_to_consume_per_traffic" => messages_to_consume_per_traffic = Some(value.as_array().expect("bad value for messages_to_consume_per_traffic").iter().map(|v| v.as_f64().expect("bad value in messages_to_consume_per_traffic") as usize).collect()),
+        match_object!(arg.cv, "MessageTaskSequence", value,
+			"tasks" => tasks = Some(value.as_usize()?),
+			"traffics" => traffics_args = Some(value.as_array()?),
+			"messages_to_send_per_traffic" => messages_to_send_per_traffic = Some(value.as_array()?.iter().map(|v| v.as_f64().expect("bad value in messages_to_send_per_traffic") as usize).collect()),
+			"messages_to_consume_per_traffic" => messages_to_consume_per_traffic = Some(value.as_array()?.iter().map(|v| v.as_f64().expect("bad value in messages_to_consume_per_traffic") as usize).collect()),
 		);
-        let tasks = tasks.expect("Number of tasks for MessageTaskSequence should be indicated");
-        let traffics_args = traffics_args.expect("There were no traffics");
+        let tasks = tasks.ok_or_else(||arg.cv.ill("Number of tasks for MessageTaskSequence should be indicated"))?;
+        let traffics_args = traffics_args.ok_or_else(||arg.cv.ill("There were no traffics"))?;
+        let messages_to_send_per_traffic = messages_to_send_per_traffic.ok_or_else(||arg.cv.ill("There were no messages_to_send_per_traffic"))?;
         let TrafficBuilderArgument { plugs, topology, rng, .. } = arg;
-        let traffics: Vec<_> = traffics_args.iter().map(|v| new_traffic(TrafficBuilderArgument { cv: v, plugs, topology, rng: &mut *rng })).collect();
-        let messages_to_send_per_traffic = messages_to_send_per_traffic.expect("There were no messages_to_send_per_traffic");
+        let traffics: Vec<_> = traffics_args.iter().map(|v| try_new_traffic(TrafficBuilderArgument { cv: v, plugs, topology, rng: &mut *rng })).collect::<Result<_,_>>()?;
         let messages_to_consume_per_traffic = messages_to_consume_per_traffic;
         for traffic in traffics.iter()
         {
             assert_eq!(traffic.number_tasks(), tasks, "In MessageTaskSequence all sub-traffics must involve the same number of tasks.");
         }
         let traffic_len = traffics.len();
-        MessageTaskSequence {
+        Ok(MessageTaskSequence {
             traffics,
             messages_to_send_per_traffic,
             messages_to_consume_per_traffic,
@@ -353,7 +386,7 @@ impl MessageTaskSequence
             messages_consumed: vec![ vec![0; traffic_len ]; tasks ],
             generated_messages: BTreeSet::new(),
             id: 0,
-        }
+        })
     }
 }
 
@@ -451,6 +484,9 @@ impl Traffic for MultimodalBurst
 			creation_cycle: cycle,
 			payload: id.to_le_bytes().into(),
             id_traffic: None,
+            routing_hints: None,
+            tag: None,
+            class: 0,
         });
 		self.generated_messages.insert(id);
 		Ok(message)
@@ -510,12 +546,12 @@ impl Traffic for MultimodalBurst
 
 impl MultimodalBurst
 {
-	pub fn new(arg:TrafficBuilderArgument) -> MultimodalBurst
+	pub fn new(arg:TrafficBuilderArgument) -> Result<MultimodalBurst,Error>
 	{
 		let mut tasks=None;
 		let mut provenance : Option<Vec<(_,_,_,_)>> = None;
-		match_object_panic!(arg.cv,"MultimodalBurst",value,
-			"tasks" | "servers" => tasks=Some(value.as_f64().expect("bad value for tasks") as usize),
+		match_object!(arg.cv,"MultimodalBurst",value,
+			"tasks" | "servers" => tasks=Some(value.as_f64()? as usize),
 			"provenance" => match value
 			{
 				&ConfigurationValue::Array(ref a) => provenance=Some(a.iter().map(|pcv|{
@@ -523,37 +559,37 @@ impl MultimodalBurst
 					let mut pattern=None;
 					let mut message_size=None;
 					let mut step_size=None;
-					match_object_panic!(pcv,"Provenance",pvalue,
-						"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:pvalue,plugs:arg.plugs})),
+					match_object!(pcv,"Provenance",pvalue,
+						"pattern" => pattern=Some(try_new_pattern(PatternBuilderArgument{cv:pvalue,plugs:arg.plugs})?),
 						"messages_per_task" | "messages_per_server" | "total_messages" =>
-							messages_per_task=Some(pvalue.as_f64().expect("bad value for messages_per_task") as usize),
-						"message_size" => message_size=Some(pvalue.as_f64().expect("bad value for message_size") as usize),
-						"step_size" => step_size=Some(pvalue.as_f64().expect("bad value for step_size") as usize),
+							messages_per_task=Some(pvalue.as_f64()? as usize),
+						"message_size" => message_size=Some(pvalue.as_f64()? as usize),
+						"step_size" => step_size=Some(pvalue.as_f64()? as usize),
 					);
-					let pattern=pattern.expect("There were no pattern");
-					let messages_per_task=messages_per_task.expect("There were no messages_per_task");
-					let message_size=message_size.expect("There were no message_size");
-					let step_size=step_size.expect("There were no step_size");
-					(pattern,messages_per_task,message_size,step_size)
-				}).collect()),
-				_ => panic!("bad value for provenance"),
+					let pattern=pattern.ok_or_else(||pcv.ill("There were no pattern"))?;
+					let messages_per_task=messages_per_task.ok_or_else(||pcv.ill("There were no messages_per_task"))?;
+					let message_size=message_size.ok_or_else(||pcv.ill("There were no message_size"))?;
+					let step_size=step_size.ok_or_else(||pcv.ill("There were no step_size"))?;
+					Ok((pattern,messages_per_task,message_size,step_size))
+				}).collect::<Result<_,_>>()?),
+				_ => return Err(value.ill("bad value for provenance")),
 			}
 		);
-		let tasks=tasks.expect("There were no tasks");
-		let mut provenance=provenance.expect("There were no provenance");
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let mut provenance=provenance.ok_or_else(||arg.cv.ill("There were no provenance"))?;
 		for (pattern,_total_messages,_message_size,_step_size) in provenance.iter_mut()
 		{
 			pattern.initialize(tasks, tasks, arg.topology, arg.rng);
 		}
 		let each_pending = provenance.iter().map(|(_pattern,total_messages,_message_size,step_size)|(*total_messages,*step_size)).collect();
-		MultimodalBurst{
+		Ok(MultimodalBurst{
 			tasks,
 			provenance,
 			pending: vec![each_pending;tasks],
 			next_provenance:vec![0;tasks],
 			generated_messages: BTreeSet::new(),
 			next_id: 0,
-		}
+		})
 	}
 }
 
@@ -664,32 +700,46 @@ impl Traffic for TimeSequenced
         // each traffic has the same number of tasks
         self.traffics[0].number_tasks()
     }
+    fn advance_cycle(&mut self, cycle:Time)
+    {
+        for traffic in self.traffics.iter_mut()
+        {
+            traffic.advance_cycle(cycle);
+        }
+    }
+    fn reset_statistics(&mut self, next_cycle:Time)
+    {
+        for traffic in self.traffics.iter_mut()
+        {
+            traffic.reset_statistics(next_cycle);
+        }
+    }
 }
 
 impl TimeSequenced
 {
-    pub fn new(mut arg:TrafficBuilderArgument) -> TimeSequenced
+    pub fn new(mut arg:TrafficBuilderArgument) -> Result<TimeSequenced,Error>
     {
         let mut traffics : Option<Vec<_>> =None;
         let mut times=None;
-        match_object_panic!(arg.cv,"TimeSequenced",value,
-			"traffics" => traffics = Some(value.as_array().expect("bad value for traffics").iter()
-				.map(|v|new_traffic(TrafficBuilderArgument{cv:v,rng:&mut arg.rng,..arg})).collect()),
+        match_object!(arg.cv,"TimeSequenced",value,
+			"traffics" => traffics = Some(value.as_array()?.iter()
+				.map(|v|try_new_traffic(TrafficBuilderArgument{cv:v,rng:&mut arg.rng,..arg})).collect::<Result<_,_>>()?),
 			"times" => times = Some(value.as_array()
-				.expect("bad value for times").iter()
-				.map(|v|v.as_time().expect("bad value in times")).collect()),
+				?.iter()
+				.map(|v|v.as_time()).collect::<Result<_,_>>()?),
 		);
-        let traffics=traffics.expect("There were no traffics");
+        let traffics=traffics.ok_or_else(||arg.cv.ill("There were no traffics"))?;
         assert!( !traffics.is_empty() , "Cannot make a TimeSequenced of 0 traffics." );
         let size = traffics[0].number_tasks();
         for traffic in traffics.iter().skip(1)
         {
             assert_eq!( traffic.number_tasks(), size , "In TimeSequenced all sub-traffics must involve the same number of tasks." );
         }
-        let times=times.expect("There were no times");
-        TimeSequenced{
+        let times=times.ok_or_else(||arg.cv.ill("There were no times"))?;
+        Ok(TimeSequenced{
             traffics,
             times,
-        }
+        })
     }
 }
@@ -0,0 +1,335 @@
+/*!
+
+A traffic executing a graph of tasks with dependencies, to study the scheduling of an application together with the network.
+
+*/
+
+use std::collections::{BTreeSet,VecDeque};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::rc::Rc;
+use rand::prelude::StdRng;
+use quantifiable_derive::Quantifiable;//the derive macro
+use crate::{match_object, error, source_location, AsMessage, Message, MessageTag, Time};
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::topology::Topology;
+use crate::traffic::{TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
+
+///A node of the graph: some computation performed by a task.
+#[derive(Debug,Quantifiable)]
+struct GraphNode
+{
+	///The task executing the node.
+	task: usize,
+	///The cycles of computation.
+	compute: Time,
+	///The edges leaving the node.
+	successors: Vec<usize>,
+	///The number of incoming edges whose data has not arrived yet.
+	pending_dependencies: usize,
+	///The cycle in which the computation was completed, if already.
+	finish_cycle: Option<Time>,
+}
+
+///An edge of the graph: data sent from a node to another once the former has been computed.
+#[derive(Debug,Quantifiable)]
+struct GraphEdge
+{
+	from: usize,
+	to: usize,
+	///The size in phits of the message. Edges between nodes of the same task do not send messages.
+	size: usize,
+}
+
+/**
+A traffic that executes a directed acyclic graph of computations read from a file. Each node is computed by a task during some cycles, and each edge is a message of some size from a node to another.
+A node can begin when the messages of all its incoming edges have been consumed, and the messages of its outgoing edges are generated when it has been computed.
+Each task computes a single node at a time, beginning with the ready node of lowest index. Edges between nodes of the same task do not produce messages, but still impose the order.
+The traffic finishes when all the nodes have been computed.
+
+Each line of the file is either `node,task,compute` or `edge,from,to,size`. The nodes are numbered from 0 in the order of the file, and the edges may refer to any of them.
+Empty lines and lines starting with `#` are ignored. For example, a node in the task 0 sending a result to two nodes in the tasks 1 and 2, which answer to a last node in the task 0.
+```ignore
+node,0,100
+node,1,50
+node,2,80
+node,0,10
+edge,0,1,64
+edge,0,2,64
+edge,1,3,16
+edge,2,3,16
+```
+
+The statistics of the traffic include the `makespan`, the cycle in which the last node was computed, and the `critical_path`, the cycles of computation along the longest path of the graph, which is the makespan of an ideal network.
+The messages are tagged with the index of their edge, see [MessageTag](crate::MessageTag).
+```ignore
+TaskGraph{
+	tasks: 64,
+	filename: "/path/to/graph.csv",
+}
+```
+**/
+#[derive(Debug,Quantifiable)]
+pub struct TaskGraph
+{
+	///Number of tasks applying this traffic.
+	tasks: usize,
+	///All the nodes in the graph.
+	nodes: Vec<GraphNode>,
+	///All the edges in the graph.
+	edges: Vec<GraphEdge>,
+	///For each task the nodes whose dependencies are satisfied but have not been begun.
+	ready: Vec<BTreeSet<usize>>,
+	///For each task the node being computed, if any, with the cycle of its completion.
+	running: Vec<Option<(usize,Time)>>,
+	///For each task the edges whose messages have to be generated.
+	outbox: Vec<VecDeque<usize>>,
+	///For each task the number of nodes not computed yet.
+	remaining_nodes: Vec<usize>,
+	///The number of nodes computed.
+	computed: usize,
+	///The cycles of computation along the longest path.
+	critical_path: Time,
+}
+
+impl Traffic for TaskGraph
+{
+	fn generate_message(&mut self, origin:usize, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+	{
+		if origin>=self.tasks
+		{
+			return Err(TrafficError::OriginOutsideTraffic);
+		}
+		let index = self.outbox[origin].pop_front().expect("There is no message ready to generate");
+		let edge = &self.edges[index];
+		let message=Rc::new(Message{
+			origin,
+			destination: self.nodes[edge.to].task,
+			size: edge.size,
+			creation_cycle: cycle,
+			payload: (index as u64).to_le_bytes().into(),
+			id_traffic: None,
+			routing_hints: None,
+			tag: Some(Rc::new(MessageTag{event:Some(index),..MessageTag::default()})),
+			class: 0,
+		});
+		Ok(message)
+	}
+	fn probability_per_cycle(&self, task:usize) -> f32
+	{
+		if self.outbox[task].is_empty() { 0.0 } else { 1.0 }
+	}
+	fn should_generate(&mut self, task:usize, _cycle:Time, _rng: &mut StdRng) -> bool
+	{
+		!self.outbox[task].is_empty()
+	}
+	fn consume(&mut self, _task:usize, message: &dyn AsMessage, _cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> bool
+	{
+		let index = u64::from_le_bytes(message.payload()[0..8].try_into().expect("bad payload")) as usize;
+		if index>=self.edges.len()
+		{
+			return false;
+		}
+		self.satisfy(self.edges[index].to);
+		true
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.computed==self.nodes.len()
+	}
+	fn task_state(&self, task:usize, _cycle:Time) -> Option<TaskTrafficState>
+	{
+		if !self.outbox[task].is_empty() || !self.ready[task].is_empty()
+		{
+			Some(TaskTrafficState::Generating)
+		}
+		else if let Some((_node,finish)) = self.running[task]
+		{
+			Some(TaskTrafficState::WaitingCycle{cycle:finish})
+		}
+		else if self.remaining_nodes[task]>0
+		{
+			Some(TaskTrafficState::WaitingData)
+		}
+		else
+		{
+			Some(TaskTrafficState::FinishedGenerating)
+		}
+	}
+	fn number_tasks(&self) -> usize
+	{
+		self.tasks
+	}
+	fn advance_cycle(&mut self, cycle:Time)
+	{
+		for task in 0..self.tasks
+		{
+			loop
+			{
+				if let Some((node,finish)) = self.running[task]
+				{
+					if finish>cycle
+					{
+						break;
+					}
+					self.running[task]=None;
+					self.complete(node,finish);
+				}
+				match self.ready[task].iter().next().copied()
+				{
+					Some(node) =>
+					{
+						self.ready[task].remove(&node);
+						self.running[task]=Some((node,cycle+self.nodes[node].compute));
+					},
+					None => break,
+				}
+			}
+		}
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let makespan = self.nodes.iter().filter_map(|node|node.finish_cycle).max().unwrap_or(0);
+		let content = vec![
+			(String::from("nodes"),ConfigurationValue::Number(self.nodes.len() as f64)),
+			(String::from("edges"),ConfigurationValue::Number(self.edges.len() as f64)),
+			(String::from("computed_nodes"),ConfigurationValue::Number(self.computed as f64)),
+			(String::from("makespan"),ConfigurationValue::Number(makespan as f64)),
+			(String::from("critical_path"),ConfigurationValue::Number(self.critical_path as f64)),
+		];
+		Some(ConfigurationValue::Object(String::from("TaskGraphStatistics"),content))
+	}
+}
+
+impl TaskGraph
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Result<TaskGraph,Error>
+	{
+		let mut tasks=None;
+		let mut filename=None;
+		match_object!(arg.cv,"TaskGraph",value,
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"filename" => filename=Some(value.as_str()?.to_string()),
+		);
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let filename=filename.ok_or_else(||arg.cv.ill("There were no filename"))?;
+		let file=File::open(&filename).map_err(|error|arg.cv.ill(&format!("Could not open the task graph file {}: {}",filename,error)))?;
+		let mut nodes = vec![];
+		let mut edges = vec![];
+		for (line_index,line) in BufReader::new(file).lines().enumerate()
+		{
+			let line = line.expect("Could not read the task graph file");
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#')
+			{
+				continue;
+			}
+			let fields : Vec<&str> = line.split(',').map(|field|field.trim()).collect();
+			let numbers : Option<Vec<usize>> = fields[1..].iter().map(|field|field.parse().ok()).collect();
+			match (fields[0],numbers.as_deref())
+			{
+				("node",Some(&[task,compute])) =>
+				{
+					assert!(task<tasks,"The node in the line {} of the task graph file {} is in the task {}, but there are only {} tasks",line_index+1,filename,task,tasks);
+					nodes.push(GraphNode{
+						task,
+						compute: compute as Time,
+						successors: vec![],
+						pending_dependencies: 0,
+						finish_cycle: None,
+					});
+				},
+				("edge",Some(&[from,to,size])) => edges.push(GraphEdge{from,to,size}),
+				_ => panic!("Could not parse the line {} of the task graph file {}: {}",line_index+1,filename,line),
+			}
+		}
+		for (index,edge) in edges.iter().enumerate()
+		{
+			assert!(edge.from<nodes.len() && edge.to<nodes.len(),"The edge {} goes from {} to {}, but there are only {} nodes",index,edge.from,edge.to,nodes.len());
+			assert!(edge.size>0 || nodes[edge.from].task==nodes[edge.to].task,"The edge {} between different tasks has no size",index);
+			nodes[edge.from].successors.push(index);
+			nodes[edge.to].pending_dependencies+=1;
+		}
+		let critical_path = critical_path(&nodes,&edges).unwrap_or_else(||panic!("The task graph in {} has a cycle",filename));
+		let mut ready = vec![BTreeSet::new();tasks];
+		let mut remaining_nodes = vec![0;tasks];
+		for (index,node) in nodes.iter().enumerate()
+		{
+			remaining_nodes[node.task]+=1;
+			if node.pending_dependencies==0
+			{
+				ready[node.task].insert(index);
+			}
+		}
+		Ok(TaskGraph{
+			tasks,
+			nodes,
+			edges,
+			ready,
+			running: vec![None;tasks],
+			outbox: vec![VecDeque::new();tasks],
+			remaining_nodes,
+			computed: 0,
+			critical_path,
+		})
+	}
+	///The data of an incoming edge of the `node` has arrived.
+	fn satisfy(&mut self, node:usize)
+	{
+		let node_data = &mut self.nodes[node];
+		node_data.pending_dependencies-=1;
+		if node_data.pending_dependencies==0
+		{
+			self.ready[node_data.task].insert(node);
+		}
+	}
+	///The computation of the `node` has been completed at the cycle `finish`.
+	fn complete(&mut self, node:usize, finish:Time)
+	{
+		let task = self.nodes[node].task;
+		self.nodes[node].finish_cycle = Some(finish);
+		self.computed+=1;
+		self.remaining_nodes[task]-=1;
+		for position in 0..self.nodes[node].successors.len()
+		{
+			let edge = self.nodes[node].successors[position];
+			let to = self.edges[edge].to;
+			if self.nodes[to].task==task
+			{
+				self.satisfy(to);
+			}
+			else
+			{
+				self.outbox[task].push_back(edge);
+			}
+		}
+	}
+}
+
+///The cycles of computation along the longest path of the graph, or `None` if it has a cycle.
+fn critical_path(nodes:&[GraphNode], edges:&[GraphEdge]) -> Option<Time>
+{
+	let mut pending : Vec<usize> = nodes.iter().map(|node|node.pending_dependencies).collect();
+	let mut queue : VecDeque<usize> = (0..nodes.len()).filter(|&index|pending[index]==0).collect();
+	//The greatest computation of a path ending at each node, including it.
+	let mut path = vec![0;nodes.len()];
+	let mut visited = 0;
+	while let Some(index) = queue.pop_front()
+	{
+		visited+=1;
+		path[index]+=nodes[index].compute;
+		for &edge in nodes[index].successors.iter()
+		{
+			let to = edges[edge].to;
+			path[to] = path[to].max(path[index]);
+			pending[to]-=1;
+			if pending[to]==0
+			{
+				queue.push_back(to);
+			}
+		}
+	}
+	if visited<nodes.len() { None } else { Some(path.into_iter().max().unwrap_or(0)) }
+}
@@ -0,0 +1,375 @@
+/*!
+
+A traffic replaying the messages recorded in a trace file, to drive the network with real applications.
+
+*/
+
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::rc::Rc;
+use rand::prelude::StdRng;
+use quantifiable_derive::Quantifiable;//the derive macro
+use crate::{match_object, error, source_location, AsMessage, Message, MessageTag, Time};
+use crate::config_parser::ConfigurationValue;
+use crate::error::{Error,SourceLocation};
+use crate::topology::Topology;
+use crate::traffic::{TaskTrafficState, Traffic, TrafficBuilderArgument, TrafficError};
+
+///A message recorded in a trace.
+#[derive(Debug,Quantifiable)]
+struct TraceEvent
+{
+	source: usize,
+	destination: usize,
+	size: usize,
+	///The earliest cycle in which the message can be generated.
+	cycle: Time,
+	///The events depending on this one.
+	dependents: Vec<usize>,
+	///The number of events this one depends on that have not been consumed yet.
+	pending_dependencies: usize,
+	///The cycle in which the message was consumed, if already.
+	consumed_cycle: Option<Time>,
+}
+
+/**
+A traffic that replays the messages of a trace file. Each line of the file is an event describing a message by its `source` and `destination` tasks, its `size` in phits,
+the earliest `cycle` in which it can be generated, and optionally the `dependencies`, a list of the events that must be consumed before generating the message.
+Events are numbered from 0 in the order of the file. Each task generates its messages in the order of the file; a message waiting for its cycle or its dependencies blocks the following messages of the same task.
+The traffic finishes when all the messages have been consumed.
+
+The file can be given in two formats, selected with `format` or otherwise by the extension of the file.
+* In `csv` each line has the fields `source,destination,size,cycle,dependencies`, where the dependencies are separated by spaces and may be omitted.
+  Empty lines and lines starting with `#` are ignored. The first remaining line is considered a header if it is not numeric.
+* In `json` each line is a JSON object with the keys `source`, `destination`, `size`, `cycle`, and optionally `dependencies` as an array of numbers.
+
+For example, a trace with a message from 0 to 1 answered from 1 to 0 after its reception.
+```ignore
+source,destination,size,cycle,dependencies
+0,1,16,0,
+1,0,16,0,0
+```
+
+The messages are tagged with the index of their event, see [MessageTag](crate::MessageTag).
+A trace with the messages generated by a simulation can be obtained with `injection_record_file`, see [InjectionRecord].
+
+The statistics of the traffic include the cycle in which the last message was consumed, allowing to measure the completion time of the application.
+```ignore
+TraceReplay{
+	tasks: 64,
+	filename: "/path/to/trace.csv",
+	//format: "csv",//optional, either csv or json
+}
+```
+**/
+#[derive(Debug,Quantifiable)]
+pub struct TraceReplay
+{
+	///Number of tasks applying this traffic.
+	tasks: usize,
+	///All the events in the trace.
+	events: Vec<TraceEvent>,
+	///The indices of the events sent by each task, in program order.
+	task_events: Vec<Vec<usize>>,
+	///For each task the position in `task_events` of the next event to generate.
+	next_event: Vec<usize>,
+	///The number of events consumed.
+	consumed: usize,
+}
+
+impl Traffic for TraceReplay
+{
+	fn generate_message(&mut self, origin:usize, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> Result<Rc<Message>,TrafficError>
+	{
+		if origin>=self.tasks
+		{
+			return Err(TrafficError::OriginOutsideTraffic);
+		}
+		let index = self.ready_event(origin,cycle).expect("There is no message ready to generate");
+		self.next_event[origin]+=1;
+		let event = &self.events[index];
+		let message=Rc::new(Message{
+			origin,
+			destination: event.destination,
+			size: event.size,
+			creation_cycle: cycle,
+			payload: (index as u64).to_le_bytes().into(),
+			id_traffic: None,
+			routing_hints: None,
+			tag: Some(Rc::new(MessageTag{event:Some(index),..MessageTag::default()})),
+			class: 0,
+		});
+		Ok(message)
+	}
+	fn probability_per_cycle(&self, task:usize) -> f32
+	{
+		if self.next_event[task]<self.task_events[task].len() { 1.0 } else { 0.0 }
+	}
+	fn should_generate(&mut self, task:usize, cycle:Time, _rng: &mut StdRng) -> bool
+	{
+		self.ready_event(task,cycle).is_some()
+	}
+	fn consume(&mut self, _task:usize, message: &dyn AsMessage, cycle:Time, _topology:&dyn Topology, _rng: &mut StdRng) -> bool
+	{
+		let index = u64::from_le_bytes(message.payload()[0..8].try_into().expect("bad payload")) as usize;
+		if index>=self.events.len() || self.events[index].consumed_cycle.is_some()
+		{
+			return false;
+		}
+		self.events[index].consumed_cycle = Some(cycle);
+		self.consumed+=1;
+		for dependent_position in 0..self.events[index].dependents.len()
+		{
+			let dependent = self.events[index].dependents[dependent_position];
+			self.events[dependent].pending_dependencies-=1;
+		}
+		true
+	}
+	fn is_finished(&self) -> bool
+	{
+		self.consumed==self.events.len()
+	}
+	fn task_state(&self, task:usize, cycle:Time) -> Option<TaskTrafficState>
+	{
+		match self.task_events[task].get(self.next_event[task])
+		{
+			None => Some(TaskTrafficState::FinishedGenerating),
+			Some(&index) =>
+			{
+				let event = &self.events[index];
+				if event.pending_dependencies>0
+				{
+					Some(TaskTrafficState::WaitingData)
+				}
+				else if event.cycle>cycle
+				{
+					Some(TaskTrafficState::WaitingCycle{cycle:event.cycle})
+				}
+				else
+				{
+					Some(TaskTrafficState::Generating)
+				}
+			}
+		}
+	}
+	fn number_tasks(&self) -> usize
+	{
+		self.tasks
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let completion = self.events.iter().filter_map(|event|event.consumed_cycle).max().unwrap_or(0);
+		let content = vec![
+			(String::from("events"),ConfigurationValue::Number(self.events.len() as f64)),
+			(String::from("consumed_events"),ConfigurationValue::Number(self.consumed as f64)),
+			(String::from("completion_cycle"),ConfigurationValue::Number(completion as f64)),
+		];
+		Some(ConfigurationValue::Object(String::from("TraceReplayStatistics"),content))
+	}
+}
+
+impl TraceReplay
+{
+	pub fn new(arg:TrafficBuilderArgument) -> Result<TraceReplay,Error>
+	{
+		let mut tasks=None;
+		let mut filename=None;
+		let mut format=None;
+		match_object!(arg.cv,"TraceReplay",value,
+			"tasks" | "servers" => tasks=Some(value.as_usize()?),
+			"filename" => filename=Some(value.as_str()?.to_string()),
+			"format" => format=Some(value.as_str()?.to_string()),
+		);
+		let tasks=tasks.ok_or_else(||arg.cv.ill("There were no tasks"))?;
+		let filename=filename.ok_or_else(||arg.cv.ill("There were no filename"))?;
+		let format = format.unwrap_or_else(||{
+			if filename.ends_with(".json") || filename.ends_with(".jsonl") { "json".to_string() } else { "csv".to_string() }
+		});
+		let file=File::open(&filename).map_err(|error|arg.cv.ill(&format!("Could not open the trace file {}: {}",filename,error)))?;
+		let mut events = vec![];
+		let mut dependencies = vec![];
+		let mut first_line = true;
+		for (line_index,line) in BufReader::new(file).lines().enumerate()
+		{
+			let line = line.expect("Could not read the trace file");
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#')
+			{
+				continue;
+			}
+			let is_first_line = first_line;
+			first_line = false;
+			let parsed = match format.as_ref()
+			{
+				"csv" => parse_csv_event(line),
+				"json" => parse_json_event(line),
+				_ => panic!("Unknown trace format {}",format),
+			};
+			let (source,destination,size,cycle,event_dependencies) = match parsed
+			{
+				Some(x) => x,
+				//Allow a header in the first line, after any comments.
+				None if is_first_line && format=="csv" => continue,
+				None => panic!("Could not parse the line {} of the trace file {}: {}",line_index+1,filename,line),
+			};
+			assert_ne!(source,destination,"The event in the line {} of the trace file {} is a self message",line_index+1,filename);
+			assert!(source<tasks && destination<tasks,"The event in the line {} of the trace file {} goes from {} to {}, but there are only {} tasks",line_index+1,filename,source,destination,tasks);
+			events.push(TraceEvent{
+				source,
+				destination,
+				size,
+				cycle,
+				dependents: vec![],
+				pending_dependencies: event_dependencies.len(),
+				consumed_cycle: None,
+			});
+			dependencies.push(event_dependencies);
+		}
+		for (index,event_dependencies) in dependencies.into_iter().enumerate()
+		{
+			for dependency in event_dependencies
+			{
+				assert!(dependency<events.len(),"The event {} depends on the event {}, but there are only {} events",index,dependency,events.len());
+				events[dependency].dependents.push(index);
+			}
+		}
+		let mut task_events = vec![vec![];tasks];
+		for (index,event) in events.iter().enumerate()
+		{
+			task_events[event.source].push(index);
+		}
+		Ok(TraceReplay{
+			tasks,
+			events,
+			task_events,
+			next_event: vec![0;tasks],
+			consumed: 0,
+		})
+	}
+	///The event to be generated next by the `task` if it can be generated in the `cycle`.
+	fn ready_event(&self, task:usize, cycle:Time) -> Option<usize>
+	{
+		let &index = self.task_events[task].get(self.next_event[task])?;
+		let event = &self.events[index];
+		if event.pending_dependencies==0 && event.cycle<=cycle { Some(index) } else { None }
+	}
+}
+
+///The fields of an event: source, destination, size, cycle, and dependencies.
+type EventFields = (usize,usize,usize,Time,Vec<usize>);
+
+///Parses a line `source,destination,size,cycle,dependencies` of a CSV trace.
+fn parse_csv_event(line:&str) -> Option<EventFields>
+{
+	let fields : Vec<&str> = line.split(',').map(|field|field.trim()).collect();
+	if fields.len()<4 || fields.len()>5
+	{
+		return None;
+	}
+	let dependencies = match fields.get(4)
+	{
+		Some(list) => list.split_whitespace().map(|x|x.parse().ok()).collect::<Option<Vec<usize>>>()?,
+		None => vec![],
+	};
+	Some((fields[0].parse().ok()?,fields[1].parse().ok()?,fields[2].parse().ok()?,fields[3].parse().ok()?,dependencies))
+}
+
+///Parses a line `{"source":s, "destination":d, "size":z, "cycle":c, "dependencies":[...]}` of a JSON trace.
+///Only flat objects with numbers and arrays of numbers are supported.
+fn parse_json_event(line:&str) -> Option<EventFields>
+{
+	let content = line.strip_prefix('{')?.strip_suffix('}')?;
+	let mut source=None;
+	let mut destination=None;
+	let mut size=None;
+	let mut cycle=None;
+	let mut dependencies=vec![];
+	let mut rest = content.trim();
+	while !rest.is_empty()
+	{
+		let key_start = rest.strip_prefix('"')?;
+		let key_end = key_start.find('"')?;
+		let key = &key_start[..key_end];
+		let after_key = key_start[key_end+1..].trim_start().strip_prefix(':')?.trim_start();
+		let value_end = if after_key.starts_with('[') { after_key.find(']')?+1 } else { after_key.find(',').unwrap_or(after_key.len()) };
+		let value = after_key[..value_end].trim();
+		match key
+		{
+			"source" => source=Some(value.parse().ok()?),
+			"destination" => destination=Some(value.parse().ok()?),
+			"size" => size=Some(value.parse().ok()?),
+			"cycle" => cycle=Some(value.parse().ok()?),
+			"dependencies" => dependencies = value.strip_prefix('[')?.strip_suffix(']')?.split(',').map(|x|x.trim()).filter(|x|!x.is_empty()).map(|x|x.parse().ok()).collect::<Option<Vec<usize>>>()?,
+			_ => return None,
+		}
+		rest = after_key[value_end..].trim_start();
+		if let Some(next) = rest.strip_prefix(',')
+		{
+			rest = next.trim_start();
+		}
+	}
+	Some((source?,destination?,size?,cycle?,dependencies))
+}
+
+/**
+Records the messages generated along a simulation, including the warmup, to replay the same injection sequence later with [TraceReplay].
+It is enabled by setting `injection_record_file: "injections.csv"` in the configuration.
+The file is a CSV trace with the header `source,destination,size,cycle` followed by a line per message, in the order of generation.
+Replaying it with `TraceReplay{tasks, filename: "injections.csv"}` under other router or routing configurations generates the same messages in the same cycles,
+except when the servers cannot store them, in which case they are generated as soon as possible keeping their order.
+This isolates the effects of the network design from the randomness of the traffic.
+
+The results include `injection_record: InjectionRecordFile{filename, messages}`.
+A relative filename is created inside the directory of the run, see [Simulation::new_in_directory](crate::Simulation::new_in_directory).
+**/
+#[derive(Debug)]
+pub struct InjectionRecord
+{
+	///The path of the file.
+	pub filename: String,
+	///Where the records are written.
+	writer: std::io::BufWriter<File>,
+	///Number of messages written.
+	pub messages: usize,
+}
+
+impl InjectionRecord
+{
+	///Creates the file `filename` inside the `directory`.
+	pub fn new(filename:&str, directory:&Path) -> Result<InjectionRecord,Error>
+	{
+		use std::io::Write;
+		let path = directory.join(filename);
+		let file = File::create(&path).map_err(|error|error!(could_not_generate_file,path.clone(),error))?;
+		let mut writer = std::io::BufWriter::new(file);
+		writeln!(writer,"source,destination,size,cycle").map_err(|error|error!(could_not_generate_file,path,error))?;
+		Ok(InjectionRecord{
+			filename: filename.to_string(),
+			writer,
+			messages: 0,
+		})
+	}
+	///Append the record of a `message` generated in `cycle`.
+	pub fn write_message(&mut self, message:&Message, cycle:Time)
+	{
+		use std::io::Write;
+		writeln!(self.writer,"{},{},{},{}",message.origin,message.destination,message.size,cycle).expect("Could not write the injection record file");
+		self.messages+=1;
+	}
+	///Ensure all the records are written into the file.
+	pub fn flush(&mut self)
+	{
+		use std::io::Write;
+		self.writer.flush().expect("Could not write the injection record file");
+	}
+	///The entry to include into the results.
+	pub fn result(&self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("InjectionRecordFile"),vec![
+			(String::from("filename"),ConfigurationValue::Literal(self.filename.clone())),
+			(String::from("messages"),ConfigurationValue::Number(self.messages as f64)),
+		])
+	}
+}
@@ -0,0 +1,59 @@
+/*!
+    Tests for the analytic estimation used instead of simulating.
+*/
+
+mod common;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Estimates a complete graph of 4 routers with a server each, in which each server sends to the next one.
+/// Each message uses a single link between routers, which is not shared with other messages.
+fn estimate_shift(load:f64) -> Vec<(String,ConfigurationValue)>
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 1,
+    };
+    simulate_basic(BasicSimulationBuilder{
+        warmup: 200,
+        measured: 1000,
+        topology: create_hamming_topology(hamming_builder),
+        pattern: create_shift_pattern(ShiftPatternBuilder{
+            sides: vec![ConfigurationValue::Number(4.0)],
+            shift: vec![ConfigurationValue::Number(1.0)],
+        }),
+        servers: 4,
+        load,
+        virtual_channels: 1,
+        buffer_size: 64,
+        output_buffer_size: 32,
+        routing: create_shortest_routing(),
+        extra: vec![("analytic".to_string(), ConfigurationValue::Object("AnalyticEstimate".to_string(), vec![
+            ("samples".to_string(), ConfigurationValue::Number(10.0)),
+        ]))],
+    })
+}
+
+/// Each link carries the load of one server. With a utilization of 0.5 the M/D/1 waiting time of a packet of 16 phits is 8 cycles,
+/// which is added both in the link between the routers and in the link to the destination server.
+#[test]
+fn analytic_estimate_shift()
+{
+    let fields = estimate_shift(0.5);
+    assert_eq!(field(&fields, "analytic"), &ConfigurationValue::True, "The results must be labelled as analytic");
+    assert!((field(&fields, "accepted_load").as_f64().unwrap() - 0.5).abs() < 1e-6);
+    assert!((field(&fields, "maximum_link_utilization").as_f64().unwrap() - 0.5).abs() < 1e-6);
+    assert!((field(&fields, "average_packet_hops").as_f64().unwrap() - 1.0).abs() < 1e-6);
+    // 16 cycles of serialization, 3 links of delay 1, and two waits of 8 cycles.
+    let delay = field(&fields, "average_packet_network_delay").as_f64().unwrap();
+    assert!((delay - 35.0).abs() < 1e-6, "Unexpected delay {}", delay);
+}
+
+/// When the links are full there is no finite delay.
+#[test]
+fn analytic_estimate_saturated()
+{
+    let fields = estimate_shift(1.0);
+    assert!((field(&fields, "accepted_load").as_f64().unwrap() - 1.0).abs() < 1e-6);
+    assert_eq!(field(&fields, "average_packet_network_delay"), &ConfigurationValue::None);
+}
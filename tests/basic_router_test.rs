@@ -217,3 +217,673 @@ fn basic_two_servers_two_routers()
 
 
 
+
+/// With `link_class_virtual_channels` the links between routers of a Hamming graph have only 2 of the 4 virtual channels of the router.
+/// We check that the phits only use the virtual channels of the links and that all traffic is delivered.
+#[test]
+fn basic_link_class_virtual_channels()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let message_size = 16;
+    let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 8,
+        load: 0.3,
+        message_size,
+    });
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    });
+    let mut router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 4,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    });
+    if let ConfigurationValue::Object(_, ref mut fields) = router
+    {
+        // Link class 0 joins routers and link class 1 joins servers with routers.
+        fields.push(("link_class_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(2.0), ConfigurationValue::Number(1.0)])));
+    }
+    let simulation_builder = SimulationBuilder{
+        random_seed: 3,
+        warmup: 200,
+        measured: 1000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.results();
+    assert!(results.accepted_load > 0.25, "accepted load {} too low", results.accepted_load);
+    assert!(results.virtual_channel_usage[0] > 0.0 && results.virtual_channel_usage[1] > 0.0, "unused virtual channels {:?}", results.virtual_channel_usage);
+    assert!(results.virtual_channel_usage.iter().skip(2).all(|&usage| usage == 0.0), "virtual channels beyond the link ones were used {:?}", results.virtual_channel_usage);
+}
+
+/// Under a saturating uniform load on a Hamming graph the phits have to wait at the head of the input buffers.
+/// Without output buffers the lack of space is seen as lack of credits, so no stall is attributed to full output buffers.
+#[test]
+fn basic_stall_causes()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let message_size = 16;
+    let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 8,
+        load: 1.0,
+        message_size,
+    });
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    });
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 0,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    });
+    let simulation_builder = SimulationBuilder{
+        random_seed: 5,
+        warmup: 200,
+        measured: 1000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let stalls = simulation.results().stall_cycles.expect("the Basic router should report its stalls");
+    assert!(stalls.total() > 0, "no stalls under saturation {:?}", stalls);
+    assert!(stalls.no_credits > 0, "no stall for lack of credits {:?}", stalls);
+    assert_eq!(stalls.output_buffer_full, 0, "stalls for full output buffers without output buffers {:?}", stalls);
+    let results = simulation.get_simulation_results();
+    assert!(format!("{}", results).contains("stall_cycles"), "the stalls are not in the results");
+}
+
+/// Test local traffic inside a router with servers of two rails. There are two servers and each server sends one message of two packets of 16 phits to each other.
+/// Each packet goes through a different rail, so the message takes half the cycles than with a single rail.
+#[test]
+fn basic_multi_rail_local_traffic()
+{
+    let rails = 2;
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(1.0)],
+        servers_per_router: 2*rails,
+    };
+    let topology = ConfigurationValue::Object("MultiRail".to_string(), vec![
+        ("topology".to_string(), create_hamming_topology(hamming_builder)),
+        ("rails".to_string(), ConfigurationValue::Number(rails as f64)),
+    ]);
+
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![ConfigurationValue::Number(2.0), ConfigurationValue::Number(1.0)],
+        shift: vec![ConfigurationValue::Number(1.0), ConfigurationValue::Number(0.0)],
+    };
+    let message_size = 32;
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers: 2,
+        messages_per_server: 1,
+        message_size,
+    };
+
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+
+    //Each rail sends a packet of 16 phits. The simulation ends when the burst is consumed.
+    let cycles = message_size / rails + 3;
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: message_size + 2,
+        topology,
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+
+    let accepted_load = message_size as f64 / cycles as f64;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => assert_eq!(value.as_f64().expect("Cycle data"), cycles as f64, "Cycle"),
+        "accepted_load" => assert_eq!(value.as_f64().expect("Accepted load data"), accepted_load, "Accepted load"),
+        _ => (),
+    );
+}
+
+/// Test a burst over two planes of a complete graph of 4 routers, in which each server sends a message of two packets to the next server.
+/// Whatever the `rail_selection`, each packet goes through a different plane, so the burst ends sooner than with a single plane.
+#[test]
+fn basic_multi_plane_burst()
+{
+    let message_size = 32;
+    let run = |planes:usize, rail_selection:&str| {
+        let plane = create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        });
+        let topology = ConfigurationValue::Object("MultiPlane".to_string(), vec![
+            ("plane".to_string(), plane),
+            ("planes".to_string(), ConfigurationValue::Number(planes as f64)),
+        ]);
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 1,
+            vcp,
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: 16,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        let mut simulation_cv = create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 0,
+            measured: 1000,
+            topology,
+            traffic: create_burst_traffic(BurstTrafficBuilder{
+                pattern: create_shift_pattern(ShiftPatternBuilder{
+                    sides: vec![ConfigurationValue::Number(4.0)],
+                    shift: vec![ConfigurationValue::Number(1.0)],
+                }),
+                servers: 4,
+                messages_per_server: 1,
+                message_size,
+            }),
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        });
+        if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+        {
+            fields.push(("rail_selection".to_string(), ConfigurationValue::Object(rail_selection.to_string(), vec![])));
+        }
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&simulation_cv, &plugs);
+        simulation.run();
+        simulation.shared.cycle
+    };
+    let single_plane = run(1, "RoundRobin");
+    assert!(single_plane < 1000, "the burst was not consumed");
+    let two_planes = run(2, "RoundRobin");
+    assert!(two_planes < single_plane, "two planes took {} cycles and a single plane {}", two_planes, single_plane);
+    assert_eq!(run(2, "Random"), two_planes);
+    assert_eq!(run(2, "Adaptive"), two_planes);
+}
+
+/// Under a low uniform load the store-and-forward switching delays each packet by its serialization in each router, while wormhole and virtual cut-through forward the phits as they arrive.
+#[test]
+fn basic_switching_modes()
+{
+    let message_size = 16;
+    let run = |switching:&str| {
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        });
+        let router = match create_basic_router(BasicRouterBuilder{
+            virtual_channels: 1,
+            vcp,
+            buffer_size: 32,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 0,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        })
+        {
+            // The switching replaces the flit size.
+            ConfigurationValue::Object(name, fields) => {
+                let mut fields:Vec<_> = fields.into_iter().filter(|(key,_)|key!="flit_size").collect();
+                fields.push(("switching".to_string(), ConfigurationValue::Object(switching.to_string(), vec![])));
+                ConfigurationValue::Object(name, fields)
+            },
+            _ => unreachable!(),
+        };
+        let simulation_builder = SimulationBuilder{
+            random_seed: 3,
+            warmup: 200,
+            measured: 2000,
+            topology: create_hamming_topology(HammingBuilder{
+                sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+                servers_per_router: 2,
+            }),
+            traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+                pattern: create_uniform_pattern(),
+                servers: 32,
+                load: 0.1,
+                message_size,
+            }),
+            router,
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        };
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+        simulation.run();
+        let results = simulation.results();
+        (results.average_packet_network_delay, results.stall_cycles.expect("the Basic router should report its stalls"))
+    };
+    let (wormhole_delay, wormhole_stalls) = run("Wormhole");
+    let (cut_through_delay, cut_through_stalls) = run("VirtualCutThrough");
+    let (store_delay, store_stalls) = run("StoreAndForward");
+    assert_eq!(wormhole_stalls.incomplete_packet, 0);
+    assert_eq!(cut_through_stalls.incomplete_packet, 0);
+    assert!(store_stalls.incomplete_packet > 0, "store-and-forward never waited for a packet {:?}", store_stalls);
+    assert!((wormhole_delay - cut_through_delay).abs() < 0.1*cut_through_delay, "wormhole delay {} and virtual cut-through delay {} should be similar at low load", wormhole_delay, cut_through_delay);
+    assert!(store_delay > cut_through_delay + message_size as f64, "store-and-forward delay {} is not greater than the virtual cut-through delay {} plus the packet serialization", store_delay, cut_through_delay);
+}
+
+/// The occupancy of the buffers is sampled periodically, merging pairs of samples to keep the series bounded.
+#[test]
+fn basic_buffer_occupancy_series()
+{
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    });
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 0,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    });
+    let simulation_cv = match create_simulation(SimulationBuilder{
+        random_seed: 5,
+        warmup: 200,
+        measured: 2000,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+            servers_per_router: 2,
+        }),
+        traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 32,
+            load: 0.5,
+            message_size: 16,
+        }),
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    })
+    {
+        ConfigurationValue::Object(name, mut fields) => {
+            fields.push(("statistics_buffer_occupancy_step".to_string(), ConfigurationValue::Number(10.0)));
+            fields.push(("statistics_buffer_occupancy_maximum_samples".to_string(), ConfigurationValue::Number(64.0)));
+            ConfigurationValue::Object(name, fields)
+        },
+        _ => unreachable!(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut checked = false;
+    match_object_panic!( &results, "Result", value,
+        "buffer_occupancy" => match_object_panic!( value, "BufferOccupancy", field,
+            "begin_cycle" => assert_eq!(field.as_f64().expect("begin_cycle data"), 200.0),
+            //The 200 samples of 10 cycles have been merged twice.
+            "step" => assert_eq!(field.as_f64().expect("step data"), 40.0),
+            "input" => {
+                let routers = field.as_array().expect("input data");
+                assert_eq!(routers.len(), 16);
+                let mut total = 0.0;
+                for router in routers
+                {
+                    let ports = router.as_array().expect("router data");
+                    assert_eq!(ports.len(), 8);
+                    for port in ports
+                    {
+                        let vcs = port.as_array().expect("port data");
+                        assert_eq!(vcs.len(), 2);
+                        for series in vcs
+                        {
+                            let series = series.as_array().expect("series data");
+                            assert_eq!(series.len(), 50);
+                            total += series.iter().map(|v|v.as_f64().expect("occupancy data")).sum::<f64>();
+                        }
+                    }
+                }
+                assert!(total > 0.0, "the buffers should hold some phits under load");
+                checked = true;
+            },
+            "output" => assert!(field.as_array().expect("output data").iter().all(|router|router.as_array().expect("router data").is_empty())),
+        ),
+        _ => (),
+    );
+    assert!(checked, "the result should include the buffer occupancy");
+}
+
+/// The arbitration by age keeps delivering the offered load, with a message delay similar to the random arbitration.
+#[test]
+fn basic_oldest_first_arbitration()
+{
+    let message_size = 16;
+    let run = |oldest_first:bool| {
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        });
+        let router = match create_basic_router(BasicRouterBuilder{
+            virtual_channels: 1,
+            vcp,
+            buffer_size: 32,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 16,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        })
+        {
+            ConfigurationValue::Object(name, mut fields) => {
+                fields.push(("oldest_first".to_string(), if oldest_first { ConfigurationValue::True } else { ConfigurationValue::False }));
+                ConfigurationValue::Object(name, fields)
+            },
+            _ => unreachable!(),
+        };
+        let simulation_builder = SimulationBuilder{
+            random_seed: 5,
+            warmup: 500,
+            measured: 2000,
+            topology: create_hamming_topology(HammingBuilder{
+                sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+                servers_per_router: 2,
+            }),
+            traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+                pattern: create_uniform_pattern(),
+                servers: 32,
+                load: 0.4,
+                message_size,
+            }),
+            router,
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        };
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+        simulation.run();
+        let results = simulation.results();
+        (results.accepted_load, results.average_message_delay)
+    };
+    let (random_load, random_delay) = run(false);
+    let (oldest_load, oldest_delay) = run(true);
+    assert!(oldest_load > 0.35, "the oldest-first arbitration only accepted {}", oldest_load);
+    assert!((oldest_load - random_load).abs() < 0.05, "accepted loads {} and {} should be similar", oldest_load, random_load);
+    assert!((oldest_delay - random_delay).abs() < 0.2*random_delay, "message delays {} and {} should be similar", oldest_delay, random_delay);
+}
+
+/// A weighted table at the output ports gives to each virtual channel a share of the saturated links proportional to its weight.
+/// In two routers with two servers each, every server sends to the server in the same position at the other router, using the virtual channel of the parity of the destination.
+/// Thus each of the links between the routers is requested at twice its capacity, evenly by both virtual channels.
+#[test]
+fn basic_weighted_virtual_channel_arbitration()
+{
+    let message_size = 16;
+    let run = |output_scheduling:ConfigurationValue| {
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("VOQ".to_string(), vec![
+                    ("num_classes".to_string(), ConfigurationValue::Number(2.0)),
+                ]),
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        });
+        let router = match create_basic_router(BasicRouterBuilder{
+            virtual_channels: 2,
+            vcp,
+            buffer_size: 32,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 16,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        })
+        {
+            ConfigurationValue::Object(name, mut fields) => {
+                fields.push(("output_scheduling".to_string(), output_scheduling));
+                ConfigurationValue::Object(name, fields)
+            },
+            _ => unreachable!(),
+        };
+        let simulation_builder = SimulationBuilder{
+            random_seed: 6,
+            warmup: 1000,
+            measured: 3000,
+            topology: create_hamming_topology(HammingBuilder{
+                sides: vec![ConfigurationValue::Number(2.0)],
+                servers_per_router: 2,
+            }),
+            traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+                pattern: create_shift_pattern(ShiftPatternBuilder{
+                    sides: vec![ConfigurationValue::Number(2.0),ConfigurationValue::Number(2.0)],
+                    shift: vec![ConfigurationValue::Number(0.0),ConfigurationValue::Number(1.0)],
+                }),
+                servers: 4,
+                load: 1.0,
+                message_size,
+            }),
+            router,
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        };
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+        simulation.run();
+        let results = simulation.results();
+        results.virtual_channel_usage[0] / results.virtual_channel_usage[1]
+    };
+    let round_robin_ratio = run(ConfigurationValue::Object("RoundRobin".to_string(), vec![]));
+    let weighted_ratio = run(ConfigurationValue::Object("WeightedTable".to_string(), vec![
+        ("weights".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(64.0), ConfigurationValue::Number(16.0)])),
+    ]));
+    assert!((round_robin_ratio - 1.0).abs() < 0.1, "the round-robin should share the links evenly, got a ratio {}", round_robin_ratio);
+    assert!((weighted_ratio - 4.0).abs() < 0.5, "the weights 64 and 16 should share the links 4 to 1, got a ratio {}", weighted_ratio);
+}
+
+/// The `link_class_mechanisms` may be given as a map of `[link_class,name]` pairs, applying each mechanism only to the router links of its class.
+#[test]
+fn basic_link_class_mechanisms_map()
+{
+    let plugs = Plugs::default();
+    let mechanism_pair = |link_class:usize, name:&str| ConfigurationValue::Array(vec![ConfigurationValue::Number(link_class as f64), ConfigurationValue::Literal(name.to_string())]);
+    let build = |mechanisms:ConfigurationValue| -> Result<Simulation,error::Error> {
+        let message_size = 16;
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        });
+        let mut router = create_basic_router(BasicRouterBuilder{
+            virtual_channels: 1,
+            vcp,
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        });
+        if let ConfigurationValue::Object(_, ref mut fields) = router
+        {
+            fields.push(("link_class_mechanisms".to_string(), mechanisms));
+        }
+        let simulation_builder = SimulationBuilder{
+            random_seed: 3,
+            warmup: 200,
+            measured: 1000,
+            topology: create_hamming_topology(HammingBuilder{
+                sides: vec![ConfigurationValue::Number(4.0)],
+                servers_per_router: 2,
+            }),
+            traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+                pattern: create_uniform_pattern(),
+                servers: 8,
+                load: 0.3,
+                message_size,
+            }),
+            router,
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        };
+        Simulation::try_new(&create_simulation(simulation_builder), &plugs)
+    };
+    // Link class 0 joins routers and link class 1 joins servers with routers, which have their own mechanisms.
+    assert!(build(ConfigurationValue::Array(vec![mechanism_pair(0,"Nonexistent")])).is_err(), "The mechanism of the router links was not used");
+    assert!(build(ConfigurationValue::Array(vec![mechanism_pair(1,"Nonexistent")])).is_ok(), "The mechanism was used outside its link class");
+    let mut from_map = build(ConfigurationValue::Array(vec![mechanism_pair(0,"TransmissionFromOblivious")])).unwrap();
+    from_map.run();
+    let mut from_array = build(ConfigurationValue::Array(vec![ConfigurationValue::Literal("TransmissionFromOblivious".to_string())])).unwrap();
+    from_array.run();
+    let (map_results, array_results) = (from_map.results(), from_array.results());
+    assert!(map_results.accepted_load > 0.25, "accepted load {} too low", map_results.accepted_load);
+    assert_eq!(map_results.accepted_load, array_results.accepted_load);
+    assert_eq!(map_results.average_message_delay, array_results.average_message_delay);
+}
+
+/// A `Preset` expands to the configuration of its router, and the other fields of the preset override those of the expansion.
+#[test]
+fn router_preset_expansion()
+{
+    let parse = |text:&str| match config_parser::parse(text).expect("could not parse the preset")
+    {
+        config_parser::Token::Value(value) => value,
+        _ => panic!("The preset should be a value"),
+    };
+    let field = |cv:&ConfigurationValue, name:&str| match cv
+    {
+        ConfigurationValue::Object(_,fields) => fields.iter().find(|(key,_)|key==name).map(|(_,value)|value.clone()),
+        _ => panic!("The expansion should be an object"),
+    };
+    let number = |value:Option<ConfigurationValue>| value.expect("missing field").as_f64().expect("not a number");
+    let maximum_packet_size = 16;
+
+    let basic = router::router_preset(&parse("Preset{name:\"IQ-VCT-2VC\"}"),maximum_packet_size);
+    assert!(matches!(&basic, ConfigurationValue::Object(name,_) if name=="Basic"), "unexpected expansion {}", basic);
+    assert_eq!(number(field(&basic,"virtual_channels")), 2.0);
+    assert_eq!(number(field(&basic,"buffer_size")), 64.0);
+    assert_eq!(number(field(&basic,"output_buffer_size")), 32.0);
+    assert_eq!(field(&basic,"switching"), Some(ConfigurationValue::Object("VirtualCutThrough".to_string(),vec![])));
+    assert_eq!(field(&basic,"output_prioritize_lowest_label"), Some(ConfigurationValue::True));
+    assert_eq!(field(&basic,"name"), None);
+
+    let input_output = router::router_preset(&parse("Preset{name:\"IOQ-WH-4VC\", buffer_size:100, crossbar_delay:2, legend_name:\"wormhole\"}"),maximum_packet_size);
+    assert!(matches!(&input_output, ConfigurationValue::Object(name,_) if name=="InputOutput"), "unexpected expansion {}", input_output);
+    assert_eq!(number(field(&input_output,"virtual_channels")), 4.0);
+    assert_eq!(number(field(&input_output,"buffer_size")), 100.0);
+    assert_eq!(number(field(&input_output,"crossbar_delay")), 2.0);
+    assert_eq!(field(&input_output,"switching"), Some(ConfigurationValue::Object("Wormhole".to_string(),vec![])));
+    assert_eq!(field(&input_output,"allocator"), Some(ConfigurationValue::Object("Random".to_string(),vec![])));
+    assert_eq!(field(&input_output,"legend_name"), None);
+
+    // A new field is appended, and a `flit_size` replaces the switching of the expansion.
+    let flits = router::router_preset(&parse("Preset{name:\"IQ-SAF-1VC\", flit_size:16, oldest_first:true}"),maximum_packet_size);
+    assert_eq!(number(field(&flits,"flit_size")), 16.0);
+    assert_eq!(field(&flits,"switching"), None);
+    assert_eq!(field(&flits,"oldest_first"), Some(ConfigurationValue::True));
+
+    for bad in ["IQ-VCT", "IQ-VCT-0VC", "OQ-VCT-2VC", "IQ-CS-2VC"].iter()
+    {
+        let cv = parse(&format!("Preset{{name:\"{}\"}}",bad));
+        assert!(std::panic::catch_unwind(||router::router_preset(&cv,maximum_packet_size)).is_err(), "The preset {} should be rejected", bad);
+    }
+}
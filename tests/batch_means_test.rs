@@ -0,0 +1,59 @@
+/*!
+    Tests for the confidence intervals by batch means.
+*/
+
+mod common;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Simulates a 4x4 torus under uniform traffic at 0.4 load with the given `statistics_batch_means`.
+fn simulate(batch_means:ConfigurationValue) -> Vec<(String,ConfigurationValue)>
+{
+    let topology = ConfigurationValue::Object("Torus".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    simulate_basic(BasicSimulationBuilder{
+        warmup: 1000,
+        measured: 10000,
+        topology,
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        load: 0.4,
+        virtual_channels: 2,
+        buffer_size: 32,
+        output_buffer_size: 16,
+        routing: create_dor_routing(vec![0,1]),
+        extra: vec![("statistics_batch_means".to_string(), batch_means)],
+    })
+}
+
+/// The intervals contain the means of the whole measurement and shrink with a lower confidence.
+#[test]
+fn batch_means_intervals()
+{
+    let batch_means = |confidence:f64| ConfigurationValue::Object("BatchMeans".to_string(), vec![
+        ("batches".to_string(), ConfigurationValue::Number(10.0)),
+        ("confidence".to_string(), ConfigurationValue::Number(confidence)),
+    ]);
+    let fields = simulate(batch_means(0.99));
+    let batches = object(field(&fields, "batch_means"), "BatchMeans");
+    assert_eq!(field(batches, "batches").as_f64().unwrap(), 10.0);
+    assert_eq!(field(batches, "batch_cycles").as_f64().unwrap(), 1000.0);
+    let mut widths = vec![];
+    for (measure,interval) in [("accepted_load","accepted_load"),("average_message_delay","average_message_delay")]
+    {
+        let interval = object(field(batches, interval), "ConfidenceInterval");
+        let total = field(&fields, measure).as_f64().unwrap();
+        let mean = field(interval, "mean").as_f64().unwrap();
+        assert!((mean-total).abs() < 0.01*total, "The mean {} of {} should be that of the whole measurement {}", mean, measure, total);
+        let lower = field(interval, "lower").as_f64().unwrap();
+        let upper = field(interval, "upper").as_f64().unwrap();
+        assert!(lower < total && total < upper, "The interval [{},{}] should contain the {} {}", lower, upper, measure, total);
+        widths.push(field(interval, "half_width").as_f64().unwrap());
+    }
+    let fields = simulate(batch_means(0.8));
+    let batches = object(field(&fields, "batch_means"), "BatchMeans");
+    let narrower = field(object(field(batches, "accepted_load"), "ConfidenceInterval"), "half_width").as_f64().unwrap();
+    assert!(narrower < widths[0], "A lower confidence should give a narrower interval, {} against {}", narrower, widths[0]);
+}
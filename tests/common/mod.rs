@@ -1,5 +1,10 @@
+//Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::path::{Path,PathBuf};
 use caminos_lib::*;
 use config_parser::ConfigurationValue;
+use caminos_lib::experiments::{Action,Experiment,ExperimentOptions};
 
 /*
     Auxiliary functions to create the configuration file for the tests. Each function has a struct as argument which contains the needed parameters
@@ -417,3 +422,139 @@ pub fn create_simulation(arg: SimulationBuilder) -> ConfigurationValue
     ])
 
 }
+
+
+/// Encapsulates the parameters of a simulation with homogeneous traffic of messages of 16 phits, each in a single flit, over Basic routers.
+pub struct BasicSimulationBuilder
+{
+    pub warmup: usize,
+    pub measured: usize,
+    pub topology: ConfigurationValue,
+    pub pattern: ConfigurationValue,
+    pub servers: usize,
+    pub load: f64,
+    pub virtual_channels: usize,
+    pub buffer_size: usize,
+    pub output_buffer_size: usize,
+    pub routing: ConfigurationValue,
+    ///Additional fields of the `Configuration`, replacing those with the same name.
+    pub extra: Vec<(String,ConfigurationValue)>,
+}
+
+/// Creates the Configuration Value of the simulation with the given parameters.
+pub fn create_basic_simulation(arg: BasicSimulationBuilder) -> ConfigurationValue
+{
+    let message_size = 16;
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: arg.pattern,
+        servers: arg.servers,
+        load: arg.load,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: arg.virtual_channels,
+        vcp,
+        buffer_size: arg.buffer_size,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: arg.output_buffer_size,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let mut simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: arg.warmup,
+        measured: arg.measured,
+        topology: arg.topology,
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: message_size,
+        general_frequency_divisor: 1,
+        routing: arg.routing,
+        link_classes: create_link_classes(),
+    });
+    if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+    {
+        let extra = arg.extra;
+        fields.retain(|(name,_)|extra.iter().all(|(extra_name,_)|extra_name!=name));
+        fields.extend(extra);
+    }
+    simulation_cv
+}
+
+/// Runs the simulation with the given parameters and returns the fields of its results.
+pub fn simulate_basic(arg: BasicSimulationBuilder) -> Vec<(String,ConfigurationValue)>
+{
+    let simulation_cv = create_basic_simulation(arg);
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    object(&simulation.get_simulation_results(), "Result").to_vec()
+}
+
+/// Gets a field of an object.
+pub fn field<'a>(fields:&'a [(String,ConfigurationValue)], name:&str) -> &'a ConfigurationValue
+{
+    &fields.iter().find(|(key,_)|key==name).unwrap_or_else(||panic!("There were no {}",name)).1
+}
+
+/// The fields of an object with the expected name.
+pub fn object<'a>(value:&'a ConfigurationValue, expected_name:&str) -> &'a [(String,ConfigurationValue)]
+{
+    match value
+    {
+        ConfigurationValue::Object(name, fields) => {
+            assert_eq!(name, expected_name);
+            fields
+        },
+        _ => panic!("There should be a {} object", expected_name),
+    }
+}
+
+/// A small experiment with uniform traffic over a 4-router ring at loads 0.2 and 0.4.
+/// The `extra` text is inserted as additional fields of the `Configuration`.
+pub fn small_experiment_cfg(extra:&str) -> String
+{
+    format!("Configuration{{
+    random_seed: 1,
+    warmup: 200,
+    measured: 500,
+    topology: Torus{{ sides:[4], servers_per_router:1 }},
+    traffic: HomogeneousTraffic{{ pattern:Uniform, servers:4, load:![0.2,0.4], message_size:16 }},
+    maximum_packet_size: 16,
+    router: Preset{{ name:\"IQ-VCT-2VC\" }},
+    routing: ChannelsPerHop{{ routing:Shortest, channels:[[0],[1],[0,1]] }},
+    link_classes: [ LinkClass{{delay:1}}, LinkClass{{delay:1}} ],
+    {}
+}}",extra)
+}
+
+/// Creates the directory `name` of an experiment inside the temporary directory, with the given `main.cfg` and `main.od`.
+/// Any previous directory with that name is removed first.
+pub fn experiment_directory(name:&str, cfg:&str, od:&str) -> PathBuf
+{
+    let root = std::env::temp_dir().join(format!("caminos_{}_{}",name,std::process::id()));
+    if root.exists()
+    {
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("main.cfg"),cfg).unwrap();
+    std::fs::write(root.join("main.od"),od).unwrap();
+    root
+}
+
+/// Executes the `action` on the experiment in `root`.
+pub fn execute_experiment_action(root:&Path, plugs:&Plugs, action:Action, options:ExperimentOptions) -> Result<(),error::Error>
+{
+    let mut experiment = Experiment::new(Path::new("caminos"),root,plugs,options);
+    experiment.execute_action(action)
+}
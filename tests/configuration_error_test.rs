@@ -0,0 +1,158 @@
+/*!
+    Tests that bad configurations are reported as errors instead of panics.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::error::ErrorKind;
+use common::*;
+
+/// Builds a simulation over a Hamming graph, whose topology may be given an unknown field.
+fn hamming_simulation(topology_extra_field:Option<&str>) -> ConfigurationValue
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let mut topology = create_hamming_topology(hamming_builder);
+    if let (Some(field),ConfigurationValue::Object(_,ref mut pairs)) = (topology_extra_field,&mut topology)
+    {
+        pairs.push( (field.to_string(),ConfigurationValue::Number(1.0)) );
+    }
+    let message_size = 16;
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 32,
+        load: 0.5,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 100,
+        measured: 100,
+        topology,
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    })
+}
+
+#[test]
+fn unknown_field_is_an_error()
+{
+    let plugs = Plugs::default();
+    let cv = hamming_simulation(Some("sidez"));
+    let error = match Simulation::try_new(&cv,&plugs)
+    {
+        Ok(_) => panic!("a simulation with an unknown field was built"),
+        Err(error) => error,
+    };
+    assert!(matches!(error.kind,ErrorKind::IllFormedConfiguration{..}),"unexpected error {}",error);
+    assert!(error.message.as_ref().is_some_and(|message|message.contains("sidez")),"the error does not mention the field: {}",error);
+    assert!(error.source_location.file.ends_with("cartesian.rs"),"the error is not located at the Hamming builder: {}",error);
+}
+
+#[test]
+fn good_configuration_is_built()
+{
+    let plugs = Plugs::default();
+    let cv = hamming_simulation(None);
+    let mut simulation = Simulation::try_new(&cv,&plugs).expect("a good simulation could not be built");
+    simulation.run();
+}
+
+/// Sets the `field` of the simulation configuration `cv`, replacing it if already present.
+fn with_field(cv:&ConfigurationValue, field:&str, value:ConfigurationValue) -> ConfigurationValue
+{
+    match cv
+    {
+        ConfigurationValue::Object(name,pairs) =>
+        {
+            let mut pairs:Vec<(String,ConfigurationValue)> = pairs.iter().filter(|(key,_)|key!=field).cloned().collect();
+            pairs.push( (field.to_string(),value) );
+            ConfigurationValue::Object(name.clone(),pairs)
+        },
+        _ => panic!("The simulation should be an Object"),
+    }
+}
+
+/// The sections of the simulation configuration other than its components are also reported as errors.
+#[test]
+fn bad_simulation_sections_are_errors()
+{
+    let plugs = Plugs::default();
+    let cv = hamming_simulation(None);
+    let object = |name:&str, fields:Vec<(&str,ConfigurationValue)>| ConfigurationValue::Object(name.to_string(), fields.into_iter().map(|(key,value)|(key.to_string(),value)).collect());
+    let bad_sections = vec![
+        ("link_classes", ConfigurationValue::Array(vec![object("LinkClass",vec![("delai",ConfigurationValue::Number(1.0))])])),
+        ("server_queue_size", ConfigurationValue::Number(0.0)),
+        ("rail_selection", object("Nonexistent",vec![])),
+        ("statistics_packet_definitions", ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)])),
+        ("deadlock_watchdog", object("DeadlockWatchdog",vec![("cycles",ConfigurationValue::Number(0.0))])),
+        ("nic", object("NIC",vec![("injection_bandwith",ConfigurationValue::Number(1.0))])),
+        ("statistics_flows", object("FlowStatistics",vec![("percentiles",ConfigurationValue::True)])),
+        ("measurement_extension", object("MeasurementExtension",vec![("check_period",ConfigurationValue::Number(100.0))])),
+        ("events", ConfigurationValue::Array(vec![object("LinkDown",vec![("cycle",ConfigurationValue::Number(10.0)),("router",ConfigurationValue::Number(0.0))])])),
+        ("steady_state_detection", object("SteadyStateDetection",vec![("window",ConfigurationValue::Number(0.0)),("tolerance",ConfigurationValue::Number(0.1))])),
+        ("statistics_batch_means", object("BatchMeans",vec![("batches",ConfigurationValue::Number(1.0))])),
+        ("progress_reporters", ConfigurationValue::Array(vec![object("ProgressFile",vec![("period",ConfigurationValue::Number(0.0)),("path",ConfigurationValue::Literal("progress.txt".to_string()))])])),
+        ("progress_reporters", ConfigurationValue::Array(vec![object("Nonexistent",vec![])])),
+    ];
+    for (field,value) in bad_sections
+    {
+        match Simulation::try_new(&with_field(&cv,field,value),&plugs)
+        {
+            Ok(_) => panic!("a simulation with a bad {} was built",field),
+            Err(error) => assert!(matches!(error.kind,ErrorKind::IllFormedConfiguration{..}),"unexpected error for {}: {}",field,error),
+        }
+    }
+}
+
+/// The optional sections of the router are also reported as errors.
+#[test]
+fn bad_router_sections_are_errors()
+{
+    let plugs = Plugs::default();
+    let cv = hamming_simulation(None);
+    let router = match cv
+    {
+        ConfigurationValue::Object(_,ref pairs) => pairs.iter().find(|(key,_)|key=="router").expect("there is no router").1.clone(),
+        _ => panic!("The simulation should be an Object"),
+    };
+    let object = |name:&str, fields:Vec<(&str,ConfigurationValue)>| ConfigurationValue::Object(name.to_string(), fields.into_iter().map(|(key,value)|(key.to_string(),value)).collect());
+    let bad_sections = vec![
+        ("output_scheduling", object("WeightedTable",vec![("weights",ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0),ConfigurationValue::Number(0.0)]))])),
+        ("output_scheduling", object("WeightedTable",vec![("table",ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)]))])),
+        ("output_scheduling", object("Nonexistent",vec![])),
+    ];
+    for (field,value) in bad_sections
+    {
+        match Simulation::try_new(&with_field(&cv,"router",with_field(&router,field,value)),&plugs)
+        {
+            Ok(_) => panic!("a simulation with a bad router {} was built",field),
+            Err(error) => assert!(matches!(error.kind,ErrorKind::IllFormedConfiguration{..}),"unexpected error for {}: {}",field,error),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+/*!
+    Tests for the end-to-end congestion control of the servers.
+*/
+
+mod common;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Simulates a complete graph of 4 routers with 4 servers each under uniform traffic at full load, with the given congestion control.
+fn simulate(congestion_control:Option<ConfigurationValue>) -> Vec<(String,ConfigurationValue)>
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 4,
+    };
+    simulate_basic(BasicSimulationBuilder{
+        warmup: 500,
+        measured: 2000,
+        topology: create_hamming_topology(hamming_builder),
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        load: 1.0,
+        virtual_channels: 1,
+        buffer_size: 64,
+        output_buffer_size: 32,
+        routing: create_shortest_routing(),
+        extra: congestion_control.into_iter().map(|control|("congestion_control".to_string(), control)).collect(),
+    })
+}
+
+/// Gets the fields of the statistics of the congestion control.
+fn control_fields(fields:&[(String,ConfigurationValue)]) -> &[(String,ConfigurationValue)]
+{
+    match field(fields, "congestion_control")
+    {
+        ConfigurationValue::Object(_, control) => control,
+        _ => panic!("The congestion_control statistics should be an Object"),
+    }
+}
+
+/// The full load saturates the network, so the ports fill and ECN lowers the injection rate.
+#[test]
+fn ecn_throttles_injection()
+{
+    let uncontrolled = simulate(None);
+    let ecn = ConfigurationValue::Object("ECN".to_string(), vec![
+        ("threshold".to_string(), ConfigurationValue::Number(32.0)),
+        ("feedback_delay".to_string(), ConfigurationValue::Number(10.0)),
+    ]);
+    let controlled = simulate(Some(ecn));
+    let control = control_fields(&controlled);
+    let marked = field(control, "marked_packets").as_f64().unwrap();
+    let consumed = field(control, "consumed_packets").as_f64().unwrap();
+    assert!(marked > 0.0 && marked <= consumed, "marked={} consumed={}", marked, consumed);
+    let rate = field(control, "average_rate").as_f64().unwrap();
+    assert!(rate < 1.0, "The rate should have been reduced, got {}", rate);
+    let injected = field(&controlled, "injected_load").as_f64().unwrap();
+    let uncontrolled_injected = field(&uncontrolled, "injected_load").as_f64().unwrap();
+    assert!(injected < uncontrolled_injected, "ECN injected {} and without control {}", injected, uncontrolled_injected);
+}
+
+/// With a window of one packet each server waits for the feedback of a packet before injecting the next.
+#[test]
+fn end_to_end_credits_window()
+{
+    let credits = ConfigurationValue::Object("EndToEndCredits".to_string(), vec![
+        ("window".to_string(), ConfigurationValue::Number(1.0)),
+        ("feedback_delay".to_string(), ConfigurationValue::Number(20.0)),
+    ]);
+    let fields = simulate(Some(credits));
+    let control = control_fields(&fields);
+    let in_flight = field(control, "average_packets_in_flight").as_f64().unwrap();
+    assert!(in_flight > 0.0 && in_flight <= 1.0, "in_flight={}", in_flight);
+    // A packet of 16 phits needs at least its serialization, 3 links, and the feedback before the next one.
+    let injected = field(&fields, "injected_load").as_f64().unwrap();
+    assert!(injected <= 16.0/(16.0+3.0+20.0), "injected={}", injected);
+}
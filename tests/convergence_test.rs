@@ -0,0 +1,66 @@
+/*!
+    Tests for the Convergence routing, which routes with stale tables for some cycles after the faults.
+*/
+
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+
+fn convergence() -> ConfigurationObject
+{
+    ConfigurationObject::new("Convergence")
+        .field("routing",ConfigurationObject::new("Shortest"))
+        .field("stale_topology",ConfigurationObject::new("Hamming").field("sides",vec![4]).field("servers_per_router",2))
+        .field("convergence_delay",500)
+        .field("enable_statistics",true)
+}
+
+/// Gets the `stale_requests` of the Convergence statistics inside those of a Sum routing.
+fn stale_requests(statistics:&ConfigurationValue, key:&str) -> f64
+{
+    let inner = match statistics
+    {
+        ConfigurationValue::Object(name,fields) if name=="SumRoutingStatistics" => &fields.iter().find(|(field,_)|field==key).expect("missing sub-routing statistics").1,
+        _ => panic!("The routing statistics should be those of the Sum routing"),
+    };
+    match inner
+    {
+        ConfigurationValue::Object(name,fields) if name=="ConvergenceStatistics" => fields.iter().find(|(field,_)|field=="stale_requests").expect("missing stale_requests").1.as_f64().expect("bad stale_requests"),
+        _ => panic!("The sub-routing statistics should be those of Convergence"),
+    }
+}
+
+/// A Convergence inside another routing follows the cycles of the simulation, so after the `convergence_delay` it stops using the stale tables.
+#[test]
+fn convergence_inside_sum_routing()
+{
+    let routing = ConfigurationObject::new("Sum")
+        .field("policy",ConfigurationObject::new("Random"))
+        .field("first_routing",convergence())
+        .field("second_routing",convergence())
+        .field("first_allowed_virtual_channels",vec![0])
+        .field("second_allowed_virtual_channels",vec![1])
+        .field("enabled_statistics",true);
+    let builder = SimulationBuilder::new()
+        .warmup(1000)
+        .measured(1000)
+        .topology(ConfigurationObject::new("RandomLinkFaults")
+            .field("topology",ConfigurationObject::new("Hamming").field("sides",vec![4]).field("servers_per_router",2))
+            .field("links",vec![vec![0,1]]))
+        .traffic(ConfigurationObject::new("HomogeneousTraffic")
+            .field("pattern",ConfigurationObject::new("Uniform"))
+            .field("servers",8)
+            .field("load",0.3)
+            .field("message_size",16))
+        .router(ConfigurationObject::new("Preset").field("name","IQ-VCT-2VC"))
+        .routing(routing)
+        .link_delays(&[1,1]);
+    let plugs = Plugs::default();
+    let mut simulation = builder.build(&plugs).unwrap();
+    simulation.run();
+    let results = simulation.results();
+    assert!((results.accepted_load-0.3).abs()<0.05,"accepted load {}",results.accepted_load);
+    let statistics = results.routing_statistics.as_ref().expect("missing routing statistics");
+    assert_eq!(stale_requests(statistics,"first_statistics"),0.0);
+    assert_eq!(stale_requests(statistics,"second_statistics"),0.0);
+}
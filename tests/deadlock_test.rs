@@ -0,0 +1,113 @@
+/*!
+    Tests for the detection of deadlocks.
+*/
+
+mod common;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Simulates a ring of 8 routers under a shift at full load with a single virtual channel. With a shift of several hops the packets fill a credit cycle.
+fn simulate(shift:usize) -> Vec<(String,ConfigurationValue)>
+{
+    let topology = ConfigurationValue::Object("Torus".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(8.0)])),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![ConfigurationValue::Number(8.0)],
+        shift: vec![ConfigurationValue::Number(shift as f64)],
+    };
+    simulate_network(topology, create_shift_pattern(shift_pattern_builder), 8, create_dor_routing(vec![0]))
+}
+
+/// Simulates the network at full load with a single virtual channel and the deadlock watchdog.
+fn simulate_network(topology:ConfigurationValue, pattern:ConfigurationValue, servers:usize, routing:ConfigurationValue) -> Vec<(String,ConfigurationValue)>
+{
+    simulate_basic(BasicSimulationBuilder{
+        warmup: 0,
+        measured: 5000,
+        topology,
+        pattern,
+        servers,
+        load: 1.0,
+        virtual_channels: 1,
+        buffer_size: 32,
+        output_buffer_size: 16,
+        routing,
+        extra: vec![("deadlock_watchdog".to_string(), ConfigurationValue::Object("DeadlockWatchdog".to_string(), vec![
+            ("cycles".to_string(), ConfigurationValue::Number(200.0)),
+        ]))],
+    })
+}
+
+/// Routes of 3 hops deadlock the ring, and the diagnosis finds the credit cycle around it.
+#[test]
+fn ring_deadlock_diagnosis()
+{
+    let fields = simulate(3);
+    let diagnosis = match field(&fields, "deadlock")
+    {
+        ConfigurationValue::Object(name, diagnosis) => {
+            assert_eq!(name, "DeadlockDiagnosis");
+            diagnosis
+        },
+        _ => panic!("The deadlock diagnosis should be an Object"),
+    };
+    let cycle = field(diagnosis, "cycle").as_f64().unwrap();
+    assert!(cycle < 5000.0, "The watchdog should end the simulation, at cycle {}", cycle);
+    assert!(cycle - field(diagnosis, "last_movement_cycle").as_f64().unwrap() >= 200.0);
+    assert!(field(diagnosis, "blocked_buffers").as_f64().unwrap() > 0.0);
+    let credit_cycle = field(diagnosis, "credit_cycle").as_array().unwrap();
+    assert_eq!(credit_cycle.len(), 8, "The credit cycle should go around the ring: {:?}", credit_cycle);
+}
+
+/// Routes of a single hop have no cyclic dependencies, so there is no diagnosis.
+#[test]
+fn ring_without_deadlock()
+{
+    let fields = simulate(1);
+    assert!(fields.iter().all(|(key,_)|key!="deadlock"), "Single hop routes should not deadlock");
+}
+
+/// Simulates a 4x4 mesh under uniform traffic routed minimally with the given forbidden turns.
+fn simulate_turn_model(forbidden_turns:Vec<Vec<ConfigurationValue>>) -> Vec<(String,ConfigurationValue)>
+{
+    let topology = ConfigurationValue::Object("Mesh".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    let routing = ConfigurationValue::Object("ConfigurableTurnModel".to_string(), vec![
+        ("forbidden_turns".to_string(), ConfigurationValue::Array(forbidden_turns.into_iter().map(ConfigurationValue::Array).collect())),
+    ]);
+    simulate_network(topology, create_uniform_pattern(), 16, routing)
+}
+
+/// A turn given by the directions as written in the configuration, optionally restricted to a parity of a dimension.
+fn turn(from:&str, to:&str, parity:Option<(usize,usize)>) -> Vec<ConfigurationValue>
+{
+    let mut turn = vec![ConfigurationValue::Literal(from.to_string()), ConfigurationValue::Literal(to.to_string())];
+    if let Some((dimension,parity)) = parity
+    {
+        turn.push(ConfigurationValue::Number(dimension as f64));
+        turn.push(ConfigurationValue::Number(parity as f64));
+    }
+    turn
+}
+
+/// The fully adaptive minimal routing deadlocks the mesh with a single virtual channel, while the turn models prevent it.
+#[test]
+fn turn_models_avoid_deadlock()
+{
+    let fields = simulate_turn_model(vec![]);
+    assert!(fields.iter().any(|(key,_)|key=="deadlock"), "Fully adaptive minimal routing should deadlock");
+    let west_first = vec![turn("+1","-0",None), turn("-1","-0",None)];
+    let negative_first = vec![turn("+0","-1",None), turn("+1","-0",None)];
+    let odd_even = vec![turn("+0","+1",Some((0,0))), turn("+0","-1",Some((0,0))), turn("+1","-0",Some((0,1))), turn("-1","-0",Some((0,1)))];
+    for (name,forbidden_turns) in [("west-first",west_first), ("negative-first",negative_first), ("odd-even",odd_even)].iter()
+    {
+        let fields = simulate_turn_model(forbidden_turns.clone());
+        assert!(fields.iter().all(|(key,_)|key!="deadlock"), "The {} turn model should not deadlock", name);
+        let accepted_load = field(&fields, "accepted_load").as_f64().unwrap();
+        assert!(accepted_load > 0.2, "The {} turn model accepted only {}", name, accepted_load);
+    }
+}
@@ -0,0 +1,218 @@
+/*!
+    Tests that simulations are deterministic given their configuration.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Fields of the results that depend on the machine running the simulation and not on the simulation itself.
+const MACHINE_DEPENDENT_FIELDS: [&str;6] = ["user_time","system_time","wall_time","cpu_time","linux_high_water_mark","git_id"];
+
+/// Builds a simulation with randomness in the traffic, the routing and the virtual channel selection.
+fn random_simulation(random_seed:usize) -> ConfigurationValue
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let message_size = 16;
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 32,
+        load: 0.6,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    create_simulation(SimulationBuilder{
+        random_seed,
+        warmup: 200,
+        measured: 1000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_omnidimensional_routing(ConfigurationValue::Number(1.0), ConfigurationValue::True),
+        link_classes: create_link_classes(),
+    })
+}
+
+/// Runs the simulation and returns its results without the machine dependent fields.
+fn run_results(simulation_cv:&ConfigurationValue) -> Vec<(String,ConfigurationValue)>
+{
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(simulation_cv, &plugs);
+    simulation.run();
+    match simulation.get_simulation_results()
+    {
+        ConfigurationValue::Object(_, fields) => fields.into_iter().filter(|(name,_)|!MACHINE_DEPENDENT_FIELDS.contains(&name.as_str())).collect(),
+        _ => panic!("The results should be an Object"),
+    }
+}
+
+/// The same configuration and seed must produce identical results, including in repeated runs inside the same process.
+#[test]
+fn same_seed_same_results()
+{
+    let simulation_cv = random_simulation(7);
+    let first = run_results(&simulation_cv);
+    let second = run_results(&simulation_cv);
+    assert_eq!(first.len(), second.len());
+    for ((name,a),(_,b)) in first.iter().zip(second.iter())
+    {
+        assert_eq!(a, b, "Field {} differs between runs", name);
+    }
+    let other = run_results(&random_simulation(8));
+    assert!(first.iter().zip(other.iter()).any(|((_,a),(_,b))|a!=b), "Different seeds should give different results");
+}
+
+/// Golden values of a fixed configuration and seed, read from the integer counters of the statistics.
+/// A platform that rounds or generates random numbers differently would not match them.
+/// They must be updated only by changes that intentionally alter the simulation.
+#[test]
+fn golden_counters()
+{
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&random_simulation(7), &plugs);
+    simulation.run();
+    let measurement = &simulation.statistics.current_measurement;
+    assert_eq!(simulation.shared.cycle, 1200);
+    assert_eq!(measurement.created_phits, 19061);
+    assert_eq!(measurement.consumed_phits, 19150);
+    assert_eq!(measurement.consumed_packets, 1196);
+}
+
+/// The typed results must agree with the fields of the results given as a `ConfigurationValue`.
+#[test]
+fn typed_results()
+{
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&random_simulation(7), &plugs);
+    simulation.run();
+    let typed = simulation.results();
+    let results = match simulation.get_simulation_results()
+    {
+        ConfigurationValue::Object(_, fields) => fields,
+        _ => panic!("The results should be an Object"),
+    };
+    let get = |field:&str| results.iter().find(|(name,_)|name==field).unwrap_or_else(||panic!("missing field {}",field)).1.as_f64().expect("not a number");
+    assert_eq!(typed.cycle as f64, get("cycle"));
+    assert_eq!(typed.injected_load, get("injected_load"));
+    assert_eq!(typed.accepted_load, get("accepted_load"));
+    assert_eq!(typed.average_message_delay, get("average_message_delay"));
+    assert_eq!(typed.average_packet_hops, get("average_packet_hops"));
+    assert_eq!(typed.servers.len(), 32);
+    let average_server_load = typed.servers.iter().map(|server|server.accepted_load).sum::<f64>() / 32.0;
+    assert!((average_server_load - typed.accepted_load).abs() < 1e-9, "The servers accept {} on average instead of {}", average_server_load, typed.accepted_load);
+}
+
+/// Registering hooks must not change the simulation, and they must be called for each event.
+#[test]
+fn hooks_observe_without_altering()
+{
+    use std::cell::Cell;
+    let plugs = Plugs::default();
+    let cv = random_simulation(11);
+    let mut plain = Simulation::new(&cv, &plugs);
+    plain.run();
+    let plain_results = plain.results();
+    let cycles = Cell::new(0);
+    let consumed_packets = Cell::new(0);
+    let generated_messages = Cell::new(0);
+    let mut hooked = Simulation::new(&cv, &plugs);
+    hooked.hooks.on_cycle_end(|shared| { assert_eq!(shared.cycle, cycles.get()); cycles.set(cycles.get()+1) });
+    hooked.hooks.on_packet_consumed(|packet,_cycle| { assert!(packet.size > 0); consumed_packets.set(consumed_packets.get()+1) });
+    hooked.hooks.on_message_generated(|_message,_cycle| generated_messages.set(generated_messages.get()+1));
+    hooked.run();
+    let hooked_results = hooked.results();
+    assert_eq!(plain_results.accepted_load, hooked_results.accepted_load);
+    assert_eq!(plain_results.average_message_delay, hooked_results.average_message_delay);
+    assert_eq!(cycles.get(), hooked_results.cycle);
+    assert!(consumed_packets.get() > 0, "No consumed packet was observed");
+    assert!(generated_messages.get() > 0, "No generated message was observed");
+}
+
+/// Running the experiments of a file in parallel threads must give the same results, in the same order.
+#[test]
+fn parallel_file_main()
+{
+    use std::fs::File;
+    let root = std::env::temp_dir().join(format!("caminos_parallel_file_main_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let cfg_path = root.join("main.cfg");
+    let experiments = ConfigurationValue::Experiments((1..=4).map(random_simulation).collect());
+    std::fs::write(&cfg_path, format!("{}", experiments)).unwrap();
+    let run = |jobs:usize| -> Vec<Vec<(String,ConfigurationValue)>> {
+        let results_path = root.join(format!("results{}", jobs));
+        let plugs = Plugs::default();
+        let mut options = FileOptions::default();
+        options.jobs = jobs;
+        file_main_with_options(&mut File::open(&cfg_path).unwrap(), &plugs, Some(File::create(&results_path).unwrap()), &[], &options).unwrap();
+        // The results are written one after another, so we join them into an array to parse them.
+        let contents = std::fs::read_to_string(&results_path).unwrap();
+        let results = match config_parser::parse(&format!("[{}]", contents.trim_end().replace("}\n\nResult", "},\nResult")))
+        {
+            Ok(config_parser::Token::Value(ConfigurationValue::Array(results))) => results,
+            _ => panic!("could not parse the results of {} jobs", jobs),
+        };
+        results.into_iter().map(|result|match result
+        {
+            ConfigurationValue::Object(_, fields) => fields.into_iter().filter(|(name,_)|!MACHINE_DEPENDENT_FIELDS.contains(&name.as_str())).collect(),
+            _ => panic!("The results should be an Object"),
+        }).collect()
+    };
+    let sequential = run(1);
+    let parallel = run(3);
+    assert_eq!(sequential.len(), 4);
+    assert_eq!(sequential, parallel);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// Parallel jobs are rejected when the experiments write other files, as all of them would write into the same path.
+#[test]
+fn parallel_file_main_rejects_output_files()
+{
+    use std::fs::File;
+    let root = std::env::temp_dir().join(format!("caminos_parallel_output_files_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let cfg_path = root.join("main.cfg");
+    let progress_file = ConfigurationValue::Array(vec![ConfigurationValue::Object("ProgressFile".to_string(), vec![("path".to_string(), ConfigurationValue::Literal("progress.txt".to_string()))])]);
+    for (field,value) in [("packet_trace_file", ConfigurationValue::Literal("trace.csv".to_string())), ("progress_reporters", progress_file)]
+    {
+        let experiments = ConfigurationValue::Experiments((1..=2).map(|seed|match random_simulation(seed)
+        {
+            ConfigurationValue::Object(name, mut fields) =>
+            {
+                fields.push((field.to_string(), value.clone()));
+                ConfigurationValue::Object(name, fields)
+            },
+            _ => panic!("The simulation should be an Object"),
+        }).collect());
+        std::fs::write(&cfg_path, format!("{}", experiments)).unwrap();
+        let plugs = Plugs::default();
+        let mut options = FileOptions::default();
+        options.jobs = 2;
+        let error = file_main_with_options(&mut File::open(&cfg_path).unwrap(), &plugs, Some(File::create(root.join("results")).unwrap()), &[], &options).unwrap_err();
+        assert!(format!("{}", error).contains(field), "unexpected error {}", error);
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,98 @@
+/*!
+    Tests for the accounting of the energy consumed by the network.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Gets a numeric field of an object.
+fn number(value:&ConfigurationValue, name:&str) -> f64
+{
+    match value
+    {
+        ConfigurationValue::Object(_, fields) => fields.iter().find(|(key,_)|key==name).unwrap_or_else(||panic!("There were no {}",name)).1.as_f64().expect("bad number"),
+        _ => panic!("{} should be an Object",value),
+    }
+}
+
+/// A complete graph of 4 routers with a server each, in which each server sends a message to the next one.
+/// Each message crosses a server link into its router, a link between routers and a link into the destination server.
+#[test]
+fn energy_of_a_burst()
+{
+    let message_size = 16;
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let mut simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 1000,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        }),
+        traffic: create_burst_traffic(BurstTrafficBuilder{
+            pattern: create_shift_pattern(ShiftPatternBuilder{
+                sides: vec![ConfigurationValue::Number(4.0)],
+                shift: vec![ConfigurationValue::Number(1.0)],
+            }),
+            servers: 4,
+            messages_per_server: 1,
+            message_size,
+        }),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+    {
+        fields.push(("energy_model".to_string(), ConfigurationValue::Object("EnergyModel".to_string(), vec![
+            ("phit_energy".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0), ConfigurationValue::Number(0.5)])),
+            ("link_static_power".to_string(), ConfigurationValue::Number(0.01)),
+            ("router_idle_power".to_string(), ConfigurationValue::Number(1.0)),
+            ("router_active_power".to_string(), ConfigurationValue::Number(2.0)),
+            ("router_phit_energy".to_string(), ConfigurationValue::Number(0.25)),
+        ])));
+    }
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let energy = match results
+    {
+        ConfigurationValue::Object(_, ref fields) => &fields.iter().find(|(key,_)|key=="energy").expect("There were no energy").1,
+        _ => panic!("The results should be an Object"),
+    };
+    let cycles = number(energy, "cycles");
+    assert!(cycles > 0.0);
+    // 4*16 phits between routers and 2*4*16 phits in the server links.
+    assert_eq!(number(energy, "links_dynamic"), 64.0 + 128.0 * 0.5);
+    // 12 links between routers and 8 server links.
+    assert!((number(energy, "links_static") - 20.0 * 0.01 * cycles).abs() < 1e-9);
+    // Each router receives 32 phits, in at least 16 different cycles.
+    assert_eq!(number(energy, "routers_dynamic"), 128.0 * 0.25);
+    let routers_static = number(energy, "routers_static");
+    assert!(routers_static >= 4.0 * cycles + 4.0 * 16.0 && routers_static <= 8.0 * cycles, "routers_static={} cycles={}", routers_static, cycles);
+    let total = number(energy, "links_dynamic") + number(energy, "links_static") + routers_static + number(energy, "routers_dynamic");
+    assert!((number(energy, "total") - total).abs() < 1e-9);
+}
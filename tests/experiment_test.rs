@@ -0,0 +1,302 @@
+/*!
+    Tests for the actions over experiment directories and the files they generate.
+*/
+
+mod common;
+
+use caminos_lib::*;
+use caminos_lib::experiments::{Action,ExperimentOptions};
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::output::OutputBuilderArgument;
+use caminos_lib::error::Error;
+use common::*;
+
+/// Each local run records its resources in the result and the journal, and the `ResourceUsage` output summarizes them.
+#[test]
+fn resource_usage_and_journal()
+{
+    let od = "[ResourceUsage{ filename:\"resources.csv\", selector:[=configuration.traffic.load] }]";
+    let root = experiment_directory("resource_usage",&small_experiment_cfg(""),od);
+    let plugs = Plugs::default();
+    execute_experiment_action(&root,&plugs,Action::LocalAndOutput,ExperimentOptions::default()).unwrap();
+    for run in 0..2
+    {
+        let result = std::fs::read_to_string(root.join(format!("runs/run{}/local.result",run))).unwrap();
+        assert!(result.contains("wall_time") && result.contains("cpu_time"),"The result of run {} lacks its resource usage",run);
+    }
+    let journal = std::fs::read_to_string(root.join("journal")).unwrap();
+    for experiment_index in 0..2
+    {
+        assert!(journal.contains(&format!("Experiment {} took",experiment_index)),"The journal lacks the resource usage of the experiment {}",experiment_index);
+    }
+    let csv = std::fs::read_to_string(root.join("outputs/resources.csv")).unwrap();
+    let lines:Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0],"selector, runs, total_wall_time, average_wall_time, total_cpu_time, average_cpu_time, maximum_high_water_mark");
+    // A row for each load and the total.
+    assert_eq!(lines.len(),4);
+    assert!(lines[1].starts_with("0.2, 1,") && lines[2].starts_with("0.4, 1,"),"unexpected rows {:?}",lines);
+    assert!(lines[3].starts_with("total, 2,"));
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// The `statistics_temporal_file` of each run is written inside its run directory, instead of all of them into the same file.
+#[test]
+fn temporal_file_in_run_directory()
+{
+    let cfg = small_experiment_cfg("statistics_temporal_step: 100, statistics_temporal_file: \"temporal.csv\",");
+    let root = experiment_directory("temporal_file",&cfg,"[]");
+    let plugs = Plugs::default();
+    execute_experiment_action(&root,&plugs,Action::Local,ExperimentOptions::default()).unwrap();
+    for run in 0..2
+    {
+        let temporal = std::fs::read_to_string(root.join(format!("runs/run{}/temporal.csv",run))).unwrap();
+        let lines:Vec<&str> = temporal.lines().collect();
+        assert!(lines[0].starts_with("begin_cycle,injected_load,accepted_load"),"unexpected header {}",lines[0]);
+        // A sample for each period of the 700 cycles simulated.
+        assert_eq!(lines.len(),1+7,"unexpected samples {:?}",lines);
+        let result = std::fs::read_to_string(root.join(format!("runs/run{}/local.result",run))).unwrap();
+        assert!(result.contains("TemporalStatisticsFile"),"The result of run {} lacks the temporal file",run);
+    }
+    assert!(!root.join("temporal.csv").exists() && !std::path::Path::new("temporal.csv").exists());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// The `statistics_link_traces` of each run are written inside its run directory.
+#[test]
+fn link_traces_in_run_directory()
+{
+    let cfg = small_experiment_cfg("statistics_link_traces: LinkTraces{ filename:\"links.csv\", links:[[0,1]] },");
+    let root = experiment_directory("link_traces",&cfg,"[]");
+    let plugs = Plugs::default();
+    execute_experiment_action(&root,&plugs,Action::Local,ExperimentOptions::default()).unwrap();
+    for run in 0..2
+    {
+        let traces = std::fs::read_to_string(root.join(format!("runs/run{}/links.csv",run))).unwrap();
+        let lines:Vec<&str> = traces.lines().collect();
+        assert_eq!(lines[0],"source,destination,port,link_class,runs");
+        assert!(lines.len()>1,"There are no traced links");
+        for line in lines[1..].iter()
+        {
+            let fields:Vec<&str> = line.split(',').collect();
+            assert_eq!(&fields[0..2],&["0","1"]);
+            // The idle and busy periods cover the measured cycles.
+            let covered:u64 = fields[4].split(' ').map(|run|run.parse::<u64>().unwrap()).sum();
+            assert_eq!(covered,500);
+        }
+    }
+    assert!(!root.join("links.csv").exists() && !std::path::Path::new("links.csv").exists());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// The `packet_trace_file` of each run is written inside its run directory, with a record of the expected columns for each packet.
+#[test]
+fn packet_trace_in_run_directory()
+{
+    let cfg = small_experiment_cfg("packet_trace_file: \"trace.csv\",");
+    let root = experiment_directory("packet_trace",&cfg,"[]");
+    let plugs = Plugs::default();
+    execute_experiment_action(&root,&plugs,Action::Local,ExperimentOptions::default()).unwrap();
+    for run in 0..2
+    {
+        let trace = std::fs::read_to_string(root.join(format!("runs/run{}/trace.csv",run))).unwrap();
+        let lines:Vec<&str> = trace.lines().collect();
+        assert_eq!(lines[0],measures::PacketTrace::COLUMNS.join(","));
+        assert!(lines.len()>1,"There are no traced packets");
+        for line in lines[1..].iter()
+        {
+            let fields:Vec<&str> = line.split(',').collect();
+            assert_eq!(fields.len(),16,"bad record {}",line);
+            let number = |index:usize| fields[index].parse::<u64>().unwrap_or_else(|_|panic!("bad field {} in {}",index,line));
+            assert_ne!(number(0),number(1),"A packet to itself in {}",line);
+            assert_eq!(number(2),16);
+            // generation, injection and consumption cycles.
+            assert!(number(5)<=number(6) && number(6)<number(7),"bad cycles in {}",line);
+            // A router more than hops, and an element for each router in the lists.
+            let routers = fields[9].split(' ').count() as u64;
+            assert_eq!(routers,number(8)+1,"bad routers in {}",line);
+            for list in fields[10..=12].iter()
+            {
+                assert_eq!(list.split(' ').count() as u64,routers,"bad list {} in {}",list,line);
+            }
+            // Untagged messages.
+            assert_eq!(&fields[13..16],&["","",""]);
+        }
+        let result:String = std::fs::read_to_string(root.join(format!("runs/run{}/local.result",run))).unwrap().split_whitespace().collect();
+        assert!(result.contains(&format!("PacketTraceFile{{filename:\"trace.csv\",packets:{},}}",lines.len()-1)),"The result of run {} does not count the traced packets",run);
+    }
+    assert!(!root.join("trace.csv").exists() && !std::path::Path::new("trace.csv").exists());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// Gets the field `key` of the result in the file `path`.
+fn result_field(path:&std::path::Path, key:&str) -> ConfigurationValue
+{
+    let contents = std::fs::read_to_string(path).unwrap();
+    match config_parser::parse(&contents)
+    {
+        Ok(config_parser::Token::Value(ConfigurationValue::Object(_,fields))) => fields.into_iter().find(|(name,_)|name==key).unwrap_or_else(||panic!("missing {} in {:?}",key,path)).1,
+        _ => panic!("could not parse the result {:?}",path),
+    }
+}
+
+/// With `statistics_link_utilization` the results include the utilization of each link, and the `LinkHeatmap` writes their matrix for each selector value.
+#[test]
+fn link_utilization_heatmap()
+{
+    let cfg = small_experiment_cfg("statistics_link_utilization: true,");
+    let od = "[LinkHeatmap{ filename:\"links.csv\", selector:=configuration.traffic.load, tex_filename:\"links.tex\" }]";
+    let root = experiment_directory("link_heatmap",&cfg,od);
+    let plugs = Plugs::default();
+    execute_experiment_action(&root,&plugs,Action::LocalAndOutput,ExperimentOptions::default()).unwrap();
+    let csv = std::fs::read_to_string(root.join("outputs/links.csv")).unwrap();
+    let lines:Vec<&str> = csv.lines().collect();
+    // For each load, the selector, the header, and a row for each of the 4 routers with its 2 links.
+    assert_eq!(lines.len(),2*(2+4),"unexpected lines {:?}",lines);
+    let mut totals = vec![];
+    for (run,load) in ["0.2","0.4"].iter().enumerate()
+    {
+        let block = &lines[run*6..(run+1)*6];
+        assert_eq!(block[0],format!("selector, {}",load));
+        assert_eq!(block[1],"router, port0, port1");
+        let mut utilizations = vec![];
+        for (router,line) in block[2..].iter().enumerate()
+        {
+            let fields:Vec<&str> = line.split(", ").collect();
+            assert_eq!(fields.len(),3,"bad row {}",line);
+            assert_eq!(fields[0],router.to_string());
+            utilizations.extend(fields[1..].iter().map(|x|x.parse::<f64>().unwrap()));
+        }
+        assert!(utilizations.iter().all(|&x|(0.0..=1.0).contains(&x)),"bad utilizations {:?}",utilizations);
+        // With a single run for each load the matrix is that of the run. Its average is the one in the result, whose maximum also includes the links of the servers.
+        let result = root.join(format!("runs/run{}/local.result",run));
+        let rows:Vec<f64> = result_field(&result,"link_utilization").as_array().unwrap().iter().flat_map(|row|row.as_array().unwrap().iter().map(|x|x.as_f64().unwrap()).collect::<Vec<f64>>()).collect();
+        assert_eq!(rows,utilizations);
+        let total:f64 = utilizations.iter().sum();
+        let maximum = utilizations.iter().cloned().fold(0.0,f64::max);
+        assert!((total/8.0-result_field(&result,"average_link_utilization").as_f64().unwrap()).abs()<1e-9);
+        assert!(maximum<=result_field(&result,"maximum_link_utilization").as_f64().unwrap());
+        totals.push(total);
+    }
+    assert!(totals[0]>0.0 && totals[0]<totals[1],"The links should be more used at the higher load, {:?}",totals);
+    let tex = std::fs::read_to_string(root.join("outputs/links.tex")).unwrap();
+    assert_eq!(tex.matches("\\begin{tikzpicture}").count(),2);
+    assert_eq!(tex.matches("mesh/cols=2").count(),2);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// A plugged output writing into its `filename` the load and accepted load of each result, in the order of the experiments.
+fn build_loads_output(arg:OutputBuilderArgument) -> Result<(),Error>
+{
+    let mut filename = None;
+    match_object_panic!(arg.cv,["Loads","CSV"],value,
+        "filename" => filename = Some(value.as_str().expect("bad value for filename").to_string()),
+        _ => (),
+    );
+    let mut lines = vec![];
+    for context in arg.environment.iter()
+    {
+        let field = |cv:&ConfigurationValue, key:&str| match cv
+        {
+            ConfigurationValue::Object(_,fields) => fields.iter().find(|(name,_)|name==key).map(|(_,value)|value.clone()).unwrap(),
+            _ => panic!("{} is not inside an object",key),
+        };
+        let load = field(&field(&field(&context,"configuration"),"traffic"),"load");
+        let accepted = field(&field(&context,"result"),"accepted_load");
+        lines.push(format!("{} {}",load,accepted));
+    }
+    let path = arg.environment.files().get_outputs_path().join(filename.expect("There were no filename"));
+    std::fs::write(&path,lines.join("\n")).unwrap();
+    Ok(())
+}
+
+/// The outputs registered with `Plugs::add_output` are built from the `main.od` with the results of the experiment, and take precedence over the predefined outputs.
+#[test]
+fn plugged_output()
+{
+    let od = "[Loads{ filename:\"loads.txt\" }, CSV{ filename:\"replaced.txt\" }]";
+    let root = experiment_directory("plugged_output",&small_experiment_cfg(""),od);
+    let mut plugs = Plugs::default();
+    plugs.add_output("Loads".to_string(),build_loads_output);
+    plugs.add_output("CSV".to_string(),build_loads_output);
+    execute_experiment_action(&root,&plugs,Action::LocalAndOutput,ExperimentOptions::default()).unwrap();
+    let loads = std::fs::read_to_string(root.join("outputs/loads.txt")).unwrap();
+    let lines:Vec<&str> = loads.lines().collect();
+    assert_eq!(lines.len(),2,"unexpected lines {:?}",lines);
+    for (line,load) in lines.iter().zip([0.2,0.4].iter())
+    {
+        let fields:Vec<f64> = line.split(' ').map(|x|x.parse().unwrap()).collect();
+        assert_eq!(fields[0],*load);
+        assert!((fields[1]-load).abs()<0.05,"accepted load {} for the load {}",fields[1],load);
+    }
+    // The CSV has been written by the plugged output, without the fields required by the predefined one.
+    assert_eq!(std::fs::read_to_string(root.join("outputs/replaced.txt")).unwrap(),loads);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// The `Gnuplot` and `Matplotlib` backends of `Plots` write a script and a data file with a block of points for each line.
+#[test]
+fn script_plot_backends()
+{
+    let plots = |prefix:&str, backend:&str| format!("Plots{{
+        selector: =configuration.traffic.message_size,
+        kind: [Plotkind{{ parameter:=configuration.traffic.load, abscissas:=configuration.traffic.load, label_abscissas:\"offered load\", ordinates:=result.accepted_load, label_ordinates:\"accepted load\", min_ordinate:0.0, max_ordinate:1.0 }}],
+        legend: =configuration.maximum_packet_size,
+        prefix: \"{}\",
+        backend: {},
+    }}",prefix,backend);
+    let od = format!("[{}, {}]",
+        plots("gnuplot","Gnuplot{ script_filename:\"throughput.gp\", pdf_filename:\"throughput.pdf\", execute:false }"),
+        plots("matplotlib","Matplotlib{ script_filename:\"throughput.py\", data_filename:\"points.dat\", pdf_filename:\"throughput_mpl.pdf\", execute:false }"));
+    let root = experiment_directory("script_plots",&small_experiment_cfg(""),&od);
+    let plugs = Plugs::default();
+    execute_experiment_action(&root,&plugs,Action::LocalAndOutput,ExperimentOptions::default()).unwrap();
+    let outputs = root.join("outputs");
+    // By default the data file is named by the prefix.
+    for data_filename in ["gnuplot.dat","points.dat"].iter()
+    {
+        let data = std::fs::read_to_string(outputs.join(data_filename)).unwrap();
+        let points:Vec<Vec<f64>> = data.lines().filter(|line|!line.is_empty() && !line.starts_with('#'))
+            .map(|line|line.split(' ').map(|x|x.parse().unwrap()).collect()).collect();
+        // A single line with a point for each load.
+        assert_eq!(points.len(),2,"unexpected data {}",data);
+        for (point,load) in points.iter().zip([0.2,0.4].iter())
+        {
+            assert_eq!(point.len(),4);
+            assert_eq!(point[0],*load);
+            assert!((point[1]-load).abs()<0.05,"accepted load {} for the load {}",point[1],load);
+        }
+        assert!(data.starts_with("# 16 / accepted load / 16\n# x y x_deviation y_deviation\n"),"unexpected header in {}",data);
+    }
+    let gnuplot = std::fs::read_to_string(outputs.join("throughput.gp")).unwrap();
+    assert!(gnuplot.contains("set output \"throughput.pdf\"\n"));
+    assert!(gnuplot.contains("set xlabel \"offered load\"\nset ylabel \"accepted load\"\n"));
+    assert!(gnuplot.contains("set yrange [0:1]\n"));
+    assert!(gnuplot.contains("plot \"gnuplot.dat\" index 0 using 1:2:3:4 with xyerrorlines title \"16\"\n"),"unexpected script {}",gnuplot);
+    let matplotlib = std::fs::read_to_string(outputs.join("throughput.py")).unwrap();
+    assert!(matplotlib.contains("blocks = read_blocks(\"points.dat\")\n"));
+    assert!(matplotlib.contains("with PdfPages(\"throughput_mpl.pdf\") as pdf:\n"));
+    assert!(matplotlib.contains("lines=[(\"16\",0)]"),"unexpected script {}",matplotlib);
+    // The scripts are not executed.
+    assert!(!outputs.join("throughput.pdf").exists() && !outputs.join("throughput_mpl.pdf").exists());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// The Validate action builds every simulation, reporting the experiments with a bad configuration without running any of them.
+#[test]
+fn validate_bad_configuration()
+{
+    let plugs = Plugs::default();
+    let root = experiment_directory("validate_good",&small_experiment_cfg(""),"[]");
+    execute_experiment_action(&root,&plugs,Action::Validate,ExperimentOptions::default()).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    // Half of the experiments use a pattern that does not exist.
+    let cfg = small_experiment_cfg("").replace("pattern:Uniform","pattern:![Uniform,Nonexistent]");
+    let root = experiment_directory("validate_bad",&cfg,"[]");
+    let error = execute_experiment_action(&root,&plugs,Action::Validate,ExperimentOptions::default()).expect_err("The bad configurations should be reported");
+    let message = format!("{}",error);
+    assert!(message.contains("2 of 4 experiments have a bad configuration"),"unexpected error {}",message);
+    let journal = std::fs::read_to_string(root.join("journal")).unwrap();
+    assert!(journal.contains("Validate checked 4 experiments, 2 failed."),"unexpected journal {}",journal);
+    assert!(!root.join("runs").exists(),"Validate should not create the runs");
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -378,4 +378,871 @@ fn sum_routing_test_3()
         "virtual_channel_usage" => assert_eq!(value.clone().as_array().expect("Virtual channel usage data").iter().map(|a| a.as_f64().expect("Virtual channel usage data")).collect::<Vec<f64>>()[0], value.clone().as_array().expect("Virtual channel usage data").iter().map(|a| a.as_f64().expect("Virtual channel usage data")).collect::<Vec<f64>>()[1], "Virtual channel usage"), //assert!( value.as_f64().expect("Injected load data") as f64 == estimated_injected_load),
         _ => (),
     );
-}
\ No newline at end of file
+}
+/// Test UGAL under an adversarial shift in a Hamming graph, in which every router sends all its traffic to the same neighbour.
+/// Both the local and the global versions should divert traffic through Valiant paths, accepting much more load than the minimal routing.
+#[test]
+fn ugal_routing_test()
+{
+    let message_size = 16;
+    let accepted_load = |routing:ConfigurationValue| -> (f64,f64)
+    {
+        let hamming_builder = HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+            servers_per_router: 2,
+        };
+        let shift_pattern_builder = ShiftPatternBuilder{
+            sides: vec![2,4,4].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+            shift: vec![0,1,0].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+        };
+        let traffic_builder = HomogeneousTrafficBuilder{
+            pattern: create_shift_pattern(shift_pattern_builder),
+            servers: 32,
+            load: 0.8,
+            message_size,
+        };
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![]),
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 4,
+            vcp,
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        // A different virtual channel in each hop to avoid deadlock.
+        let channels = vec![vec![0.0],vec![1.0],vec![2.0],vec![3.0],vec![0.0,1.0,2.0,3.0]];
+        let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+            ("routing".to_string(), routing),
+            ("channels".to_string(), ConfigurationValue::Array(channels.into_iter().map(|vcs|ConfigurationValue::Array(vcs.into_iter().map(ConfigurationValue::Number).collect())).collect())),
+        ]);
+        let simulation_cv = create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 500,
+            measured: 1500,
+            topology: create_hamming_topology(hamming_builder),
+            traffic: create_homogeneous_traffic(traffic_builder),
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing,
+            link_classes: create_link_classes(),
+        });
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&simulation_cv, &plugs);
+        simulation.run();
+        let results = simulation.get_simulation_results();
+        let mut accepted = None;
+        let mut nonminimal = 0.0;
+        match_object_panic!( &results, "Result", value,
+            "accepted_load" => accepted = Some(value.as_f64().expect("Accepted load data")),
+            "routing_statistics" => match_object_panic!(value, "UGALStatistics", statistic,
+                "minimal" => (),
+                "nonminimal" => nonminimal = statistic.as_f64().expect("Nonminimal decisions data"),
+            ),
+            _ => (),
+        );
+        (accepted.expect("There were no accepted_load"), nonminimal)
+    };
+    let (minimal_load, _) = accepted_load(create_shortest_routing());
+    assert!(minimal_load < 0.55, "Minimal routing should saturate, accepted load {}", minimal_load);
+    for global in [ConfigurationValue::False, ConfigurationValue::True]
+    {
+        let ugal = ConfigurationValue::Object("UGAL".to_string(), vec![
+            ("routing".to_string(), create_shortest_routing()),
+            ("global".to_string(), global.clone()),
+        ]);
+        let (load, nonminimal) = accepted_load(ugal);
+        assert!(load > 0.7, "UGAL with global={:?} should accept the offered load, accepted load {}", global, load);
+        assert!(nonminimal > 0.0, "UGAL with global={:?} should take Valiant paths", global);
+    }
+}
+
+/// Test the instrumentation of the adaptive decisions of UGAL under the adversarial shift of `ugal_routing_test`.
+#[test]
+fn choice_regret_test()
+{
+    let message_size = 16;
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![2,4,4].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+        shift: vec![0,1,0].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+    };
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers: 32,
+        load: 0.8,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 4,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let channels = vec![vec![0.0],vec![1.0],vec![2.0],vec![3.0],vec![0.0,1.0,2.0,3.0]];
+    let ugal = ConfigurationValue::Object("UGAL".to_string(), vec![
+        ("routing".to_string(), create_shortest_routing()),
+    ]);
+    let routing = ConfigurationValue::Object("ChoiceRegret".to_string(), vec![
+        ("routing".to_string(), ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+            ("routing".to_string(), ugal),
+            ("channels".to_string(), ConfigurationValue::Array(channels.into_iter().map(|vcs|ConfigurationValue::Array(vcs.into_iter().map(ConfigurationValue::Number).collect())).collect())),
+        ])),
+        ("hop_latency".to_string(), ConfigurationValue::Number(2.0)),
+    ]);
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 500,
+        measured: 1500,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut statistics = vec![];
+    match_object_panic!( &results, "Result", value,
+        "routing_statistics" => match_object_panic!(value, "ChoiceRegretStatistics", statistic,
+            "routing_statistics" => (),
+            "decisions" | "average_latency" | "average_chosen_estimate" | "average_best_alternative_estimate" | "average_regret" | "regretted_fraction" => statistics.push(statistic.as_f64().expect("bad statistic")),
+        ),
+        _ => (),
+    );
+    assert_eq!(statistics.len(), 6, "There were no ChoiceRegretStatistics");
+    let (decisions, latency, chosen, best_alternative, regret, regretted) = (statistics[0], statistics[1], statistics[2], statistics[3], statistics[4], statistics[5]);
+    assert!(decisions > 0.0, "UGAL should take adaptive decisions");
+    assert!(latency > 0.0 && chosen > 0.0 && best_alternative > 0.0, "latency={} chosen={} best_alternative={}", latency, chosen, best_alternative);
+    assert!((regret - (latency - best_alternative)).abs() < 1e-6);
+    assert!((0.0..=1.0).contains(&regretted), "regretted_fraction={}", regretted);
+}
+
+/// Test the export of the features of the routing decisions of UGAL under the adversarial shift of `ugal_routing_test`.
+#[test]
+fn decision_features_test()
+{
+    let message_size = 16;
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![2,4,4].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+        shift: vec![0,1,0].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+    };
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers: 32,
+        load: 0.5,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 4,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let channels = vec![vec![0.0],vec![1.0],vec![2.0],vec![3.0],vec![0.0,1.0,2.0,3.0]];
+    let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+        ("routing".to_string(), ConfigurationValue::Object("UGAL".to_string(), vec![
+            ("routing".to_string(), create_shortest_routing()),
+        ])),
+        ("channels".to_string(), ConfigurationValue::Array(channels.into_iter().map(|vcs|ConfigurationValue::Array(vcs.into_iter().map(ConfigurationValue::Number).collect())).collect())),
+    ]);
+    let mut simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 200,
+        measured: 1000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    });
+    let path = std::env::temp_dir().join("caminos_decision_features_test.csv");
+    if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+    {
+        fields.push(("statistics_decision_features".to_string(), ConfigurationValue::Object("DecisionFeatures".to_string(), vec![
+            ("filename".to_string(), ConfigurationValue::Literal(path.to_str().expect("bad path").to_string())),
+            ("sampling".to_string(), ConfigurationValue::Number(0.5)),
+            ("seed".to_string(), ConfigurationValue::Number(1.0)),
+        ])));
+    }
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut counts = vec![];
+    match_object_panic!( &results, "Result", value,
+        "decision_features" => match_object_panic!(value, "DecisionFeaturesFile", statistic,
+            "filename" => (),
+            "packets" | "decisions" => counts.push(statistic.as_f64().expect("bad count")),
+        ),
+        _ => (),
+    );
+    assert_eq!(counts.len(), 2, "There were no DecisionFeaturesFile");
+    let (packets, decisions) = (counts[0], counts[1]);
+    assert!(packets > 0.0 && decisions >= packets, "packets={} decisions={}", packets, decisions);
+    let content = std::fs::read_to_string(&path).expect("could not read the features");
+    std::fs::remove_file(&path).ok();
+    let mut lines = content.lines();
+    assert_eq!(lines.next(), Some(caminos_lib::measures::DecisionFeatures::COLUMNS.join(",").as_str()));
+    let chosen_column = caminos_lib::measures::DecisionFeatures::COLUMNS.iter().position(|c|*c=="chosen").unwrap();
+    let mut chosen_per_decision = std::collections::BTreeMap::new();
+    for line in lines
+    {
+        let values: Vec<&str> = line.split(',').collect();
+        assert_eq!(values.len(), caminos_lib::measures::DecisionFeatures::COLUMNS.len(), "Bad row {}", line);
+        *chosen_per_decision.entry(values[0].to_string()).or_insert(0) += values[chosen_column].parse::<usize>().expect("bad chosen");
+    }
+    assert_eq!(chosen_per_decision.len() as f64, decisions);
+    assert!(chosen_per_decision.values().all(|&c|c==1), "Each decision should have exactly one chosen candidate");
+}
+
+/// Test that the routing reads the hints attached by the traffic. Under the adversarial shift of `ugal_routing_test`, UGAL must route minimally the messages hinted as latency critical.
+#[test]
+fn routing_hints_test()
+{
+    let message_size = 16;
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![2,4,4].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+        shift: vec![0,1,0].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+    };
+    let traffic = ConfigurationValue::Object("WithRoutingHints".to_string(), vec![
+        ("traffic".to_string(), create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_shift_pattern(shift_pattern_builder),
+            servers: 32,
+            load: 0.8,
+            message_size,
+        })),
+        ("latency_critical".to_string(), ConfigurationValue::True),
+    ]);
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 4,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let channels = vec![vec![0.0],vec![1.0],vec![2.0],vec![3.0],vec![0.0,1.0,2.0,3.0]];
+    let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+        ("routing".to_string(), ConfigurationValue::Object("UGAL".to_string(), vec![
+            ("routing".to_string(), create_shortest_routing()),
+        ])),
+        ("channels".to_string(), ConfigurationValue::Array(channels.into_iter().map(|vcs|ConfigurationValue::Array(vcs.into_iter().map(ConfigurationValue::Number).collect())).collect())),
+    ]);
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 500,
+        measured: 1500,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+            servers_per_router: 2,
+        }),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    match_object_panic!( &results, "Result", value,
+        "average_packet_hops" => assert_eq!(value.as_f64().expect("Packet hops data"), 1.0, "Latency critical packets should take the minimal path"),
+        "routing_statistics" => match_object_panic!(value, "UGALStatistics", statistic,
+            "minimal" => (),
+            "nonminimal" => assert_eq!(statistic.as_f64().expect("Nonminimal decisions data"), 0.0, "Latency critical packets should not take Valiant paths"),
+        ),
+        _ => (),
+    );
+}
+
+/// A routing that uses more virtual channels than the routers have must fail when building the simulation, before simulating.
+#[test]
+#[should_panic(expected = "ChannelsPerHop uses the virtual channel 2 but some router has only 2 virtual channels")]
+fn insufficient_virtual_channels_test()
+{
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let channels = vec![vec![0.0],vec![1.0],vec![2.0]];
+    let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+        ("routing".to_string(), create_shortest_routing()),
+        ("channels".to_string(), ConfigurationValue::Array(channels.into_iter().map(|vcs|ConfigurationValue::Array(vcs.into_iter().map(ConfigurationValue::Number).collect())).collect())),
+    ]);
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 100,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        }),
+        traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 16,
+            load: 0.1,
+            message_size: 16,
+        }),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    Simulation::new(&simulation_cv, &plugs);
+}
+
+/// Test PiggybackPAR in a Dragonfly under a shift of the groups, in which the servers of each group send all their traffic to the next group.
+/// The congestion of the global links known through the summaries of the groups should divert traffic through Valiant paths, accepting more load than the minimal routing.
+#[test]
+fn piggyback_par_routing_test()
+{
+    let message_size = 16;
+    let load = 0.4;
+    let accepted_load = |routing:ConfigurationValue| -> (f64,f64)
+    {
+        // A canonic Dragonfly with 9 groups of 4 routers and 72 servers.
+        let topology = ConfigurationValue::Object("Dragonfly".to_string(), vec![
+            ("global_ports_per_router".to_string(), ConfigurationValue::Number(2.0)),
+            ("servers_per_router".to_string(), ConfigurationValue::Number(2.0)),
+        ]);
+        let shift_pattern_builder = ShiftPatternBuilder{
+            sides: vec![8,9].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+            shift: vec![0,1].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+        };
+        let traffic_builder = HomogeneousTrafficBuilder{
+            pattern: create_shift_pattern(shift_pattern_builder),
+            servers: 72,
+            load,
+            message_size,
+        };
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![]),
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 8,
+            vcp,
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        // A different virtual channel in each hop to avoid deadlock.
+        let channels = (0..8).map(|vc|ConfigurationValue::Array(vec![ConfigurationValue::Number(vc as f64)])).collect();
+        let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+            ("routing".to_string(), routing),
+            ("channels".to_string(), ConfigurationValue::Array(channels)),
+        ]);
+        let simulation_cv = create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 500,
+            measured: 1500,
+            topology,
+            traffic: create_homogeneous_traffic(traffic_builder),
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing,
+            link_classes: create_link_classes(),
+        });
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&simulation_cv, &plugs);
+        simulation.run();
+        let results = simulation.get_simulation_results();
+        let mut accepted = None;
+        let mut nonminimal = 0.0;
+        match_object_panic!( &results, "Result", value,
+            "accepted_load" => accepted = Some(value.as_f64().expect("Accepted load data")),
+            "routing_statistics" => match_object_panic!(value, "PiggybackPARStatistics", statistic,
+                "minimal" => (),
+                "nonminimal" => nonminimal += statistic.as_f64().expect("Nonminimal decisions data"),
+                "revised" => nonminimal += statistic.as_f64().expect("Revised decisions data"),
+            ),
+            _ => (),
+        );
+        (accepted.expect("There were no accepted_load"), nonminimal)
+    };
+    let (minimal_load, _) = accepted_load(create_shortest_routing());
+    let piggyback = ConfigurationValue::Object("PiggybackPAR".to_string(), vec![
+        ("routing".to_string(), create_shortest_routing()),
+        ("local_latency".to_string(), ConfigurationValue::Number(2.0)),
+        ("global_latency".to_string(), ConfigurationValue::Number(20.0)),
+    ]);
+    let (piggyback_load, nonminimal) = accepted_load(piggyback);
+    assert!(nonminimal > 0.0, "PiggybackPAR should take Valiant paths");
+    assert!(piggyback_load > minimal_load + 0.03, "PiggybackPAR should accept more load than minimal routing, {} against {}", piggyback_load, minimal_load);
+    assert!(piggyback_load > load - 0.03, "PiggybackPAR should accept the offered load, accepted load {}", piggyback_load);
+}
+
+/// When each group sends to the next one in a Dragonfly and the routing takes a single global hop only one global link per group is used.
+/// The global link report must find these links as hotspots, each one used by a single pair of consecutive groups.
+#[test]
+fn global_link_report_test()
+{
+    let message_size = 16;
+    let topology = ConfigurationValue::Object("Dragonfly".to_string(), vec![
+        ("global_ports_per_router".to_string(), ConfigurationValue::Number(2.0)),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(2.0)),
+    ]);
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![8,9].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+        shift: vec![0,1].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+    };
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers: 72,
+        load: 0.2,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 4,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let channels = (0..4).map(|vc|ConfigurationValue::Array(vec![ConfigurationValue::Number(vc as f64)])).collect();
+    // Weighting the global links avoids the paths with two global hops.
+    let weighed_shortest = ConfigurationValue::Object("WeighedShortest".to_string(), vec![
+        ("class_weight".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0), ConfigurationValue::Number(10.0)])),
+    ]);
+    let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+        ("routing".to_string(), weighed_shortest),
+        ("channels".to_string(), ConfigurationValue::Array(channels)),
+    ]);
+    let mut simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 200,
+        measured: 1000,
+        topology,
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    });
+    if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+    {
+        fields.push(("statistics_global_links".to_string(), ConfigurationValue::Object("GlobalLinkReport".to_string(), vec![])));
+    }
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut report = None;
+    match_object_panic!( &results, "Result", value,
+        "global_links" => report = Some(value.clone()),
+        _ => (),
+    );
+    let mut links = 0;
+    let mut hotspots = vec![];
+    match_object_panic!( &report.expect("There were no global_links"), "GlobalLinkReport", value,
+        "average_utilization" => assert!(value.as_f64().expect("average_utilization data") > 0.0),
+        "maximum_utilization" => (),
+        "links" => links = value.as_array().expect("links data").len(),
+        "hotspots" => hotspots = value.as_array().expect("hotspots data").clone(),
+    );
+    // 9 groups of 4 routers with 2 global ports each.
+    assert_eq!(links, 72, "Each global link must be reported");
+    assert_eq!(hotspots.len(), 9, "There should be a hotspot from each group to the next one");
+    for hotspot in hotspots.iter()
+    {
+        match_object_panic!( hotspot, "GlobalLinkHotspot", value,
+            "ratio" => assert!(value.as_f64().expect("ratio data") > 2.0),
+            "group_pairs" => {
+                let pairs = value.as_array().expect("group_pairs data");
+                assert_eq!(pairs.len(), 1, "Only one pair of groups should use each hotspot");
+                let (mut source_group, mut destination_group) = (0, 0);
+                match_object_panic!( &pairs[0], "GroupPair", pair_value,
+                    "source_group" => source_group = pair_value.as_usize().expect("source_group data"),
+                    "destination_group" => destination_group = pair_value.as_usize().expect("destination_group data"),
+                    "packets" => (),
+                );
+                assert_eq!(destination_group, (source_group + 1) % 9, "The hotspot should be used by consecutive groups");
+            },
+            _ => (),
+        );
+    }
+}
+
+/// Test the source routing over the k shortest paths. Under the adversarial shift of `ugal_routing_test` the minimal routing saturates,
+/// while storing several of the 8 shortest paths in the packets spreads the load among the two and three hop paths.
+#[test]
+fn k_shortest_paths_routing_test()
+{
+    let message_size = 16;
+    let accepted_load = |routing:ConfigurationValue| -> f64
+    {
+        let hamming_builder = HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+            servers_per_router: 2,
+        };
+        let shift_pattern_builder = ShiftPatternBuilder{
+            sides: vec![2,4,4].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+            shift: vec![0,1,0].into_iter().map(|a| ConfigurationValue::Number(a as f64)).collect(),
+        };
+        let traffic_builder = HomogeneousTrafficBuilder{
+            pattern: create_shift_pattern(shift_pattern_builder),
+            servers: 32,
+            load: 0.8,
+            message_size,
+        };
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![]),
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 4,
+            vcp,
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        // A different virtual channel in each hop to avoid deadlock.
+        let channels = vec![vec![0.0],vec![1.0],vec![2.0],vec![3.0]];
+        let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+            ("routing".to_string(), routing),
+            ("channels".to_string(), ConfigurationValue::Array(channels.into_iter().map(|vcs|ConfigurationValue::Array(vcs.into_iter().map(ConfigurationValue::Number).collect())).collect())),
+        ]);
+        let simulation_cv = create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 500,
+            measured: 1500,
+            topology: create_hamming_topology(hamming_builder),
+            traffic: create_homogeneous_traffic(traffic_builder),
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing,
+            link_classes: create_link_classes(),
+        });
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&simulation_cv, &plugs);
+        simulation.run();
+        simulation.results().accepted_load
+    };
+    let minimal_load = accepted_load(create_shortest_routing());
+    let k_shortest = ConfigurationValue::Object("SourceAdaptive".to_string(), vec![
+        ("routing".to_string(), ConfigurationValue::Object("KShortestPaths".to_string(), vec![
+            ("k".to_string(), ConfigurationValue::Number(8.0)),
+        ])),
+        ("amount".to_string(), ConfigurationValue::Number(4.0)),
+    ]);
+    let load = accepted_load(k_shortest);
+    assert!(load > minimal_load + 0.1, "The k shortest paths should accept more load than minimal routing, {} vs {}", load, minimal_load);
+}
+
+/// Test the routing by tables. The table computed without a file has the minimal routes, so it must behave exactly as the minimal routing.
+/// Its dump, read back as the table, must give again the same results.
+#[test]
+fn table_routing_test()
+{
+    let message_size = 16;
+    let results = |routing:ConfigurationValue| -> (f64,f64)
+    {
+        let hamming_builder = HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+            servers_per_router: 2,
+        };
+        let traffic_builder = HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 32,
+            load: 0.5,
+            message_size,
+        };
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![]),
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 2,
+            vcp,
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        let routing = ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+            ("routing".to_string(), routing),
+            ("channels".to_string(), ConfigurationValue::Array(vec![
+                ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)]),
+                ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)]),
+                ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0),ConfigurationValue::Number(1.0)]),
+            ])),
+        ]);
+        let simulation_cv = create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 200,
+            measured: 800,
+            topology: create_hamming_topology(hamming_builder),
+            traffic: create_homogeneous_traffic(traffic_builder),
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing,
+            link_classes: create_link_classes(),
+        });
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&simulation_cv, &plugs);
+        simulation.run();
+        let results = simulation.results();
+        (results.accepted_load, results.average_message_delay)
+    };
+    let path = std::env::temp_dir().join(format!("caminos_table_routing_{}.txt", std::process::id()));
+    let filename = ConfigurationValue::Literal(path.to_str().expect("bad path").to_string());
+    let minimal = results(create_shortest_routing());
+    let computed = results(ConfigurationValue::Object("TableRouting".to_string(), vec![
+        ("dump".to_string(), filename.clone()),
+    ]));
+    assert_eq!(computed, minimal, "The computed table should behave as the minimal routing");
+    let dumped = std::fs::read_to_string(&path).expect("the table should have been dumped");
+    // Each of the 16 routers has a route towards each of the other 15.
+    assert_eq!(dumped.lines().filter(|line|!line.starts_with('#')).count(), 16*15);
+    let loaded = results(ConfigurationValue::Object("TableRouting".to_string(), vec![
+        ("filename".to_string(), filename),
+    ]));
+    assert_eq!(loaded, minimal, "The table read from the file should behave as the minimal routing");
+    std::fs::remove_file(&path).expect("could not remove the table");
+}
+
+/// Test the EscapeChannels routing in a Hamming graph with a single adaptive virtual channel, in which the minimal routing alone could deadlock.
+/// Under uniform traffic at high load both the adaptive and the escape channels must be used, and the packets must keep being delivered.
+#[test]
+fn escape_channels_routing_test()
+{
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let routing = ConfigurationValue::Object("EscapeChannels".to_string(), vec![
+        ("routing".to_string(), create_shortest_routing()),
+        ("escape".to_string(), create_dor_routing(vec![0,1])),
+        ("escape_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)])),
+        ("escape_extra_label".to_string(), ConfigurationValue::Number(1.0)),
+        ("enabled_statistics".to_string(), ConfigurationValue::True),
+    ]);
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 500,
+        measured: 1500,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+            servers_per_router: 2,
+        }),
+        traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 32,
+            load: 1.0,
+            message_size: 16,
+        }),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.results();
+    assert!(results.accepted_load > 0.3, "The network should keep delivering packets, accepted load {}", results.accepted_load);
+    let mut adaptive_hops = None;
+    let mut escape_hops = None;
+    match_object_panic!( &results.routing_statistics.expect("There were no routing statistics"), "EscapeChannelsStatistics", value,
+        "adaptive_hops" => adaptive_hops = Some(value.as_f64().expect("adaptive hops data")),
+        "escape_hops" => escape_hops = Some(value.as_f64().expect("escape hops data")),
+    );
+    let adaptive_hops = adaptive_hops.expect("There were no adaptive_hops");
+    let escape_hops = escape_hops.expect("There were no escape_hops");
+    assert!(adaptive_hops > 0.0, "No hop used the adaptive channels");
+    assert!(escape_hops > 0.0, "No hop used the escape channels");
+}
+
+/// Test the Duato routing, which checks before simulating that its escape channels are deadlock-free.
+/// The DOR escape in a Hamming graph satisfies Duato's condition and runs with the default fully adaptive minimal routing, while the minimal routing as escape, with or without returning to the adaptive channels, has cyclic dependencies and is rejected.
+#[test]
+fn duato_routing_verification_test()
+{
+    let duato = |escape: ConfigurationValue, return_to_adaptive: ConfigurationValue| {
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![]),
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 2,
+            vcp,
+            buffer_size: 32,
+            bubble: ConfigurationValue::False,
+            flit_size: 16,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        let routing = ConfigurationValue::Object("Duato".to_string(), vec![
+            ("escape".to_string(), escape),
+            ("escape_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)])),
+            ("escape_extra_label".to_string(), ConfigurationValue::Number(1.0)),
+            ("return_to_adaptive".to_string(), return_to_adaptive),
+        ]);
+        create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 200,
+            measured: 800,
+            topology: create_hamming_topology(HammingBuilder{
+                sides: vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)],
+                servers_per_router: 1,
+            }),
+            traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+                pattern: create_uniform_pattern(),
+                servers: 16,
+                load: 0.8,
+                message_size: 16,
+            }),
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing,
+            link_classes: create_link_classes(),
+        })
+    };
+    let plugs = Plugs::default();
+    for return_to_adaptive in [ConfigurationValue::False, ConfigurationValue::True]
+    {
+        let mut simulation = Simulation::try_new(&duato(create_dor_routing(vec![0,1]), return_to_adaptive.clone()), &plugs).expect("DOR is a valid escape in a Hamming graph");
+        simulation.run();
+        assert!(simulation.results().accepted_load > 0.3);
+        let error = match Simulation::try_new(&duato(create_shortest_routing(), return_to_adaptive), &plugs)
+        {
+            Ok(_) => panic!("The minimal routing is not a deadlock-free escape"),
+            Err(error) => format!("{}", error),
+        };
+        assert!(error.contains("Duato's condition"), "Unexpected error: {}", error);
+    }
+}
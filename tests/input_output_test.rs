@@ -334,3 +334,226 @@ fn input_output_internal_speedup()
     );
 
 }
+
+/// With DVFS the links of a lightly loaded Hamming graph go to slower levels, reducing their power, while the traffic is still delivered.
+#[test]
+fn input_output_dvfs_low_load()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let message_size = 16;
+    let load = 0.05;
+    let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 8,
+        load,
+        message_size,
+    });
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    });
+    let mut router = create_input_output_router(InputOutputRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        crossbar_delay: 1,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Random".to_string(), vec![("seed".to_string(), ConfigurationValue::Number(1f64))]),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    });
+    if let ConfigurationValue::Object(_, ref mut fields) = router
+    {
+        fields.push(("dvfs".to_string(), ConfigurationValue::Object("Dvfs".to_string(), vec![
+            ("period".to_string(), ConfigurationValue::Number(200.0)),
+            ("transition_latency".to_string(), ConfigurationValue::Number(10.0)),
+            ("multipliers".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0), ConfigurationValue::Number(2.0), ConfigurationValue::Number(4.0)])),
+            ("upper_threshold".to_string(), ConfigurationValue::Number(0.6)),
+            ("lower_threshold".to_string(), ConfigurationValue::Number(0.2)),
+        ])));
+    }
+    let simulation_builder = SimulationBuilder{
+        random_seed: 2,
+        warmup: 1000,
+        measured: 4000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.results();
+    assert!((results.accepted_load - load).abs() < 0.02, "accepted load {} far from the offered {}", results.accepted_load, load);
+    let field = |cv:&ConfigurationValue, name:&str| -> ConfigurationValue {
+        match cv
+        {
+            ConfigurationValue::Object(_, fields) => fields.iter().find(|(key,_)| key==name).map(|(_,value)| value.clone()).unwrap_or_else(|| panic!("there is no field {}", name)),
+            _ => panic!("{} is not an object", cv),
+        }
+    };
+    let router_statistics = results.router_statistics.expect("there are no router statistics");
+    let dvfs = field(&router_statistics, "dvfs");
+    let link_power = field(&dvfs, "average_link_power").as_f64().expect("average_link_power is not a number");
+    let link_multiplier = field(&dvfs, "average_link_multiplier").as_f64().expect("average_link_multiplier is not a number");
+    assert!(link_multiplier > 2.0, "the links have not been slowed {}", link_multiplier);
+    assert!(link_power < 0.5, "the power of the links has not been reduced {}", link_power);
+}
+
+/// A `HierarchicalCrossbar` with tiles of 3 ports in a Hamming graph of radix 9 delivers the offered load, while the tiles report the phits held back by their internal links.
+#[test]
+fn hierarchical_crossbar_tiles()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(8.0)],
+        servers_per_router: 2,
+    };
+    let message_size = 16;
+    let load = 0.4;
+    let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        load,
+        message_size,
+    });
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    });
+    let mut router = create_input_output_router(InputOutputRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        crossbar_delay: 1,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Islip".to_string(), vec![("num_iter".to_string(), ConfigurationValue::Number(1.0))]),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    });
+    if let ConfigurationValue::Object(ref mut name, ref mut fields) = router
+    {
+        *name = "HierarchicalCrossbar".to_string();
+        fields.push(("tile_size".to_string(), ConfigurationValue::Number(3.0)));
+        fields.push(("internal_speedup".to_string(), ConfigurationValue::Number(2.0)));
+        fields.push(("tile_delay".to_string(), ConfigurationValue::Number(1.0)));
+    }
+    let simulation_builder = SimulationBuilder{
+        random_seed: 3,
+        warmup: 1000,
+        measured: 4000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 2,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.results();
+    assert!((results.accepted_load - load).abs() < 0.02, "accepted load {} far from the offered {}", results.accepted_load, load);
+    let field = |cv:&ConfigurationValue, name:&str| -> ConfigurationValue {
+        match cv
+        {
+            ConfigurationValue::Object(_, fields) => fields.iter().find(|(key,_)| key==name).map(|(_,value)| value.clone()).unwrap_or_else(|| panic!("there is no field {}", name)),
+            _ => panic!("{} is not an object", cv),
+        }
+    };
+    let router_statistics = results.router_statistics.expect("there are no router statistics");
+    let hierarchy = field(&router_statistics, "hierarchy");
+    let column_channel_stalls = field(&hierarchy, "column_channel_stalls").as_f64().expect("column_channel_stalls is not a number");
+    assert!(column_channel_stalls > 0.0, "the column channels never held back a phit");
+}
+
+/// The output scheduling disciplines and a crossbar with speedup 1 still deliver a moderate uniform load.
+#[test]
+fn input_output_output_scheduling_and_crossbar_speedup()
+{
+    let message_size = 16;
+    let load = 0.4;
+    let run = |extra_fields:Vec<(String,ConfigurationValue)>| {
+        let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 8,
+            load,
+            message_size,
+        });
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        });
+        let mut router = create_input_output_router(InputOutputRouterBuilder{
+            virtual_channels: 2,
+            vcp,
+            crossbar_delay: 1,
+            crossbar_frequency_divisor: 1,
+            allocator: ConfigurationValue::Object("Random".to_string(), vec![("seed".to_string(), ConfigurationValue::Number(1f64))]),
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: message_size,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+        });
+        if let ConfigurationValue::Object(_, ref mut fields) = router
+        {
+            fields.extend(extra_fields);
+        }
+        let simulation_builder = SimulationBuilder{
+            random_seed: 4,
+            warmup: 1000,
+            measured: 3000,
+            topology: create_hamming_topology(HammingBuilder{
+                sides: vec![ConfigurationValue::Number(4.0)],
+                servers_per_router: 2,
+            }),
+            traffic,
+            router,
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        };
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+        simulation.run();
+        simulation.results().accepted_load
+    };
+    let weighted_table = ConfigurationValue::Object("WeightedTable".to_string(), vec![
+        ("weights".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(64.0), ConfigurationValue::Number(16.0)])),
+    ]);
+    let disciplines = ["RoundRobin", "Random", "Fifo", "OldestFirst", "LabelPriority"].iter()
+        .map(|discipline|ConfigurationValue::Object(discipline.to_string(), vec![]))
+        .chain(std::iter::once(weighted_table));
+    for discipline in disciplines
+    {
+        let accepted_load = run(vec![("output_scheduling".to_string(), discipline.clone())]);
+        assert!((accepted_load - load).abs() < 0.04, "accepted load {} far from the offered {} with {}", accepted_load, load, discipline);
+    }
+    let accepted_load = run(vec![("crossbar_speedup".to_string(), ConfigurationValue::Number(1.0))]);
+    assert!((accepted_load - load).abs() < 0.04, "accepted load {} far from the offered {} with crossbar_speedup 1", accepted_load, load);
+}
@@ -0,0 +1,152 @@
+/*!
+    Tests for the link classes with a variable delay or a limited packet size.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Simulates a complete graph of 4 routers with a server each under uniform traffic, with the given class for the links between routers.
+fn simulate(router_link_class:ConfigurationValue) -> Vec<(String,ConfigurationValue)>
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 1,
+    };
+    let message_size = 16;
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 4,
+        load: 0.3,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let mut link_classes = create_link_classes();
+    if let ConfigurationValue::Array(ref mut classes) = link_classes
+    {
+        classes[0] = router_link_class;
+    }
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 3,
+        warmup: 200,
+        measured: 2000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes,
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    match simulation.get_simulation_results()
+    {
+        ConfigurationValue::Object(name, fields) => {
+            assert_eq!(name, "Result");
+            fields
+        },
+        _ => panic!("The results should be an Object"),
+    }
+}
+
+/// Gets a numeric field of the results.
+fn field(fields:&[(String,ConfigurationValue)], name:&str) -> f64
+{
+    fields.iter().find(|(key,_)|key==name).unwrap_or_else(||panic!("There were no {}",name)).1.as_f64().expect("bad value in results")
+}
+
+fn fixed(delay:f64) -> ConfigurationValue
+{
+    ConfigurationValue::Object("LinkClass".to_string(), vec![("delay".to_string(), ConfigurationValue::Number(delay))])
+}
+
+fn uniform(min:f64, max:f64, sampling:&str) -> ConfigurationValue
+{
+    ConfigurationValue::Object("LinkClass".to_string(), vec![
+        ("delay_distribution".to_string(), ConfigurationValue::Object("Uniform".to_string(), vec![
+            ("min".to_string(), ConfigurationValue::Number(min)),
+            ("max".to_string(), ConfigurationValue::Number(max)),
+        ])),
+        ("delay_sampling".to_string(), ConfigurationValue::Object(sampling.to_string(), vec![])),
+    ])
+}
+
+/// A distribution with a single value behaves as the fixed delay, whatever the sampling.
+#[test]
+fn degenerate_distribution_is_fixed_delay()
+{
+    let reference = simulate(fixed(5.0));
+    for sampling in ["Phit", "Packet", "Link"]
+    {
+        let results = simulate(uniform(5.0, 5.0, sampling));
+        for name in ["accepted_load", "average_packet_network_delay", "average_message_delay"]
+        {
+            assert_eq!(field(&results, name), field(&reference, name), "{} with {} sampling", name, sampling);
+        }
+    }
+}
+
+/// With variable delays every packet is still delivered, and its delay lies between the ones of the extreme fixed delays.
+#[test]
+fn variable_delay_between_extremes()
+{
+    let shortest = field(&simulate(fixed(2.0)), "average_packet_network_delay");
+    let longest = field(&simulate(fixed(8.0)), "average_packet_network_delay");
+    for sampling in ["Phit", "Packet", "Link"]
+    {
+        let results = simulate(uniform(2.0, 8.0, sampling));
+        let delay = field(&results, "average_packet_network_delay");
+        assert!(shortest < delay && delay < longest, "delay {} with {} sampling is not between {} and {}", delay, sampling, shortest, longest);
+        let injected = field(&results, "injected_load");
+        let accepted = field(&results, "accepted_load");
+        assert!((accepted - injected).abs() < 0.02, "accepted load {} differs from injected load {} with {} sampling", accepted, injected, sampling);
+    }
+}
+
+/// Limiting the packet size of the links between routers splits each message into more packets, all delivered through minimal routes.
+#[test]
+fn link_class_maximum_packet_size()
+{
+    let limited = ConfigurationValue::Object("LinkClass".to_string(), vec![
+        ("delay".to_string(), ConfigurationValue::Number(1.0)),
+        ("maximum_packet_size".to_string(), ConfigurationValue::Number(4.0)),
+    ]);
+    let results = simulate(limited);
+    let fragmentation = match &results.iter().find(|(key,_)|key=="fragmentation").expect("There were no fragmentation").1
+    {
+        ConfigurationValue::Object(name, fields) => {
+            assert_eq!(name, "Fragmentation");
+            fields.clone()
+        },
+        _ => panic!("The fragmentation should be an Object"),
+    };
+    let packets = field(&fragmentation, "packets");
+    assert!(packets > 0.0);
+    assert_eq!(packets, 4.0*field(&fragmentation, "packets_without_limits"), "Messages of 16 phits should be split in 4 packets");
+    assert_eq!(field(&fragmentation, "overhead"), 3.0);
+    assert_eq!(field(&fragmentation, "oversized_hops"), 0.0, "Minimal routes should not use oversized packets");
+    let injected = field(&results, "injected_load");
+    let accepted = field(&results, "accepted_load");
+    assert!((accepted - injected).abs() < 0.02, "accepted load {} differs from injected load {}", accepted, injected);
+    assert!(!simulate(fixed(1.0)).iter().any(|(key,_)|key=="fragmentation"), "Without limits there should be no fragmentation");
+}
@@ -0,0 +1,118 @@
+/*!
+    Tests for the lossy networks, in which the routers drop packets and the servers retransmit them.
+*/
+
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+
+/// A burst of uniform traffic on a complete graph whose routers drop the packets when the next buffer is full.
+fn lossy_burst() -> SimulationBuilder
+{
+    SimulationBuilder::new()
+        .warmup(0)
+        .measured(20000)
+        .topology(ConfigurationObject::new("Hamming").field("sides",vec![4]).field("servers_per_router",2))
+        .traffic(ConfigurationObject::new("Burst")
+            .field("pattern",ConfigurationObject::new("Uniform"))
+            .field("tasks",8)
+            .field("messages_per_task",20)
+            .field("message_size",32))
+        .router(ConfigurationObject::new("Basic")
+            .field("virtual_channels",1)
+            .field("virtual_channel_policies",vec![ConfigurationObject::new("EnforceFlowControl"),ConfigurationObject::new("Random")])
+            .field("buffer_size",16)
+            .field("output_buffer_size",16)
+            .field("bubble",false)
+            .field("flit_size",16)
+            .field("allow_request_busy_port",true)
+            .field("intransit_priority",false)
+            .field("neglect_busy_output",false)
+            .field("output_prioritize_lowest_label",false)
+            .field("drop_when_full",true))
+        .routing(ConfigurationObject::new("Shortest"))
+        .link_delays(&[1,1])
+}
+
+/// Gets the value of a field of the `lossy` results.
+fn lossy_field(simulation:&Simulation, name:&str) -> f64
+{
+    let mut lossy = None;
+    match_object_panic!( &simulation.get_simulation_results(), "Result", value,
+        "lossy" => lossy = Some(value.clone()),
+        _ => (),
+    );
+    match lossy
+    {
+        Some(ConfigurationValue::Object(ref object_name,ref fields)) if object_name=="Lossy" =>
+            fields.iter().find(|(key,_)|key==name).unwrap_or_else(||panic!("There were no {} in the lossy results",name)).1.as_f64().expect("bad lossy statistic"),
+        _ => panic!("There were no lossy results"),
+    }
+}
+
+/// Runs the burst retransmitting after `timeout` cycles, returning the simulation.
+fn run_with_timeout(plugs:&Plugs, timeout:usize) -> Simulation<'_>
+{
+    let mut simulation = lossy_burst()
+        .option("lossy",ConfigurationObject::new("Lossy").field("retransmission_timeout",timeout))
+        .build(plugs).unwrap();
+    simulation.run();
+    simulation
+}
+
+/// The dropped packets are retransmitted until all the messages of the burst are delivered.
+#[test]
+fn retransmission_completes_burst()
+{
+    let plugs = Plugs::default();
+    let simulation = run_with_timeout(&plugs,200);
+    let dropped = lossy_field(&simulation,"dropped_packets");
+    assert!(dropped>0.0,"Some packets should have been dropped");
+    let needed = lossy_field(&simulation,"retransmitted_packets") - lossy_field(&simulation,"spurious_retransmissions");
+    assert!(needed>0.0 && needed<=dropped,"{} retransmissions for {} dropped packets",needed,dropped);
+    assert_eq!(lossy_field(&simulation,"lost_packets"),0.0);
+    assert!(simulation.results().cycle<20000,"The burst should have been completed");
+}
+
+/// A short timeout retransmits packets that were only delayed, whose copies are discarded at the destination.
+/// A long timeout delays the recovery of the dropped packets.
+#[test]
+fn retransmission_timeout_tradeoff()
+{
+    let plugs = Plugs::default();
+    let short = run_with_timeout(&plugs,10);
+    assert!(lossy_field(&short,"spurious_retransmissions")>0.0,"A short timeout should retransmit spuriously");
+    assert!(lossy_field(&short,"duplicate_packets")>0.0,"A short timeout should deliver duplicates");
+    assert!(short.results().cycle<20000,"The burst should have been completed");
+    let medium = run_with_timeout(&plugs,200);
+    let long = run_with_timeout(&plugs,2000);
+    assert!(long.results().cycle<20000,"The burst should have been completed");
+    assert!(medium.results().cycle<long.results().cycle,"The long timeout should take longer, {} against {}",long.results().cycle,medium.results().cycle);
+}
+
+/// The routers must be able to drop packets for `lossy` to be configured.
+#[test]
+fn lossy_requires_dropping_routers()
+{
+    let plugs = Plugs::default();
+    let builder = lossy_burst()
+        .option("lossy",ConfigurationObject::new("Lossy").field("retransmission_timeout",200))
+        .router(ConfigurationObject::input_output_router(1,16,16,16));
+    assert!(builder.build(&plugs).is_err());
+    let builder = lossy_burst()
+        .option("lossy",ConfigurationObject::new("Lossy").field("retransmission_timeout",0));
+    assert!(builder.build(&plugs).is_err());
+}
+
+/// Without retransmission the dropped packets are lost and the burst never completes.
+#[test]
+fn lost_packets_without_retransmission()
+{
+    let plugs = Plugs::default();
+    let mut simulation = lossy_burst().build(&plugs).unwrap();
+    simulation.run();
+    let dropped = lossy_field(&simulation,"dropped_packets");
+    assert!(dropped>0.0,"Some packets should have been dropped");
+    assert_eq!(lossy_field(&simulation,"lost_packets"),dropped);
+    assert_eq!(simulation.results().cycle,20000);
+}
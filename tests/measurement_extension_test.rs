@@ -0,0 +1,85 @@
+/*!
+    Tests for the extension of the measured period until the accepted load is stable.
+*/
+
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+
+/// Simulates a 4x4 Hamming graph under uniform traffic without warmup, so the first estimates of the accepted load are still growing.
+/// Returns the accepted load and the fields of the `measurement_extension` result.
+fn simulate(measured:Time, extension:ConfigurationObject) -> (f64,Vec<(String,ConfigurationValue)>)
+{
+    let plugs = Plugs::default();
+    let mut simulation = SimulationBuilder::new()
+        .warmup(0)
+        .measured(measured)
+        .topology(ConfigurationObject::new("Hamming").field("sides",vec![4,4]).field("servers_per_router",1))
+        .traffic(ConfigurationObject::new("HomogeneousTraffic")
+            .field("pattern",ConfigurationObject::new("Uniform"))
+            .field("servers",16)
+            .field("load",0.3)
+            .field("message_size",16))
+        .router(ConfigurationObject::new("Preset").field("name","IQ-VCT-2VC"))
+        .routing(ConfigurationObject::new("Shortest"))
+        .link_delays(&[1,1,1])
+        .option("measurement_extension",extension)
+        .build(&plugs)
+        .unwrap();
+    simulation.run();
+    let mut fields = None;
+    match_object_panic!( &simulation.get_simulation_results(), "Result", value,
+        "measurement_extension" => match value
+        {
+            ConfigurationValue::Object(name,content) if name=="MeasurementExtension" => fields = Some(content.clone()),
+            _ => panic!("bad value for measurement_extension"),
+        },
+        _ => (),
+    );
+    (simulation.results().accepted_load,fields.expect("There was no measurement_extension in the result"))
+}
+
+fn field<'a>(fields:&'a [(String,ConfigurationValue)], name:&str) -> &'a ConfigurationValue
+{
+    &fields.iter().find(|(key,_)|key==name).unwrap_or_else(||panic!("missing field {}",name)).1
+}
+
+/// Without warmup the estimate changes too much during the first checks, so the measurement continues after `measured` until it is stable.
+#[test]
+fn extended_until_stable()
+{
+    let measured = 500;
+    let check_period = 500;
+    let tolerance = 0.01;
+    let stable_checks = 3;
+    let extension = ConfigurationObject::new("MeasurementExtension")
+        .field("check_period",check_period)
+        .field("tolerance",tolerance)
+        .field("stable_checks",stable_checks)
+        .field("maximum_measured",100000);
+    let (accepted_load,fields) = simulate(measured,extension);
+    let measured_cycles = field(&fields,"measured_cycles").as_time().unwrap();
+    let checks = field(&fields,"checks").as_usize().unwrap();
+    assert!(measured_cycles>measured,"The measurement was not extended, {} cycles",measured_cycles);
+    assert_eq!(measured_cycles%check_period,0,"The measurement should stop at a check");
+    assert_eq!(checks as Time,measured_cycles/check_period);
+    // The first check has no previous estimate to compare with.
+    assert!(checks>stable_checks,"Only {} checks",checks);
+    assert_eq!(field(&fields,"converged"),&ConfigurationValue::True);
+    assert!(field(&fields,"relative_change").as_f64().unwrap()<=tolerance);
+    assert!((accepted_load-0.3).abs()<0.02,"accepted load {}",accepted_load);
+}
+
+/// A tolerance that is never met stops the measurement at `maximum_measured`, flagged as not converged.
+#[test]
+fn stopped_at_maximum_measured()
+{
+    let extension = ConfigurationObject::new("MeasurementExtension")
+        .field("check_period",250)
+        .field("tolerance",0)
+        .field("maximum_measured",2000);
+    let (_accepted_load,fields) = simulate(500,extension);
+    assert_eq!(field(&fields,"measured_cycles").as_time().unwrap(),2000);
+    assert_eq!(field(&fields,"checks").as_usize().unwrap(),8);
+    assert_eq!(field(&fields,"converged"),&ConfigurationValue::False);
+}
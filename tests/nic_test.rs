@@ -0,0 +1,108 @@
+/*!
+    Tests for the model of the network interface of the servers.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// A complete graph of 4 routers with a server each, in which each server sends a message to the next one.
+/// Returns the cycle in which the burst is consumed and the `nic` results, if any.
+fn burst_with_nic(nic:Option<ConfigurationValue>) -> (f64,Option<ConfigurationValue>)
+{
+    let message_size = 16;
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let mut simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 1000,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        }),
+        traffic: create_burst_traffic(BurstTrafficBuilder{
+            pattern: create_shift_pattern(ShiftPatternBuilder{
+                sides: vec![ConfigurationValue::Number(4.0)],
+                shift: vec![ConfigurationValue::Number(1.0)],
+            }),
+            servers: 4,
+            messages_per_server: 1,
+            message_size,
+        }),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    if let (Some(nic), ConfigurationValue::Object(_, ref mut fields)) = (nic, &mut simulation_cv)
+    {
+        fields.push(("nic".to_string(), nic));
+    }
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    match simulation.get_simulation_results()
+    {
+        ConfigurationValue::Object(_, fields) => {
+            let cycle = fields.iter().find(|(key,_)|key=="cycle").expect("There were no cycle").1.as_f64().expect("bad cycle");
+            let nic = fields.into_iter().find(|(key,_)|key=="nic").map(|(_,value)|value);
+            (cycle, nic)
+        },
+        _ => panic!("The results should be an Object"),
+    }
+}
+
+/// Builds a `NIC` object with the given fields.
+fn nic(fields:&[(&str,f64)]) -> ConfigurationValue
+{
+    ConfigurationValue::Object("NIC".to_string(), fields.iter().map(|(name,value)|(name.to_string(),ConfigurationValue::Number(*value))).collect())
+}
+
+/// Gets a numeric field of an object.
+fn number(value:&ConfigurationValue, name:&str) -> f64
+{
+    match value
+    {
+        ConfigurationValue::Object(_, fields) => fields.iter().find(|(key,_)|key==name).unwrap_or_else(||panic!("There were no {}",name)).1.as_f64().expect("bad number"),
+        _ => panic!("{} should be an Object",value),
+    }
+}
+
+/// The setup latency delays every message, and a lower injection bandwidth serializes the messages more slowly.
+#[test]
+fn nic_latency_and_bandwidth()
+{
+    let (base_cycle, base_nic) = burst_with_nic(None);
+    assert!(base_nic.is_none());
+    // A NIC with the default parameters behaves as the plain server.
+    let (default_cycle, default_nic) = burst_with_nic(Some(nic(&[])));
+    assert_eq!(default_cycle, base_cycle);
+    let default_nic = default_nic.expect("the results should include the nic");
+    assert_eq!(number(&default_nic, "bandwidth_stalls"), 0.0);
+    assert_eq!(number(&default_nic, "out_of_order_packets"), 0.0);
+    let (dma_cycle, _) = burst_with_nic(Some(nic(&[("dma_latency",20.0)])));
+    assert_eq!(dma_cycle, base_cycle + 20.0);
+    // Injecting 16 phits at half bandwidth takes 15 more cycles.
+    let (bandwidth_cycle, bandwidth_nic) = burst_with_nic(Some(nic(&[("injection_bandwidth",0.5)])));
+    assert_eq!(bandwidth_cycle, base_cycle + 15.0);
+    assert!(number(&bandwidth_nic.unwrap(), "bandwidth_stalls") > 0.0);
+}
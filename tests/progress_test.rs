@@ -0,0 +1,49 @@
+/*!
+    Tests for the reporters of the progress of the simulations.
+*/
+
+use caminos_lib::*;
+use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+use caminos_lib::progress::read_progress_percent;
+
+/// A `ProgressFile` ends with the whole simulation done, and the reporters do not alter the results.
+#[test]
+fn progress_file_completed()
+{
+    let path = std::env::temp_dir().join("caminos_progress_test.progress");
+    let reporters = vec![
+        ConfigurationObject::new("ProgressFile").field("period",500).field("path",path.to_str().unwrap()),
+        ConfigurationObject::new("Console").field("period",300).field("eta",false),
+    ];
+    let builder = SimulationBuilder::new()
+        .warmup(500)
+        .measured(2000)
+        .topology(ConfigurationObject::new("Hamming").field("sides",vec![4]).field("servers_per_router",1))
+        .traffic(ConfigurationObject::new("HomogeneousTraffic")
+            .field("pattern",ConfigurationObject::new("Uniform"))
+            .field("servers",4)
+            .field("load",0.5)
+            .field("message_size",16))
+        .router(ConfigurationObject::new("Basic")
+            .field("virtual_channels",1)
+            .field("virtual_channel_policies",vec![ConfigurationObject::new("EnforceFlowControl"),ConfigurationObject::new("Random")])
+            .field("buffer_size",64)
+            .field("output_buffer_size",32)
+            .field("bubble",false)
+            .field("flit_size",16)
+            .field("allow_request_busy_port",true)
+            .field("intransit_priority",false)
+            .field("neglect_busy_output",false)
+            .field("output_prioritize_lowest_label",false))
+        .routing(ConfigurationObject::new("Shortest"))
+        .link_delays(&[1,1]);
+    let plugs = Plugs::default();
+    let mut default_simulation = builder.build(&plugs).unwrap();
+    default_simulation.run();
+    let mut simulation = builder.clone().option("progress_reporters",reporters).build(&plugs).unwrap();
+    simulation.run();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(read_progress_percent(&contents),Some(100.0));
+    assert_eq!(simulation.results().accepted_load,default_simulation.results().accepted_load);
+}
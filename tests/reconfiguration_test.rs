@@ -0,0 +1,65 @@
+/*!
+    Tests for the changes of the topology during the simulation.
+*/
+
+mod common;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// A link event of the given kind on the link at the port 1 of the router 0, towards the router 1 in a ring.
+fn link_event(kind:&str, cycle:usize) -> ConfigurationValue
+{
+    ConfigurationValue::Object(kind.to_string(), vec![
+        ("cycle".to_string(), ConfigurationValue::Number(cycle as f64)),
+        ("router".to_string(), ConfigurationValue::Number(0.0)),
+        ("port".to_string(), ConfigurationValue::Number(1.0)),
+    ])
+}
+
+/// Simulates a ring of 8 routers under uniform traffic with the given topology events and routing.
+fn simulate(events:Vec<ConfigurationValue>, routing:ConfigurationValue) -> Vec<(String,ConfigurationValue)>
+{
+    let topology = ConfigurationValue::Object("Torus".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(8.0)])),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    simulate_basic(BasicSimulationBuilder{
+        warmup: 500,
+        measured: 3000,
+        topology,
+        pattern: create_uniform_pattern(),
+        servers: 8,
+        load: 0.2,
+        virtual_channels: 2,
+        buffer_size: 64,
+        output_buffer_size: 32,
+        routing,
+        extra: vec![("events".to_string(), ConfigurationValue::Array(events))],
+    })
+}
+
+/// The traffic goes around a link that is down, and the events applied are reported.
+#[test]
+fn link_down_and_up()
+{
+    let fields = simulate(vec![link_event("LinkDown",1000), link_event("LinkUp",2500)], create_shortest_routing());
+    assert_eq!(field(&fields, "topology_events").as_f64().unwrap(), 2.0);
+    let accepted_load = field(&fields, "accepted_load").as_f64().unwrap();
+    assert!(accepted_load > 0.18, "The traffic should go around the link down, but the accepted load was {}", accepted_load);
+}
+
+/// A source routing computes its paths again when notified, so it keeps delivering after the link goes down.
+#[test]
+fn source_routing_recomputes_paths()
+{
+    let routing = ConfigurationValue::Object("SourceAdaptive".to_string(), vec![
+        ("routing".to_string(), ConfigurationValue::Object("KShortestPaths".to_string(), vec![
+            ("k".to_string(), ConfigurationValue::Number(2.0)),
+        ])),
+        ("amount".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    let fields = simulate(vec![link_event("LinkDown",0)], routing);
+    assert_eq!(field(&fields, "topology_events").as_f64().unwrap(), 1.0);
+    let accepted_load = field(&fields, "accepted_load").as_f64().unwrap();
+    assert!(accepted_load > 0.18, "The paths should avoid the link down, but the accepted load was {}", accepted_load);
+}
@@ -0,0 +1,138 @@
+/*!
+    Drift between the Basic and InputOutput router models.
+
+    The same small configuration is simulated with both routers and the differences of their main metrics are reported and checked against the expected bounds.
+    Both routers implement virtual cut-through with the same buffers, but the InputOutput router crosses an explicit crossbar with `crossbar_delay` cycles and an allocator,
+    while the Basic router moves the phits directly from the input buffers to the output buffers. Thus some drift is expected and the bounds describe how much.
+
+    Expected bounds, for a complete graph of 8 routers with 2 servers each under uniform traffic of 16-phit packets:
+    * Below saturation (loads 0.2 and 0.5) both routers accept all the offered load, so the `accepted_load` differs by less than 0.01.
+    * Below saturation the `average_packet_network_delay` differs by at most the crossbar traversal, which adds `crossbar_delay` cycles per router,
+      and by a further 20% of the delay of the Basic router, from the different arbitration.
+    * The `average_packet_hops` differs by less than 0.05 at any load, as both routers take the same minimal routes.
+    * Over saturation (load 0.9) the accepted loads differ by less than 0.05, as the allocators have different efficiencies.
+      The delays are not bounded there, since they grow with the queues of the servers. The InputOutput router typically shows a 50% greater delay.
+
+    Run with `cargo test --test router_drift_test -- --nocapture` to see the table of differences.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+const CROSSBAR_DELAY: usize = 2;
+
+/// The metrics compared between the two routers.
+const METRICS: [&str; 4] = ["accepted_load", "average_message_delay", "average_packet_network_delay", "average_packet_hops"];
+
+fn vcp() -> ConfigurationValue
+{
+    create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    })
+}
+
+/// Simulates the drift configuration with the given router at the given load and returns the values of the [METRICS].
+fn simulate(router: ConfigurationValue, load: f64) -> Vec<f64>
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(8.0)],
+        servers_per_router: 2,
+    };
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        load,
+        message_size: 16,
+    };
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 5,
+        warmup: 1000,
+        measured: 5000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_homogeneous_traffic(traffic_builder),
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.results();
+    vec![results.accepted_load, results.average_message_delay, results.average_packet_network_delay, results.average_packet_hops]
+}
+
+/// Simulates both routers at the given load, prints the table of differences, and returns the pairs `(basic,input_output)` for each metric.
+fn drift(load: f64) -> Vec<(f64, f64)>
+{
+    let basic = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp: vcp(),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    });
+    let input_output = create_input_output_router(InputOutputRouterBuilder{
+        virtual_channels: 2,
+        vcp: vcp(),
+        crossbar_delay: CROSSBAR_DELAY,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Random".to_string(), vec![("seed".to_string(), ConfigurationValue::Number(1.0))]),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    });
+    let basic = simulate(basic, load);
+    let input_output = simulate(input_output, load);
+    println!("load {}", load);
+    println!("{:<30} {:>12} {:>12} {:>12}", "metric", "Basic", "InputOutput", "difference");
+    for (index, name) in METRICS.iter().enumerate()
+    {
+        println!("{:<30} {:>12.4} {:>12.4} {:>12.4}", name, basic[index], input_output[index], input_output[index] - basic[index]);
+    }
+    basic.into_iter().zip(input_output).collect()
+}
+
+/// Below saturation both routers deliver all the load with similar delays.
+#[test]
+fn drift_below_saturation()
+{
+    for load in [0.2, 0.5]
+    {
+        let values = drift(load);
+        let (basic, input_output) = values[0];
+        assert!((basic - input_output).abs() < 0.01, "accepted_load differs more than expected at load {}: {} vs {}", load, basic, input_output);
+        let (basic_hops, input_output_hops) = values[3];
+        assert!((basic_hops - input_output_hops).abs() < 0.05, "average_packet_hops differs more than expected at load {}: {} vs {}", load, basic_hops, input_output_hops);
+        let (basic, input_output) = values[2];
+        //The crossbar is crossed at every router, which is one more than the hops.
+        let bound = CROSSBAR_DELAY as f64 * (basic_hops + 1.0) + 0.2 * basic;
+        assert!((basic - input_output).abs() <= bound, "average_packet_network_delay differs more than {} at load {}: {} vs {}", bound, load, basic, input_output);
+    }
+}
+
+/// Over saturation the accepted loads depend on the allocators, but stay close.
+#[test]
+fn drift_over_saturation()
+{
+    let values = drift(0.9);
+    let (basic, input_output) = values[0];
+    assert!((basic - input_output).abs() < 0.05, "accepted_load differs more than expected: {} vs {}", basic, input_output);
+    let (basic, input_output) = values[3];
+    assert!((basic - input_output).abs() < 0.05, "average_packet_hops differs more than expected: {} vs {}", basic, input_output);
+}
@@ -0,0 +1,103 @@
+/*!
+    Tests for networks built with different routers, selected by `RouterGroup`s.
+*/
+
+use caminos_lib::*;
+use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+
+/// A fat-tree with 4 leaf and 4 spine routers under uniform traffic of the given `load` with the given `router`.
+fn fat_tree(router:impl Into<config_parser::ConfigurationValue>, load:f64) -> SimulationBuilder
+{
+    SimulationBuilder::new()
+        .warmup(500)
+        .measured(2000)
+        .topology(ConfigurationObject::new("XGFT")
+            .field("height",1)
+            .field("down",vec![4])
+            .field("up",vec![4])
+            .field("servers_per_leaf",4))
+        .traffic(ConfigurationObject::new("HomogeneousTraffic")
+            .field("pattern",ConfigurationObject::new("Uniform"))
+            .field("servers",16)
+            .field("load",load)
+            .field("message_size",16))
+        .router(router)
+        .routing(ConfigurationObject::new("UpDown"))
+        .link_delays(&[1,1])
+}
+
+fn group(router:&str) -> ConfigurationObject
+{
+    ConfigurationObject::new("RouterGroup").field("router",ConfigurationObject::new("Preset").field("name",router))
+}
+
+/// Leaf and spine routers of different architectures deliver the offered load.
+#[test]
+fn leaf_and_spine_routers()
+{
+    let groups = vec![
+        group("IQ-VCT-2VC").field("role","leaf"),
+        group("IOQ-VCT-2VC").field("role","spine"),
+    ];
+    let plugs = Plugs::default();
+    let mut simulation = fat_tree(groups,0.3).build(&plugs).unwrap();
+    simulation.run();
+    let results = simulation.results();
+    assert!((results.accepted_load-0.3).abs()<0.05,"accepted load {}",results.accepted_load);
+    // The statistics of the routers are aggregated over each group.
+    match results.router_statistics
+    {
+        Some(config_parser::ConfigurationValue::Array(ref groups)) =>
+        {
+            let names:Vec<_> = groups.iter().map(|group|match group
+            {
+                config_parser::ConfigurationValue::Object(name,_) => name.as_str(),
+                _ => panic!("The statistics of a group should be an Object"),
+            }).collect();
+            assert_eq!(names,vec!["Basic","InputOutput"]);
+        },
+        ref other => panic!("The router statistics should be an array, not {:?}",other),
+    }
+}
+
+/// The groups may select routers by index range, and the first matching group is used.
+#[test]
+fn index_ranges_and_default_group()
+{
+    let groups = vec![
+        group("IQ-WH-2VC").field("routers",vec![0,2]),
+        group("IOQ-VCT-2VC").field("routers",vec![0,6]),
+        group("IQ-VCT-2VC"),
+    ];
+    let plugs = Plugs::default();
+    let mut simulation = fat_tree(groups,0.3).build(&plugs).unwrap();
+    simulation.run();
+    let accepted_load = simulation.results().accepted_load;
+    assert!((accepted_load-0.3).abs()<0.05,"accepted load {}",accepted_load);
+    // Without a group for the spine the simulation cannot be built.
+    let plugs = Plugs::default();
+    assert!(fat_tree(vec![group("IQ-VCT-1VC").field("role","leaf")],0.3).build(&plugs).is_err());
+}
+
+/// Spine routers in a slower clock domain delay the packets and, with the links to them, limit the throughput.
+#[test]
+fn slower_spine_clock_domain()
+{
+    let groups = |spine_divisor:u64| vec![
+        group("IQ-VCT-2VC").field("role","leaf"),
+        group("IQ-VCT-2VC").field("role","spine").field("frequency_divisor",spine_divisor),
+    ];
+    let plugs = Plugs::default();
+    let mut fast = fat_tree(groups(1),0.3).build(&plugs).unwrap();
+    fast.run();
+    let mut slow = fat_tree(groups(2),0.3).build(&plugs).unwrap();
+    slow.run();
+    let (fast,slow) = (fast.results(),slow.results());
+    assert!((slow.accepted_load-0.3).abs()<0.05,"accepted load {}",slow.accepted_load);
+    assert!(slow.average_message_delay>fast.average_message_delay,"delay {} with the slower spine and {} without it",slow.average_message_delay,fast.average_message_delay);
+    // The links to the spine carry a phit every 2 cycles, which cannot hold 3/4 of the load of 0.9 that crosses them.
+    let mut saturated = fat_tree(groups(2),0.9).build(&plugs).unwrap();
+    saturated.run();
+    let accepted_load = saturated.results().accepted_load;
+    assert!(accepted_load<0.7,"accepted load {}",accepted_load);
+}
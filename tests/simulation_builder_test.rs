@@ -0,0 +1,113 @@
+/*!
+    Tests of the construction of simulations from Rust code with caminos_lib::builder::SimulationBuilder.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::builder::{self,ConfigurationObject};
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// A simulation over a Hamming graph with 4 servers, missing only its routing.
+fn builder_without_routing() -> builder::SimulationBuilder
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 1,
+    };
+    let traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 4,
+        load: 0.3,
+        message_size: 16,
+    };
+    builder::SimulationBuilder::new()
+        .random_seed(2)
+        .warmup(200)
+        .measured(1000)
+        .topology(create_hamming_topology(hamming_builder))
+        .traffic(create_homogeneous_traffic(traffic_builder))
+        .router(ConfigurationObject::basic_router(1,64,32,16))
+        .link_delays(&[1,1])
+}
+
+#[test]
+fn build_and_run()
+{
+    let plugs = Plugs::default();
+    let builder = builder_without_routing();
+    // The routing is missing.
+    assert!(builder.build(&plugs).is_err());
+    let builder = builder.routing(ConfigurationObject::shortest()).option("server_queue_size",10).option("server_queue_size",30);
+    let configuration = builder.configuration().unwrap();
+    let fields = object(&configuration,"Configuration");
+    let queue_sizes:Vec<_> = fields.iter().filter(|(key,_)|key=="server_queue_size").collect();
+    assert_eq!(queue_sizes.len(),1);
+    assert_eq!(queue_sizes[0].1,ConfigurationValue::Number(30.0));
+    let mut simulation = builder.build(&plugs).unwrap();
+    simulation.run();
+    let results = simulation.results();
+    assert_eq!(results.cycle,1200);
+    assert!((results.accepted_load-0.3).abs()<0.05,"accepted load {}",results.accepted_load);
+}
+
+#[test]
+fn typed_constructors()
+{
+    let plugs = Plugs::default();
+    let builder = builder::SimulationBuilder::new()
+        .warmup(200)
+        .measured(500)
+        .topology(ConfigurationObject::torus(&[4,4],1))
+        .traffic(ConfigurationObject::homogeneous_traffic(ConfigurationObject::uniform(),16,0.2,16))
+        .router(ConfigurationObject::input_output_router(2,64,32,16))
+        .routing(ConfigurationObject::dor(&[0,1]))
+        .link_delays(&[1,1,1]);
+    let mut simulation = builder.build(&plugs).unwrap();
+    simulation.run();
+    assert!((simulation.results().accepted_load-0.2).abs()<0.05);
+    let builder = builder
+        .topology(ConfigurationObject::hamming(&[4,4],1))
+        .router(ConfigurationObject::basic_router(2,64,32,16))
+        .routing(ConfigurationObject::valiant(ConfigurationObject::shortest(),ConfigurationObject::shortest()));
+    let mut simulation = builder.build(&plugs).unwrap();
+    simulation.run();
+    assert!((simulation.results().accepted_load-0.2).abs()<0.05);
+}
+
+#[test]
+fn missing_cycles_and_dedicated_options_are_rejected()
+{
+    let plugs = Plugs::default();
+    let full = builder_without_routing().routing(ConfigurationObject::shortest());
+    assert!(full.build(&plugs).is_ok());
+    let configuration = builder_without_routing().routing(ConfigurationObject::shortest()).configuration().unwrap();
+    let fields = object(&configuration,"Configuration");
+    // Every mandatory field must be given to its own method.
+    for missing in ["warmup","measured"]
+    {
+        let mut builder = builder::SimulationBuilder::new();
+        for (name,value) in fields.iter().filter(|(name,_)|name!=missing)
+        {
+            builder = match name.as_str()
+            {
+                "warmup" => builder.warmup(value.as_time().unwrap()),
+                "measured" => builder.measured(value.as_time().unwrap()),
+                "topology" => builder.topology(value.clone()),
+                "traffic" => builder.traffic(value.clone()),
+                "router" => builder.router(value.clone()),
+                "routing" => builder.routing(value.clone()),
+                "link_classes" => builder.link_delays(&[1,1]),
+                _ => builder,
+            };
+        }
+        let error = builder.configuration().err().unwrap_or_else(||panic!("a configuration without {} was built",missing));
+        assert!(error.message.as_ref().is_some_and(|message|message.contains(missing)),"the error does not mention {}: {}",missing,error);
+    }
+    for dedicated in ["warmup","topology","random_seed"]
+    {
+        let builder = builder_without_routing().routing(ConfigurationObject::shortest()).option(dedicated,1);
+        let error = builder.build(&plugs).err().unwrap_or_else(||panic!("a simulation with option {} was built",dedicated));
+        assert!(error.message.as_ref().is_some_and(|message|message.contains(dedicated)),"the error does not mention {}: {}",dedicated,error);
+    }
+}
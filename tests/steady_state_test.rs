@@ -0,0 +1,76 @@
+/*!
+    Tests for the detection of the steady state during the warmup.
+*/
+
+mod common;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Simulates a 4x4 torus under uniform traffic with the given warmup and `steady_state_detection`.
+fn simulate(warmup:usize, detection:ConfigurationValue) -> Vec<(String,ConfigurationValue)>
+{
+    let topology = ConfigurationValue::Object("Torus".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    simulate_basic(BasicSimulationBuilder{
+        warmup,
+        measured: 4000,
+        topology,
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        load: 0.4,
+        virtual_channels: 2,
+        buffer_size: 32,
+        output_buffer_size: 16,
+        routing: create_dor_routing(vec![0,1]),
+        extra: vec![("steady_state_detection".to_string(), detection)],
+    })
+}
+
+/// Gets the `steady_state` object of the results.
+fn steady_state(fields:&[(String,ConfigurationValue)]) -> &[(String,ConfigurationValue)]
+{
+    match field(fields, "steady_state")
+    {
+        ConfigurationValue::Object(name, steady_state) => {
+            assert_eq!(name, "SteadyState");
+            steady_state
+        },
+        _ => panic!("The steady state should be an Object"),
+    }
+}
+
+/// A warmup of a single window cannot reach the steady state, and without `maximum_warmup` it is only flagged.
+#[test]
+fn short_warmup_flagged()
+{
+    let fields = simulate(200, ConfigurationValue::Object("SteadyStateDetection".to_string(), vec![
+        ("window".to_string(), ConfigurationValue::Number(200.0)),
+        ("tolerance".to_string(), ConfigurationValue::Number(0.1)),
+    ]));
+    let steady_state = steady_state(&fields);
+    assert_eq!(field(steady_state, "reached"), &ConfigurationValue::False);
+    assert_eq!(field(steady_state, "warmup_cycles").as_f64().unwrap(), 200.0);
+    assert_eq!(field(&fields, "cycle").as_f64().unwrap(), 4200.0);
+}
+
+/// With `maximum_warmup` the warmup is extended until the accepted load stabilizes, and the measurement is then stable.
+#[test]
+fn warmup_extended()
+{
+    let fields = simulate(200, ConfigurationValue::Object("SteadyStateDetection".to_string(), vec![
+        ("window".to_string(), ConfigurationValue::Number(200.0)),
+        ("tolerance".to_string(), ConfigurationValue::Number(0.1)),
+        ("maximum_warmup".to_string(), ConfigurationValue::Number(10000.0)),
+    ]));
+    let steady_state = steady_state(&fields);
+    assert_eq!(field(steady_state, "reached"), &ConfigurationValue::True);
+    let warmup = field(steady_state, "warmup_cycles").as_f64().unwrap();
+    assert!(warmup > 200.0 && warmup < 10000.0, "The warmup was extended to {}", warmup);
+    assert_eq!(field(steady_state, "reached_cycle").as_f64().unwrap(), warmup);
+    assert_eq!(field(&fields, "cycle").as_f64().unwrap(), warmup + 4000.0);
+    assert_eq!(field(steady_state, "stable_measurement"), &ConfigurationValue::True);
+    let first = field(steady_state, "first_half_accepted_load").as_f64().unwrap();
+    assert!((first - 0.4).abs() < 0.05, "The accepted load should match the offered one, got {}", first);
+}
@@ -256,4 +256,1179 @@ fn sum_traffic_test()
         _ => (),
     );
 
-}
\ No newline at end of file
+}
+///Replays a trace in which each message depends on the reception of the previous one, in both file formats.
+///The CSV trace begins with a comment before its header.
+#[test]
+fn trace_replay_traffic_test()
+{
+    let traces = [
+        ("caminos_trace_replay_test.csv", "# A ping-pong between two tasks.\n\nsource,destination,size,cycle,dependencies\n0,1,16,10,\n1,0,16,0,0\n0,1,16,0,1\n"),
+        ("caminos_trace_replay_test.json", "{\"source\":0, \"destination\":1, \"size\":16, \"cycle\":10}\n{\"source\":1, \"destination\":0, \"size\":16, \"cycle\":0, \"dependencies\":[0]}\n{\"source\":0, \"destination\":1, \"size\":16, \"cycle\":0, \"dependencies\":[1]}\n"),
+    ];
+    let mut completion_cycles = vec![];
+    for (name,content) in traces.iter()
+    {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, content).expect("could not write the trace");
+        let hamming_builder = HammingBuilder{
+            sides: vec![ConfigurationValue::Number(2.0)],
+            servers_per_router: 1,
+        };
+        let traffic = ConfigurationValue::Object("TraceReplay".to_string(), vec![
+            ("tasks".to_string(), ConfigurationValue::Number(2.0)),
+            ("filename".to_string(), ConfigurationValue::Literal(path.to_str().expect("bad path").to_string())),
+        ]);
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![]),
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 1,
+            vcp,
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: 16,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        let simulation_cv = create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 0,
+            measured: 1000,
+            topology: create_hamming_topology(hamming_builder),
+            traffic,
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        });
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&simulation_cv, &plugs);
+        simulation.run();
+        let results = simulation.get_simulation_results();
+        std::fs::remove_file(&path).ok();
+        let mut statistics = None;
+        match_object_panic!( &results, "Result", value,
+            "traffic_extra_statistics" => statistics = Some(value.clone()),
+            _ => (),
+        );
+        let mut completion_cycle = None;
+        match_object_panic!( &statistics.expect("There were no traffic statistics"), "TraceReplayStatistics", value,
+            "events" => assert_eq!(value.as_f64().expect("events data"), 3.0, "Events"),
+            "consumed_events" => assert_eq!(value.as_f64().expect("consumed events data"), 3.0, "Consumed events"),
+            "completion_cycle" => completion_cycle = Some(value.as_f64().expect("completion cycle data")),
+        );
+        let completion_cycle = completion_cycle.expect("There were no completion_cycle");
+        // Three messages of 16 phits in sequence starting at cycle 10.
+        assert!(completion_cycle >= 10.0 + 3.0*16.0, "Completion cycle {} too early", completion_cycle);
+        assert!(completion_cycle < 1000.0, "The trace did not complete");
+        completion_cycles.push(completion_cycle);
+    }
+    assert_eq!(completion_cycles[0], completion_cycles[1], "Both formats should give the same result");
+}
+
+#[test]
+fn task_graph_traffic_test()
+{
+    // A fork from the task 0 to the tasks 1 and 2, joined back in the task 0, preceded by a local node.
+    let graph = "# fork and join\nnode,0,20\nnode,0,100\nnode,1,50\nnode,2,80\nnode,0,10\nedge,0,1,0\nedge,1,2,64\nedge,1,3,64\nedge,2,4,16\nedge,3,4,16\n";
+    let path = std::env::temp_dir().join("caminos_task_graph_test.csv");
+    std::fs::write(&path, graph).expect("could not write the task graph");
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(3.0)],
+        servers_per_router: 1,
+    };
+    let traffic = ConfigurationValue::Object("TaskGraph".to_string(), vec![
+        ("tasks".to_string(), ConfigurationValue::Number(3.0)),
+        ("filename".to_string(), ConfigurationValue::Literal(path.to_str().expect("bad path").to_string())),
+    ]);
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 1000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    std::fs::remove_file(&path).ok();
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "traffic_extra_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    let mut makespan = None;
+    match_object_panic!( &statistics.expect("There were no traffic statistics"), "TaskGraphStatistics", value,
+        "nodes" => assert_eq!(value.as_f64().expect("nodes data"), 5.0, "Nodes"),
+        "edges" => assert_eq!(value.as_f64().expect("edges data"), 5.0, "Edges"),
+        "computed_nodes" => assert_eq!(value.as_f64().expect("computed nodes data"), 5.0, "Computed nodes"),
+        "critical_path" => assert_eq!(value.as_f64().expect("critical path data"), 210.0, "Critical path"),
+        "makespan" => makespan = Some(value.as_f64().expect("makespan data")),
+    );
+    let makespan = makespan.expect("There were no makespan");
+    // The critical path plus a message of 64 phits and another of 16 phits.
+    assert!(makespan >= 210.0 + 64.0 + 16.0, "Makespan {} too early", makespan);
+    assert!(makespan < 1000.0, "The graph did not complete");
+}
+
+#[test]
+fn collective_algorithms_test()
+{
+    // (traffic, algorithm, tasks, expected phases)
+    let cases = [
+        ("ScatterReduce", "Ring", 6, 5),
+        ("AllGather", "Ring", 6, 5),
+        ("ScatterReduce", "RecursiveHalving", 8, 3),
+        ("AllGather", "RecursiveDoubling", 8, 3),
+        ("ScatterReduce", "Bruck", 6, 3),
+        ("AllGather", "Bruck", 6, 3),
+        ("All2All", "Pairwise", 6, 5),
+        ("All2All", "Ring", 6, 5),
+        ("All2All", "Bruck", 6, 3),
+    ];
+    for (name, algorithm, tasks, phases) in cases.iter()
+    {
+        for chunk_size in [None, Some(8.0)]
+        {
+            let hamming_builder = HammingBuilder{
+                sides: vec![ConfigurationValue::Number(*tasks as f64)],
+                servers_per_router: 1,
+            };
+            let mut traffic_fields = vec![
+                ("tasks".to_string(), ConfigurationValue::Number(*tasks as f64)),
+                ("data_size".to_string(), ConfigurationValue::Number(16.0 * *tasks as f64)),
+                ("algorithm".to_string(), ConfigurationValue::Literal(algorithm.to_string())),
+            ];
+            if let Some(chunk_size) = chunk_size
+            {
+                traffic_fields.push(("chunk_size".to_string(), ConfigurationValue::Number(chunk_size)));
+            }
+            let traffic = ConfigurationValue::Object(name.to_string(), traffic_fields);
+            let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+                policies: vec![
+                    ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                    ConfigurationValue::Object("Random".to_string(), vec![]),
+                ]
+            });
+            let router_args = BasicRouterBuilder{
+                virtual_channels: 1,
+                vcp,
+                buffer_size: 64,
+                bubble: ConfigurationValue::False,
+                flit_size: 16,
+                allow_request_busy_port: ConfigurationValue::True,
+                intransit_priority: ConfigurationValue::False,
+                output_buffer_size: 32,
+                neglect_busy_outport: ConfigurationValue::False,
+                output_prioritize_lowest_label: ConfigurationValue::False,
+            };
+            let simulation_cv = create_simulation(SimulationBuilder{
+                random_seed: 1,
+                warmup: 0,
+                measured: 2000,
+                topology: create_hamming_topology(hamming_builder),
+                traffic,
+                router: create_basic_router(router_args),
+                maximum_packet_size: 16,
+                general_frequency_divisor: 1,
+                routing: create_shortest_routing(),
+                link_classes: create_link_classes(),
+            });
+            let plugs = Plugs::default();
+            let mut simulation = Simulation::new(&simulation_cv, &plugs);
+            simulation.run();
+            let results = simulation.get_simulation_results();
+            let mut statistics = None;
+            match_object_panic!( &results, "Result", value,
+                "traffic_extra_statistics" => statistics = Some(value.clone()),
+                _ => (),
+            );
+            let mut completion_cycles = None;
+            match_object_panic!( &statistics.expect("There were no traffic statistics"), "CollectiveScheduleStatistics", value,
+                "phases" => assert_eq!(value.as_f64().expect("phases data"), *phases as f64, "Phases of {} {}", name, algorithm),
+                "phase_completion_cycles" => completion_cycles = Some(value.as_array().expect("completion cycles data").iter()
+                    .map(|cycle|cycle.as_f64().unwrap_or_else(|_|panic!("{} {} did not complete", name, algorithm))).collect::<Vec<f64>>()),
+            );
+            let completion_cycles = completion_cycles.expect("There were no phase_completion_cycles");
+            assert_eq!(completion_cycles.len(), *phases);
+            assert!(completion_cycles.windows(2).all(|w|w[0]<w[1]), "The phases of {} {} should complete in order: {:?}", name, algorithm, completion_cycles);
+        }
+    }
+}
+
+/// The messages of a collective are tagged with their phase and the collective given by `Tagged`, and the message statistics can be grouped by them.
+#[test]
+fn message_tags_test()
+{
+    use caminos_lib::config_parser::Expr;
+    use std::rc::Rc;
+    let tasks = 6;
+    let phases = 5;
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(tasks as f64)],
+        servers_per_router: 1,
+    };
+    let collective = ConfigurationValue::Object("AllGather".to_string(), vec![
+        ("tasks".to_string(), ConfigurationValue::Number(tasks as f64)),
+        ("data_size".to_string(), ConfigurationValue::Number(16.0 * tasks as f64)),
+        ("algorithm".to_string(), ConfigurationValue::Literal("Ring".to_string())),
+    ]);
+    let traffic = ConfigurationValue::Object("Tagged".to_string(), vec![
+        ("traffic".to_string(), collective),
+        ("collective".to_string(), ConfigurationValue::Number(7.0)),
+    ]);
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let mut simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 2000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    let tag_field = |name:&str| ConfigurationValue::Expression(Expr::Member(Rc::new(Expr::Ident("tag".to_string())), name.to_string()));
+    let definition = ConfigurationValue::Array(vec![
+        ConfigurationValue::Array(vec![tag_field("collective"), tag_field("phase")]),
+        ConfigurationValue::Array(vec![ConfigurationValue::Expression(Expr::Ident("delay".to_string()))]),
+    ]);
+    if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+    {
+        fields.push(("statistics_message_definitions".to_string(), ConfigurationValue::Array(vec![definition])));
+    }
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut bins = None;
+    match_object_panic!( &results, "Result", value,
+        "message_defined_statistics" => bins = Some(value.as_array().expect("message statistics data")[0].as_array().expect("definition data").clone()),
+        _ => (),
+    );
+    let bins = bins.expect("There were no message_defined_statistics");
+    assert_eq!(bins.len(), phases, "There should be a bin for each phase");
+    let mut seen_phases = vec![];
+    for bin in bins
+    {
+        match_object_panic!( &bin, "MessageBin", value,
+            "key" => {
+                let key = value.as_array().expect("key data");
+                assert_eq!(key[0].as_f64().expect("collective data"), 7.0, "The collective must be the one of Tagged");
+                seen_phases.push(key[1].as_f64().expect("phase data") as usize);
+            },
+            "count" => assert_eq!(value.as_f64().expect("count data"), tasks as f64, "Each task sends a message per phase"),
+            "average" => (),
+        );
+    }
+    seen_phases.sort_unstable();
+    assert_eq!(seen_phases, (0..phases).collect::<Vec<usize>>());
+}
+
+/// Two homogeneous traffics of different classes, each with its own virtual channel, must be reported separately in `class_statistics`.
+#[test]
+fn traffic_class_test()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 1,
+    };
+    let loads = [0.2, 0.1];
+    let list = loads.iter().enumerate().map(|(class, &load)|{
+        let mut traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 4,
+            load,
+            message_size: 16,
+        });
+        if let ConfigurationValue::Object(_, ref mut fields) = traffic
+        {
+            fields.push(("class".to_string(), ConfigurationValue::Number(class as f64)));
+        }
+        traffic
+    }).collect();
+    let traffic = ConfigurationValue::Object("TrafficSum".to_string(), vec![
+        ("list".to_string(), ConfigurationValue::Array(list)),
+        ("tasks".to_string(), ConfigurationValue::Number(4.0)),
+        ("server_task_isolation".to_string(), ConfigurationValue::False),
+    ]);
+    let allowed = |vc:f64| ConfigurationValue::Object("ArgumentVC".to_string(), vec![
+        ("allowed".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(vc)])),
+    ]);
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("MapClass".to_string(), vec![
+                ("class_to_policy".to_string(), ConfigurationValue::Array(vec![allowed(0.0), allowed(1.0)])),
+            ]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 2,
+        warmup: 500,
+        measured: 5000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.results();
+    assert_eq!(results.classes.len(), 2, "There should be statistics for each class");
+    let total: f64 = results.classes.iter().map(|class|class.accepted_load).sum();
+    assert!((total - results.accepted_load).abs() < 1e-9, "The accepted loads of the classes must add to the total");
+    for (class, &load) in loads.iter().enumerate()
+    {
+        let accepted = results.classes[class].accepted_load;
+        assert!((accepted - load).abs() < 0.03, "class {} accepted {} instead of {}", class, accepted, load);
+    }
+    assert_eq!(results.virtual_channel_usage.len(), 2, "Each class should use its own virtual channel");
+    let mut class_statistics = None;
+    match_object_panic!( &simulation.get_simulation_results(), "Result", value,
+        "class_statistics" => class_statistics = Some(value.as_array().expect("class statistics data").len()),
+        _ => (),
+    );
+    assert_eq!(class_statistics, Some(2));
+}
+
+/// Two homogeneous traffics sharing the injection opportunities with weights 3 and 1 inject their loads in that proportion, reported in `sub_traffic_statistics`.
+#[test]
+fn weighted_sum_traffic_test()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 1,
+    };
+    let load = 0.8;
+    let list = (0..2).map(|_|create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 4,
+        load,
+        message_size: 16,
+    })).collect();
+    let traffic = ConfigurationValue::Object("TrafficSum".to_string(), vec![
+        ("list".to_string(), ConfigurationValue::Array(list)),
+        ("tasks".to_string(), ConfigurationValue::Number(4.0)),
+        ("weights".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(3.0), ConfigurationValue::Number(1.0)])),
+    ]);
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 3,
+        warmup: 500,
+        measured: 10000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.results();
+    assert_eq!(results.sub_traffics.len(), 2, "There should be statistics for each sub-traffic");
+    let total: f64 = results.sub_traffics.iter().map(|sub|sub.injected_load).sum();
+    assert!((total - results.injected_load).abs() < 1e-9, "The injected loads of the sub-traffics must add to the total");
+    for (index, share) in [0.75, 0.25].iter().enumerate()
+    {
+        let injected = results.sub_traffics[index].injected_load;
+        assert!((injected - load*share).abs() < 0.04, "sub-traffic {} injected {} instead of {}", index, injected, load*share);
+        assert!(results.sub_traffics[index].average_message_delay > 0.0);
+    }
+    let mut sub_traffic_statistics = None;
+    match_object_panic!( &simulation.get_simulation_results(), "Result", value,
+        "sub_traffic_statistics" => sub_traffic_statistics = Some(value.as_array().expect("sub-traffic statistics data").len()),
+        _ => (),
+    );
+    assert_eq!(sub_traffic_statistics, Some(2));
+}
+
+/// Simulates a `TrafficSum` with named sub-traffics, returning the results and the `sub_traffic_statistics` of the output.
+fn simulate_named_sum(list:Vec<ConfigurationValue>, names:Vec<&str>) -> (results::SimulationResults, Vec<ConfigurationValue>)
+{
+    let traffic = ConfigurationValue::Object("TrafficSum".to_string(), vec![
+        ("list".to_string(), ConfigurationValue::Array(list)),
+        ("tasks".to_string(), ConfigurationValue::Number(4.0)),
+        ("server_task_isolation".to_string(), ConfigurationValue::False),
+        ("names".to_string(), ConfigurationValue::Array(names.into_iter().map(|name|ConfigurationValue::Literal(name.to_string())).collect())),
+    ]);
+    let simulation_cv = create_basic_simulation(BasicSimulationBuilder{
+        warmup: 1000,
+        measured: 10000,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        }),
+        pattern: create_uniform_pattern(),
+        servers: 4,
+        load: 0.0,
+        virtual_channels: 1,
+        buffer_size: 64,
+        output_buffer_size: 32,
+        routing: create_shortest_routing(),
+        extra: vec![
+            ("random_seed".to_string(), ConfigurationValue::Number(5.0)),
+            ("traffic".to_string(), traffic),
+        ],
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let sub_traffic_statistics = field(object(&simulation.get_simulation_results(), "Result"), "sub_traffic_statistics").as_array().expect("sub-traffic statistics data").clone();
+    (simulation.results(), sub_traffic_statistics)
+}
+
+/// A victim traffic is measured apart from an aggressor background traffic, with its statistics labelled by its name.
+#[test]
+fn victim_aggressor_sum_traffic_test()
+{
+    let victim = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 4,
+        load: 0.1,
+        message_size: 16,
+    });
+    let aggressor = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: ConfigurationValue::Object("Hotspots".to_string(), vec![
+            ("destinations".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)])),
+        ]),
+        servers: 4,
+        load: 0.25,
+        message_size: 16,
+    });
+    let (alone, _) = simulate_named_sum(vec![victim.clone()], vec!["victim"]);
+    let (results, statistics) = simulate_named_sum(vec![victim, aggressor], vec!["victim", "aggressor"]);
+    let names: Vec<String> = statistics.iter().map(|sub|{
+        let mut name = None;
+        match_object_panic!( sub, "SubTrafficStatistics", value,
+            "name" => name = Some(value.as_str().expect("bad name").to_string()),
+            _ => (),
+        );
+        name.expect("The sub-traffic statistics should have a name")
+    }).collect();
+    assert_eq!(names, vec!["victim", "aggressor"]);
+    assert_eq!(results.sub_traffic_names, names);
+    let victim_results = &results.sub_traffics[0];
+    assert!((victim_results.accepted_load - 0.1).abs() < 0.02, "The victim should deliver its load, got {}", victim_results.accepted_load);
+    assert!(victim_results.average_message_delay > alone.sub_traffics[0].average_message_delay,
+        "The aggressor should increase the delay of the victim from {} but got {}", alone.sub_traffics[0].average_message_delay, victim_results.average_message_delay);
+}
+
+/// Records the injections of a random traffic and replays them with another router, which must generate the same messages in the same cycles.
+#[test]
+fn injection_record_replay_test()
+{
+    let recorded = std::env::temp_dir().join("caminos_injection_record_test.csv");
+    let replayed = std::env::temp_dir().join("caminos_injection_replay_test.csv");
+    let simulate = |traffic:ConfigurationValue, buffer_size:usize, record:&std::path::Path|
+    {
+        let hamming_builder = HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        };
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![]),
+            ]
+        });
+        let router_args = BasicRouterBuilder{
+            virtual_channels: 1,
+            vcp,
+            buffer_size,
+            bubble: ConfigurationValue::False,
+            flit_size: 16,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::False,
+            output_buffer_size: buffer_size/2,
+            neglect_busy_outport: ConfigurationValue::False,
+            output_prioritize_lowest_label: ConfigurationValue::False,
+        };
+        let mut simulation_cv = create_simulation(SimulationBuilder{
+            random_seed: 1,
+            warmup: 0,
+            measured: 500,
+            topology: create_hamming_topology(hamming_builder),
+            traffic,
+            router: create_basic_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_shortest_routing(),
+            link_classes: create_link_classes(),
+        });
+        if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+        {
+            fields.push(("injection_record_file".to_string(), ConfigurationValue::Literal(record.to_str().expect("bad path").to_string())));
+        }
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&simulation_cv, &plugs);
+        simulation.run();
+        let results = simulation.get_simulation_results();
+        let mut messages = None;
+        match_object_panic!( &results, "Result", value,
+            "injection_record" => match_object_panic!( value, "InjectionRecordFile", field,
+                "messages" => messages = Some(field.as_f64().expect("messages data")),
+                "filename" => (),
+            ),
+            _ => (),
+        );
+        messages.expect("There were no injection_record")
+    };
+    let random_traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 4,
+        load: 0.2,
+        message_size: 16,
+    });
+    let recorded_messages = simulate(random_traffic, 64, &recorded);
+    assert!(recorded_messages > 0.0, "There were no messages recorded");
+    let replay_traffic = ConfigurationValue::Object("TraceReplay".to_string(), vec![
+        ("tasks".to_string(), ConfigurationValue::Number(4.0)),
+        ("filename".to_string(), ConfigurationValue::Literal(recorded.to_str().expect("bad path").to_string())),
+    ]);
+    let replayed_messages = simulate(replay_traffic, 32, &replayed);
+    assert_eq!(recorded_messages, replayed_messages, "The replay should generate the same number of messages");
+    let recorded_content = std::fs::read_to_string(&recorded).expect("could not read the record");
+    let replayed_content = std::fs::read_to_string(&replayed).expect("could not read the replay");
+    std::fs::remove_file(&recorded).ok();
+    std::fs::remove_file(&replayed).ok();
+    assert_eq!(recorded_content, replayed_content, "The replay should generate the same injection sequence");
+}
+
+///Iterations of halo exchange in a periodic 2x2x2 grid, each one lasting at least the computation and the serialization of the halos.
+#[test]
+fn stencil3d_traffic_test()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let (iterations, compute_cycles, message_size) = (4, 100, 16);
+    let traffic = ConfigurationValue::Object("Stencil3D".to_string(), vec![
+        ("process_grid".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(2.0);3])),
+        ("iterations".to_string(), ConfigurationValue::Number(iterations as f64)),
+        ("message_size".to_string(), ConfigurationValue::Number(message_size as f64)),
+        ("compute_cycles".to_string(), ConfigurationValue::Number(compute_cycles as f64)),
+    ]);
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 5000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "traffic_extra_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    let mut iteration_times = None;
+    match_object_panic!( &statistics.expect("There were no traffic statistics"), "Stencil3DStatistics", value,
+        "completed_iterations" => assert_eq!(value.as_f64().expect("completed iterations data"), iterations as f64, "Completed iterations"),
+        "iteration_times" => iteration_times = Some(value.as_array().expect("iteration times data").iter().map(|x|x.as_f64().expect("iteration time data")).collect::<Vec<f64>>()),
+        "begin_cycle" | "completion_cycles" | "average_iteration_time" => (),
+    );
+    let iteration_times = iteration_times.expect("There were no iteration_times");
+    assert_eq!(iteration_times.len(), iterations);
+    // Each task sends 6 halos, which are serialized through its single link.
+    let minimum = (compute_cycles + 6*message_size) as f64;
+    assert!(iteration_times.iter().all(|&time|time>=minimum), "Iterations faster than {}: {:?}", minimum, iteration_times);
+}
+
+///An incast of all the servers into the server 0, whose flows complete at the pace of its consumption.
+#[test]
+fn flow_statistics_incast_test()
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    };
+    let (messages_per_server, message_size) = (4, 16);
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern: ConfigurationValue::Object("Hotspots".to_string(), vec![
+            ("destinations".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)])),
+        ]),
+        servers: 8,
+        messages_per_server,
+        message_size,
+    });
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let mut simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 5000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    if let ConfigurationValue::Object(_, ref mut fields) = simulation_cv
+    {
+        fields.push(("statistics_flows".to_string(), ConfigurationValue::Object("FlowStatistics".to_string(), vec![
+            ("percentiles".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(50.0), ConfigurationValue::Number(100.0)])),
+        ])));
+    }
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut flows = None;
+    match_object_panic!( &results, "Result", value,
+        "flows" => flows = Some(value.clone()),
+        _ => (),
+    );
+    // All the phits of the 7 other servers are consumed one per cycle by the server 0.
+    let incast_phits = (7 * messages_per_server * message_size) as f64;
+    let mut top_destinations = None;
+    let (mut median, mut maximum) = (None, None);
+    match_object_panic!( &flows.expect("There were no flow statistics"), "FlowStatistics", value,
+        "flows" => assert_eq!(value.as_f64().expect("flows data"), 7.0, "Flows"),
+        "messages" => assert_eq!(value.as_f64().expect("messages data"), 7.0 * messages_per_server as f64, "Messages"),
+        "average_flow_size" => assert_eq!(value.as_f64().expect("flow size data"), (messages_per_server * message_size) as f64, "Flow size"),
+        "destinations" => assert_eq!(value.as_f64().expect("destinations data"), 1.0, "Destinations"),
+        "maximum_incast_degree" | "average_incast_degree" => assert_eq!(value.as_f64().expect("incast degree data"), 7.0, "Incast degree"),
+        "completion_time_percentile50" => median = Some(value.as_f64().expect("completion time data")),
+        "completion_time_percentile100" => maximum = Some(value.as_f64().expect("completion time data")),
+        "destination_completion_time_percentile100" => assert!(value.as_f64().expect("completion time data") >= incast_phits, "The incast cannot complete before consuming its phits"),
+        "top_destinations" => top_destinations = Some(value.as_array().expect("top destinations data").clone()),
+        _ => (),
+    );
+    let (median, maximum) = (median.expect("There were no median"), maximum.expect("There were no maximum"));
+    assert!(median <= maximum && maximum >= incast_phits - (messages_per_server * message_size) as f64, "median={} maximum={}", median, maximum);
+    let top_destinations = top_destinations.expect("There were no top_destinations");
+    assert_eq!(top_destinations.len(), 1);
+    match_object_panic!( &top_destinations[0], "Destination", value,
+        "server" => assert_eq!(value.as_f64().expect("server data"), 0.0, "Destination server"),
+        "sources" => assert_eq!(value.as_f64().expect("sources data"), 7.0, "Sources"),
+        _ => (),
+    );
+}
+
+/// The statistics kept by the traffic must be cleared at the end of the warmup, so that they agree with the measured loads.
+#[test]
+fn traffic_statistics_exclude_warmup_test()
+{
+    let servers = 4;
+    let message_size = 16;
+    let measured = 1000;
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(servers as f64)],
+        servers_per_router: 1,
+    };
+    let traffic = create_sum_traffic(SumTrafficBuilder{
+        traffics: vec![create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers,
+            load: 0.5,
+            message_size,
+        })],
+        tasks: servers,
+        temporal_step: 0,
+    });
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let simulation_cv = create_simulation(SimulationBuilder{
+        random_seed: 5,
+        warmup: 2000,
+        measured,
+        topology: create_hamming_topology(hamming_builder),
+        traffic,
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    });
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let mut accepted_load = None;
+    let mut consumed_phits = None;
+    let mut sub_consumed_phits = None;
+    match_object_panic!( &simulation.get_simulation_results(), "Result", value,
+        "accepted_load" => accepted_load = value.as_f64().ok(),
+        "traffic_statistics" => match_object_panic!( value, "traffic_statistics", statistic,
+            "total_consumed_phits" => consumed_phits = statistic.as_f64().ok(),
+            "sub_traffics" => sub_consumed_phits = statistic.as_array().expect("sub-traffic statistics data").iter().map(|sub|{
+                let mut phits = 0.0;
+                match_object_panic!( sub, "traffic_statistics", field,
+                    "total_consumed_phits" => phits = field.as_f64().expect("consumed phits data"),
+                    _ => (),
+                );
+                phits
+            }).next(),
+            _ => (),
+        ),
+        _ => (),
+    );
+    let measured_phits = accepted_load.expect("there is no accepted_load") * (servers*measured) as f64;
+    let consumed_phits = consumed_phits.expect("there are no traffic statistics");
+    //Only the messages being consumed at the boundaries of the measurement may differ.
+    assert!((consumed_phits - measured_phits).abs() <= (2*servers*message_size) as f64, "the traffic consumed {} phits but the measurement {}", consumed_phits, measured_phits);
+    assert_eq!(sub_consumed_phits, Some(consumed_phits));
+}
+
+///The arrival processes must give the average rate requested, with their own distribution of the gaps between messages.
+#[test]
+fn arrival_processes_test()
+{
+    use caminos_lib::traffic::arrival::ArrivalProcess;
+    use rand::{SeedableRng, rngs::StdRng};
+    let message_size = 16;
+    let load = 0.4;
+    let cycles = 200_000u64;
+    //Returns the generation rate and the largest amount of messages in a window of 100 cycles.
+    let measure = |arrival:ConfigurationValue, load:Option<f64>| {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut process = ArrivalProcess::new(Some(&arrival), load, message_size, 1, &mut rng);
+        let mut generated = 0;
+        let mut window = 0;
+        let mut largest_window = 0;
+        let mut cycles_between = std::collections::BTreeSet::new();
+        let mut last = None;
+        for cycle in 0..cycles
+        {
+            if cycle % 100 == 0
+            {
+                largest_window = largest_window.max(window);
+                window = 0;
+            }
+            if process.should_generate(0, cycle, &mut rng)
+            {
+                generated += 1;
+                window += 1;
+                if let Some(last) = last
+                {
+                    cycles_between.insert(cycle - last);
+                }
+                last = Some(cycle);
+            }
+        }
+        (generated as f64 / cycles as f64, largest_window, cycles_between)
+    };
+    let expected = load / message_size as f64;
+    let (bernoulli_rate, bernoulli_window, _) = measure(ConfigurationValue::Object("Bernoulli".to_string(), vec![]), Some(load));
+    let (poisson_rate, _, poisson_gaps) = measure(ConfigurationValue::Object("Poisson".to_string(), vec![]), Some(load));
+    let (deterministic_rate, deterministic_window, deterministic_gaps) = measure(ConfigurationValue::Object("Deterministic".to_string(), vec![]), Some(load));
+    let on_off = ConfigurationValue::Object("MarkovOnOff".to_string(), vec![
+        ("p_on".to_string(), ConfigurationValue::Number(0.001)),
+        ("p_off".to_string(), ConfigurationValue::Number(0.004)),
+    ]);
+    let (on_off_rate, on_off_window, _) = measure(on_off, Some(load));
+    for (name, rate) in [("Bernoulli", bernoulli_rate), ("Poisson", poisson_rate), ("Deterministic", deterministic_rate), ("MarkovOnOff", on_off_rate)].iter()
+    {
+        assert!((rate - expected).abs() < 0.1 * expected, "{} generates {} messages per cycle instead of {}", name, rate, expected);
+    }
+    //A message each 40 cycles, which may be 39 or 41 due to the rounding to cycles.
+    assert!(deterministic_gaps.iter().all(|&gap| (39..=41).contains(&gap)), "Deterministic gaps {:?}", deterministic_gaps);
+    assert_eq!(deterministic_window, 3);
+    assert!(poisson_gaps.len() > 50, "Poisson should have many different gaps, but got {:?}", poisson_gaps);
+    //The ON periods generate at five times the average load.
+    assert!(on_off_window > bernoulli_window, "MarkovOnOff has a busiest window of {} messages against {} of Bernoulli", on_off_window, bernoulli_window);
+}
+
+///The round trips of a PingPong are still reported when it is composed inside a TrafficMap or a TrafficSum.
+#[test]
+fn ping_pong_statistics_through_operations_test()
+{
+    use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+    let ping_pong = || ConfigurationObject::new("PingPong")
+        .field("tasks",4)
+        .field("pattern",ConfigurationObject::new("CartesianTransform").field("sides",vec![4]).field("shift",vec![1]))
+        .field("iterations",10)
+        .field("message_size",16);
+    //The number of round trips recorded in the `PingPongStatistics`.
+    let round_trips = |statistics:&ConfigurationValue| -> f64 {
+        let mut count = None;
+        match_object_panic!( statistics, "PingPongStatistics", value,
+            "count" => count = Some(value.as_f64().expect("count data")),
+            _ => (),
+        );
+        count.expect("There was no count")
+    };
+    let traffics = vec![
+        ping_pong(),
+        ConfigurationObject::new("TrafficMap").field("tasks",4).field("application",ping_pong()).field("map",ConfigurationObject::new("Identity")),
+        ConfigurationObject::new("TrafficSum").field("tasks",4).field("list",vec![ping_pong()]).field("names",vec!["pairs"]),
+    ];
+    let mut counts = vec![];
+    for (index,traffic) in traffics.into_iter().enumerate()
+    {
+        let is_sum = index==2;
+        let plugs = Plugs::default();
+        let mut simulation = SimulationBuilder::new()
+            .warmup(0)
+            .measured(2000)
+            .topology(ConfigurationObject::new("Hamming").field("sides",vec![4]).field("servers_per_router",1))
+            .traffic(traffic)
+            .router(ConfigurationObject::new("Preset").field("name","IQ-VCT-2VC"))
+            .routing(ConfigurationObject::new("Shortest"))
+            .link_delays(&[1,1])
+            .build(&plugs)
+            .unwrap();
+        simulation.run();
+        let statistics = simulation.results().traffic_statistics.expect("There were no traffic_extra_statistics");
+        let count = if is_sum
+        {
+            let mut summands = None;
+            match_object_panic!( &statistics, "TrafficSumStatistics", value,
+                "summands" => summands = Some(value.as_array().expect("summands data").clone()),
+            );
+            let summands = summands.expect("There were no summands");
+            assert_eq!(summands.len(), 1);
+            let mut count = None;
+            match_object_panic!( &summands[0], "SubTrafficExtraStatistics", value,
+                "index" => assert_eq!(value.as_f64().expect("index data"), 0.0),
+                "name" => assert_eq!(value.as_str().expect("name data"), "pairs"),
+                "statistics" => count = Some(round_trips(value)),
+            );
+            count.expect("There were no sub-traffic statistics")
+        } else {
+            round_trips(&statistics)
+        };
+        counts.push(count);
+    }
+    //Each of the 4 initiators completes its 10 round trips.
+    assert_eq!(counts, vec![40.0;3]);
+}
+
+///An External traffic inside a TrafficSum synchronizes with the external process, which injects a message and receives its delivery.
+#[test]
+fn external_traffic_test()
+{
+    use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+    use std::io::{BufRead,BufReader,Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+    let listener = TcpListener::bind("127.0.0.1:0").expect("could not listen");
+    let address = listener.local_addr().expect("no local address").to_string();
+    //The external process. It returns the lines received from the simulator.
+    let external = std::thread::spawn(move ||{
+        let (stream,_) = listener.accept().expect("could not accept the simulator");
+        stream.set_read_timeout(Some(Duration::from_secs(10))).expect("could not set the timeout");
+        let mut reader = BufReader::new(stream.try_clone().expect("could not clone the stream"));
+        let mut writer = stream;
+        let mut received = vec![];
+        let mut read_line = |received:&mut Vec<String>| -> Option<String> {
+            let mut line = String::new();
+            let read = reader.read_line(&mut line).expect("the simulator did not synchronize");
+            if read==0 { return None; }
+            received.push(line.trim().to_string());
+            Some(line.trim().to_string())
+        };
+        assert_eq!(read_line(&mut received).as_deref(), Some("Cycle{cycle:0}"));
+        writeln!(writer,"Inject{{id:7,origin:0,destination:1,size:16}}").unwrap();
+        writeln!(writer,"Advance{{until:200}}").unwrap();
+        while let Some(line) = read_line(&mut received)
+        {
+            if line.starts_with("Cycle")
+            {
+                writeln!(writer,"Finish").unwrap();
+            }
+        }
+        received
+    });
+    let traffic = ConfigurationObject::new("TrafficSum")
+        .field("tasks",4)
+        .field("list",vec![ConfigurationObject::new("External").field("tasks",4).field("address",address)]);
+    let plugs = Plugs::default();
+    {
+        let mut simulation = SimulationBuilder::new()
+            .warmup(0)
+            .measured(1000)
+            .topology(ConfigurationObject::new("Hamming").field("sides",vec![4]).field("servers_per_router",1))
+            .traffic(traffic)
+            .router(ConfigurationObject::new("Preset").field("name","IQ-VCT-2VC"))
+            .routing(ConfigurationObject::new("Shortest"))
+            .link_delays(&[1,1])
+            .build(&plugs)
+            .unwrap();
+        simulation.run();
+        assert!(simulation.results().cycle<1000, "The External traffic did not finish");
+    }
+    let received = external.join().expect("the external process failed");
+    assert_eq!(received.len(), 3, "received {:?}", received);
+    let delivered = config_parser::parse(&received[1]).expect("bad Delivered");
+    let mut delivery_cycle = None;
+    match delivered
+    {
+        config_parser::Token::Value(value) => match_object_panic!( &value, "Delivered", field,
+            "id" => assert_eq!(field.as_f64().expect("id data"), 7.0),
+            "origin" => assert_eq!(field.as_f64().expect("origin data"), 0.0),
+            "destination" => assert_eq!(field.as_f64().expect("destination data"), 1.0),
+            "size" => assert_eq!(field.as_f64().expect("size data"), 16.0),
+            "creation_cycle" => assert_eq!(field.as_f64().expect("creation_cycle data"), 0.0),
+            "cycle" => delivery_cycle = Some(field.as_f64().expect("cycle data")),
+        ),
+        _ => panic!("The simulator sent something that is not a value"),
+    }
+    let delivery_cycle = delivery_cycle.expect("There was no delivery cycle");
+    assert!((16.0..200.0).contains(&delivery_cycle), "Delivered at cycle {}", delivery_cycle);
+    assert_eq!(received[2], "Cycle{cycle:200}");
+}
+
+///Counters of the `Eager` traffic of `admission_control_keeps_generation_opportunities`.
+static EAGER_OPPORTUNITIES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+static EAGER_GENERATED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+///A traffic in which the task 0 wants to send a message to the task 1 each time it is asked.
+#[derive(Debug)]
+struct Eager
+{
+    generated: std::collections::BTreeSet<usize>,
+}
+
+impl caminos_lib::quantify::Quantifiable for Eager
+{
+    fn total_memory(&self) -> usize
+    {
+        std::mem::size_of::<Eager>()
+    }
+    fn print_memory_breakdown(&self)
+    {
+        unimplemented!();
+    }
+    fn forecast_total_memory(&self) -> usize
+    {
+        unimplemented!();
+    }
+}
+
+impl caminos_lib::traffic::Traffic for Eager
+{
+    fn generate_message(&mut self, origin:usize, cycle:Time, _topology:&dyn caminos_lib::topology::Topology, _rng:&mut rand::rngs::StdRng) -> Result<std::rc::Rc<Message>,caminos_lib::traffic::TrafficError>
+    {
+        let id = EAGER_GENERATED.fetch_add(1,std::sync::atomic::Ordering::Relaxed);
+        self.generated.insert(id);
+        Ok(std::rc::Rc::new(Message{
+            origin,
+            destination: 1,
+            size: 16,
+            creation_cycle: cycle,
+            payload: id.to_le_bytes().into(),
+            id_traffic: None,
+            routing_hints: None,
+            tag: None,
+            class: 0,
+        }))
+    }
+    fn probability_per_cycle(&self, task:usize) -> f32
+    {
+        if task==0 { 1.0 } else { 0.0 }
+    }
+    fn consume(&mut self, _task:usize, message:&dyn AsMessage, _cycle:Time, _topology:&dyn caminos_lib::topology::Topology, _rng:&mut rand::rngs::StdRng) -> bool
+    {
+        let mut id = [0u8;std::mem::size_of::<usize>()];
+        let length = id.len();
+        id.copy_from_slice(&message.payload()[0..length]);
+        self.generated.remove(&usize::from_le_bytes(id))
+    }
+    fn is_finished(&self) -> bool
+    {
+        false
+    }
+    fn should_generate(&mut self, task:usize, _cycle:Time, _rng:&mut rand::rngs::StdRng) -> bool
+    {
+        if task==0
+        {
+            EAGER_OPPORTUNITIES.fetch_add(1,std::sync::atomic::Ordering::Relaxed);
+            true
+        }
+        else
+        {
+            false
+        }
+    }
+    fn task_state(&self, _task:usize, _cycle:Time) -> Option<caminos_lib::traffic::TaskTrafficState>
+    {
+        Some(caminos_lib::traffic::TaskTrafficState::Generating)
+    }
+    fn number_tasks(&self) -> usize
+    {
+        4
+    }
+}
+
+///`AdmissionControl` must only ask its inner traffic when it has tokens to generate, as each `true` answer is an opportunity consumed by the inner traffic.
+#[test]
+fn admission_control_keeps_generation_opportunities()
+{
+    use caminos_lib::builder::{SimulationBuilder,ConfigurationObject};
+    let mut plugs = Plugs::default();
+    plugs.add_traffic("Eager".to_string(),|_arg|Box::new(Eager{generated:std::collections::BTreeSet::new()}));
+    let rate = 0.25;
+    let traffic = ConfigurationObject::new("AdmissionControl")
+        .field("tasks",4)
+        .field("traffic",ConfigurationObject::new("Eager"))
+        .field("target_delay",1000)
+        .field("adaptation_cycles",0)
+        .field("initial_rate",rate);
+    let mut simulation = SimulationBuilder::new()
+        .warmup(0)
+        .measured(2000)
+        .topology(ConfigurationObject::new("Hamming").field("sides",vec![4]).field("servers_per_router",1))
+        .traffic(traffic)
+        .router(ConfigurationObject::new("Preset").field("name","IQ-VCT-2VC"))
+        .routing(ConfigurationObject::new("Shortest"))
+        .link_delays(&[1,1])
+        .build(&plugs)
+        .unwrap();
+    simulation.run();
+    let opportunities = EAGER_OPPORTUNITIES.load(std::sync::atomic::Ordering::Relaxed);
+    let generated = EAGER_GENERATED.load(std::sync::atomic::Ordering::Relaxed);
+    assert_eq!(opportunities, generated, "The inner traffic was asked {} times but only {} messages were generated", opportunities, generated);
+    // A message of 16 phits each 16/rate cycles.
+    let expected = 2000.0*rate/16.0;
+    assert!((generated as f64-expected).abs()<=2.0, "generated {} messages instead of {}", generated, expected);
+}